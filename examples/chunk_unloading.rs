@@ -11,7 +11,7 @@ use bevy::{
     },
     gizmos::{config::GizmoConfig, AppGizmoBuilder},
     math::{IVec2, UVec2, Vec2},
-    render::render_resource::FilterMode,
+    render::{color::Color, render_resource::FilterMode},
     utils::HashSet,
     window::{Window, WindowPlugin},
     DefaultPlugins,
@@ -23,6 +23,7 @@ use bevy_entitiles::{
         chunk::{
             load::{ChunkLoadCache, ChunkLoadConfig},
             save::{self, ChunkSaveCache, ChunkSaveConfig},
+            stream::ChunkStreaming,
         },
         map::TilemapLayer,
     },
@@ -60,10 +61,10 @@ fn main() {
             PhysicsDebugPlugin::default(),
         ))
         .add_systems(Startup, setup)
-        .add_systems(Update, on_update)
         .insert_resource(ChunkSaveConfig {
             path: "generated/chunk_unloading".to_string(),
             chunks_per_frame: 1,
+            placeholder: Some(Color::rgba(0.2, 0.2, 0.2, 0.5)),
         })
         .insert_resource(ChunkLoadConfig {
             path: "generated/chunk_unloading".to_string(),
@@ -154,43 +155,10 @@ fn setup(
     );
     commands.entity(entity).insert(physics_tilemap);
 
-    commands.entity(entity).insert(tilemap);
-}
-
-fn on_update(
-    mut commands: Commands,
-    mut ev: EventReader<CameraChunkUpdation>,
-    tilemap: Query<Entity, With<TilemapStorage>>,
-    mut load_cache: ResMut<ChunkLoadCache>,
-    mut save_cache: ResMut<ChunkSaveCache>,
-) {
-    let tilemap = tilemap.single();
-    let mut to_load = Vec::new();
-    let mut to_unload = Vec::new();
-
-    ev.read().for_each(|e| match e {
-        CameraChunkUpdation::Entered(_, chunk) => to_load.push(*chunk),
-        CameraChunkUpdation::Left(_, chunk) => to_unload.push((*chunk, true)),
-    });
-
-    // You can actually do everything you want
-    // This case we load/save the chunk
-
-    if !to_load.is_empty() {
-        load_cache.schedule_many(
-            &mut commands,
-            tilemap,
-            TilemapLayer::COLOR | TilemapLayer::PHYSICS,
-            to_load.into_iter(),
-        );
-    }
-
-    if !to_unload.is_empty() {
-        save_cache.schedule_many(
-            &mut commands,
-            tilemap,
-            TilemapLayer::COLOR | TilemapLayer::PHYSICS,
-            to_unload.into_iter(),
-        );
-    }
+    // Save the chunks the camera leaves and load the ones it enters.
+    // Listen to `CameraChunkUpdation` if you want to generate the chunks at runtime instead.
+    commands.entity(entity).insert((
+        tilemap,
+        ChunkStreaming::new(TilemapLayer::COLOR | TilemapLayer::PHYSICS),
+    ));
 }
@@ -8,7 +8,7 @@
 
 use bevy::{
     app::{App, PluginGroup, Startup, Update},
-    asset::{AssetServer, Assets},
+    asset::{AssetEvent, AssetServer},
     core_pipeline::core_2d::Camera2dBundle,
     ecs::{
         component::Component,
@@ -20,8 +20,7 @@ use bevy::{
     input::{keyboard::KeyCode, ButtonInput},
     math::Vec2,
     reflect::Reflect,
-    render::{mesh::Mesh, render_resource::FilterMode, texture::ImagePlugin, view::Msaa},
-    sprite::TextureAtlasLayout,
+    render::{render_resource::FilterMode, texture::ImagePlugin, view::Msaa},
     utils::HashMap,
     DefaultPlugins,
 };
@@ -29,16 +28,16 @@ use bevy_entitiles::tilemap::{physics::PhysicsTileSpawn, tile::RawTileAnimation}
 use bevy_entitiles::{
     ldtk::{
         app_ext::LdtkApp,
+        asset::LdtkProject,
         events::LdtkEvent,
         json::{field::FieldInstance, level::EntityInstance, EntityRef},
         layer::physics::LdtkPhysicsLayer,
         resources::{LdtkAdditionalLayers, LdtkAssets, LdtkLevelManager, LdtkLoadConfig},
-        sprite::LdtkEntityMaterial,
     },
     tilemap::physics::PhysicsTile,
     EntiTilesPlugin,
 };
-use bevy_entitiles_derive::{LdtkEntity, LdtkEntityTag, LdtkEnum};
+use bevy_entitiles_derive::{LdtkEntity, LdtkEntityTag, LdtkEnum, LdtkLevel};
 use bevy_xpbd_2d::{
     components::{Friction, LinearVelocity, Mass, RigidBody},
     plugins::{collision::Collider, debug::PhysicsGizmos, PhysicsDebugPlugin, PhysicsPlugins},
@@ -71,6 +70,7 @@ fn main() {
         .register_type::<Teleport>()
         .register_type::<Player>()
         .register_type::<Item>()
+        .register_type::<Room>()
         // turn off msaa to avoid the white lines between tiles
         .insert_resource(Msaa::Off)
         .insert_resource(Gravity(Vec2::new(0., -98.)))
@@ -78,7 +78,7 @@ fn main() {
             // replace the filename with grid_vania.ldtk before running
             // this file uses finalbossblues-icons_full_16 and it only exists
             // in my local disk.
-            file_path: "assets/ldtk/ignore grid_vania.ldtk".to_string(),
+            file_path: "ldtk/ignore grid_vania.ldtk".to_string(),
             asset_path_prefix: "ldtk/".to_string(),
             filter_mode: FilterMode::Nearest,
             ignore_unregistered_entities: true,
@@ -124,6 +124,7 @@ fn main() {
         .register_ldtk_entity_tag::<Actor>("actor")
         .register_ldtk_entity_tag::<Loot>("loot")
         .register_ldtk_entity_tag::<Region>("region")
+        .register_ldtk_level::<Room>()
         .run();
 }
 
@@ -169,23 +170,19 @@ fn hot_reload(
     input: Res<ButtonInput<KeyCode>>,
     mut manager: ResMut<LdtkLevelManager>,
     config: Res<LdtkLoadConfig>,
-    mut assets: ResMut<LdtkAssets>,
     asset_server: Res<AssetServer>,
-    mut atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
-    mut entity_material_assets: ResMut<Assets<LdtkEntityMaterial>>,
-    mut mesh_assets: ResMut<Assets<Mesh>>,
+    mut project_events: EventReader<AssetEvent<LdtkProject>>,
 ) {
+    // the project is also reloaded when the project file or any external level file
+    // is modified, as the `file_watcher` feature of bevy is enabled
     if input.just_pressed(KeyCode::Enter) {
-        manager.reload_json(&config);
-        assets.initialize(
-            &config,
-            &manager,
-            &asset_server,
-            &mut atlas_layouts,
-            &mut entity_material_assets,
-            &mut mesh_assets,
-        );
-        println!("Hot reloaded!")
+        manager.reload_json(&config, &asset_server);
+    }
+
+    for event in project_events.read() {
+        if let AssetEvent::Modified { .. } = event {
+            println!("Hot reloaded!");
+        }
     }
 }
 
@@ -286,6 +283,24 @@ pub enum ItemType {
     VorpalBlade,
 }
 
+#[derive(LdtkEnum, Reflect, Clone, Copy, Debug)]
+#[wrapper_derive(Reflect, Default)]
+pub enum RoomType {
+    Entrance,
+    Exit,
+    Shop,
+    Boss,
+    Save,
+}
+
+// This will be inserted to every level entity.
+// The raw fields are also available in the LdtkLevelFields component.
+#[derive(Component, LdtkLevel, Reflect)]
+pub struct Room {
+    #[ldtk_name = "roomType"]
+    pub room_type: RoomTypeOption,
+}
+
 #[derive(Component, LdtkEntity, Default, Reflect)]
 // this means the entity will be spawned with a sprite
 #[spawn_sprite]
@@ -14,11 +14,11 @@ use bevy::{
     DefaultPlugins,
 };
 use bevy_entitiles::{
-    algorithm::pathfinding::{PathFinder, PathFindingQueue, PathTilemaps},
+    algorithm::pathfinding::{PathAlgorithm, PathFinder, PathFindingQueue, PathTilemaps},
     math::TileArea,
     render::material::StandardTilemapMaterial,
     tilemap::{
-        algorithm::path::{PathTile, PathTilemap},
+        algorithm::path::{PathCapabilities, PathTile, PathTilemap},
         bundles::StandardTilemapBundle,
         map::{
             TileRenderSize, TilemapRotation, TilemapSlotSize, TilemapStorage, TilemapTexture,
@@ -89,11 +89,7 @@ fn setup(
     let mut path_tilemap = PathTilemap::new();
     path_tilemap.fill_path_rect_custom(
         TileArea::new(IVec2::ZERO, UVec2 { x: 1000, y: 1000 }),
-        |_| {
-            Some(PathTile {
-                cost: rand::random::<u32>() % 10,
-            })
-        },
+        |_| Some(PathTile::new(rand::random::<u32>() % 10)),
     );
     path_tilemaps.insert(entity, path_tilemap);
 
@@ -104,7 +100,12 @@ fn setup(
                 origin: IVec2::ZERO,
                 dest: IVec2::splat(499),
                 allow_diagonal: false,
+                capabilities: PathCapabilities::all(),
                 max_steps: None,
+                algorithm: PathAlgorithm::AStar,
+                bidirectional: false,
+                max_steps_per_frame: 1000,
+                cancel: None,
             },
         )
     });
@@ -15,11 +15,11 @@ use bevy::{
     DefaultPlugins,
 };
 use bevy_entitiles::{
-    algorithm::pathfinding::{PathFinder, PathFindingQueue, PathGrid, PathTilemaps},
+    algorithm::pathfinding::{PathAlgorithm, PathFinder, PathFindingQueue, PathGrid, PathTilemaps},
     math::TileArea,
     render::material::StandardTilemapMaterial,
     tilemap::{
-        algorithm::path::{PathTile, PathTilemap},
+        algorithm::path::{PathCapabilities, PathTile, PathTilemap},
         bundles::StandardTilemapBundle,
         map::{
             TileRenderSize, TilemapRotation, TilemapSlotSize, TilemapStorage, TilemapTexture,
@@ -82,14 +82,10 @@ fn setup(
     );
 
     let mut path_tilemap = PathTilemap::new();
-    path_tilemap.fill_path_rect_custom(
-        TileArea::new(IVec2::ZERO, UVec2 { x: 100, y: 100 }),
-        |_| {
-            Some(PathTile {
-                cost: rand::random::<u32>() % 10,
-            })
-        },
-    );
+    path_tilemap
+        .fill_path_rect_custom(TileArea::new(IVec2::ZERO, UVec2 { x: 100, y: 100 }), |_| {
+            Some(PathTile::new(rand::random::<u32>() % 10))
+        });
     path_tilemaps.insert(entity, path_tilemap);
 
     let queue = (0..20).into_iter().map(|_| {
@@ -99,8 +95,12 @@ fn setup(
                 origin: IVec2::ZERO,
                 dest: IVec2::splat(99),
                 allow_diagonal: false,
+                capabilities: PathCapabilities::all(),
                 max_steps: None,
+                algorithm: PathAlgorithm::AStar,
+                bidirectional: false,
                 max_steps_per_frame: 1000,
+                cancel: None,
                 tilemap_ty: TilemapType::Square,
             },
         )
@@ -18,7 +18,7 @@ use bevy_entitiles::{
     render::material::StandardTilemapMaterial,
     serializing::map::{
         load::TilemapLoader,
-        save::{TilemapSaver, TilemapSaverMode},
+        save::{TilemapSaver, TilemapSaverMode, TilemapSaverRange},
         TilemapLayer,
     },
     tilemap::{
@@ -95,9 +95,7 @@ fn setup(
 
     let mut path_tilemap = PathTilemap::new();
     path_tilemap.fill_path_rect_custom(TileArea::new(IVec2::ZERO, UVec2 { x: 20, y: 20 }), |_| {
-        Some(PathTile {
-            cost: rand::random::<u32>() % 10,
-        })
+        Some(PathTile::new(rand::random::<u32>() % 10))
     });
     path_tilemaps.insert(entity, path_tilemap);
 
@@ -135,6 +133,7 @@ fn save_and_load(
                 layers: TilemapLayer::all(),
                 texture_path: Some("test_isometric.png".to_string()),
                 remove_after_save: true,
+                range: TilemapSaverRange::Full,
             });
             println!("Saved tilemap!");
         }
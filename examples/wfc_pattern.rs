@@ -15,7 +15,7 @@ use bevy_entitiles::{
     math::TileArea,
     render::material::StandardTilemapMaterial,
     serializing::map::{
-        save::{TilemapSaver, TilemapSaverMode},
+        save::{TilemapSaver, TilemapSaverMode, TilemapSaverRange},
         TilemapLayer,
     },
     tilemap::{
@@ -108,6 +108,7 @@ fn setup(mut commands: Commands, mut materials: ResMut<Assets<StandardTilemapMat
             layers: TilemapLayer::COLOR,
             texture_path: None,
             remove_after_save: true,
+            range: TilemapSaverRange::Full,
         });
     });
 
@@ -1,6 +1,7 @@
 mod ldtk_entity;
 mod ldtk_entity_tag;
 mod ldtk_enum;
+mod ldtk_level;
 mod tiled_class;
 mod tiled_enum;
 mod tiled_object;
@@ -23,6 +24,11 @@ pub fn derive_ldtk_entity_tags(input: proc_macro::TokenStream) -> proc_macro::To
     ldtk_entity_tag::expand_ldtk_entity_tag_derive(syn::parse(input).unwrap())
 }
 
+#[proc_macro_derive(LdtkLevel, attributes(ldtk_default, ldtk_name, callback))]
+pub fn derive_ldtk_levels(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    ldtk_level::expand_ldtk_level_derive(syn::parse(input).unwrap())
+}
+
 #[proc_macro_derive(
     TiledObject,
     attributes(
@@ -0,0 +1,698 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        removal_detection::RemovedComponents,
+        system::{Query, Res, ResMut, Resource},
+    },
+    math::IVec2,
+    reflect::Reflect,
+    time::Time,
+    utils::{HashMap, HashSet},
+};
+
+use crate::{
+    math::extension::TileIndex,
+    tilemap::{
+        algorithm::path::{PathCapabilities, PathTilemap},
+        map::TilemapType,
+    },
+};
+
+use super::{
+    connection::TilemapTile,
+    pathfinding::{Path, PathTilemaps},
+};
+
+/// The tiles reserved by the `PathFollower`s, so agents don't stack on a tile.
+///
+/// Each agent reserves the tile it stands on and the tile it's moving to.
+/// For turn-based games, agents can also `book` the tiles to move to in the next turn,
+/// and all the bookings are resolved at once with `resolve_turn`.
+#[derive(Resource, Debug, Default, Clone, Reflect)]
+pub struct TileReservations {
+    pub(crate) tiles: HashMap<TilemapTile, Entity>,
+    pub(crate) bookings: Vec<TileBooking>,
+}
+
+/// A request to move to a tile in the next turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub struct TileBooking {
+    pub agent: Entity,
+    pub tile: TilemapTile,
+    /// Used by `BookingConflict::Priority`. Higher goes first.
+    pub priority: i32,
+}
+
+/// How to pick the winner when several agents book the same tile.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum BookingConflict {
+    /// The agent that booked first wins.
+    #[default]
+    FirstCome,
+    /// The agent with the highest priority wins, or the first one if tied.
+    Priority,
+    /// Nobody gets the tile.
+    Reject,
+}
+
+/// Why a booking was denied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum BookingDenial {
+    /// Another agent won the tile.
+    Lost { winner: Entity },
+    /// Several agents booked the tile with `BookingConflict::Reject`.
+    Conflict,
+    /// The tile is reserved by an agent that doesn't move away this turn.
+    Blocked { occupant: Entity },
+}
+
+/// The result of `TileReservations::resolve_turn`.
+#[derive(Debug, Default, Clone, Reflect)]
+pub struct TurnResolution {
+    /// The bookings granted, in the order they were made.
+    /// The agents now reserve these tiles instead of their previous ones on the same tilemap.
+    pub moved: Vec<TileBooking>,
+    pub denied: Vec<(TileBooking, BookingDenial)>,
+}
+
+impl TileReservations {
+    /// The agent that reserved the tile.
+    #[inline]
+    pub fn get(&self, tile: TilemapTile) -> Option<Entity> {
+        self.tiles.get(&tile).copied()
+    }
+
+    /// Returns `true` if the tile is not reserved by an agent other than `agent`.
+    #[inline]
+    pub fn is_free_for(&self, tile: TilemapTile, agent: Entity) -> bool {
+        self.get(tile).map_or(true, |a| a == agent)
+    }
+
+    /// Reserve the tile for `agent`. Returns `false` if it's taken by another agent.
+    pub fn reserve(&mut self, tile: TilemapTile, agent: Entity) -> bool {
+        if !self.is_free_for(tile, agent) {
+            return false;
+        }
+        self.tiles.insert(tile, agent);
+        true
+    }
+
+    /// Release the tile if it's reserved by `agent`.
+    pub fn release(&mut self, tile: TilemapTile, agent: Entity) {
+        if self.get(tile) == Some(agent) {
+            self.tiles.remove(&tile);
+        }
+    }
+
+    /// Release every tile reserved by `agent`, and cancel its booking.
+    pub fn release_agent(&mut self, agent: Entity) {
+        self.tiles.retain(|_, a| *a != agent);
+        self.cancel_booking(agent);
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.tiles.clear();
+        self.bookings.clear();
+    }
+
+    /// Book a tile for `agent` to move to in the next turn.
+    /// Replaces the previous booking of the agent.
+    pub fn book(&mut self, tile: TilemapTile, agent: Entity, priority: i32) {
+        self.cancel_booking(agent);
+        self.bookings.push(TileBooking {
+            agent,
+            tile,
+            priority,
+        });
+    }
+
+    pub fn cancel_booking(&mut self, agent: Entity) {
+        self.bookings.retain(|b| b.agent != agent);
+    }
+
+    #[inline]
+    pub fn bookings(&self) -> &[TileBooking] {
+        &self.bookings
+    }
+
+    /// Resolve all the bookings at once, and move the reservations of the agents
+    /// whose bookings are granted.
+    ///
+    /// A booking of a tile reserved by another agent is only granted if that agent
+    /// moves away in the same turn. Agents moving in a cycle, including swapping
+    /// places, are all granted.
+    pub fn resolve_turn(&mut self, conflict: BookingConflict) -> TurnResolution {
+        let bookings = std::mem::take(&mut self.bookings);
+        let mut resolution = TurnResolution::default();
+
+        let mut by_tile = HashMap::<_, Vec<_>>::new();
+        bookings
+            .iter()
+            .for_each(|b| by_tile.entry(b.tile).or_default().push(*b));
+
+        let mut granted = HashMap::new();
+        by_tile.into_values().for_each(|bookers| {
+            let winner = match conflict {
+                _ if bookers.len() == 1 => Some(bookers[0]),
+                BookingConflict::FirstCome => Some(bookers[0]),
+                BookingConflict::Priority => bookers
+                    .iter()
+                    .copied()
+                    .min_by_key(|b| std::cmp::Reverse(b.priority)),
+                BookingConflict::Reject => None,
+            };
+
+            bookers.into_iter().for_each(|b| match winner {
+                Some(w) if w == b => {
+                    granted.insert(b.agent, b);
+                }
+                Some(w) => resolution
+                    .denied
+                    .push((b, BookingDenial::Lost { winner: w.agent })),
+                None => resolution.denied.push((b, BookingDenial::Conflict)),
+            });
+        });
+
+        // Denying a booking keeps the agent in place, which may block others.
+        while let Some((booking, occupant)) = granted.values().find_map(|b: &TileBooking| {
+            self.get(b.tile)
+                .filter(|o| *o != b.agent && !granted.contains_key(o))
+                .map(|o| (*b, o))
+        }) {
+            granted.remove(&booking.agent);
+            resolution
+                .denied
+                .push((booking, BookingDenial::Blocked { occupant }));
+        }
+
+        self.tiles
+            .retain(|(tilemap, _), a| granted.get(a).map_or(true, |b| b.tile.0 != *tilemap));
+        resolution.moved = bookings
+            .into_iter()
+            .filter(|b| granted.get(&b.agent) == Some(b))
+            .collect();
+        resolution.moved.iter().for_each(|b| {
+            self.tiles.insert(b.tile, b.agent);
+        });
+        resolution
+    }
+}
+
+/// What a `PathFollower` does when the next tile is reserved by another agent.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Reflect)]
+pub enum AvoidanceBehavior {
+    /// Wait until the tile is free.
+    #[default]
+    Wait,
+    /// After waiting `patience` seconds, step aside to a free neighbour and wait
+    /// there for `patience` seconds before coming back. Good for letting others
+    /// pass in corridors.
+    Yield { patience: f32 },
+    /// After waiting `patience` seconds, walk around the reserved tiles and rejoin
+    /// the path. The detour has at most `max_length` tiles, and ignores the costs.
+    Repath { patience: f32, max_length: u32 },
+}
+
+/// Moves an agent along its `Path` one tile at a time, reserving the tiles in
+/// `TileReservations` so agents don't stack on a tile.
+///
+/// Move the agent towards `next_tile` and call `arrive` when it gets there.
+/// The follower consumes the `Path` it's on, so don't call `Path::step` yourself.
+#[derive(Component, Debug, Clone, Reflect)]
+pub struct PathFollower {
+    pub tilemap: Entity,
+    pub behavior: AvoidanceBehavior,
+    /// Used to find the tiles to step aside or walk around.
+    pub allow_diagonal: bool,
+    #[reflect(ignore)]
+    pub capabilities: PathCapabilities,
+    pub(crate) current: IVec2,
+    pub(crate) next: Option<IVec2>,
+    pub(crate) arrived: bool,
+    pub(crate) blocked_for: f32,
+    /// The time left to wait before moving on, after stepping aside.
+    pub(crate) yield_for: f32,
+}
+
+impl PathFollower {
+    /// Create a follower for an agent standing on `current`.
+    pub fn new(tilemap: Entity, current: IVec2) -> Self {
+        Self {
+            tilemap,
+            behavior: AvoidanceBehavior::Wait,
+            allow_diagonal: false,
+            capabilities: PathCapabilities::all(),
+            current,
+            next: None,
+            arrived: false,
+            blocked_for: 0.,
+            yield_for: 0.,
+        }
+    }
+
+    pub fn with_behavior(mut self, behavior: AvoidanceBehavior) -> Self {
+        self.behavior = behavior;
+        self
+    }
+
+    pub fn with_diagonal(mut self, allow_diagonal: bool) -> Self {
+        self.allow_diagonal = allow_diagonal;
+        self
+    }
+
+    pub fn with_capabilities(mut self, capabilities: PathCapabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// The tile the agent is standing on.
+    #[inline]
+    pub fn current_tile(&self) -> IVec2 {
+        self.current
+    }
+
+    /// The tile reserved for the agent to move to, if any.
+    #[inline]
+    pub fn next_tile(&self) -> Option<IVec2> {
+        self.next
+    }
+
+    /// How long the agent has been waiting for the next tile, in seconds.
+    #[inline]
+    pub fn blocked_for(&self) -> f32 {
+        self.blocked_for
+    }
+
+    /// Tell the follower that the agent reached `next_tile`.
+    pub fn arrive(&mut self) {
+        if self.next.is_some() {
+            self.arrived = true;
+        }
+    }
+
+    pub(crate) fn update(
+        &mut self,
+        agent: Entity,
+        path: Option<&mut Path>,
+        reservations: &mut TileReservations,
+        delta: f32,
+        ty: TilemapType,
+        path_tilemap: Option<&PathTilemap>,
+    ) {
+        if self.arrived {
+            reservations.release((self.tilemap, self.current), agent);
+            self.current = self.next.take().unwrap();
+            self.arrived = false;
+        }
+        reservations.reserve((self.tilemap, self.current), agent);
+
+        if self.next.is_some() {
+            return;
+        }
+        if self.yield_for > 0. {
+            self.yield_for -= delta;
+            return;
+        }
+        let Some(path) = path.filter(|p| p.tilemap() == self.tilemap) else {
+            return;
+        };
+        let Some(target) = path.path.last().copied() else {
+            return;
+        };
+
+        if reservations.reserve((self.tilemap, target), agent) {
+            path.path.pop();
+            self.next = Some(target);
+            self.blocked_for = 0.;
+            return;
+        }
+
+        self.blocked_for += delta;
+        let Some(path_tilemap) = path_tilemap else {
+            return;
+        };
+        match self.behavior {
+            AvoidanceBehavior::Wait => {}
+            AvoidanceBehavior::Yield { patience } => {
+                if self.blocked_for < patience {
+                    return;
+                }
+                let side = self
+                    .current
+                    .neighbours(ty, self.allow_diagonal)
+                    .into_iter()
+                    .flatten()
+                    .find(|index| {
+                        *index != target
+                            && !path_tilemap.is_blocked(self.current, *index)
+                            && path_tilemap
+                                .get_passable(*index, self.capabilities)
+                                .is_some()
+                            && reservations.reserve((self.tilemap, *index), agent)
+                    });
+                if let Some(side) = side {
+                    path.path.push(self.current);
+                    self.next = Some(side);
+                    self.blocked_for = 0.;
+                    self.yield_for = patience;
+                }
+            }
+            AvoidanceBehavior::Repath {
+                patience,
+                max_length,
+            } => {
+                if self.blocked_for < patience {
+                    return;
+                }
+                if let Some((rejoin, detour)) = self.detour(
+                    agent,
+                    &path.path,
+                    reservations,
+                    ty,
+                    path_tilemap,
+                    max_length,
+                ) {
+                    path.path.truncate(rejoin + 1);
+                    path.path.extend(detour);
+                }
+                // Don't search again every frame if there's no way around.
+                self.blocked_for = 0.;
+            }
+        }
+    }
+
+    /// Search for the shortest way from the current tile to the remaining path,
+    /// avoiding the tiles reserved by others.
+    ///
+    /// Returns the index in `path` to rejoin at, and the tiles to push onto the path
+    /// before it, in the same order as the path.
+    fn detour(
+        &self,
+        agent: Entity,
+        path: &[IVec2],
+        reservations: &TileReservations,
+        ty: TilemapType,
+        path_tilemap: &PathTilemap,
+        max_length: u32,
+    ) -> Option<(usize, Vec<IVec2>)> {
+        // The next tile is the one blocked.
+        let rejoin_at = path[..path.len() - 1]
+            .iter()
+            .enumerate()
+            .map(|(i, index)| (*index, i))
+            .collect::<HashMap<_, _>>();
+        let mut parents = HashMap::new();
+        let mut visited = HashSet::from([self.current]);
+        let mut to_explore = VecDeque::from([(self.current, 0)]);
+
+        while let Some((index, length)) = to_explore.pop_front() {
+            if let Some(rejoin) = rejoin_at.get(&index) {
+                let mut detour = Vec::new();
+                // The path may pass by the current tile again, which has no parent.
+                let mut tile = *parents.get(&index)?;
+                while tile != self.current {
+                    detour.push(tile);
+                    tile = *parents.get(&tile)?;
+                }
+                return Some((*rejoin, detour));
+            }
+            if length >= max_length {
+                continue;
+            }
+
+            index
+                .neighbours(ty, self.allow_diagonal)
+                .into_iter()
+                .flatten()
+                .filter(|n| {
+                    !path_tilemap.is_blocked(index, *n)
+                        && path_tilemap.get_passable(*n, self.capabilities).is_some()
+                        && reservations.is_free_for((self.tilemap, *n), agent)
+                })
+                .for_each(|n| {
+                    if visited.insert(n) {
+                        parents.insert(n, index);
+                        to_explore.push_back((n, length + 1));
+                    }
+                });
+        }
+        None
+    }
+}
+
+pub fn path_follower_updater(
+    mut followers_query: Query<(Entity, &mut PathFollower, Option<&mut Path>)>,
+    tilemaps_query: Query<&TilemapType>,
+    path_tilemaps: Res<PathTilemaps>,
+    mut reservations: ResMut<TileReservations>,
+    mut removed: RemovedComponents<PathFollower>,
+    time: Res<Time>,
+) {
+    removed
+        .read()
+        .for_each(|agent| reservations.release_agent(agent));
+
+    followers_query
+        .iter_mut()
+        .for_each(|(agent, mut follower, path)| {
+            let ty = tilemaps_query
+                .get(follower.tilemap)
+                .copied()
+                .unwrap_or(TilemapType::Square);
+            #[cfg(feature = "multi-threaded")]
+            let path_tilemap = path_tilemaps.lock(follower.tilemap);
+            #[cfg(not(feature = "multi-threaded"))]
+            let path_tilemap = path_tilemaps.get(follower.tilemap);
+
+            follower.update(
+                agent,
+                path.map(|p| p.into_inner()),
+                &mut reservations,
+                time.delta_seconds(),
+                ty,
+                path_tilemap.as_deref(),
+            );
+        });
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::math::UVec2;
+
+    use crate::{math::TileArea, tilemap::algorithm::path::PathTile};
+
+    use super::*;
+
+    const TILEMAP: Entity = Entity::PLACEHOLDER;
+
+    /// A path from `origin` going straight along x to `dest_x`.
+    fn straight(origin: IVec2, dest_x: i32) -> Path {
+        let dir = (dest_x - origin.x).signum();
+        let mut path = (1..=(dest_x - origin.x).abs())
+            .map(|i| origin + IVec2::new(i * dir, 0))
+            .collect::<Vec<_>>();
+        path.reverse();
+        Path {
+            path,
+            current_step: 0,
+            tilemap: TILEMAP,
+        }
+    }
+
+    /// Update the agents in order, and teleport them to their next tiles.
+    fn simulate(
+        agents: &mut [(PathFollower, Path)],
+        path_tilemap: &PathTilemap,
+        reservations: &mut TileReservations,
+    ) {
+        for _ in 0..50 {
+            for (i, (follower, path)) in agents.iter_mut().enumerate() {
+                follower.update(
+                    Entity::from_raw(i as u32),
+                    Some(path),
+                    reservations,
+                    0.25,
+                    TilemapType::Square,
+                    Some(path_tilemap),
+                );
+                follower.arrive();
+            }
+
+            let mut occupied = agents
+                .iter()
+                .map(|(follower, _)| follower.current_tile())
+                .collect::<Vec<_>>();
+            occupied.sort_by_key(|index| (index.x, index.y));
+            occupied.dedup();
+            assert_eq!(occupied.len(), agents.len());
+        }
+    }
+
+    #[test]
+    fn test_resolve_turn() {
+        let agents = (0..7).map(Entity::from_raw).collect::<Vec<_>>();
+        let tile = |x| (TILEMAP, IVec2::new(x, 0));
+        let mut reservations = TileReservations::default();
+        [0, 2, 3, 4, 5, 10, 11]
+            .into_iter()
+            .zip(&agents)
+            .for_each(|(x, agent)| assert!(reservations.reserve(tile(x), *agent)));
+
+        let book = |reservations: &mut TileReservations| {
+            reservations.book(tile(1), agents[0], 0);
+            reservations.book(tile(1), agents[1], 1);
+            reservations.book(tile(2), agents[2], 0);
+            // The occupant of 5 stays.
+            reservations.book(tile(5), agents[3], 0);
+            // Swap.
+            reservations.book(tile(11), agents[5], 0);
+            reservations.book(tile(10), agents[6], 0);
+        };
+        let moved = |resolution: &TurnResolution| {
+            resolution
+                .moved
+                .iter()
+                .map(|b| b.agent.index())
+                .collect::<Vec<_>>()
+        };
+
+        let mut priority = reservations.clone();
+        book(&mut priority);
+        let resolution = priority.resolve_turn(BookingConflict::Priority);
+        assert_eq!(moved(&resolution), vec![1, 2, 5, 6]);
+        assert!(resolution.denied.contains(&(
+            booking(agents[0], tile(1), 0),
+            BookingDenial::Lost { winner: agents[1] }
+        )));
+        assert!(resolution.denied.contains(&(
+            booking(agents[3], tile(5), 0),
+            BookingDenial::Blocked {
+                occupant: agents[4]
+            }
+        )));
+        assert_eq!(priority.get(tile(2)), Some(agents[2]));
+        assert_eq!(priority.get(tile(3)), None);
+        assert_eq!(priority.get(tile(10)), Some(agents[6]));
+        assert!(priority.bookings().is_empty());
+
+        // Agent 1 loses and stays, so agent 2 can't move either.
+        let mut first_come = reservations.clone();
+        book(&mut first_come);
+        let resolution = first_come.resolve_turn(BookingConflict::FirstCome);
+        assert_eq!(moved(&resolution), vec![0, 5, 6]);
+        assert_eq!(first_come.get(tile(0)), None);
+        assert_eq!(first_come.get(tile(3)), Some(agents[2]));
+
+        let mut reject = reservations.clone();
+        book(&mut reject);
+        let resolution = reject.resolve_turn(BookingConflict::Reject);
+        assert_eq!(moved(&resolution), vec![5, 6]);
+        assert_eq!(resolution.denied.len(), 4);
+    }
+
+    fn booking(agent: Entity, tile: TilemapTile, priority: i32) -> TileBooking {
+        TileBooking {
+            agent,
+            tile,
+            priority,
+        }
+    }
+
+    #[test]
+    fn test_yield() {
+        // A corridor with a bay in the middle.
+        let mut path_tilemap = PathTilemap::new();
+        path_tilemap.fill_path_rect(
+            TileArea::new(IVec2::ZERO, UVec2::new(7, 1)),
+            PathTile::new(1),
+        );
+        path_tilemap.set(IVec2::new(3, 1), PathTile::new(1));
+
+        let mut reservations = TileReservations::default();
+        let mut agents = [
+            (
+                PathFollower::new(TILEMAP, IVec2::ZERO)
+                    .with_behavior(AvoidanceBehavior::Yield { patience: 0.5 }),
+                straight(IVec2::ZERO, 6),
+            ),
+            (
+                PathFollower::new(TILEMAP, IVec2::new(6, 0)),
+                straight(IVec2::new(6, 0), 0),
+            ),
+        ];
+        simulate(&mut agents, &path_tilemap, &mut reservations);
+
+        assert_eq!(agents[0].0.current_tile(), IVec2::new(6, 0));
+        assert_eq!(agents[1].0.current_tile(), IVec2::ZERO);
+        assert!(agents.iter().all(|(_, path)| path.is_arrived()));
+        assert_eq!(reservations.tiles.len(), 2);
+    }
+
+    #[test]
+    fn test_detour_through_current() {
+        let mut path_tilemap = PathTilemap::new();
+        path_tilemap.fill_path_rect(
+            TileArea::new(IVec2::ZERO, UVec2::new(3, 1)),
+            PathTile::new(1),
+        );
+        let follower = PathFollower::new(TILEMAP, IVec2::ZERO);
+        // The path loops back to the current tile after the blocked one.
+        let path = [IVec2::new(2, 0), IVec2::ZERO, IVec2::new(1, 0)];
+
+        assert!(follower
+            .detour(
+                Entity::PLACEHOLDER,
+                &path,
+                &TileReservations::default(),
+                TilemapType::Square,
+                &path_tilemap,
+                8,
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn test_repath() {
+        let mut path_tilemap = PathTilemap::new();
+        path_tilemap.fill_path_rect(
+            TileArea::new(IVec2::ZERO, UVec2::new(7, 3)),
+            PathTile::new(1),
+        );
+
+        let mut reservations = TileReservations::default();
+        let mut agents = [
+            (
+                PathFollower::new(TILEMAP, IVec2::new(0, 1)).with_behavior(
+                    AvoidanceBehavior::Repath {
+                        patience: 0.5,
+                        max_length: 8,
+                    },
+                ),
+                straight(IVec2::new(0, 1), 6),
+            ),
+            // Standing in the way.
+            (
+                PathFollower::new(TILEMAP, IVec2::new(3, 1)),
+                straight(IVec2::new(3, 1), 3),
+            ),
+        ];
+        simulate(&mut agents, &path_tilemap, &mut reservations);
+        assert_eq!(agents[0].0.current_tile(), IVec2::new(6, 1));
+
+        // No way around with a short detour.
+        reservations.clear();
+        agents[0] = (
+            PathFollower::new(TILEMAP, IVec2::new(0, 1)).with_behavior(AvoidanceBehavior::Repath {
+                patience: 0.5,
+                max_length: 1,
+            }),
+            straight(IVec2::new(0, 1), 6),
+        );
+        simulate(&mut agents, &path_tilemap, &mut reservations);
+        assert_eq!(agents[0].0.current_tile(), IVec2::new(2, 1));
+    }
+}
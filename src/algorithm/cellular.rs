@@ -0,0 +1,462 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::Without,
+        system::{Commands, Query, Res, ResMut},
+    },
+    math::{IVec2, UVec2},
+    reflect::Reflect,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    math::TileArea,
+    tilemap::{
+        budget::{TileSpawnBudget, TileSpawnQueue},
+        map::TilemapStorage,
+        tile::TileBuilder,
+    },
+};
+
+use super::{cancel::CancelToken, constraint::TilemapConstraints};
+
+#[cfg(feature = "multi-threaded")]
+use bevy::{
+    ecs::{change_detection::DetectChanges, query::Has, world::Ref},
+    tasks::{AsyncComputeTaskPool, Task},
+};
+
+/// A grid of alive or dead cells, with `(0, 0)` at the bottom left corner.
+#[derive(Debug, Clone, PartialEq, Eq, Reflect)]
+pub struct CellularGrid {
+    size: UVec2,
+    cells: Vec<bool>,
+}
+
+impl CellularGrid {
+    pub fn new(size: UVec2) -> Self {
+        Self {
+            size,
+            cells: vec![false; (size.x * size.y) as usize],
+        }
+    }
+
+    /// Every cell is alive with the probability.
+    pub fn random(size: UVec2, probability: f32, rng: &mut impl Rng) -> Self {
+        Self {
+            size,
+            cells: (0..size.x * size.y)
+                .map(|_| rng.gen_bool(probability.clamp(0., 1.) as f64))
+                .collect(),
+        }
+    }
+
+    /// The cells are alive where `alive` returns true,
+    /// like where a noise function is above a threshold.
+    pub fn from_fn(size: UVec2, alive: impl Fn(UVec2) -> bool) -> Self {
+        Self {
+            size,
+            cells: (0..size.y)
+                .flat_map(|y| (0..size.x).map(move |x| UVec2 { x, y }))
+                .map(alive)
+                .collect(),
+        }
+    }
+
+    #[inline]
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// Returns `None` if the index is out of the grid.
+    #[inline]
+    pub fn get(&self, index: IVec2) -> Option<bool> {
+        if index.x < 0
+            || index.y < 0
+            || index.x >= self.size.x as i32
+            || index.y >= self.size.y as i32
+        {
+            return None;
+        }
+        Some(self.cells[(index.y * self.size.x as i32 + index.x) as usize])
+    }
+
+    #[inline]
+    pub fn set(&mut self, index: UVec2, alive: bool) {
+        self.cells[(index.y * self.size.x + index.x) as usize] = alive;
+    }
+
+    pub fn alive_count(&self) -> usize {
+        self.cells.iter().filter(|c| **c).count()
+    }
+
+    /// Iterate over the indices of the alive cells.
+    pub fn iter_alive(&self) -> impl Iterator<Item = UVec2> + '_ {
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, alive)| **alive)
+            .map(|(i, _)| UVec2 {
+                x: i as u32 % self.size.x,
+                y: i as u32 / self.size.x,
+            })
+    }
+}
+
+/// The rules of a cellular automaton on a square grid, counting the 8 neighbours.
+#[derive(Debug, Clone, Reflect)]
+pub struct CellularAutomaton {
+    /// Bit `n` is set if a dead cell with `n` alive neighbours becomes alive.
+    pub birth: u16,
+    /// Bit `n` is set if an alive cell with `n` alive neighbours stays alive.
+    pub survival: u16,
+    pub iterations: u32,
+    /// The probability of a cell being alive in the random initial grid.
+    pub fill_probability: f32,
+    pub seed: Option<u64>,
+    /// Count the cells out of the grid as alive, which closes the edges of caves.
+    pub border_alive: bool,
+}
+
+impl CellularAutomaton {
+    /// `birth` and `survival` are the neighbour counts, like `[3]` and `[2, 3]`
+    /// for the Game of Life.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a count is greater than 8, as a cell only has 8 neighbours.
+    pub fn new(birth: &[u8], survival: &[u8]) -> Self {
+        let mask = |counts: &[u8]| {
+            counts.iter().fold(0, |mask, c| {
+                assert!(*c <= 8, "A cell only has 8 neighbours, but got {}!", c);
+                mask | 1 << c
+            })
+        };
+        Self {
+            birth: mask(birth),
+            survival: mask(survival),
+            iterations: 1,
+            fill_probability: 0.5,
+            seed: None,
+            border_alive: false,
+        }
+    }
+
+    /// The classic rules to generate caves, where the alive cells are the walls.
+    pub fn cave() -> Self {
+        Self::new(&[5, 6, 7, 8], &[4, 5, 6, 7, 8])
+            .with_iterations(5)
+            .with_fill_probability(0.45)
+            .with_border_alive(true)
+    }
+
+    pub fn with_iterations(mut self, iterations: u32) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    pub fn with_fill_probability(mut self, probability: f32) -> Self {
+        self.fill_probability = probability;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn with_border_alive(mut self, border_alive: bool) -> Self {
+        self.border_alive = border_alive;
+        self
+    }
+
+    /// Advance the grid by one generation.
+    pub fn step(&self, grid: &CellularGrid) -> CellularGrid {
+        let mut next = CellularGrid::new(grid.size);
+        for y in 0..grid.size.y as i32 {
+            for x in 0..grid.size.x as i32 {
+                let index = IVec2 { x, y };
+                let neighbours = (-1..=1)
+                    .flat_map(|dy| (-1..=1).map(move |dx| IVec2 { x: dx, y: dy }))
+                    .filter(|offset| *offset != IVec2::ZERO)
+                    .filter(|offset| grid.get(index + *offset).unwrap_or(self.border_alive))
+                    .count();
+
+                let rule = if grid.get(index).unwrap() {
+                    self.survival
+                } else {
+                    self.birth
+                };
+                next.set(index.as_uvec2(), rule & (1 << neighbours) != 0);
+            }
+        }
+        next
+    }
+
+    /// Run all the iterations on the grid.
+    pub fn run(&self, mut grid: CellularGrid) -> CellularGrid {
+        for _ in 0..self.iterations {
+            grid = self.step(&grid);
+        }
+        grid
+    }
+
+    /// Run all the iterations on a random grid.
+    pub fn generate(&self, size: UVec2) -> CellularGrid {
+        self.run(self.random_grid(size))
+    }
+
+    fn random_grid(&self, size: UVec2) -> CellularGrid {
+        let mut rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        CellularGrid::random(size, self.fill_probability, &mut rng)
+    }
+}
+
+/// Generate the tiles in the area of the tilemap with a cellular automaton.
+/// Removed when the tiles are set.
+///
+/// In multi-threaded mode, the generation runs in the async compute task pool.
+/// Inserting the runner again restarts the generation, and the previous task
+/// is cancelled, like when the tilemap is despawned.
+#[derive(Component, Debug, Clone, Reflect)]
+pub struct CellularAutomataRunner {
+    pub automaton: CellularAutomaton,
+    pub area: TileArea,
+    /// Use this instead of a random grid, the size should be the same as the area.
+    pub initial: Option<CellularGrid>,
+    pub alive: TileBuilder,
+    /// Remove the tiles if `None`.
+    pub dead: Option<TileBuilder>,
+    /// Stop the generation early once cancelled. The runner is removed then,
+    /// and no tiles are set.
+    #[reflect(ignore)]
+    pub cancel: Option<CancelToken>,
+}
+
+impl CellularAutomataRunner {
+    pub fn new(automaton: CellularAutomaton, area: TileArea, alive: TileBuilder) -> Self {
+        Self {
+            automaton,
+            area,
+            initial: None,
+            alive,
+            dead: None,
+            cancel: None,
+        }
+    }
+
+    pub fn with_initial(mut self, initial: CellularGrid) -> Self {
+        assert_eq!(
+            initial.size, self.area.extent,
+            "The size of the initial grid doesn't match the area!"
+        );
+        self.initial = Some(initial);
+        self
+    }
+
+    pub fn with_dead(mut self, dead: TileBuilder) -> Self {
+        self.dead = Some(dead);
+        self
+    }
+
+    pub fn with_cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Returns `None` if cancelled, by the token of the runner or by `dropped`.
+    fn generate(&self, dropped: Option<&CancelToken>) -> Option<CellularGrid> {
+        let is_cancelled = || {
+            self.cancel.as_ref().is_some_and(CancelToken::is_cancelled)
+                || dropped.is_some_and(CancelToken::is_cancelled)
+        };
+
+        let mut grid = match &self.initial {
+            Some(initial) => initial.clone(),
+            None => self.automaton.random_grid(self.area.extent),
+        };
+        for _ in 0..self.automaton.iterations {
+            if is_cancelled() {
+                return None;
+            }
+            grid = self.automaton.step(&grid);
+        }
+        Some(grid)
+    }
+}
+
+/// The result of a `CellularAutomataRunner`, inserted on the tilemap before applying.
+#[derive(Component, Debug, Clone, Reflect)]
+pub struct CellularData(pub CellularGrid);
+
+#[cfg(feature = "multi-threaded")]
+#[derive(Component)]
+pub struct CellularTask {
+    task: Task<Option<CellularGrid>>,
+    /// Cancelled when the task is dropped before finishing, like when the entity
+    /// is despawned or the task is replaced by a restarted one.
+    dropped: CancelToken,
+}
+
+#[cfg(feature = "multi-threaded")]
+impl Drop for CellularTask {
+    fn drop(&mut self) {
+        self.dropped.cancel();
+    }
+}
+
+#[cfg(feature = "multi-threaded")]
+pub fn cellular_automata(
+    mut commands: Commands,
+    runners_query: Query<
+        (Entity, Ref<CellularAutomataRunner>, Has<CellularTask>),
+        Without<CellularData>,
+    >,
+) {
+    let thread_pool = AsyncComputeTaskPool::get();
+    runners_query
+        .iter()
+        .filter(|(_, runner, has_task)| !has_task || runner.is_changed())
+        .for_each(|(entity, runner, _)| {
+            let runner = runner.clone();
+            let dropped = CancelToken::new();
+            let token = dropped.clone();
+            let task = thread_pool.spawn(async move { runner.generate(Some(&token)) });
+            // Replacing the previous task drops it.
+            commands
+                .entity(entity)
+                .insert(CellularTask { task, dropped });
+        });
+}
+
+#[cfg(feature = "multi-threaded")]
+pub fn cellular_data_assigner(
+    mut commands: Commands,
+    mut tasks_query: Query<(Entity, &mut CellularTask)>,
+) {
+    tasks_query.iter_mut().for_each(|(entity, mut task)| {
+        if let Some(grid) = bevy::tasks::block_on(futures_lite::future::poll_once(&mut task.task)) {
+            let mut entity = commands.entity(entity);
+            entity.remove::<CellularTask>();
+            match grid {
+                Some(grid) => entity.insert(CellularData(grid)),
+                None => entity.remove::<CellularAutomataRunner>(),
+            };
+        }
+    });
+}
+
+#[cfg(not(feature = "multi-threaded"))]
+pub fn cellular_automata_single_threaded(
+    mut commands: Commands,
+    runners_query: Query<(Entity, &CellularAutomataRunner), Without<CellularData>>,
+) {
+    runners_query.iter().for_each(|(entity, runner)| {
+        let mut entity = commands.entity(entity);
+        match runner.generate(None) {
+            Some(grid) => entity.insert(CellularData(grid)),
+            None => entity.remove::<CellularAutomataRunner>(),
+        };
+    });
+}
+
+pub fn cellular_applier(
+    mut commands: Commands,
+    mut tilemaps_query: Query<(
+        Entity,
+        &mut TilemapStorage,
+        &CellularAutomataRunner,
+        &CellularData,
+        Option<&TilemapConstraints>,
+    )>,
+    spawn_budget: Res<TileSpawnBudget>,
+    mut spawn_queue: ResMut<TileSpawnQueue>,
+) {
+    tilemaps_query
+        .iter_mut()
+        .for_each(|(entity, mut storage, runner, data, constraints)| {
+            let area = runner.area;
+            for y in 0..area.extent.y {
+                for x in 0..area.extent.x {
+                    let index = area.origin + IVec2::new(x as i32, y as i32);
+                    if constraints.is_some_and(|c| c.is_locked(index)) {
+                        continue;
+                    }
+
+                    let tile = if data.0.get(IVec2::new(x as i32, y as i32)).unwrap() {
+                        Some(&runner.alive)
+                    } else {
+                        runner.dead.as_ref()
+                    };
+                    match (tile, spawn_budget.is_limited()) {
+                        (Some(tile), true) => spawn_queue.set(entity, index, tile.clone()),
+                        (Some(tile), false) => storage.set(&mut commands, index, tile.clone()),
+                        (None, _) => storage.remove(&mut commands, index),
+                    }
+                }
+            }
+
+            if let Some(constraints) = constraints {
+                constraints.apply(
+                    &mut commands,
+                    entity,
+                    &mut storage,
+                    spawn_budget.is_limited().then_some(spawn_queue.as_mut()),
+                );
+            }
+
+            commands
+                .entity(entity)
+                .remove::<CellularData>()
+                .remove::<CellularAutomataRunner>();
+        });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cellular_automaton() {
+        let life = CellularAutomaton::new(&[3], &[2, 3]);
+        let horizontal = CellularGrid::from_fn(UVec2::splat(3), |i| i.y == 1);
+        let vertical = CellularGrid::from_fn(UVec2::splat(3), |i| i.x == 1);
+
+        // The blinker oscillates.
+        assert_eq!(life.step(&horizontal), vertical);
+        assert_eq!(life.with_iterations(2).run(horizontal.clone()), horizontal);
+
+        // The caves are closed at the edges and deterministic with a seed.
+        let cave = CellularAutomaton::cave().with_seed(7);
+        let grid = cave.generate(UVec2::splat(32));
+        assert_eq!(grid, cave.generate(UVec2::splat(32)));
+        assert!(grid.get(IVec2::ZERO).unwrap());
+        assert!(grid.alive_count() < 32 * 32);
+    }
+
+    #[test]
+    fn test_cancel() {
+        let area = TileArea::new(IVec2::ZERO, UVec2::splat(8));
+        let runner =
+            CellularAutomataRunner::new(CellularAutomaton::cave(), area, TileBuilder::new());
+        let dropped = CancelToken::new();
+        assert!(runner.generate(Some(&dropped)).is_some());
+
+        dropped.cancel();
+        assert!(runner.generate(Some(&dropped)).is_none());
+
+        let token = CancelToken::new();
+        token.cancel();
+        assert!(runner.with_cancel_token(token).generate(None).is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_count() {
+        CellularAutomaton::new(&[3], &[2, 16]);
+    }
+}
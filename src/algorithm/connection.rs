@@ -0,0 +1,362 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        system::{Commands, Query, Res, Resource},
+    },
+    math::IVec2,
+    reflect::Reflect,
+    utils::HashMap,
+};
+
+use crate::tilemap::{algorithm::path::PathCapabilities, map::TilemapType};
+
+use super::pathfinding::{
+    Path, PathAlgorithm, PathFailure, PathFinder, PathGrid, PathNotFound, PathTilemaps,
+};
+
+/// A tile on a specific tilemap.
+pub type TilemapTile = (Entity, IVec2);
+
+/// A connection point between two tilemaps, like a door or stairs.
+///
+/// Both ends should be walkable tiles in their `PathTilemap`s.
+#[derive(Debug, Clone, Copy, Reflect)]
+pub struct TilemapConnection {
+    pub from: TilemapTile,
+    pub to: TilemapTile,
+    /// The cost to go through the connection.
+    pub cost: u32,
+    /// If false, the connection can only be used from `from` to `to`.
+    pub bidirectional: bool,
+}
+
+/// All the connections between tilemaps, used by `WorldPathFinder`.
+#[derive(Resource, Debug, Default, Clone, Reflect)]
+pub struct TilemapConnections {
+    pub(crate) connections: Vec<TilemapConnection>,
+}
+
+impl TilemapConnections {
+    /// Connect two tiles both ways.
+    pub fn connect(&mut self, a: TilemapTile, b: TilemapTile, cost: u32) {
+        self.connections.push(TilemapConnection {
+            from: a,
+            to: b,
+            cost,
+            bidirectional: true,
+        });
+    }
+
+    /// Connect two tiles, only from `from` to `to`.
+    pub fn connect_one_way(&mut self, from: TilemapTile, to: TilemapTile, cost: u32) {
+        self.connections.push(TilemapConnection {
+            from,
+            to,
+            cost,
+            bidirectional: false,
+        });
+    }
+
+    /// Remove every connection from or to the tilemap.
+    pub fn disconnect_tilemap(&mut self, tilemap: Entity) {
+        self.connections
+            .retain(|c| c.from.0 != tilemap && c.to.0 != tilemap);
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &TilemapConnection> {
+        self.connections.iter()
+    }
+
+    /// The tiles that can be reached from `tile` through a connection, and the costs.
+    pub fn exits(&self, tile: TilemapTile) -> impl Iterator<Item = (TilemapTile, u32)> + '_ {
+        self.connections.iter().filter_map(move |c| {
+            if c.from == tile {
+                Some((c.to, c.cost))
+            } else if c.bidirectional && c.to == tile {
+                Some((c.from, c.cost))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// Find a path that may go through multiple tilemaps using the `TilemapConnections`.
+///
+/// The connections form an abstract graph, and the paths inside each tilemap are found
+/// with A* only when needed. `WorldPath` or `PathNotFound` is inserted on the entity
+/// in the same frame.
+#[derive(Component, Debug, Clone, Reflect)]
+pub struct WorldPathFinder {
+    pub origin: TilemapTile,
+    pub dest: TilemapTile,
+    pub allow_diagonal: bool,
+    #[reflect(ignore)]
+    pub capabilities: PathCapabilities,
+    /// The maximum steps of each search inside a tilemap.
+    pub max_steps: Option<u32>,
+}
+
+/// A path through multiple tilemaps.
+///
+/// Each segment is a `Path` on a single tilemap. Like `Path`, a segment doesn't contain
+/// the tile where it starts, which is the origin or the end of the connection
+/// from the previous segment.
+#[derive(Component, Debug, Clone, Reflect)]
+pub struct WorldPath {
+    segments: Vec<Path>,
+    current_segment: usize,
+}
+
+impl WorldPath {
+    #[inline]
+    pub fn segments(&self) -> &[Path] {
+        &self.segments
+    }
+
+    /// The segment being followed, or `None` if arrived.
+    #[inline]
+    pub fn cur_segment(&self) -> Option<&Path> {
+        self.segments.get(self.current_segment)
+    }
+
+    #[inline]
+    pub fn cur_segment_mut(&mut self) -> Option<&mut Path> {
+        self.segments.get_mut(self.current_segment)
+    }
+
+    /// Move on to the next segment, after going through a connection.
+    pub fn next_segment(&mut self) {
+        if self.current_segment < self.segments.len() {
+            self.current_segment += 1;
+        }
+    }
+
+    #[inline]
+    pub fn is_arrived(&self) -> bool {
+        self.current_segment >= self.segments.len()
+    }
+}
+
+/// Returns the segments of the cheapest path through the connections.
+pub fn find_world_path(
+    finder: &WorldPathFinder,
+    connections: &TilemapConnections,
+    path_tilemaps: &PathTilemaps,
+    tilemap_type: impl Fn(Entity) -> Option<TilemapType>,
+) -> Result<Vec<Path>, PathFailure> {
+    let mut nodes = vec![finder.origin, finder.dest];
+    connections.iter().for_each(|c| {
+        [c.from, c.to].into_iter().for_each(|tile| {
+            if !nodes.contains(&tile) {
+                nodes.push(tile);
+            }
+        })
+    });
+
+    let mut local_paths = HashMap::new();
+    let mut costs = HashMap::from([(0, 0)]);
+    let mut parents: HashMap<usize, (usize, Option<Path>)> = HashMap::new();
+    let mut to_explore = BinaryHeap::from([Reverse((0, 0))]);
+
+    while let Some(Reverse((cost, current))) = to_explore.pop() {
+        if current == 1 {
+            break;
+        }
+        if costs[&current] < cost {
+            continue;
+        }
+
+        let (tilemap, index) = nodes[current];
+        let mut edges = Vec::new();
+        for (next, (next_tilemap, next_index)) in nodes.iter().enumerate() {
+            if next == current || *next_tilemap != tilemap {
+                continue;
+            }
+            let local = local_paths.entry((current, next)).or_insert_with(|| {
+                let ty = tilemap_type(tilemap)?;
+                local_path(finder, path_tilemaps, tilemap, ty, index, *next_index)
+            });
+            if let Some((path, local_cost)) = local {
+                edges.push((next, *local_cost, Some(path.clone())));
+            }
+        }
+        connections.exits(nodes[current]).for_each(|(tile, cost)| {
+            let next = nodes.iter().position(|n| *n == tile).unwrap();
+            edges.push((next, cost, None));
+        });
+
+        for (next, edge_cost, path) in edges {
+            let new_cost = cost + edge_cost;
+            if costs.get(&next).is_some_and(|c| *c <= new_cost) {
+                continue;
+            }
+            costs.insert(next, new_cost);
+            parents.insert(next, (current, path));
+            to_explore.push(Reverse((new_cost, next)));
+        }
+    }
+
+    if !costs.contains_key(&1) {
+        return Err(PathFailure::Unreachable);
+    }
+
+    let mut segments = Vec::new();
+    let mut current = 1;
+    while let Some((parent, path)) = parents.remove(&current) {
+        segments.extend(path.filter(|p| p.iter().next().is_some()));
+        current = parent;
+    }
+    segments.reverse();
+    Ok(segments)
+}
+
+/// The path inside a single tilemap and its cost.
+fn local_path(
+    finder: &WorldPathFinder,
+    path_tilemaps: &PathTilemaps,
+    tilemap: Entity,
+    tilemap_ty: TilemapType,
+    origin: IVec2,
+    dest: IVec2,
+) -> Option<(Path, u32)> {
+    let local_finder = PathFinder {
+        origin,
+        dest,
+        allow_diagonal: finder.allow_diagonal,
+        capabilities: finder.capabilities,
+        max_steps: finder.max_steps,
+        algorithm: PathAlgorithm::AStar,
+        bidirectional: false,
+        max_steps_per_frame: u32::MAX,
+        cancel: None,
+        #[cfg(not(feature = "multi-threaded"))]
+        tilemap_ty,
+    };
+
+    #[cfg(feature = "multi-threaded")]
+    let (mut grid, path_tilemaps) = (
+        PathGrid::new(
+            local_finder,
+            tilemap,
+            tilemap,
+            tilemap_ty,
+            path_tilemaps.get(tilemap)?,
+        ),
+        None,
+    );
+    #[cfg(not(feature = "multi-threaded"))]
+    let (mut grid, path_tilemaps) = {
+        path_tilemaps.get(tilemap)?;
+        (
+            PathGrid::new(local_finder, tilemap, tilemap, tilemap_ty),
+            Some(path_tilemaps),
+        )
+    };
+
+    while !grid.is_done {
+        grid.find_path(path_tilemaps);
+    }
+    let path = grid.result().ok()?;
+    Some((path, grid.all_nodes[&dest].g_cost))
+}
+
+pub fn world_path_finder(
+    mut commands: Commands,
+    finders_query: Query<(Entity, &WorldPathFinder)>,
+    tilemaps_query: Query<&TilemapType>,
+    path_tilemaps: Res<PathTilemaps>,
+    connections: Res<TilemapConnections>,
+) {
+    finders_query.iter().for_each(|(requester, finder)| {
+        let result = find_world_path(finder, &connections, &path_tilemaps, |tilemap| {
+            tilemaps_query.get(tilemap).ok().copied()
+        });
+
+        let mut requester = commands.entity(requester);
+        requester.remove::<WorldPathFinder>();
+        match result {
+            Ok(segments) => {
+                requester
+                    .insert(WorldPath {
+                        segments,
+                        current_segment: 0,
+                    })
+                    .remove::<PathNotFound>();
+            }
+            Err(reason) => {
+                requester
+                    .insert(PathNotFound {
+                        tilemap: finder.dest.0,
+                        reason,
+                    })
+                    .remove::<WorldPath>();
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::math::UVec2;
+
+    use crate::{
+        math::TileArea,
+        tilemap::algorithm::path::{PathTile, PathTilemap},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_world_path() {
+        let (a, b) = (Entity::from_raw(1), Entity::from_raw(2));
+        let mut path_tilemaps = PathTilemaps::default();
+        for tilemap in [a, b] {
+            let mut path_tilemap = PathTilemap::new();
+            path_tilemap.fill_path_rect(
+                TileArea::new(IVec2::ZERO, UVec2::new(3, 1)),
+                PathTile::new(1),
+            );
+            path_tilemaps.insert(tilemap, path_tilemap);
+        }
+
+        let mut connections = TilemapConnections::default();
+        connections.connect((a, IVec2::new(2, 0)), (b, IVec2::ZERO), 5);
+
+        let finder = WorldPathFinder {
+            origin: (a, IVec2::ZERO),
+            dest: (b, IVec2::new(2, 0)),
+            allow_diagonal: false,
+            capabilities: PathCapabilities::all(),
+            max_steps: None,
+        };
+        let ty = |_| Some(TilemapType::Square);
+
+        let segments = find_world_path(&finder, &connections, &path_tilemaps, ty).unwrap();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].tilemap(), a);
+        assert_eq!(segments[0].iter().next(), Some(&IVec2::new(2, 0)));
+        assert_eq!(segments[1].tilemap(), b);
+        assert_eq!(segments[1].iter().count(), 2);
+
+        // The way back only works if the connection is bidirectional.
+        let back = WorldPathFinder {
+            origin: finder.dest,
+            dest: finder.origin,
+            ..finder.clone()
+        };
+        assert!(find_world_path(&back, &connections, &path_tilemaps, ty).is_ok());
+
+        let mut one_way = TilemapConnections::default();
+        one_way.connect_one_way((a, IVec2::new(2, 0)), (b, IVec2::ZERO), 5);
+        assert!(find_world_path(&finder, &one_way, &path_tilemaps, ty).is_ok());
+        assert_eq!(
+            find_world_path(&back, &one_way, &path_tilemaps, ty).unwrap_err(),
+            PathFailure::Unreachable
+        );
+    }
+}
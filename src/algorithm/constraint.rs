@@ -0,0 +1,97 @@
+use bevy::{
+    ecs::{component::Component, entity::Entity, system::Commands},
+    math::IVec2,
+    reflect::Reflect,
+    utils::HashMap,
+};
+
+use crate::{
+    math::TileArea,
+    tilemap::{budget::TileSpawnQueue, map::TilemapStorage, tile::TileBuilder},
+};
+
+/// Hand authored cells that the generators respect. Insert this on the tilemap before the generation.
+///
+/// The wfc applier, the cellular automata applier and the post processing rules read it
+/// from the tilemap. Pass it to `DungeonGrid::apply` for the maze and BSP dungeons.
+///
+/// Pinned cells always end up with the pinned tile, and masked cells are never touched.
+/// Use `WfcRunner::with_fixed_element` as well if the generated surroundings
+/// should fit the pinned tiles.
+#[derive(Component, Debug, Clone, Default, Reflect)]
+pub struct TilemapConstraints {
+    pub(crate) pinned: HashMap<IVec2, TileBuilder>,
+    pub(crate) masks: Vec<TileArea>,
+}
+
+impl TilemapConstraints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The cell must be this tile.
+    pub fn with_pinned(mut self, index: IVec2, tile: TileBuilder) -> Self {
+        self.pin(index, tile);
+        self
+    }
+
+    /// The cells in this area are left as they are.
+    pub fn with_mask(mut self, area: TileArea) -> Self {
+        self.mask(area);
+        self
+    }
+
+    #[inline]
+    pub fn pin(&mut self, index: IVec2, tile: TileBuilder) {
+        self.pinned.insert(index, tile);
+    }
+
+    #[inline]
+    pub fn unpin(&mut self, index: IVec2) -> Option<TileBuilder> {
+        self.pinned.remove(&index)
+    }
+
+    #[inline]
+    pub fn mask(&mut self, area: TileArea) {
+        self.masks.push(area);
+    }
+
+    #[inline]
+    pub fn get_pinned(&self, index: IVec2) -> Option<&TileBuilder> {
+        self.pinned.get(&index)
+    }
+
+    #[inline]
+    pub fn pinned(&self) -> impl Iterator<Item = (IVec2, &TileBuilder)> {
+        self.pinned.iter().map(|(index, tile)| (*index, tile))
+    }
+
+    #[inline]
+    pub fn is_masked(&self, index: IVec2) -> bool {
+        self.masks.iter().any(|area| area.aabb().contains(index))
+    }
+
+    /// Returns true if generators should not write to this cell.
+    #[inline]
+    pub fn is_locked(&self, index: IVec2) -> bool {
+        self.pinned.contains_key(&index) || self.is_masked(index)
+    }
+
+    /// Set the pinned tiles on the tilemap, through the spawn queue if it's given.
+    pub fn apply(
+        &self,
+        commands: &mut Commands,
+        tilemap: Entity,
+        storage: &mut TilemapStorage,
+        spawn_queue: Option<&mut TileSpawnQueue>,
+    ) {
+        match spawn_queue {
+            Some(queue) => self
+                .pinned()
+                .for_each(|(index, tile)| queue.set(tilemap, index, tile.clone())),
+            None => self
+                .pinned()
+                .for_each(|(index, tile)| storage.set(commands, index, tile.clone())),
+        }
+    }
+}
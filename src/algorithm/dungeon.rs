@@ -0,0 +1,486 @@
+use bevy::{
+    ecs::system::Commands,
+    math::{IVec2, UVec2},
+    reflect::Reflect,
+};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::{
+    algorithm::constraint::TilemapConstraints,
+    math::TileArea,
+    serializing::pattern::TilemapPattern,
+    tilemap::{
+        algorithm::path::PathTile, buffers::TileBuilderBuffer, map::TilemapStorage,
+        tile::TileBuilder,
+    },
+};
+
+/// The floors and walls generated by `MazeGenerator` or `BspDungeonGenerator`,
+/// with `(0, 0)` at the bottom left corner.
+#[derive(Debug, Clone, Reflect)]
+pub struct DungeonGrid {
+    size: UVec2,
+    floors: Vec<bool>,
+    rooms: Vec<TileArea>,
+}
+
+impl DungeonGrid {
+    fn new(size: UVec2) -> Self {
+        Self {
+            size,
+            floors: vec![false; (size.x * size.y) as usize],
+            rooms: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// The rooms of the dungeon. Empty for mazes.
+    #[inline]
+    pub fn rooms(&self) -> &[TileArea] {
+        &self.rooms
+    }
+
+    /// Returns false for the walls and the indices out of the grid.
+    #[inline]
+    pub fn is_floor(&self, index: IVec2) -> bool {
+        index.x >= 0
+            && index.y >= 0
+            && index.x < self.size.x as i32
+            && index.y < self.size.y as i32
+            && self.floors[(index.y * self.size.x as i32 + index.x) as usize]
+    }
+
+    #[inline]
+    fn set_floor(&mut self, index: IVec2) {
+        self.floors[(index.y * self.size.x as i32 + index.x) as usize] = true;
+    }
+
+    fn fill_floor(&mut self, area: TileArea) {
+        area.aabb()
+            .into_iter()
+            .for_each(|index| self.set_floor(index));
+    }
+
+    /// Iterate over all the indices, and whether they are floors.
+    pub fn iter(&self) -> impl Iterator<Item = (IVec2, bool)> + '_ {
+        self.floors.iter().enumerate().map(|(i, floor)| {
+            (
+                IVec2 {
+                    x: i as i32 % self.size.x as i32,
+                    y: i as i32 / self.size.x as i32,
+                },
+                *floor,
+            )
+        })
+    }
+
+    /// The walls are left empty if `wall` is `None`.
+    pub fn to_buffer(&self, floor: &TileBuilder, wall: Option<&TileBuilder>) -> TileBuilderBuffer {
+        let mut buffer = TileBuilderBuffer::new();
+        self.iter().for_each(|(index, is_floor)| {
+            if is_floor {
+                buffer.set(index, floor.clone());
+            } else if let Some(wall) = wall {
+                buffer.set(index, wall.clone());
+            }
+        });
+        buffer
+    }
+
+    /// The floors are also walkable in the path tiles of the pattern.
+    pub fn to_pattern(
+        &self,
+        label: Option<String>,
+        floor: &TileBuilder,
+        wall: Option<&TileBuilder>,
+    ) -> TilemapPattern {
+        let mut pattern = TilemapPattern::new(label);
+        pattern.tiles = self.to_buffer(floor, wall);
+        self.iter()
+            .filter(|(_, is_floor)| *is_floor)
+            .for_each(|(index, _)| pattern.path_tiles.set(index, PathTile::new(1)));
+        pattern
+    }
+
+    /// Set the tiles directly on the tilemap, with the bottom left corner at `origin`.
+    ///
+    /// The cells locked by the constraints are left as they are,
+    /// and the pinned tiles are set afterwards.
+    pub fn apply(
+        &self,
+        commands: &mut Commands,
+        storage: &mut TilemapStorage,
+        origin: IVec2,
+        floor: &TileBuilder,
+        wall: Option<&TileBuilder>,
+        constraints: Option<&TilemapConstraints>,
+    ) {
+        let mut buffer = self.to_buffer(floor, wall);
+        if let Some(constraints) = constraints {
+            buffer
+                .tiles
+                .retain(|index, _| !constraints.is_locked(origin + *index));
+        }
+        storage.fill_with_buffer(commands, origin, buffer);
+
+        if let Some(constraints) = constraints {
+            let tilemap = storage.tilemap;
+            constraints.apply(commands, tilemap, storage, None);
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum MazeAlgorithm {
+    /// Long winding corridors with few dead ends.
+    #[default]
+    RecursiveBacktracker,
+    /// More branches and shorter dead ends.
+    Kruskal,
+}
+
+/// Generates perfect mazes, where there's exactly one path between any two cells.
+///
+/// Each cell is a floor tile, and the cells are separated by wall tiles.
+/// So the size of the result is `size * 2 + 1`.
+#[derive(Debug, Clone, Reflect)]
+pub struct MazeGenerator {
+    /// The amount of cells.
+    pub size: UVec2,
+    pub algorithm: MazeAlgorithm,
+    pub seed: Option<u64>,
+}
+
+impl MazeGenerator {
+    pub fn new(size: UVec2) -> Self {
+        Self {
+            size,
+            algorithm: MazeAlgorithm::default(),
+            seed: None,
+        }
+    }
+
+    pub fn with_algorithm(mut self, algorithm: MazeAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn generate(&self) -> DungeonGrid {
+        let mut rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let mut grid = DungeonGrid::new(self.size * 2 + 1);
+        let size = self.size.as_ivec2();
+        let cell_count = (size.x * size.y) as usize;
+        if cell_count == 0 {
+            return grid;
+        }
+
+        let to_tile = |cell: IVec2| cell * 2 + 1;
+        let in_maze =
+            |cell: IVec2| cell.x >= 0 && cell.y >= 0 && cell.x < size.x && cell.y < size.y;
+        let linear = |cell: IVec2| (cell.y * size.x + cell.x) as usize;
+        let dirs = [IVec2::Y, IVec2::X, IVec2::NEG_X, IVec2::NEG_Y];
+
+        match self.algorithm {
+            MazeAlgorithm::RecursiveBacktracker => {
+                let mut visited = vec![false; cell_count];
+                let mut stack = vec![IVec2::ZERO];
+                visited[0] = true;
+                grid.set_floor(to_tile(IVec2::ZERO));
+
+                while let Some(current) = stack.last().copied() {
+                    let unvisited = dirs
+                        .iter()
+                        .map(|dir| current + *dir)
+                        .filter(|next| in_maze(*next) && !visited[linear(*next)])
+                        .collect::<Vec<_>>();
+
+                    match unvisited.choose(&mut rng) {
+                        Some(next) => {
+                            visited[linear(*next)] = true;
+                            grid.set_floor(to_tile(current) + *next - current);
+                            grid.set_floor(to_tile(*next));
+                            stack.push(*next);
+                        }
+                        None => {
+                            stack.pop();
+                        }
+                    }
+                }
+            }
+            MazeAlgorithm::Kruskal => {
+                let mut sets = (0..cell_count).collect::<Vec<_>>();
+                fn find(sets: &mut [usize], i: usize) -> usize {
+                    if sets[i] != i {
+                        sets[i] = find(sets, sets[i]);
+                    }
+                    sets[i]
+                }
+
+                let mut walls = (0..size.y)
+                    .flat_map(|y| (0..size.x).map(move |x| IVec2 { x, y }))
+                    .flat_map(|cell| [(cell, IVec2::X), (cell, IVec2::Y)])
+                    .filter(|(cell, dir)| in_maze(*cell + *dir))
+                    .collect::<Vec<_>>();
+                walls.shuffle(&mut rng);
+
+                (0..size.y)
+                    .flat_map(|y| (0..size.x).map(move |x| IVec2 { x, y }))
+                    .for_each(|cell| grid.set_floor(to_tile(cell)));
+                for (cell, dir) in walls {
+                    let (a, b) = (
+                        find(&mut sets, linear(cell)),
+                        find(&mut sets, linear(cell + dir)),
+                    );
+                    if a != b {
+                        sets[a] = b;
+                        grid.set_floor(to_tile(cell) + dir);
+                    }
+                }
+            }
+        }
+
+        grid
+    }
+}
+
+/// Generates rooms connected by corridors, by splitting the area recursively
+/// and putting a room in each leaf.
+#[derive(Debug, Clone, Reflect)]
+pub struct BspDungeonGenerator {
+    pub size: UVec2,
+    /// The minimum size of the rooms, the leaves are at least this plus the padding.
+    pub min_room_size: UVec2,
+    /// The amount of wall tiles around the rooms in each leaf.
+    pub room_padding: u32,
+    /// How many times the area is split at most.
+    pub max_depth: u32,
+    pub seed: Option<u64>,
+}
+
+impl BspDungeonGenerator {
+    pub fn new(size: UVec2) -> Self {
+        Self {
+            size,
+            min_room_size: UVec2::splat(4),
+            room_padding: 1,
+            max_depth: 4,
+            seed: None,
+        }
+    }
+
+    pub fn with_min_room_size(mut self, min_room_size: UVec2) -> Self {
+        self.min_room_size = min_room_size;
+        self
+    }
+
+    pub fn with_room_padding(mut self, room_padding: u32) -> Self {
+        self.room_padding = room_padding;
+        self
+    }
+
+    pub fn with_max_depth(mut self, max_depth: u32) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn generate(&self) -> DungeonGrid {
+        let mut rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let mut grid = DungeonGrid::new(self.size);
+        self.split(
+            &mut grid,
+            TileArea::new(IVec2::ZERO, self.size),
+            0,
+            &mut rng,
+        );
+        grid
+    }
+
+    /// Returns the indices of the rooms in the leaf.
+    fn split(
+        &self,
+        grid: &mut DungeonGrid,
+        leaf: TileArea,
+        depth: u32,
+        rng: &mut StdRng,
+    ) -> Vec<usize> {
+        let min_leaf = self.min_room_size + self.room_padding * 2;
+        let can_split_x = leaf.extent.x >= min_leaf.x * 2;
+        let can_split_y = leaf.extent.y >= min_leaf.y * 2;
+
+        if depth >= self.max_depth || !(can_split_x || can_split_y) {
+            if leaf.extent.x < min_leaf.x || leaf.extent.y < min_leaf.y {
+                return Vec::new();
+            }
+            let max_room = leaf.extent - self.room_padding * 2;
+            let size = UVec2 {
+                x: rng.gen_range(self.min_room_size.x..=max_room.x),
+                y: rng.gen_range(self.min_room_size.y..=max_room.y),
+            };
+            let offset = UVec2 {
+                x: rng.gen_range(0..=max_room.x - size.x),
+                y: rng.gen_range(0..=max_room.y - size.y),
+            } + self.room_padding;
+            let room = TileArea::new(leaf.origin + offset.as_ivec2(), size);
+            grid.fill_floor(room);
+            grid.rooms.push(room);
+            return vec![grid.rooms.len() - 1];
+        }
+
+        // Prefer splitting the longer side.
+        let split_x = match (can_split_x, can_split_y) {
+            (true, true) => {
+                leaf.extent.x > leaf.extent.y || (leaf.extent.x == leaf.extent.y && rng.gen())
+            }
+            (split_x, _) => split_x,
+        };
+        let (first, second) = if split_x {
+            let at = rng.gen_range(min_leaf.x..=leaf.extent.x - min_leaf.x);
+            (
+                TileArea::new(leaf.origin, UVec2::new(at, leaf.extent.y)),
+                TileArea::new(
+                    leaf.origin + IVec2::new(at as i32, 0),
+                    UVec2::new(leaf.extent.x - at, leaf.extent.y),
+                ),
+            )
+        } else {
+            let at = rng.gen_range(min_leaf.y..=leaf.extent.y - min_leaf.y);
+            (
+                TileArea::new(leaf.origin, UVec2::new(leaf.extent.x, at)),
+                TileArea::new(
+                    leaf.origin + IVec2::new(0, at as i32),
+                    UVec2::new(leaf.extent.x, leaf.extent.y - at),
+                ),
+            )
+        };
+
+        let mut first = self.split(grid, first, depth + 1, rng);
+        let second = self.split(grid, second, depth + 1, rng);
+        if let (Some(a), Some(b)) = (first.choose(rng), second.choose(rng)) {
+            let (a, b) = (grid.rooms[*a], grid.rooms[*b]);
+            self.dig_corridor(grid, center(a), center(b), rng);
+        }
+        first.extend(second);
+        first
+    }
+
+    /// Dig an L shaped corridor.
+    fn dig_corridor(&self, grid: &mut DungeonGrid, from: IVec2, to: IVec2, rng: &mut StdRng) {
+        let corner = if rng.gen() {
+            IVec2::new(to.x, from.y)
+        } else {
+            IVec2::new(from.x, to.y)
+        };
+        for (a, b) in [(from, corner), (corner, to)] {
+            grid.fill_floor(TileArea::from_min_max(a.min(b), a.max(b)));
+        }
+    }
+}
+
+#[inline]
+fn center(area: TileArea) -> IVec2 {
+    area.origin + area.extent.as_ivec2() / 2
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::utils::HashSet;
+
+    use super::*;
+
+    /// The amount of floor tiles reachable from the first floor tile.
+    fn reachable(grid: &DungeonGrid) -> usize {
+        let Some((first, _)) = grid.iter().find(|(_, floor)| *floor) else {
+            return 0;
+        };
+        let mut visited = HashSet::from([first]);
+        let mut stack = vec![first];
+        while let Some(index) = stack.pop() {
+            for dir in [IVec2::Y, IVec2::X, IVec2::NEG_X, IVec2::NEG_Y] {
+                let next = index + dir;
+                if grid.is_floor(next) && visited.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        visited.len()
+    }
+
+    #[test]
+    fn test_dungeon_generators() {
+        for algorithm in [MazeAlgorithm::RecursiveBacktracker, MazeAlgorithm::Kruskal] {
+            let maze = MazeGenerator::new(UVec2::new(8, 5))
+                .with_algorithm(algorithm)
+                .with_seed(0)
+                .generate();
+            assert_eq!(maze.size(), UVec2::new(17, 11));
+            // 40 cells and 39 passages between them.
+            let floors = maze.iter().filter(|(_, floor)| *floor).count();
+            assert_eq!(floors, 79);
+            assert_eq!(reachable(&maze), floors);
+        }
+
+        let dungeon = BspDungeonGenerator::new(UVec2::splat(48))
+            .with_seed(0)
+            .generate();
+        assert!(dungeon.rooms().len() > 1);
+        let floors = dungeon.iter().filter(|(_, floor)| *floor).count();
+        assert_eq!(reachable(&dungeon), floors);
+    }
+
+    #[test]
+    fn test_apply_constraints() {
+        use bevy::ecs::{system::CommandQueue, world::World};
+
+        use crate::tilemap::tile::{Tile, TileLayer};
+
+        let mut world = World::new();
+        let tilemap = world.spawn_empty().id();
+        let mut storage = TilemapStorage::new(4, tilemap);
+        let maze = MazeGenerator::new(UVec2::splat(2)).with_seed(0).generate();
+
+        let floor = TileBuilder::new().with_layer(0, TileLayer::no_flip(0));
+        let wall = TileBuilder::new().with_layer(0, TileLayer::no_flip(1));
+        let pinned = TileBuilder::new().with_layer(0, TileLayer::no_flip(2));
+        let constraints = TilemapConstraints::new()
+            .with_pinned(IVec2::new(11, 11), pinned.clone())
+            .with_mask(TileArea::new(IVec2::splat(10), UVec2::ONE));
+
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        maze.apply(
+            &mut commands,
+            &mut storage,
+            IVec2::splat(10),
+            &floor,
+            Some(&wall),
+            Some(&constraints),
+        );
+        queue.apply(&mut world);
+
+        // The corner is masked, and the tile next to it is pinned.
+        assert!(storage.get(IVec2::splat(10)).is_none());
+        let tile = world.get::<Tile>(storage.get(IVec2::splat(11)).unwrap());
+        assert!(tile.is_some_and(|t| t.texture == pinned.texture));
+        assert!(storage.get(IVec2::new(10, 14)).is_some());
+    }
+}
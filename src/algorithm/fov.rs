@@ -0,0 +1,241 @@
+use bevy::{
+    math::{IVec2, IVec3, Vec3},
+    utils::HashSet,
+};
+
+use crate::tilemap::map::TilemapType;
+
+/// Calculate the tiles visible from `origin` within `radius`.
+///
+/// Opaque tiles block the sight but are visible themselves. For square and isometric tilemaps,
+/// this uses symmetric shadowcasting, so if `a` can see `b` then `b` can also see `a`.
+/// For hexagonal tilemaps, a tile is visible if there's a clear hex line to it.
+pub fn field_of_view(
+    ty: TilemapType,
+    origin: IVec2,
+    radius: u32,
+    is_opaque: impl Fn(IVec2) -> bool,
+) -> HashSet<IVec2> {
+    let mut visible = HashSet::from([origin]);
+    match ty {
+        TilemapType::Square | TilemapType::Isometric => {
+            let mut caster = ShadowCaster {
+                origin,
+                radius: radius as i64,
+                is_opaque: &is_opaque,
+                visible: &mut visible,
+            };
+            for quadrant in [IVec2::Y, IVec2::X, IVec2::NEG_Y, IVec2::NEG_X] {
+                caster.scan(
+                    quadrant,
+                    Row {
+                        depth: 1,
+                        start: Slope::new(-1, 1),
+                        end: Slope::new(1, 1),
+                    },
+                );
+            }
+        }
+        TilemapType::Hexagonal(_) => hex_field_of_view(origin, radius, &is_opaque, &mut visible),
+    }
+    visible
+}
+
+/// A rational slope `num / den`, with `den > 0`.
+#[derive(Clone, Copy)]
+struct Slope {
+    num: i64,
+    den: i64,
+}
+
+impl Slope {
+    fn new(num: i64, den: i64) -> Self {
+        Self { num, den }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Row {
+    depth: i64,
+    start: Slope,
+    end: Slope,
+}
+
+impl Row {
+    fn columns(&self) -> std::ops::RangeInclusive<i64> {
+        // Round half up for the start, and half down for the end.
+        let min = (2 * self.depth * self.start.num + self.start.den).div_euclid(2 * self.start.den);
+        let max = -(-(2 * self.depth * self.end.num - self.end.den)).div_euclid(2 * self.end.den);
+        min..=max
+    }
+
+    fn next(&self) -> Self {
+        Self {
+            depth: self.depth + 1,
+            ..*self
+        }
+    }
+
+    fn is_symmetric(&self, col: i64) -> bool {
+        col * self.start.den >= self.depth * self.start.num
+            && col * self.end.den <= self.depth * self.end.num
+    }
+
+    /// The slope of the left edge of the tile.
+    fn slope(&self, col: i64) -> Slope {
+        Slope::new(2 * col - 1, 2 * self.depth)
+    }
+}
+
+struct ShadowCaster<'a, F: Fn(IVec2) -> bool> {
+    origin: IVec2,
+    radius: i64,
+    is_opaque: &'a F,
+    visible: &'a mut HashSet<IVec2>,
+}
+
+impl<'a, F: Fn(IVec2) -> bool> ShadowCaster<'a, F> {
+    fn tile(&self, quadrant: IVec2, depth: i64, col: i64) -> IVec2 {
+        let (depth, col) = (depth as i32, col as i32);
+        // Rotate (col, depth) from the north quadrant to the target one.
+        self.origin + quadrant * depth + quadrant.perp() * -col
+    }
+
+    fn scan(&mut self, quadrant: IVec2, mut row: Row) {
+        if row.depth > self.radius {
+            return;
+        }
+
+        let mut prev_opaque = None;
+        for col in row.columns() {
+            let tile = self.tile(quadrant, row.depth, col);
+            let opaque = (self.is_opaque)(tile);
+            if (opaque || row.is_symmetric(col))
+                && row.depth * row.depth + col * col <= self.radius * self.radius
+            {
+                self.visible.insert(tile);
+            }
+
+            match (prev_opaque, opaque) {
+                (Some(true), false) => row.start = row.slope(col),
+                (Some(false), true) => {
+                    let mut next = row.next();
+                    next.end = row.slope(col);
+                    self.scan(quadrant, next);
+                }
+                _ => {}
+            }
+            prev_opaque = Some(opaque);
+        }
+
+        if prev_opaque == Some(false) {
+            self.scan(quadrant, row.next());
+        }
+    }
+}
+
+/// The cube coordinates of a hexagonal tile.
+#[inline]
+fn to_cube(index: IVec2) -> IVec3 {
+    IVec3::new(index.x, -index.y, index.y - index.x)
+}
+
+#[inline]
+fn hex_distance(a: IVec2, b: IVec2) -> i32 {
+    let d = (to_cube(a) - to_cube(b)).abs();
+    d.x.max(d.y).max(d.z)
+}
+
+fn cube_round(cube: Vec3) -> IVec2 {
+    let mut rounded = cube.round();
+    let diff = (rounded - cube).abs();
+    if diff.x > diff.y && diff.x > diff.z {
+        rounded.x = -rounded.y - rounded.z;
+    } else if diff.y > diff.z {
+        rounded.y = -rounded.x - rounded.z;
+    }
+    IVec2::new(rounded.x as i32, -rounded.y as i32)
+}
+
+fn hex_field_of_view(
+    origin: IVec2,
+    radius: u32,
+    is_opaque: &impl Fn(IVec2) -> bool,
+    visible: &mut HashSet<IVec2>,
+) {
+    let radius = radius as i32;
+    let from = to_cube(origin).as_vec3();
+
+    // Lines nudged to both sides, so tiles exactly between two tiles are handled symmetrically.
+    let is_clear = |target: IVec2, nudge: Vec3| {
+        let steps = hex_distance(origin, target);
+        let to = to_cube(target).as_vec3();
+        (1..steps).all(|step| {
+            let t = step as f32 / steps as f32;
+            !is_opaque(cube_round(from + nudge + (to - from) * t))
+        })
+    };
+
+    for y in -radius..=radius {
+        for x in -radius..=radius {
+            let target = origin + IVec2::new(x, y);
+            if hex_distance(origin, target) > radius {
+                continue;
+            }
+
+            if is_clear(target, Vec3::new(1e-4, 2e-4, -3e-4))
+                || is_clear(target, Vec3::new(-1e-4, -2e-4, 3e-4))
+            {
+                visible.insert(target);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_square_fov() {
+        // A pillar at (0, 2).
+        let pillar = |index: IVec2| index == IVec2::new(0, 2);
+        let visible = field_of_view(TilemapType::Square, IVec2::ZERO, 4, pillar);
+
+        assert!(visible.contains(&IVec2::new(0, 2)));
+        assert!(!visible.contains(&IVec2::new(0, 3)));
+        assert!(!visible.contains(&IVec2::new(0, 4)));
+        assert!(visible.contains(&IVec2::new(4, 0)));
+        assert!(!visible.contains(&IVec2::new(4, 4)));
+
+        // Symmetry between all the transparent tiles.
+        let walls = [IVec2::new(1, 1), IVec2::new(-2, 0), IVec2::new(2, -1)];
+        let is_opaque = |index: IVec2| walls.contains(&index);
+        let tiles = (-3..=3)
+            .flat_map(|y| (-3..=3).map(move |x| IVec2::new(x, y)))
+            .filter(|index| !is_opaque(*index))
+            .collect::<Vec<_>>();
+        for a in tiles.iter() {
+            let from_a = field_of_view(TilemapType::Square, *a, 10, is_opaque);
+            for b in tiles.iter() {
+                let from_b = field_of_view(TilemapType::Square, *b, 10, is_opaque);
+                assert_eq!(from_a.contains(b), from_b.contains(a), "{} {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_hex_fov() {
+        let wall = |index: IVec2| index == IVec2::X;
+        let visible = field_of_view(TilemapType::Hexagonal(0), IVec2::ZERO, 3, wall);
+
+        assert!(visible.contains(&IVec2::X));
+        assert!(!visible.contains(&IVec2::new(2, 0)));
+        assert!(visible.contains(&IVec2::new(0, 3)));
+        assert!(!visible.contains(&IVec2::new(4, 0)));
+        assert_eq!(
+            field_of_view(TilemapType::Hexagonal(0), IVec2::ZERO, 2, |_| false).len(),
+            19
+        );
+    }
+}
@@ -0,0 +1,417 @@
+use bevy::{log::debug, math::IVec2, utils::HashSet};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::{
+    serializing::pattern::TilemapPattern,
+    tilemap::{
+        algorithm::path::PathTile,
+        tile::{TileBuilder, TileTexture},
+    },
+};
+
+const NEIGHBOURS: [IVec2; 4] = [IVec2::Y, IVec2::X, IVec2::NEG_X, IVec2::NEG_Y];
+
+/// A gameplay guarantee of a generated pattern, like the exit being reachable.
+///
+/// The path tiles of the pattern are the walkable cells, like the patterns
+/// from `DungeonGrid::to_pattern`.
+pub trait GenerationConstraint: Send + Sync {
+    /// Returns `true` if the pattern satisfies the constraint.
+    fn check(&self, pattern: &TilemapPattern) -> bool;
+
+    /// Edit the pattern locally so it satisfies the constraint.
+    ///
+    /// Returns `false` if it can't be repaired, and the pattern will be regenerated.
+    fn repair(&self, _pattern: &mut TilemapPattern, _rng: &mut StdRng) -> bool {
+        false
+    }
+}
+
+#[inline]
+fn is_walkable(pattern: &TilemapPattern, index: IVec2) -> bool {
+    pattern.path_tiles.get(index).is_some()
+}
+
+fn set_walkable(pattern: &mut TilemapPattern, index: IVec2, floor: &TileBuilder) {
+    pattern.tiles.set(index, floor.clone());
+    pattern.path_tiles.set(index, PathTile::new(1));
+}
+
+/// `from` and `to` must be walkable and connected, like the start and the exit.
+#[derive(Debug, Clone)]
+pub struct ConnectedConstraint {
+    pub from: IVec2,
+    pub to: IVec2,
+    /// If set, the constraint is repaired by carving a corridor with this tile.
+    pub carve: Option<TileBuilder>,
+}
+
+impl ConnectedConstraint {
+    pub fn new(from: IVec2, to: IVec2) -> Self {
+        Self {
+            from,
+            to,
+            carve: None,
+        }
+    }
+
+    pub fn with_carve(mut self, floor: TileBuilder) -> Self {
+        self.carve = Some(floor);
+        self
+    }
+}
+
+impl GenerationConstraint for ConnectedConstraint {
+    fn check(&self, pattern: &TilemapPattern) -> bool {
+        if !is_walkable(pattern, self.from) || !is_walkable(pattern, self.to) {
+            return false;
+        }
+
+        let mut visited = HashSet::from([self.from]);
+        let mut stack = vec![self.from];
+        while let Some(index) = stack.pop() {
+            if index == self.to {
+                return true;
+            }
+            NEIGHBOURS.iter().for_each(|dir| {
+                let next = index + *dir;
+                if is_walkable(pattern, next) && visited.insert(next) {
+                    stack.push(next);
+                }
+            });
+        }
+        false
+    }
+
+    /// Carves an L shaped corridor between the two tiles.
+    fn repair(&self, pattern: &mut TilemapPattern, _rng: &mut StdRng) -> bool {
+        let Some(floor) = &self.carve else {
+            return false;
+        };
+
+        let corner = IVec2::new(self.to.x, self.from.y);
+        let (min, max) = (self.from.min(corner), self.from.max(corner));
+        (min.x..=max.x).for_each(|x| set_walkable(pattern, IVec2::new(x, min.y), floor));
+        let (min, max) = (corner.min(self.to), corner.max(self.to));
+        (min.y..=max.y).for_each(|y| set_walkable(pattern, IVec2::new(min.x, y), floor));
+        true
+    }
+}
+
+/// There must be at least `min` tiles with this texture index, like resources or chests.
+///
+/// Repaired by replacing random walkable tiles with `tile`.
+#[derive(Debug, Clone)]
+pub struct MinTileCountConstraint {
+    pub texture_index: i32,
+    pub min: usize,
+    pub tile: TileBuilder,
+}
+
+impl MinTileCountConstraint {
+    pub fn new(texture_index: i32, min: usize, tile: TileBuilder) -> Self {
+        Self {
+            texture_index,
+            min,
+            tile,
+        }
+    }
+
+    fn has_texture(&self, tile: &TileBuilder) -> bool {
+        match &tile.texture {
+            TileTexture::Static(layers) => layers
+                .iter()
+                .any(|layer| layer.texture_index == self.texture_index),
+            TileTexture::Animated(_) => false,
+        }
+    }
+
+    fn count(&self, pattern: &TilemapPattern) -> usize {
+        pattern
+            .tiles
+            .tiles
+            .values()
+            .filter(|tile| self.has_texture(tile))
+            .count()
+    }
+}
+
+impl GenerationConstraint for MinTileCountConstraint {
+    fn check(&self, pattern: &TilemapPattern) -> bool {
+        self.count(pattern) >= self.min
+    }
+
+    fn repair(&self, pattern: &mut TilemapPattern, rng: &mut StdRng) -> bool {
+        let missing = self.min.saturating_sub(self.count(pattern));
+        let mut candidates = pattern
+            .path_tiles
+            .tiles
+            .keys()
+            .filter(|index| {
+                !pattern
+                    .tiles
+                    .get(**index)
+                    .is_some_and(|t| self.has_texture(t))
+            })
+            .copied()
+            .collect::<Vec<_>>();
+        if candidates.len() < missing {
+            return false;
+        }
+
+        // Sorted so the result only depends on the rng.
+        candidates.sort_unstable_by_key(|index| (index.y, index.x));
+        candidates
+            .choose_multiple(rng, missing)
+            .for_each(|index| pattern.tiles.set(*index, self.tile.clone()));
+        true
+    }
+}
+
+/// Walkable tiles can't have non-walkable tiles on both opposite sides,
+/// so there are no 1-tile-wide corridors.
+///
+/// Repaired by widening the corridors with `floor`, inside the bounds of the pattern.
+#[derive(Debug, Clone)]
+pub struct MinCorridorWidthConstraint {
+    pub floor: TileBuilder,
+}
+
+impl MinCorridorWidthConstraint {
+    pub fn new(floor: TileBuilder) -> Self {
+        Self { floor }
+    }
+
+    /// The walkable tiles squeezed between two walls, and the axis of the walls.
+    fn narrow_tiles(pattern: &TilemapPattern) -> Vec<(IVec2, IVec2)> {
+        let mut narrow = pattern
+            .path_tiles
+            .tiles
+            .keys()
+            .filter_map(|index| {
+                [IVec2::X, IVec2::Y].into_iter().find_map(|dir| {
+                    (!is_walkable(pattern, *index + dir) && !is_walkable(pattern, *index - dir))
+                        .then_some((*index, dir))
+                })
+            })
+            .collect::<Vec<_>>();
+        narrow.sort_unstable_by_key(|(index, _)| (index.y, index.x));
+        narrow
+    }
+}
+
+impl GenerationConstraint for MinCorridorWidthConstraint {
+    fn check(&self, pattern: &TilemapPattern) -> bool {
+        Self::narrow_tiles(pattern).is_empty()
+    }
+
+    fn repair(&self, pattern: &mut TilemapPattern, _rng: &mut StdRng) -> bool {
+        let aabb = pattern.tiles.aabb;
+        for (index, dir) in Self::narrow_tiles(pattern) {
+            if is_walkable(pattern, index + dir) || is_walkable(pattern, index - dir) {
+                // Already widened by a neighbour.
+                continue;
+            }
+            match [index + dir, index - dir]
+                .into_iter()
+                .find(|side| aabb.contains(*side))
+            {
+                Some(side) => set_walkable(pattern, side, &self.floor),
+                None => return false,
+            }
+        }
+        true
+    }
+}
+
+/// Why `GenerationGuarantees` failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenerationFailed {
+    /// The number of generated patterns.
+    pub attempts: u32,
+    /// The indices of the constraints the last pattern violated.
+    pub violated: Vec<usize>,
+}
+
+/// A validation and repair pass run after the generation, so the generated
+/// patterns always satisfy the gameplay constraints.
+///
+/// The failed constraints are repaired first, and the pattern is regenerated
+/// with a different seed if it can't be repaired.
+pub struct GenerationGuarantees {
+    pub constraints: Vec<Box<dyn GenerationConstraint>>,
+    /// The number of rounds of repairs before giving up on a pattern.
+    pub max_repairs: u32,
+    /// The number of patterns generated before `generate` fails.
+    pub max_attempts: u32,
+    pub seed: Option<u64>,
+}
+
+impl Default for GenerationGuarantees {
+    fn default() -> Self {
+        Self {
+            constraints: Vec::new(),
+            max_repairs: 4,
+            max_attempts: 16,
+            seed: None,
+        }
+    }
+}
+
+impl GenerationGuarantees {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_constraint(mut self, constraint: impl GenerationConstraint + 'static) -> Self {
+        self.constraints.push(Box::new(constraint));
+        self
+    }
+
+    pub fn with_max_repairs(mut self, max_repairs: u32) -> Self {
+        self.max_repairs = max_repairs;
+        self
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// The indices of the constraints the pattern violates.
+    pub fn violations(&self, pattern: &TilemapPattern) -> Vec<usize> {
+        self.constraints
+            .iter()
+            .enumerate()
+            .filter(|(_, constraint)| !constraint.check(pattern))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Repair the pattern until it satisfies all the constraints, as a repair
+    /// may break another constraint.
+    ///
+    /// Returns the violated constraints if it can't be repaired.
+    pub fn repair(&self, pattern: &mut TilemapPattern, rng: &mut StdRng) -> Result<(), Vec<usize>> {
+        for _ in 0..self.max_repairs {
+            let violated = self.violations(pattern);
+            if violated.is_empty() {
+                return Ok(());
+            }
+            if !violated
+                .iter()
+                .all(|i| self.constraints[*i].repair(pattern, rng))
+            {
+                return Err(self.violations(pattern));
+            }
+        }
+
+        let violated = self.violations(pattern);
+        if violated.is_empty() {
+            Ok(())
+        } else {
+            Err(violated)
+        }
+    }
+
+    /// Generate a pattern with `generator`, which takes a seed, then validate and repair it.
+    /// Returns the pattern and the seed it's generated with.
+    pub fn generate(
+        &self,
+        mut generator: impl FnMut(u64) -> TilemapPattern,
+    ) -> Result<(TilemapPattern, u64), GenerationFailed> {
+        let mut rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        let mut violated = Vec::new();
+        for attempt in 0..self.max_attempts {
+            let seed = rng.gen();
+            let mut pattern = generator(seed);
+            match self.repair(&mut pattern, &mut rng) {
+                Ok(()) => return Ok((pattern, seed)),
+                Err(v) => {
+                    debug!(
+                        "Generation attempt {} with seed {} violated {:?}",
+                        attempt, seed, v
+                    );
+                    violated = v;
+                }
+            }
+        }
+
+        Err(GenerationFailed {
+            attempts: self.max_attempts,
+            violated,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::math::UVec2;
+
+    use crate::{algorithm::dungeon::MazeGenerator, tilemap::tile::TileLayer};
+
+    use super::*;
+
+    fn tile(texture_index: i32) -> TileBuilder {
+        TileBuilder::new().with_layer(0, TileLayer::no_flip(texture_index))
+    }
+
+    #[test]
+    fn test_generation_guarantees() {
+        let maze = |seed: u64| {
+            MazeGenerator::new(UVec2::new(6, 4))
+                .with_seed(seed)
+                .generate()
+                .to_pattern(None, &tile(0), Some(&tile(1)))
+        };
+        let exit = IVec2::new(11, 7);
+
+        let guarantees = GenerationGuarantees::new()
+            .with_constraint(ConnectedConstraint::new(IVec2::ONE, exit))
+            .with_constraint(MinTileCountConstraint::new(2, 3, tile(2)))
+            .with_constraint(MinCorridorWidthConstraint::new(tile(0)))
+            .with_max_repairs(32)
+            .with_seed(0);
+
+        let mut pattern = maze(0);
+        assert_eq!(guarantees.violations(&pattern), vec![1, 2]);
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(guarantees.repair(&mut pattern, &mut rng), Ok(()));
+        assert!(guarantees.violations(&pattern).is_empty());
+        assert_eq!(pattern.tiles.aabb.max, IVec2::new(12, 8));
+
+        // Walls around the exit can't be repaired without carving.
+        let walled = |seed: u64| {
+            let mut pattern = maze(seed);
+            pattern.path_tiles.remove(exit - IVec2::X);
+            pattern.path_tiles.remove(exit - IVec2::Y);
+            pattern
+        };
+        let guarantees = GenerationGuarantees::new()
+            .with_constraint(ConnectedConstraint::new(IVec2::ONE, exit))
+            .with_max_attempts(3)
+            .with_seed(0);
+        assert_eq!(
+            guarantees.generate(walled).err(),
+            Some(GenerationFailed {
+                attempts: 3,
+                violated: vec![0],
+            })
+        );
+
+        let carving = GenerationGuarantees::new()
+            .with_constraint(ConnectedConstraint::new(IVec2::ONE, exit).with_carve(tile(0)))
+            .with_seed(0);
+        let (pattern, _) = carving.generate(walled).unwrap();
+        assert!(is_walkable(&pattern, exit - IVec2::Y));
+    }
+}
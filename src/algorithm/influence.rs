@@ -0,0 +1,290 @@
+use bevy::{
+    ecs::{
+        change_detection::{DetectChanges, DetectChangesMut, Ref},
+        component::Component,
+        entity::{Entity, EntityHashMap},
+        system::{Commands, Query},
+    },
+    math::{IVec2, Vec2},
+    reflect::Reflect,
+    render::color::Color,
+    utils::{HashMap, HashSet},
+};
+
+use crate::{
+    math::{extension::TileIndex, TileArea},
+    tilemap::{
+        buffers::PureColorTileBuffer,
+        chunking::storage::ChunkedStorage,
+        coordinates,
+        map::{TilemapAxisFlip, TilemapStorage, TilemapTransform, TilemapType},
+        sensor::TilePosition,
+    },
+};
+
+/// A float value per tile for AI, like the threat from enemies or the attraction of resources.
+///
+/// Insert this on the tilemap. Every `FixedUpdate`, the `InfluenceSource`s write their
+/// strengths, then the values spread to the neighbours and decay.
+#[derive(Component, Debug, Clone, Reflect)]
+pub struct TilemapInfluence {
+    pub(crate) storage: ChunkedStorage<f32>,
+    /// How much of the difference to the average of the neighbours is taken each step.
+    /// `0` means no spreading and `1` means taking the average.
+    pub diffusion: f32,
+    /// The values are multiplied by this each step.
+    pub decay: f32,
+    /// Values with an absolute value below this are removed.
+    pub threshold: f32,
+}
+
+impl TilemapInfluence {
+    pub fn new(diffusion: f32, decay: f32) -> Self {
+        Self {
+            storage: ChunkedStorage::default(),
+            diffusion,
+            decay,
+            threshold: 0.01,
+        }
+    }
+
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Get the influence of a tile. Tiles without influence are `0`.
+    #[inline]
+    pub fn get(&self, index: IVec2) -> f32 {
+        self.storage.get_elem(index).cloned().unwrap_or_default()
+    }
+
+    /// Get the influence at a world position.
+    #[inline]
+    pub fn get_world(
+        &self,
+        world: Vec2,
+        ty: TilemapType,
+        transform: &TilemapTransform,
+        pivot: Vec2,
+        slot_size: Vec2,
+        axis_flip: TilemapAxisFlip,
+    ) -> f32 {
+        self.get(coordinates::world_to_index_flipped(
+            world, ty, transform, pivot, slot_size, axis_flip,
+        ))
+    }
+
+    #[inline]
+    pub fn set(&mut self, index: IVec2, value: f32) {
+        self.storage.set_elem(index, value);
+    }
+
+    #[inline]
+    pub fn add(&mut self, index: IVec2, value: f32) {
+        self.set(index, self.get(index) + value);
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.storage.clear();
+    }
+
+    /// Iterate over the tiles with influence.
+    pub fn iter(&self) -> impl Iterator<Item = (IVec2, f32)> + '_ {
+        self.storage
+            .chunked_iter_some()
+            .map(|(chunk_index, in_chunk_index, value)| {
+                (
+                    self.storage
+                        .inverse_transform_index(chunk_index, in_chunk_index),
+                    *value,
+                )
+            })
+    }
+
+    /// The neighbour with the highest influence, which is the way to go
+    /// for an AI attracted by the influence. Use negative values to flee.
+    pub fn highest_neighbour(
+        &self,
+        index: IVec2,
+        ty: TilemapType,
+        allow_diagonal: bool,
+    ) -> Option<(IVec2, f32)> {
+        index
+            .neighbours(ty, allow_diagonal)
+            .into_iter()
+            .flatten()
+            .map(|nei| (nei, self.get(nei)))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+    }
+
+    /// Spread and decay the values once.
+    pub fn step(&mut self, ty: TilemapType) {
+        let values = self.iter().collect::<HashMap<_, _>>();
+        let affected = values
+            .keys()
+            .flat_map(|index| index.neighbours(ty, false).into_iter().flatten())
+            .chain(values.keys().copied())
+            .collect::<HashSet<_>>();
+
+        self.storage.clear();
+        for index in affected {
+            let value = values.get(&index).copied().unwrap_or_default();
+            let neighbours = index.neighbours(ty, false);
+            let average = neighbours
+                .iter()
+                .flatten()
+                .map(|nei| values.get(nei).copied().unwrap_or_default())
+                .sum::<f32>()
+                / neighbours.len() as f32;
+
+            let new = (value + (average - value) * self.diffusion) * self.decay;
+            if new.abs() >= self.threshold {
+                self.storage.set_elem(index, new);
+            }
+        }
+    }
+}
+
+/// Keeps the tile at the `TilePosition` of this entity at `strength`.
+#[derive(Component, Debug, Clone, Copy, Reflect)]
+pub struct InfluenceSource {
+    pub strength: f32,
+}
+
+/// Visualize the `TilemapInfluence` on this tilemap using another pure color tilemap.
+#[derive(Component, Debug, Clone, Reflect)]
+pub struct InfluenceHeatmap {
+    /// The pure color tilemap to draw on.
+    pub target: Entity,
+    /// The color of `-max`.
+    pub negative: Color,
+    /// The color of `max`.
+    pub positive: Color,
+    pub max: f32,
+    pub(crate) drawn: HashSet<IVec2>,
+}
+
+impl InfluenceHeatmap {
+    pub fn new(target: Entity, max: f32) -> Self {
+        Self {
+            target,
+            negative: Color::rgba(0., 0., 1., 0.5),
+            positive: Color::rgba(1., 0., 0., 0.5),
+            max,
+            drawn: HashSet::new(),
+        }
+    }
+
+    pub fn with_colors(mut self, negative: Color, positive: Color) -> Self {
+        self.negative = negative;
+        self.positive = positive;
+        self
+    }
+
+    pub fn color_of(&self, value: f32) -> Color {
+        let t = (value / self.max).clamp(-1., 1.);
+        let color = if t < 0. { self.negative } else { self.positive };
+        color.with_a(color.a() * t.abs())
+    }
+}
+
+pub fn influence_updater(
+    mut tilemaps_query: Query<(Entity, &TilemapType, &mut TilemapInfluence)>,
+    sources_query: Query<(&InfluenceSource, &TilePosition)>,
+) {
+    let mut sources = EntityHashMap::<Vec<_>>::default();
+    sources_query.iter().for_each(|(source, position)| {
+        sources
+            .entry(position.tilemap)
+            .or_default()
+            .push((position.index, source.strength));
+    });
+
+    tilemaps_query
+        .iter_mut()
+        .for_each(|(entity, ty, mut influence)| {
+            if let Some(sources) = sources.get(&entity) {
+                sources
+                    .iter()
+                    .for_each(|(index, strength)| influence.set(*index, *strength));
+            }
+            influence.step(*ty);
+        });
+}
+
+pub fn influence_heatmap_drawer(
+    mut commands: Commands,
+    mut heatmaps_query: Query<(Ref<TilemapInfluence>, &mut InfluenceHeatmap)>,
+    mut storages_query: Query<&mut TilemapStorage>,
+) {
+    heatmaps_query
+        .iter_mut()
+        .for_each(|(influence, mut heatmap)| {
+            if !influence.is_changed() && !heatmap.is_changed() {
+                return;
+            }
+            let heatmap = heatmap.bypass_change_detection();
+            let Ok(mut storage) = storages_query.get_mut(heatmap.target) else {
+                return;
+            };
+
+            let values = influence.iter().collect::<Vec<_>>();
+            let Some((min, max)) = values
+                .iter()
+                .map(|(index, _)| (*index, *index))
+                .reduce(|(min, max), (index, _)| (min.min(index), max.max(index)))
+            else {
+                heatmap
+                    .drawn
+                    .drain()
+                    .for_each(|index| storage.remove(&mut commands, index));
+                return;
+            };
+
+            let area = TileArea::from_min_max(min, max);
+            let mut buffer = PureColorTileBuffer::new(area.origin, area.extent);
+            let mut drawn = HashSet::with_capacity(values.len());
+            values.into_iter().for_each(|(index, value)| {
+                buffer.set(index, heatmap.color_of(value));
+                drawn.insert(index);
+            });
+
+            heatmap
+                .drawn
+                .iter()
+                .filter(|index| !drawn.contains(*index))
+                .for_each(|index| storage.remove(&mut commands, *index));
+            storage.set_colors(&mut commands, &buffer);
+            heatmap.drawn = drawn;
+        });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_influence_step() {
+        let mut influence = TilemapInfluence::new(0.5, 0.9);
+        influence.set(IVec2::ZERO, 1.);
+        influence.step(TilemapType::Square);
+
+        // (1 + (0 - 1) * 0.5) * 0.9
+        assert_eq!(influence.get(IVec2::ZERO), 0.45);
+        // (0 + (0.25 - 0) * 0.5) * 0.9
+        assert_eq!(influence.get(IVec2::X), 0.1125);
+        assert_eq!(influence.get(IVec2::ONE), 0.);
+        assert_eq!(influence.iter().count(), 5);
+        assert_eq!(
+            influence.highest_neighbour(IVec2::X, TilemapType::Square, false),
+            Some((IVec2::ZERO, 0.45))
+        );
+
+        for _ in 0..100 {
+            influence.step(TilemapType::Square);
+        }
+        assert_eq!(influence.iter().count(), 0);
+    }
+}
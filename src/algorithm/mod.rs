@@ -1,25 +1,69 @@
-use bevy::prelude::{Plugin, Update};
+use bevy::prelude::{FixedUpdate, Plugin, Update};
 
 use self::{
-    pathfinding::{Path, PathTilemaps},
+    avoidance::{PathFollower, TileReservations},
+    cellular::{CellularAutomataRunner, CellularData},
+    connection::{TilemapConnections, WorldPath, WorldPathFinder},
+    constraint::TilemapConstraints,
+    influence::{InfluenceHeatmap, InfluenceSource, TilemapInfluence},
+    pathfinding::{Path, PathCache, PathNotFound, PathTilemaps},
+    rules::{TilemapPostProcessed, TilemapPostProcessor},
     wfc::{WfcData, WfcElement, WfcHistory, WfcSource},
 };
 
+pub mod avoidance;
+pub mod cancel;
+pub mod cellular;
+pub mod connection;
+pub mod constraint;
+pub mod dungeon;
+pub mod fov;
+pub mod guarantee;
+pub mod influence;
 pub mod pathfinding;
+pub mod rules;
+pub mod scatter;
+pub mod surface;
 pub mod wfc;
 
 pub struct EntiTilesAlgorithmPlugin;
 
 impl Plugin for EntiTilesAlgorithmPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.register_type::<Path>();
+        app.register_type::<Path>().register_type::<PathNotFound>();
+
+        app.register_type::<PathFollower>()
+            .register_type::<TileReservations>();
+
+        app.register_type::<WorldPathFinder>()
+            .register_type::<WorldPath>()
+            .register_type::<TilemapConnections>();
+
+        app.register_type::<TilemapConstraints>();
+
+        app.register_type::<CellularAutomataRunner>()
+            .register_type::<CellularData>();
+
+        app.register_type::<TilemapInfluence>()
+            .register_type::<InfluenceSource>()
+            .register_type::<InfluenceHeatmap>();
+
+        app.register_type::<TilemapPostProcessor>()
+            .register_type::<TilemapPostProcessed>();
+
+        app.add_event::<TilemapPostProcessed>();
 
         app.register_type::<WfcElement>()
             .register_type::<WfcHistory>()
             .register_type::<WfcData>()
             .register_type::<WfcSource>();
 
-        app.init_resource::<PathTilemaps>();
+        app.init_resource::<PathTilemaps>()
+            .init_resource::<PathCache>()
+            .init_resource::<TilemapConnections>()
+            .init_resource::<TileReservations>();
+
+        app.add_systems(FixedUpdate, influence::influence_updater);
 
         app.add_systems(
             Update,
@@ -29,6 +73,8 @@ impl Plugin for EntiTilesAlgorithmPlugin {
                 pathfinding::path_assigner,
                 #[cfg(not(feature = "multi-threaded"))]
                 pathfinding::path_finding_single_threaded,
+                connection::world_path_finder,
+                avoidance::path_follower_updater,
                 #[cfg(feature = "multi-threaded")]
                 wfc::wave_function_collapse,
                 #[cfg(feature = "multi-threaded")]
@@ -38,6 +84,15 @@ impl Plugin for EntiTilesAlgorithmPlugin {
                 wfc::wfc_applier,
                 #[cfg(feature = "ldtk")]
                 wfc::ldtk_wfc_helper,
+                #[cfg(feature = "multi-threaded")]
+                cellular::cellular_automata,
+                #[cfg(feature = "multi-threaded")]
+                cellular::cellular_data_assigner,
+                #[cfg(not(feature = "multi-threaded"))]
+                cellular::cellular_automata_single_threaded,
+                cellular::cellular_applier,
+                rules::tilemap_post_processor,
+                influence::influence_heatmap_drawer,
             ),
         );
     }
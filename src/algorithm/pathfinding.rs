@@ -3,7 +3,7 @@ use std::{cmp::Ordering, collections::BinaryHeap};
 use bevy::{
     ecs::{
         entity::EntityHashMap,
-        system::{Commands, Query, Res, Resource},
+        system::{Commands, Query, Res, ResMut, Resource},
     },
     math::IVec2,
     prelude::{Component, Entity},
@@ -13,9 +13,14 @@ use bevy::{
 
 use crate::{
     math::extension::{ManhattanDistance, TileIndex},
-    tilemap::{algorithm::path::PathTilemap, map::TilemapType},
+    tilemap::{
+        algorithm::path::{PathCapabilities, PathTilemap},
+        map::TilemapType,
+    },
 };
 
+use super::cancel::CancelToken;
+
 #[cfg(feature = "multi-threaded")]
 use bevy::tasks::{AsyncComputeTaskPool, Task};
 #[cfg(feature = "multi-threaded")]
@@ -41,6 +46,11 @@ impl PathTilemaps {
         self.tilemaps.get(&tilemap).map(|t| t.lock().unwrap())
     }
 
+    #[inline]
+    pub fn revision(&self, tilemap: Entity) -> Option<u32> {
+        self.lock(tilemap).map(|t| t.revision())
+    }
+
     #[inline]
     pub fn get_mut(&mut self, tilemap: Entity) -> Option<&mut Arc<Mutex<PathTilemap>>> {
         self.tilemaps.get_mut(&tilemap)
@@ -56,6 +66,14 @@ impl PathTilemaps {
     pub fn remove(&mut self, tilemap: Entity) {
         self.tilemaps.remove(&tilemap);
     }
+
+    /// Edit the path tilemap if it exists.
+    #[inline]
+    pub fn edit(&mut self, tilemap: Entity, f: impl FnOnce(&mut PathTilemap)) {
+        if let Some(mut path_tilemap) = self.lock(tilemap) {
+            f(&mut path_tilemap);
+        }
+    }
 }
 
 #[cfg(not(feature = "multi-threaded"))]
@@ -70,6 +88,11 @@ impl PathTilemaps {
         self.tilemaps.get_mut(&tilemap)
     }
 
+    #[inline]
+    pub fn revision(&self, tilemap: Entity) -> Option<u32> {
+        self.get(tilemap).map(|t| t.revision())
+    }
+
     #[inline]
     pub fn insert(&mut self, tilemap: Entity, path_tilemap: PathTilemap) {
         self.tilemaps.insert(tilemap, path_tilemap);
@@ -79,6 +102,101 @@ impl PathTilemaps {
     pub fn remove(&mut self, tilemap: Entity) {
         self.tilemaps.remove(&tilemap);
     }
+
+    /// Edit the path tilemap if it exists.
+    #[inline]
+    pub fn edit(&mut self, tilemap: Entity, f: impl FnOnce(&mut PathTilemap)) {
+        if let Some(path_tilemap) = self.get_mut(tilemap) {
+            f(path_tilemap);
+        }
+    }
+}
+
+/// Caches the paths found, so later requests between the same regions can reuse them.
+///
+/// Requests are also batched in the same way, so requests between the same regions
+/// scheduled in the same frame are only computed once.
+/// Cached paths are invalidated once the `PathTilemap` is modified.
+#[derive(Resource)]
+pub struct PathCache {
+    /// The size of the regions in tiles. With the default size of 1, only requests
+    /// with the same origin and destination share the path.
+    ///
+    /// For larger regions, the shared path starts and ends at the origin and destination
+    /// of the first request, so it's only approximate for the others.
+    pub region_size: u32,
+    /// The maximum number of cached paths. Set to 0 to disable caching.
+    pub capacity: usize,
+    pub(crate) paths: HashMap<PathCacheKey, (Path, u32)>,
+}
+
+impl Default for PathCache {
+    fn default() -> Self {
+        Self {
+            region_size: 1,
+            capacity: 1024,
+            paths: HashMap::new(),
+        }
+    }
+}
+
+impl PathCache {
+    pub(crate) fn key(&self, tilemap: Entity, finder: &PathFinder) -> PathCacheKey {
+        let region_size = self.region_size.max(1) as i32;
+        PathCacheKey {
+            tilemap,
+            origin: finder.origin.div_euclid(IVec2::splat(region_size)),
+            dest: finder.dest.div_euclid(IVec2::splat(region_size)),
+            allow_diagonal: finder.allow_diagonal,
+            capabilities: finder.capabilities,
+        }
+    }
+
+    /// Get the cached path if it's still valid.
+    pub(crate) fn get(&self, key: &PathCacheKey, revision: u32) -> Option<&Path> {
+        self.paths
+            .get(key)
+            .filter(|(_, r)| *r == revision)
+            .map(|(path, _)| path)
+    }
+
+    pub(crate) fn insert(&mut self, key: PathCacheKey, path: Path, revision: u32) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.paths.len() >= self.capacity && !self.paths.contains_key(&key) {
+            if let Some(evicted) = self.paths.keys().next().copied() {
+                self.paths.remove(&evicted);
+            }
+        }
+        self.paths.insert(key, (path, revision));
+    }
+
+    /// Remove all the cached paths of the tilemap.
+    pub fn invalidate(&mut self, tilemap: Entity) {
+        self.paths.retain(|key, _| key.tilemap != tilemap);
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.paths.clear();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct PathCacheKey {
+    tilemap: Entity,
+    origin: IVec2,
+    dest: IVec2,
+    allow_diagonal: bool,
+    capabilities: PathCapabilities,
+}
+
+/// The requests sharing the path of the request computed.
+pub(crate) struct PathBatch {
+    key: PathCacheKey,
+    revision: u32,
+    followers: Vec<Entity>,
 }
 
 #[derive(Component, Reflect)]
@@ -86,24 +204,77 @@ pub struct PathFinder {
     pub origin: IVec2,
     pub dest: IVec2,
     pub allow_diagonal: bool,
+    /// Only the tiles that can be passed with any of these capabilities are used.
+    #[reflect(ignore)]
+    pub capabilities: PathCapabilities,
+    /// Give up and insert `PathNotFound` after this many steps.
     pub max_steps: Option<u32>,
-    #[cfg(not(feature = "multi-threaded"))]
+    pub algorithm: PathAlgorithm,
+    /// Search from both ends at the same time. Ignored by `PathAlgorithm::JumpPoint`.
+    pub bidirectional: bool,
+    /// The maximum steps to search in one frame.
+    ///
+    /// In multi-threaded mode, the task yields to other tasks of the pool after
+    /// this many steps instead, so long paths don't occupy a thread.
     pub max_steps_per_frame: u32,
+    /// Stop the search early once cancelled. Cancelled requests are not batched
+    /// with others and get no result.
+    #[reflect(ignore)]
+    pub cancel: Option<CancelToken>,
     #[cfg(not(feature = "multi-threaded"))]
     pub tilemap_ty: TilemapType,
 }
 
+impl PathFinder {
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(CancelToken::is_cancelled)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum PathAlgorithm {
+    #[default]
+    AStar,
+    /// Jump point search. Much faster on large open maps, but only works on square tilemaps
+    /// with diagonal moves allowed and the same cost on every tile. Falls back to `AStar`
+    /// otherwise.
+    ///
+    /// Diagonal moves are treated as longer than straight ones, so the path may have
+    /// more steps than the one found by `AStar`.
+    JumpPoint,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum PathFailure {
+    /// There's no path to the destination.
+    Unreachable,
+    /// The search exceeded `PathFinder::max_steps`.
+    TooManySteps,
+    /// The `PathFinder::cancel` token was cancelled.
+    Cancelled,
+}
+
+/// Inserted on the requester instead of `Path` when the pathfinding failed.
+#[derive(Component, Debug, Clone, Copy, Reflect)]
+pub struct PathNotFound {
+    pub tilemap: Entity,
+    pub reason: PathFailure,
+}
+
 #[derive(Component)]
 pub struct PathFindingQueue {
     pub(crate) finders: EntityHashMap<PathFinder>,
+    pub(crate) batches: EntityHashMap<PathBatch>,
     #[cfg(feature = "multi-threaded")]
-    pub(crate) tasks: EntityHashMap<Task<Path>>,
+    pub(crate) tasks: EntityHashMap<Task<Result<Path, PathFailure>>>,
 }
 
 impl PathFindingQueue {
     pub fn new_with_schedules(schedules: impl Iterator<Item = (Entity, PathFinder)>) -> Self {
         PathFindingQueue {
             finders: schedules.collect(),
+            batches: EntityHashMap::default(),
             #[cfg(feature = "multi-threaded")]
             tasks: EntityHashMap::default(),
         }
@@ -121,11 +292,11 @@ impl PathFindingQueue {
     }
 }
 
-#[derive(Component, Clone, Reflect)]
+#[derive(Component, Debug, Clone, Reflect)]
 pub struct Path {
-    path: Vec<IVec2>,
-    current_step: usize,
-    tilemap: Entity,
+    pub(crate) path: Vec<IVec2>,
+    pub(crate) current_step: usize,
+    pub(crate) tilemap: Entity,
 }
 
 impl Path {
@@ -202,20 +373,29 @@ pub struct PathGrid {
     pub requester: Entity,
     pub tilemap: Entity,
     pub allow_diagonal: bool,
+    pub capabilities: PathCapabilities,
+    pub algorithm: PathAlgorithm,
+    pub bidirectional: bool,
     pub tilemap_ty: TilemapType,
     pub origin: IVec2,
     pub dest: IVec2,
     pub to_explore: BinaryHeap<PathNode>,
     pub explored: HashSet<IVec2>,
     pub all_nodes: HashMap<IVec2, PathNode>,
+    /// The nodes explored from the destination in bidirectional search.
+    /// Their `g_cost` is the cost from the node to the destination.
+    pub backward_to_explore: BinaryHeap<PathNode>,
+    pub backward_nodes: HashMap<IVec2, PathNode>,
+    /// The best node where the two searches met, and the total cost through it.
+    pub meeting: Option<(IVec2, u32)>,
     pub steps: u32,
     pub max_steps: Option<u32>,
     #[cfg(feature = "multi-threaded")]
     pub path_tilemap: Arc<Mutex<PathTilemap>>,
-    #[cfg(not(feature = "multi-threaded"))]
     pub max_steps_per_frame: u32,
-    #[cfg(not(feature = "multi-threaded"))]
+    pub cancel: Option<CancelToken>,
     pub is_done: bool,
+    pub failure: Option<PathFailure>,
 }
 
 impl PathGrid {
@@ -230,20 +410,26 @@ impl PathGrid {
             requester,
             tilemap,
             allow_diagonal: finder.allow_diagonal,
+            capabilities: finder.capabilities,
+            algorithm: finder.algorithm,
+            bidirectional: finder.bidirectional,
             tilemap_ty,
             origin: finder.origin,
             dest: finder.dest,
             to_explore: BinaryHeap::new(),
             explored: HashSet::new(),
             all_nodes: HashMap::new(),
+            backward_to_explore: BinaryHeap::new(),
+            backward_nodes: HashMap::new(),
+            meeting: None,
             steps: 0,
             max_steps: finder.max_steps,
             #[cfg(feature = "multi-threaded")]
             path_tilemap,
-            #[cfg(not(feature = "multi-threaded"))]
             max_steps_per_frame: finder.max_steps_per_frame,
-            #[cfg(not(feature = "multi-threaded"))]
+            cancel: finder.cancel,
             is_done: false,
+            failure: None,
         }
     }
 
@@ -252,11 +438,15 @@ impl PathGrid {
         if let Some(node) = self.all_nodes.get(&index) {
             Some(node.clone())
         } else {
-            self.path_tilemap.lock().unwrap().get(index).map(|tile| {
-                let new = PathNode::new(index, u32::MAX, self.dest, tile.cost);
-                self.all_nodes.insert(index, new);
-                new
-            })
+            self.path_tilemap
+                .lock()
+                .unwrap()
+                .get_passable(index, self.capabilities)
+                .map(|tile| {
+                    let new = PathNode::new(index, u32::MAX, self.dest, tile.cost);
+                    self.all_nodes.insert(index, new);
+                    new
+                })
         }
     }
 
@@ -272,7 +462,7 @@ impl PathGrid {
             path_tilemaps
                 .get(self.tilemap)
                 .unwrap()
-                .get(index)
+                .get_passable(index, self.capabilities)
                 .map(|tile| {
                     let new = PathNode::new(index, u32::MAX, self.dest, tile.cost);
                     self.all_nodes.insert(index, new);
@@ -283,82 +473,293 @@ impl PathGrid {
 
     #[cfg(feature = "multi-threaded")]
     pub fn neighbours(&mut self, index: IVec2) -> Vec<PathNode> {
-        index
-            .neighbours(self.tilemap_ty, self.allow_diagonal)
+        self.unblocked_neighbours(index, None)
             .into_iter()
-            .filter_map(|p| p.and_then(|p| self.get_or_register(p)))
+            .filter_map(|p| self.get_or_register(p))
             .collect()
     }
 
     #[cfg(not(feature = "multi-threaded"))]
     pub fn neighbours(&mut self, index: IVec2, path_tilemaps: &PathTilemaps) -> Vec<PathNode> {
-        index
-            .neighbours(self.tilemap_ty, self.allow_diagonal)
+        self.unblocked_neighbours(index, Some(path_tilemaps))
             .into_iter()
-            .filter_map(|p| p.and_then(|p| self.get_or_register(p, path_tilemaps)))
+            .filter_map(|p| self.get_or_register(p, path_tilemaps))
             .collect()
     }
 
-    #[allow(unused)]
+    /// The neighbours of the tile that are not behind a wall.
+    fn unblocked_neighbours(
+        &self,
+        index: IVec2,
+        path_tilemaps: Option<&PathTilemaps>,
+    ) -> Vec<IVec2> {
+        let neighbours = index.neighbours(self.tilemap_ty, self.allow_diagonal);
+        self.with_path_tilemap(path_tilemaps, |path_tilemap| {
+            neighbours
+                .into_iter()
+                .flatten()
+                .filter(|n| !path_tilemap.is_blocked(index, *n))
+                .collect()
+        })
+    }
+
+    #[cfg(feature = "multi-threaded")]
+    fn with_path_tilemap<R>(
+        &self,
+        _path_tilemaps: Option<&PathTilemaps>,
+        f: impl FnOnce(&PathTilemap) -> R,
+    ) -> R {
+        f(&self.path_tilemap.lock().unwrap())
+    }
+
+    #[cfg(not(feature = "multi-threaded"))]
+    fn with_path_tilemap<R>(
+        &self,
+        path_tilemaps: Option<&PathTilemaps>,
+        f: impl FnOnce(&PathTilemap) -> R,
+    ) -> R {
+        f(path_tilemaps.unwrap().get(self.tilemap).unwrap())
+    }
+
+    /// Search for at most `max_steps_per_frame` steps. `is_done` is set when the search
+    /// is finished, and `failure` is set if no path is found.
     pub fn find_path(&mut self, path_tilemaps: Option<&PathTilemaps>) {
-        let origin = PathNode::new(self.origin, 0, self.dest, 0);
-        self.to_explore.push(origin.clone());
-        self.all_nodes.insert(self.origin, origin);
+        if self.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+            self.fail(PathFailure::Cancelled);
+            return;
+        }
+        if self.all_nodes.is_empty() {
+            self.init(path_tilemaps);
+        }
 
-        #[cfg(not(feature = "multi-threaded"))]
         let mut steps_cur_frame = 0;
+        while !self.is_done {
+            if steps_cur_frame >= self.max_steps_per_frame {
+                return;
+            }
+            steps_cur_frame += 1;
 
-        while !self.to_explore.is_empty() {
             if let Some(max_steps) = self.max_steps {
                 if self.steps > max_steps {
+                    self.fail(PathFailure::TooManySteps);
                     return;
                 }
             }
             self.steps += 1;
 
-            #[cfg(not(feature = "multi-threaded"))]
-            {
-                if steps_cur_frame >= self.max_steps_per_frame {
-                    return;
-                }
-                steps_cur_frame += 1;
+            if self.algorithm == PathAlgorithm::JumpPoint {
+                self.jump_point_step(path_tilemaps);
+            } else if self.bidirectional {
+                self.bidirectional_step(path_tilemaps);
+            } else {
+                self.forward_step(path_tilemaps);
             }
+        }
+    }
 
-            let current = self.to_explore.pop().unwrap();
-            if current.index == self.dest {
-                break;
+    fn init(&mut self, path_tilemaps: Option<&PathTilemaps>) {
+        if self.algorithm == PathAlgorithm::JumpPoint
+            && !(self.tilemap_ty == TilemapType::Square
+                && self.allow_diagonal
+                && self.with_path_tilemap(path_tilemaps, |t| t.is_uniform() && !t.has_walls()))
+        {
+            self.algorithm = PathAlgorithm::AStar;
+        }
+
+        let mut origin = PathNode::new(self.origin, 0, self.dest, 0);
+        if self.algorithm == PathAlgorithm::JumpPoint {
+            origin.h_cost = octile_distance(self.origin, self.dest);
+        }
+        self.to_explore.push(origin);
+        self.all_nodes.insert(self.origin, origin);
+
+        if self.bidirectional && self.algorithm == PathAlgorithm::AStar {
+            let capabilities = self.capabilities;
+            let Some(cost) = self.with_path_tilemap(path_tilemaps, |t| {
+                t.get_passable(self.dest, capabilities).copied()
+            }) else {
+                self.fail(PathFailure::Unreachable);
+                return;
+            };
+            let dest = PathNode::new(self.dest, 0, self.origin, cost.cost);
+            self.backward_to_explore.push(dest);
+            self.backward_nodes.insert(self.dest, dest);
+        }
+    }
+
+    #[inline]
+    fn fail(&mut self, failure: PathFailure) {
+        self.failure = Some(failure);
+        self.is_done = true;
+    }
+
+    #[inline]
+    fn update_meeting(&mut self, index: IVec2, cost: u32) {
+        if let Some((_, best)) = self.meeting {
+            if best <= cost {
+                return;
             }
-            if current.g_cost > self.all_nodes[&current.index].g_cost {
-                continue;
+        }
+        self.meeting = Some((index, cost));
+    }
+
+    /// Pop the next node to expand from the origin side, or finish the search
+    /// if there's nothing left or the destination is reached.
+    fn next_forward(&mut self) -> Option<PathNode> {
+        let Some(current) = self.to_explore.pop() else {
+            self.fail(PathFailure::Unreachable);
+            return None;
+        };
+        if current.index == self.dest {
+            self.is_done = true;
+            return None;
+        }
+        (current.g_cost <= self.all_nodes[&current.index].g_cost).then_some(current)
+    }
+
+    fn relax_forward(&mut self, node: PathNode) {
+        match self.all_nodes.entry(node.index) {
+            Entry::Occupied(mut e) => {
+                if e.get().g_cost <= node.g_cost {
+                    return;
+                }
+                e.insert(node);
+            }
+            Entry::Vacant(e) => {
+                e.insert(node);
             }
+        };
+        self.to_explore.push(node);
 
-            #[cfg(feature = "multi-threaded")]
-            let neighbours = self.neighbours(current.index);
-            #[cfg(not(feature = "multi-threaded"))]
-            let neighbours = self.neighbours(current.index, path_tilemaps.unwrap());
-
-            for mut neighbour in neighbours {
-                neighbour.g_cost = current.g_cost + neighbour.cost_to_pass;
-                neighbour.parent = Some(current.index);
-
-                match self.all_nodes.entry(neighbour.index) {
-                    Entry::Occupied(mut e) => {
-                        if e.get().g_cost > neighbour.g_cost {
-                            e.insert(neighbour.clone());
-                            self.to_explore.push(neighbour);
-                        }
-                    }
-                    Entry::Vacant(e) => {
-                        e.insert(neighbour.clone());
-                        self.to_explore.push(neighbour);
+        if let Some(backward) = self.backward_nodes.get(&node.index) {
+            self.update_meeting(node.index, node.g_cost + backward.g_cost);
+        }
+    }
+
+    #[allow(unused)]
+    fn forward_step(&mut self, path_tilemaps: Option<&PathTilemaps>) {
+        let Some(current) = self.next_forward() else {
+            return;
+        };
+
+        #[cfg(feature = "multi-threaded")]
+        let neighbours = self.neighbours(current.index);
+        #[cfg(not(feature = "multi-threaded"))]
+        let neighbours = self.neighbours(current.index, path_tilemaps.unwrap());
+
+        for mut neighbour in neighbours {
+            neighbour.g_cost = current.g_cost + neighbour.cost_to_pass;
+            neighbour.parent = Some(current.index);
+            self.relax_forward(neighbour);
+        }
+    }
+
+    fn backward_step(&mut self, path_tilemaps: Option<&PathTilemaps>) {
+        let current = self.backward_to_explore.pop().unwrap();
+        if current.g_cost > self.backward_nodes[&current.index].g_cost {
+            return;
+        }
+
+        // The tiles that have `current` as their neighbour.
+        let offsets = IVec2::ZERO.neighbours(self.tilemap_ty, self.allow_diagonal);
+        let predecessors = self.with_path_tilemap(path_tilemaps, |path_tilemap| {
+            offsets
+                .into_iter()
+                .flatten()
+                .map(|offset| current.index - offset)
+                .filter(|index| !path_tilemap.is_blocked(*index, current.index))
+                .filter_map(|index| {
+                    path_tilemap
+                        .get_passable(index, self.capabilities)
+                        .map(|tile| (index, tile.cost))
+                })
+                .collect::<Vec<_>>()
+        });
+
+        for (index, cost) in predecessors {
+            let node = PathNode {
+                index,
+                parent: Some(current.index),
+                g_cost: current.g_cost + current.cost_to_pass,
+                h_cost: self.origin.manhattan_distance(index),
+                cost_to_pass: cost,
+            };
+
+            match self.backward_nodes.entry(index) {
+                Entry::Occupied(mut e) => {
+                    if e.get().g_cost <= node.g_cost {
+                        continue;
                     }
-                };
+                    e.insert(node);
+                }
+                Entry::Vacant(e) => {
+                    e.insert(node);
+                }
+            };
+            self.backward_to_explore.push(node);
+
+            if let Some(forward) = self.all_nodes.get(&index).filter(|n| n.g_cost != u32::MAX) {
+                self.update_meeting(index, forward.g_cost + node.g_cost);
             }
         }
+    }
 
-        #[cfg(not(feature = "multi-threaded"))]
+    /// Expand the side with the lower cost, until no better path can be found
+    /// than the one through the meeting node.
+    fn bidirectional_step(&mut self, path_tilemaps: Option<&PathTilemaps>) {
+        let (Some(forward), Some(backward)) =
+            (self.to_explore.peek(), self.backward_to_explore.peek())
+        else {
+            match self.meeting {
+                Some(_) => self.is_done = true,
+                None => self.fail(PathFailure::Unreachable),
+            }
+            return;
+        };
+
+        let (forward, backward) = (forward.g_cost, backward.g_cost);
+        if self
+            .meeting
+            .is_some_and(|(_, cost)| forward + backward >= cost)
         {
             self.is_done = true;
+        } else if forward <= backward {
+            self.forward_step(path_tilemaps);
+        } else {
+            self.backward_step(path_tilemaps);
+        }
+    }
+
+    fn jump_point_step(&mut self, path_tilemaps: Option<&PathTilemaps>) {
+        let Some(current) = self.next_forward() else {
+            return;
+        };
+
+        let (dest, capabilities) = (self.dest, self.capabilities);
+        let jump_points = self.with_path_tilemap(path_tilemaps, |path_tilemap| {
+            jps_directions(current, path_tilemap, capabilities)
+                .into_iter()
+                .filter_map(|dir| jump(current.index, dir, dest, path_tilemap, capabilities))
+                .map(|jump_point| (jump_point, path_tilemap.get(jump_point).unwrap().cost))
+                .collect::<Vec<_>>()
+        });
+
+        for (jump_point, cost) in jump_points {
+            self.relax_forward(PathNode {
+                index: jump_point,
+                parent: Some(current.index),
+                g_cost: current.g_cost + octile_distance(current.index, jump_point),
+                h_cost: octile_distance(jump_point, dest),
+                cost_to_pass: cost,
+            });
+        }
+    }
+
+    /// Returns the path, or why it's not found.
+    pub fn result(&self) -> Result<Path, PathFailure> {
+        match self.failure {
+            Some(failure) => Err(failure),
+            None => Ok(self.collect_path()),
         }
     }
 
@@ -368,19 +769,177 @@ impl PathGrid {
             current_step: 0,
             tilemap: self.tilemap,
         };
-        let mut current = self.all_nodes.get(&self.dest).unwrap();
+
+        let mut current = match self.meeting {
+            Some((meeting, _)) => {
+                let mut backward = Vec::new();
+                let mut index = meeting;
+                while let Some(next) = self.backward_nodes[&index].parent {
+                    backward.push(next);
+                    index = next;
+                }
+                path.path.extend(backward.into_iter().rev());
+                self.all_nodes.get(&meeting).unwrap()
+            }
+            None => self.all_nodes.get(&self.dest).unwrap(),
+        };
         while current.index != self.origin {
-            path.path.push(current.index);
-            current = self.all_nodes.get(&current.parent.unwrap()).unwrap();
+            // Jump point search leaves straight or diagonal gaps between the nodes.
+            let parent = current.parent.unwrap();
+            let dir = (parent - current.index).signum();
+            let mut index = current.index;
+            while index != parent {
+                path.path.push(index);
+                index += dir;
+            }
+            current = self.all_nodes.get(&parent).unwrap();
         }
         path
     }
 }
 
+/// Straight moves cost 10 and diagonal moves cost 14.
+#[inline]
+fn octile_distance(a: IVec2, b: IVec2) -> u32 {
+    let d = (a - b).abs();
+    (d.max_element() * 10 + d.min_element() * 4) as u32
+}
+
+/// The directions to search from the node, with the neighbours that can be reached
+/// through the parent without passing the node pruned.
+fn jps_directions(
+    node: PathNode,
+    path_tilemap: &PathTilemap,
+    capabilities: PathCapabilities,
+) -> Vec<IVec2> {
+    let Some(parent) = node.parent else {
+        return [
+            IVec2::Y,
+            IVec2::X,
+            IVec2::NEG_X,
+            IVec2::NEG_Y,
+            IVec2::ONE,
+            IVec2::NEG_ONE,
+            IVec2::new(1, -1),
+            IVec2::new(-1, 1),
+        ]
+        .to_vec();
+    };
+
+    let blocked = |offset: IVec2| {
+        path_tilemap
+            .get_passable(node.index + offset, capabilities)
+            .is_none()
+    };
+    let dir = (node.index - parent).signum();
+    let mut dirs = vec![dir];
+    if dir.x == 0 || dir.y == 0 {
+        let side = dir.perp();
+        [side, -side]
+            .into_iter()
+            .filter(|side| blocked(*side))
+            .for_each(|side| dirs.push(dir + side));
+    } else {
+        dirs.extend([IVec2::new(dir.x, 0), IVec2::new(0, dir.y)]);
+        if blocked(IVec2::new(-dir.x, 0)) {
+            dirs.push(IVec2::new(-dir.x, dir.y));
+        }
+        if blocked(IVec2::new(0, -dir.y)) {
+            dirs.push(IVec2::new(dir.x, -dir.y));
+        }
+    }
+    dirs
+}
+
+/// Move from `index` towards `dir` until reaching a jump point.
+fn jump(
+    mut index: IVec2,
+    dir: IVec2,
+    dest: IVec2,
+    path_tilemap: &PathTilemap,
+    capabilities: PathCapabilities,
+) -> Option<IVec2> {
+    let walkable = |index: IVec2| path_tilemap.get_passable(index, capabilities).is_some();
+    loop {
+        index += dir;
+        if !walkable(index) {
+            return None;
+        }
+        if index == dest {
+            return Some(index);
+        }
+
+        if dir.x == 0 || dir.y == 0 {
+            // Check for forced neighbours.
+            let side = dir.perp();
+            if [side, -side]
+                .into_iter()
+                .any(|side| !walkable(index + side) && walkable(index + side + dir))
+            {
+                return Some(index);
+            }
+        } else {
+            let (x, y) = (IVec2::new(dir.x, 0), IVec2::new(0, dir.y));
+            if (!walkable(index - x) && walkable(index - x + y))
+                || (!walkable(index - y) && walkable(index - y + x))
+                || jump(index, x, dest, path_tilemap, capabilities).is_some()
+                || jump(index, y, dest, path_tilemap, capabilities).is_some()
+            {
+                return Some(index);
+            }
+        }
+    }
+}
+
+/// Reuse the cached paths, and batch the requests that share the same path.
+/// Returns the requests to compute.
+fn batch_requests(
+    commands: &mut Commands,
+    cache: &PathCache,
+    queue: &mut PathFindingQueue,
+    tilemap: Entity,
+    revision: u32,
+) -> Vec<(Entity, PathFinder)> {
+    let mut leaders = HashMap::new();
+    let mut requests = Vec::new();
+    queue.finders.drain().for_each(|(requester, finder)| {
+        let cancellable = finder.cancel.is_some();
+        if finder.is_cancelled() {
+            return;
+        }
+
+        let key = cache.key(tilemap, &finder);
+        if let Some(path) = cache.get(&key, revision) {
+            insert_result(commands, requester, tilemap, Ok(path.clone()));
+        } else if let Some(leader) = leaders.get(&key).filter(|_| !cancellable) {
+            if let Some(batch) = queue.batches.get_mut(leader) {
+                batch.followers.push(requester);
+            }
+        } else {
+            // Others can't share a search that may be cancelled.
+            if !cancellable {
+                leaders.insert(key, requester);
+            }
+            queue.batches.insert(
+                requester,
+                PathBatch {
+                    key,
+                    revision,
+                    followers: Vec::new(),
+                },
+            );
+            requests.push((requester, finder));
+        }
+    });
+    requests
+}
+
 #[cfg(feature = "multi-threaded")]
 pub fn pathfinding_scheduler(
+    mut commands: Commands,
     mut queues_query: Query<(Entity, &TilemapType, &mut PathFindingQueue)>,
     path_tilemaps: Res<PathTilemaps>,
+    cache: Res<PathCache>,
 ) {
     let thread_pool = AsyncComputeTaskPool::get();
     queues_query
@@ -388,13 +947,19 @@ pub fn pathfinding_scheduler(
         .for_each(|(tilemap, ty, mut queue)| {
             let mut tasks = Vec::new();
             let path_tilemap = path_tilemaps.get(tilemap).unwrap();
-            queue.finders.drain().for_each(|(requester, finder)| {
+            let revision = path_tilemap.lock().unwrap().revision();
+            let requests = batch_requests(&mut commands, &cache, &mut queue, tilemap, revision);
+            requests.into_iter().for_each(|(requester, finder)| {
                 let ty = *ty;
                 let path_tilemap = path_tilemap.clone();
                 let task = thread_pool.spawn(async move {
                     let mut grid = PathGrid::new(finder, requester, tilemap, ty, path_tilemap);
                     grid.find_path(None);
-                    grid.collect_path()
+                    while !grid.is_done {
+                        futures_lite::future::yield_now().await;
+                        grid.find_path(None);
+                    }
+                    grid.result()
                 });
                 tasks.push((requester, task));
             });
@@ -406,11 +971,15 @@ pub fn pathfinding_scheduler(
 pub fn pathfinding_scheduler(
     mut commands: Commands,
     mut queues_query: Query<(Entity, &TilemapType, &mut PathFindingQueue)>,
+    path_tilemaps: Res<PathTilemaps>,
+    cache: Res<PathCache>,
 ) {
     queues_query
         .iter_mut()
         .for_each(|(tilemap, ty, mut queue)| {
-            queue.finders.drain().for_each(|(requester, finder)| {
+            let revision = path_tilemaps.revision(tilemap).unwrap();
+            let requests = batch_requests(&mut commands, &cache, &mut queue, tilemap, revision);
+            requests.into_iter().for_each(|(requester, finder)| {
                 commands
                     .entity(requester)
                     .insert(PathGrid::new(finder, requester, tilemap, *ty));
@@ -418,11 +987,61 @@ pub fn pathfinding_scheduler(
         });
 }
 
+/// Assign the result to the requester and the batched requests, and cache the path.
+fn complete_batch(
+    commands: &mut Commands,
+    cache: &mut PathCache,
+    queue: &mut PathFindingQueue,
+    requester: Entity,
+    tilemap: Entity,
+    result: Result<Path, PathFailure>,
+) {
+    if result.as_ref().err() == Some(&PathFailure::Cancelled) {
+        queue.batches.remove(&requester);
+        return;
+    }
+
+    if let Some(batch) = queue.batches.remove(&requester) {
+        batch.followers.into_iter().for_each(|follower| {
+            insert_result(commands, follower, tilemap, result.clone());
+        });
+        if let Ok(path) = &result {
+            cache.insert(batch.key, path.clone(), batch.revision);
+        }
+    }
+    insert_result(commands, requester, tilemap, result);
+}
+
+fn insert_result(
+    commands: &mut Commands,
+    requester: Entity,
+    tilemap: Entity,
+    result: Result<Path, PathFailure>,
+) {
+    match result {
+        Err(PathFailure::Cancelled) => {}
+        Ok(path) => {
+            commands
+                .entity(requester)
+                .insert(path)
+                .remove::<PathNotFound>();
+        }
+        Err(reason) => {
+            commands
+                .entity(requester)
+                .insert(PathNotFound { tilemap, reason })
+                .remove::<Path>();
+        }
+    }
+}
+
 #[cfg(not(feature = "multi-threaded"))]
 pub fn path_finding_single_threaded(
     mut commands: Commands,
     mut tasks_query: Query<(Entity, &mut PathGrid)>,
+    mut queues_query: Query<&mut PathFindingQueue>,
     path_tilemaps: Res<PathTilemaps>,
+    mut cache: ResMut<PathCache>,
 ) {
     let Some((requester, mut cur_task)) = tasks_query.iter_mut().next() else {
         return;
@@ -430,23 +1049,341 @@ pub fn path_finding_single_threaded(
 
     cur_task.find_path(Some(&path_tilemaps));
     if cur_task.is_done {
-        commands.entity(requester).insert(cur_task.collect_path());
+        let tilemap = cur_task.tilemap;
+        let result = cur_task.result();
+        match queues_query.get_mut(tilemap) {
+            Ok(mut queue) => complete_batch(
+                &mut commands,
+                &mut cache,
+                &mut queue,
+                requester,
+                tilemap,
+                result,
+            ),
+            Err(_) => insert_result(&mut commands, requester, tilemap, result),
+        }
         commands.entity(requester).remove::<PathGrid>();
     }
 }
 
 #[cfg(feature = "multi-threaded")]
-pub fn path_assigner(mut commands: Commands, mut queues_query: Query<&mut PathFindingQueue>) {
-    queues_query.iter_mut().for_each(|mut queue| {
+pub fn path_assigner(
+    mut commands: Commands,
+    mut queues_query: Query<(Entity, &mut PathFindingQueue)>,
+    mut cache: ResMut<PathCache>,
+) {
+    queues_query.iter_mut().for_each(|(tilemap, mut queue)| {
         let mut completed = Vec::new();
         queue.tasks.iter_mut().for_each(|(requester, task)| {
-            if let Some(path) = bevy::tasks::block_on(futures_lite::future::poll_once(task)) {
-                commands.entity(*requester).insert(path);
-                completed.push(*requester);
+            if let Some(result) = bevy::tasks::block_on(futures_lite::future::poll_once(task)) {
+                completed.push((*requester, result));
             }
         });
-        completed.iter().for_each(|requester| {
-            queue.tasks.remove(requester);
+        completed.into_iter().for_each(|(requester, result)| {
+            queue.tasks.remove(&requester);
+            complete_batch(
+                &mut commands,
+                &mut cache,
+                &mut queue,
+                requester,
+                tilemap,
+                result,
+            );
         });
     });
 }
+
+#[cfg(test)]
+mod test {
+    use bevy::math::UVec2;
+
+    use crate::{
+        math::TileArea,
+        tilemap::{
+            algorithm::path::PathTile,
+            wall::{TileEdge, TileSide},
+        },
+    };
+
+    use super::*;
+
+    fn finder(dest: IVec2) -> PathFinder {
+        PathFinder {
+            origin: IVec2::ZERO,
+            dest,
+            allow_diagonal: true,
+            capabilities: PathCapabilities::all(),
+            max_steps: None,
+            algorithm: PathAlgorithm::AStar,
+            bidirectional: false,
+            max_steps_per_frame: 16,
+            cancel: None,
+            #[cfg(not(feature = "multi-threaded"))]
+            tilemap_ty: TilemapType::Square,
+        }
+    }
+
+    fn find_path(path_tilemap: &PathTilemap, finder: PathFinder) -> Result<Path, PathFailure> {
+        let tilemap = Entity::PLACEHOLDER;
+
+        #[cfg(feature = "multi-threaded")]
+        let (mut grid, path_tilemaps) = {
+            let path_tilemap = Arc::new(Mutex::new(path_tilemap.clone()));
+            (
+                PathGrid::new(finder, tilemap, tilemap, TilemapType::Square, path_tilemap),
+                None,
+            )
+        };
+        #[cfg(not(feature = "multi-threaded"))]
+        let (mut grid, path_tilemaps) = {
+            let mut path_tilemaps = PathTilemaps::default();
+            path_tilemaps.insert(tilemap, path_tilemap.clone());
+            (
+                PathGrid::new(finder, tilemap, tilemap, TilemapType::Square),
+                Some(path_tilemaps),
+            )
+        };
+
+        while !grid.is_done {
+            grid.find_path(path_tilemaps.as_ref());
+        }
+        grid.result()
+    }
+
+    fn assert_valid(path: &Path, path_tilemap: &PathTilemap, dest: IVec2) {
+        assert_eq!(path.path[0], dest);
+        assert!(path
+            .iter()
+            .chain([IVec2::ZERO].iter())
+            .zip(path.iter().skip(1).chain([IVec2::ZERO].iter()))
+            .all(|(a, b)| (*a - *b).abs().max_element() == 1 && path_tilemap.get(*a).is_some()));
+    }
+
+    /// A wall at x = 10 with a gap at the top.
+    fn walled() -> PathTilemap {
+        let mut path_tilemap = PathTilemap::new();
+        path_tilemap.fill_path_rect_custom(TileArea::new(IVec2::ZERO, UVec2::splat(20)), |index| {
+            (index.x != 10 || index.y == 19).then_some(PathTile::new(1))
+        });
+        path_tilemap
+    }
+
+    #[test]
+    fn test_jump_point_search() {
+        let mut path_tilemap = walled();
+        let dest = IVec2::new(19, 0);
+        let jps = PathFinder {
+            algorithm: PathAlgorithm::JumpPoint,
+            ..finder(dest)
+        };
+
+        let path = find_path(&path_tilemap, jps).unwrap();
+        assert_valid(&path, &path_tilemap, dest);
+        assert!(path.iter().any(|index| *index == IVec2::new(10, 19)));
+        // 19 steps up to the gap and 19 steps down to the destination.
+        assert_eq!(path.path.len(), 38);
+
+        // Falls back to A* with different costs.
+        path_tilemap.set(IVec2::ONE, PathTile::new(2));
+        let jps = PathFinder {
+            algorithm: PathAlgorithm::JumpPoint,
+            ..finder(dest)
+        };
+        let path = find_path(&path_tilemap, jps).unwrap();
+        assert_eq!(path.path.len(), 38);
+    }
+
+    #[test]
+    fn test_bidirectional() {
+        let mut path_tilemap = walled();
+        let dest = IVec2::new(19, 0);
+
+        let bidirectional = PathFinder {
+            bidirectional: true,
+            ..finder(dest)
+        };
+        let path = find_path(&path_tilemap, bidirectional).unwrap();
+        assert_valid(&path, &path_tilemap, dest);
+        assert_eq!(path.path.len(), 38);
+
+        let bidirectional = PathFinder {
+            bidirectional: true,
+            allow_diagonal: false,
+            ..finder(dest)
+        };
+        let path = find_path(&path_tilemap, bidirectional).unwrap();
+        assert_eq!(path.path.len(), 19 + 10 + 19 + 9);
+
+        // Close the gap.
+        path_tilemap.remove(IVec2::new(10, 19));
+        let bidirectional = PathFinder {
+            bidirectional: true,
+            ..finder(dest)
+        };
+        assert_eq!(
+            find_path(&path_tilemap, bidirectional).err(),
+            Some(PathFailure::Unreachable)
+        );
+        assert_eq!(
+            find_path(&path_tilemap, finder(dest)).err(),
+            Some(PathFailure::Unreachable)
+        );
+        let limited = PathFinder {
+            max_steps: Some(50),
+            ..finder(dest)
+        };
+        assert_eq!(
+            find_path(&path_tilemap, limited).err(),
+            Some(PathFailure::TooManySteps)
+        );
+    }
+
+    #[test]
+    fn test_cancel() {
+        let path_tilemap = walled();
+        let token = CancelToken::new();
+        let cancellable = PathFinder {
+            cancel: Some(token.clone()),
+            ..finder(IVec2::new(19, 0))
+        };
+        assert!(find_path(&path_tilemap, cancellable).is_ok());
+
+        token.cancel();
+        let cancelled = PathFinder {
+            cancel: Some(token),
+            ..finder(IVec2::new(19, 0))
+        };
+        assert!(cancelled.is_cancelled());
+        assert_eq!(
+            find_path(&path_tilemap, cancelled).err(),
+            Some(PathFailure::Cancelled)
+        );
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let mut path_tilemap = walled();
+        let dest = IVec2::new(19, 0);
+        // A river along the wall.
+        (0..19).for_each(|y| {
+            path_tilemap.set(
+                IVec2::new(10, y),
+                PathTile::new(1).with_capabilities(PathCapabilities::SWIM),
+            )
+        });
+
+        let walker = PathFinder {
+            capabilities: PathCapabilities::WALK,
+            ..finder(dest)
+        };
+        let path = find_path(&path_tilemap, walker).unwrap();
+        assert!(path.iter().all(|index| index.x != 10 || index.y == 19));
+        assert_eq!(path.path.len(), 38);
+
+        let swimmer = PathFinder {
+            capabilities: PathCapabilities::WALK | PathCapabilities::SWIM,
+            ..finder(dest)
+        };
+        assert_eq!(find_path(&path_tilemap, swimmer).unwrap().path.len(), 19);
+
+        let jps = PathFinder {
+            capabilities: PathCapabilities::WALK,
+            algorithm: PathAlgorithm::JumpPoint,
+            ..finder(dest)
+        };
+        assert_eq!(find_path(&path_tilemap, jps).unwrap().path.len(), 38);
+
+        path_tilemap.remove(IVec2::new(10, 19));
+        let walker = PathFinder {
+            capabilities: PathCapabilities::WALK,
+            bidirectional: true,
+            ..finder(dest)
+        };
+        assert_eq!(
+            find_path(&path_tilemap, walker).err(),
+            Some(PathFailure::Unreachable)
+        );
+
+        let cache = PathCache::default();
+        let tilemap = Entity::PLACEHOLDER;
+        let swimmer = PathFinder {
+            capabilities: PathCapabilities::SWIM,
+            ..finder(dest)
+        };
+        assert_ne!(
+            cache.key(tilemap, &finder(dest)),
+            cache.key(tilemap, &swimmer)
+        );
+    }
+
+    #[test]
+    fn test_walls() {
+        let mut path_tilemap = PathTilemap::new();
+        path_tilemap.fill_path_rect(
+            TileArea::new(IVec2::ZERO, UVec2::splat(20)),
+            PathTile::new(1),
+        );
+        let dest = IVec2::new(19, 0);
+        // A thin wall along x = 10.
+        let wall = (0..20).map(|y| TileEdge::new(IVec2::new(10, y), TileSide::Left));
+        path_tilemap.set_walls(wall.clone());
+        assert_eq!(
+            find_path(&path_tilemap, finder(dest)).err(),
+            Some(PathFailure::Unreachable)
+        );
+
+        // Open a door at the top.
+        path_tilemap.set_walls(wall.filter(|edge| edge.index().y != 19));
+        [PathAlgorithm::AStar, PathAlgorithm::JumpPoint]
+            .into_iter()
+            .for_each(|algorithm| {
+                let finder = PathFinder {
+                    algorithm,
+                    ..finder(dest)
+                };
+                let path = find_path(&path_tilemap, finder).unwrap();
+                assert_valid(&path, &path_tilemap, dest);
+                assert!(path.iter().any(|index| index.y == 19));
+                assert!(path
+                    .iter()
+                    .zip(path.iter().skip(1))
+                    .all(|(a, b)| !path_tilemap.is_blocked(*b, *a)));
+            });
+    }
+
+    #[test]
+    fn test_path_cache() {
+        let mut path_tilemap = walled();
+        let dest = IVec2::new(19, 0);
+        let tilemap = Entity::PLACEHOLDER;
+        let mut cache = PathCache {
+            region_size: 4,
+            capacity: 1,
+            ..Default::default()
+        };
+
+        let path = find_path(&path_tilemap, finder(dest)).unwrap();
+        let key = cache.key(tilemap, &finder(dest));
+        cache.insert(key, path, path_tilemap.revision());
+
+        // Nearby requests share the path.
+        let nearby = PathFinder {
+            origin: IVec2::new(3, 3),
+            ..finder(IVec2::new(16, 1))
+        };
+        assert_eq!(cache.key(tilemap, &nearby), key);
+        assert!(cache.get(&key, path_tilemap.revision()).is_some());
+        assert_ne!(cache.key(tilemap, &finder(IVec2::new(15, 0))), key);
+
+        // Invalidated by changes.
+        path_tilemap.remove(IVec2::new(10, 19));
+        assert!(cache.get(&key, path_tilemap.revision()).is_none());
+
+        // Evicted when full.
+        let other = cache.key(tilemap, &finder(IVec2::ONE));
+        let path = find_path(&path_tilemap, finder(IVec2::ONE)).unwrap();
+        cache.insert(other, path, path_tilemap.revision());
+        assert_eq!(cache.paths.len(), 1);
+        assert!(cache.get(&other, path_tilemap.revision()).is_some());
+    }
+}
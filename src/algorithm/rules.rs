@@ -0,0 +1,483 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::{Event, EventWriter},
+        system::{Commands, Query},
+    },
+    log::error,
+    math::{IVec2, UVec2},
+    reflect::Reflect,
+    utils::{HashMap, HashSet},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    algorithm::constraint::TilemapConstraints,
+    math::TileArea,
+    serializing::pattern::TilemapPattern,
+    tilemap::{
+        height::{CliffTileSet, TilemapHeights},
+        map::{TilemapStorage, TilemapType},
+        tile::{Tile, TileBuilder, TileTexture},
+    },
+};
+
+/// A condition on a single tile of a `TileRule`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum RuleCell {
+    Any,
+    /// There's no tile.
+    Empty,
+    /// There's a tile, no matter what texture it has.
+    Filled,
+    /// The tile has this texture index on the layer of the rule.
+    Texture(i32),
+    /// The tile doesn't exist, or doesn't have this texture index on the layer of the rule.
+    NotTexture(i32),
+}
+
+impl RuleCell {
+    fn matches(&self, tile: Option<&TileBuilder>, layer: usize) -> bool {
+        let texture = tile.and_then(|tile| match &tile.texture {
+            TileTexture::Static(layers) => layers
+                .get(layer)
+                .map(|l| l.texture_index)
+                .filter(|i| *i >= 0),
+            TileTexture::Animated(_) => None,
+        });
+
+        match self {
+            RuleCell::Any => true,
+            RuleCell::Empty => tile.is_none(),
+            RuleCell::Filled => tile.is_some(),
+            RuleCell::Texture(index) => texture == Some(*index),
+            RuleCell::NotTexture(index) => texture != Some(*index),
+        }
+    }
+}
+
+/// What happens to a single tile when a `TileRule` matches.
+#[derive(Debug, Clone, Default, Reflect)]
+pub enum RuleOutput {
+    #[default]
+    Keep,
+    Remove,
+    Set(TileBuilder),
+}
+
+/// Replaces the tiles that match a pattern, like the auto rules in LDtk.
+///
+/// The cells and the outputs are in rows from bottom to top.
+#[derive(Debug, Clone, Reflect)]
+pub struct TileRule {
+    pub(crate) size: UVec2,
+    pub(crate) cells: Vec<RuleCell>,
+    pub(crate) outputs: Vec<RuleOutput>,
+    /// The layer to look for texture indices.
+    pub layer: usize,
+    /// The chance to apply the rule on each match.
+    pub probability: f32,
+    /// Only match the tiles inside this area.
+    pub area: Option<TileArea>,
+    /// The maximum amount of matches to apply.
+    pub limit: Option<usize>,
+}
+
+impl TileRule {
+    /// Create a rule that keeps everything, use `with_output` or `with_pattern`
+    /// to specify the replacement.
+    pub fn new(size: UVec2, cells: Vec<RuleCell>) -> Self {
+        assert_eq!(
+            cells.len(),
+            (size.x * size.y) as usize,
+            "The amount of cells doesn't match the size of the rule!"
+        );
+
+        Self {
+            size,
+            cells,
+            outputs: vec![RuleOutput::Keep; (size.x * size.y) as usize],
+            layer: 0,
+            probability: 1.,
+            area: None,
+            limit: None,
+        }
+    }
+
+    pub fn with_output(mut self, index: UVec2, output: RuleOutput) -> Self {
+        self.outputs[(index.y * self.size.x + index.x) as usize] = output;
+        self
+    }
+
+    /// Replace the tiles with the tiles in the pattern.
+    /// The bottom left tile of the pattern is placed at the bottom left of the rule.
+    pub fn with_pattern(mut self, pattern: &TilemapPattern) -> Self {
+        let origin = pattern.tiles.aabb.min;
+        pattern.tiles.tiles.iter().for_each(|(index, tile)| {
+            let rel = (*index - origin).as_uvec2();
+            if rel.x < self.size.x && rel.y < self.size.y {
+                self.outputs[(rel.y * self.size.x + rel.x) as usize] =
+                    RuleOutput::Set(tile.clone());
+            }
+        });
+        self
+    }
+
+    pub fn with_layer(mut self, layer: usize) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    pub fn with_probability(mut self, probability: f32) -> Self {
+        self.probability = probability;
+        self
+    }
+
+    pub fn within(mut self, area: TileArea) -> Self {
+        self.area = Some(area);
+        self
+    }
+
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    #[inline]
+    fn cell_indices(&self) -> impl Iterator<Item = IVec2> + '_ {
+        (0..self.size.y as i32).flat_map(|y| (0..self.size.x as i32).map(move |x| IVec2 { x, y }))
+    }
+
+    /// All the origins where the rule may match.
+    fn candidates(&self, tiles: &HashMap<IVec2, TileBuilder>) -> Vec<IVec2> {
+        let size = self.size.as_ivec2();
+        let (min, max) = match self.area {
+            Some(area) => (area.origin, area.dest - size + 1),
+            None => {
+                let mut indices = tiles.keys();
+                let Some(first) = indices.next() else {
+                    return Vec::new();
+                };
+                let (min, max) = indices.fold((*first, *first), |(min, max), index| {
+                    (min.min(*index), max.max(*index))
+                });
+                // Patterns partially outside the tiles can still match empty cells.
+                (min - size + 1, max)
+            }
+        };
+
+        (min.y..=max.y)
+            .flat_map(|y| (min.x..=max.x).map(move |x| IVec2 { x, y }))
+            .collect()
+    }
+
+    fn matches(&self, tiles: &HashMap<IVec2, TileBuilder>, origin: IVec2) -> bool {
+        self.cell_indices()
+            .zip(self.cells.iter())
+            .all(|(index, cell)| cell.matches(tiles.get(&(origin + index)), self.layer))
+    }
+}
+
+/// Applies the rules in order to the tiles. Each rule matches against the tiles
+/// left by the previous rules. Returns the changed indices and how many times
+/// each rule is applied.
+///
+/// The pinned tiles in the constraints are placed before matching,
+/// and the outputs on pinned or masked cells are ignored.
+pub fn apply_rules(
+    tiles: &mut HashMap<IVec2, TileBuilder>,
+    rules: &[TileRule],
+    constraints: &TilemapConstraints,
+    rng: &mut impl Rng,
+) -> (HashSet<IVec2>, Vec<usize>) {
+    let mut changed = HashSet::new();
+    constraints.pinned().for_each(|(index, tile)| {
+        tiles.insert(index, tile.clone());
+        changed.insert(index);
+    });
+
+    let applied = rules
+        .iter()
+        .map(|rule| {
+            let matches = rule
+                .candidates(tiles)
+                .into_iter()
+                .filter(|origin| rule.matches(tiles, *origin))
+                .collect::<Vec<_>>();
+
+            let mut count = 0;
+            for origin in matches {
+                if rule.limit.is_some_and(|limit| count >= limit) {
+                    break;
+                }
+                if rule.probability < 1. && !rng.gen_bool(rule.probability.max(0.) as f64) {
+                    continue;
+                }
+
+                rule.cell_indices()
+                    .zip(rule.outputs.iter())
+                    .for_each(|(index, output)| {
+                        let index = origin + index;
+                        if constraints.is_locked(index) {
+                            return;
+                        }
+                        match output {
+                            RuleOutput::Keep => return,
+                            RuleOutput::Remove => {
+                                tiles.remove(&index);
+                            }
+                            RuleOutput::Set(tile) => {
+                                tiles.insert(index, tile.clone());
+                            }
+                        }
+                        changed.insert(index);
+                    });
+                count += 1;
+            }
+            count
+        })
+        .collect();
+
+    (changed, applied)
+}
+
+/// Runs the rules on the tilemap once, then removes itself.
+/// Insert this after the tilemap is generated or imported, for decoration or cleanup passes.
+#[derive(Component, Debug, Clone, Reflect)]
+pub struct TilemapPostProcessor {
+    pub rules: Vec<TileRule>,
+    /// Place the cliff tiles along the `TilemapHeights` of the tilemap before the rules,
+    /// so the rules can match against them.
+    pub cliffs: Option<CliffTileSet>,
+    pub seed: Option<u64>,
+}
+
+impl TilemapPostProcessor {
+    pub fn new(rules: Vec<TileRule>) -> Self {
+        Self {
+            rules,
+            cliffs: None,
+            seed: None,
+        }
+    }
+
+    pub fn with_cliffs(mut self, tile_set: CliffTileSet) -> Self {
+        self.cliffs = Some(tile_set);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+/// Place the cliff tiles on the cells that are not locked by the constraints.
+fn place_cliffs(
+    tiles: &mut HashMap<IVec2, TileBuilder>,
+    heights: &TilemapHeights,
+    ty: TilemapType,
+    tile_set: &CliffTileSet,
+    constraints: &TilemapConstraints,
+) -> HashSet<IVec2> {
+    heights
+        .cliff_tiles(ty, tile_set)
+        .tiles
+        .into_iter()
+        .filter(|(index, _)| !constraints.is_locked(*index))
+        .map(|(index, tile)| {
+            tiles.insert(index, tile);
+            index
+        })
+        .collect()
+}
+
+/// Fired when a `TilemapPostProcessor` is done.
+#[derive(Event, Debug, Clone, Reflect)]
+pub struct TilemapPostProcessed {
+    pub tilemap: Entity,
+    /// How many times each rule is applied.
+    pub applied: Vec<usize>,
+}
+
+pub fn tilemap_post_processor(
+    mut commands: Commands,
+    mut tilemaps_query: Query<(
+        Entity,
+        &mut TilemapStorage,
+        &TilemapType,
+        &TilemapPostProcessor,
+        Option<&TilemapConstraints>,
+        Option<&TilemapHeights>,
+    )>,
+    tiles_query: Query<&Tile>,
+    mut processed: EventWriter<TilemapPostProcessed>,
+) {
+    for (entity, mut storage, ty, processor, constraints, heights) in &mut tilemaps_query {
+        let Some(mut tiles) = storage
+            .storage
+            .chunked_iter_some()
+            .map(|(ci, ti, tile)| {
+                tiles_query.get(*tile).ok().map(|tile| {
+                    (
+                        storage.storage.inverse_transform_index(ci, ti),
+                        tile.clone().into(),
+                    )
+                })
+            })
+            .collect::<Option<HashMap<_, _>>>()
+        else {
+            // Some tiles are not spawned yet.
+            continue;
+        };
+
+        let mut rng = match processor.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let default_constraints = TilemapConstraints::default();
+        let constraints = constraints.unwrap_or(&default_constraints);
+        let mut changed = HashSet::new();
+        match (&processor.cliffs, heights) {
+            (Some(_), _) if matches!(ty, TilemapType::Hexagonal(_)) => {
+                error!(
+                    "Cliff tiles are not supported on hexagonal tilemap {:?}!",
+                    entity
+                );
+            }
+            (Some(tile_set), Some(heights)) => {
+                changed = place_cliffs(&mut tiles, heights, *ty, tile_set, constraints);
+            }
+            _ => {}
+        }
+        let (rules_changed, applied) =
+            apply_rules(&mut tiles, &processor.rules, constraints, &mut rng);
+        changed.extend(rules_changed);
+
+        changed
+            .into_iter()
+            .for_each(|index| match tiles.remove(&index) {
+                Some(tile) => storage.set(&mut commands, index, tile),
+                None => storage.remove(&mut commands, index),
+            });
+
+        commands.entity(entity).remove::<TilemapPostProcessor>();
+        processed.send(TilemapPostProcessed {
+            tilemap: entity,
+            applied,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tilemap::tile::TileLayer;
+
+    use super::*;
+
+    #[test]
+    fn test_apply_rules() {
+        let tile = |texture| TileBuilder::new().with_layer(0, TileLayer::no_flip(texture));
+        let mut tiles = [(0, 0), (1, 0), (2, 0), (5, 5)]
+            .into_iter()
+            .map(|(x, y)| (IVec2::new(x, y), tile(0)))
+            .collect::<HashMap<_, _>>();
+
+        use RuleCell::*;
+        let rules = [
+            // Remove isolated tiles.
+            TileRule::new(
+                UVec2::splat(3),
+                vec![
+                    Empty, Empty, Empty, Empty, Filled, Empty, Empty, Empty, Empty,
+                ],
+            )
+            .with_output(UVec2::ONE, RuleOutput::Remove),
+            // Put a decoration above the grounds.
+            TileRule::new(UVec2::new(1, 2), vec![Texture(0), Empty])
+                .with_output(UVec2::Y, RuleOutput::Set(tile(1)))
+                .with_limit(2),
+        ];
+
+        let original = tiles.clone();
+        let (changed, applied) = apply_rules(
+            &mut tiles,
+            &rules,
+            &TilemapConstraints::default(),
+            &mut StdRng::seed_from_u64(0),
+        );
+        assert_eq!(applied, vec![1, 2]);
+        assert_eq!(changed.len(), 3);
+        assert!(!tiles.contains_key(&IVec2::new(5, 5)));
+        assert!(RuleCell::Texture(1).matches(tiles.get(&IVec2::new(0, 1)), 0));
+        assert!(RuleCell::Empty.matches(tiles.get(&IVec2::new(2, 1)), 0));
+
+        // The isolated tile is masked, and the pinned tile blocks the first decoration.
+        let mut tiles = original;
+        let constraints = TilemapConstraints::new()
+            .with_mask(TileArea::new(IVec2::splat(5), UVec2::ONE))
+            .with_pinned(IVec2::new(0, 1), tile(2));
+        let (changed, applied) = apply_rules(
+            &mut tiles,
+            &rules,
+            &constraints,
+            &mut StdRng::seed_from_u64(0),
+        );
+        assert_eq!(applied, vec![1, 2]);
+        assert_eq!(changed.len(), 3);
+        assert!(tiles.contains_key(&IVec2::new(5, 5)));
+        assert!(RuleCell::Texture(2).matches(tiles.get(&IVec2::new(0, 1)), 0));
+        assert!(RuleCell::Texture(1).matches(tiles.get(&IVec2::new(2, 1)), 0));
+    }
+
+    #[test]
+    fn test_cliff_rules() {
+        use crate::tilemap::height::CliffEdges;
+
+        let tile = |texture| TileBuilder::new().with_layer(0, TileLayer::no_flip(texture));
+        let mut heights = TilemapHeights::new();
+        heights.set(IVec2::ZERO, 1);
+        heights.set(IVec2::X, 0);
+        let tile_set = CliffTileSet::new().with_tile(CliffEdges::RIGHT, tile(5));
+        // Put a decoration above the cliffs.
+        let rules = [TileRule::new(
+            UVec2::new(1, 2),
+            vec![RuleCell::Texture(5), RuleCell::Empty],
+        )
+        .with_output(UVec2::Y, RuleOutput::Set(tile(6)))];
+
+        let mut tiles = HashMap::new();
+        let constraints = TilemapConstraints::default();
+        let cliffs = place_cliffs(
+            &mut tiles,
+            &heights,
+            TilemapType::Square,
+            &tile_set,
+            &constraints,
+        );
+        assert_eq!(cliffs, HashSet::from([IVec2::ZERO]));
+        let (_, applied) = apply_rules(
+            &mut tiles,
+            &rules,
+            &constraints,
+            &mut StdRng::seed_from_u64(0),
+        );
+        assert_eq!(applied, vec![1]);
+        assert!(RuleCell::Texture(6).matches(tiles.get(&IVec2::Y), 0));
+
+        // The masked cliff is not placed.
+        let mut tiles = HashMap::new();
+        let constraints =
+            TilemapConstraints::new().with_mask(TileArea::new(IVec2::ZERO, UVec2::ONE));
+        let cliffs = place_cliffs(
+            &mut tiles,
+            &heights,
+            TilemapType::Square,
+            &tile_set,
+            &constraints,
+        );
+        assert!(cliffs.is_empty());
+        assert!(tiles.is_empty());
+    }
+}
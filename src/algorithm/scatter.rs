@@ -0,0 +1,265 @@
+use std::f32::consts::TAU;
+
+use bevy::{
+    math::{IVec2, UVec2, Vec2},
+    reflect::Reflect,
+};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::math::TileArea;
+
+/// Scatter tiles in an area so that no two of them are closer than `radius`,
+/// which looks more natural than uniform random for decorations, spawners and resources.
+///
+/// This keeps adding tiles until there's no room left.
+#[derive(Debug, Clone, Reflect)]
+pub struct PoissonDiskScatter {
+    pub area: TileArea,
+    /// The minimum distance between two tiles, in tiles.
+    pub radius: f32,
+    /// How many candidates are tried around a tile before giving up on it.
+    pub attempts: u32,
+    pub seed: Option<u64>,
+}
+
+impl PoissonDiskScatter {
+    pub fn new(area: TileArea, radius: f32) -> Self {
+        assert!(radius > 0., "The radius should be positive!");
+        Self {
+            area,
+            radius,
+            attempts: 30,
+            seed: None,
+        }
+    }
+
+    pub fn with_attempts(mut self, attempts: u32) -> Self {
+        self.attempts = attempts;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    #[inline]
+    pub fn scatter(&self) -> Vec<IVec2> {
+        self.scatter_masked(|_| true)
+    }
+
+    /// Only scatter on the tiles where `mask` returns true,
+    /// like the floors of a dungeon or the tiles not locked by `TilemapConstraints`.
+    pub fn scatter_masked(&self, mask: impl Fn(IVec2) -> bool) -> Vec<IVec2> {
+        let mut rng = rng_of(self.seed);
+        let mut grid = ScatterGrid::new(self.area, self.radius);
+        let mut active = Vec::new();
+
+        // Every tile is a potential seed, so separated parts of the mask are covered too.
+        let mut seeds = self.area.aabb().into_iter().collect::<Vec<_>>();
+        seeds.shuffle(&mut rng);
+
+        for seed in seeds {
+            if !mask(seed) || !grid.is_free(seed) {
+                continue;
+            }
+            grid.insert(seed);
+            active.push(seed);
+
+            while !active.is_empty() {
+                let i = rng.gen_range(0..active.len());
+                let center = active[i].as_vec2();
+                let found = (0..self.attempts).find_map(|_| {
+                    let angle = rng.gen_range(0. ..TAU);
+                    let dist = rng.gen_range(self.radius..self.radius * 2.);
+                    let candidate = (center + Vec2::from_angle(angle) * dist).round().as_ivec2();
+                    (grid.contains(candidate) && mask(candidate) && grid.is_free(candidate))
+                        .then_some(candidate)
+                });
+
+                match found {
+                    Some(candidate) => {
+                        grid.insert(candidate);
+                        active.push(candidate);
+                    }
+                    None => {
+                        active.swap_remove(i);
+                    }
+                }
+            }
+        }
+
+        grid.points
+    }
+}
+
+/// Scatter a fixed amount of tiles in an area, keeping them as far from each other as possible.
+///
+/// This uses Mitchell's best candidate algorithm: for each tile, several random candidates are
+/// generated and the one furthest from the existing tiles is kept.
+#[derive(Debug, Clone, Reflect)]
+pub struct BlueNoiseScatter {
+    pub area: TileArea,
+    pub count: usize,
+    /// How many candidates are generated for each tile. More means more evenly spaced.
+    pub candidates: u32,
+    pub seed: Option<u64>,
+}
+
+impl BlueNoiseScatter {
+    pub fn new(area: TileArea, count: usize) -> Self {
+        Self {
+            area,
+            count,
+            candidates: 10,
+            seed: None,
+        }
+    }
+
+    pub fn with_candidates(mut self, candidates: u32) -> Self {
+        self.candidates = candidates;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    #[inline]
+    pub fn scatter(&self) -> Vec<IVec2> {
+        self.scatter_masked(|_| true)
+    }
+
+    /// Only scatter on the tiles where `mask` returns true.
+    /// Returns less tiles than `count` if there are not enough tiles available.
+    pub fn scatter_masked(&self, mask: impl Fn(IVec2) -> bool) -> Vec<IVec2> {
+        let mut rng = rng_of(self.seed);
+        let mut available = self
+            .area
+            .aabb()
+            .into_iter()
+            .filter(|index| mask(*index))
+            .collect::<Vec<_>>();
+        let mut points = Vec::with_capacity(self.count.min(available.len()));
+
+        while points.len() < self.count && !available.is_empty() {
+            let best = (0..self.candidates.max(1))
+                .map(|_| rng.gen_range(0..available.len()))
+                .max_by_key(|candidate| {
+                    points
+                        .iter()
+                        .map(|p: &IVec2| p.distance_squared(available[*candidate]))
+                        .min()
+                        .unwrap_or(i32::MAX)
+                })
+                .unwrap();
+            points.push(available.swap_remove(best));
+        }
+
+        points
+    }
+}
+
+#[inline]
+fn rng_of(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    }
+}
+
+/// A background grid with cells small enough to hold at most one point,
+/// so only the nearby cells are checked for the distance.
+struct ScatterGrid {
+    area: TileArea,
+    radius_squared: f32,
+    cell_size: f32,
+    size: UVec2,
+    cells: Vec<Option<IVec2>>,
+    points: Vec<IVec2>,
+}
+
+impl ScatterGrid {
+    fn new(area: TileArea, radius: f32) -> Self {
+        let cell_size = radius / 2f32.sqrt();
+        let size = (area.extent.as_vec2() / cell_size)
+            .ceil()
+            .as_uvec2()
+            .max(UVec2::ONE);
+        Self {
+            area,
+            radius_squared: radius * radius,
+            cell_size,
+            size,
+            cells: vec![None; (size.x * size.y) as usize],
+            points: Vec::new(),
+        }
+    }
+
+    #[inline]
+    fn contains(&self, index: IVec2) -> bool {
+        index.cmpge(self.area.origin).all() && index.cmple(self.area.dest).all()
+    }
+
+    #[inline]
+    fn cell_of(&self, index: IVec2) -> IVec2 {
+        ((index - self.area.origin).as_vec2() / self.cell_size)
+            .floor()
+            .as_ivec2()
+            .min(self.size.as_ivec2() - 1)
+    }
+
+    fn is_free(&self, index: IVec2) -> bool {
+        let cell = self.cell_of(index);
+        (-2..=2)
+            .flat_map(|dy| (-2..=2).map(move |dx| cell + IVec2::new(dx, dy)))
+            .filter(|c| c.cmpge(IVec2::ZERO).all() && c.cmplt(self.size.as_ivec2()).all())
+            .filter_map(|c| self.cells[(c.y * self.size.x as i32 + c.x) as usize])
+            .all(|p| ((p - index).length_squared() as f32) >= self.radius_squared)
+    }
+
+    fn insert(&mut self, index: IVec2) {
+        let cell = self.cell_of(index);
+        self.cells[(cell.y * self.size.x as i32 + cell.x) as usize] = Some(index);
+        self.points.push(index);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_scatter() {
+        let area = TileArea::new(IVec2::new(-10, 5), UVec2::new(40, 30));
+        let scatter = PoissonDiskScatter::new(area, 4.).with_seed(3);
+        let points = scatter.scatter();
+        assert_eq!(points, scatter.scatter());
+        assert!(points.len() > 20);
+        for (i, a) in points.iter().enumerate() {
+            assert!(area.aabb().contains(*a));
+            for b in &points[i + 1..] {
+                assert!(a.distance_squared(*b) >= 16);
+            }
+        }
+
+        // Every free tile in the mask is too close to a scattered one.
+        let mask = |index: IVec2| index.x < 0 || index.y > 25;
+        let points = scatter.scatter_masked(mask);
+        assert!(points.iter().all(|p| mask(*p)));
+        assert!(area
+            .aabb()
+            .into_iter()
+            .filter(|index| mask(*index))
+            .all(|index| points.iter().any(|p| p.distance_squared(index) < 16)));
+
+        let points = BlueNoiseScatter::new(area, 10).with_seed(3).scatter();
+        assert_eq!(points.len(), 10);
+        let single = TileArea::new(IVec2::ZERO, UVec2::ONE);
+        assert_eq!(
+            BlueNoiseScatter::new(single, 10).scatter(),
+            vec![IVec2::ZERO]
+        );
+    }
+}
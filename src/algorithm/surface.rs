@@ -0,0 +1,242 @@
+use bevy::{
+    ecs::entity::Entity,
+    math::{IVec2, Vec2},
+    reflect::Reflect,
+};
+use rand::Rng;
+
+use crate::{
+    math::aabb::IAabb2d,
+    render::cull::TilemapVisibleTiles,
+    tilemap::{
+        coordinates,
+        lookup::TilemapTextureLookup,
+        map::{TilemapAxisFlip, TilemapStorage, TilemapTransform, TilemapType},
+    },
+};
+
+/// A set of weighted tiles to pick random positions from, like emitting fireflies
+/// above the grass tiles or dust from the sand tiles for particle systems.
+///
+/// Build this when the tiles or the view change, and sample it every frame.
+#[derive(Debug, Clone, Reflect)]
+pub struct TileSpawnSurface {
+    tiles: Vec<IVec2>,
+    /// The prefix sums of the weights.
+    weights: Vec<f32>,
+    /// How far the positions of `sample_world` can be from the center of the tile,
+    /// where `0` is always the center and `1` is anywhere on the tile.
+    spread: f32,
+}
+
+impl Default for TileSpawnSurface {
+    fn default() -> Self {
+        Self {
+            tiles: Vec::new(),
+            weights: Vec::new(),
+            spread: 1.,
+        }
+    }
+}
+
+impl TileSpawnSurface {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how far the positions can be from the center of the tile. `1` by default.
+    pub fn with_spread(mut self, spread: f32) -> Self {
+        self.spread = spread.clamp(0., 1.);
+        self
+    }
+
+    /// Collect the tiles whose `weight` is positive.
+    ///
+    /// If `visible` is set, only the tiles in the visible chunks are visited,
+    /// so particles are not spawned off-screen.
+    pub fn from_storage(
+        storage: &TilemapStorage,
+        visible: Option<&TilemapVisibleTiles>,
+        area: Option<IAabb2d>,
+        mut weight: impl FnMut(IVec2, Entity) -> f32,
+    ) -> Self {
+        let mut surface = Self::new();
+        let mut add = |index: IVec2, entity: Entity| {
+            if area.map_or(true, |area| area.contains(index)) {
+                surface.add(index, weight(index, entity));
+            }
+        };
+
+        match visible {
+            Some(visible) => visible
+                .iter_tiles(storage)
+                .for_each(|(index, entity)| add(index, entity)),
+            None => storage.storage.chunked_iter_some().for_each(
+                |(chunk_index, in_chunk_index, entity)| {
+                    add(
+                        storage
+                            .storage
+                            .inverse_transform_index(chunk_index, in_chunk_index),
+                        *entity,
+                    )
+                },
+            ),
+        }
+
+        surface
+    }
+
+    /// Collect the tiles using the textures, which are given as `(layer, texture_index, weight)`.
+    ///
+    /// This only visits the matching tiles instead of the whole tilemap.
+    /// If `visible` is set, only the tiles in the visible chunks are kept.
+    pub fn from_lookup(
+        lookup: &TilemapTextureLookup,
+        textures: &[(usize, i32, f32)],
+        visible: Option<(&TilemapStorage, &TilemapVisibleTiles)>,
+        area: Option<IAabb2d>,
+    ) -> Self {
+        let mut surface = Self::new();
+        textures.iter().for_each(|(layer, texture_index, weight)| {
+            let Some(tiles) = lookup.get(*layer, *texture_index) else {
+                return;
+            };
+
+            tiles
+                .iter()
+                .filter(|index| area.map_or(true, |area| area.contains(**index)))
+                .filter(|index| {
+                    visible.map_or(true, |(storage, visible)| {
+                        visible.is_visible(storage, **index)
+                    })
+                })
+                .for_each(|index| surface.add(*index, *weight));
+        });
+        surface
+    }
+
+    /// Add a tile. Tiles with non-positive weights are ignored.
+    pub fn add(&mut self, index: IVec2, weight: f32) {
+        if weight <= 0. {
+            return;
+        }
+
+        self.weights
+            .push(self.weights.last().copied().unwrap_or_default() + weight);
+        self.tiles.push(index);
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// Pick a random tile, with the chance proportional to its weight.
+    pub fn sample_index(&self, rng: &mut impl Rng) -> Option<IVec2> {
+        let total = *self.weights.last()?;
+        let target = rng.gen_range(0. ..total);
+        let i = self.weights.partition_point(|w| *w <= target);
+        Some(self.tiles[i.min(self.tiles.len() - 1)])
+    }
+
+    /// Pick a random world position on a random tile, see `with_spread`.
+    pub fn sample_world(
+        &self,
+        rng: &mut impl Rng,
+        ty: TilemapType,
+        transform: &TilemapTransform,
+        pivot: Vec2,
+        slot_size: Vec2,
+        axis_flip: TilemapAxisFlip,
+    ) -> Option<Vec2> {
+        let index = self.sample_index(rng)?;
+        let center = coordinates::index_to_world_flipped(
+            index,
+            ty,
+            &TilemapTransform::IDENTITY,
+            pivot,
+            slot_size,
+            axis_flip,
+        ) + slot_size / 2.;
+
+        let (u, v) = (rng.gen_range(-0.5..=0.5), rng.gen_range(-0.5..=0.5));
+        let offset = match ty {
+            TilemapType::Square => Vec2::new(u, v) * slot_size,
+            TilemapType::Isometric => Vec2::new(u - v, u + v) * slot_size / 2.,
+            // Only the rectangle between the legs, so the position never leaves the tile.
+            TilemapType::Hexagonal(legs) => Vec2::new(u * slot_size.x, v * legs as f32),
+        };
+
+        Some(transform.transform_point(center + offset * self.spread))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::tilemap::tile::{TileBuilder, TileLayer};
+
+    use super::*;
+
+    #[test]
+    fn test_spawn_surface() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut surface = TileSpawnSurface::new();
+        assert_eq!(surface.sample_index(&mut rng), None);
+
+        surface.add(IVec2::ZERO, 1.);
+        surface.add(IVec2::X, 0.);
+        surface.add(IVec2::Y, 3.);
+        assert_eq!(surface.len(), 2);
+        let hits = (0..1000)
+            .filter(|_| surface.sample_index(&mut rng) == Some(IVec2::Y))
+            .count();
+        assert!((650..850).contains(&hits));
+
+        let surface = surface.with_spread(0.9);
+        let transform = TilemapTransform::from_translation(Vec2::new(100., 0.));
+        for axis_flip in [TilemapAxisFlip::NONE, TilemapAxisFlip::Y] {
+            for _ in 0..100 {
+                let world = surface
+                    .sample_world(
+                        &mut rng,
+                        TilemapType::Square,
+                        &transform,
+                        Vec2::ZERO,
+                        Vec2::splat(16.),
+                        axis_flip,
+                    )
+                    .unwrap();
+                let index = coordinates::world_to_index_flipped(
+                    world,
+                    TilemapType::Square,
+                    &transform,
+                    Vec2::ZERO,
+                    Vec2::splat(16.),
+                    axis_flip,
+                );
+                assert!(index == IVec2::ZERO || index == IVec2::Y);
+            }
+        }
+
+        let storage = TilemapStorage::new(4, Entity::from_raw(0));
+        let mut lookup = TilemapTextureLookup::default();
+        let grass = TileBuilder::new().with_layer(0, TileLayer::no_flip(2));
+        for x in 0..8 {
+            lookup.insert(&grass.build_component(IVec2::new(x, 0), &storage, storage.tilemap));
+        }
+        let surface = TileSpawnSurface::from_lookup(
+            &lookup,
+            &[(0, 2, 1.), (0, 3, 1.)],
+            None,
+            Some(IAabb2d::new(2, 0, 5, 0)),
+        );
+        assert_eq!(surface.len(), 4);
+    }
+}
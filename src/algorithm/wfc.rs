@@ -2,7 +2,10 @@
 use std::{collections::VecDeque, path::Path};
 
 use bevy::{
-    ecs::{entity::Entity, system::ResMut},
+    ecs::{
+        entity::Entity,
+        system::{Res, ResMut},
+    },
     log::warn,
     math::IVec2,
     prelude::{Commands, Component, Query, UVec2},
@@ -20,6 +23,7 @@ use crate::{
     serializing::pattern::{PackedPatternLayers, PatternsLayer, TilemapPattern},
     tilemap::{
         algorithm::path::PathTilemap,
+        budget::{TileSpawnBudget, TileSpawnQueue},
         bundles::StandardPureColorTilemapBundle,
         map::{
             TileRenderSize, TilemapAnimations, TilemapName, TilemapSlotSize, TilemapStorage,
@@ -30,9 +34,9 @@ use crate::{
     DEFAULT_CHUNK_SIZE,
 };
 
-use super::pathfinding::PathTilemaps;
+use super::{cancel::CancelToken, constraint::TilemapConstraints, pathfinding::PathTilemaps};
 
-#[cfg(feature = "physics")]
+#[cfg(feature = "physics_core")]
 use crate::tilemap::physics::{PhysicsTilemap, SerializablePhysicsSource};
 
 #[cfg(feature = "multi-threaded")]
@@ -101,6 +105,38 @@ impl WfcRules {
         res
     }
 
+    /// Learn the rules from a sample map. Two elements can be next to each other
+    /// if they are next to each other somewhere in the sample or one of the
+    /// variants enabled in `symmetry`.
+    ///
+    /// Only square tilemaps are supported.
+    pub fn from_sample(sample: &WfcSample, symmetry: WfcSymmetry) -> Self {
+        let elem_count = sample.data.iter().max().map_or(0, |max| *max as usize + 1);
+        assert!(elem_count <= 128, "We only support 128 elements for now");
+
+        let mut rule = vec![vec![0u128; 4]; elem_count];
+        for variant in sample.variants(symmetry) {
+            for y in 0..variant.size.y as i32 {
+                for x in 0..variant.size.x as i32 {
+                    let this = IVec2 { x, y };
+                    let this_elem = variant.get(this).unwrap() as usize;
+                    for (dir, offset) in [IVec2::Y, IVec2::X, IVec2::NEG_X, IVec2::NEG_Y]
+                        .into_iter()
+                        .enumerate()
+                    {
+                        if let Some(another) = variant.get(this + offset) {
+                            rule[this_elem][dir] |= 1 << another;
+                        }
+                    }
+                }
+            }
+        }
+
+        let res = Self(rule);
+        res.check_rules(TilemapType::Square);
+        res
+    }
+
     /// Check if there are conflicts in the rules.
     pub fn check_rules(&self, ty: TilemapType) {
         let (total_dirs, dir_names) = match ty {
@@ -131,6 +167,250 @@ impl WfcRules {
     }
 }
 
+/// The rules of the overlapping model, which learns every `pattern_size` x `pattern_size`
+/// window of a sample map. The output only contains windows that appear in the sample,
+/// so it looks locally similar to the sample.
+///
+/// Each pattern is an element in the wfc grid, so there can be at most 128 patterns.
+/// Use `WfcRunner::new_overlapping` to run it.
+#[derive(Debug, Clone, Reflect)]
+pub struct WfcOverlappingRules {
+    pattern_size: u32,
+    /// Row major, with `(0, 0)` at the bottom left corner.
+    patterns: Vec<Vec<u8>>,
+    /// How many times each pattern appears in the sample.
+    weights: Vec<u8>,
+    /// Which patterns overlap consistently, in the same format as `WfcRules`.
+    conn_rules: Vec<Vec<u128>>,
+}
+
+impl WfcOverlappingRules {
+    /// Learn the patterns from the sample and the variants enabled in `symmetry`.
+    ///
+    /// If `wrap_sample` is true, the windows wrap around the edges of the sample,
+    /// so the sample is considered to be tileable.
+    pub fn from_sample(
+        sample: &WfcSample,
+        pattern_size: u32,
+        symmetry: WfcSymmetry,
+        wrap_sample: bool,
+    ) -> Self {
+        assert_ne!(pattern_size, 0, "The pattern size should be at least 1");
+
+        let mut patterns = Vec::new();
+        let mut counts: Vec<u32> = Vec::new();
+        let mut pattern_indices = HashMap::new();
+        for variant in sample.variants(symmetry) {
+            let size = variant.size;
+            let windows = if wrap_sample {
+                size
+            } else {
+                (size + 1).saturating_sub(UVec2::splat(pattern_size))
+            };
+
+            for y in 0..windows.y {
+                for x in 0..windows.x {
+                    let pattern = (0..pattern_size * pattern_size)
+                        .map(|i| {
+                            let index = UVec2 {
+                                x: (x + i % pattern_size) % size.x,
+                                y: (y + i / pattern_size) % size.y,
+                            };
+                            variant.get(index.as_ivec2()).unwrap()
+                        })
+                        .collect::<Vec<_>>();
+
+                    match pattern_indices.get(&pattern) {
+                        Some(index) => counts[*index] += 1,
+                        None => {
+                            pattern_indices.insert(pattern.clone(), patterns.len());
+                            patterns.push(pattern);
+                            counts.push(1);
+                        }
+                    }
+                }
+            }
+        }
+
+        assert!(
+            patterns.len() <= 128,
+            "We only support 128 patterns for now, but found {} patterns! \
+            Try a smaller pattern size or fewer symmetry variants.",
+            patterns.len()
+        );
+
+        let n = pattern_size as i32;
+        let overlaps = |this: &[u8], another: &[u8], offset: IVec2| {
+            (0..n).all(|y| {
+                (0..n).all(|x| {
+                    let other = IVec2 { x, y } - offset;
+                    other.cmplt(IVec2::ZERO).any()
+                        || other.cmpge(IVec2::splat(n)).any()
+                        || this[(y * n + x) as usize] == another[(other.y * n + other.x) as usize]
+                })
+            })
+        };
+
+        let conn_rules = patterns
+            .iter()
+            .map(|this| {
+                [IVec2::Y, IVec2::X, IVec2::NEG_X, IVec2::NEG_Y]
+                    .into_iter()
+                    .map(|offset| {
+                        patterns
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, another)| overlaps(this, another, offset))
+                            .fold(0, |rule, (index, _)| rule | 1 << index)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            pattern_size,
+            patterns,
+            weights: counts
+                .into_iter()
+                .map(|c| c.min(u8::MAX as u32) as u8)
+                .collect(),
+            conn_rules,
+        }
+    }
+
+    #[inline]
+    pub fn pattern_size(&self) -> u32 {
+        self.pattern_size
+    }
+
+    #[inline]
+    pub fn pattern_count(&self) -> usize {
+        self.patterns.len()
+    }
+
+    /// The element that a pattern generates, which is its bottom left corner.
+    #[inline]
+    pub fn output_of(&self, pattern: usize) -> Option<u8> {
+        self.patterns.get(pattern).map(|p| p[0])
+    }
+}
+
+bitflags::bitflags! {
+    /// The rotated and mirrored variants of a `WfcSample` that are learned
+    /// in addition to the sample itself.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct WfcSymmetry: u32 {
+        const NONE                 = 0;
+        /// Clockwise.
+        const ROTATE_90            = 1 << 0;
+        const ROTATE_180           = 1 << 1;
+        const ROTATE_270           = 1 << 2;
+        /// Mirror along the x axis, so left becomes right.
+        const MIRROR_X             = 1 << 3;
+        /// Mirror along the y axis, so up becomes down.
+        const MIRROR_Y             = 1 << 4;
+        /// Mirror along the diagonal from the bottom left to the top right.
+        const MIRROR_DIAGONAL      = 1 << 5;
+        /// Mirror along the diagonal from the top left to the bottom right.
+        const MIRROR_ANTI_DIAGONAL = 1 << 6;
+        const ROTATIONS            = Self::ROTATE_90.bits()
+                                   | Self::ROTATE_180.bits()
+                                   | Self::ROTATE_270.bits();
+        const MIRRORS              = Self::MIRROR_X.bits()
+                                   | Self::MIRROR_Y.bits()
+                                   | Self::MIRROR_DIAGONAL.bits()
+                                   | Self::MIRROR_ANTI_DIAGONAL.bits();
+        const ALL                  = Self::ROTATIONS.bits() | Self::MIRRORS.bits();
+    }
+}
+
+impl Default for WfcSymmetry {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+impl WfcSymmetry {
+    /// Returns the size of the transformed sample and the transformed index.
+    /// `self` should be a single variant.
+    fn transform(self, size: UVec2, index: UVec2) -> (UVec2, UVec2) {
+        let (w, h) = (size.x - 1, size.y - 1);
+        let (x, y) = (index.x, index.y);
+        match self {
+            Self::ROTATE_90 => (UVec2::new(size.y, size.x), UVec2::new(y, w - x)),
+            Self::ROTATE_180 => (size, UVec2::new(w - x, h - y)),
+            Self::ROTATE_270 => (UVec2::new(size.y, size.x), UVec2::new(h - y, x)),
+            Self::MIRROR_X => (size, UVec2::new(w - x, y)),
+            Self::MIRROR_Y => (size, UVec2::new(x, h - y)),
+            Self::MIRROR_DIAGONAL => (UVec2::new(size.y, size.x), UVec2::new(y, x)),
+            Self::MIRROR_ANTI_DIAGONAL => (UVec2::new(size.y, size.x), UVec2::new(h - y, w - x)),
+            _ => (size, index),
+        }
+    }
+}
+
+/// A hand made map that the wfc rules can be learned from.
+/// Only square tilemaps are supported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WfcSample {
+    size: UVec2,
+    data: Vec<u8>,
+}
+
+impl WfcSample {
+    /// `data` is row major with `(0, 0)` at the bottom left corner.
+    /// The values are the element indices, which should be less than 128.
+    pub fn new(size: UVec2, data: Vec<u8>) -> Self {
+        assert_eq!(
+            data.len(),
+            (size.x * size.y) as usize,
+            "The length of the data doesn't match the size!"
+        );
+        Self { size, data }
+    }
+
+    #[inline]
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    #[inline]
+    pub fn get(&self, index: IVec2) -> Option<u8> {
+        if index.x < 0
+            || index.y < 0
+            || index.x >= self.size.x as i32
+            || index.y >= self.size.y as i32
+        {
+            return None;
+        }
+        Some(self.data[(index.y * self.size.x as i32 + index.x) as usize])
+    }
+
+    /// Returns the sample itself followed by every variant enabled in `symmetry`.
+    /// Variants that are identical to an earlier one are skipped.
+    pub fn variants(&self, symmetry: WfcSymmetry) -> Vec<WfcSample> {
+        let mut variants = vec![self.clone()];
+        for variant in symmetry.iter() {
+            let mut data = vec![0; self.data.len()];
+            let mut size = self.size;
+            for y in 0..self.size.y {
+                for x in 0..self.size.x {
+                    let (s, index) = variant.transform(self.size, UVec2::new(x, y));
+                    size = s;
+                    data[(index.y * s.x + index.x) as usize] =
+                        self.data[(y * self.size.x + x) as usize];
+                }
+            }
+
+            let sample = WfcSample { size, data };
+            if !variants.contains(&sample) {
+                variants.push(sample);
+            }
+        }
+        variants
+    }
+}
+
 #[derive(Default, Clone, PartialEq, Eq, Debug, Reflect)]
 pub enum WfcMode {
     #[default]
@@ -248,6 +528,12 @@ pub struct WfcRunner {
     max_retrace_factor: u32,
     max_retrace_time: u32,
     max_history: usize,
+    /// The element each rule element generates. Only used by the overlapping model.
+    outputs: Option<Vec<u8>>,
+    wrap: bool,
+    fixed: Vec<(UVec2, u8)>,
+    #[reflect(ignore)]
+    cancel: Option<CancelToken>,
 }
 
 impl WfcRunner {
@@ -263,9 +549,61 @@ impl WfcRunner {
             max_retrace_factor: size.ilog10().clamp(2, 16),
             max_retrace_time: size.ilog10().clamp(2, 16) * 100,
             max_history: (size.ilog10().clamp(1, 8) * 20) as usize,
+            outputs: None,
+            wrap: false,
+            fixed: Vec::new(),
+            cancel: None,
         }
     }
 
+    /// Run the overlapping model. The patterns are already weighted by
+    /// how many times they appear in the sample, so you can't set the weights
+    /// or the sampler.
+    ///
+    /// The output only contains the bottom left corner of each pattern,
+    /// so the patterns at the top and right edges are not complete unless
+    /// wrapping is enabled.
+    pub fn new_overlapping(rules: WfcOverlappingRules, area: TileArea, seed: Option<u64>) -> Self {
+        let outputs = (0..rules.pattern_count())
+            .map(|p| rules.output_of(p).unwrap())
+            .collect();
+        let mut runner = Self::new(TilemapType::Square, WfcRules(rules.conn_rules), area, seed);
+        runner.mode = WfcMode::Weighted(rules.weights);
+        runner.outputs = Some(outputs);
+        runner
+    }
+
+    /// Make the output tileable, so the elements at the edges have to
+    /// fit the elements at the opposite edges.
+    ///
+    /// Hexagonal tilemaps are not supported.
+    pub fn with_wrapping(mut self) -> Self {
+        assert!(
+            !matches!(self.ty, TilemapType::Hexagonal(_)),
+            "Wrapping is not supported for hexagonal tilemaps"
+        );
+        self.wrap = true;
+        self
+    }
+
+    /// Pre-place an element, like an entrance that must be at a certain tile.
+    /// The `index` is relative to the origin of the area.
+    ///
+    /// The generation fails if the fixed elements conflict with the rules.
+    pub fn with_fixed_element(mut self, index: UVec2, element: u8) -> Self {
+        self.fixed.push((index, element));
+        self
+    }
+
+    /// Stop the generation early once the token is cancelled.
+    /// No `WfcData` will be inserted then.
+    ///
+    /// In multi-threaded mode, the generation also stops if the entity is despawned.
+    pub fn with_cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
     /// Set the weights of the tiles.
     /// The length of the weights should be the same as the length of the rule.
     pub fn with_weights(mut self, weights_path: String) -> Self {
@@ -438,6 +776,9 @@ pub struct WfcGrid {
     max_retrace_time: u32,
     retraced_time: u32,
     sampler: Option<Box<dyn Fn(&WfcElement, &mut StdRng) -> u8 + Send + Sync>>,
+    outputs: Option<Vec<u8>>,
+    wrap: bool,
+    cancel: Option<CancelToken>,
 }
 
 impl WfcGrid {
@@ -462,7 +803,28 @@ impl WfcGrid {
             }
         }
 
-        WfcGrid {
+        let mut fixed = Vec::with_capacity(runner.fixed.len());
+        for (index, element) in runner.fixed.iter() {
+            let Some(elem) = elements.get_mut(index) else {
+                warn!("Fixed element at {} is out of the area!", index);
+                continue;
+            };
+            let psbs = match &runner.outputs {
+                Some(outputs) => outputs
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, output)| *output == element)
+                    .fold(0, |psbs, (pattern, _)| psbs | 1 << pattern),
+                None => 1 << element,
+            };
+
+            uncollapsed.remove(&(elem.psbs.count_ones() as u8, *index));
+            elem.psbs &= psbs;
+            uncollapsed.insert((elem.psbs.count_ones() as u8, *index));
+            fixed.push(*index);
+        }
+
+        let mut grid = WfcGrid {
             mode: runner.mode.clone(),
             area: runner.area,
             conn_rules: runner.conn_rules.clone(),
@@ -481,7 +843,32 @@ impl WfcGrid {
             max_retrace_time: runner.max_retrace_time,
             retraced_time: 0,
             sampler: runner.sampler.take(),
+            outputs: runner.outputs.clone(),
+            wrap: runner.wrap,
+            cancel: runner.cancel.clone(),
+        };
+
+        for index in fixed {
+            if grid.elements[&index].psbs == 0 {
+                grid.retraced_time = grid.max_retrace_time;
+                break;
+            }
+            grid.constrain(index);
+        }
+
+        grid
+    }
+
+    /// Collapse until the generation is finished or failed.
+    /// Returns `None` if failed or cancelled.
+    pub fn run(&mut self) -> Option<WfcData> {
+        while self.remaining > 0 && self.retraced_time < self.max_retrace_time {
+            if self.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+                return None;
+            }
+            self.collapse();
         }
+        self.generate_data()
     }
 
     pub fn collapse(&mut self) {
@@ -535,7 +922,17 @@ impl WfcGrid {
             spreaded.insert(cur_center);
 
             let cur_elem = self.elements.get(&cur_center).cloned().unwrap();
-            let neis = cur_center.neighbours(self.ty, false);
+            let neis = if self.wrap {
+                let extent = self.area.extent.as_ivec2();
+                cur_center
+                    .as_ivec2()
+                    .neighbours(self.ty, false)
+                    .into_iter()
+                    .map(|nei| nei.map(|n| n.rem_euclid(extent).as_uvec2()))
+                    .collect()
+            } else {
+                cur_center.neighbours(self.ty, false)
+            };
             let nei_count = neis.len();
 
             for dir in 0..nei_count {
@@ -637,7 +1034,11 @@ impl WfcGrid {
 
         let mut data = WfcData::new(self.area);
         self.elements.drain().for_each(|(i, e)| {
-            data.set(i, e.element_index.unwrap());
+            let element = e.element_index.unwrap();
+            match &self.outputs {
+                Some(outputs) => data.set(i, outputs[element as usize]),
+                None => data.set(i, element),
+            }
         });
         Some(data)
     }
@@ -645,7 +1046,20 @@ impl WfcGrid {
 
 #[cfg(feature = "multi-threaded")]
 #[derive(Component)]
-pub struct WfcTask(Task<Option<WfcData>>);
+pub struct WfcTask {
+    task: Task<Option<WfcData>>,
+    /// Cancelled when the task is dropped before finishing, like when the entity is despawned.
+    cancel: Option<CancelToken>,
+}
+
+#[cfg(feature = "multi-threaded")]
+impl Drop for WfcTask {
+    fn drop(&mut self) {
+        if let Some(cancel) = self.cancel.take() {
+            cancel.cancel();
+        }
+    }
+}
 
 #[cfg(feature = "multi-threaded")]
 pub fn wave_function_collapse(
@@ -654,17 +1068,16 @@ pub fn wave_function_collapse(
 ) {
     let thread_pool = AsyncComputeTaskPool::get();
     runner_query.iter_mut().for_each(|(entity, mut runner)| {
+        let cancel = runner.cancel.get_or_insert_with(CancelToken::new).clone();
         let mut wfc_grid = WfcGrid::from_runner(&mut runner);
-        let task = thread_pool.spawn(async move {
-            while wfc_grid.remaining > 0 && wfc_grid.retraced_time < wfc_grid.max_retrace_time {
-                wfc_grid.collapse();
-            }
-            wfc_grid.generate_data()
-        });
+        let task = thread_pool.spawn(async move { wfc_grid.run() });
 
         commands
             .entity(entity)
-            .insert(WfcTask(task))
+            .insert(WfcTask {
+                task,
+                cancel: Some(cancel),
+            })
             .remove::<WfcRunner>();
     });
 }
@@ -675,12 +1088,7 @@ pub fn wave_function_collapse_single_threaded(
     mut runner_query: Query<(Entity, &mut WfcRunner)>,
 ) {
     runner_query.iter_mut().for_each(|(entity, mut runner)| {
-        let mut wfc_grid = WfcGrid::from_runner(&mut runner);
-
-        while wfc_grid.remaining > 0 && wfc_grid.retraced_time < wfc_grid.max_retrace_time {
-            wfc_grid.collapse();
-        }
-        let data = wfc_grid.generate_data();
+        let data = WfcGrid::from_runner(&mut runner).run();
 
         commands.entity(entity).remove::<WfcRunner>();
         if let Some(data) = data {
@@ -692,7 +1100,8 @@ pub fn wave_function_collapse_single_threaded(
 #[cfg(feature = "multi-threaded")]
 pub fn wfc_data_assigner(mut commands: Commands, mut tasks_query: Query<(Entity, &mut WfcTask)>) {
     tasks_query.iter_mut().for_each(|(entity, mut task)| {
-        if let Some(data) = bevy::tasks::block_on(futures_lite::future::poll_once(&mut task.0)) {
+        if let Some(data) = bevy::tasks::block_on(futures_lite::future::poll_once(&mut task.task)) {
+            task.cancel = None;
             let mut entity = commands.entity(entity);
             entity.remove::<WfcTask>();
             if let Some(data) = data {
@@ -711,14 +1120,17 @@ pub fn wfc_applier(
         Option<&mut TilemapStorage>,
         &WfcData,
         &WfcSource,
+        Option<&TilemapConstraints>,
     )>,
     mut path_tilemaps: ResMut<PathTilemaps>,
-    #[cfg(feature = "physics")] mut physics_tilemaps_query: Query<
+    spawn_budget: Res<TileSpawnBudget>,
+    mut spawn_queue: ResMut<TileSpawnQueue>,
+    #[cfg(feature = "physics_core")] mut physics_tilemaps_query: Query<
         &mut crate::tilemap::physics::PhysicsTilemap,
     >,
 ) {
     tilemaps_query.iter_mut().for_each(
-        |(entity, slot_size, tile_render_size,mut tilemap_storage, wfc_data, source)| match source {
+        |(entity, slot_size, tile_render_size,mut tilemap_storage, wfc_data, source, constraints)| match source {
             WfcSource::SingleTile(tiles) => {
                 let tilemap = tilemap_storage.as_mut().unwrap_or_else(|| {
                     panic!("SingleTile source requires a tilemap on the entity!")
@@ -726,10 +1138,30 @@ pub fn wfc_applier(
 
                 for (i, e) in wfc_data.data.iter().enumerate() {
                     let ser_tile = tiles.get(*e as usize).unwrap();
-                    tilemap.set(
+                    if constraints.is_some_and(|c| c.is_locked(wfc_data.elem_idx_to_grid(i))) {
+                        continue;
+                    }
+                    if spawn_budget.is_limited() {
+                        spawn_queue.set(
+                            entity,
+                            wfc_data.elem_idx_to_grid(i),
+                            ser_tile.clone(),
+                        );
+                    } else {
+                        tilemap.set(
+                            &mut commands,
+                            wfc_data.elem_idx_to_grid(i),
+                            ser_tile.clone(),
+                        );
+                    }
+                }
+
+                if let Some(constraints) = constraints {
+                    constraints.apply(
                         &mut commands,
-                        wfc_data.elem_idx_to_grid(i),
-                        ser_tile.clone().into(),
+                        entity,
+                        tilemap,
+                        spawn_budget.is_limited().then_some(spawn_queue.as_mut()),
                     );
                 }
 
@@ -747,7 +1179,15 @@ pub fn wfc_applier(
                     let p = &patterns.get(*e as usize);
                     let origin =
                         (wfc_data.elem_idx_to_grid(i) + wfc_data.area.origin) * p.tiles.aabb.size();
-                    tilemap.fill_with_buffer(&mut commands, origin, p.tiles.clone());
+                    let mut tiles = p.tiles.clone();
+                    if let Some(constraints) = constraints {
+                        tiles.tiles.retain(|index, _| !constraints.is_locked(origin + *index));
+                    }
+                    if spawn_budget.is_limited() {
+                        spawn_queue.fill_with_buffer(entity, origin, tiles);
+                    } else {
+                        tilemap.fill_with_buffer(&mut commands, origin, tiles);
+                    }
 
                     if let Some(tilemap) = path_tilemaps.get_mut(entity) {
                         #[cfg(feature = "multi-threaded")]
@@ -758,7 +1198,7 @@ pub fn wfc_applier(
                         warn!("Skipping algorithm layers as the tilemap does not have a PathTilemap component!");
                     }
 
-                    #[cfg(feature = "physics")]
+                    #[cfg(feature = "physics_core")]
                     if let Ok(mut tilemap) = physics_tilemaps_query.get_mut(entity) {
                         match &p.physics_tiles {
                             SerializablePhysicsSource::Data(data) => {
@@ -773,6 +1213,15 @@ pub fn wfc_applier(
                     }
                 });
 
+                if let Some(constraints) = constraints {
+                    constraints.apply(
+                        &mut commands,
+                        entity,
+                        tilemap,
+                        spawn_budget.is_limited().then_some(spawn_queue.as_mut()),
+                    );
+                }
+
                 commands
                     .entity(entity)
                     .remove::<WfcData>()
@@ -838,7 +1287,7 @@ pub fn wfc_applier(
                             path_tilemaps.insert(layer_entity, path_tilemap);
                         }
 
-                        #[cfg(feature = "physics")]
+                        #[cfg(feature = "physics_core")]
                         match &layer.physics_tiles {
                             SerializablePhysicsSource::Data(data) => {
                                 if !data.data.is_empty() {
@@ -914,3 +1363,62 @@ pub fn ldtk_wfc_helper(
             _ => {}
         });
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rules_from_sample() {
+        let sample = WfcSample::new(UVec2::new(2, 1), vec![0, 1]);
+
+        let rules = WfcRules::from_sample(&sample, WfcSymmetry::NONE);
+        assert_eq!(rules.0, vec![vec![0, 0b10, 0, 0], vec![0, 0, 0b01, 0]]);
+
+        // Rotated clockwise, 0 is on top of 1.
+        let rules = WfcRules::from_sample(&sample, WfcSymmetry::ROTATE_90);
+        assert_eq!(
+            rules.0,
+            vec![vec![0, 0b10, 0, 0b10], vec![0b01, 0, 0b01, 0]]
+        );
+
+        // Every direction is covered, and identical variants are skipped.
+        assert_eq!(sample.variants(WfcSymmetry::ALL).len(), 4);
+        let rules = WfcRules::from_sample(&sample, WfcSymmetry::ALL);
+        assert!(rules.0.iter().flatten().all(|r| *r == 0b10 || *r == 0b01));
+    }
+
+    fn run(runner: WfcRunner) -> Option<WfcData> {
+        WfcGrid::from_runner(&mut { runner }).run()
+    }
+
+    #[test]
+    fn test_overlapping_model() {
+        let checkerboard = WfcSample::new(
+            UVec2::splat(4),
+            (0..16).map(|i| ((i % 4 + i / 4) % 2) as u8).collect(),
+        );
+        let rules = WfcOverlappingRules::from_sample(&checkerboard, 2, WfcSymmetry::ALL, true);
+        assert_eq!(rules.pattern_count(), 2);
+
+        let area = TileArea::new(IVec2::ZERO, UVec2::splat(6));
+        let runner = WfcRunner::new_overlapping(rules.clone(), area, Some(0))
+            .with_fixed_element(UVec2::ZERO, 1);
+        let data = run(runner).unwrap();
+        for y in 0..6 {
+            for x in 0..6 {
+                assert_eq!(data.get(UVec2 { x, y }), Some(((x + y + 1) % 2) as u8));
+            }
+        }
+
+        // A checkerboard with an odd size can't wrap around.
+        let area = TileArea::new(IVec2::ZERO, UVec2::splat(5));
+        let runner = WfcRunner::new_overlapping(rules.clone(), area, Some(0)).with_wrapping();
+        assert!(run(runner).is_none());
+
+        let token = CancelToken::new();
+        token.cancel();
+        let runner = WfcRunner::new_overlapping(rules, area, Some(0)).with_cancel_token(token);
+        assert!(run(runner).is_none());
+    }
+}
@@ -2,9 +2,13 @@ use bevy::{ecs::system::Query, gizmos::gizmos::Gizmos, math::Vec2, render::color
 
 use crate::{
     math::{aabb::Aabb2d, CameraAabb2d},
-    tilemap::map::{
-        TilePivot, TilemapAabbs, TilemapAxisFlip, TilemapSlotSize, TilemapStorage,
-        TilemapTransform, TilemapType,
+    tilemap::{
+        coordinates,
+        flow::{TilemapFlowArrows, TilemapFlowField},
+        map::{
+            TilePivot, TilemapAabbs, TilemapAxisFlip, TilemapSlotSize, TilemapStorage,
+            TilemapTransform, TilemapType,
+        },
     },
 };
 
@@ -23,7 +27,7 @@ pub fn draw_chunk_aabb(
     )>,
 ) {
     for (ty, tile_pivot, axis_flip, slot_size, transform, storage) in tilemaps.iter() {
-        storage.storage.chunks.keys().for_each(|chunk| {
+        storage.storage.chunk_indices().for_each(|chunk| {
             let aabb = Aabb2d::from_tilemap(
                 *chunk,
                 storage.storage.chunk_size,
@@ -43,6 +47,36 @@ pub fn draw_chunk_aabb(
     }
 }
 
+pub fn draw_flow_arrows(
+    mut gizmos: Gizmos,
+    tilemaps: Query<(
+        &TilemapType,
+        &TilePivot,
+        &TilemapSlotSize,
+        &TilemapTransform,
+        &TilemapFlowField,
+        &TilemapFlowArrows,
+    )>,
+) {
+    for (ty, tile_pivot, slot_size, transform, flow, arrows) in tilemaps.iter() {
+        flow.iter().for_each(|(index, vector)| {
+            let center = coordinates::index_to_world(
+                index,
+                *ty,
+                &TilemapTransform::IDENTITY,
+                tile_pivot.0,
+                slot_size.0,
+            ) + slot_size.0 / 2.;
+            let end = center + vector * slot_size.0 * arrows.scale;
+            gizmos.arrow_2d(
+                transform.transform_point(center),
+                transform.transform_point(end),
+                arrows.color,
+            );
+        });
+    }
+}
+
 pub fn draw_tilemap_aabb(mut gizmos: Gizmos, tilemaps: Query<&TilemapAabbs>) {
     tilemaps.iter().for_each(|aabb| {
         gizmos.rect_2d(
@@ -60,22 +94,24 @@ pub fn draw_path(
     path_query: Query<&Path>,
     tilemaps: Query<(
         &TilemapType,
+        &TilemapAxisFlip,
         &TilemapTransform,
         &TilePivot,
         &TilemapSlotSize,
     )>,
 ) {
     for path in path_query.iter() {
-        let (ty, transform, pivot, slot_size) = tilemaps.get(path.tilemap()).unwrap();
+        let (ty, axis_flip, transform, pivot, slot_size) = tilemaps.get(path.tilemap()).unwrap();
 
         for node in path.iter() {
             gizmos.circle_2d(
-                crate::tilemap::coordinates::index_to_world(
+                crate::tilemap::coordinates::index_to_world_flipped(
                     *node,
                     *ty,
                     transform,
                     pivot.0,
                     slot_size.0,
+                    *axis_flip,
                 ),
                 10.,
                 Color::YELLOW_GREEN,
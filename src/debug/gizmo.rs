@@ -0,0 +1,338 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::{Event, EventWriter},
+        query::With,
+        system::{Query, Res, ResMut, Resource},
+    },
+    gizmos::gizmos::Gizmos,
+    input::{mouse::MouseButton, ButtonInput},
+    math::{IVec2, Vec2},
+    reflect::Reflect,
+    render::{camera::Camera, color::Color},
+    transform::components::GlobalTransform,
+    window::{PrimaryWindow, Window},
+};
+
+use crate::{
+    math::TileArea,
+    tilemap::{
+        coordinates,
+        map::{TilePivot, TilemapAxisFlip, TilemapSlotSize, TilemapTransform, TilemapType},
+    },
+};
+
+/// The position of the cursor in world space, using the first active camera.
+#[derive(Resource, Default, Debug, Clone, Copy)]
+pub struct GizmoCursor(pub Option<Vec2>);
+
+/// Insert this on a tilemap to draw interactive editing gizmos for it.
+///
+/// The gizmos never change the tilemap by themselves. Instead, they send `TilemapEditIntent`s
+/// and it's up to you to apply them, for example by updating the `TilemapTransform`
+/// and `TilemapGizmos::area`.
+#[derive(Component, Debug, Clone, Reflect)]
+pub struct TilemapGizmos {
+    /// The editable area of the tilemap. Resize handles are placed on its corners.
+    pub area: TileArea,
+    /// The radius of the handles in world units.
+    pub handle_size: f32,
+    pub color: Color,
+    pub cursor_color: Color,
+    #[reflect(ignore)]
+    pub(crate) hovered: Option<IVec2>,
+    #[reflect(ignore)]
+    pub(crate) dragging: Option<GizmoHandle>,
+}
+
+impl TilemapGizmos {
+    pub fn new(area: TileArea) -> Self {
+        Self {
+            area,
+            handle_size: 8.,
+            color: Color::ORANGE,
+            cursor_color: Color::WHITE,
+            hovered: None,
+            dragging: None,
+        }
+    }
+
+    /// The tile under the cursor.
+    #[inline]
+    pub fn hovered(&self) -> Option<IVec2> {
+        self.hovered
+    }
+
+    /// The handle being dragged.
+    #[inline]
+    pub fn dragging(&self) -> Option<GizmoHandle> {
+        self.dragging
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoHandle {
+    /// The origin of the tilemap.
+    Origin,
+    /// The corner at `area.origin`.
+    Min,
+    /// The corner at `area.dest`.
+    Max,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub enum TilemapEditIntent {
+    /// The origin handle is dragged to `translation`.
+    Move { tilemap: Entity, translation: Vec2 },
+    /// A resize handle is dragged and the area should become `area`.
+    Resize { tilemap: Entity, area: TileArea },
+    /// The cursor moved onto another tile.
+    Hover { tilemap: Entity, index: IVec2 },
+    /// A mouse button is pressed on a tile, or the cursor moved onto
+    /// another tile while the button is held.
+    Press {
+        tilemap: Entity,
+        index: IVec2,
+        button: MouseButton,
+    },
+}
+
+/// The corners of the slot outline from `min` to `max`, both inclusive.
+pub(crate) fn outline(
+    min: IVec2,
+    max: IVec2,
+    ty: TilemapType,
+    transform: &TilemapTransform,
+    pivot: Vec2,
+    slot_size: Vec2,
+    axis_flip: TilemapAxisFlip,
+) -> [Vec2; 5] {
+    let offset = transform.apply_rotation(coordinates::axis_flip_offset(ty, slot_size, axis_flip));
+    let corner = |index: IVec2| {
+        coordinates::index_to_world(
+            coordinates::flip_corner(index, axis_flip),
+            ty,
+            transform,
+            pivot,
+            slot_size,
+        ) - offset
+    };
+    let max = max + 1;
+    [
+        corner(min),
+        corner(IVec2::new(max.x, min.y)),
+        corner(max),
+        corner(IVec2::new(min.x, max.y)),
+        corner(min),
+    ]
+}
+
+/// The nearest slot corner to the world position.
+fn corner_index(
+    world: Vec2,
+    ty: TilemapType,
+    transform: &TilemapTransform,
+    pivot: Vec2,
+    slot_size: Vec2,
+    axis_flip: TilemapAxisFlip,
+) -> IVec2 {
+    let offset = transform.apply_rotation(coordinates::axis_flip_offset(ty, slot_size, axis_flip));
+    coordinates::flip_corner(
+        coordinates::world_to_index(world + offset, ty, transform, pivot + 0.5, slot_size),
+        axis_flip,
+    )
+}
+
+pub fn gizmo_cursor_updater(
+    mut cursor: ResMut<GizmoCursor>,
+    windows_query: Query<&Window, With<PrimaryWindow>>,
+    cameras_query: Query<(&Camera, &GlobalTransform)>,
+) {
+    cursor.0 = windows_query
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position())
+        .and_then(|pos| {
+            cameras_query
+                .iter()
+                .find(|(camera, _)| camera.is_active)
+                .and_then(|(camera, transform)| camera.viewport_to_world_2d(transform, pos))
+        });
+}
+
+pub fn gizmo_interaction(
+    cursor: Res<GizmoCursor>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut tilemaps_query: Query<(
+        Entity,
+        &mut TilemapGizmos,
+        &TilemapType,
+        &TilemapAxisFlip,
+        &TilemapTransform,
+        &TilePivot,
+        &TilemapSlotSize,
+    )>,
+    mut intents: EventWriter<TilemapEditIntent>,
+) {
+    let Some(cursor) = cursor.0 else {
+        return;
+    };
+    let mut any_dragging = tilemaps_query
+        .iter()
+        .any(|(_, gizmos, ..)| gizmos.dragging.is_some());
+
+    tilemaps_query.iter_mut().for_each(
+        |(tilemap, mut gizmos, ty, axis_flip, transform, pivot, slot_size)| {
+            if !mouse.pressed(MouseButton::Left) {
+                gizmos.dragging = None;
+            }
+
+            // Pick a handle.
+            if mouse.just_pressed(MouseButton::Left) && !any_dragging {
+                let area = gizmos.area;
+                let corners = outline(
+                    area.origin,
+                    area.dest,
+                    *ty,
+                    transform,
+                    pivot.0,
+                    slot_size.0,
+                    *axis_flip,
+                );
+                gizmos.dragging = [
+                    (GizmoHandle::Origin, transform.translation),
+                    (GizmoHandle::Min, corners[0]),
+                    (GizmoHandle::Max, corners[2]),
+                ]
+                .into_iter()
+                .find(|(_, pos)| pos.distance(cursor) <= gizmos.handle_size)
+                .map(|(handle, _)| handle);
+                any_dragging |= gizmos.dragging.is_some();
+            }
+
+            match gizmos.dragging {
+                Some(GizmoHandle::Origin) => {
+                    if transform.translation != cursor {
+                        intents.send(TilemapEditIntent::Move {
+                            tilemap,
+                            translation: cursor,
+                        });
+                    }
+                }
+                Some(handle) => {
+                    let corner =
+                        corner_index(cursor, *ty, transform, pivot.0, slot_size.0, *axis_flip);
+                    let area = gizmos.area;
+                    let area = if handle == GizmoHandle::Min {
+                        TileArea::from_min_max(corner.min(area.dest), area.dest)
+                    } else {
+                        TileArea::from_min_max(area.origin, (corner - 1).max(area.origin))
+                    };
+                    if area.origin != gizmos.area.origin || area.dest != gizmos.area.dest {
+                        intents.send(TilemapEditIntent::Resize { tilemap, area });
+                    }
+                }
+                None => {
+                    let index = coordinates::world_to_index_flipped(
+                        cursor,
+                        *ty,
+                        transform,
+                        pivot.0,
+                        slot_size.0,
+                        *axis_flip,
+                    );
+                    let moved = gizmos.hovered != Some(index);
+                    if moved {
+                        gizmos.hovered = Some(index);
+                        intents.send(TilemapEditIntent::Hover { tilemap, index });
+                    }
+
+                    if any_dragging {
+                        return;
+                    }
+                    mouse
+                        .get_pressed()
+                        .filter(|button| moved || mouse.just_pressed(**button))
+                        .for_each(|button| {
+                            intents.send(TilemapEditIntent::Press {
+                                tilemap,
+                                index,
+                                button: *button,
+                            });
+                        });
+                }
+            }
+        },
+    );
+}
+
+pub fn draw_gizmos(
+    mut gizmos: Gizmos,
+    tilemaps_query: Query<(
+        &TilemapGizmos,
+        &TilemapType,
+        &TilemapAxisFlip,
+        &TilemapTransform,
+        &TilePivot,
+        &TilemapSlotSize,
+    )>,
+) {
+    tilemaps_query.iter().for_each(
+        |(tilemap_gizmos, ty, axis_flip, transform, pivot, slot_size)| {
+            let area = tilemap_gizmos.area;
+            let boundary = outline(
+                area.origin,
+                area.dest,
+                *ty,
+                transform,
+                pivot.0,
+                slot_size.0,
+                *axis_flip,
+            );
+            gizmos.linestrip_2d(boundary, tilemap_gizmos.color);
+
+            let handle_color = |handle: GizmoHandle| {
+                if tilemap_gizmos.dragging == Some(handle) {
+                    tilemap_gizmos.cursor_color
+                } else {
+                    tilemap_gizmos.color
+                }
+            };
+            gizmos.circle_2d(
+                transform.translation,
+                tilemap_gizmos.handle_size,
+                handle_color(GizmoHandle::Origin),
+            );
+            gizmos.rect_2d(
+                boundary[0],
+                0.,
+                Vec2::splat(tilemap_gizmos.handle_size * 2.),
+                handle_color(GizmoHandle::Min),
+            );
+            gizmos.rect_2d(
+                boundary[2],
+                0.,
+                Vec2::splat(tilemap_gizmos.handle_size * 2.),
+                handle_color(GizmoHandle::Max),
+            );
+
+            if tilemap_gizmos.dragging.is_none() {
+                if let Some(index) = tilemap_gizmos.hovered {
+                    gizmos.linestrip_2d(
+                        outline(
+                            index,
+                            index,
+                            *ty,
+                            transform,
+                            pivot.0,
+                            slot_size.0,
+                            *axis_flip,
+                        ),
+                        tilemap_gizmos.cursor_color,
+                    );
+                }
+            }
+        },
+    );
+}
@@ -1,10 +1,11 @@
 use bevy::{
     app::{Plugin, Update},
-    ecs::system::Resource,
+    ecs::{schedule::IntoSystemConfigs, system::Resource},
     math::Vec2,
 };
 
 pub mod drawing;
+pub mod gizmo;
 
 pub struct EntiTilesDebugPlugin;
 
@@ -17,13 +18,25 @@ impl Plugin for EntiTilesDebugPlugin {
                 drawing::draw_tilemap_aabb,
                 drawing::draw_axis,
                 drawing::draw_camera_aabb,
+                drawing::draw_flow_arrows,
                 // #[cfg(feature = "algorithm")]
                 // drawing::draw_path,
                 #[cfg(feature = "serializing")]
                 drawing::draw_updater_aabbs,
+                crate::tilemap::validation::tilemap_validator,
+                (
+                    gizmo::gizmo_cursor_updater,
+                    gizmo::gizmo_interaction,
+                    gizmo::draw_gizmos,
+                )
+                    .chain(),
             ),
         );
 
+        app.init_resource::<gizmo::GizmoCursor>()
+            .add_event::<gizmo::TilemapEditIntent>()
+            .register_type::<gizmo::TilemapGizmos>();
+
         #[cfg(feature = "debug")]
         app.init_resource::<CameraAabbScale>();
     }
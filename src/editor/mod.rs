@@ -0,0 +1,134 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{entity::Entity, schedule::IntoSystemConfigs, system::Resource},
+    math::IVec2,
+    reflect::Reflect,
+};
+
+use crate::{debug::gizmo, math::TileArea, tilemap::tile::TileJitter};
+
+pub mod systems;
+
+/// A simple in-game tile editor.
+///
+/// Set `TileEditor::tilemap` to the tilemap you want to edit. The tilemap needs
+/// `TilemapGizmos` to receive the inputs, and a `TilemapName` to be saved.
+/// This plugin requires `EntiTilesPlugin` with the `debug` feature.
+///
+/// | Input                 | Action                                      |
+/// | --------------------- | ------------------------------------------- |
+/// | `B` `E` `R` `F`       | Paint, erase, rect and fill tool            |
+/// | `1` ~ `4`             | Switch the layer                            |
+/// | `[` `]`               | Select the previous or next texture         |
+/// | Left mouse            | Use the tool                                |
+/// | Right mouse           | Erase                                       |
+/// | Middle mouse          | Pick the texture under the cursor           |
+/// | `Ctrl + Z` `Ctrl + Y` | Undo and redo                               |
+/// | `Ctrl + S`            | Save the tilemap to `TileEditor::save_path` |
+pub struct EntiTilesEditorPlugin;
+
+impl Plugin for EntiTilesEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                systems::editor_input,
+                systems::intent_applier,
+                systems::draw_rect_preview,
+            )
+                .chain()
+                .after(gizmo::gizmo_interaction),
+        );
+
+        app.init_resource::<TileEditor>();
+
+        app.register_type::<TileEditor>();
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum EditorTool {
+    #[default]
+    Paint,
+    Erase,
+    /// Drag to fill a rectangle.
+    Rect,
+    /// Flood fill the tiles with the same texture on the current layer.
+    /// The fill is limited to the area of the `TilemapGizmos`.
+    Fill,
+}
+
+#[derive(Resource, Debug, Clone, Reflect)]
+pub struct TileEditor {
+    /// The tilemap being edited.
+    pub tilemap: Option<Entity>,
+    pub tool: EditorTool,
+    pub layer: usize,
+    /// The selected texture index in the tilemap texture.
+    pub texture_index: u32,
+    /// Randomly move and rotate the painted tiles, for scatter layers like stones
+    /// and grass tufts. `None` keeps the offsets of the tiles.
+    pub jitter: Option<TileJitter>,
+    /// Where to save the tilemap. See `TilemapSaver::path`.
+    pub save_path: String,
+    pub(crate) rect_start: Option<IVec2>,
+}
+
+impl Default for TileEditor {
+    fn default() -> Self {
+        Self {
+            tilemap: None,
+            tool: EditorTool::default(),
+            layer: 0,
+            texture_index: 0,
+            jitter: None,
+            save_path: "generated/editor".to_string(),
+            rect_start: None,
+        }
+    }
+}
+
+/// Collect the tiles connected to `seed` that have the same texture,
+/// without leaving the `area`.
+pub(crate) fn flood_fill(
+    seed: IVec2,
+    area: TileArea,
+    texture: impl Fn(IVec2) -> Option<i32>,
+) -> Vec<IVec2> {
+    let contains = |index: IVec2| index.cmpge(area.origin).all() && index.cmple(area.dest).all();
+    let Some(target) = texture(seed).filter(|_| contains(seed)) else {
+        return Vec::new();
+    };
+
+    let mut visited = bevy::utils::HashSet::from([seed]);
+    let mut stack = vec![seed];
+    let mut result = Vec::new();
+    while let Some(index) = stack.pop() {
+        result.push(index);
+        for neighbor in [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y].map(|d| index + d) {
+            if contains(neighbor) && texture(neighbor) == Some(target) && visited.insert(neighbor) {
+                stack.push(neighbor);
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_flood_fill() {
+        // A wall at x = 2 splits the area.
+        let texture = |index: IVec2| Some(if index.x == 2 { 1 } else { -1 });
+        let area = TileArea::new(IVec2::ZERO, bevy::math::UVec2::splat(5));
+
+        let filled = flood_fill(IVec2::ZERO, area, texture);
+        assert_eq!(filled.len(), 10);
+        assert!(filled.iter().all(|index| index.x < 2));
+
+        assert_eq!(flood_fill(IVec2::new(2, 3), area, texture).len(), 5);
+        assert!(flood_fill(IVec2::new(5, 0), area, texture).is_empty());
+    }
+}
@@ -0,0 +1,284 @@
+use bevy::{
+    ecs::{
+        event::EventReader,
+        system::{Commands, Query, Res, ResMut, SystemParam},
+    },
+    gizmos::gizmos::Gizmos,
+    input::{keyboard::KeyCode, mouse::MouseButton, ButtonInput},
+    math::IVec2,
+};
+
+use crate::{
+    debug::gizmo::{self, TilemapEditIntent, TilemapGizmos},
+    math::TileArea,
+    serializing::map::{
+        save::{TilemapSaver, TilemapSaverMode, TilemapSaverRange},
+        TilemapLayer,
+    },
+    tilemap::{
+        map::{
+            TilePivot, TilemapAxisFlip, TilemapSlotSize, TilemapStorage, TilemapTexture,
+            TilemapTransform, TilemapType,
+        },
+        tile::{Tile, TileBuilder, TileLayer, TileTexture},
+        transaction::{TilemapEditJournal, TilemapTransactions},
+    },
+    MAX_LAYER_COUNT,
+};
+
+use super::{flood_fill, EditorTool, TileEditor};
+
+/// The texture of the tile on the layer, `-1` for empty layers and missing tiles.
+/// Returns `None` for animated tiles.
+fn layer_texture(tile: Option<&Tile>, layer: usize) -> Option<i32> {
+    match tile.map(|t| &t.texture) {
+        None => Some(-1),
+        Some(TileTexture::Static(layers)) => {
+            Some(layers.get(layer).map(|l| l.texture_index).unwrap_or(-1))
+        }
+        Some(TileTexture::Animated(_)) => None,
+    }
+}
+
+/// The tile with the selected texture painted on the current layer.
+fn painted(editor: &TileEditor, index: IVec2, tile: Option<&Tile>) -> TileBuilder {
+    let mut builder = match tile {
+        Some(tile) if matches!(tile.texture, TileTexture::Static(_)) => tile.clone().into(),
+        _ => TileBuilder::new(),
+    };
+    if let Some(jitter) = editor.jitter {
+        builder = builder.with_offset(jitter.sample(index));
+    }
+    builder.with_layer(
+        editor.layer,
+        TileLayer::no_flip(editor.texture_index as i32),
+    )
+}
+
+/// The keyboard and mouse buttons used by the editor.
+#[derive(SystemParam)]
+pub struct EditorButtons<'w> {
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    mouse: Res<'w, ButtonInput<MouseButton>>,
+}
+
+pub fn editor_input(
+    mut commands: Commands,
+    mut editor: ResMut<TileEditor>,
+    mut transactions: ResMut<TilemapTransactions>,
+    mut intents: EventReader<TilemapEditIntent>,
+    buttons: EditorButtons,
+    tilemaps_query: Query<(
+        &TilemapStorage,
+        &TilemapGizmos,
+        Option<&TilemapTexture>,
+        Option<&TilemapEditJournal>,
+    )>,
+    tiles_query: Query<&Tile>,
+) {
+    let Some(tilemap) = editor.tilemap else {
+        intents.clear();
+        return;
+    };
+    let Ok((storage, gizmos, texture, journal)) = tilemaps_query.get(tilemap) else {
+        intents.clear();
+        return;
+    };
+    if journal.is_none() {
+        commands
+            .entity(tilemap)
+            .insert(TilemapEditJournal::default());
+    }
+    let get_tile = |index: IVec2| storage.get(index).and_then(|e| tiles_query.get(e).ok());
+    let (keys, mouse) = (&buttons.keys, &buttons.mouse);
+
+    // Tools and layers.
+    [
+        (KeyCode::KeyB, EditorTool::Paint),
+        (KeyCode::KeyE, EditorTool::Erase),
+        (KeyCode::KeyR, EditorTool::Rect),
+        (KeyCode::KeyF, EditorTool::Fill),
+    ]
+    .into_iter()
+    .filter(|(key, _)| keys.just_pressed(*key))
+    .for_each(|(_, tool)| {
+        editor.tool = tool;
+        editor.rect_start = None;
+    });
+    [
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+    ]
+    .into_iter()
+    .take(MAX_LAYER_COUNT)
+    .enumerate()
+    .filter(|(_, key)| keys.just_pressed(*key))
+    .for_each(|(layer, _)| editor.layer = layer);
+
+    // Palette.
+    if let Some(texture) = texture {
+        let grid = texture.desc().tile_count();
+        let count = grid.x * grid.y;
+        if keys.just_pressed(KeyCode::BracketRight) {
+            editor.texture_index = (editor.texture_index + 1) % count;
+        }
+        if keys.just_pressed(KeyCode::BracketLeft) {
+            editor.texture_index = (editor.texture_index + count - 1) % count;
+        }
+    }
+    if mouse.just_pressed(MouseButton::Middle) {
+        if let Some(index) = gizmos.hovered() {
+            if let Some(texture) = layer_texture(get_tile(index), editor.layer).filter(|t| *t >= 0)
+            {
+                editor.texture_index = texture as u32;
+            }
+        }
+    }
+
+    // Undo, redo and save.
+    if keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        if keys.just_pressed(KeyCode::KeyZ) {
+            transactions.undo(tilemap);
+        }
+        if keys.just_pressed(KeyCode::KeyY) {
+            transactions.redo(tilemap);
+        }
+        if keys.just_pressed(KeyCode::KeyS) {
+            commands.entity(tilemap).insert(TilemapSaver {
+                path: editor.save_path.clone(),
+                mode: TilemapSaverMode::Tilemap,
+                layers: TilemapLayer::all(),
+                texture_path: None,
+                remove_after_save: false,
+                range: TilemapSaverRange::Full,
+            });
+        }
+    }
+
+    // Rect tool.
+    if editor.tool == EditorTool::Rect && gizmos.dragging().is_none() {
+        if mouse.just_pressed(MouseButton::Left) {
+            editor.rect_start = gizmos.hovered();
+        }
+        if mouse.just_released(MouseButton::Left) {
+            if let (Some(start), Some(end)) = (editor.rect_start.take(), gizmos.hovered()) {
+                let area = TileArea::from_min_max(start.min(end), start.max(end));
+                transactions.transaction(tilemap, |tx| {
+                    for y in area.origin.y..=area.dest.y {
+                        for x in area.origin.x..=area.dest.x {
+                            let index = IVec2 { x, y };
+                            tx.set(index, painted(&editor, index, get_tile(index)));
+                        }
+                    }
+                });
+            }
+        }
+    }
+
+    // Other tools.
+    for intent in intents.read() {
+        let TilemapEditIntent::Press {
+            tilemap: pressed,
+            index,
+            button,
+        } = *intent
+        else {
+            continue;
+        };
+        if pressed != tilemap {
+            continue;
+        }
+
+        match (button, editor.tool) {
+            (MouseButton::Right, _) | (MouseButton::Left, EditorTool::Erase)
+                if get_tile(index).is_some() =>
+            {
+                transactions.transaction(tilemap, |tx| {
+                    tx.remove(index);
+                });
+            }
+            (MouseButton::Left, EditorTool::Paint) => {
+                transactions.transaction(tilemap, |tx| {
+                    tx.set(index, painted(&editor, index, get_tile(index)));
+                });
+            }
+            (MouseButton::Left, EditorTool::Fill) => {
+                let tiles = flood_fill(index, gizmos.area, |index| {
+                    layer_texture(get_tile(index), editor.layer)
+                });
+                transactions.transaction(tilemap, |tx| {
+                    tiles.into_iter().for_each(|index| {
+                        tx.set(index, painted(&editor, index, get_tile(index)));
+                    });
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Move and resize the tilemap according to the gizmos.
+pub fn intent_applier(
+    editor: Res<TileEditor>,
+    mut intents: EventReader<TilemapEditIntent>,
+    mut tilemaps_query: Query<(&mut TilemapTransform, &mut TilemapGizmos)>,
+) {
+    for intent in intents.read() {
+        match *intent {
+            TilemapEditIntent::Move {
+                tilemap,
+                translation,
+            } if editor.tilemap == Some(tilemap) => {
+                if let Ok((mut transform, _)) = tilemaps_query.get_mut(tilemap) {
+                    transform.translation = translation;
+                }
+            }
+            TilemapEditIntent::Resize { tilemap, area } if editor.tilemap == Some(tilemap) => {
+                if let Ok((_, mut gizmos)) = tilemaps_query.get_mut(tilemap) {
+                    gizmos.area = area;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn draw_rect_preview(
+    mut gizmos: Gizmos,
+    editor: Res<TileEditor>,
+    tilemaps_query: Query<(
+        &TilemapGizmos,
+        &TilemapType,
+        &TilemapAxisFlip,
+        &TilemapTransform,
+        &TilePivot,
+        &TilemapSlotSize,
+    )>,
+) {
+    let (Some(tilemap), Some(start)) = (editor.tilemap, editor.rect_start) else {
+        return;
+    };
+    let Ok((tilemap_gizmos, ty, axis_flip, transform, pivot, slot_size)) =
+        tilemaps_query.get(tilemap)
+    else {
+        return;
+    };
+    let Some(end) = tilemap_gizmos.hovered() else {
+        return;
+    };
+
+    gizmos.linestrip_2d(
+        gizmo::outline(
+            start.min(end),
+            start.max(end),
+            *ty,
+            transform,
+            pivot.0,
+            slot_size.0,
+            *axis_flip,
+        ),
+        tilemap_gizmos.cursor_color,
+    );
+}
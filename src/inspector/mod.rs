@@ -0,0 +1,288 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        entity::Entity,
+        query::With,
+        system::{Query, ResMut, Resource},
+    },
+    render::camera::Camera,
+    transform::components::GlobalTransform,
+    window::{PrimaryWindow, Window},
+};
+use bevy_egui::{
+    egui::{self, CollapsingHeader, ComboBox, DragValue, Grid, Ui},
+    EguiContexts, EguiPlugin,
+};
+
+use crate::tilemap::{
+    coordinates,
+    map::{
+        TilePivot, TileRenderSize, TilemapAabbs, TilemapAnimations, TilemapAxisFlip, TilemapName,
+        TilemapSlotSize, TilemapStorage, TilemapTexture, TilemapTransform, TilemapType,
+    },
+    tile::{Tile, TileTexture},
+};
+
+/// Ready-made egui panels for inspecting tilemaps during development.
+///
+/// Adds `EguiPlugin` if it's not added yet.
+pub struct EntiTilesInspectorPlugin;
+
+impl Plugin for EntiTilesInspectorPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+
+        app.add_systems(Update, inspector_ui);
+
+        app.init_resource::<TilemapInspector>();
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct TilemapInspector {
+    pub enabled: bool,
+    /// The tilemap being inspected. Can also be selected in the panel.
+    pub tilemap: Option<Entity>,
+}
+
+impl Default for TilemapInspector {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            tilemap: None,
+        }
+    }
+}
+
+fn property(ui: &mut Ui, name: &str, value: impl ToString) {
+    ui.label(name);
+    ui.label(value.to_string());
+    ui.end_row();
+}
+
+pub fn inspector_ui(
+    mut contexts: EguiContexts,
+    mut inspector: ResMut<TilemapInspector>,
+    mut tilemaps_query: Query<(
+        Entity,
+        Option<&TilemapName>,
+        &TilemapType,
+        &TilemapAxisFlip,
+        &mut TilemapTransform,
+        &TilePivot,
+        &TilemapSlotSize,
+        &TileRenderSize,
+        &TilemapStorage,
+        &TilemapAabbs,
+        Option<&TilemapTexture>,
+        Option<&TilemapAnimations>,
+    )>,
+    tiles_query: Query<&Tile>,
+    windows_query: Query<&Window, With<PrimaryWindow>>,
+    cameras_query: Query<(&Camera, &GlobalTransform)>,
+) {
+    if !inspector.enabled {
+        return;
+    }
+
+    let cursor = windows_query
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position())
+        .and_then(|pos| {
+            cameras_query
+                .iter()
+                .find(|(camera, _)| camera.is_active)
+                .and_then(|(camera, transform)| camera.viewport_to_world_2d(transform, pos))
+        });
+    let display_name = |entity: Entity, name: Option<&TilemapName>| match name {
+        Some(name) => format!("{} ({:?})", name.0, entity),
+        None => format!("{:?}", entity),
+    };
+
+    egui::Window::new("Tilemaps").show(contexts.ctx_mut(), |ui| {
+        if inspector
+            .tilemap
+            .map_or(true, |e| !tilemaps_query.contains(e))
+        {
+            inspector.tilemap = tilemaps_query.iter().next().map(|(e, ..)| e);
+        }
+        let Some(selected) = inspector.tilemap else {
+            ui.label("No tilemaps.");
+            return;
+        };
+
+        ComboBox::from_label("Tilemap")
+            .selected_text(display_name(
+                selected,
+                tilemaps_query.get(selected).ok().and_then(|t| t.1),
+            ))
+            .show_ui(ui, |ui| {
+                tilemaps_query.iter().for_each(|(entity, name, ..)| {
+                    ui.selectable_value(
+                        &mut inspector.tilemap,
+                        Some(entity),
+                        display_name(entity, name),
+                    );
+                });
+            });
+
+        let Ok((
+            _,
+            _,
+            ty,
+            axis_flip,
+            mut transform,
+            pivot,
+            slot_size,
+            render_size,
+            storage,
+            aabbs,
+            texture,
+            animations,
+        )) = tilemaps_query.get_mut(selected)
+        else {
+            return;
+        };
+
+        CollapsingHeader::new("Properties")
+            .default_open(true)
+            .show(ui, |ui| {
+                Grid::new("properties").show(ui, |ui| {
+                    property(ui, "Type", format!("{:?}", ty));
+                    ui.label("Translation");
+                    ui.horizontal(|ui| {
+                        ui.add(DragValue::new(&mut transform.translation.x));
+                        ui.add(DragValue::new(&mut transform.translation.y));
+                    });
+                    ui.end_row();
+                    ui.label("Z Index");
+                    ui.add(DragValue::new(&mut transform.z_index).speed(0.1));
+                    ui.end_row();
+                    property(ui, "Rotation", format!("{:?}", transform.rotation));
+                    property(ui, "Axis Flip", format!("{:?}", axis_flip));
+                    property(ui, "Slot Size", slot_size.0);
+                    property(ui, "Render Size", render_size.0);
+                    property(ui, "Pivot", pivot.0);
+                    if let Some(texture) = texture {
+                        property(ui, "Texture Size", texture.desc().size);
+                        property(ui, "Texture Tile Size", texture.desc().tile_size);
+                    }
+                });
+            });
+
+        CollapsingHeader::new("Tile Under Cursor").show(ui, |ui| {
+            let Some(cursor) = cursor else {
+                ui.label("The cursor is outside the window.");
+                return;
+            };
+            let index = coordinates::world_to_index_flipped(
+                cursor,
+                *ty,
+                &transform,
+                pivot.0,
+                slot_size.0,
+                *axis_flip,
+            );
+            Grid::new("tile").show(ui, |ui| {
+                property(ui, "Index", index);
+                let Some(tile) = storage.get(index).and_then(|e| tiles_query.get(e).ok()) else {
+                    property(ui, "Tile", "None");
+                    return;
+                };
+                property(ui, "Chunk", tile.chunk_index);
+                property(ui, "Tint", format!("{:?}", tile.tint));
+                match &tile.texture {
+                    TileTexture::Static(layers) => {
+                        layers.iter().enumerate().for_each(|(i, layer)| {
+                            property(
+                                ui,
+                                &format!("Layer {}", i),
+                                format!("{} {:?}", layer.texture_index, layer.flip),
+                            );
+                        });
+                    }
+                    TileTexture::Animated(anim) => {
+                        property(
+                            ui,
+                            "Animation",
+                            format!(
+                                "start {}, length {}, fps {}",
+                                anim.start(),
+                                anim.length(),
+                                anim.fps()
+                            ),
+                        );
+                    }
+                }
+            });
+        });
+
+        CollapsingHeader::new("Animations").show(ui, |ui| {
+            let Some(animations) = animations.filter(|a| !a.is_empty()) else {
+                ui.label("No animations.");
+                return;
+            };
+            Grid::new("animations").striped(true).show(ui, |ui| {
+                ui.label("Start");
+                ui.label("Frames");
+                ui.label("Fps");
+                ui.end_row();
+                animations.iter().for_each(|anim| {
+                    ui.label(anim.start().to_string());
+                    ui.label(
+                        animations
+                            .get(*anim)
+                            .map(|raw| format!("{:?}", raw.sequence))
+                            .unwrap_or_default(),
+                    );
+                    ui.label(anim.fps().to_string());
+                    ui.end_row();
+                });
+            });
+        });
+
+        CollapsingHeader::new("Chunks").show(ui, |ui| {
+            let chunks = storage.storage.chunk_indices().count();
+            let tiles = storage.storage.iter_some().count();
+            let chunk_size = storage.storage.chunk_size;
+            Grid::new("chunks").show(ui, |ui| {
+                property(ui, "Chunk Size", chunk_size);
+                property(ui, "Chunks", chunks);
+                property(ui, "Tiles", tiles);
+                property(
+                    ui,
+                    "Occupancy",
+                    format!(
+                        "{:.1}%",
+                        tiles as f32 / (chunks.max(1) as u32 * chunk_size * chunk_size) as f32
+                            * 100.
+                    ),
+                );
+                property(
+                    ui,
+                    "Chunk Aabb",
+                    format!("{:?} ~ {:?}", aabbs.chunk_aabb.min, aabbs.chunk_aabb.max),
+                );
+                if let Some(cursor) = cursor {
+                    let index = coordinates::world_to_index_flipped(
+                        cursor,
+                        *ty,
+                        &transform,
+                        pivot.0,
+                        slot_size.0,
+                        *axis_flip,
+                    );
+                    let chunk = storage.storage.transform_index(index).0;
+                    property(
+                        ui,
+                        "Chunk Under Cursor",
+                        format!("{} ({} tiles)", chunk, storage.iter_chunk(chunk).count()),
+                    );
+                }
+            });
+        });
+    });
+}
@@ -4,13 +4,15 @@ use bevy::{
 };
 
 use super::traits::{
-    LdtkEntity, LdtkEntityRegistry, LdtkEntityTag, LdtkEntityTagRegistry, PhantomLdtkEntity,
-    PhantomLdtkEntityTag,
+    LdtkEntity, LdtkEntityRegistry, LdtkEntityTag, LdtkEntityTagRegistry, LdtkLevel,
+    LdtkLevelRegistry, PhantomLdtkEntity, PhantomLdtkEntityTag, PhantomLdtkLevel,
 };
 
 pub trait LdtkApp {
     fn register_ldtk_entity<T: LdtkEntity + Bundle>(&mut self, ident: &str) -> &mut App;
     fn register_ldtk_entity_tag<T: LdtkEntityTag + Component>(&mut self, tag: &str) -> &mut App;
+    /// `T::initialize` will be called for every loaded level.
+    fn register_ldtk_level<T: LdtkLevel + Bundle>(&mut self) -> &mut App;
 }
 
 impl LdtkApp for App {
@@ -46,4 +48,19 @@ impl LdtkApp for App {
 
         self
     }
+
+    fn register_ldtk_level<T: LdtkLevel + Bundle>(&mut self) -> &mut App {
+        match self.world.get_non_send_resource_mut::<LdtkLevelRegistry>() {
+            Some(mut registry) => {
+                registry.push(Box::new(PhantomLdtkLevel::<T>::new()));
+            }
+            None => {
+                self.world
+                    .insert_non_send_resource(LdtkLevelRegistry::default());
+                self.register_ldtk_level::<T>();
+            }
+        }
+
+        self
+    }
 }
@@ -0,0 +1,128 @@
+use std::fmt::{Display, Formatter};
+
+use bevy::{
+    asset::{
+        io::Reader, Asset, AssetLoader, AsyncReadExt, LoadContext, ParseAssetPathError,
+        ReadAssetBytesError,
+    },
+    log::warn,
+    reflect::TypePath,
+    utils::{BoxedFuture, HashSet},
+};
+
+use super::json::{level::Level, LdtkJson};
+
+/// A LDtk project, with all the external levels merged into it.
+///
+/// The external level files (`*.ldtkl`) and the source files of the external
+/// enums are loaded as dependencies of the project, so modifying any of them
+/// reloads the project if the hot reloading of bevy is enabled.
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct LdtkProject(pub LdtkJson);
+
+#[derive(Debug)]
+pub enum LdtkProjectLoaderError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    ReadAssetBytes(ReadAssetBytesError),
+    ParseAssetPath(ParseAssetPathError),
+}
+
+impl Display for LdtkProjectLoaderError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LdtkProjectLoaderError::Io(e) => write!(f, "Could not read the LDtk file: {}", e),
+            LdtkProjectLoaderError::Json(e) => write!(f, "Could not parse the LDtk file: {}", e),
+            LdtkProjectLoaderError::ReadAssetBytes(e) => {
+                write!(f, "Could not read the external level file: {}", e)
+            }
+            LdtkProjectLoaderError::ParseAssetPath(e) => {
+                write!(f, "Invalid path of the external level file: {}", e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LdtkProjectLoaderError {}
+
+impl From<std::io::Error> for LdtkProjectLoaderError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<serde_json::Error> for LdtkProjectLoaderError {
+    fn from(value: serde_json::Error) -> Self {
+        Self::Json(value)
+    }
+}
+
+impl From<ReadAssetBytesError> for LdtkProjectLoaderError {
+    fn from(value: ReadAssetBytesError) -> Self {
+        Self::ReadAssetBytes(value)
+    }
+}
+
+impl From<ParseAssetPathError> for LdtkProjectLoaderError {
+    fn from(value: ParseAssetPathError) -> Self {
+        Self::ParseAssetPath(value)
+    }
+}
+
+#[derive(Default)]
+pub struct LdtkProjectLoader;
+
+impl AssetLoader for LdtkProjectLoader {
+    type Asset = LdtkProject;
+    type Settings = ();
+    type Error = LdtkProjectLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let mut ldtk_json = serde_json::from_slice::<LdtkJson>(&bytes)?;
+
+            if ldtk_json.external_levels {
+                for level in ldtk_json.iter_levels_mut() {
+                    let Some(rel_path) = level.external_rel_path.as_ref() else {
+                        continue;
+                    };
+                    let path = load_context.asset_path().resolve_embed(rel_path)?;
+                    let bytes = load_context.read_asset_bytes(path).await?;
+                    *level = serde_json::from_slice::<Level>(&bytes)?;
+                }
+            }
+
+            // The values of the external enums are already in the project file,
+            // so the source files are only read to be watched. They are usually
+            // out of the assets folder, so failing to read them is not an error.
+            let enum_sources = ldtk_json
+                .defs
+                .external_enums
+                .iter()
+                .filter_map(|e| e.external_rel_path.clone())
+                .collect::<HashSet<_>>();
+            for rel_path in enum_sources {
+                let path = load_context.asset_path().resolve_embed(&rel_path)?;
+                if let Err(e) = load_context.read_asset_bytes(path).await {
+                    warn!(
+                        "Could not read the external enum file {:?}, it won't be watched: {}",
+                        rel_path, e
+                    );
+                }
+            }
+
+            Ok(LdtkProject(ldtk_json))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ldtk"]
+    }
+}
@@ -5,7 +5,7 @@ use bevy::{
     utils::HashMap,
 };
 
-use super::resources::LdtkGlobalEntityRegistry;
+use super::{json::field::FieldInstance, resources::LdtkGlobalEntityRegistry};
 
 #[derive(Reflect, Default, Clone, Copy, PartialEq, Eq)]
 pub enum LdtkLoaderMode {
@@ -50,6 +50,17 @@ impl LdtkLoadedLevel {
     }
 }
 
+/// The custom fields of a loaded level, keyed by the field identifiers.
+#[derive(Component, Debug, Clone, Reflect)]
+pub struct LdtkLevelFields(pub HashMap<String, FieldInstance>);
+
+impl LdtkLevelFields {
+    #[inline]
+    pub fn get(&self, identifier: &str) -> Option<&FieldInstance> {
+        self.0.get(identifier)
+    }
+}
+
 #[derive(Component, Debug, Clone)]
 pub struct LdtkTempTransform {
     pub level_translation: Vec2,
@@ -23,6 +23,16 @@ pub struct Definitions {
     pub tilesets: Vec<TilesetDef>,
 }
 
+impl Definitions {
+    /// Find an enum by its identifier, including the external enums.
+    pub fn find_enum(&self, identifier: &str) -> Option<&EnumDef> {
+        self.enums
+            .iter()
+            .chain(self.external_enums.iter())
+            .find(|e| e.identifier == identifier)
+    }
+}
+
 /*
  * Layer Definition
  */
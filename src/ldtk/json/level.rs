@@ -62,6 +62,7 @@ pub struct Level {
     ///
     /// This array is **sorted in display order**: the 1st layer is
     /// the top-most and the last is behind.
+    #[serde(default, deserialize_with = "super::null_as_default")]
     pub layer_instances: Vec<LayerInstance>,
 
     /// Height of the level in pixels
@@ -1,5 +1,5 @@
 use bevy::{math::Vec4, reflect::Reflect, render::color::Color, utils::HashMap};
-use serde::{de::Visitor, Deserialize, Serialize};
+use serde::{de::Visitor, Deserialize, Deserializer, Serialize};
 
 use self::{definitions::Definitions, level::Level};
 
@@ -128,6 +128,53 @@ pub struct LdtkJson {
     pub worlds: Vec<World>,
 }
 
+impl LdtkJson {
+    /// Iterate over all the levels, including the ones in `worlds`
+    /// if the Multi-worlds option is enabled.
+    pub fn iter_levels(&self) -> impl Iterator<Item = &Level> {
+        self.levels
+            .iter()
+            .chain(self.worlds.iter().flat_map(|world| world.levels.iter()))
+    }
+
+    /// Mutable version of `iter_levels`.
+    pub fn iter_levels_mut(&mut self) -> impl Iterator<Item = &mut Level> {
+        self.levels.iter_mut().chain(
+            self.worlds
+                .iter_mut()
+                .flat_map(|world| world.levels.iter_mut()),
+        )
+    }
+
+    /// Find a level by its identifier, returns the level and
+    /// the layout of the world it belongs to.
+    pub fn find_level(&self, identifier: &str) -> Option<(&Level, Option<WorldLayout>)> {
+        self.levels
+            .iter()
+            .find(|level| level.identifier == identifier)
+            .map(|level| (level, self.world_layout))
+            .or_else(|| {
+                self.worlds.iter().find_map(|world| {
+                    world
+                        .levels
+                        .iter()
+                        .find(|level| level.identifier == identifier)
+                        .map(|level| (level, world.world_layout))
+                })
+            })
+    }
+}
+
+/// Deserialize `null` as the default value.
+/// Some fields are null when the project option "Save levels separately" is enabled.
+pub(crate) fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Option::unwrap_or_default)
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Reflect)]
 #[serde(rename_all = "camelCase")]
 pub struct Toc {
@@ -238,3 +285,24 @@ pub struct GridPoint {
     /// Y grid-based coordinate
     pub cy: i32,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_null_as_default() {
+        #[derive(Deserialize)]
+        struct Data {
+            #[serde(default, deserialize_with = "null_as_default")]
+            value: Vec<i32>,
+        }
+
+        let data: Data = serde_json::from_str(r#"{ "value": null }"#).unwrap();
+        assert!(data.value.is_empty());
+        let data: Data = serde_json::from_str(r#"{}"#).unwrap();
+        assert!(data.value.is_empty());
+        let data: Data = serde_json::from_str(r#"{ "value": [1, 2] }"#).unwrap();
+        assert_eq!(data.value, vec![1, 2]);
+    }
+}
@@ -18,23 +18,28 @@ use crate::{
     tilemap::{
         buffers::TileBuffer,
         bundles::StandardTilemapBundle,
+        id::TilemapId,
         map::{
-            TileRenderSize, TilemapLayerOpacities, TilemapName, TilemapSlotSize, TilemapStorage,
-            TilemapTexture, TilemapTransform, TilemapType,
+            TileRenderSize, TilemapAxisFlip, TilemapLayerOpacities, TilemapName, TilemapSlotSize,
+            TilemapStorage, TilemapTexture, TilemapTransform, TilemapType,
         },
+        marker::{TileMarkers, TileMarkersSource},
         tile::{TileBuilder, TileFlip, TileLayer, TileTexture},
     },
     DEFAULT_CHUNK_SIZE,
 };
 
 use super::{
-    components::{EntityIid, LayerIid, LdtkLoadedLevel, LdtkTempTransform, LevelIid},
+    components::{
+        EntityIid, LayerIid, LdtkLevelFields, LdtkLoadedLevel, LdtkTempTransform, LevelIid,
+    },
     json::{
+        definitions::LayerType,
         field::FieldInstance,
         level::{EntityInstance, LayerInstance, Level, TileInstance},
     },
-    resources::{LdtkAssets, LdtkLoadConfig, LdtkPatterns},
-    traits::{LdtkEntityRegistry, LdtkEntityTagRegistry},
+    resources::{LdtkAdditionalLayers, LdtkAssets, LdtkLoadConfig, LdtkPatterns},
+    traits::{LdtkEntityRegistry, LdtkEntityTagRegistry, LdtkLevelRegistry},
     LdtkLoaderMode,
 };
 
@@ -44,16 +49,249 @@ use crate::{
     tilemap::{algorithm::path::PathTilemap, chunking::storage::ChunkedStorage},
 };
 
-#[cfg(feature = "physics")]
+#[cfg(feature = "physics_core")]
 use crate::tilemap::physics::{DataPhysicsTilemap, SerializablePhysicsSource};
-#[cfg(feature = "physics")]
+#[cfg(feature = "physics_core")]
 use bevy::math::UVec2;
 
 #[cfg(feature = "algorithm")]
 pub mod path;
-#[cfg(feature = "physics")]
+#[cfg(feature = "physics_core")]
 pub mod physics;
 
+/// Convert the grid coordinates in LDtk, where `(0, 0)` is the top-left cell
+/// and y goes down, to the tile index.
+pub fn grid_to_index(
+    grid: IVec2,
+    height: i32,
+    mode: LdtkLoaderMode,
+    config: &LdtkLoadConfig,
+) -> IVec2 {
+    match mode {
+        LdtkLoaderMode::Tilemap if config.y_down => grid,
+        LdtkLoaderMode::Tilemap => IVec2::new(grid.x, -grid.y - 1),
+        LdtkLoaderMode::MapPattern => IVec2::new(grid.x, height - grid.y - 1),
+    }
+}
+
+/// The tiles covered by a layer.
+fn layer_aabb(
+    layer: &LayerInstance,
+    height: i32,
+    mode: LdtkLoaderMode,
+    config: &LdtkLoadConfig,
+) -> IAabb2d {
+    let a = grid_to_index(IVec2::ZERO, height, mode, config);
+    let b = grid_to_index(
+        IVec2::new(layer.c_wid - 1, layer.c_hei - 1),
+        height,
+        mode,
+        config,
+    );
+    IAabb2d {
+        min: a.min(b),
+        max: a.max(b),
+    }
+}
+
+fn new_layer_pattern(layer: &LayerInstance, aabb: IAabb2d) -> TilemapPattern {
+    TilemapPattern {
+        label: Some(layer.identifier.clone()),
+        tiles: TileBuffer {
+            aabb,
+            tiles: HashMap::new(),
+        },
+        animations: Default::default(),
+        anchor: IVec2::ZERO,
+        sockets: HashMap::new(),
+        links: HashMap::new(),
+        #[cfg(feature = "algorithm")]
+        path_tiles: TileBuffer {
+            aabb,
+            tiles: HashMap::new(),
+        },
+        #[cfg(feature = "physics_core")]
+        physics_tiles: SerializablePhysicsSource::Buffer(TileBuffer {
+            aabb,
+            tiles: HashMap::new(),
+        }),
+    }
+}
+
+fn insert_tile(
+    pattern: &mut TilemapPattern,
+    tile_size: IVec2,
+    tile: &TileInstance,
+    height: i32,
+    mode: LdtkLoaderMode,
+    config: &LdtkLoadConfig,
+) {
+    let tile_index = grid_to_index(
+        IVec2::new(tile.px[0], tile.px[1]) / tile_size,
+        height,
+        mode,
+        config,
+    );
+    let texture_index = tile.tile_id;
+
+    if let Some(ser_tile) = pattern.tiles.get_mut(tile_index) {
+        let TileTexture::Static(tile_layers) = &mut ser_tile.texture else {
+            panic!(
+                "Trying to insert multiple layers into a animated tile at {}!",
+                tile_index
+            );
+        };
+        tile_layers.push(TileLayer {
+            texture_index,
+            ..Default::default()
+        });
+    } else {
+        let mut builder = TileBuilder::new().with_tint(Color::rgba_linear(1., 1., 1., tile.alpha));
+        builder = {
+            if let Some(anim) = config.animation_mapper.get(&(texture_index as u32)) {
+                let animation = pattern.animations.register(anim.clone());
+                builder.with_animation(animation)
+            } else {
+                builder.with_layer(
+                    0,
+                    TileLayer {
+                        texture_index,
+                        flip: TileFlip::from_bits(tile.flip as u32).unwrap(),
+                    },
+                )
+            }
+        };
+
+        pattern.tiles.tiles.insert(tile_index, builder);
+    }
+}
+
+/// A level imported by `LdtkLevelManager::import_patterns`.
+#[derive(Clone)]
+pub struct LdtkLevelPatterns {
+    pub identifier: String,
+    pub iid: LevelIid,
+    /// One pattern for each tile layer with tiles, from the top layer to the bottom one.
+    /// The patterns are labeled with the layer identifiers.
+    pub layers: Vec<(TilemapPattern, TilemapTexture, LayerIid, LayerOpacity)>,
+    /// The entities in the entity layers by their identifiers.
+    pub markers: TileMarkers,
+}
+
+/// Convert the tile layers of a level into patterns without spawning anything,
+/// so the level can be stamped anywhere like a prefab.
+///
+/// The bottom-left tile of the level is at `(0, 0)`. The first entity with each identifier
+/// also becomes a socket of every pattern, like the doors to connect the rooms with.
+/// The path and physics layers are imported into the patterns of their parent layers,
+/// like in `LdtkLoaderMode::MapPattern`.
+pub fn import_level_patterns(
+    level: &Level,
+    config: &LdtkLoadConfig,
+    #[allow(unused_variables)] addi_layers: &LdtkAdditionalLayers,
+    ldtk_assets: &LdtkAssets,
+) -> LdtkLevelPatterns {
+    let mode = LdtkLoaderMode::MapPattern;
+    let mut markers = TileMarkers::default();
+    #[cfg(feature = "algorithm")]
+    let mut path_layer = None;
+    #[cfg(feature = "physics_core")]
+    let mut physics_layer = None;
+
+    let mut layers = Vec::new();
+    for layer in level.layer_instances.iter() {
+        let height = layer.c_hei;
+
+        #[cfg(feature = "algorithm")]
+        if let Some(path) = addi_layers.path_layer.as_ref() {
+            if layer.identifier == path.identifier {
+                let tiles = path::analyze_path_layer(layer, path, |grid| {
+                    grid_to_index(grid, height, mode, config)
+                });
+                path_layer = Some((path.parent.clone(), tiles));
+                continue;
+            }
+        }
+
+        #[cfg(feature = "physics_core")]
+        if let Some(phy) = addi_layers.physics_layer.as_ref() {
+            if layer.identifier == phy.identifier {
+                let data = DataPhysicsTilemap::new(
+                    IVec2::ZERO,
+                    layer.int_grid_csv.clone(),
+                    UVec2::new(layer.c_wid as u32, layer.c_hei as u32),
+                    phy.air,
+                    phy.tiles.clone().unwrap_or_default(),
+                );
+                physics_layer = Some((phy.parent.clone(), data));
+                continue;
+            }
+        }
+
+        let tiles = match layer.ty {
+            LayerType::IntGrid | LayerType::AutoLayer => &layer.auto_layer_tiles,
+            LayerType::Tiles => &layer.grid_tiles,
+            LayerType::Entities => {
+                layer.entity_instances.iter().for_each(|entity| {
+                    markers.add(
+                        entity.identifier.clone(),
+                        grid_to_index(entity.grid.into(), height, mode, config),
+                    );
+                });
+                continue;
+            }
+        };
+
+        let Some(tileset_uid) = layer.tileset_def_uid.filter(|_| !tiles.is_empty()) else {
+            continue;
+        };
+
+        let texture = ldtk_assets.get_tileset(tileset_uid).clone();
+        let mut pattern = new_layer_pattern(layer, layer_aabb(layer, height, mode, config));
+        tiles.iter().for_each(|tile| {
+            insert_tile(
+                &mut pattern,
+                texture.desc.tile_size.as_ivec2(),
+                tile,
+                height,
+                mode,
+                config,
+            );
+        });
+
+        layers.push((pattern, texture, LayerIid(layer.iid.clone()), layer.opacity));
+    }
+
+    layers.iter_mut().for_each(|(pattern, ..)| {
+        markers.names().for_each(|name| {
+            pattern
+                .sockets
+                .insert(name.to_string(), markers.first(name).unwrap());
+        });
+
+        #[cfg(feature = "algorithm")]
+        if let Some((parent, tiles)) = &path_layer {
+            if pattern.label.as_ref() == Some(parent) {
+                pattern.path_tiles.tiles = tiles.clone();
+            }
+        }
+
+        #[cfg(feature = "physics_core")]
+        if let Some((parent, data)) = &physics_layer {
+            if pattern.label.as_ref() == Some(parent) {
+                pattern.physics_tiles = SerializablePhysicsSource::Data(data.clone());
+            }
+        }
+    });
+
+    LdtkLevelPatterns {
+        identifier: level.identifier.clone(),
+        iid: LevelIid(level.iid.clone()),
+        layers,
+        markers,
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PackedLdtkEntity {
     pub instance: EntityInstance,
@@ -116,6 +354,7 @@ pub struct LdtkLayers<'a> {
     pub level_entity: Entity,
     pub layers: Vec<Option<(TilemapPattern, TilemapTexture, LayerIid, LayerOpacity)>>,
     pub entities: Vec<PackedLdtkEntity>,
+    pub markers: TileMarkers,
     pub tilesets: &'a HashMap<i32, TilemapTexture>,
     pub translation: Vec2,
     pub base_z_index: f32,
@@ -125,7 +364,7 @@ pub struct LdtkLayers<'a> {
         path::LdtkPathLayer,
         HashMap<IVec2, crate::tilemap::algorithm::path::PathTile>,
     )>,
-    #[cfg(feature = "physics")]
+    #[cfg(feature = "physics_core")]
     pub physics_layer: Option<(physics::LdtkPhysicsLayer, Vec<i32>, UVec2)>,
 }
 
@@ -143,6 +382,7 @@ impl<'a> LdtkLayers<'a> {
             level_entity,
             layers: vec![None; total_layers],
             entities: vec![],
+            markers: TileMarkers::default(),
             tilesets: &ldtk_assets.tilesets,
             translation,
             base_z_index,
@@ -150,7 +390,7 @@ impl<'a> LdtkLayers<'a> {
             ty,
             #[cfg(feature = "algorithm")]
             path_layer: None,
-            #[cfg(feature = "physics")]
+            #[cfg(feature = "physics_core")]
             physics_layer: None,
         }
     }
@@ -164,59 +404,25 @@ impl<'a> LdtkLayers<'a> {
         patterns: &LdtkPatterns,
         mode: &LdtkLoaderMode,
     ) {
-        self.try_create_new_layer(layer_index, layer);
+        let height = patterns.pattern_size.y as i32;
+        self.try_create_new_layer(layer_index, layer, layer_aabb(layer, height, *mode, config));
 
         let (pattern, texture, _, _) = self.layers[layer_index].as_mut().unwrap();
-        let tile_size = texture.desc.tile_size;
-        let tile_index = IVec2 {
-            x: tile.px[0] / tile_size.x as i32,
-            y: match mode {
-                LdtkLoaderMode::Tilemap => -tile.px[1] / tile_size.y as i32 - 1,
-                LdtkLoaderMode::MapPattern => {
-                    patterns.pattern_size.y as i32 - tile.px[1] / tile_size.y as i32 - 1
-                }
-            },
-        };
-        let texture_index = tile.tile_id;
-
-        if let Some(ser_tile) = pattern.tiles.get_mut(tile_index) {
-            let TileTexture::Static(tile_layers) = &mut ser_tile.texture else {
-                panic!(
-                    "Trying to insert multiple layers into a animated tile at {}!",
-                    tile_index
-                );
-            };
-            tile_layers.push(TileLayer {
-                texture_index,
-                ..Default::default()
-            });
-        } else {
-            let mut builder =
-                TileBuilder::new().with_tint(Color::rgba_linear(1., 1., 1., tile.alpha));
-            builder = {
-                if let Some(anim) = config.animation_mapper.get(&(texture_index as u32)) {
-                    let animation = pattern.animations.register(anim.clone());
-                    builder.with_animation(animation)
-                } else {
-                    builder.with_layer(
-                        0,
-                        TileLayer {
-                            texture_index,
-                            flip: TileFlip::from_bits(tile.flip as u32).unwrap(),
-                        },
-                    )
-                }
-            };
-
-            pattern.tiles.tiles.insert(tile_index, builder);
-        }
+        insert_tile(
+            pattern,
+            texture.desc.tile_size.as_ivec2(),
+            tile,
+            height,
+            *mode,
+            config,
+        );
     }
 
     pub fn set_entity(&mut self, entity: PackedLdtkEntity) {
         self.entities.push(entity);
     }
 
-    fn try_create_new_layer(&mut self, layer_index: usize, layer: &LayerInstance) {
+    fn try_create_new_layer(&mut self, layer_index: usize, layer: &LayerInstance, aabb: IAabb2d) {
         let tileset = self
             .tilesets
             .get(&layer.tileset_def_uid.unwrap())
@@ -227,30 +433,8 @@ impl<'a> LdtkLayers<'a> {
             return;
         }
 
-        let aabb = IAabb2d {
-            min: IVec2::new(0, -layer.c_hei + 1),
-            max: IVec2::new(layer.c_wid - 1, 0),
-        };
-
         self.layers[layer_index] = Some((
-            TilemapPattern {
-                label: Some(layer.identifier.clone()),
-                tiles: TileBuffer {
-                    aabb,
-                    tiles: HashMap::new(),
-                },
-                animations: Default::default(),
-                #[cfg(feature = "algorithm")]
-                path_tiles: TileBuffer {
-                    aabb,
-                    tiles: HashMap::new(),
-                },
-                #[cfg(feature = "physics")]
-                physics_tiles: SerializablePhysicsSource::Buffer(TileBuffer {
-                    aabb,
-                    tiles: HashMap::new(),
-                }),
-            },
+            new_layer_pattern(layer, aabb),
             tileset,
             LayerIid(layer.iid.clone()),
             layer.opacity,
@@ -264,6 +448,7 @@ impl<'a> LdtkLayers<'a> {
         level: &Level,
         entity_registry: &LdtkEntityRegistry,
         entity_tag_registry: &LdtkEntityTagRegistry,
+        level_registry: &LdtkLevelRegistry,
         config: &LdtkLoadConfig,
         ldtk_assets: &LdtkAssets,
         asset_server: &AssetServer,
@@ -306,6 +491,11 @@ impl<'a> LdtkLayers<'a> {
                                 z_index: self.base_z_index - index as f32 - 1.,
                                 ..Default::default()
                             },
+                            axis_flip: if config.y_down {
+                                TilemapAxisFlip::Y
+                            } else {
+                                TilemapAxisFlip::NONE
+                            },
                             layer_opacities: TilemapLayerOpacities([opacity; 4].into()),
                             animations: pattern.animations.clone(),
                             ..Default::default()
@@ -325,35 +515,66 @@ impl<'a> LdtkLayers<'a> {
                                             path_tilemap.clone(),
                                             None,
                                         ),
+                                        revision: 0,
+                                        walls: Default::default(),
                                     },
                                 );
                             }
                         }
 
-                        #[cfg(feature = "physics")]
+                        #[cfg(feature = "physics_core")]
                         if let Some((physics_layer, physics_data, size)) = &self.physics_layer {
                             if pattern.label.clone().unwrap() == physics_layer.parent {
-                                commands
-                                    .entity(tilemap_entity)
-                                    .insert(DataPhysicsTilemap::new(
+                                let tiles = physics_layer.tiles.clone().unwrap_or_default();
+                                // The rows in LDtk are already in the y-down order.
+                                let data = if config.y_down {
+                                    DataPhysicsTilemap::new_flipped(
+                                        IVec2::ZERO,
+                                        physics_data.clone(),
+                                        *size,
+                                        physics_layer.air,
+                                        tiles,
+                                    )
+                                } else {
+                                    DataPhysicsTilemap::new(
                                         IVec2::new(0, -(size.y as i32)),
                                         physics_data.clone(),
                                         *size,
                                         physics_layer.air,
-                                        physics_layer.tiles.clone().unwrap_or_default(),
-                                    ));
+                                        tiles,
+                                    )
+                                };
+                                commands.entity(tilemap_entity).insert(data);
                             }
                         }
 
-                        commands
-                            .entity(tilemap_entity)
-                            .insert((tilemap, iid.clone()));
+                        commands.entity(tilemap_entity).insert((
+                            tilemap,
+                            TilemapId(iid.0.clone()),
+                            iid.clone(),
+                            TileMarkersSource(self.level_entity),
+                        ));
                         layers.insert(iid, tilemap_entity);
                     });
 
                 let bg = commands.spawn(self.background.clone()).id();
-
-                commands.entity(self.level_entity).insert((
+                let fields = level
+                    .field_instances
+                    .iter()
+                    .map(|field| (field.identifier.clone(), field.clone()))
+                    .collect::<HashMap<_, _>>();
+
+                let mut level_entity = commands.entity(self.level_entity);
+                level_registry.iter().for_each(|phantom_level| {
+                    phantom_level.initialize(
+                        &mut level_entity,
+                        level,
+                        &fields,
+                        asset_server,
+                        ldtk_assets,
+                    );
+                });
+                level_entity.insert((
                     LdtkLoadedLevel {
                         identifier: level.identifier.clone(),
                         layers,
@@ -365,6 +586,8 @@ impl<'a> LdtkLayers<'a> {
                         ..Default::default()
                     },
                     LevelIid(level.iid.clone()),
+                    LdtkLevelFields(fields),
+                    std::mem::take(&mut self.markers),
                 ));
             }
             LdtkLoaderMode::MapPattern => {
@@ -385,7 +608,7 @@ impl<'a> LdtkLayers<'a> {
                             }
                         }
 
-                        #[cfg(feature = "physics")]
+                        #[cfg(feature = "physics_core")]
                         if let Some((physics_layer, physics_data, size)) =
                             self.physics_layer.as_ref()
                         {
@@ -424,7 +647,7 @@ impl<'a> LdtkLayers<'a> {
         self.path_layer = Some((path, tilemap));
     }
 
-    #[cfg(feature = "physics")]
+    #[cfg(feature = "physics_core")]
     pub fn assign_physics_layer(
         &mut self,
         physics_layer: physics::LdtkPhysicsLayer,
@@ -434,3 +657,101 @@ impl<'a> LdtkLayers<'a> {
         self.physics_layer = Some((physics_layer, physics_data, size));
     }
 }
+
+#[cfg(test)]
+mod test {
+    use bevy::{asset::Handle, math::UVec2, render::render_resource::FilterMode, utils::HashMap};
+
+    use crate::{
+        ldtk::{
+            json::LdtkJson,
+            resources::{LdtkLevelManager, LdtkLoadConfig},
+        },
+        tilemap::map::{TilemapRotation, TilemapTextureDescriptor},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_import_level_patterns() {
+        let bytes = std::fs::read(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/assets/ldtk/grid_vania.ldtk"
+        ))
+        .unwrap();
+        let json = serde_json::from_slice::<LdtkJson>(&bytes).unwrap();
+        let (level, _) = json.find_level("Entrance").unwrap();
+
+        let config = LdtkLoadConfig {
+            file_path: "ldtk/grid_vania.ldtk".to_string(),
+            ..Default::default()
+        };
+        let ldtk_assets = LdtkAssets {
+            tilesets: HashMap::from([(
+                6,
+                TilemapTexture::new(
+                    Handle::default(),
+                    TilemapTextureDescriptor::new(
+                        UVec2::new(368, 336),
+                        UVec2::splat(16),
+                        FilterMode::Nearest,
+                    ),
+                    TilemapRotation::None,
+                ),
+            )]),
+            ..Default::default()
+        };
+        let addi_layers = LdtkAdditionalLayers {
+            #[cfg(feature = "physics_core")]
+            physics_layer: Some(super::physics::LdtkPhysicsLayer {
+                identifier: "PhysicsColliders".to_string(),
+                parent: "Collisions".to_string(),
+                air: 0,
+                tiles: None,
+            }),
+            ..Default::default()
+        };
+
+        // Nothing can be imported before the project and the assets are initialized.
+        assert!(LdtkLevelManager::default()
+            .import_patterns("Entrance", &config, &addi_layers, &ldtk_assets)
+            .is_none());
+
+        let imported = import_level_patterns(level, &config, &addi_layers, &ldtk_assets);
+        assert_eq!(imported.identifier, "Entrance");
+        let labels = imported
+            .layers
+            .iter()
+            .map(|(p, ..)| p.label.clone().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            labels,
+            ["Animation", "Wall_shadows", "Collisions", "Background"]
+        );
+
+        // The level is 32x16 and the bottom-left tile is at (0, 0).
+        let (animation, ..) = &imported.layers[0];
+        assert_eq!(animation.tiles.aabb.min, IVec2::ZERO);
+        assert_eq!(animation.tiles.aabb.max, IVec2::new(31, 15));
+        assert_eq!(animation.tiles.tiles.len(), 4);
+        // The tile at px (240, 48), which is the grid (15, 3).
+        let TileTexture::Static(layers) = &animation.tiles.get(IVec2::new(15, 12)).unwrap().texture
+        else {
+            panic!("The tile is not static!");
+        };
+        assert_eq!(layers[0].texture_index, 470);
+
+        // The first entity with each identifier becomes a socket.
+        assert_eq!(animation.sockets["Item"], IVec2::new(28, 5));
+        assert_eq!(animation.sockets["Player"], IVec2::new(18, 5));
+        assert_eq!(animation.sockets["Ladder"], IVec2::new(5, 9));
+        assert_eq!(imported.markers.get("Item").len(), 3);
+
+        // Only the parent layer gets the physics data.
+        #[cfg(feature = "physics_core")]
+        for (pattern, ..) in imported.layers.iter() {
+            let is_data = matches!(pattern.physics_tiles, SerializablePhysicsSource::Data(_));
+            assert_eq!(is_data, pattern.label.as_deref() == Some("Collisions"));
+        }
+    }
+}
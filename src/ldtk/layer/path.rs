@@ -12,7 +12,13 @@ pub struct LdtkPathLayer {
     pub cost_mapper: Option<HashMap<i32, u32>>,
 }
 
-pub fn analyze_path_layer(layer: &LayerInstance, path: &LdtkPathLayer) -> HashMap<IVec2, PathTile> {
+/// Collect the path tiles. `index` converts the grid coordinates in LDtk to the tile index,
+/// so the path tiles match the tiles of the parent layer.
+pub fn analyze_path_layer(
+    layer: &LayerInstance,
+    path: &LdtkPathLayer,
+    index: impl Fn(IVec2) -> IVec2,
+) -> HashMap<IVec2, PathTile> {
     if layer.ty != LayerType::IntGrid {
         panic!(
             "The path layer {:?} is not an IntGrid layer!",
@@ -28,12 +34,12 @@ pub fn analyze_path_layer(layer: &LayerInstance, path: &LdtkPathLayer) -> HashMa
     for y in 0..size.y {
         for x in 0..size.x {
             tiles.insert(
-                IVec2 { x, y },
-                PathTile {
-                    cost: *cost_mapper
+                index(IVec2 { x, y }),
+                PathTile::new(
+                    *cost_mapper
                         .get(&grid[(y * size.x + x) as usize])
                         .unwrap_or(&(grid[(y * size.x + x) as usize] as u32)),
-                },
+                ),
             );
         }
     }
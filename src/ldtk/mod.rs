@@ -2,13 +2,14 @@ use std::path::Path;
 
 use bevy::{
     app::{Plugin, Startup, Update},
-    asset::{load_internal_asset, AssetServer, Assets, Handle},
+    asset::{load_internal_asset, AssetApp, AssetEvent, AssetServer, Assets, Handle},
     ecs::{
         entity::Entity,
-        event::EventWriter,
+        event::{EventReader, EventWriter},
         query::{Added, With},
         system::{Commands, NonSend, ParallelCommands, Query, Res, ResMut},
     },
+    log::error,
     math::{UVec2, Vec2},
     render::{mesh::Mesh, render_resource::Shader},
     sprite::{Material2dPlugin, Sprite, SpriteBundle, TextureAtlasLayout},
@@ -32,8 +33,10 @@ use crate::{
 };
 
 use self::{
+    asset::{LdtkProject, LdtkProjectLoader},
     components::{
-        EntityIid, GlobalEntity, LdtkLoadedLevel, LdtkTempTransform, LdtkUnloadLayer, LevelIid,
+        EntityIid, GlobalEntity, LdtkLevelFields, LdtkLoadedLevel, LdtkTempTransform,
+        LdtkUnloadLayer, LevelIid,
     },
     events::{LdtkEvent, LevelEvent},
     json::{
@@ -44,13 +47,14 @@ use self::{
     layer::{LdtkLayers, PackedLdtkEntity},
     resources::{LdtkLevelManager, LdtkLoadConfig},
     sprite::LdtkEntityMaterial,
-    traits::{LdtkEntityRegistry, LdtkEntityTagRegistry},
+    traits::{LdtkEntityRegistry, LdtkEntityTagRegistry, LdtkLevelRegistry},
 };
 
 #[cfg(feature = "algorithm")]
 use crate::algorithm::pathfinding::PathTilemaps;
 
 pub mod app_ext;
+pub mod asset;
 pub mod components;
 pub mod events;
 pub mod json;
@@ -74,10 +78,14 @@ impl Plugin for EntiTilesLdtkPlugin {
 
         app.add_plugins(Material2dPlugin::<LdtkEntityMaterial>::default());
 
+        app.init_asset::<LdtkProject>()
+            .init_asset_loader::<LdtkProjectLoader>();
+
         app.add_systems(Startup, parse_ldtk_json);
         app.add_systems(
             Update,
             (
+                sync_ldtk_project,
                 load_ldtk_json,
                 unload_ldtk_level,
                 unload_ldtk_layer,
@@ -99,6 +107,7 @@ impl Plugin for EntiTilesLdtkPlugin {
         app.add_event::<LdtkEvent>();
 
         app.register_type::<LdtkLoadedLevel>()
+            .register_type::<LdtkLevelFields>()
             .register_type::<GlobalEntity>()
             .register_type::<EntityIid>()
             .register_type::<LayerIid>()
@@ -141,15 +150,36 @@ impl Plugin for EntiTilesLdtkPlugin {
             app.register_type::<resources::LdtkWfcManager>();
         }
 
-        #[cfg(feature = "physics")]
+        #[cfg(feature = "physics_core")]
         {
             app.register_type::<layer::physics::LdtkPhysicsLayer>();
         }
     }
 }
 
-fn parse_ldtk_json(mut manager: ResMut<LdtkLevelManager>, config: Res<LdtkLoadConfig>) {
-    manager.reload_json(&config);
+fn parse_ldtk_json(
+    mut manager: ResMut<LdtkLevelManager>,
+    config: Res<LdtkLoadConfig>,
+    asset_server: Res<AssetServer>,
+) {
+    manager.reload_json(&config, &asset_server);
+}
+
+fn sync_ldtk_project(
+    mut manager: ResMut<LdtkLevelManager>,
+    mut asset_events: EventReader<AssetEvent<LdtkProject>>,
+    projects: Res<Assets<LdtkProject>>,
+) {
+    for event in asset_events.read() {
+        match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }
+                if *id == manager.project.id() =>
+            {
+                manager.ldtk_json = projects.get(*id).map(|project| project.0.clone());
+            }
+            _ => {}
+        }
+    }
 }
 
 fn global_entity_registerer(
@@ -191,7 +221,7 @@ pub fn unload_ldtk_level(
     });
 }
 
-#[cfg(not(feature = "physics"))]
+#[cfg(not(feature = "physics_core"))]
 pub fn unload_ldtk_layer(
     mut commands: Commands,
     mut query: Query<&mut TilemapStorage, With<LdtkUnloadLayer>>,
@@ -201,7 +231,7 @@ pub fn unload_ldtk_layer(
     });
 }
 
-#[cfg(feature = "physics")]
+#[cfg(feature = "physics_core")]
 pub fn unload_ldtk_layer(
     mut commands: Commands,
     mut query: Query<
@@ -224,8 +254,11 @@ pub fn load_ldtk_json(
     mut commands: Commands,
     loader_query: Query<(Entity, &LdtkLoader)>,
     asset_server: Res<AssetServer>,
-    entity_registry: Option<NonSend<LdtkEntityRegistry>>,
-    entity_tag_registry: Option<NonSend<LdtkEntityTagRegistry>>,
+    (entity_registry, entity_tag_registry, level_registry): (
+        Option<NonSend<LdtkEntityRegistry>>,
+        Option<NonSend<LdtkEntityTagRegistry>>,
+        Option<NonSend<LdtkLevelRegistry>>,
+    ),
     mut atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
     mut ldtk_events: EventWriter<LdtkEvent>,
     config: Res<LdtkLoadConfig>,
@@ -238,9 +271,14 @@ pub fn load_ldtk_json(
     global_entities: Res<LdtkGlobalEntityRegistry>,
     #[cfg(feature = "algorithm")] mut path_tilemaps: ResMut<PathTilemaps>,
 ) {
+    if !manager.is_initialized() {
+        return;
+    }
+
     for (entity, loader) in loader_query.iter() {
         let entity_registry = entity_registry.as_ref().map(|r| &**r);
         let entity_tag_registry = entity_tag_registry.as_ref().map(|r| &**r);
+        let level_registry = level_registry.as_ref().map(|r| &**r);
 
         ldtk_assets.initialize(
             &config,
@@ -260,6 +298,7 @@ pub fn load_ldtk_json(
             &asset_server,
             &entity_registry.unwrap_or(&LdtkEntityRegistry::default()),
             &entity_tag_registry.unwrap_or(&LdtkEntityTagRegistry::default()),
+            &level_registry.unwrap_or(&LdtkLevelRegistry::default()),
             entity,
             &mut ldtk_events,
             &mut ldtk_assets,
@@ -282,6 +321,7 @@ fn load_levels(
     asset_server: &AssetServer,
     entity_registry: &LdtkEntityRegistry,
     entity_tag_registry: &LdtkEntityTagRegistry,
+    level_registry: &LdtkLevelRegistry,
     level_entity: Entity,
     ldtk_events: &mut EventWriter<LdtkEvent>,
     ldtk_assets: &mut LdtkAssets,
@@ -291,18 +331,22 @@ fn load_levels(
 ) {
     let ldtk_data = manager.get_cached_data();
 
-    let Some((level_index, level)) = ldtk_data
-        .levels
-        .iter()
-        .enumerate()
-        .find(|(_, level)| level.identifier == loader.level)
-    else {
+    let Some((level, world_layout)) = ldtk_data.find_level(&loader.level) else {
         return;
     };
 
-    let translation = loader
-        .trans_ovrd
-        .unwrap_or_else(|| get_level_translation(&ldtk_data, level_index));
+    let translation = match loader.trans_ovrd {
+        Some(translation) => translation,
+        None => match get_level_translation(level, world_layout) {
+            Ok(translation) => translation,
+            Err(e) => {
+                error!("{}", e);
+                manager.loaded_levels.remove(&loader.level);
+                commands.entity(level_entity).despawn();
+                return;
+            }
+        },
+    };
 
     let level_px = UVec2 {
         x: level.px_wid as u32,
@@ -325,13 +369,15 @@ fn load_levels(
         #[cfg(feature = "algorithm")]
         if let Some(path) = addi_layers.path_layer.as_ref() {
             if layer.identifier == path.identifier {
-                ldtk_layers
-                    .assign_path_layer(path.clone(), layer::path::analyze_path_layer(layer, path));
+                let tiles = layer::path::analyze_path_layer(layer, path, |grid| {
+                    layer::grid_to_index(grid, patterns.pattern_size.y as i32, loader.mode, config)
+                });
+                ldtk_layers.assign_path_layer(path.clone(), tiles);
                 continue;
             }
         }
 
-        #[cfg(feature = "physics")]
+        #[cfg(feature = "physics_core")]
         if let Some(phy) = addi_layers.physics_layer.as_ref() {
             if layer.identifier == phy.identifier {
                 ldtk_layers.assign_physics_layer(
@@ -364,6 +410,7 @@ fn load_levels(
         level,
         entity_registry,
         entity_tag_registry,
+        level_registry,
         config,
         ldtk_assets,
         asset_server,
@@ -423,6 +470,16 @@ fn load_layer(
         }
         LayerType::Entities => {
             for (order, entity_instance) in layer.entity_instances.iter().enumerate() {
+                ldtk_layers.markers.add(
+                    entity_instance.identifier.clone(),
+                    layer::grid_to_index(
+                        entity_instance.grid.into(),
+                        patterns.pattern_size.y as i32,
+                        loader.mode,
+                        config,
+                    ),
+                );
+
                 let iid = EntityIid(entity_instance.iid.clone());
                 if global_entities.contains(&iid) {
                     continue;
@@ -455,13 +512,16 @@ fn load_layer(
     }
 }
 
-fn get_level_translation(ldtk_data: &LdtkJson, index: usize) -> Vec2 {
-    let level = &ldtk_data.levels[index];
-    match ldtk_data.world_layout.unwrap() {
-        WorldLayout::GridVania | WorldLayout::Free => Vec2 {
+fn get_level_translation(level: &Level, world_layout: Option<WorldLayout>) -> Result<Vec2, String> {
+    match world_layout {
+        Some(WorldLayout::GridVania | WorldLayout::Free) => Ok(Vec2 {
             x: level.world_x as f32,
             y: -level.world_y as f32,
-        },
-        WorldLayout::LinearHorizontal | WorldLayout::LinearVertical => Vec2::ZERO,
+        }),
+        Some(WorldLayout::LinearHorizontal | WorldLayout::LinearVertical) => Ok(Vec2::ZERO),
+        None => Err(format!(
+            "Level {:?} has no world layout! Specify the translation when loading it.",
+            level.identifier
+        )),
     }
 }
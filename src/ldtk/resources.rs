@@ -1,7 +1,7 @@
-use std::{fs::read_to_string, path::Path};
+use std::path::Path;
 
 use bevy::{
-    asset::{AssetServer, Assets, Handle},
+    asset::{AssetPath, AssetServer, Assets, Handle},
     ecs::{
         entity::Entity,
         system::{Commands, Resource},
@@ -25,8 +25,10 @@ use crate::{
 };
 
 use super::{
+    asset::LdtkProject,
     components::{EntityIid, LayerIid},
     json::{definitions::EntityDef, EntityRef, LdtkJson, TocInstance},
+    layer::{import_level_patterns, LdtkLevelPatterns},
     sprite::{AtlasRect, LdtkEntityMaterial},
     LdtkLoader, LdtkLoaderMode, LdtkUnloader,
 };
@@ -163,6 +165,11 @@ impl LdtkAssets {
         self.materials.get(iid).unwrap().clone()
     }
 
+    /// Whether the assets are initialized for the project in the config.
+    pub fn is_initialized(&self, config: &LdtkLoadConfig) -> bool {
+        !self.associated_file.is_empty() && self.associated_file == config.file_path
+    }
+
     /// Initialize the assets.
     ///
     /// You need to call this after you changed something like the size of an entity,
@@ -205,6 +212,7 @@ impl LdtkAssets {
                     y: tileset.tile_grid_size as u32,
                 },
                 filter_mode: config.filter_mode,
+                ..Default::default()
             };
             let texture = TilemapTexture {
                 texture,
@@ -232,8 +240,7 @@ impl LdtkAssets {
         });
 
         ldtk_data
-            .levels
-            .iter()
+            .iter_levels()
             .map(|level| level.layer_instances.iter())
             .flatten()
             .map(|layer| layer.entity_instances.iter())
@@ -341,13 +348,14 @@ impl LdtkTocs {
 pub struct LdtkAdditionalLayers {
     #[cfg(feature = "algorithm")]
     pub path_layer: Option<super::layer::path::LdtkPathLayer>,
-    #[cfg(feature = "physics")]
+    #[cfg(feature = "physics_core")]
     pub physics_layer: Option<super::layer::physics::LdtkPhysicsLayer>,
 }
 
 /// Configuration for loading the LDtk file.
 #[derive(Resource, Default, Reflect)]
 pub struct LdtkLoadConfig {
+    /// Path of the project file, relative to the assets folder.
     pub file_path: String,
     pub asset_path_prefix: String,
     #[reflect(ignore)]
@@ -357,32 +365,42 @@ pub struct LdtkLoadConfig {
     pub animation_mapper: HashMap<u32, RawTileAnimation>,
     pub ignore_unregistered_entities: bool,
     pub ignore_unregistered_entity_tags: bool,
+    /// Use the same coordinates as the LDtk editor, where `(0, 0)` is the top-left tile
+    /// and y goes down. The tilemaps will have `TilemapAxisFlip::Y`.
+    ///
+    /// Otherwise, the top-left tile is at `(0, -1)` and y goes up.
+    /// Only used in `LdtkLoaderMode::Tilemap`.
+    pub y_down: bool,
 }
 
 #[derive(Resource, Default, Reflect)]
 pub struct LdtkLevelManager {
+    pub(crate) project: Handle<LdtkProject>,
     pub(crate) ldtk_json: Option<LdtkJson>,
     pub(crate) loaded_levels: HashMap<String, Entity>,
 }
 
 impl LdtkLevelManager {
-    /// Reloads the LDtk file and refresh the level cache.
-    pub fn reload_json(&mut self, config: &LdtkLoadConfig) {
+    /// Starts loading the LDtk file through the asset server.
+    ///
+    /// The level cache is refreshed once the project and all the external level files
+    /// are loaded, and whenever any of them is reloaded.
+    pub fn reload_json(&mut self, config: &LdtkLoadConfig, asset_server: &AssetServer) {
         if config.file_path.is_empty() {
             error!("No specified LDtk level file path!");
             return;
         }
 
-        let path = std::env::current_dir().unwrap().join(&config.file_path);
-        let str_raw = match read_to_string(&path) {
-            Ok(data) => data,
-            Err(e) => panic!("Could not read file at path: {:?}!\n{}", path, e),
-        };
+        if self.project.path() == Some(&AssetPath::from(config.file_path.clone())) {
+            asset_server.reload(config.file_path.clone());
+        } else {
+            self.project = asset_server.load(config.file_path.clone());
+        }
+    }
 
-        self.ldtk_json = match serde_json::from_str::<LdtkJson>(&str_raw) {
-            Ok(data) => Some(data),
-            Err(e) => panic!("Could not parse file at path: {}!\n{}", config.file_path, e),
-        };
+    /// Returns the handle of the LDtk project asset.
+    pub fn get_project(&self) -> &Handle<LdtkProject> {
+        &self.project
     }
 
     pub fn get_cached_data(&self) -> &LdtkJson {
@@ -390,9 +408,8 @@ impl LdtkLevelManager {
         self.ldtk_json.as_ref().unwrap()
     }
 
+    /// Load a level. The level will be spawned as soon as the project is loaded.
     pub fn load(&mut self, commands: &mut Commands, level: String, trans_ovrd: Option<Vec2>) {
-        self.check_initialized();
-
         if self.loaded_levels.contains_key(&level) {
             error!("Trying to load {:?} that is already loaded!", level);
         } else {
@@ -411,8 +428,7 @@ impl LdtkLevelManager {
         self.ldtk_json
             .as_ref()
             .unwrap()
-            .levels
-            .iter()
+            .iter_levels()
             .for_each(|level| {
                 if self.loaded_levels.contains_key(&level.identifier) {
                     error!("Trying to load {:?} that is already loaded!", level);
@@ -426,8 +442,36 @@ impl LdtkLevelManager {
             });
     }
 
+    /// Import a level into patterns, one for each tile layer, without spawning anything.
+    /// Returns `None` if the level doesn't exist, or the project or the `LdtkAssets`
+    /// are not initialized yet.
+    ///
+    /// Use this to treat the authored levels as prefabs, like stitching the rooms
+    /// into a procedurally arranged dungeon. See `import_level_patterns` for the details.
+    ///
+    /// The `LdtkAssets` are initialized when any level is loaded.
+    /// Otherwise call `LdtkAssets::initialize` first.
+    pub fn import_patterns(
+        &self,
+        level: &str,
+        config: &LdtkLoadConfig,
+        addi_layers: &LdtkAdditionalLayers,
+        ldtk_assets: &LdtkAssets,
+    ) -> Option<LdtkLevelPatterns> {
+        if !self.is_initialized() || !ldtk_assets.is_initialized(config) {
+            return None;
+        }
+
+        let (level, _) = self.get_cached_data().find_level(level)?;
+        Some(import_level_patterns(
+            level,
+            config,
+            addi_layers,
+            ldtk_assets,
+        ))
+    }
+
     pub fn switch_to(&mut self, commands: &mut Commands, level: String, trans_ovrd: Option<Vec2>) {
-        self.check_initialized();
         if self.loaded_levels.contains_key(&level) {
             error!("Trying to load {:?} that is already loaded!", level);
         } else {
@@ -7,7 +7,10 @@ use bevy::{
 };
 
 use super::{
-    json::{field::FieldInstance, level::EntityInstance},
+    json::{
+        field::FieldInstance,
+        level::{EntityInstance, Level},
+    },
     resources::LdtkAssets,
 };
 
@@ -59,6 +62,61 @@ impl<T: LdtkEntity + Bundle> PhantomLdtkEntityTrait for PhantomLdtkEntity<T> {
     }
 }
 
+pub type LdtkLevelRegistry = Vec<Box<dyn PhantomLdtkLevelTrait>>;
+
+/// Implement this to turn the custom fields of levels into your own components.
+pub trait LdtkLevel {
+    fn initialize(
+        commands: &mut EntityCommands,
+        level: &Level,
+        fields: &HashMap<String, FieldInstance>,
+        asset_server: &AssetServer,
+        ldtk_assets: &LdtkAssets,
+    );
+}
+
+pub struct PhantomLdtkLevel<T: LdtkLevel + Bundle> {
+    pub marker: PhantomData<T>,
+}
+
+impl<T: LdtkLevel + Bundle> PhantomLdtkLevel<T> {
+    pub fn new() -> Self {
+        Self {
+            marker: PhantomData::<T>,
+        }
+    }
+}
+
+impl<T: LdtkLevel + Bundle> Default for PhantomLdtkLevel<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub trait PhantomLdtkLevelTrait {
+    fn initialize(
+        &self,
+        commands: &mut EntityCommands,
+        level: &Level,
+        fields: &HashMap<String, FieldInstance>,
+        asset_server: &AssetServer,
+        ldtk_assets: &LdtkAssets,
+    );
+}
+
+impl<T: LdtkLevel + Bundle> PhantomLdtkLevelTrait for PhantomLdtkLevel<T> {
+    fn initialize(
+        &self,
+        commands: &mut EntityCommands,
+        level: &Level,
+        fields: &HashMap<String, FieldInstance>,
+        asset_server: &AssetServer,
+        ldtk_assets: &LdtkAssets,
+    ) {
+        T::initialize(commands, level, fields, asset_server, ldtk_assets);
+    }
+}
+
 pub trait LdtkEnum {
     fn get_identifier(ident: &str) -> Self;
 }
@@ -1,7 +1,7 @@
 use bevy::prelude::Plugin;
 use math::EntiTilesMathPlugin;
 use render::{
-    material::{EntiTilesMaterialPlugin, StandardTilemapMaterial},
+    material::{DissolveTilemapMaterial, EntiTilesMaterialPlugin, StandardTilemapMaterial},
     EntiTilesRendererPlugin,
 };
 use shaders::EntiTilesShaderPlugin;
@@ -11,6 +11,10 @@ use tilemap::EntiTilesTilemapPlugin;
 pub mod algorithm;
 #[cfg(feature = "debug")]
 pub mod debug;
+#[cfg(feature = "editor")]
+pub mod editor;
+#[cfg(feature = "egui")]
+pub mod inspector;
 #[cfg(feature = "ldtk")]
 pub mod ldtk;
 pub mod math;
@@ -25,37 +29,125 @@ pub mod utils;
 
 pub const MAX_LAYER_COUNT: usize = 4;
 pub const DEFAULT_CHUNK_SIZE: u32 = 16;
+/// The animation group is packed into the higher 16 bits of the vertex data.
+pub const MAX_ANIMATION_GROUPS: usize = 1 << 16;
 
 pub mod prelude {
     #[cfg(feature = "algorithm")]
     pub use crate::algorithm::{
+        avoidance::{AvoidanceBehavior, BookingConflict, PathFollower, TileReservations},
+        cancel::CancelToken,
+        cellular::{CellularAutomataRunner, CellularAutomaton, CellularGrid},
+        connection::{TilemapConnections, WorldPath, WorldPathFinder},
+        constraint::TilemapConstraints,
+        dungeon::{BspDungeonGenerator, MazeAlgorithm, MazeGenerator},
+        fov::field_of_view,
+        guarantee::{
+            ConnectedConstraint, GenerationConstraint, GenerationGuarantees,
+            MinCorridorWidthConstraint, MinTileCountConstraint,
+        },
+        influence::{InfluenceHeatmap, InfluenceSource, TilemapInfluence},
         pathfinding::{Path, PathFinder},
+        rules::{RuleCell, RuleOutput, TileRule, TilemapPostProcessor},
+        scatter::{BlueNoiseScatter, PoissonDiskScatter},
+        surface::TileSpawnSurface,
         wfc::WfcRunner,
     };
+    #[cfg(feature = "editor")]
+    pub use crate::editor::{EditorTool, EntiTilesEditorPlugin, TileEditor};
+    #[cfg(feature = "egui")]
+    pub use crate::inspector::{EntiTilesInspectorPlugin, TilemapInspector};
     #[cfg(feature = "ldtk")]
     pub use crate::ldtk::resources::{LdtkAssets, LdtkLevelManager};
     pub use crate::math::{aabb::Aabb2d, TileArea};
+    #[cfg(feature = "minimap")]
+    pub use crate::render::minimap::{MinimapMarker, MinimapMaterial};
+    pub use crate::render::{
+        cull::TilemapVisibleTiles,
+        material::{DissolveTilemapMaterial, StandardTilemapMaterial},
+        prewarm::{TilemapPrewarm, TilemapPrewarmRequest},
+    };
     #[cfg(feature = "serializing")]
     pub use crate::serializing::{
         chunk::{
             load::{ChunkLoadCache, ChunkLoadConfig},
             save::{ChunkSaveCache, ChunkSaveConfig},
+            stream::ChunkStreaming,
+            ChunkSerializingProgress,
+        },
+        csv::LayerGrid,
+        map::{
+            hook::{TilemapSerializerHook, TilemapSerializerHooks, TypedSerializerHook},
+            load::{TilemapLoader, TilemapTextureMissing, TilemapTexturePlaceholder},
+            save::TilemapSaver,
+            TilemapDirtyChunks, TilemapTileMirror,
         },
-        map::{load::TilemapLoader, save::TilemapSaver},
+        slot::SaveSlots,
     };
     #[cfg(feature = "tiled")]
     pub use crate::tiled::resources::{TiledLoadConfig, TiledTilemapManger};
-    #[cfg(feature = "physics")]
-    pub use crate::tilemap::physics::{DataPhysicsTilemap, PhysicsTile, PhysicsTilemap};
+    #[cfg(feature = "algorithm")]
+    pub use crate::tilemap::algorithm::path::{PathCapabilities, PathTilemapGenerator};
+    #[cfg(feature = "physics_core")]
+    pub use crate::tilemap::physics::{
+        backend::{EntiTilesPhysicsBackendPlugin, TilemapPhysicsBackend},
+        DataPhysicsTilemap, PhysicsTile, PhysicsTilemap, PlatformVelocityDriven,
+        TilemapPhysicsPlatform,
+    };
     pub use crate::tilemap::{
+        behavior::{
+            TileBehaviorActivator, TileBehaviorKey, TileBehaviorTrigger, TileBehaviors,
+            TileInteraction,
+        },
+        budget::{TileSpawnBudget, TileSpawnQueue},
+        buffers::PureColorTileBuffer,
         bundles::{StandardPureColorTilemapBundle, StandardTilemapBundle},
-        chunking::camera::{CameraChunkUpdater, CameraChunkUpdation},
+        chunking::{
+            camera::{CameraChunkUpdater, CameraChunkUpdation},
+            compression::TilemapChunkCompression,
+            storage::ChunkCompressionConfig,
+        },
+        conveyor::{ConveyanceMode, TileConveyed},
+        fade::{TilemapFade, TilemapFadeCompleted, TilemapOpacity},
+        floor::{TilemapFloorChanged, TilemapFloors},
+        flow::{TilemapFlowArrows, TilemapFlowField},
+        height::{CliffEdges, CliffTileSet, TilemapHeights},
+        hex::{
+            HexCorner, HexCornerSide, HexDirection, HexEdge, HexEdgeOverlay, HexEdgeOverlayTilemap,
+        },
+        hierarchy::TilemapTileHierarchy,
+        id::{TilemapId, TilemapIds},
+        lookup::TilemapTextureLookup,
         map::{
-            TilePivot, TileRenderSize, TilemapAnimations, TilemapLayerOpacities, TilemapName,
-            TilemapSlotSize, TilemapStorage, TilemapTexture, TilemapTextureDescriptor,
-            TilemapTransform, TilemapType,
+            AnimationGroup, TilePivot, TileRenderSize, TilemapAnimationGroups, TilemapAnimations,
+            TilemapChunkTints, TilemapCompactVertices, TilemapGrid, TilemapLayerOpacities,
+            TilemapName, TilemapSlotSize, TilemapStatic, TilemapStorage, TilemapTexture,
+            TilemapTextureDescriptor, TilemapTileSortKey, TilemapTransform, TilemapType,
+            TilemapUniformTint,
+        },
+        marker::{TileMarkers, TileMarkersSource},
+        metadata::{TileAlpha, TilemapTextureMetadata},
+        object::{TileObject, TileObjectBuilder, TilemapObjects},
+        origin::{FloatingOrigin, TilemapAnchor},
+        ownership::{OwnerId, TilemapOwnership},
+        packed::TilemapPackedTiles,
+        pool::{PooledTilemap, TilemapPool},
+        portal::{
+            PortalTargetMissing, PortalTraveled, PortalTraveler, TilePortal, TilemapPortals,
+        },
+        sensor::{
+            TilePosition, TileSensors, TileTriggerActivator, TileTriggerEntered,
+            TileTriggerExited,
+        },
+        tile::{RawTileAnimation, TileBuilder, TileJitter, TileLayer, TileOffset, TileUpdater},
+        transaction::{
+            TilemapEditJournal, TilemapTransaction, TilemapTransactionApplied,
+            TilemapTransactionFailed, TilemapTransactions,
         },
-        tile::{RawTileAnimation, TileBuilder, TileLayer, TileUpdater},
+        validation::{TilemapIssue, ValidateTilemap},
+        wall::{TileEdge, TileSide, TilemapWalls, WallOverlayTilemap},
+        weather::TileSkyExposure,
+        zone::{TileZoneChanged, TileZoneTracker, TileZones},
     };
 }
 
@@ -67,6 +159,7 @@ impl Plugin for EntiTilesPlugin {
             EntiTilesTilemapPlugin,
             EntiTilesRendererPlugin,
             EntiTilesMaterialPlugin::<StandardTilemapMaterial>::default(),
+            EntiTilesMaterialPlugin::<DissolveTilemapMaterial>::default(),
             EntiTilesMathPlugin,
             EntiTilesShaderPlugin,
             #[cfg(feature = "debug")]
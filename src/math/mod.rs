@@ -6,13 +6,18 @@ use bevy::{
         query::{Added, Changed, Or},
         system::{Commands, Query},
     },
-    math::{IVec2, Vec3Swizzles},
+    math::{IVec2, Vec2, Vec3Swizzles},
     prelude::UVec2,
     reflect::Reflect,
     render::camera::{Camera, OrthographicProjection},
     transform::components::Transform,
 };
 
+use crate::tilemap::{
+    coordinates,
+    map::{TilemapAabbs, TilemapAxisFlip, TilemapTransform, TilemapType},
+};
+
 use self::aabb::{Aabb2d, IAabb2d};
 
 pub mod aabb;
@@ -74,6 +79,73 @@ pub fn camera_aabb_updater(
     });
 }
 
+/// Move and scale the orthographic camera so that the whole tilemap is visible,
+/// leaving `padding` world units around it.
+///
+/// This relies on `OrthographicProjection::area`, which is calculated by bevy
+/// after the camera is rendered for the first time.
+pub fn fit_camera_to_tilemap(
+    transform: &mut Transform,
+    projection: &mut OrthographicProjection,
+    tilemap: &TilemapAabbs,
+    padding: f32,
+) {
+    let unscaled = projection.area.size() / projection.scale;
+    if unscaled.cmple(Vec2::ZERO).any() {
+        return;
+    }
+
+    let target = tilemap.world_aabb();
+    let old_scale = projection.scale;
+    projection.scale = ((target.size() + padding * 2.) / unscaled).max_element();
+    // The viewport is not necessarily centered on the camera.
+    let offset = projection.area.center() / old_scale * projection.scale;
+    transform.translation = (target.center() - offset).extend(transform.translation.z);
+}
+
+/// The tiles of the tilemap that are visible to the camera, or `None` if
+/// the tilemap is out of sight.
+///
+/// For isometric and hexagonal tilemaps, this is the smallest index range
+/// covering the visible area, so a few tiles in the corners are not actually visible.
+pub fn tilemap_visible_region(
+    camera: &CameraAabb2d,
+    tilemap: &TilemapAabbs,
+    ty: TilemapType,
+    transform: &TilemapTransform,
+    pivot: Vec2,
+    slot_size: Vec2,
+    axis_flip: TilemapAxisFlip,
+) -> Option<IAabb2d> {
+    if !camera.0.is_intersected(tilemap.world_aabb()) {
+        return None;
+    }
+
+    // Keep the corners inside, as the edges on the max side belong to the next tiles.
+    let visible = camera.0.intersection(tilemap.world_aabb());
+    let visible = Aabb2d {
+        min: visible.min,
+        max: visible.max - 1e-3,
+    };
+    let mut region = IAabb2d {
+        min: IVec2::MAX,
+        max: IVec2::MIN,
+    };
+    [
+        visible.min,
+        visible.max,
+        Vec2::new(visible.min.x, visible.max.y),
+        Vec2::new(visible.max.x, visible.min.y),
+    ]
+    .into_iter()
+    .for_each(|corner| {
+        region.expand_to_contain(coordinates::world_to_index_flipped(
+            corner, ty, transform, pivot, slot_size, axis_flip,
+        ))
+    });
+    Some(region)
+}
+
 #[derive(Debug, Clone, Copy, Reflect)]
 pub struct TileArea {
     pub origin: IVec2,
@@ -114,3 +186,56 @@ impl TileArea {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use bevy::math::{Rect, Vec3};
+
+    use super::*;
+
+    #[test]
+    fn test_camera_helpers() {
+        let tilemap = TilemapAabbs {
+            world_aabb: Aabb2d::new(0., 0., 320., 160.),
+            ..Default::default()
+        };
+
+        let mut transform = Transform::from_translation(Vec3::Z);
+        let mut projection = OrthographicProjection {
+            area: Rect::new(-50., -50., 50., 50.),
+            ..Default::default()
+        };
+        fit_camera_to_tilemap(&mut transform, &mut projection, &tilemap, 10.);
+        assert_eq!(projection.scale, 3.4);
+        assert_eq!(transform.translation, Vec3::new(160., 80., 1.));
+
+        let camera = CameraAabb2d(Aabb2d::new(-100., 40., 100., 200.));
+        let region = |axis_flip| {
+            tilemap_visible_region(
+                &camera,
+                &tilemap,
+                TilemapType::Square,
+                &TilemapTransform::default(),
+                Vec2::ZERO,
+                Vec2::splat(16.),
+                axis_flip,
+            )
+        };
+        let flipped = region(TilemapAxisFlip::Y).unwrap();
+        let region = region(TilemapAxisFlip::NONE).unwrap();
+        assert_eq!(region.min, IVec2::new(0, 2));
+        assert_eq!(region.max, IVec2::new(6, 9));
+        assert_eq!(flipped.min, IVec2::new(0, -10));
+        assert_eq!(flipped.max, IVec2::new(6, -3));
+        assert!(tilemap_visible_region(
+            &CameraAabb2d(Aabb2d::new(-100., -100., -10., -10.)),
+            &tilemap,
+            TilemapType::Square,
+            &TilemapTransform::default(),
+            Vec2::ZERO,
+            Vec2::splat(16.),
+            TilemapAxisFlip::NONE,
+        )
+        .is_none());
+    }
+}
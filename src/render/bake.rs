@@ -40,11 +40,31 @@ pub struct BakedTilemap {
     pub size_px: UVec2,
     pub slot_size: Vec2,
     pub tile_render_size: Vec2,
+    /// The indices of the tiles covered by the texture.
+    pub tile_aabb: IAabb2d,
     /// Ignore the `Option`, it's just used for taking the `Image` out without cloning.
     /// You can always unwrap this.
     pub texture: Option<Image>,
 }
 
+/// Exports the tilemap into a png file, composited on the CPU without rendering.
+/// Useful for documentation, thumbnails or diffing maps in CI.
+///
+/// The component is removed once the tilemap is exported.
+#[derive(Component, Reflect)]
+pub struct TilemapExporter {
+    pub path: String,
+}
+
+impl BakedTilemap {
+    /// Get the uv of the center of the tile in the baked texture.
+    pub fn index_to_uv(&self, index: IVec2) -> Vec2 {
+        let size = self.tile_aabb.size().as_vec2();
+        let rel = (index - self.tile_aabb.min).as_vec2() + 0.5;
+        Vec2::new(rel.x / size.x, 1. - rel.y / size.y)
+    }
+}
+
 pub fn tilemap_baker(
     mut commands: Commands,
     mut tilemaps_query: Query<(
@@ -62,94 +82,24 @@ pub fn tilemap_baker(
     for (tilemap_entity, tile_render_size, slot_size, mut storage, opacities, texture, baker) in
         &mut tilemaps_query
     {
-        let chunk_size = storage.storage.chunk_size as i32;
-        let mut tilemap_aabb = IAabb2d::default();
-
-        let tiles = storage
-            .storage
-            .chunks
-            .iter()
-            .flat_map(|(ci, c)| {
-                c.iter().enumerate().filter_map(move |(ti, t)| {
-                    if let Some(tile) = t {
-                        Some((
-                            *ci * chunk_size
-                                + IVec2 {
-                                    x: ti as i32 % chunk_size,
-                                    y: ti as i32 / chunk_size,
-                                },
-                            *tile,
-                        ))
-                    } else {
-                        None
-                    }
-                })
-            })
-            .filter_map(|(tile_index, tile_entity)| {
-                let Ok(tile) = tiles_query.get(tile_entity) else {
-                    return None;
-                };
-
-                tilemap_aabb.expand_to_contain(tile_index);
-                Some((tile_index, tile))
-            })
-            .collect::<Vec<_>>();
-
         let texture_image = image_assets.get(texture.handle()).unwrap();
-        let target_size = tilemap_aabb.size().as_uvec2() * texture.desc.tile_size;
-        let mut bake_target = vec![0; (target_size.x * target_size.y * 4) as usize];
-
-        tiles.into_iter().for_each(|(tile_index, tile)| {
-            let mut rel_index = (tile_index - tilemap_aabb.min).as_uvec2();
-            rel_index.y = tilemap_aabb.size().y as u32 - rel_index.y - 1;
-
-            match &tile.texture {
-                TileTexture::Static(layers) => layers
-                    .iter()
-                    .rev()
-                    .take(MAX_LAYER_COUNT)
-                    .enumerate()
-                    .filter_map(|(i, l)| {
-                        if l.texture_index >= 0 {
-                            Some((opacities.0[i], l))
-                        } else {
-                            None
-                        }
-                    })
-                    .for_each(|(opacity, layer)| {
-                        set_tile(
-                            texture,
-                            texture_image,
-                            rel_index,
-                            target_size,
-                            &mut bake_target,
-                            layer,
-                            opacity,
-                        );
-                    }),
-                TileTexture::Animated(_) => {
-                    warn!("Skipping animated tile at {:?}", tile_index);
-                }
-            };
-
-            set_tile_tint(texture, rel_index, target_size, &mut bake_target, tile.tint);
-        });
+        if !is_bakeable(texture_image) {
+            warn!(
+                "Unable to bake tilemap {:?}, the texture format {:?} is not supported!",
+                tilemap_entity, texture_image.texture_descriptor.format
+            );
+            commands.entity(tilemap_entity).remove::<TilemapBaker>();
+            continue;
+        }
 
+        let (baked, tile_aabb) =
+            bake_tiles_in_aabb(&storage, &tiles_query, opacities, texture, texture_image);
         let baked_tilemap = BakedTilemap {
-            size_px: target_size,
+            size_px: baked.size(),
             slot_size: slot_size.0,
             tile_render_size: tile_render_size.0,
-            texture: Some(Image::new(
-                Extent3d {
-                    width: target_size.x,
-                    height: target_size.y,
-                    depth_or_array_layers: 1,
-                },
-                TextureDimension::D2,
-                bake_target,
-                TextureFormat::bevy_default(),
-                RenderAssetUsages::all(),
-            )),
+            tile_aabb,
+            texture: Some(baked),
         };
 
         commands.entity(tilemap_entity).remove::<TilemapBaker>();
@@ -163,6 +113,146 @@ pub fn tilemap_baker(
     }
 }
 
+pub fn tilemap_exporter(
+    mut commands: Commands,
+    tilemaps_query: Query<(
+        Entity,
+        &TilemapStorage,
+        &TilemapLayerOpacities,
+        &TilemapTexture,
+        &TilemapExporter,
+    )>,
+    tiles_query: Query<&Tile>,
+    image_assets: Res<Assets<Image>>,
+) {
+    for (tilemap_entity, storage, opacities, texture, exporter) in &tilemaps_query {
+        // Wait for the texture to be loaded.
+        let Some(texture_image) = image_assets.get(texture.handle()) else {
+            continue;
+        };
+
+        commands.entity(tilemap_entity).remove::<TilemapExporter>();
+        if !is_bakeable(texture_image) {
+            warn!(
+                "Unable to export tilemap {:?}, the texture format {:?} is not supported!",
+                tilemap_entity, texture_image.texture_descriptor.format
+            );
+            continue;
+        }
+
+        let result = bake_tiles(storage, &tiles_query, opacities, texture, texture_image)
+            .try_into_dynamic()
+            .map_err(|err| err.to_string())
+            .and_then(|image| image.save(&exporter.path).map_err(|err| err.to_string()));
+
+        if let Err(err) = result {
+            warn!(
+                "Failed to export tilemap {:?} to {}: {}",
+                tilemap_entity, exporter.path, err
+            );
+        }
+    }
+}
+
+/// The pixels are read directly, so compressed textures can't be baked.
+#[inline]
+fn is_bakeable(texture_image: &Image) -> bool {
+    matches!(
+        texture_image.texture_descriptor.format,
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb
+    )
+}
+
+/// Composite the tiles into an image on the CPU. The texture must be `Rgba8`.
+///
+/// The image covers the aabb of all the tiles, and animated tiles are skipped.
+pub fn bake_tiles(
+    storage: &TilemapStorage,
+    tiles_query: &Query<&Tile>,
+    opacities: &TilemapLayerOpacities,
+    texture: &TilemapTexture,
+    texture_image: &Image,
+) -> Image {
+    bake_tiles_in_aabb(storage, tiles_query, opacities, texture, texture_image).0
+}
+
+/// Same as `bake_tiles`, but also returns the indices of the tiles covered by the image.
+pub fn bake_tiles_in_aabb(
+    storage: &TilemapStorage,
+    tiles_query: &Query<&Tile>,
+    opacities: &TilemapLayerOpacities,
+    texture: &TilemapTexture,
+    texture_image: &Image,
+) -> (Image, IAabb2d) {
+    let mut tilemap_aabb = IAabb2d::default();
+
+    let tiles = storage
+        .storage
+        .chunked_iter_some()
+        .map(|(ci, ti, tile)| (storage.storage.inverse_transform_index(ci, ti), *tile))
+        .filter_map(|(tile_index, tile_entity)| {
+            let Ok(tile) = tiles_query.get(tile_entity) else {
+                return None;
+            };
+
+            tilemap_aabb.expand_to_contain(tile_index);
+            Some((tile_index, tile))
+        })
+        .collect::<Vec<_>>();
+
+    let target_size = tilemap_aabb.size().as_uvec2() * texture.desc.tile_size;
+    let mut bake_target = vec![0; (target_size.x * target_size.y * 4) as usize];
+
+    tiles.into_iter().for_each(|(tile_index, tile)| {
+        let mut rel_index = (tile_index - tilemap_aabb.min).as_uvec2();
+        rel_index.y = tilemap_aabb.size().y as u32 - rel_index.y - 1;
+
+        match &tile.texture {
+            TileTexture::Static(layers) => layers
+                .iter()
+                .rev()
+                .take(MAX_LAYER_COUNT)
+                .enumerate()
+                .filter_map(|(i, l)| {
+                    if l.texture_index >= 0 {
+                        Some((opacities.0[i], l))
+                    } else {
+                        None
+                    }
+                })
+                .for_each(|(opacity, layer)| {
+                    set_tile(
+                        texture,
+                        texture_image,
+                        rel_index,
+                        target_size,
+                        &mut bake_target,
+                        layer,
+                        opacity,
+                    );
+                }),
+            TileTexture::Animated(_) => {
+                warn!("Skipping animated tile at {:?}", tile_index);
+            }
+        };
+
+        set_tile_tint(texture, rel_index, target_size, &mut bake_target, tile.tint);
+    });
+
+    let image = Image::new(
+        Extent3d {
+            width: target_size.x,
+            height: target_size.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        bake_target,
+        TextureFormat::bevy_default(),
+        RenderAssetUsages::all(),
+    );
+    (image, tilemap_aabb)
+}
+
 fn set_tile(
     texture: &TilemapTexture,
     texture_image: &Image,
@@ -1,5 +1,5 @@
 use bevy::{
-    asset::{AssetId, Handle},
+    asset::AssetId,
     ecs::{component::Component, entity::EntityHashMap, system::Resource, world::FromWorld},
     render::{
         render_asset::RenderAssets,
@@ -19,11 +19,9 @@ use super::{
         PerTilemapBuffersStorage, TilemapStorageBuffers, TilemapUniform, TilemapUniformBuffer,
         UniformBuffer,
     },
-    extract::ExtractedTilemap,
     material::TilemapMaterial,
     pipeline::EntiTilesPipeline,
     resources::ExtractedTilemapMaterials,
-    texture::TilemapTexturesStorage,
 };
 
 use bevy::render::render_resource::binding_types as binding;
@@ -37,7 +35,6 @@ pub struct TilemapViewBindGroup {
 pub struct TilemapBindGroups<M: TilemapMaterial> {
     pub tilemap_uniform_buffer: Option<BindGroup>,
     pub tilemap_storage_buffers: EntityHashMap<BindGroup>,
-    pub colored_textures: HashMap<Handle<Image>, BindGroup>,
     pub material_bind_groups: HashMap<AssetId<M>, BindGroup>,
 }
 
@@ -46,7 +43,6 @@ impl<M: TilemapMaterial> Default for TilemapBindGroups<M> {
         Self {
             tilemap_uniform_buffer: Default::default(),
             tilemap_storage_buffers: Default::default(),
-            colored_textures: Default::default(),
             material_bind_groups: Default::default(),
         }
     }
@@ -109,36 +105,6 @@ impl<M: TilemapMaterial> TilemapBindGroups<M> {
                 self.material_bind_groups.insert(*id, bind_group.bind_group);
             });
     }
-
-    /// Returns is_pure_color
-    pub fn queue_textures(
-        &mut self,
-        tilemap: &ExtractedTilemap<M>,
-        render_device: &RenderDevice,
-        textures_storage: &TilemapTexturesStorage,
-        entitile_pipeline: &EntiTilesPipeline<M>,
-    ) -> bool {
-        let Some(tilemap_texture) = &tilemap.texture else {
-            return true;
-        };
-
-        let Some(texture) = textures_storage.get_texture(tilemap_texture.handle()) else {
-            return !textures_storage.contains(tilemap_texture.handle());
-        };
-
-        if !self.colored_textures.contains_key(tilemap_texture.handle()) {
-            self.colored_textures.insert(
-                tilemap_texture.clone_weak(),
-                render_device.create_bind_group(
-                    Some("color_texture_bind_group"),
-                    &entitile_pipeline.color_texture_layout,
-                    &BindGroupEntries::sequential((&texture.texture_view, &texture.sampler)),
-                ),
-            );
-        }
-
-        false
-    }
 }
 
 #[derive(Resource)]
@@ -147,6 +113,7 @@ pub struct TilemapBindGroupLayouts {
     pub tilemap_uniforms_layout: BindGroupLayout,
     pub tilemap_storage_layout: BindGroupLayout,
     pub color_texture_layout: BindGroupLayout,
+    pub color_texture_atlas_layout: BindGroupLayout,
 }
 
 impl FromWorld for TilemapBindGroupLayouts {
@@ -176,7 +143,6 @@ impl FromWorld for TilemapBindGroupLayouts {
             ),
         );
 
-        #[cfg(not(feature = "atlas"))]
         let color_texture_layout = render_device.create_bind_group_layout(
             "color_texture_layout",
             &BindGroupLayoutEntries::sequential(
@@ -188,9 +154,8 @@ impl FromWorld for TilemapBindGroupLayouts {
             ),
         );
 
-        #[cfg(feature = "atlas")]
-        let color_texture_layout = render_device.create_bind_group_layout(
-            "color_texture_layout",
+        let color_texture_atlas_layout = render_device.create_bind_group_layout(
+            "color_texture_atlas_layout",
             &BindGroupLayoutEntries::sequential(
                 ShaderStages::FRAGMENT,
                 (
@@ -205,6 +170,7 @@ impl FromWorld for TilemapBindGroupLayouts {
             tilemap_uniforms_layout,
             tilemap_storage_layout,
             color_texture_layout,
+            color_texture_atlas_layout,
         }
     }
 }
@@ -2,7 +2,7 @@ use std::marker::PhantomData;
 
 use bevy::{
     ecs::entity::{Entity, EntityHashMap},
-    math::{Mat2, Vec4},
+    math::{IVec2, Mat2, Vec4},
     prelude::{Component, Resource, Vec2},
     render::{
         render_resource::{
@@ -102,10 +102,25 @@ pub struct TilemapUniform {
     pub axis_dir: Vec2,
     pub hex_legs: f32,
     pub time: f32,
-    #[cfg(feature = "atlas")]
-    pub texture_tiled_size: bevy::math::IVec2,
-    #[cfg(feature = "atlas")]
+    pub opacity: f32,
+    pub grid_color: Vec4,
+    pub checker_tint: Vec4,
+    pub grid_thickness: f32,
+    pub index_offset: IVec2,
+    // Where the animation groups start in the storage buffer.
+    pub anim_groups_offset: i32,
+    pub anim_group_count: u32,
+    pub chunk_size: u32,
+    // Where the chunk tints start in the storage buffer.
+    pub chunk_tints_offset: i32,
+    pub chunk_tint_count: u32,
+    // Only used by texture atlases.
+    pub texture_tiled_size: IVec2,
     pub tile_uv_size: Vec2,
+    pub tile_uv_stride: Vec2,
+    pub uv_margin: Vec2,
+    // See `TilemapUniformTint`.
+    pub tint: Vec4,
 }
 
 #[derive(Resource)]
@@ -123,16 +138,33 @@ impl<M: TilemapMaterial> Default for TilemapUniformBuffer<M> {
     }
 }
 
-impl<M: TilemapMaterial> UniformBuffer<(&ExtractedTilemap<M>, f32), TilemapUniform>
+/// The offset and the count of the animation groups in `TilemapStorageBuffers`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AnimationGroupsSlice {
+    pub offset: i32,
+    pub count: u32,
+}
+
+/// The tints of the tinted chunks in `TilemapStorageBuffers`, stored as
+/// `(chunk.x, chunk.y, rgba bits)` and sorted by `(chunk.y, chunk.x)`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ChunkTintsSlice {
+    pub offset: i32,
+    pub count: u32,
+}
+
+impl<M: TilemapMaterial>
+    UniformBuffer<(&ExtractedTilemap<M>, f32, AnimationGroupsSlice, ChunkTintsSlice), TilemapUniform>
     for TilemapUniformBuffer<M>
 {
     /// Update the uniform buffer with the current tilemap uniforms.
     /// Returns the `TilemapUniform` component to be used in the tilemap render pass.
     fn insert(
         &mut self,
-        extracted: &(&ExtractedTilemap<M>, f32),
+        extracted: &(&ExtractedTilemap<M>, f32, AnimationGroupsSlice, ChunkTintsSlice),
     ) -> DynamicOffsetComponent<TilemapUniform> {
-        let (extracted, time) = (&extracted.0, extracted.1);
+        let (extracted, time, anim_groups, chunk_tints) =
+            (&extracted.0, extracted.1, extracted.2, extracted.3);
 
         let uv_rotation = {
             if let Some(tex) = extracted.texture.as_ref() {
@@ -142,18 +174,31 @@ impl<M: TilemapMaterial> UniformBuffer<(&ExtractedTilemap<M>, f32), TilemapUnifo
             }
         };
 
-        #[cfg(feature = "atlas")]
-        let (texture_tiled_size, tile_uv_size) = {
+        let (texture_tiled_size, tile_uv_size, tile_uv_stride, uv_margin) = {
             if let Some(tex) = extracted.texture.as_ref() {
+                let size = tex.desc.size.as_vec2();
                 (
-                    (tex.desc.size / tex.desc.tile_size).as_ivec2(),
-                    tex.desc.tile_size.as_vec2() / tex.desc.size.as_vec2(),
+                    tex.desc.tile_count().as_ivec2(),
+                    tex.desc.tile_size.as_vec2() / size,
+                    (tex.desc.tile_size + tex.desc.spacing).as_vec2() / size,
+                    Vec2::splat(tex.desc.margin as f32) / size,
                 )
             } else {
-                (bevy::math::IVec2::ZERO, Vec2::ZERO)
+                (IVec2::ZERO, Vec2::ZERO, Vec2::ZERO, Vec2::ZERO)
             }
         };
 
+        let (grid_color, checker_tint, grid_thickness) = match extracted.grid {
+            Some(grid) => (
+                grid.line_color.rgba_linear_to_vec4(),
+                grid.checker_tint
+                    .map(|tint| tint.rgba_linear_to_vec4())
+                    .unwrap_or(Vec4::ONE),
+                grid.line_thickness,
+            ),
+            None => (Vec4::ZERO, Vec4::ONE, 0.),
+        };
+
         DynamicOffsetComponent::new(self.buffer().push(&TilemapUniform {
             translation: extracted.transform.translation,
             rotation: extracted.transform.get_rotation_matrix(),
@@ -168,10 +213,21 @@ impl<M: TilemapMaterial> UniformBuffer<(&ExtractedTilemap<M>, f32), TilemapUnifo
                 _ => 0.,
             },
             time,
-            #[cfg(feature = "atlas")]
+            opacity: extracted.opacity,
+            grid_color,
+            checker_tint,
+            grid_thickness,
+            index_offset: extracted.index_offset,
+            anim_groups_offset: anim_groups.offset,
+            anim_group_count: anim_groups.count,
+            chunk_size: extracted.chunk_size,
+            chunk_tints_offset: chunk_tints.offset,
+            chunk_tint_count: chunk_tints.count,
             texture_tiled_size,
-            #[cfg(feature = "atlas")]
             tile_uv_size,
+            tile_uv_stride,
+            uv_margin,
+            tint: extracted.uniform_tint.unwrap_or(Vec4::ONE),
         }))
     }
 
@@ -181,6 +237,9 @@ impl<M: TilemapMaterial> UniformBuffer<(&ExtractedTilemap<M>, f32), TilemapUnifo
     }
 }
 
+/// The animation sequences of each tilemap, followed by the animation groups and the chunk tints.
+/// The groups are stored as the bits of `(elapsed, phase.x, phase.y, 0)`,
+/// and the tints as the bits of their linear rgba.
 #[derive(Resource, Default)]
 pub struct TilemapStorageBuffers(EntityHashMap<(StorageBuffer<Vec<i32>>, Vec<i32>)>);
 
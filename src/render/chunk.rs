@@ -1,15 +1,22 @@
-use std::marker::PhantomData;
+use std::{marker::PhantomData, ops::Range};
 
 use bevy::{
-    ecs::{component::Component, entity::EntityHashMap, event::Event},
+    core::cast_slice,
+    ecs::{
+        component::Component,
+        entity::{EntityHashMap, EntityHashSet},
+        event::Event,
+    },
     math::{IVec2, IVec4, UVec4},
     prelude::{Entity, Mesh, Resource, Vec3, Vec4},
     reflect::Reflect,
     render::{
-        mesh::{GpuBufferInfo, GpuMesh, Indices},
+        mesh::{GpuBufferInfo, GpuMesh, Indices, VertexAttributeValues},
         render_asset::RenderAssetUsages,
-        render_resource::{BufferInitDescriptor, BufferUsages, IndexFormat, PrimitiveTopology},
-        renderer::RenderDevice,
+        render_resource::{
+            Buffer, BufferInitDescriptor, BufferUsages, IndexFormat, PrimitiveTopology,
+        },
+        renderer::{RenderDevice, RenderQueue},
     },
     utils::HashMap,
 };
@@ -17,7 +24,7 @@ use bevy::{
 use crate::{
     math::{aabb::Aabb2d, extension::DivToFloor},
     tilemap::{
-        map::{TilemapTexture, TilemapType},
+        map::{TilemapTexture, TilemapTileSortKey, TilemapType},
         tile::TileTexture,
     },
     MAX_LAYER_COUNT,
@@ -26,8 +33,9 @@ use crate::{
 use super::{
     extract::{ExtractedTile, ExtractedTilemap},
     material::TilemapMaterial,
-    TILEMAP_MESH_ATTR_COLOR, TILEMAP_MESH_ATTR_FLIP, TILEMAP_MESH_ATTR_INDEX,
-    TILEMAP_MESH_ATTR_TEX_INDICES,
+    TILEMAP_MESH_ATTR_COLOR, TILEMAP_MESH_ATTR_COMPACT_COLOR, TILEMAP_MESH_ATTR_COMPACT_FLIP,
+    TILEMAP_MESH_ATTR_COMPACT_POSITION, TILEMAP_MESH_ATTR_COMPACT_TEX_INDICES,
+    TILEMAP_MESH_ATTR_FLIP, TILEMAP_MESH_ATTR_INDEX, TILEMAP_MESH_ATTR_TEX_INDICES,
 };
 
 #[derive(Component, Default, Debug, Clone, Reflect)]
@@ -49,6 +57,8 @@ pub struct MeshTileData {
     pub texture_indices: IVec4,
     pub tint: Vec4,
     pub flip: UVec4,
+    // The translation and the rotation of the `TileOffset`.
+    pub offset: Vec3,
 }
 
 #[derive(Clone)]
@@ -60,6 +70,12 @@ pub struct TilemapRenderChunk<M: TilemapMaterial> {
     pub size: u32,
     pub texture: Option<TilemapTexture>,
     pub tiles: Vec<Option<MeshTileData>>,
+    /// Tiles with greater keys are drawn later.
+    pub sort_key: fn(IVec2) -> i64,
+    /// Whether the mesh uses the compact vertex format. See `TilemapCompactVertices`.
+    pub compact: bool,
+    /// Whether the mesh leaves out the tints. See `TilemapUniformTint`.
+    pub uniform_tint: bool,
     pub mesh: Mesh,
     pub gpu_mesh: Option<GpuMesh>,
     pub aabb: Aabb2d,
@@ -68,37 +84,110 @@ pub struct TilemapRenderChunk<M: TilemapMaterial> {
 
 impl<M: TilemapMaterial> TilemapRenderChunk<M> {
     pub fn from_index(index: IVec2, tilemap: &ExtractedTilemap<M>) -> Self {
+        Self::with_buffers(
+            index,
+            tilemap,
+            vec![None; (tilemap.chunk_size * tilemap.chunk_size) as usize],
+            None,
+        )
+    }
+
+    /// Reuse a pooled chunk for another tilemap, keeping its allocations and GPU buffers.
+    pub fn recycle(self, index: IVec2, tilemap: &ExtractedTilemap<M>) -> Self {
+        let mut tiles = self.tiles;
+        tiles.clear();
+        tiles.resize((tilemap.chunk_size * tilemap.chunk_size) as usize, None);
+        Self::with_buffers(index, tilemap, tiles, self.gpu_mesh)
+    }
+
+    fn with_buffers(
+        index: IVec2,
+        tilemap: &ExtractedTilemap<M>,
+        tiles: Vec<Option<MeshTileData>>,
+        gpu_mesh: Option<GpuMesh>,
+    ) -> Self {
         TilemapRenderChunk {
             visible: true,
             index: index.div_to_floor(IVec2::splat(tilemap.chunk_size as i32)),
             size: tilemap.chunk_size,
             ty: tilemap.ty,
             texture: tilemap.texture.clone(),
-            tiles: vec![None; (tilemap.chunk_size * tilemap.chunk_size) as usize],
+            tiles,
+            sort_key: TilemapTileSortKey::default().0,
+            compact: tilemap.compact_vertices,
+            uniform_tint: tilemap.uniform_tint.is_some(),
             mesh: Mesh::new(
                 PrimitiveTopology::TriangleList,
                 RenderAssetUsages::RENDER_WORLD,
             ),
-            gpu_mesh: None,
+            gpu_mesh,
             dirty_mesh: true,
-            aabb: Aabb2d::from_tilemap(
-                index,
-                tilemap.chunk_size,
-                tilemap.ty,
-                tilemap.tile_pivot,
-                tilemap.axis_flip,
-                tilemap.slot_size,
-                tilemap.transform,
-            ),
+            aabb: tilemap.chunk_aabb(index),
             marker: PhantomData,
         }
     }
 
-    /// Update the raw mesh for GPU processing.
-    pub fn try_update_mesh(&mut self, render_device: &RenderDevice) {
-        if !self.dirty_mesh {
+    /// Update the raw mesh and upload it for GPU processing.
+    pub fn try_update_mesh(&mut self, render_device: &RenderDevice, render_queue: &RenderQueue) {
+        if !self.update_mesh() && self.gpu_mesh.is_some() {
             return;
         }
+
+        let mesh_vert_count = self.mesh.count_vertices() as u32;
+        let mesh_indices_count = self.mesh.indices().unwrap().len() as u32;
+
+        // Reuse the old buffers if they are large enough.
+        let (old_vertex_buffer, old_index_buffer) = match self.gpu_mesh.take() {
+            Some(gpu_mesh) => (
+                Some(gpu_mesh.vertex_buffer),
+                match gpu_mesh.buffer_info {
+                    GpuBufferInfo::Indexed { buffer, .. } => Some(buffer),
+                    GpuBufferInfo::NonIndexed => None,
+                },
+            ),
+            None => (None, None),
+        };
+
+        let vertex_buffer = write_or_create_buffer(
+            render_device,
+            render_queue,
+            old_vertex_buffer,
+            &self.mesh.get_vertex_buffer_data(),
+            "tilemap_vertex_buffer",
+            BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        );
+
+        let buffer_info =
+            self.mesh
+                .get_index_buffer_bytes()
+                .map_or(GpuBufferInfo::NonIndexed, |data| GpuBufferInfo::Indexed {
+                    buffer: write_or_create_buffer(
+                        render_device,
+                        render_queue,
+                        old_index_buffer,
+                        data,
+                        "tilemap_index_buffer",
+                        BufferUsages::INDEX | BufferUsages::COPY_DST,
+                    ),
+                    count: mesh_indices_count,
+                    index_format: IndexFormat::Uint32,
+                });
+
+        self.gpu_mesh = Some(GpuMesh {
+            vertex_buffer,
+            vertex_count: mesh_vert_count,
+            morph_targets: None,
+            buffer_info,
+            primitive_topology: PrimitiveTopology::TriangleList,
+            layout: self.mesh.get_mesh_vertex_buffer_layout(),
+        });
+    }
+
+    /// Rebuild the raw mesh if the chunk changed. Returns true if rebuilt.
+    pub fn update_mesh(&mut self) -> bool {
+        if !self.dirty_mesh {
+            return false;
+        }
         let is_pure_color = self.texture.is_none();
 
         let mut v_index = 0;
@@ -111,88 +200,115 @@ impl<M: TilemapMaterial> TilemapRenderChunk<M> {
         let mut color = Vec::with_capacity(len * 4);
         let mut flip = Vec::with_capacity(len * 4);
 
-        for tile_data in self.tiles.iter() {
-            if let Some(tile) = tile_data {
-                if !is_pure_color {
-                    texture_indices.extend_from_slice(&[
-                        tile.texture_indices,
-                        tile.texture_indices,
-                        tile.texture_indices,
-                        tile.texture_indices,
-                    ]);
-                }
+        let mut tiles = self
+            .tiles
+            .iter()
+            .filter_map(|t| t.as_ref())
+            .collect::<Vec<_>>();
+        tiles.sort_by_cached_key(|tile| (self.sort_key)(tile.index.truncate().truncate()));
+
+        for tile in tiles {
+            if !is_pure_color {
+                texture_indices.extend_from_slice(&[
+                    tile.texture_indices,
+                    tile.texture_indices,
+                    tile.texture_indices,
+                    tile.texture_indices,
+                ]);
+            }
 
-                let pos = Vec3::ZERO;
-                positions.extend_from_slice(&[pos, pos, pos, pos]);
+            let pos = tile.offset;
+            positions.extend_from_slice(&[pos, pos, pos, pos]);
 
-                vertex_indices.extend_from_slice(&[
-                    v_index,
-                    v_index + 1,
-                    v_index + 3,
-                    v_index + 1,
-                    v_index + 2,
-                    v_index + 3,
-                ]);
+            vertex_indices.extend_from_slice(&[
+                v_index,
+                v_index + 1,
+                v_index + 3,
+                v_index + 1,
+                v_index + 2,
+                v_index + 3,
+            ]);
 
-                v_index += 4;
+            v_index += 4;
 
-                grid_indices.extend_from_slice(&[tile.index, tile.index, tile.index, tile.index]);
+            grid_indices.extend_from_slice(&[tile.index, tile.index, tile.index, tile.index]);
+            if !self.uniform_tint {
                 color.extend_from_slice(&[tile.tint, tile.tint, tile.tint, tile.tint]);
-                flip.extend_from_slice(&[tile.flip, tile.flip, tile.flip, tile.flip]);
             }
+            flip.extend_from_slice(&[tile.flip, tile.flip, tile.flip, tile.flip]);
         }
 
-        self.mesh
-            .insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
         self.mesh
             .insert_attribute(TILEMAP_MESH_ATTR_INDEX, grid_indices);
-        self.mesh.insert_attribute(TILEMAP_MESH_ATTR_COLOR, color);
-        if !is_pure_color {
+        if self.compact {
+            self.mesh.insert_attribute(
+                TILEMAP_MESH_ATTR_COMPACT_POSITION,
+                VertexAttributeValues::Uint32x2(
+                    positions
+                        .into_iter()
+                        .map(|p| {
+                            [
+                                f32_to_f16_bits(p.x) | f32_to_f16_bits(p.y) << 16,
+                                f32_to_f16_bits(p.z),
+                            ]
+                        })
+                        .collect(),
+                ),
+            );
+            if !self.uniform_tint {
+                self.mesh.insert_attribute(
+                    TILEMAP_MESH_ATTR_COMPACT_COLOR,
+                    VertexAttributeValues::Unorm8x4(
+                        color
+                            .into_iter()
+                            .map(|c| (c.clamp(Vec4::ZERO, Vec4::ONE) * 255.).round().as_uvec4())
+                            .map(|c| [c.x as u8, c.y as u8, c.z as u8, c.w as u8])
+                            .collect(),
+                    ),
+                );
+            }
+            if !is_pure_color {
+                self.mesh.insert_attribute(
+                    TILEMAP_MESH_ATTR_COMPACT_TEX_INDICES,
+                    VertexAttributeValues::Sint16x4(
+                        texture_indices
+                            .into_iter()
+                            .map(|i| {
+                                let i = i.clamp(IVec4::splat(-1), IVec4::splat(i16::MAX as i32));
+                                [i.x as i16, i.y as i16, i.z as i16, i.w as i16]
+                            })
+                            .collect(),
+                    ),
+                );
+                self.mesh.insert_attribute(
+                    TILEMAP_MESH_ATTR_COMPACT_FLIP,
+                    VertexAttributeValues::Uint8x4(
+                        flip.into_iter()
+                            .map(|f| [f.x as u8, f.y as u8, f.z as u8, f.w as u8])
+                            .collect(),
+                    ),
+                );
+            }
+        } else {
             self.mesh
-                .insert_attribute(TILEMAP_MESH_ATTR_TEX_INDICES, texture_indices);
-            self.mesh.insert_attribute(TILEMAP_MESH_ATTR_FLIP, flip)
+                .insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+            if !self.uniform_tint {
+                self.mesh.insert_attribute(TILEMAP_MESH_ATTR_COLOR, color);
+            }
+            if !is_pure_color {
+                self.mesh
+                    .insert_attribute(TILEMAP_MESH_ATTR_TEX_INDICES, texture_indices);
+                self.mesh.insert_attribute(TILEMAP_MESH_ATTR_FLIP, flip)
+            }
         }
         self.mesh.insert_indices(Indices::U32(vertex_indices));
 
-        let mesh_vert_count = self.mesh.count_vertices() as u32;
-        let mesh_indices_count = self.mesh.indices().unwrap().len() as u32;
-
-        let vertex_buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
-            label: Some("tilemap_vertex_buffer"),
-            contents: &self.mesh.get_vertex_buffer_data(),
-            usage: BufferUsages::VERTEX,
-        });
-
-        let buffer_info =
-            self.mesh
-                .get_index_buffer_bytes()
-                .map_or(GpuBufferInfo::NonIndexed, |data| GpuBufferInfo::Indexed {
-                    buffer: render_device.create_buffer_with_data(&BufferInitDescriptor {
-                        label: Some("tilemap_index_buffer"),
-                        contents: data,
-                        usage: BufferUsages::INDEX,
-                    }),
-                    count: mesh_indices_count,
-                    index_format: IndexFormat::Uint32,
-                });
-
-        self.gpu_mesh = Some(GpuMesh {
-            vertex_buffer,
-            vertex_count: mesh_vert_count,
-            morph_targets: None,
-            buffer_info,
-            primitive_topology: PrimitiveTopology::TriangleList,
-            layout: self.mesh.get_mesh_vertex_buffer_layout(),
-        });
-
         self.dirty_mesh = false;
+        true
     }
 
     /// Set a tile in the chunk. Overwrites the previous tile.
     pub fn set_tile(&mut self, index: usize, tile: Option<&ExtractedTile>) {
-        // TODO fix this. This allows the tile sort by y axis. But this approach looks weird.
-        let index = self.tiles.len() - index - 1;
-
         let Some(tile) = tile else {
             self.tiles[index] = None;
             self.dirty_mesh = true;
@@ -218,7 +334,8 @@ impl<M: TilemapMaterial> TilemapRenderChunk<M> {
                     tile.index.x,
                     tile.index.y,
                     anim.start as i32,
-                    anim.length as i32,
+                    // The animation group is packed into the higher bits.
+                    (anim.length | anim.group << 16) as i32,
                 ),
             }
         };
@@ -228,34 +345,301 @@ impl<M: TilemapMaterial> TilemapRenderChunk<M> {
             texture_indices,
             tint: tile.tint.rgba_linear_to_vec4(),
             flip,
+            offset: tile.offset.translation.extend(tile.offset.rotation),
         });
         self.dirty_mesh = true;
     }
+
+    /// Switch between the default and the compact vertex format.
+    /// The mesh is rebuilt from scratch as the attributes are different.
+    pub fn set_compact(&mut self, compact: bool) {
+        if self.compact != compact {
+            self.compact = compact;
+            self.reset_mesh();
+        }
+    }
+
+    /// Leave out or bring back the tints of the tiles.
+    /// The mesh is rebuilt from scratch as the attributes are different.
+    pub fn set_uniform_tint(&mut self, uniform_tint: bool) {
+        if self.uniform_tint != uniform_tint {
+            self.uniform_tint = uniform_tint;
+            self.reset_mesh();
+        }
+    }
+
+    fn reset_mesh(&mut self) {
+        self.mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::RENDER_WORLD,
+        );
+        self.gpu_mesh = None;
+        self.dirty_mesh = true;
+    }
+
+    /// Set the sort key of the tiles and rebuild the mesh.
+    pub fn set_sort_key(&mut self, sort_key: fn(IVec2) -> i64) {
+        self.sort_key = sort_key;
+        self.dirty_mesh = true;
+    }
+}
+
+/// Convert to the bits of a half float, rounding to the nearest.
+/// Values too large become infinity.
+fn f32_to_f16_bits(value: f32) -> u32 {
+    let bits = value.to_bits();
+    let sign = (bits >> 16) & 0x8000;
+    let exp = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x7f_ffff;
+
+    if value.is_nan() {
+        return sign | 0x7e00;
+    }
+    if exp >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exp <= 0 {
+        // Subnormal or zero.
+        if exp < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exp) as u32;
+        return sign | ((mantissa + (1 << (shift - 1))) >> shift);
+    }
+
+    // The carry of the rounding correctly bumps the exponent.
+    sign | (((exp as u32) << 10 | mantissa >> 13) + ((mantissa >> 12) & 1))
+}
+
+fn write_or_create_buffer(
+    render_device: &RenderDevice,
+    render_queue: &RenderQueue,
+    buffer: Option<Buffer>,
+    contents: &[u8],
+    label: &'static str,
+    usage: BufferUsages,
+) -> Buffer {
+    match buffer {
+        Some(buffer)
+            if !contents.is_empty()
+                && buffer.size() >= contents.len() as u64
+                && buffer.usage().contains(BufferUsages::COPY_DST) =>
+        {
+            render_queue.write_buffer(&buffer, 0, contents);
+            buffer
+        }
+        _ => render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some(label),
+            contents,
+            usage,
+        }),
+    }
+}
+
+/// The meshes of all the chunks of a static tilemap in one buffer,
+/// so adjacent visible chunks are drawn with a single draw call.
+pub struct TilemapChunkBatch {
+    pub vertex_buffer: Buffer,
+    pub index_buffer: Buffer,
+    /// The indices of each chunk in the index buffer, in the order they are stored.
+    pub ranges: Vec<(IVec2, Range<u32>)>,
+}
+
+impl TilemapChunkBatch {
+    /// Concatenate the meshes of the chunks. Returns `None` if there's nothing to draw.
+    pub fn new<M: TilemapMaterial>(
+        chunks: &HashMap<IVec2, TilemapRenderChunk<M>>,
+        render_device: &RenderDevice,
+    ) -> Option<Self> {
+        let mut sorted = chunks
+            .iter()
+            .filter(|(_, chunk)| chunk.mesh.count_vertices() > 0)
+            .collect::<Vec<_>>();
+        sorted.sort_by_key(|(index, _)| (index.y, index.x));
+
+        let mut vertices = Vec::new();
+        let mut vertex_count = 0;
+        let mut indices = Vec::new();
+        let mut ranges = Vec::with_capacity(sorted.len());
+        for (index, chunk) in sorted {
+            let Some(Indices::U32(chunk_indices)) = chunk.mesh.indices() else {
+                continue;
+            };
+
+            let start = indices.len() as u32;
+            vertices.extend(chunk.mesh.get_vertex_buffer_data());
+            indices.extend(chunk_indices.iter().map(|i| i + vertex_count));
+            vertex_count += chunk.mesh.count_vertices() as u32;
+            ranges.push((*index, start..indices.len() as u32));
+        }
+
+        if ranges.is_empty() {
+            return None;
+        }
+
+        // Static tilemaps are rarely changed, so the buffers are recreated instead of written.
+        Some(Self {
+            vertex_buffer: render_device.create_buffer_with_data(&BufferInitDescriptor {
+                label: Some("tilemap_batch_vertex_buffer"),
+                contents: &vertices,
+                usage: BufferUsages::VERTEX,
+            }),
+            index_buffer: render_device.create_buffer_with_data(&BufferInitDescriptor {
+                label: Some("tilemap_batch_index_buffer"),
+                contents: cast_slice(&indices),
+                usage: BufferUsages::INDEX,
+            }),
+            ranges,
+        })
+    }
+
+    /// The index ranges to draw, where the adjacent visible chunks are merged.
+    pub fn draw_ranges<'a>(
+        &'a self,
+        is_visible: impl Fn(IVec2) -> bool + 'a,
+    ) -> impl Iterator<Item = Range<u32>> + 'a {
+        merge_ranges(
+            self.ranges
+                .iter()
+                .filter(move |(index, _)| is_visible(*index))
+                .map(|(_, range)| range.clone()),
+        )
+    }
+}
+
+/// Merge the ranges that are next to each other.
+fn merge_ranges(ranges: impl Iterator<Item = Range<u32>>) -> impl Iterator<Item = Range<u32>> {
+    let mut ranges = ranges.peekable();
+    std::iter::from_fn(move || {
+        let mut merged = ranges.next()?;
+        while let Some(next) = ranges.next_if(|next| next.start == merged.end) {
+            merged.end = next.end;
+        }
+        Some(merged)
+    })
 }
 
 #[derive(Resource)]
 pub struct RenderChunkStorage<M: TilemapMaterial> {
     pub(crate) value: EntityHashMap<HashMap<IVec2, TilemapRenderChunk<M>>>,
+    pub(crate) tints: EntityHashMap<HashMap<IVec2, Vec4>>,
+    pub(crate) sort_keys: EntityHashMap<fn(IVec2) -> i64>,
+    /// Chunks of the despawned tilemaps, grouped by chunk size.
+    pub(crate) pool: HashMap<u32, Vec<TilemapRenderChunk<M>>>,
+    /// Tilemaps with chunk changes that are not prepared yet.
+    /// Static tilemaps that are not in here are skipped when preparing.
+    pub(crate) changed: EntityHashSet,
+    /// The merged chunks of the static tilemaps.
+    pub(crate) batches: EntityHashMap<TilemapChunkBatch>,
 }
 
 impl<M: TilemapMaterial> Default for RenderChunkStorage<M> {
     fn default() -> Self {
         Self {
             value: Default::default(),
+            tints: Default::default(),
+            sort_keys: Default::default(),
+            pool: Default::default(),
+            changed: Default::default(),
+            batches: Default::default(),
         }
     }
 }
 
 impl<M: TilemapMaterial> RenderChunkStorage<M> {
     /// Update the mesh for all chunks of a tilemap.
-    pub fn prepare_chunks(&mut self, tilemap: &ExtractedTilemap<M>, render_device: &RenderDevice) {
-        if let Some(chunks) = self.value.get_mut(&tilemap.id) {
-            chunks
-                .values_mut()
-                .for_each(|c| c.try_update_mesh(render_device));
+    ///
+    /// The chunks of static tilemaps are merged into a `TilemapChunkBatch`
+    /// instead of having their own buffers.
+    pub fn prepare_chunks(
+        &mut self,
+        tilemap: &ExtractedTilemap<M>,
+        render_device: &RenderDevice,
+        render_queue: &RenderQueue,
+    ) {
+        // All the chunks of a tilemap share the vertex format, so checking one is enough.
+        let format_changed = self
+            .value
+            .get(&tilemap.id)
+            .and_then(|chunks| chunks.values().next())
+            .is_some_and(|c| {
+                c.compact != tilemap.compact_vertices
+                    || c.uniform_tint != tilemap.uniform_tint.is_some()
+            });
+        if !self.changed.remove(&tilemap.id) && tilemap.is_static && !format_changed {
+            return;
+        }
+
+        let Some(chunks) = self.value.get_mut(&tilemap.id) else {
+            self.batches.remove(&tilemap.id);
+            return;
+        };
+
+        if tilemap.is_static {
+            chunks.values_mut().for_each(|c| {
+                c.set_compact(tilemap.compact_vertices);
+                c.set_uniform_tint(tilemap.uniform_tint.is_some());
+                c.update_mesh();
+                c.gpu_mesh = None;
+            });
+            match TilemapChunkBatch::new(chunks, render_device) {
+                Some(batch) => self.batches.insert(tilemap.id, batch),
+                None => self.batches.remove(&tilemap.id),
+            };
+        } else {
+            self.batches.remove(&tilemap.id);
+            chunks.values_mut().for_each(|c| {
+                c.set_compact(tilemap.compact_vertices);
+                c.set_uniform_tint(tilemap.uniform_tint.is_some());
+                c.try_update_mesh(render_device, render_queue);
+            });
         }
     }
 
+    #[inline]
+    pub fn get_batch(&self, tilemap: Entity) -> Option<&TilemapChunkBatch> {
+        self.batches.get(&tilemap)
+    }
+
+    /// Make sure the chunks of the tilemap are checked in the next `prepare_chunks`,
+    /// even if the tilemap is static.
+    #[inline]
+    pub fn mark_changed(&mut self, tilemap: Entity) {
+        self.changed.insert(tilemap);
+    }
+
+    /// Create a new chunk, reusing a pooled one if possible.
+    pub fn new_chunk(
+        &mut self,
+        index: IVec2,
+        tilemap: &ExtractedTilemap<M>,
+    ) -> TilemapRenderChunk<M> {
+        match self
+            .pool
+            .get_mut(&tilemap.chunk_size)
+            .and_then(|chunks| chunks.pop())
+        {
+            Some(chunk) => chunk.recycle(index, tilemap),
+            None => TilemapRenderChunk::from_index(index, tilemap),
+        }
+    }
+
+    /// Remove the tilemap and keep at most `max_chunks` chunks in the pool.
+    pub fn recycle_tilemap(&mut self, tilemap: Entity, max_chunks: usize) {
+        let Some(chunks) = self.remove_tilemap(tilemap) else {
+            return;
+        };
+
+        let mut pooled = self.pool.values().map(|c| c.len()).sum::<usize>();
+        chunks.into_values().for_each(|chunk| {
+            if pooled < max_chunks {
+                self.pool.entry(chunk.size).or_default().push(chunk);
+                pooled += 1;
+            }
+        });
+    }
+
     #[inline]
     pub fn get_chunks(&self, tilemap: Entity) -> Option<&HashMap<IVec2, TilemapRenderChunk<M>>> {
         self.value.get(&tilemap)
@@ -269,16 +653,83 @@ impl<M: TilemapMaterial> RenderChunkStorage<M> {
         self.value.get_mut(&tilemap)
     }
 
+    /// Get the tint of a chunk.
+    #[inline]
+    pub fn get_tint(&self, tilemap: Entity, index: IVec2) -> Vec4 {
+        self.tints
+            .get(&tilemap)
+            .and_then(|t| t.get(&index))
+            .cloned()
+            .unwrap_or(Vec4::ONE)
+    }
+
+    /// Replace the chunk tints of a tilemap.
+    ///
+    /// They are uploaded to the storage buffer and applied in the shader,
+    /// so the meshes are left untouched.
+    pub fn set_tints(&mut self, tilemap: Entity, tints: HashMap<IVec2, Vec4>) {
+        self.tints.insert(tilemap, tints);
+    }
+
+    /// Get the tile sort key of a tilemap.
+    #[inline]
+    pub fn get_sort_key(&self, tilemap: Entity) -> fn(IVec2) -> i64 {
+        self.sort_keys
+            .get(&tilemap)
+            .cloned()
+            .unwrap_or(TilemapTileSortKey::default().0)
+    }
+
+    /// Replace the tile sort key of a tilemap.
+    pub fn set_sort_key(&mut self, tilemap: Entity, sort_key: fn(IVec2) -> i64) {
+        if let Some(chunks) = self.value.get_mut(&tilemap) {
+            chunks
+                .values_mut()
+                .for_each(|chunk| chunk.set_sort_key(sort_key));
+        }
+        self.sort_keys.insert(tilemap, sort_key);
+        self.changed.insert(tilemap);
+    }
+
     #[inline]
     pub fn remove_tilemap(
         &mut self,
         tilemap: Entity,
     ) -> Option<HashMap<IVec2, TilemapRenderChunk<M>>> {
+        self.tints.remove(&tilemap);
+        self.sort_keys.remove(&tilemap);
+        self.changed.remove(&tilemap);
+        self.batches.remove(&tilemap);
         self.value.remove(&tilemap)
     }
 
     #[inline]
     pub fn remove_chunk(&mut self, tilemap: Entity, index: IVec2) -> Option<TilemapRenderChunk<M>> {
+        self.changed.insert(tilemap);
         self.value.get_mut(&tilemap).and_then(|c| c.remove(&index))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_merge_ranges() {
+        let merged = merge_ranges([0..6, 6..12, 18..24, 24..30, 36..42].into_iter());
+        assert_eq!(merged.collect::<Vec<_>>(), vec![0..12, 18..30, 36..42]);
+        assert_eq!(merge_ranges(std::iter::empty()).count(), 0);
+    }
+
+    #[test]
+    fn test_f32_to_f16_bits() {
+        assert_eq!(f32_to_f16_bits(0.), 0);
+        assert_eq!(f32_to_f16_bits(1.), 0x3c00);
+        assert_eq!(f32_to_f16_bits(-2.), 0xc000);
+        assert_eq!(f32_to_f16_bits(0.5), 0x3800);
+        assert_eq!(f32_to_f16_bits(1.5), 0x3e00);
+        assert_eq!(f32_to_f16_bits(65504.), 0x7bff);
+        assert_eq!(f32_to_f16_bits(1e6), 0x7c00);
+        assert_eq!(f32_to_f16_bits(2f32.powi(-24)), 1);
+    }
+}
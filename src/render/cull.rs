@@ -1,10 +1,20 @@
 use bevy::{
-    ecs::system::{Res, Resource},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        system::{Res, Resource},
+    },
+    math::IVec2,
     prelude::{Query, ResMut},
+    reflect::Reflect,
     render::view::ViewVisibility,
+    utils::HashSet,
 };
 
-use crate::{math::CameraAabb2d, tilemap::map::TilemapAabbs};
+use crate::{
+    math::CameraAabb2d,
+    tilemap::map::{TilemapAabbs, TilemapStorage},
+};
 
 use super::{
     chunk::RenderChunkStorage,
@@ -39,6 +49,64 @@ pub fn cull_tilemaps(
     });
 }
 
+/// The chunks of the tilemap that are in the view of any camera.
+///
+/// Insert this component on the tilemap so gameplay systems can only work on the on-screen tiles.
+/// It's updated every frame using the same chunk aabbs as the culling, so the tiles near
+/// the edges of the views may be included. Use `Changed<TilemapVisibleTiles>` to detect
+/// when the visible chunks change.
+#[derive(Component, Debug, Default, Clone, Reflect)]
+pub struct TilemapVisibleTiles {
+    pub(crate) chunks: HashSet<IVec2>,
+}
+
+impl TilemapVisibleTiles {
+    #[inline]
+    pub fn is_chunk_visible(&self, chunk_index: IVec2) -> bool {
+        self.chunks.contains(&chunk_index)
+    }
+
+    /// Returns true if the chunk that the tile is in is visible.
+    #[inline]
+    pub fn is_visible(&self, storage: &TilemapStorage, index: IVec2) -> bool {
+        self.is_chunk_visible(storage.storage.transform_index(index).0)
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = &IVec2> {
+        self.chunks.iter()
+    }
+
+    /// Iterate over the tiles in the visible chunks.
+    pub fn iter_tiles<'a>(
+        &'a self,
+        storage: &'a TilemapStorage,
+    ) -> impl Iterator<Item = (IVec2, Entity)> + 'a {
+        self.chunks
+            .iter()
+            .flat_map(|chunk_index| storage.iter_chunk(*chunk_index))
+    }
+}
+
+pub fn visible_tiles_updater(
+    mut tilemaps: Query<(&TilemapStorage, &mut TilemapVisibleTiles)>,
+    cameras: Query<&CameraAabb2d>,
+) {
+    tilemaps
+        .par_iter_mut()
+        .for_each(|(storage, mut visible_tiles)| {
+            let chunks = storage
+                .reserved
+                .iter()
+                .filter(|(_, aabb)| cameras.iter().any(|camera| aabb.is_intersected(camera.0)))
+                .map(|(chunk_index, _)| *chunk_index)
+                .collect::<HashSet<_>>();
+
+            if visible_tiles.chunks != chunks {
+                visible_tiles.chunks = chunks;
+            }
+        });
+}
+
 pub fn cull_chunks<M: TilemapMaterial>(
     tilemaps: Query<&ExtractedTilemap<M>>,
     mut render_chunk_storage: ResMut<RenderChunkStorage<M>>,
@@ -53,8 +121,10 @@ pub fn cull_chunks<M: TilemapMaterial>(
         let Some(chunks) = render_chunk_storage.get_chunks_mut(tilemap.id) else {
             return;
         };
-        chunks.values_mut().for_each(|c| {
+        // The tilemap may have moved, like when the floating origin changed.
+        chunks.iter_mut().for_each(|(index, c)| {
             c.visible = false;
+            c.aabb = tilemap.chunk_aabb(*index);
         });
     });
 
@@ -72,3 +142,61 @@ pub fn cull_chunks<M: TilemapMaterial>(
         });
     });
 }
+
+#[cfg(test)]
+mod test {
+    use bevy::math::{Vec2, Vec4};
+
+    use crate::{
+        math::aabb::Aabb2d,
+        render::{chunk::TilemapRenderChunk, material::StandardTilemapMaterial},
+        tilemap::map::{TilemapAxisFlip, TilemapTransform, TilemapType},
+    };
+
+    use super::*;
+
+    fn extracted_tilemap(index_offset: IVec2) -> ExtractedTilemap<StandardTilemapMaterial> {
+        ExtractedTilemap {
+            id: Entity::from_raw(0),
+            name: Default::default(),
+            tile_render_size: Vec2::splat(16.),
+            slot_size: Vec2::splat(16.),
+            ty: TilemapType::Square,
+            tile_pivot: Vec2::ZERO,
+            layer_opacities: Vec4::ONE,
+            transform: TilemapTransform::IDENTITY,
+            axis_flip: TilemapAxisFlip::NONE,
+            material: Default::default(),
+            texture: None,
+            animations: None,
+            grid: None,
+            index_offset,
+            animation_groups: None,
+            opacity: 1.,
+            chunk_size: 16,
+            is_static: false,
+            compact_vertices: false,
+            uniform_tint: None,
+        }
+    }
+
+    #[test]
+    fn test_chunk_aabb_with_index_offset() {
+        let camera = Aabb2d {
+            min: Vec2::splat(-100.),
+            max: Vec2::splat(100.),
+        };
+
+        let tilemap = extracted_tilemap(IVec2::ZERO);
+        let chunk = TilemapRenderChunk::from_index(IVec2::ZERO, &tilemap);
+        assert!(chunk.aabb.is_intersected(camera));
+
+        // Tile (1000, 0) is rendered at the tilemap origin, which is in chunk (62, 0).
+        let tilemap = extracted_tilemap(IVec2::new(1000, 0));
+        let chunk = TilemapRenderChunk::from_index(IVec2::new(62, 0), &tilemap);
+        assert_eq!(chunk.aabb.min, Vec2::new(-128., 0.));
+        assert!(chunk.aabb.is_intersected(camera));
+        let chunk = TilemapRenderChunk::from_index(IVec2::ZERO, &tilemap);
+        assert!(!chunk.aabb.is_intersected(camera));
+    }
+}
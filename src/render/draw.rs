@@ -13,6 +13,7 @@ use bevy::{
     render::{
         mesh::GpuBufferInfo,
         render_phase::{RenderCommand, RenderCommandResult, SetItemPipeline, TrackedRenderPass},
+        render_resource::IndexFormat,
         view::ViewUniformOffset,
     },
 };
@@ -23,6 +24,7 @@ use super::{
     chunk::RenderChunkStorage,
     material::TilemapMaterial,
     resources::TilemapInstances,
+    texture::TilemapTexturesStorage,
 };
 
 pub type DrawTilemap<M> = (
@@ -124,12 +126,14 @@ impl<const I: usize, M: TilemapMaterial> RenderCommand<Transparent2d>
     }
 }
 
+/// Binds the storage buffer to group `I`, or to `I - 1` for pure color tilemaps
+/// as they don't have the color texture group.
 #[derive(Default)]
 pub struct SetTilemapStorageBufferBindGroup<const I: usize, M: TilemapMaterial>(PhantomData<M>);
 impl<const I: usize, M: TilemapMaterial> RenderCommand<Transparent2d>
     for SetTilemapStorageBufferBindGroup<I, M>
 {
-    type Param = SRes<TilemapBindGroups<M>>;
+    type Param = (SRes<TilemapBindGroups<M>>, SRes<TilemapInstances<M>>);
 
     type ViewQuery = ();
 
@@ -140,18 +144,26 @@ impl<const I: usize, M: TilemapMaterial> RenderCommand<Transparent2d>
         item: &Transparent2d,
         _view: ROQueryItem<'w, Self::ViewQuery>,
         _entity: Option<ROQueryItem<'w, Self::ItemQuery>>,
-        bind_groups: SystemParamItem<'w, '_, Self::Param>,
+        (bind_groups, instances): SystemParamItem<'w, '_, Self::Param>,
         pass: &mut TrackedRenderPass<'w>,
     ) -> RenderCommandResult {
+        let Some(inst) = instances.0.get(&item.entity) else {
+            error!("Failed to get tilemap instance!");
+            return RenderCommandResult::Failure;
+        };
+
         if let Some(bind_group) = bind_groups
             .into_inner()
             .tilemap_storage_buffers
             .get(&item.entity)
         {
-            pass.set_bind_group(I, bind_group, &[]);
+            let index = if inst.texture.is_some() { I } else { I - 1 };
+            pass.set_bind_group(index, bind_group, &[]);
+            RenderCommandResult::Success
+        } else {
+            error!("Failed to get tilemap storage bind group!");
+            RenderCommandResult::Failure
         }
-
-        RenderCommandResult::Success
     }
 }
 
@@ -160,7 +172,7 @@ pub struct SetTilemapColorTextureBindGroup<const I: usize, M: TilemapMaterial>(P
 impl<const I: usize, M: TilemapMaterial> RenderCommand<Transparent2d>
     for SetTilemapColorTextureBindGroup<I, M>
 {
-    type Param = (SRes<TilemapBindGroups<M>>, SRes<TilemapInstances<M>>);
+    type Param = (SRes<TilemapTexturesStorage>, SRes<TilemapInstances<M>>);
 
     type ViewQuery = ();
 
@@ -171,17 +183,16 @@ impl<const I: usize, M: TilemapMaterial> RenderCommand<Transparent2d>
         item: &Transparent2d,
         _view: ROQueryItem<'w, Self::ViewQuery>,
         _entity: Option<ROQueryItem<'w, Self::ItemQuery>>,
-        (bind_groups, instances): SystemParamItem<'w, '_, Self::Param>,
+        (textures_storage, instances): SystemParamItem<'w, '_, Self::Param>,
         pass: &mut TrackedRenderPass<'w>,
     ) -> RenderCommandResult {
         let Some(texture) = instances.0.get(&item.entity).unwrap().texture.as_ref() else {
             return RenderCommandResult::Success;
         };
 
-        if let Some(bind_group) = &bind_groups
+        if let Some(bind_group) = textures_storage
             .into_inner()
-            .colored_textures
-            .get(texture.handle())
+            .get_bind_group(texture.handle())
         {
             pass.set_bind_group(I, bind_group, &[]);
             RenderCommandResult::Success
@@ -209,7 +220,17 @@ impl<M: TilemapMaterial> RenderCommand<Transparent2d> for DrawTileMesh<M> {
         render_chunks: SystemParamItem<'w, '_, Self::Param>,
         pass: &mut TrackedRenderPass<'w>,
     ) -> RenderCommandResult {
-        if let Some(chunks) = render_chunks.into_inner().get_chunks(item.entity) {
+        let render_chunks = render_chunks.into_inner();
+        if let (Some(batch), Some(chunks)) = (
+            render_chunks.get_batch(item.entity),
+            render_chunks.get_chunks(item.entity),
+        ) {
+            pass.set_vertex_buffer(0, batch.vertex_buffer.slice(..));
+            pass.set_index_buffer(batch.index_buffer.slice(..), 0, IndexFormat::Uint32);
+            batch
+                .draw_ranges(|index| chunks.get(&index).is_some_and(|chunk| chunk.visible))
+                .for_each(|range| pass.draw_indexed(range, 0, 0..1));
+        } else if let Some(chunks) = render_chunks.get_chunks(item.entity) {
             for chunk in chunks.values() {
                 if !chunk.visible {
                     continue;
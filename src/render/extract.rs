@@ -3,31 +3,42 @@ use bevy::{
     ecs::{
         entity::EntityHashMap,
         event::EventReader,
-        query::{Or, With},
+        query::{Added, Has, Or, With, Without},
+        removal_detection::RemovedComponents,
         system::{Res, ResMut},
     },
+    math::IVec2,
     prelude::{Changed, Commands, Component, Entity, Query, Vec2, Vec4},
     render::{view::InheritedVisibility, Extract},
+    utils::HashMap,
 };
 
 use crate::{
-    math::CameraAabb2d,
+    math::{aabb::Aabb2d, CameraAabb2d},
     tilemap::{
         despawn::{DespawnedTile, DespawnedTilemap},
+        fade::TilemapOpacity,
+        metadata::TilemapTextureMetadata,
         map::{
-            TilePivot, TileRenderSize, TilemapAnimations, TilemapAxisFlip, TilemapLayerOpacities,
-            TilemapName, TilemapSlotSize, TilemapStorage, TilemapTexture, TilemapTransform,
-            TilemapType,
+            TilePivot, TileRenderSize, TilemapAnimationGroups, TilemapAnimations, TilemapAxisFlip,
+            TilemapChunkTints, TilemapCompactVertices, TilemapGrid, TilemapLayerOpacities,
+            TilemapName, TilemapSlotSize, TilemapStatic, TilemapStorage, TilemapTexture,
+            TilemapTileSortKey, TilemapTransform, TilemapType, TilemapUniformTint,
         },
+        origin::{self, FloatingOrigin, TilemapAnchor},
+        ownership::TilemapOwnership,
+        packed::TilemapPackedTiles,
+        pool::TilemapPool,
         tile::Tile,
+        weather::TileSkyExposure,
     },
 };
 
 use super::{
-    chunk::{ChunkUnload, UnloadRenderChunk},
+    chunk::{ChunkUnload, RenderChunkStorage, UnloadRenderChunk},
     cull::FrustumCulling,
     material::TilemapMaterial,
-    resources::{ExtractedTilemapMaterials, TilemapInstances},
+    resources::{ExtractedTilemapMaterials, ExtractedTilemapPool, TilemapInstances},
 };
 
 #[derive(Component, Debug)]
@@ -47,11 +58,68 @@ pub struct ExtractedTilemap<M: TilemapMaterial> {
     pub material: Handle<M>,
     pub texture: Option<TilemapTexture>,
     pub animations: Option<TilemapAnimations>,
+    pub grid: Option<TilemapGrid>,
+    /// The tile index that is treated as `(0, 0)` when rendering. See `TilemapAnchor`.
+    pub index_offset: IVec2,
+    /// `(elapsed, phase.x, phase.y, 0)` of each group. See `TilemapAnimationGroups`.
+    pub animation_groups: Option<Vec<Vec4>>,
+    /// See `TilemapOpacity`.
+    pub opacity: f32,
     pub chunk_size: u32,
+    /// See `TilemapStatic`.
+    pub is_static: bool,
+    /// See `TilemapCompactVertices`.
+    pub compact_vertices: bool,
+    /// The tint in linear color space. See `TilemapUniformTint`.
+    pub uniform_tint: Option<Vec4>,
+}
+
+impl<M: TilemapMaterial> ExtractedTilemap<M> {
+    /// The aabb of the chunk where it's rendered.
+    ///
+    /// The tiles are rebased by `index_offset` in the shader,
+    /// so the aabb is moved along with them.
+    pub fn chunk_aabb(&self, chunk_index: IVec2) -> Aabb2d {
+        let mut transform = self.transform;
+        transform.translation -= transform.apply_rotation(
+            origin::index_offset(
+                self.index_offset.as_i64vec2(),
+                self.ty,
+                self.axis_flip,
+                self.slot_size,
+            )
+            .as_vec2(),
+        );
+
+        Aabb2d::from_tilemap(
+            chunk_index,
+            self.chunk_size,
+            self.ty,
+            self.tile_pivot,
+            self.axis_flip,
+            self.slot_size,
+            transform,
+        )
+    }
 }
 
 pub type ExtractedTile = Tile;
 
+/// The chunk tints of a tilemap in linear color space.
+/// Only exists on the frames that the tints changed.
+#[derive(Component, Debug, Default)]
+pub struct ExtractedChunkTints(pub HashMap<IVec2, Vec4>);
+
+/// The tile sort key of a tilemap.
+/// Only exists on the frames that the key changed.
+#[derive(Component, Debug)]
+pub struct ExtractedTileSortKey(pub fn(IVec2) -> i64);
+
+/// The packed tiles of a tilemap that changed in this frame.
+/// `None` means the tile is removed. See `TilemapPackedTiles`.
+#[derive(Component, Debug, Default)]
+pub struct ExtractedPackedTiles(pub Vec<(IVec2, usize, Option<ExtractedTile>)>);
+
 pub type ExtractedView = CameraAabb2d;
 
 pub fn extract_changed_tilemaps<M: TilemapMaterial>(
@@ -71,6 +139,13 @@ pub fn extract_changed_tilemaps<M: TilemapMaterial>(
                 &Handle<M>,
                 Option<&TilemapTexture>,
                 Option<&TilemapAnimations>,
+                Option<&TilemapGrid>,
+                (
+                    Option<&TilemapAnchor>,
+                    Has<TilemapStatic>,
+                    Has<TilemapCompactVertices>,
+                    Option<&TilemapUniformTint>,
+                ),
             ),
             Or<(
                 Changed<TileRenderSize>,
@@ -83,11 +158,34 @@ pub fn extract_changed_tilemaps<M: TilemapMaterial>(
                 Changed<Handle<M>>,
                 Changed<TilemapTexture>,
                 Changed<TilemapAnimations>,
+                Changed<TilemapGrid>,
+                Changed<TilemapAnchor>,
+                Added<TilemapStatic>,
+                Added<TilemapCompactVertices>,
+                Changed<TilemapUniformTint>,
             )>,
         >,
     >,
+    origin: Extract<Res<FloatingOrigin>>,
+    mut removed_grids: Extract<RemovedComponents<TilemapGrid>>,
+    mut removed_statics: Extract<RemovedComponents<TilemapStatic>>,
+    mut removed_compacts: Extract<RemovedComponents<TilemapCompactVertices>>,
+    mut removed_uniform_tints: Extract<RemovedComponents<TilemapUniformTint>>,
+    mut removed_materials: Extract<RemovedComponents<Handle<M>>>,
+    swapped_query: Extract<Query<(), (With<TilemapStorage>, Without<Handle<M>>)>>,
     mut instances: ResMut<TilemapInstances<M>>,
+    mut render_chunks: ResMut<RenderChunkStorage<M>>,
 ) {
+    // The material was swapped to another one, so stop rendering the tilemap as `M`.
+    // Despawned tilemaps are handled in `prepare_despawned_tilemaps`.
+    removed_materials
+        .read()
+        .filter(|entity| swapped_query.contains(*entity))
+        .for_each(|entity| {
+            instances.0.remove(&entity);
+            render_chunks.remove_tilemap(entity);
+        });
+
     tilemaps_query.iter().for_each(
         |(
             entity,
@@ -103,6 +201,8 @@ pub fn extract_changed_tilemaps<M: TilemapMaterial>(
             material,
             texture,
             animations,
+            grid,
+            (anchor, is_static, compact_vertices, uniform_tint),
         )| {
             assert_ne!(
                 storage.tilemap,
@@ -111,6 +211,25 @@ pub fn extract_changed_tilemaps<M: TilemapMaterial>(
                 Did you use the default storage? If so, you have to assign the valid \
                 entity for the storage when creating."
             );
+
+            let mut transform = *transform;
+            let index_offset = match anchor {
+                Some(anchor) => {
+                    let (index_offset, translation) = origin::rebase(
+                        anchor,
+                        &origin,
+                        *ty,
+                        &transform,
+                        *axis_flip,
+                        tile_pivot.0,
+                        slot_size.0,
+                    );
+                    transform.translation = translation;
+                    index_offset
+                }
+                None => IVec2::ZERO,
+            };
+
             instances.0.insert(
                 entity,
                 ExtractedTilemap {
@@ -121,16 +240,91 @@ pub fn extract_changed_tilemaps<M: TilemapMaterial>(
                     ty: *ty,
                     tile_pivot: tile_pivot.0,
                     layer_opacities: layer_opacities.0,
-                    transform: *transform,
+                    transform,
                     axis_flip: *axis_flip,
                     texture: texture.cloned(),
                     material: material.clone(),
                     animations: animations.cloned(),
+                    grid: grid.cloned(),
+                    index_offset,
+                    animation_groups: None,
+                    opacity: 1.,
                     chunk_size: storage.storage.chunk_size,
+                    is_static,
+                    compact_vertices,
+                    uniform_tint: uniform_tint.map(|tint| tint.0.rgba_linear_to_vec4()),
                 },
             );
         },
     );
+
+    removed_grids.read().for_each(|entity| {
+        if let Some(tilemap) = instances.0.get_mut(&entity) {
+            tilemap.grid = None;
+        }
+    });
+
+    removed_statics.read().for_each(|entity| {
+        if let Some(tilemap) = instances.0.get_mut(&entity) {
+            tilemap.is_static = false;
+        }
+    });
+
+    removed_compacts.read().for_each(|entity| {
+        if let Some(tilemap) = instances.0.get_mut(&entity) {
+            tilemap.compact_vertices = false;
+        }
+    });
+
+    removed_uniform_tints.read().for_each(|entity| {
+        if let Some(tilemap) = instances.0.get_mut(&entity) {
+            tilemap.uniform_tint = None;
+        }
+    });
+}
+
+/// Extracted every frame as the clocks are always ticking.
+pub fn extract_animation_groups<M: TilemapMaterial>(
+    groups_query: Extract<Query<(Entity, &TilemapAnimationGroups)>>,
+    mut removed: Extract<RemovedComponents<TilemapAnimationGroups>>,
+    mut instances: ResMut<TilemapInstances<M>>,
+) {
+    groups_query.iter().for_each(|(entity, groups)| {
+        if let Some(tilemap) = instances.0.get_mut(&entity) {
+            tilemap.animation_groups = Some(
+                groups
+                    .0
+                    .iter()
+                    .map(|group| Vec4::new(group.elapsed, group.phase.x, group.phase.y, 0.))
+                    .collect(),
+            );
+        }
+    });
+
+    removed.read().for_each(|entity| {
+        if let Some(tilemap) = instances.0.get_mut(&entity) {
+            tilemap.animation_groups = None;
+        }
+    });
+}
+
+/// Extracted every frame as the opacity changes every frame when fading.
+pub fn extract_tilemap_opacities<M: TilemapMaterial>(
+    opacities_query: Extract<Query<(Entity, &TilemapOpacity)>>,
+    mut removed: Extract<RemovedComponents<TilemapOpacity>>,
+    mut instances: ResMut<TilemapInstances<M>>,
+) {
+    opacities_query.iter().for_each(|(entity, opacity)| {
+        if let Some(tilemap) = instances.0.get_mut(&entity) {
+            tilemap.opacity = opacity.0;
+        }
+    });
+
+    removed.read().for_each(|entity| {
+        if let Some(tilemap) = instances.0.get_mut(&entity) {
+            tilemap.opacity = 1.;
+        }
+    });
 }
 
 pub fn extract_tilemaps(
@@ -154,11 +348,26 @@ pub fn extract_tilemaps(
 pub fn extract_tiles(
     mut commands: Commands,
     tiles_query: Extract<Query<(Entity, &Tile), Changed<Tile>>>,
+    exposures_query: Extract<Query<&TileSkyExposure>>,
+    ownerships_query: Extract<Query<&TilemapOwnership>>,
+    metadata_query: Extract<Query<(&TilemapTextureMetadata, &TilemapLayerOpacities)>>,
 ) {
     commands.insert_or_spawn_batch(
         tiles_query
             .iter()
             .map(|(entity, tile)| {
+                let mut tint = tile.tint;
+                if let Ok(exposure) = exposures_query.get(tile.tilemap_id) {
+                    tint = exposure.apply_wet_tint(tile.index, tint);
+                }
+                if let Ok(ownership) = ownerships_query.get(tile.tilemap_id) {
+                    tint = ownership.apply_tint(tile.index, tint);
+                }
+                let mut texture = tile.texture.clone();
+                if let Ok((metadata, layer_opacities)) = metadata_query.get(tile.tilemap_id) {
+                    metadata.cull_layers(&mut texture, layer_opacities.0);
+                }
+
                 (
                     entity,
                     ExtractedTile {
@@ -166,8 +375,9 @@ pub fn extract_tiles(
                         chunk_index: tile.chunk_index,
                         in_chunk_index: tile.in_chunk_index,
                         index: tile.index,
-                        texture: tile.texture.clone(),
-                        tint: tile.tint,
+                        texture,
+                        tint,
+                        offset: tile.offset,
                     },
                 )
             })
@@ -175,6 +385,106 @@ pub fn extract_tiles(
     );
 }
 
+pub fn extract_packed_tiles(
+    mut commands: Commands,
+    tilemaps_query: Extract<Query<(Entity, &TilemapPackedTiles)>>,
+    exposures_query: Extract<Query<&TileSkyExposure>>,
+    ownerships_query: Extract<Query<&TilemapOwnership>>,
+    metadata_query: Extract<Query<(&TilemapTextureMetadata, &TilemapLayerOpacities)>>,
+) {
+    commands.insert_or_spawn_batch(
+        tilemaps_query
+            .iter()
+            .filter(|(_, packed)| !packed.extracting.is_empty())
+            .map(|(entity, packed)| {
+                let tiles = packed
+                    .extracting
+                    .iter()
+                    .map(|index| {
+                        let (chunk_index, in_chunk_index) = packed.storage.transform_index(*index);
+                        let tile = packed.get(*index).map(|builder| {
+                            let mut tint = builder.tint;
+                            if let Ok(exposure) = exposures_query.get(entity) {
+                                tint = exposure.apply_wet_tint(*index, tint);
+                            }
+                            if let Ok(ownership) = ownerships_query.get(entity) {
+                                tint = ownership.apply_tint(*index, tint);
+                            }
+                            let mut texture = builder.texture.clone();
+                            if let Ok((metadata, layer_opacities)) = metadata_query.get(entity) {
+                                metadata.cull_layers(&mut texture, layer_opacities.0);
+                            }
+
+                            ExtractedTile {
+                                tilemap_id: entity,
+                                chunk_index,
+                                in_chunk_index,
+                                index: *index,
+                                texture,
+                                tint,
+                                offset: builder.offset,
+                            }
+                        });
+                        (chunk_index, in_chunk_index, tile)
+                    })
+                    .collect();
+
+                (entity, ExtractedPackedTiles(tiles))
+            })
+            .collect::<Vec<_>>(),
+    );
+}
+
+pub fn extract_chunk_tints(
+    mut commands: Commands,
+    tilemaps_query: Extract<Query<(Entity, &TilemapChunkTints), Changed<TilemapChunkTints>>>,
+    mut removed: Extract<RemovedComponents<TilemapChunkTints>>,
+) {
+    let mut tints = tilemaps_query
+        .iter()
+        .map(|(entity, tints)| {
+            (
+                entity,
+                ExtractedChunkTints(
+                    tints
+                        .0
+                        .iter()
+                        .map(|(index, tint)| (*index, tint.rgba_linear_to_vec4()))
+                        .collect(),
+                ),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    tints.extend(
+        removed
+            .read()
+            .map(|entity| (entity, ExtractedChunkTints::default())),
+    );
+
+    commands.insert_or_spawn_batch(tints);
+}
+
+pub fn extract_tile_sort_keys(
+    mut commands: Commands,
+    tilemaps_query: Extract<Query<(Entity, &TilemapTileSortKey), Changed<TilemapTileSortKey>>>,
+    mut removed: Extract<RemovedComponents<TilemapTileSortKey>>,
+) {
+    let mut keys = tilemaps_query
+        .iter()
+        .map(|(entity, key)| (entity, ExtractedTileSortKey(key.0)))
+        .collect::<Vec<_>>();
+
+    keys.extend(removed.read().map(|entity| {
+        (
+            entity,
+            ExtractedTileSortKey(TilemapTileSortKey::default().0),
+        )
+    }));
+
+    commands.insert_or_spawn_batch(keys);
+}
+
 pub fn extract_materials<M: TilemapMaterial>(
     mut commands: Commands,
     mut events: Extract<EventReader<AssetEvent<M>>>,
@@ -225,8 +535,15 @@ pub fn extract_unloaded_chunks(
     ));
 }
 
-pub fn extract_resources(mut commands: Commands, frustum_culling: Extract<Res<FrustumCulling>>) {
+pub fn extract_resources(
+    mut commands: Commands,
+    frustum_culling: Extract<Res<FrustumCulling>>,
+    pool: Extract<Res<TilemapPool>>,
+) {
     commands.insert_resource(FrustumCulling(frustum_culling.0));
+    commands.insert_resource(ExtractedTilemapPool {
+        max_chunks: pool.max_chunks,
+    });
 }
 
 pub fn extract_despawned_tilemaps(
@@ -1,10 +1,16 @@
 use std::marker::PhantomData;
 
 use bevy::{
-    app::{App, Plugin},
-    asset::{Asset, AssetApp},
+    app::{App, Plugin, PostUpdate},
+    asset::{Asset, AssetApp, Handle},
     core_pipeline::core_2d::Transparent2d,
-    ecs::schedule::IntoSystemConfigs,
+    ecs::{
+        change_detection::{DetectChanges, DetectChangesMut},
+        query::Added,
+        schedule::IntoSystemConfigs,
+        system::Query,
+        world::Ref,
+    },
     reflect::TypePath,
     render::{
         color::Color,
@@ -17,6 +23,12 @@ use bevy::{
     },
 };
 
+use crate::tilemap::{
+    map::{TilemapChunkTints, TilemapStorage, TilemapTileSortKey},
+    packed::TilemapPackedTiles,
+    tile::Tile,
+};
+
 use super::{
     binding::TilemapBindGroups,
     buffer::TilemapUniformBuffer,
@@ -25,7 +37,9 @@ use super::{
     draw::DrawTilemap,
     extract,
     pipeline::EntiTilesPipeline,
-    prepare, queue,
+    prepare,
+    prewarm::{self, TilemapPrewarm},
+    queue,
     resources::{ExtractedTilemapMaterials, TilemapInstances},
 };
 
@@ -34,7 +48,9 @@ pub struct EntiTilesMaterialPlugin<M: TilemapMaterial>(PhantomData<M>);
 
 impl<M: TilemapMaterial> Plugin for EntiTilesMaterialPlugin<M> {
     fn build(&self, app: &mut App) {
-        app.init_asset::<M>();
+        app.init_asset::<M>()
+            .init_resource::<TilemapPrewarm<M>>()
+            .add_systems(PostUpdate, material_swap_updater::<M>);
 
         let render_app = app.get_sub_app_mut(RenderApp).unwrap();
 
@@ -43,14 +59,23 @@ impl<M: TilemapMaterial> Plugin for EntiTilesMaterialPlugin<M> {
                 ExtractSchedule,
                 (
                     extract::extract_changed_tilemaps::<M>,
+                    extract::extract_animation_groups::<M>
+                        .after(extract::extract_changed_tilemaps::<M>),
+                    extract::extract_tilemap_opacities::<M>
+                        .after(extract::extract_changed_tilemaps::<M>),
                     extract::extract_materials::<M>,
+                    prewarm::extract_prewarm::<M>,
                 ),
             )
             .add_systems(
                 Render,
                 (
+                    prewarm::prepare_prewarm::<M>.before(prepare::prepare_tilemaps::<M>),
                     prepare::prepare_tilemaps::<M>,
                     prepare::prepare_tiles::<M>,
+                    prepare::prepare_packed_tiles::<M>.after(prepare::prepare_tiles::<M>),
+                    prepare::prepare_chunk_tints::<M>,
+                    prepare::prepare_tile_sort_keys::<M>,
                     prepare::prepare_unloaded_chunks::<M>,
                     prepare::prepare_despawned_tilemaps::<M>,
                     prepare::prepare_despawned_tiles::<M>,
@@ -58,7 +83,14 @@ impl<M: TilemapMaterial> Plugin for EntiTilesMaterialPlugin<M> {
                 )
                     .in_set(RenderSet::Prepare),
             )
-            .add_systems(Render, queue::queue::<M>.in_set(RenderSet::Queue));
+            .add_systems(
+                Render,
+                (
+                    prewarm::queue_prewarm::<M>.before(queue::queue::<M>),
+                    queue::queue::<M>,
+                )
+                    .in_set(RenderSet::Queue),
+            );
 
         render_app
             .init_resource::<RenderChunkStorage<M>>()
@@ -79,6 +111,47 @@ impl<M: TilemapMaterial> Plugin for EntiTilesMaterialPlugin<M> {
     }
 }
 
+/// Re-extracts the tilemaps whose material was swapped to `M` from another material type,
+/// as the render chunks are stored separately for each material type.
+///
+/// To swap the material, just remove the old handle and insert the new one.
+pub fn material_swap_updater<M: TilemapMaterial>(
+    mut tilemaps_query: Query<
+        (
+            Ref<TilemapStorage>,
+            Option<&mut TilemapChunkTints>,
+            Option<&mut TilemapTileSortKey>,
+            Option<&mut TilemapPackedTiles>,
+        ),
+        Added<Handle<M>>,
+    >,
+    mut tiles_query: Query<&mut Tile>,
+) {
+    tilemaps_query
+        .iter_mut()
+        .for_each(|(storage, tints, sort_key, packed)| {
+            // Newly spawned tilemaps are extracted anyway.
+            if storage.is_added() {
+                return;
+            }
+
+            storage.storage.iter_some().for_each(|entity| {
+                if let Ok(mut tile) = tiles_query.get_mut(*entity) {
+                    tile.set_changed();
+                }
+            });
+            if let Some(mut tints) = tints {
+                tints.set_changed();
+            }
+            if let Some(mut sort_key) = sort_key {
+                sort_key.set_changed();
+            }
+            if let Some(mut packed) = packed {
+                packed.mark_all_changed();
+            }
+        });
+}
+
 pub trait TilemapMaterial: Default + Asset + AsBindGroup + TypePath + Clone {
     fn vertex_shader() -> ShaderRef {
         super::TILEMAP_SHADER.into()
@@ -118,3 +191,107 @@ impl TilemapMaterial for StandardTilemapMaterial {
         super::TILEMAP_SHADER.into()
     }
 }
+
+#[derive(ShaderType)]
+pub struct DissolveTilemapUniform {
+    pub tint: Color,
+    pub edge_color: Color,
+    pub progress: f32,
+    pub edge_width: f32,
+    pub noise_scale: f32,
+}
+
+impl From<&DissolveTilemapMaterial> for DissolveTilemapUniform {
+    fn from(value: &DissolveTilemapMaterial) -> Self {
+        Self {
+            tint: value.tint,
+            edge_color: value.edge_color,
+            progress: value.progress.clamp(0., 1.),
+            edge_width: value.edge_width,
+            noise_scale: value.noise_scale.max(f32::EPSILON),
+        }
+    }
+}
+
+/// A material that dissolves the tilemap using a noise threshold,
+/// with a glowing edge along the dissolving border.
+///
+/// The progress is stored in the material, so give each tilemap
+/// its own material if you want to control them separately.
+#[derive(Asset, AsBindGroup, TypePath, Clone)]
+#[uniform(0, DissolveTilemapUniform)]
+pub struct DissolveTilemapMaterial {
+    pub tint: Color,
+    /// The color of the dissolving edge. The alpha controls how strong the glow is.
+    pub edge_color: Color,
+    /// `0.` means fully visible and `1.` means fully dissolved.
+    pub progress: f32,
+    /// The width of the edge in noise space. Usually between `0.` and `0.2`.
+    pub edge_width: f32,
+    /// The size of the noise cells in world units.
+    pub noise_scale: f32,
+}
+
+impl Default for DissolveTilemapMaterial {
+    fn default() -> Self {
+        Self {
+            tint: Color::WHITE,
+            edge_color: Color::ORANGE,
+            progress: 0.,
+            edge_width: 0.05,
+            noise_scale: 16.,
+        }
+    }
+}
+
+impl TilemapMaterial for DissolveTilemapMaterial {
+    fn fragment_shader() -> ShaderRef {
+        super::DISSOLVE_SHADER.into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::{
+        app::{App, Update},
+        math::{IVec2, UVec2},
+    };
+
+    use crate::{
+        math::TileArea,
+        tilemap::tile::{TileBuilder, TileLayer},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_packed_tiles_material_swap() {
+        let mut app = App::new();
+        app.add_systems(Update, material_swap_updater::<StandardTilemapMaterial>);
+
+        let tilemap = app.world.spawn_empty().id();
+        let mut packed = TilemapPackedTiles::new(4);
+        packed.fill_rect(
+            TileArea::new(IVec2::ZERO, UVec2::new(3, 2)),
+            TileBuilder::new().with_layer(0, TileLayer::no_flip(0)),
+        );
+        app.world
+            .entity_mut(tilemap)
+            .insert((packed, TilemapStorage::new(4, tilemap)));
+        app.update();
+
+        // The tiles were extracted with the old material.
+        app.world
+            .get_mut::<TilemapPackedTiles>(tilemap)
+            .unwrap()
+            .dirty
+            .clear();
+        app.world
+            .entity_mut(tilemap)
+            .insert(Handle::<StandardTilemapMaterial>::default());
+        app.update();
+
+        let packed = app.world.get::<TilemapPackedTiles>(tilemap).unwrap();
+        assert_eq!(packed.dirty.len(), 6);
+    }
+}
@@ -0,0 +1,137 @@
+use bevy::{
+    asset::{Asset, Assets, Handle},
+    math::Vec2,
+    reflect::TypePath,
+    render::{
+        color::Color,
+        render_resource::{AsBindGroup, ShaderRef, ShaderType},
+        texture::Image,
+    },
+    ui::UiMaterial,
+};
+
+use super::bake::BakedTilemap;
+
+/// A point drawn on top of the minimap, like a player, an enemy or an objective.
+#[derive(ShaderType, Debug, Clone, Copy, Default)]
+pub struct MinimapMarker {
+    /// The uv in the map texture. Use `BakedTilemap::index_to_uv` to get it from a tile index.
+    pub position: Vec2,
+    /// The radius in pixels of the ui node, so the markers keep their size when zooming.
+    pub radius: f32,
+    pub color: Color,
+}
+
+impl MinimapMarker {
+    pub fn new(position: Vec2, radius: f32, color: Color) -> Self {
+        Self {
+            position,
+            radius,
+            color,
+        }
+    }
+}
+
+#[derive(ShaderType)]
+pub struct MinimapUniform {
+    pub view_min: Vec2,
+    pub view_max: Vec2,
+    pub background: Color,
+    pub marker_count: u32,
+}
+
+impl From<&MinimapMaterial> for MinimapUniform {
+    fn from(value: &MinimapMaterial) -> Self {
+        Self {
+            view_min: value.view_min,
+            view_max: value.view_max,
+            background: value.background,
+            marker_count: value.marker_count,
+        }
+    }
+}
+
+/// A ui material that shows a region of a map texture, usually a baked tilemap,
+/// with markers drawn on top of it.
+///
+/// Use it in a `MaterialNodeBundle`. Each minimap needs its own material, so several
+/// minimaps can show different regions of the same texture.
+#[derive(Asset, AsBindGroup, TypePath, Debug, Clone)]
+#[uniform(0, MinimapUniform)]
+pub struct MinimapMaterial {
+    #[texture(1)]
+    #[sampler(2)]
+    pub map: Handle<Image>,
+    /// The min uv of the region to show.
+    pub view_min: Vec2,
+    /// The max uv of the region to show.
+    pub view_max: Vec2,
+    /// The color outside of the map texture.
+    pub background: Color,
+    // Storage buffers can't be empty, so this always has at least one element.
+    #[storage(3, read_only)]
+    markers: Vec<MinimapMarker>,
+    marker_count: u32,
+}
+
+impl MinimapMaterial {
+    /// Show the whole `map` with no markers.
+    pub fn new(map: Handle<Image>) -> Self {
+        Self {
+            map,
+            view_min: Vec2::ZERO,
+            view_max: Vec2::ONE,
+            background: Color::NONE,
+            markers: vec![MinimapMarker::default()],
+            marker_count: 0,
+        }
+    }
+
+    /// Show the region centered at `center` with the `size` in uv.
+    pub fn with_view(mut self, center: Vec2, size: Vec2) -> Self {
+        self.set_view(center, size);
+        self
+    }
+
+    pub fn with_background(mut self, background: Color) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Move the shown region. Use this to follow the player.
+    pub fn set_view(&mut self, center: Vec2, size: Vec2) {
+        self.view_min = center - size / 2.;
+        self.view_max = center + size / 2.;
+    }
+
+    /// Replace all the markers.
+    pub fn set_markers(&mut self, markers: impl IntoIterator<Item = MinimapMarker>) {
+        self.markers.clear();
+        self.markers.extend(markers);
+        self.marker_count = self.markers.len() as u32;
+        if self.markers.is_empty() {
+            self.markers.push(MinimapMarker::default());
+        }
+    }
+
+    #[inline]
+    pub fn markers(&self) -> &[MinimapMarker] {
+        &self.markers[..self.marker_count as usize]
+    }
+}
+
+impl UiMaterial for MinimapMaterial {
+    fn fragment_shader() -> ShaderRef {
+        super::MINIMAP_SHADER.into()
+    }
+}
+
+impl BakedTilemap {
+    /// Create a minimap material showing the whole baked tilemap.
+    /// The texture is taken out of the baked tilemap.
+    pub fn take_minimap(&mut self, images: &mut Assets<Image>) -> Option<MinimapMaterial> {
+        self.texture
+            .take()
+            .map(|texture| MinimapMaterial::new(images.add(texture)))
+    }
+}
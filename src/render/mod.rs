@@ -13,7 +13,8 @@ use crate::render::{
     binding::TilemapBindGroupLayouts,
     buffer::TilemapStorageBuffers,
     chunk::{ChunkUnload, RenderChunkStorage, UnloadRenderChunk},
-    cull::FrustumCulling,
+    cull::{FrustumCulling, TilemapVisibleTiles},
+    resources::ExtractedTilemapPool,
     texture::TilemapTexturesStorage,
 };
 
@@ -26,8 +27,11 @@ pub mod cull;
 pub mod draw;
 pub mod extract;
 pub mod material;
+#[cfg(feature = "minimap")]
+pub mod minimap;
 pub mod pipeline;
 pub mod prepare;
+pub mod prewarm;
 pub mod queue;
 pub mod resources;
 pub mod texture;
@@ -37,6 +41,9 @@ pub const ISOMETRIC: Handle<Shader> = Handle::weak_from_u128(45522415151365135);
 pub const HEXAGONAL: Handle<Shader> = Handle::weak_from_u128(341658413214563135);
 pub const COMMON: Handle<Shader> = Handle::weak_from_u128(1321023135616351);
 pub const TILEMAP_SHADER: Handle<Shader> = Handle::weak_from_u128(89646584153215);
+pub const DISSOLVE_SHADER: Handle<Shader> = Handle::weak_from_u128(16874516846532115);
+#[cfg(feature = "minimap")]
+pub const MINIMAP_SHADER: Handle<Shader> = Handle::weak_from_u128(73516841351365143);
 
 pub const TILEMAP_MESH_ATTR_INDEX: MeshVertexAttribute =
     MeshVertexAttribute::new("GridIndex", 14513156146, VertexFormat::Sint32x4);
@@ -47,6 +54,17 @@ pub const TILEMAP_MESH_ATTR_TEX_INDICES: MeshVertexAttribute =
 pub const TILEMAP_MESH_ATTR_FLIP: MeshVertexAttribute =
     MeshVertexAttribute::new("Flip", 7365156123161, VertexFormat::Uint32x4);
 
+// The attributes are stored in the order of their ids, so the ids of the compact ones
+// must keep the same order as the default ones. See `TilemapCompactVertices`.
+pub const TILEMAP_MESH_ATTR_COMPACT_POSITION: MeshVertexAttribute =
+    MeshVertexAttribute::new("CompactPosition", 9146521651, VertexFormat::Uint32x2);
+pub const TILEMAP_MESH_ATTR_COMPACT_COLOR: MeshVertexAttribute =
+    MeshVertexAttribute::new("CompactColor", 54163215643, VertexFormat::Unorm8x4);
+pub const TILEMAP_MESH_ATTR_COMPACT_TEX_INDICES: MeshVertexAttribute =
+    MeshVertexAttribute::new("CompactTextureIndex", 135461535136, VertexFormat::Sint16x4);
+pub const TILEMAP_MESH_ATTR_COMPACT_FLIP: MeshVertexAttribute =
+    MeshVertexAttribute::new("CompactFlip", 8461351321651, VertexFormat::Uint8x4);
+
 #[derive(Default)]
 pub struct EntiTilesRendererPlugin;
 
@@ -63,6 +81,12 @@ impl Plugin for EntiTilesRendererPlugin {
             "shaders/tilemap.wgsl",
             Shader::from_wgsl
         );
+        load_internal_asset!(
+            app,
+            DISSOLVE_SHADER,
+            "shaders/dissolve.wgsl",
+            Shader::from_wgsl
+        );
 
         app.add_systems(
             Update,
@@ -70,24 +94,43 @@ impl Plugin for EntiTilesRendererPlugin {
                 texture::set_texture_usage,
                 #[cfg(feature = "baking")]
                 bake::tilemap_baker,
+                #[cfg(feature = "baking")]
+                bake::tilemap_exporter,
             ),
         )
         .add_systems(
             PostUpdate,
-            cull::cull_tilemaps
-                .in_set(VisibilitySystems::CheckVisibility)
-                .after(bevy::render::view::check_visibility),
+            (
+                cull::cull_tilemaps
+                    .in_set(VisibilitySystems::CheckVisibility)
+                    .after(bevy::render::view::check_visibility),
+                cull::visible_tiles_updater.in_set(VisibilitySystems::CheckVisibility),
+            ),
         )
         .init_resource::<FrustumCulling>()
         .register_type::<UnloadRenderChunk>()
+        .register_type::<TilemapVisibleTiles>()
         .add_event::<ChunkUnload>();
 
         #[cfg(feature = "baking")]
         {
-            use bake::{BakedTilemap, TilemapBaker};
+            use bake::{BakedTilemap, TilemapBaker, TilemapExporter};
 
             app.register_type::<TilemapBaker>()
-                .register_type::<BakedTilemap>();
+                .register_type::<BakedTilemap>()
+                .register_type::<TilemapExporter>();
+        }
+
+        #[cfg(feature = "minimap")]
+        {
+            load_internal_asset!(
+                app,
+                MINIMAP_SHADER,
+                "shaders/minimap.wgsl",
+                Shader::from_wgsl
+            );
+
+            app.add_plugins(bevy::ui::UiMaterialPlugin::<minimap::MinimapMaterial>::default());
         }
 
         let render_app = app.sub_app_mut(RenderApp);
@@ -98,6 +141,9 @@ impl Plugin for EntiTilesRendererPlugin {
                 (
                     extract::extract_tilemaps,
                     extract::extract_tiles,
+                    extract::extract_packed_tiles,
+                    extract::extract_chunk_tints,
+                    extract::extract_tile_sort_keys,
                     extract::extract_view,
                     extract::extract_unloaded_chunks,
                     extract::extract_resources,
@@ -106,7 +152,8 @@ impl Plugin for EntiTilesRendererPlugin {
                 ),
             )
             .init_resource::<TilemapTexturesStorage>()
-            .init_resource::<TilemapStorageBuffers>();
+            .init_resource::<TilemapStorageBuffers>()
+            .init_resource::<ExtractedTilemapPool>();
     }
 
     fn finish(&self, app: &mut App) {
@@ -26,6 +26,7 @@ pub struct EntiTilesPipeline<M: TilemapMaterial> {
     pub uniform_buffers_layout: BindGroupLayout,
     pub storage_buffers_layout: BindGroupLayout,
     pub color_texture_layout: BindGroupLayout,
+    pub color_texture_atlas_layout: BindGroupLayout,
     pub material_layout: BindGroupLayout,
     pub vertex_shader: Handle<Shader>,
     pub fragment_shader: Handle<Shader>,
@@ -37,6 +38,9 @@ pub struct EntiTilesPipelineKey {
     pub msaa: u32,
     pub map_type: TilemapType,
     pub is_pure_color: bool,
+    pub is_texture_array: bool,
+    pub is_compact: bool,
+    pub is_uniform_tint: bool,
 }
 
 impl<M: TilemapMaterial> FromWorld for EntiTilesPipeline<M> {
@@ -50,6 +54,7 @@ impl<M: TilemapMaterial> FromWorld for EntiTilesPipeline<M> {
             uniform_buffers_layout: layouts.tilemap_uniforms_layout.clone(),
             storage_buffers_layout: layouts.tilemap_storage_layout.clone(),
             color_texture_layout: layouts.color_texture_layout.clone(),
+            color_texture_atlas_layout: layouts.color_texture_atlas_layout.clone(),
             material_layout: M::bind_group_layout(render_device),
             vertex_shader: match M::vertex_shader() {
                 ShaderRef::Default => panic!("You must provide a valid custom vertex shader!"),
@@ -83,20 +88,38 @@ impl<M: TilemapMaterial> SpecializedRenderPipeline for EntiTilesPipeline<M> {
             }
             .into(),
         );
-        #[cfg(feature = "atlas")]
-        shader_defs.push("ATLAS".into());
-
-        let mut vtx_fmt = vec![
-            // position
-            VertexFormat::Float32x3,
-            // index + anim_start + anim_len
-            VertexFormat::Sint32x4,
-            // color
-            VertexFormat::Float32x4,
-        ];
+        if !key.is_pure_color && !key.is_texture_array {
+            shader_defs.push("ATLAS".into());
+        }
+
+        let mut vtx_fmt = if key.is_compact {
+            shader_defs.push("COMPACT_VERTEX".into());
+            vec![
+                // position, packed as f16
+                VertexFormat::Uint32x2,
+                // index + anim_start + anim_len
+                VertexFormat::Sint32x4,
+                // color
+                VertexFormat::Unorm8x4,
+            ]
+        } else {
+            vec![
+                // position
+                VertexFormat::Float32x3,
+                // index + anim_start + anim_len
+                VertexFormat::Sint32x4,
+                // color
+                VertexFormat::Float32x4,
+            ]
+        };
 
         if key.is_pure_color {
             shader_defs.push("PURE_COLOR".into());
+        } else if key.is_compact {
+            // texture_indices
+            vtx_fmt.push(VertexFormat::Sint16x4);
+            // flip
+            vtx_fmt.push(VertexFormat::Uint8x4);
         } else {
             // texture_indices
             vtx_fmt.push(VertexFormat::Sint32x4);
@@ -104,8 +127,21 @@ impl<M: TilemapMaterial> SpecializedRenderPipeline for EntiTilesPipeline<M> {
             vtx_fmt.push(VertexFormat::Uint32x4);
         }
 
-        let vertex_layout =
+        if key.is_uniform_tint {
+            shader_defs.push("UNIFORM_TINT".into());
+            vtx_fmt.remove(2);
+        }
+
+        let mut vertex_layout =
             VertexBufferLayout::from_vertex_formats(VertexStepMode::Vertex, vtx_fmt);
+        if key.is_uniform_tint {
+            // Keep the locations of the attributes after the color, so the shaders don't change.
+            vertex_layout
+                .attributes
+                .iter_mut()
+                .skip(2)
+                .for_each(|attr| attr.shader_location += 1);
+        }
 
         let mut layout = vec![
             // group(0)
@@ -118,10 +154,14 @@ impl<M: TilemapMaterial> SpecializedRenderPipeline for EntiTilesPipeline<M> {
 
         if !key.is_pure_color {
             // group(3)
-            layout.push(self.color_texture_layout.clone());
-            // group(4)
-            layout.push(self.storage_buffers_layout.clone());
+            layout.push(if key.is_texture_array {
+                self.color_texture_layout.clone()
+            } else {
+                self.color_texture_atlas_layout.clone()
+            });
         }
+        // group(4), or group(3) for pure color tilemaps
+        layout.push(self.storage_buffers_layout.clone());
 
         let mut desc=RenderPipelineDescriptor {
             label: Some("tilemap_pipeline".into()),
@@ -1,5 +1,7 @@
 use bevy::{
     ecs::{entity::Entity, query::With},
+    log::warn,
+    math::{IVec2, Vec4},
     prelude::{Commands, Query, Res, ResMut},
     render::{
         render_asset::RenderAssets,
@@ -7,6 +9,7 @@ use bevy::{
         texture::{FallbackImage, Image},
     },
     time::Time,
+    utils::HashMap,
 };
 
 use crate::tilemap::despawn::{DespawnedTile, DespawnedTilemap};
@@ -14,13 +17,17 @@ use crate::tilemap::despawn::{DespawnedTile, DespawnedTilemap};
 use super::{
     binding::TilemapBindGroups,
     buffer::{
-        PerTilemapBuffersStorage, TilemapStorageBuffers, TilemapUniformBuffer, UniformBuffer,
+        AnimationGroupsSlice, ChunkTintsSlice, PerTilemapBuffersStorage, TilemapStorageBuffers,
+        TilemapUniformBuffer, UniformBuffer,
+    },
+    chunk::UnloadRenderChunk,
+    extract::{
+        ExtractedChunkTints, ExtractedPackedTiles, ExtractedTile, ExtractedTileSortKey,
+        ExtractedTilemap, TilemapInstance,
     },
-    chunk::{TilemapRenderChunk, UnloadRenderChunk},
-    extract::{ExtractedTile, TilemapInstance},
     material::TilemapMaterial,
     pipeline::EntiTilesPipeline,
-    resources::{ExtractedTilemapMaterials, TilemapInstances},
+    resources::{ExtractedTilemapMaterials, ExtractedTilemapPool, TilemapInstances},
     texture::TilemapTexturesStorage,
     RenderChunkStorage,
 };
@@ -45,29 +52,79 @@ pub fn prepare_tilemaps<M: TilemapMaterial>(
     uniform_buffers.clear();
     storage_buffers.clear();
 
+    // Counted in `i32`s.
+    let max_storage_len = render_device.limits().max_storage_buffer_binding_size as usize / 4;
+
     extracted_tilemaps
         .iter()
         .filter_map(|tilemap| tilemap_instances.0.get(&tilemap))
         .for_each(|tilemap| {
-            commands
-                .entity(tilemap.id)
-                .insert(uniform_buffers.insert(&(tilemap, time.elapsed_seconds())));
-
-            render_chunks.prepare_chunks(tilemap, &render_device);
+            // Groups that are not uploaded follow the global time.
+            let mut anim_groups = AnimationGroupsSlice::default();
+            // Tilemaps without animations still need a non-empty buffer to be bound.
+            let buffer = storage_buffers.get_or_insert_buffer(tilemap.id);
 
             if let Some(texture) = tilemap.texture.as_ref() {
-                storage_buffers
-                    .get_or_insert_buffer(tilemap.id)
-                    .extend(&tilemap.animations.as_ref().unwrap().0);
+                match tilemap.animations.as_ref() {
+                    Some(animations) if !animations.0.is_empty() => buffer.extend(&animations.0),
+                    _ => buffer.push(0),
+                }
+
+                if let Some(groups) = tilemap.animation_groups.as_ref() {
+                    let count = groups
+                        .len()
+                        .min(max_storage_len.saturating_sub(buffer.len()) / 4);
+                    if count < groups.len() {
+                        warn!(
+                            "Tilemap {:?} has {} animation groups, but only {} of them fit in the storage buffer!",
+                            tilemap.id,
+                            groups.len(),
+                            count
+                        );
+                    }
+
+                    anim_groups = AnimationGroupsSlice {
+                        offset: buffer.len() as i32,
+                        count: count as u32,
+                    };
+                    buffer.extend(
+                        groups[..count]
+                            .iter()
+                            .flat_map(|group| group.to_array().map(|v| v.to_bits() as i32)),
+                    );
+                }
 
                 if !textures_storage.contains(&texture.texture) {
                     textures_storage.insert(texture.clone_weak(), texture.desc());
                 }
+            } else {
+                buffer.push(0);
             }
+
+            let chunk_tints = match render_chunks.tints.get(&tilemap.id) {
+                Some(tints) if !tints.is_empty() => {
+                    upload_chunk_tints(tints, buffer, max_storage_len).unwrap_or_else(|| {
+                        warn!(
+                            "The chunk tints of tilemap {:?} don't fit in the storage buffer!",
+                            tilemap.id
+                        );
+                        ChunkTintsSlice::default()
+                    })
+                }
+                _ => ChunkTintsSlice::default(),
+            };
+
+            commands.entity(tilemap.id).insert(uniform_buffers.insert(&(
+                tilemap,
+                time.elapsed_seconds(),
+                anim_groups,
+                chunk_tints,
+            )));
+
+            render_chunks.prepare_chunks(tilemap, &render_device, &render_queue);
         });
 
-    #[cfg(not(feature = "atlas"))]
-    textures_storage.prepare_textures(&render_device);
+    textures_storage.prepare_textures(&render_device, &images);
     uniform_buffers.write(&render_device, &render_queue);
     storage_buffers.write(&render_device, &render_queue);
 
@@ -82,6 +139,34 @@ pub fn prepare_tilemaps<M: TilemapMaterial>(
     );
 }
 
+/// Append the chunk tints to the storage buffer, sorted by `(chunk.y, chunk.x)`
+/// so the shader can binary search them. The chunks that are not tinted are white.
+///
+/// Returns `None` if they don't fit.
+fn upload_chunk_tints(
+    tints: &HashMap<IVec2, Vec4>,
+    buffer: &mut Vec<i32>,
+    max_storage_len: usize,
+) -> Option<ChunkTintsSlice> {
+    if buffer.len() + tints.len() * 6 > max_storage_len {
+        return None;
+    }
+
+    let mut sorted = tints.iter().collect::<Vec<_>>();
+    sorted.sort_unstable_by_key(|(chunk, _)| (chunk.y, chunk.x));
+
+    let offset = buffer.len() as i32;
+    buffer.extend(sorted.into_iter().flat_map(|(chunk, tint)| {
+        let [r, g, b, a] = tint.to_array().map(|v| v.to_bits() as i32);
+        [chunk.x, chunk.y, r, g, b, a]
+    }));
+
+    Some(ChunkTintsSlice {
+        offset,
+        count: tints.len() as u32,
+    })
+}
+
 pub fn prepare_tiles<M: TilemapMaterial>(
     extracted_tiles: Query<&mut ExtractedTile>,
     mut render_chunks: ResMut<RenderChunkStorage<M>>,
@@ -92,13 +177,82 @@ pub fn prepare_tiles<M: TilemapMaterial>(
             return;
         };
 
-        let chunks = render_chunks.value.entry(tile.tilemap_id).or_default();
+        ensure_chunk(&mut render_chunks, tile.tilemap_id, tile.chunk_index, tilemap);
+        if let Some(chunk) = render_chunks
+            .get_chunks_mut(tile.tilemap_id)
+            .and_then(|chunks| chunks.get_mut(&tile.chunk_index))
+        {
+            chunk.set_tile(tile.in_chunk_index, Some(tile));
+        }
+        render_chunks.mark_changed(tile.tilemap_id);
+    });
+}
+
+pub fn prepare_packed_tiles<M: TilemapMaterial>(
+    packed_query: Query<(Entity, &ExtractedPackedTiles)>,
+    mut render_chunks: ResMut<RenderChunkStorage<M>>,
+    tilemap_instances: Res<TilemapInstances<M>>,
+) {
+    packed_query.iter().for_each(|(entity, packed)| {
+        let Some(tilemap) = tilemap_instances.0.get(&entity) else {
+            return;
+        };
+
+        packed
+            .0
+            .iter()
+            .for_each(|(chunk_index, in_chunk_index, tile)| {
+                if tile.is_some() {
+                    ensure_chunk(&mut render_chunks, entity, *chunk_index, tilemap);
+                }
+                if let Some(chunk) = render_chunks
+                    .get_chunks_mut(entity)
+                    .and_then(|chunks| chunks.get_mut(chunk_index))
+                {
+                    chunk.set_tile(*in_chunk_index, tile.as_ref());
+                }
+            });
+        render_chunks.mark_changed(entity);
+    });
+}
 
-        let chunk = chunks
-            .entry(tile.chunk_index)
-            .or_insert_with(|| TilemapRenderChunk::from_index(tile.chunk_index, tilemap));
+fn ensure_chunk<M: TilemapMaterial>(
+    render_chunks: &mut RenderChunkStorage<M>,
+    tilemap_id: Entity,
+    chunk_index: IVec2,
+    tilemap: &ExtractedTilemap<M>,
+) {
+    if render_chunks
+        .get_chunks(tilemap_id)
+        .map_or(false, |chunks| chunks.contains_key(&chunk_index))
+    {
+        return;
+    }
+
+    let mut chunk = render_chunks.new_chunk(chunk_index, tilemap);
+    chunk.sort_key = render_chunks.get_sort_key(tilemap_id);
+    render_chunks
+        .value
+        .entry(tilemap_id)
+        .or_default()
+        .insert(chunk_index, chunk);
+}
+
+pub fn prepare_chunk_tints<M: TilemapMaterial>(
+    mut render_chunks: ResMut<RenderChunkStorage<M>>,
+    mut tints_query: Query<(Entity, &mut ExtractedChunkTints)>,
+) {
+    tints_query.iter_mut().for_each(|(entity, mut tints)| {
+        render_chunks.set_tints(entity, std::mem::take(&mut tints.0));
+    });
+}
 
-        chunk.set_tile(tile.in_chunk_index, Some(tile));
+pub fn prepare_tile_sort_keys<M: TilemapMaterial>(
+    mut render_chunks: ResMut<RenderChunkStorage<M>>,
+    keys_query: Query<(Entity, &ExtractedTileSortKey)>,
+) {
+    keys_query.iter().for_each(|(entity, key)| {
+        render_chunks.set_sort_key(entity, key.0);
     });
 }
 
@@ -118,9 +272,10 @@ pub fn prepare_despawned_tilemaps<M: TilemapMaterial>(
     mut storage_buffers: ResMut<TilemapStorageBuffers>,
     mut tilemap_instaces: ResMut<TilemapInstances<M>>,
     tilemaps_query: Query<&DespawnedTilemap>,
+    pool: Res<ExtractedTilemapPool>,
 ) {
     tilemaps_query.iter().for_each(|map| {
-        render_chunks.remove_tilemap(map.0);
+        render_chunks.recycle_tilemap(map.0, pool.max_chunks);
         storage_buffers.remove(map.0);
         tilemap_instaces.0.remove(&map.0);
     });
@@ -137,5 +292,49 @@ pub fn prepare_despawned_tiles<M: TilemapMaterial>(
         {
             chunk.set_tile(tile.in_chunk_index, None);
         }
+        render_chunks.mark_changed(tile.tilemap);
     });
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_upload_chunk_tints() {
+        let tints = HashMap::from([
+            (IVec2::new(-1, 2), Vec4::new(0.5, 0.5, 0.5, 1.)),
+            (IVec2::new(1, 3), Vec4::ZERO),
+            (IVec2::new(-1000, -1000), Vec4::new(0., 1., 0., 1.)),
+        ]);
+        let mut buffer = vec![0];
+
+        let slice = upload_chunk_tints(&tints, &mut buffer, usize::MAX).unwrap();
+        assert_eq!(slice.offset, 1);
+        assert_eq!(slice.count, 3);
+        // Only the tinted chunks are stored, however far apart they are.
+        assert_eq!(buffer.len(), 1 + 3 * 6);
+
+        // The same search as `chunk_tint` in common.wgsl.
+        let tint_at = |chunk: IVec2| {
+            let entries = buffer[slice.offset as usize..]
+                .chunks_exact(6)
+                .collect::<Vec<_>>();
+            let i = entries.partition_point(|e| (e[1], e[0]) < (chunk.y, chunk.x));
+            match entries.get(i) {
+                Some(e) if e[0] == chunk.x && e[1] == chunk.y => {
+                    Vec4::from_array(std::array::from_fn(|i| f32::from_bits(e[i + 2] as u32)))
+                }
+                _ => Vec4::ONE,
+            }
+        };
+        assert_eq!(tint_at(IVec2::new(-1, 2)), Vec4::new(0.5, 0.5, 0.5, 1.));
+        assert_eq!(tint_at(IVec2::new(1, 3)), Vec4::ZERO);
+        assert_eq!(tint_at(IVec2::new(-1000, -1000)), Vec4::new(0., 1., 0., 1.));
+        assert_eq!(tint_at(IVec2::new(0, 2)), Vec4::ONE);
+
+        let mut full = vec![0; 4];
+        assert!(upload_chunk_tints(&tints, &mut full, 8).is_none());
+        assert_eq!(full.len(), 4);
+    }
+}
@@ -0,0 +1,176 @@
+use std::{
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use bevy::{
+    ecs::{
+        change_detection::DetectChanges,
+        system::{Commands, Res, ResMut, Resource},
+    },
+    prelude::Msaa,
+    render::{
+        render_asset::RenderAssets,
+        render_resource::{CachedPipelineState, PipelineCache, SpecializedRenderPipelines},
+        renderer::{RenderDevice, RenderQueue},
+        texture::Image,
+        Extract,
+    },
+};
+
+use crate::tilemap::map::{TilemapTexture, TilemapType};
+
+use super::{
+    binding::TilemapBindGroupLayouts,
+    material::TilemapMaterial,
+    pipeline::{EntiTilesPipeline, EntiTilesPipelineKey},
+    texture::TilemapTexturesStorage,
+};
+
+/// A kind of tilemap that will be rendered later. See `TilemapPrewarm`.
+#[derive(Debug, Default, Clone)]
+pub struct TilemapPrewarmRequest {
+    pub ty: TilemapType,
+    /// `None` for pure color tilemaps.
+    pub texture: Option<TilemapTexture>,
+    /// See `TilemapCompactVertices`.
+    pub compact_vertices: bool,
+    /// See `TilemapUniformTint`.
+    pub uniform_tint: bool,
+}
+
+/// Specializes the render pipelines and creates the texture bind groups
+/// for the requested kinds of tilemaps before they are spawned.
+///
+/// Add the requests when the loading screen shows up, and wait for `is_ready`
+/// before spawning the big maps, so the first frame they appear doesn't hitch
+/// on compiling the pipelines. The pipelines use the current `Msaa`.
+///
+/// The textures are kept alive by this resource until the requests are cleared.
+#[derive(Resource, Clone)]
+pub struct TilemapPrewarm<M: TilemapMaterial> {
+    pub(crate) requests: Vec<TilemapPrewarmRequest>,
+    pub(crate) ready: Arc<AtomicBool>,
+    pub(crate) marker: PhantomData<M>,
+}
+
+impl<M: TilemapMaterial> Default for TilemapPrewarm<M> {
+    fn default() -> Self {
+        Self {
+            requests: Default::default(),
+            ready: Arc::new(AtomicBool::new(true)),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<M: TilemapMaterial> TilemapPrewarm<M> {
+    /// Request a kind of tilemap to be pre-warmed.
+    pub fn request(&mut self, request: TilemapPrewarmRequest) {
+        self.requests.push(request);
+        self.ready.store(false, Ordering::Release);
+    }
+
+    /// Remove all the requests and release the textures.
+    pub fn clear(&mut self) {
+        self.requests.clear();
+        self.ready.store(true, Ordering::Release);
+    }
+
+    /// Whether all the requested pipelines are compiled and the bind groups are created.
+    #[inline]
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire)
+    }
+}
+
+pub fn extract_prewarm<M: TilemapMaterial>(
+    mut commands: Commands,
+    prewarm: Extract<Res<TilemapPrewarm<M>>>,
+) {
+    if prewarm.is_changed() {
+        commands.insert_resource(prewarm.clone());
+    }
+}
+
+/// Queues the requested textures, so they are prepared along with the extracted tilemaps.
+pub fn prepare_prewarm<M: TilemapMaterial>(
+    prewarm: Option<Res<TilemapPrewarm<M>>>,
+    mut textures_storage: ResMut<TilemapTexturesStorage>,
+) {
+    let Some(prewarm) = prewarm else {
+        return;
+    };
+
+    prewarm
+        .requests
+        .iter()
+        .filter_map(|req| req.texture.as_ref())
+        .for_each(|texture| {
+            if !textures_storage.contains(&texture.texture) {
+                textures_storage.insert(texture.clone_weak(), texture.desc());
+            }
+        });
+}
+
+pub fn queue_prewarm<M: TilemapMaterial>(
+    prewarm: Option<Res<TilemapPrewarm<M>>>,
+    pipeline_cache: Res<PipelineCache>,
+    mut sp_entitiles_pipeline: ResMut<SpecializedRenderPipelines<EntiTilesPipeline<M>>>,
+    entitiles_pipeline: Res<EntiTilesPipeline<M>>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    bind_group_layouts: Res<TilemapBindGroupLayouts>,
+    mut textures_storage: ResMut<TilemapTexturesStorage>,
+    mut render_images: ResMut<RenderAssets<Image>>,
+    msaa: Res<Msaa>,
+) {
+    let Some(prewarm) = prewarm else {
+        return;
+    };
+    if prewarm.is_ready() {
+        return;
+    }
+
+    textures_storage.queue_textures(&render_device, &render_queue, &mut render_images);
+    textures_storage.bind_textures(&render_device, &bind_group_layouts);
+
+    let mut ready = true;
+    for req in prewarm.requests.iter() {
+        if let Some(texture) = req.texture.as_ref() {
+            if textures_storage.get_bind_group(texture.handle()).is_none() {
+                // The pipeline key depends on whether the texture ends up as an array.
+                ready = false;
+                continue;
+            }
+        }
+
+        let texture_array = textures_storage.texture_kind(req.texture.as_ref());
+        let pipeline = sp_entitiles_pipeline.specialize(
+            &pipeline_cache,
+            &entitiles_pipeline,
+            EntiTilesPipelineKey {
+                msaa: msaa.samples(),
+                map_type: req.ty,
+                is_pure_color: texture_array.is_none(),
+                is_texture_array: texture_array.unwrap_or_default(),
+                is_compact: req.compact_vertices,
+                is_uniform_tint: req.uniform_tint,
+            },
+        );
+
+        if !matches!(
+            pipeline_cache.get_render_pipeline_state(pipeline),
+            CachedPipelineState::Ok(_)
+        ) {
+            ready = false;
+        }
+    }
+
+    if ready {
+        prewarm.ready.store(true, Ordering::Release);
+    }
+}
@@ -6,7 +6,7 @@ use bevy::{
         render_asset::RenderAssets,
         render_phase::{DrawFunctions, RenderPhase},
         render_resource::{BindGroupEntry, PipelineCache, SpecializedRenderPipelines},
-        renderer::RenderDevice,
+        renderer::{RenderDevice, RenderQueue},
         texture::Image,
         view::ViewUniforms,
     },
@@ -14,7 +14,7 @@ use bevy::{
 };
 
 use super::{
-    binding::{TilemapBindGroups, TilemapViewBindGroup},
+    binding::{TilemapBindGroupLayouts, TilemapViewBindGroup},
     draw::DrawTilemap,
     extract::TilemapInstance,
     material::TilemapMaterial,
@@ -23,9 +23,6 @@ use super::{
     texture::TilemapTexturesStorage,
 };
 
-#[cfg(not(feature = "atlas"))]
-use bevy::render::renderer::RenderQueue;
-
 pub fn queue<M: TilemapMaterial>(
     mut commands: Commands,
     mut views_query: Query<(Entity, &mut RenderPhase<Transparent2d>)>,
@@ -36,22 +33,19 @@ pub fn queue<M: TilemapMaterial>(
     entitiles_pipeline: Res<EntiTilesPipeline<M>>,
     view_uniforms: Res<ViewUniforms>,
     render_device: Res<RenderDevice>,
-    mut bind_groups: ResMut<TilemapBindGroups<M>>,
+    bind_group_layouts: Res<TilemapBindGroupLayouts>,
     mut textures_storage: ResMut<TilemapTexturesStorage>,
     msaa: Res<Msaa>,
     tilemap_instances: Res<TilemapInstances<M>>,
-    #[cfg(not(feature = "atlas"))] render_queue: Res<RenderQueue>,
-    #[cfg(not(feature = "atlas"))] render_images: Res<RenderAssets<Image>>,
-    #[cfg(feature = "atlas")] mut render_images: ResMut<RenderAssets<Image>>,
+    render_queue: Res<RenderQueue>,
+    mut render_images: ResMut<RenderAssets<Image>>,
 ) {
     let Some(view_binding) = view_uniforms.uniforms.binding() else {
         return;
     };
 
-    #[cfg(not(feature = "atlas"))]
-    textures_storage.queue_textures(&render_device, &render_queue, &render_images);
-    #[cfg(feature = "atlas")]
-    textures_storage.queue_textures(&render_device, &mut render_images);
+    textures_storage.queue_textures(&render_device, &render_queue, &mut render_images);
+    textures_storage.bind_textures(&render_device, &bind_group_layouts);
 
     for (view_entity, mut transparent_phase) in views_query.iter_mut() {
         commands.entity(view_entity).insert(TilemapViewBindGroup {
@@ -72,12 +66,7 @@ pub fn queue<M: TilemapMaterial>(
         radsort::sort_by_key(&mut tilemaps, |m| m.transform.z_index);
 
         for tilemap in tilemaps.iter() {
-            let is_pure_color = bind_groups.queue_textures(
-                &tilemap,
-                &render_device,
-                &textures_storage,
-                &entitiles_pipeline,
-            );
+            let texture_array = textures_storage.texture_kind(tilemap.texture.as_ref());
 
             let pipeline = sp_entitiles_pipeline.specialize(
                 &pipeline_cache,
@@ -85,7 +74,10 @@ pub fn queue<M: TilemapMaterial>(
                 EntiTilesPipelineKey {
                     msaa: msaa.samples(),
                     map_type: tilemap.ty,
-                    is_pure_color,
+                    is_pure_color: texture_array.is_none(),
+                    is_texture_array: texture_array.unwrap_or_default(),
+                    is_compact: tilemap.compact_vertices,
+                    is_uniform_tint: tilemap.uniform_tint.is_some(),
                 },
             );
 
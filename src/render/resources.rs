@@ -14,6 +14,12 @@ impl<M: TilemapMaterial> Default for TilemapInstances<M> {
     }
 }
 
+/// The render world part of `TilemapPool`.
+#[derive(Resource, Default)]
+pub struct ExtractedTilemapPool {
+    pub max_chunks: usize,
+}
+
 #[derive(Resource)]
 pub struct ExtractedTilemapMaterials<M: TilemapMaterial> {
     pub changed: Vec<(AssetId<M>, M)>,
@@ -2,12 +2,20 @@
 
 struct TilemapVertexInput {
     @builtin(vertex_index) v_index: u32,
+    // The offset of the tile in pixels and the rotation around its center.
+#ifdef COMPACT_VERTEX
+    // Packed as f16: (x | y << 16, rotation). Use `tile_offset` to read it.
+    @location(0) position: vec2<u32>,
+#else
     @location(0) position: vec3<f32>,
+#endif
     // When the third and forth component of index are not -1,
     // it means this tile is a animated tile.
     // So the zw components are the start index and the length of the animation sequence.
     @location(1) index: vec4<i32>,
+#ifndef UNIFORM_TINT
     @location(2) tint: vec4<f32>,
+#endif
 #ifndef PURE_COLOR
     @location(3) texture_indices: vec4<i32>,
     @location(4) flip: vec4<u32>,
@@ -17,6 +25,11 @@ struct TilemapVertexInput {
 struct TilemapVertexOutput {
     @builtin(position) position: vec4<f32>,
     @location(0) tint: vec4<f32>,
+    // The position of this fragment in world space.
+    @location(5) world_position: vec2<f32>,
+    // The position of this fragment inside the tile quad, from 0 to 1.
+    @location(6) tile_uv: vec2<f32>,
+    @location(7) @interpolate(flat) grid_index: vec2<i32>,
 #ifndef PURE_COLOR
     @location(1) uv: vec2<f32>,
     @location(2) flip: vec4<u32>,
@@ -38,11 +51,31 @@ struct Tilemap {
     // this value will only be meaningful when the tilemap is hexagonal!
     hex_legs: f32,
     time: f32,
-#ifdef ATLAS
-    // texture size in tiles
+    // The opacity of the whole tilemap.
+    opacity: f32,
+    grid_color: vec4<f32>,
+    checker_tint: vec4<f32>,
+    // 0 means no grid lines.
+    grid_thickness: f32,
+    // The tile index that is treated as (0, 0), to keep the positions small in huge worlds.
+    index_offset: vec2<i32>,
+    // Where the animation groups start in anim_seqs.
+    // Each group is stored as the bits of (elapsed, phase.x, phase.y, 0).
+    anim_groups_offset: i32,
+    anim_group_count: u32,
+    chunk_size: u32,
+    // The chunk tints are stored in anim_seqs as (chunk.x, chunk.y, rgba bits),
+    // only for the tinted chunks and sorted by (chunk.y, chunk.x).
+    chunk_tints_offset: i32,
+    chunk_tint_count: u32,
+    // texture size in tiles, only used by texture atlases
     texture_tiled_size: vec2<i32>,
     tile_uv_size: vec2<f32>,
-#endif
+    // tile_uv_size plus the spacing between the tiles
+    tile_uv_stride: vec2<f32>,
+    uv_margin: vec2<f32>,
+    // The tint of the whole tilemap when the tints of the tiles are left out.
+    tint: vec4<f32>,
 }
 
 struct StandardTilemapUniform {
@@ -66,7 +99,133 @@ var color_texture: texture_2d_array<f32>;
 
 @group(3) @binding(1)
 var color_texture_sampler: sampler;
+#endif
 
+// Also holds the animation groups and the chunk tints.
+#ifdef PURE_COLOR
+@group(3) @binding(0)
+#else
 @group(4) @binding(0)
+#endif
 var<storage> anim_seqs: array<i32>;
+
+// The offset and the rotation of the tile, whichever vertex format is used.
+fn tile_offset(input: TilemapVertexInput) -> vec3<f32> {
+#ifdef COMPACT_VERTEX
+    return vec3<f32>(unpack2x16float(input.position.x), unpack2x16float(input.position.y).x);
+#else
+    return input.position;
+#endif
+}
+
+// The tint of the tile, or of the whole tilemap if the tints of the tiles are left out.
+fn tile_tint(input: TilemapVertexInput) -> vec4<f32> {
+#ifdef UNIFORM_TINT
+    return tilemap.tint;
+#else
+    return input.tint;
 #endif
+}
+
+// The tint of the chunk that the tile at this index belongs to.
+fn chunk_tint(index: vec2<i32>) -> vec4<f32> {
+    let chunk_size = vec2<i32>(i32(tilemap.chunk_size));
+    // Rounds towards negative infinity, like the chunk indices on the cpu side.
+    let floored = index / chunk_size
+                  - select(vec2<i32>(0), vec2<i32>(1), index % chunk_size < vec2<i32>(0));
+
+    // Binary search for the first entry not less than the chunk.
+    var lo = 0;
+    var hi = i32(tilemap.chunk_tint_count);
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        let base = tilemap.chunk_tints_offset + mid * 6;
+        let entry = vec2<i32>(anim_seqs[base], anim_seqs[base + 1]);
+        if entry.y < floored.y || (entry.y == floored.y && entry.x < floored.x) {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+
+    let base = tilemap.chunk_tints_offset + lo * 6;
+    if lo == i32(tilemap.chunk_tint_count)
+        || any(vec2<i32>(anim_seqs[base], anim_seqs[base + 1]) != floored) {
+        return vec4<f32>(1., 1., 1., 1.);
+    }
+
+    return vec4<f32>(
+        bitcast<f32>(anim_seqs[base + 2]),
+        bitcast<f32>(anim_seqs[base + 3]),
+        bitcast<f32>(anim_seqs[base + 4]),
+        bitcast<f32>(anim_seqs[base + 5]),
+    );
+}
+
+#ifndef PURE_COLOR
+// Samples and mixes all the layers of a tile.
+fn sample_tile_layers(input: TilemapVertexOutput) -> vec4<f32> {
+    var color = vec4<f32>(0., 0., 0., 0.);
+
+    // Sample the 4 layers.
+    for (var i = 0u; i < 4u; i++) {
+        if input.texture_indices[i] < 0 {
+            // No texture for this layer.
+            continue;
+        }
+
+        var uv = input.uv;
+        // Flip the uv if needed.
+        if (input.flip[i] & 1u) != 0u {
+            uv.x = 1. - uv.x;
+        }
+        if (input.flip[i] & 2u) != 0u {
+            uv.y = 1. - uv.y;
+        }
+#ifdef ATLAS
+        // If the texture is an atlas, we need to calculate the uv.
+        let tile_index = vec2<f32>(f32(input.texture_indices[i] % tilemap.texture_tiled_size.x),
+                                   f32(input.texture_indices[i] / tilemap.texture_tiled_size.x));
+        let atlas_uv = tilemap.uv_margin + tile_index * tilemap.tile_uv_stride
+                       + uv * tilemap.tile_uv_size;
+        let tex_color = textureSample(color_texture, color_texture_sampler, atlas_uv);
+#else
+        // Otherwise, sample the texture at the right layer using the uv directly.
+        let tex_color = textureSample(color_texture, color_texture_sampler,
+                                      uv, input.texture_indices[i]);
+#endif
+        // Mix the color of each layer.
+        color = mix(color, tex_color, tex_color.a * tilemap.layer_opacities[i]);
+
+        if input.anim_flag != -1 {
+            // Indicates that this tile is a animated tile.
+            // We only need to sample the first layer as animated tiles are always single layered.
+            break;
+        }
+    }
+
+    return color;
+}
+#endif
+
+// Applies the checkerboard tint and the grid lines of the tilemap.
+fn apply_grid(color: vec4<f32>, input: TilemapVertexOutput) -> vec4<f32> {
+    var result = color;
+
+    if ((input.grid_index.x + input.grid_index.y) & 1) != 0 {
+        result *= tilemap.checker_tint;
+    }
+
+    if tilemap.grid_thickness > 0. {
+        // Distance to the nearest edge of the tile in world units.
+        let dist = min(input.tile_uv, 1. - input.tile_uv) * tilemap.tile_render_size;
+        if min(dist.x, dist.y) < tilemap.grid_thickness * 0.5 {
+            result = vec4<f32>(
+                mix(result.rgb, tilemap.grid_color.rgb, tilemap.grid_color.a),
+                max(result.a, tilemap.grid_color.a * tilemap.opacity)
+            );
+        }
+    }
+
+    return result;
+}
@@ -0,0 +1,57 @@
+#import bevy_entitiles::common::TilemapVertexOutput
+
+#ifndef PURE_COLOR
+#import bevy_entitiles::common::sample_tile_layers
+#endif
+
+struct DissolveTilemapUniform {
+    tint: vec4<f32>,
+    edge_color: vec4<f32>,
+    progress: f32,
+    edge_width: f32,
+    noise_scale: f32,
+}
+
+@group(2) @binding(0)
+var<uniform> dissolve: DissolveTilemapUniform;
+
+fn hash(p: vec2<f32>) -> f32 {
+    var q = fract(p * vec2<f32>(123.34, 456.21));
+    q += dot(q, q + 45.32);
+    return fract(q.x * q.y);
+}
+
+// Smoothed value noise in [0, 1).
+fn value_noise(p: vec2<f32>) -> f32 {
+    let i = floor(p);
+    let f = fract(p);
+    let u = f * f * (3. - 2. * f);
+
+    return mix(
+        mix(hash(i), hash(i + vec2<f32>(1., 0.)), u.x),
+        mix(hash(i + vec2<f32>(0., 1.)), hash(i + vec2<f32>(1., 1.)), u.x),
+        u.y
+    );
+}
+
+@fragment
+fn tilemap_fragment(input: TilemapVertexOutput) -> @location(0) vec4<f32> {
+#ifdef PURE_COLOR
+    var color = input.tint * dissolve.tint;
+#else
+    var color = sample_tile_layers(input) * input.tint * dissolve.tint;
+#endif
+
+    // The noise is sampled in world space so the pattern is continuous across tiles.
+    let noise = value_noise(input.world_position / dissolve.noise_scale);
+    if noise < dissolve.progress {
+        discard;
+    }
+
+    // Fragments that are about to dissolve glow with the edge color.
+    if dissolve.progress > 0. && noise < dissolve.progress + dissolve.edge_width {
+        color = vec4<f32>(mix(color.rgb, dissolve.edge_color.rgb, dissolve.edge_color.a), color.a);
+    }
+
+    return color;
+}
@@ -0,0 +1,45 @@
+#import bevy_ui::ui_vertex_output::UiVertexOutput
+
+struct MinimapUniform {
+    view_min: vec2<f32>,
+    view_max: vec2<f32>,
+    background: vec4<f32>,
+    marker_count: u32,
+}
+
+struct MinimapMarker {
+    position: vec2<f32>,
+    radius: f32,
+    color: vec4<f32>,
+}
+
+@group(1) @binding(0)
+var<uniform> minimap: MinimapUniform;
+@group(1) @binding(1)
+var map_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var map_sampler: sampler;
+@group(1) @binding(3)
+var<storage, read> markers: array<MinimapMarker>;
+
+@fragment
+fn fragment(input: UiVertexOutput) -> @location(0) vec4<f32> {
+    let view_size = minimap.view_max - minimap.view_min;
+    let uv = minimap.view_min + input.uv * view_size;
+    var color = textureSample(map_texture, map_sampler, uv);
+
+    if any(uv < vec2<f32>(0.)) || any(uv > vec2<f32>(1.)) {
+        color = minimap.background;
+    }
+
+    // In pixels of the ui node.
+    let px_per_uv = input.size / view_size;
+    for (var i = 0u; i < minimap.marker_count; i++) {
+        let marker = markers[i];
+        let dist = length((marker.position - uv) * px_per_uv);
+        let coverage = clamp(marker.radius - dist + 0.5, 0., 1.) * marker.color.a;
+        color = mix(color, vec4<f32>(marker.color.rgb, 1.), coverage);
+    }
+
+    return color;
+}
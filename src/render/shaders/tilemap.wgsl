@@ -1,5 +1,6 @@
 #import bevy_entitiles::common::{
-    TilemapVertexInput, TilemapVertexOutput, tilemap, atlas_uvs, anim_seqs, material
+    TilemapVertexInput, TilemapVertexOutput, tilemap, atlas_uvs, anim_seqs, material,
+    sample_tile_layers, apply_grid, tile_offset, tile_tint, chunk_tint
 }
 #import bevy_sprite::mesh2d_view_bindings::view
 
@@ -20,7 +21,9 @@
 @vertex
 fn tilemap_vertex(input: TilemapVertexInput) -> TilemapVertexOutput {
     var output: TilemapVertexOutput;
-    var mesh_origin = get_mesh_origin(input);
+    var rebased = input;
+    rebased.index = vec4<i32>(input.index.xy - tilemap.index_offset, input.index.zw);
+    var mesh_origin = get_mesh_origin(rebased);
     
     var translations = array<vec2<f32>, 4>(
         vec2<f32>(0., 0.),
@@ -29,12 +32,25 @@ fn tilemap_vertex(input: TilemapVertexInput) -> TilemapVertexOutput {
         vec2<f32>(1., 0.),
     );
 
-    var position_model = (translations[input.v_index % 4u] - tilemap.pivot)
-                          * tilemap.tile_render_size + mesh_origin;
+    // Rotate around the center of the tile.
+    let offset = tile_offset(input);
+    let sin_cos = vec2<f32>(sin(offset.z), cos(offset.z));
+    let from_center = (translations[input.v_index % 4u] - 0.5) * tilemap.tile_render_size;
+    let rotated = vec2<f32>(
+        from_center.x * sin_cos.y - from_center.y * sin_cos.x,
+        from_center.x * sin_cos.x + from_center.y * sin_cos.y,
+    );
+    var position_model = rotated + (0.5 - tilemap.pivot) * tilemap.tile_render_size
+                          + mesh_origin + offset.xy;
     var position_world = vec4<f32>((tilemap.rot_mat * position_model) + tilemap.translation, 0., 1.);
 
     output.position = view.view_proj * position_world;
-    output.tint = input.tint;
+    output.world_position = position_world.xy;
+    // The opacity of the tilemap is applied through the tint, so custom materials get it too.
+    output.tint = tile_tint(input) * chunk_tint(input.index.xy)
+                  * vec4<f32>(1., 1., 1., tilemap.opacity);
+    output.tile_uv = translations[input.v_index % 4u];
+    output.grid_index = input.index.xy;
 
 #ifndef PURE_COLOR
 #ifdef ATLAS
@@ -59,11 +75,28 @@ fn tilemap_vertex(input: TilemapVertexInput) -> TilemapVertexOutput {
     if input.index.z != -1 {
         // Means that this tile is a animated tile
         let start = input.index.z;
-        let length = input.index.w;
+        // The higher bits are the animation group.
+        let length = input.index.w & 0xffff;
+        let group_index = u32(input.index.w) >> 16u;
+        // The groups that are not uploaded follow the global time.
+        var group = vec4<f32>(tilemap.time, 0., 0., 0.);
+        if group_index < tilemap.anim_group_count {
+            let base = tilemap.anim_groups_offset + i32(group_index) * 4;
+            group = vec4<f32>(
+                bitcast<f32>(anim_seqs[base]),
+                bitcast<f32>(anim_seqs[base + 1]),
+                bitcast<f32>(anim_seqs[base + 2]),
+                0.,
+            );
+        }
         // The number before the start index is the fps.
         // See register function in TilemapAnimations.
         let fps = f32(anim_seqs[start - 1]);
-        var frame = i32(tilemap.time * fps) % length;
+        let phase = dot(vec2<f32>(input.index.xy), group.yz);
+        var frame = i32(floor(group.x * fps + phase)) % length;
+        if frame < 0 {
+            frame += length;
+        }
         output.texture_indices[0] = anim_seqs[start + frame];
     } else {
         output.texture_indices = input.texture_indices;
@@ -76,49 +109,10 @@ fn tilemap_vertex(input: TilemapVertexInput) -> TilemapVertexOutput {
 @fragment
 fn tilemap_fragment(input: TilemapVertexOutput) -> @location(0) vec4<f32> {
 #ifdef PURE_COLOR
-    return input.tint;
-#else
-    var color = vec4<f32>(0., 0., 0., 0.);
-
-    // Sample the 4 layers.
-    for (var i = 0u; i < 4u; i++) {
-        if input.texture_indices[i] < 0 {
-            // No texture for this layer.
-            continue;
-        }
-
-        var uv = input.uv;
-        // Flip the uv if needed.
-        if (input.flip[i] & 1u) != 0u {
-            uv.x = 1. - uv.x;
-        }
-        if (input.flip[i] & 2u) != 0u {
-            uv.y = 1. - uv.y;
-        }
-#ifdef ATLAS
-        // If `atlas` feature is enabled, we need to calculate the uv.
-        let tile_index = vec2<f32>(f32(input.texture_indices[i] % tilemap.texture_tiled_size.x),
-                                   f32(input.texture_indices[i] / tilemap.texture_tiled_size.x));
-        let atlas_uv = (tile_index + uv) * tilemap.tile_uv_size;
-        let tex_color = textureSample(bevy_entitiles::common::color_texture,
-                                      bevy_entitiles::common::color_texture_sampler,
-                                      atlas_uv);
+    return apply_grid(input.tint, input);
 #else
-        // Otherwise, sample the texture at the right layer using the uv directly.
-        let tex_color = textureSample(bevy_entitiles::common::color_texture,
-                                      bevy_entitiles::common::color_texture_sampler,
-                                      uv, input.texture_indices[i]);
-#endif
-        // Mix the color of each layer.
-        color = mix(color, tex_color, tex_color.a * tilemap.layer_opacities[i]);
-
-        if input.anim_flag != -1 {
-            // Indicates that this tile is a animated tile.
-            // We only need to sample the first layer as animated tiles are always single layered.
-            break;
-        }
-    }
+    let color = sample_tile_layers(input);
     // Apply the tint of the tile and the tilemap.
-    return color * input.tint * material.color;
+    return apply_grid(color * input.tint * material.color, input);
 #endif
 }
@@ -5,65 +5,146 @@ use bevy::{
         query::With,
         system::{Commands, Query, ResMut, Resource},
     },
+    log::warn,
+    math::{UVec2, Vec2},
     prelude::Image,
     render::{
         render_asset::RenderAssets,
-        render_resource::{AddressMode, SamplerDescriptor, TextureUsages},
-        renderer::RenderDevice,
-        texture::GpuImage,
-    },
-    utils::HashMap,
-};
-
-#[cfg(not(feature = "atlas"))]
-use bevy::{
-    math::Vec2,
-    render::{
         render_resource::{
-            Extent3d, ImageCopyTexture, Origin3d, TextureAspect, TextureDescriptor,
-            TextureDimension, TextureFormat, TextureViewDescriptor, TextureViewDimension,
+            AddressMode, BindGroup, BindGroupEntries, Extent3d, ImageCopyTexture, Origin3d,
+            SamplerDescriptor, TextureAspect, TextureDescriptor, TextureDimension, TextureFormat,
+            TextureUsages, TextureViewDescriptor, TextureViewDimension,
         },
-        renderer::RenderQueue,
+        renderer::{RenderDevice, RenderQueue},
+        texture::GpuImage,
     },
+    utils::{HashMap, HashSet},
 };
 
 use crate::tilemap::map::{TilemapTexture, TilemapTextureDescriptor, WaitForTextureUsageChange};
 
+use super::binding::TilemapBindGroupLayouts;
+
 #[derive(Resource, Default)]
 pub struct TilemapTexturesStorage {
     textures: HashMap<Handle<Image>, GpuImage>,
+    texture_arrays: HashSet<Handle<Image>>,
     prepare_queue: HashMap<Handle<Image>, TilemapTextureDescriptor>,
     queue_queue: HashMap<Handle<Image>, TilemapTextureDescriptor>,
+    /// One bind group for each texture, shared by all the tilemaps and materials using it.
+    bind_groups: HashMap<Handle<Image>, BindGroup>,
 }
 
 impl TilemapTexturesStorage {
     pub fn insert(&mut self, handle: Handle<Image>, desc: &TilemapTextureDescriptor) {
-        #[cfg(not(feature = "atlas"))]
-        self.prepare_queue.insert(handle, desc.clone());
-        #[cfg(feature = "atlas")]
-        self.queue_queue.insert(handle, desc.clone());
+        if desc.texture_array {
+            self.prepare_queue.insert(handle, desc.clone());
+        } else {
+            self.queue_queue.insert(handle, desc.clone());
+        }
     }
 
-    /// Try to get the processed texture array.
+    /// Try to get the processed texture array or atlas.
     pub fn get_texture(&self, image: &Handle<Image>) -> Option<&GpuImage> {
         self.textures.get(image)
     }
 
+    /// Whether the processed texture is a texture array, or the original atlas.
+    pub fn is_texture_array(&self, image: &Handle<Image>) -> bool {
+        self.texture_arrays.contains(image)
+    }
+
+    /// Returns `None` if the tilemap is pure color, otherwise whether the texture is a texture array.
+    pub fn texture_kind(&self, texture: Option<&TilemapTexture>) -> Option<bool> {
+        let texture = texture?;
+        if self.textures.contains_key(texture.handle()) {
+            Some(self.is_texture_array(texture.handle()))
+        } else {
+            self.contains(texture.handle())
+                .then_some(texture.desc().texture_array)
+        }
+    }
+
+    #[inline]
+    pub fn get_bind_group(&self, image: &Handle<Image>) -> Option<&BindGroup> {
+        self.bind_groups.get(image)
+    }
+
+    /// Create the bind groups for the textures that don't have one yet.
+    pub fn bind_textures(
+        &mut self,
+        render_device: &RenderDevice,
+        layouts: &TilemapBindGroupLayouts,
+    ) {
+        self.textures.iter().for_each(|(handle, texture)| {
+            if self.bind_groups.contains_key(handle) {
+                return;
+            }
+
+            self.bind_groups.insert(
+                handle.clone_weak(),
+                render_device.create_bind_group(
+                    Some("color_texture_bind_group"),
+                    if self.texture_arrays.contains(handle) {
+                        &layouts.color_texture_layout
+                    } else {
+                        &layouts.color_texture_atlas_layout
+                    },
+                    &BindGroupEntries::sequential((&texture.texture_view, &texture.sampler)),
+                ),
+            );
+        });
+    }
+
     /// Prepare the texture, creating the texture array and translate images in `queue_texture` function.
-    #[cfg(not(feature = "atlas"))]
-    pub fn prepare_textures(&mut self, render_device: &RenderDevice) {
+    ///
+    /// The texture array uses the same format as the image, so compressed textures stay compressed.
+    /// If the tiles are not aligned to the compressed blocks, the texture is used as an atlas instead.
+    pub fn prepare_textures(
+        &mut self,
+        render_device: &RenderDevice,
+        render_images: &RenderAssets<Image>,
+    ) {
         if self.prepare_queue.is_empty() {
             return;
         }
 
         let to_prepare = self.prepare_queue.drain().collect::<Vec<_>>();
 
-        for (image_handle, desc) in to_prepare.iter() {
+        for (image_handle, desc) in to_prepare.into_iter() {
             if image_handle.id() == Handle::<Image>::default().id() {
                 continue;
             }
 
-            let tile_count = desc.size / desc.tile_size;
+            let Some(raw_gpu_image) = render_images.get(&image_handle) else {
+                self.prepare_queue.insert(image_handle, desc);
+                continue;
+            };
+
+            let format = raw_gpu_image.texture_format;
+            let mut mip_level_count =
+                array_mip_level_count(desc.tile_size, format).min(raw_gpu_image.mip_level_count);
+            if desc.is_padded() {
+                // The padding is not aligned on the smaller mip levels.
+                mip_level_count = mip_level_count.min(1);
+            }
+            if mip_level_count == 0 {
+                warn!(
+                    "The tile size {} is not aligned to the blocks of {:?}, \
+                    using the texture as an atlas instead of a texture array.",
+                    desc.tile_size, format
+                );
+                self.queue_queue.insert(
+                    image_handle,
+                    TilemapTextureDescriptor {
+                        texture_array: false,
+                        ..desc
+                    },
+                );
+                continue;
+            }
+
+            let tile_count = desc.tile_count();
 
             let texture = render_device.create_texture(&TextureDescriptor {
                 label: Some("tilemap_texture_array"),
@@ -72,10 +153,10 @@ impl TilemapTexturesStorage {
                     height: desc.tile_size.y,
                     depth_or_array_layers: tile_count.x * tile_count.y,
                 },
-                mip_level_count: 1,
+                mip_level_count,
                 sample_count: 1,
                 dimension: TextureDimension::D2,
-                format: TextureFormat::Rgba8UnormSrgb,
+                format,
                 usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
                 view_formats: &[],
             });
@@ -97,7 +178,7 @@ impl TilemapTexturesStorage {
 
             let texture_view = texture.create_view(&TextureViewDescriptor {
                 label: Some("tilemap_texture_array_view"),
-                format: Some(TextureFormat::Rgba8UnormSrgb),
+                format: Some(format),
                 dimension: Some(TextureViewDimension::D2Array),
                 aspect: TextureAspect::All,
                 base_mip_level: 0,
@@ -115,19 +196,19 @@ impl TilemapTexturesStorage {
                 size: Vec2::new(desc.tile_size.x as f32, desc.tile_size.y as f32),
             };
 
+            self.bind_groups.remove(&image_handle);
             self.textures.insert(image_handle.clone_weak(), gpu_image);
-            self.queue_queue
-                .insert(image_handle.clone_weak(), desc.clone());
+            self.texture_arrays.insert(image_handle.clone_weak());
+            self.queue_queue.insert(image_handle.clone_weak(), desc);
         }
     }
 
-    /// Translate images to texture array.
-    #[cfg(not(feature = "atlas"))]
+    /// Translate images to texture arrays, and set up the samplers of the atlases.
     pub fn queue_textures(
         &mut self,
         render_device: &RenderDevice,
         render_queue: &RenderQueue,
-        render_images: &RenderAssets<Image>,
+        render_images: &mut RenderAssets<Image>,
     ) {
         if self.queue_queue.is_empty() {
             return;
@@ -135,43 +216,72 @@ impl TilemapTexturesStorage {
 
         let to_queue = self.queue_queue.drain().collect::<Vec<_>>();
 
-        for (image_handle, desc) in to_queue.iter() {
-            let Some(raw_gpu_image) = render_images.get(image_handle) else {
-                self.queue_queue
-                    .insert(image_handle.clone_weak(), desc.clone());
-                continue;
+        for (image_handle, desc) in to_queue.into_iter() {
+            let queued = if desc.texture_array {
+                self.queue_texture_array(
+                    &image_handle,
+                    &desc,
+                    render_device,
+                    render_queue,
+                    render_images,
+                )
+            } else {
+                self.queue_texture_atlas(&image_handle, &desc, render_device, render_images)
             };
 
-            if !raw_gpu_image
-                .texture
-                .usage()
-                .contains(TextureUsages::COPY_SRC)
-            {
-                self.queue_queue
-                    .insert(image_handle.clone_weak(), desc.clone());
-                continue;
+            if !queued {
+                self.queue_queue.insert(image_handle, desc);
             }
+        }
+    }
+
+    /// Returns false if the image is not ready yet.
+    fn queue_texture_array(
+        &mut self,
+        image_handle: &Handle<Image>,
+        desc: &TilemapTextureDescriptor,
+        render_device: &RenderDevice,
+        render_queue: &RenderQueue,
+        render_images: &RenderAssets<Image>,
+    ) -> bool {
+        let Some(raw_gpu_image) = render_images.get(image_handle) else {
+            return false;
+        };
 
-            let tile_count = desc.size / desc.tile_size;
-            let array_gpu_image = self.textures.get(image_handle).unwrap();
-            let mut command_encoder = render_device.create_command_encoder(&Default::default());
+        if !raw_gpu_image
+            .texture
+            .usage()
+            .contains(TextureUsages::COPY_SRC)
+        {
+            return false;
+        }
+
+        let tile_count = desc.tile_count();
+        let array_gpu_image = self.textures.get(image_handle).unwrap();
+        let mut command_encoder = render_device.create_command_encoder(&Default::default());
 
+        for mip_level in 0..array_gpu_image.mip_level_count {
+            let tile_size = desc.tile_size >> mip_level;
             for index_y in 0..tile_count.y {
                 for index_x in 0..tile_count.x {
                     command_encoder.copy_texture_to_texture(
                         ImageCopyTexture {
                             texture: &raw_gpu_image.texture,
-                            mip_level: 0,
-                            origin: Origin3d {
-                                x: index_x * desc.tile_size.x,
-                                y: index_y * desc.tile_size.y,
-                                z: 0,
+                            mip_level,
+                            origin: {
+                                let origin =
+                                    desc.tile_origin(index_x + index_y * tile_count.x) >> mip_level;
+                                Origin3d {
+                                    x: origin.x,
+                                    y: origin.y,
+                                    z: 0,
+                                }
                             },
                             aspect: TextureAspect::All,
                         },
                         ImageCopyTexture {
                             texture: &array_gpu_image.texture,
-                            mip_level: 0,
+                            mip_level,
                             origin: Origin3d {
                                 x: 0,
                                 y: 0,
@@ -180,54 +290,50 @@ impl TilemapTexturesStorage {
                             aspect: TextureAspect::All,
                         },
                         Extent3d {
-                            width: desc.tile_size.x,
-                            height: desc.tile_size.y,
+                            width: tile_size.x,
+                            height: tile_size.y,
                             depth_or_array_layers: 1,
                         },
                     );
                 }
             }
-
-            render_queue.submit(vec![command_encoder.finish()]);
         }
+
+        render_queue.submit(vec![command_encoder.finish()]);
+        true
     }
 
-    #[cfg(feature = "atlas")]
-    pub fn queue_textures(
+    /// Returns false if the image is not ready yet.
+    fn queue_texture_atlas(
         &mut self,
+        image_handle: &Handle<Image>,
+        desc: &TilemapTextureDescriptor,
         render_device: &RenderDevice,
         render_images: &mut RenderAssets<Image>,
-    ) {
-        if self.queue_queue.is_empty() {
-            return;
-        }
-
-        let to_queue = self.queue_queue.drain().collect::<Vec<_>>();
-
-        for (image_handle, desc) in to_queue.into_iter() {
-            let Some(texture) = render_images.get_mut(&image_handle) else {
-                self.queue_queue.insert(image_handle, desc);
-                continue;
-            };
+    ) -> bool {
+        let Some(texture) = render_images.get_mut(image_handle) else {
+            return false;
+        };
 
-            let sampler = render_device.create_sampler(&SamplerDescriptor {
-                label: Some("tilemap_texture_atlas_sampler"),
-                address_mode_u: AddressMode::ClampToEdge,
-                address_mode_v: AddressMode::ClampToEdge,
-                address_mode_w: AddressMode::ClampToEdge,
-                mag_filter: desc.filter_mode,
-                min_filter: desc.filter_mode,
-                mipmap_filter: desc.filter_mode,
-                lod_min_clamp: 0.,
-                lod_max_clamp: f32::MAX,
-                compare: None,
-                anisotropy_clamp: 1,
-                border_color: None,
-            });
+        let sampler = render_device.create_sampler(&SamplerDescriptor {
+            label: Some("tilemap_texture_atlas_sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: desc.filter_mode,
+            min_filter: desc.filter_mode,
+            mipmap_filter: desc.filter_mode,
+            lod_min_clamp: 0.,
+            lod_max_clamp: f32::MAX,
+            compare: None,
+            anisotropy_clamp: 1,
+            border_color: None,
+        });
 
-            texture.sampler = sampler;
-            self.textures.insert(image_handle, texture.clone());
-        }
+        texture.sampler = sampler;
+        self.bind_groups.remove(image_handle);
+        self.textures.insert(image_handle.clone(), texture.clone());
+        true
     }
 
     pub fn contains(&self, handle: &Handle<Image>) -> bool {
@@ -237,6 +343,17 @@ impl TilemapTexturesStorage {
     }
 }
 
+/// The number of mip levels that can be copied tile by tile.
+/// The tiles must be aligned to the blocks of compressed formats on every level.
+fn array_mip_level_count(tile_size: UVec2, format: TextureFormat) -> u32 {
+    let (block_width, block_height) = format.block_dimensions();
+    (0..u32::BITS)
+        .take_while(|level| {
+            tile_size.x % (block_width << level) == 0 && tile_size.y % (block_height << level) == 0
+        })
+        .count() as u32
+}
+
 pub fn set_texture_usage(
     mut commands: Commands,
     tilemaps_query: Query<(Entity, &TilemapTexture), With<WaitForTextureUsageChange>>,
@@ -266,3 +383,18 @@ pub fn set_texture_usage(
             .remove::<WaitForTextureUsageChange>();
     });
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_array_mip_level_count() {
+        let rgba = TextureFormat::Rgba8UnormSrgb;
+        let bc7 = TextureFormat::Bc7RgbaUnormSrgb;
+        assert_eq!(array_mip_level_count(UVec2::splat(16), rgba), 5);
+        assert_eq!(array_mip_level_count(UVec2::new(16, 24), rgba), 4);
+        assert_eq!(array_mip_level_count(UVec2::splat(16), bc7), 3);
+        assert_eq!(array_mip_level_count(UVec2::splat(10), bc7), 0);
+    }
+}
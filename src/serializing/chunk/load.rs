@@ -5,19 +5,30 @@ use bevy::{
         component::Component,
         entity::{Entity, EntityHashMap},
         query::With,
+        removal_detection::RemovedComponents,
         system::{Commands, ParallelCommands, Query, Res, ResMut, Resource},
     },
     math::IVec2,
     reflect::Reflect,
+    render::color::Color,
+    sprite::{Sprite, SpriteBundle},
+    transform::components::Transform,
     utils::HashMap,
 };
 
 use crate::{
-    math::extension::ChunkIndex,
-    serializing::{load_object, map::TilemapLayer},
+    math::{aabb::Aabb2d, extension::ChunkIndex},
+    serializing::{
+        load_object,
+        map::{SerializedTile, TilemapLayer},
+    },
     tilemap::{
-        buffers::TileBuilderBuffer,
-        map::{TilemapName, TilemapStorage},
+        buffers::TileBuffer,
+        map::{
+            TilePivot, TilemapAnimations, TilemapAxisFlip, TilemapName, TilemapSlotSize,
+            TilemapStorage, TilemapTransform, TilemapType,
+        },
+        packed::TilemapPackedTiles,
         tile::Tile,
     },
 };
@@ -29,13 +40,13 @@ use crate::{
     algorithm::pathfinding::PathTilemaps, serializing::chunk::PATH_TILE_CHUNKS_FOLDER,
     tilemap::buffers::PathTileBuffer,
 };
-#[cfg(feature = "physics")]
+#[cfg(feature = "physics_core")]
 use crate::{
     serializing::chunk::PHYSICS_TILE_CHUNKS_FOLDER,
     tilemap::{buffers::PackedPhysicsTileBuffer, physics::PhysicsTilemap},
 };
 
-#[cfg(any(feature = "algorithm", feature = "physics"))]
+#[cfg(any(feature = "algorithm", feature = "physics_core"))]
 use bevy::log::error;
 
 #[derive(Component)]
@@ -45,10 +56,45 @@ pub struct ScheduledLoadChunks;
 pub struct ChunkLoadConfig {
     pub path: String,
     pub chunks_per_frame: usize,
+    /// If set, a rectangle in this color will be displayed on every chunk
+    /// that is scheduled but not loaded yet.
+    pub placeholder: Option<Color>,
 }
 
+/// The placeholders of the chunks that are waiting to be loaded.
+///
+/// A placeholder is removed once its chunk is loaded. If the chunk fails to load,
+/// the placeholder stays until the tilemap is despawned.
 #[derive(Resource, Default)]
-pub struct ChunkLoadCache(pub(crate) EntityHashMap<HashMap<TilemapLayer, VecDeque<IVec2>>>);
+pub struct ChunkLoadPlaceholders(pub(crate) EntityHashMap<HashMap<IVec2, Entity>>);
+
+impl ChunkLoadPlaceholders {
+    /// Returns true if the chunk is waiting to be loaded and a placeholder is displayed on it.
+    #[inline]
+    pub fn contains(&self, tilemap: Entity, chunk_index: IVec2) -> bool {
+        self.0
+            .get(&tilemap)
+            .is_some_and(|chunks| chunks.contains_key(&chunk_index))
+    }
+
+    #[inline]
+    pub(crate) fn remove(&mut self, commands: &mut Commands, tilemap: Entity, chunk_index: IVec2) {
+        if let Some(placeholder) = self
+            .0
+            .get_mut(&tilemap)
+            .and_then(|chunks| chunks.remove(&chunk_index))
+        {
+            commands.entity(placeholder).despawn();
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ChunkLoadCache(
+    pub(crate) EntityHashMap<HashMap<TilemapLayer, VecDeque<IVec2>>>,
+    /// The number of chunks scheduled since the queue was empty.
+    pub(crate) EntityHashMap<usize>,
+);
 
 impl ChunkLoadCache {
     #[inline]
@@ -66,6 +112,7 @@ impl ChunkLoadCache {
                 .entry(layer)
                 .or_default()
                 .push_front(chunk_index);
+            *self.1.entry(tilemap).or_default() += 1;
         }
         commands.entity(tilemap).insert(ScheduledLoadChunks);
     }
@@ -80,14 +127,25 @@ impl ChunkLoadCache {
     ) {
         for layer in layers {
             let queue = self.0.entry(tilemap).or_default().entry(layer).or_default();
+            let before = queue.len();
             queue.reserve(chunk_indices.size_hint().0);
             chunk_indices
                 .clone()
                 .for_each(|chunk_index| queue.push_front(chunk_index));
+            *self.1.entry(tilemap).or_default() += queue.len() - before;
         }
         commands.entity(tilemap).insert(ScheduledLoadChunks);
     }
 
+    /// Returns the number of chunks that are still waiting in the queue.
+    #[inline]
+    pub fn remaining(&self, tilemap: Entity) -> usize {
+        self.0
+            .get(&tilemap)
+            .map(|layers| layers.values().map(|chunks| chunks.len()).sum())
+            .unwrap_or_default()
+    }
+
     #[inline]
     pub fn pop_chunk(&mut self, tilemap: Entity, layer: TilemapLayer) -> Option<IVec2> {
         self.0.get_mut(&tilemap).and_then(|layers| {
@@ -99,19 +157,108 @@ impl ChunkLoadCache {
     }
 }
 
+pub fn load_placeholder_spawner(
+    mut commands: Commands,
+    tilemaps_query: Query<
+        (
+            Entity,
+            &TilemapStorage,
+            &TilemapType,
+            &TilePivot,
+            &TilemapAxisFlip,
+            &TilemapSlotSize,
+            &TilemapTransform,
+        ),
+        With<ScheduledLoadChunks>,
+    >,
+    config: Res<ChunkLoadConfig>,
+    cache: Res<ChunkLoadCache>,
+    mut placeholders: ResMut<ChunkLoadPlaceholders>,
+) {
+    let Some(color) = config.placeholder else {
+        return;
+    };
+
+    tilemaps_query.iter().for_each(
+        |(entity, storage, ty, tile_pivot, axis_flip, slot_size, transform)| {
+            let Some(scheduled) = cache
+                .0
+                .get(&entity)
+                .and_then(|layers| layers.get(&TilemapLayer::COLOR))
+            else {
+                return;
+            };
+
+            let tilemap_placeholders = placeholders.0.entry(entity).or_default();
+            scheduled.iter().for_each(|chunk_index| {
+                if tilemap_placeholders.contains_key(chunk_index) {
+                    return;
+                }
+
+                let aabb = Aabb2d::from_tilemap(
+                    *chunk_index,
+                    storage.storage.chunk_size,
+                    *ty,
+                    tile_pivot.0,
+                    *axis_flip,
+                    slot_size.0,
+                    *transform,
+                );
+                let placeholder = commands
+                    .spawn(SpriteBundle {
+                        sprite: Sprite {
+                            color,
+                            custom_size: Some(aabb.size()),
+                            ..Default::default()
+                        },
+                        transform: Transform::from_translation(
+                            aabb.center().extend(transform.z_index),
+                        ),
+                        ..Default::default()
+                    })
+                    .id();
+                tilemap_placeholders.insert(*chunk_index, placeholder);
+            });
+        },
+    );
+}
+
+pub fn load_placeholder_cleaner(
+    mut commands: Commands,
+    mut removed: RemovedComponents<TilemapStorage>,
+    mut placeholders: ResMut<ChunkLoadPlaceholders>,
+) {
+    removed.read().for_each(|tilemap| {
+        if let Some(chunks) = placeholders.0.remove(&tilemap) {
+            chunks.into_values().for_each(|placeholder| {
+                commands.entity(placeholder).despawn();
+            });
+        }
+    });
+}
+
 pub fn load_color_layer(
     commands: ParallelCommands,
     mut tilemaps_query: Query<
-        (Entity, &TilemapName, &mut TilemapStorage),
+        (
+            Entity,
+            &TilemapName,
+            &mut TilemapStorage,
+            Option<&mut TilemapAnimations>,
+            Option<&mut TilemapPackedTiles>,
+        ),
         With<ScheduledLoadChunks>,
     >,
     config: Res<ChunkLoadConfig>,
     mut cache: ResMut<ChunkLoadCache>,
+    mut placeholders: ResMut<ChunkLoadPlaceholders>,
 ) {
-    tilemaps_query
-        .iter_mut()
-        .for_each(|(entity, name, mut storage)| {
+    tilemaps_query.iter_mut().for_each(
+        |(entity, name, mut storage, mut animations, mut packed)| {
             let chunk_size = storage.storage.chunk_size as i32;
+            // Tilemaps without animations get a new `TilemapAnimations`
+            // if any of the loaded tiles is animated.
+            let mut new_animations = TilemapAnimations::default();
             (0..config.chunks_per_frame).into_iter().for_each(|_| {
                 let Some(chunk_index) = cache.pop_chunk(entity, TilemapLayer::COLOR) else {
                     cache
@@ -122,7 +269,13 @@ pub fn load_color_layer(
                     return;
                 };
 
-                let Ok(chunk) = load_object::<TileBuilderBuffer>(
+                // The render chunk was removed when the chunk was unloaded,
+                // but the packed tiles in it are still there.
+                if let Some(packed) = packed.as_mut() {
+                    packed.mark_chunk_changed(chunk_index);
+                }
+
+                let Ok(chunk) = load_object::<TileBuffer<SerializedTile>>(
                     &Path::new(&config.path)
                         .join(&name.0)
                         .join(TILE_CHUNKS_FOLDER),
@@ -136,6 +289,10 @@ pub fn load_color_layer(
                     let mut entities = vec![None; (chunk_size * chunk_size) as usize];
                     let chunk_origin = chunk_index * chunk_size;
                     chunk.tiles.into_iter().for_each(|(in_chunk_index, tile)| {
+                        let tile = match animations.as_mut() {
+                            Some(animations) => tile.into_builder(animations, None),
+                            None => tile.into_builder(&mut new_animations, None),
+                        };
                         let e = c.spawn_empty().id();
                         let in_chunk_index_vec =
                             (in_chunk_index.x + in_chunk_index.y * chunk_size) as usize;
@@ -149,6 +306,7 @@ pub fn load_color_layer(
                                 index: chunk_origin + in_chunk_index,
                                 texture: tile.texture,
                                 tint: tile.tint,
+                                offset: tile.offset,
                             },
                         ));
                         entities[in_chunk_index_vec] = Some(e);
@@ -156,9 +314,17 @@ pub fn load_color_layer(
 
                     storage.set_chunk_entity(chunk_index, entities);
                     c.insert_or_spawn_batch(tiles);
+                    placeholders.remove(&mut c, entity, chunk_index);
                 });
             });
-        });
+
+            if !new_animations.is_empty() {
+                commands.command_scope(|mut c| {
+                    c.entity(entity).insert(new_animations);
+                });
+            }
+        },
+    );
 }
 
 #[cfg(feature = "algorithm")]
@@ -166,7 +332,8 @@ pub fn load_path_layer(
     tilemaps_query: Query<(Entity, &TilemapName), With<ScheduledLoadChunks>>,
     config: Res<ChunkLoadConfig>,
     mut cache: ResMut<ChunkLoadCache>,
-    path_tilemaps: Res<PathTilemaps>,
+    #[cfg(feature = "multi-threaded")] path_tilemaps: Res<PathTilemaps>,
+    #[cfg(not(feature = "multi-threaded"))] mut path_tilemaps: ResMut<PathTilemaps>,
 ) {
     tilemaps_query.iter().for_each(|(entity, name)| {
         (0..config.chunks_per_frame).into_iter().for_each(|_| {
@@ -180,12 +347,12 @@ pub fn load_path_layer(
             };
 
             #[cfg(feature = "multi-threaded")]
-            let Some(path_tilemap) = path_tilemaps.lock(entity) else {
+            let Some(mut path_tilemap) = path_tilemaps.lock(entity) else {
                 error!("PathTilemap not found for entity: {:?}, skipping.", entity);
                 return;
             };
             #[cfg(not(feature = "multi-threaded"))]
-            let Some(path_tilemap) = path_tilemaps.get(entity) else {
+            let Some(path_tilemap) = path_tilemaps.get_mut(entity) else {
                 error!("PathTilemap not found for entity: {:?}, skipping.", entity);
                 return;
             };
@@ -204,12 +371,12 @@ pub fn load_path_layer(
             chunk.tiles.into_iter().for_each(|(in_chunk_index, tile)| {
                 c[(in_chunk_index.y * chunk_size + in_chunk_index.x) as usize] = Some(tile);
             });
-            path_tilemap.storage.get_chunk(chunk_index).replace(&c);
+            path_tilemap.storage.set_chunk(chunk_index, c);
         });
     });
 }
 
-#[cfg(feature = "physics")]
+#[cfg(feature = "physics_core")]
 pub fn load_physics_layer(
     mut commands: Commands,
     mut tilemaps_query: Query<
@@ -258,3 +425,64 @@ pub fn load_physics_layer(
             });
         });
 }
+
+#[cfg(test)]
+mod test {
+    use bevy::{
+        app::{App, Update},
+        ecs::schedule::IntoSystemConfigs,
+        math::Vec2,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_load_placeholders() {
+        let mut app = App::new();
+        app.init_resource::<ChunkLoadCache>()
+            .init_resource::<ChunkLoadPlaceholders>()
+            .insert_resource(ChunkLoadConfig {
+                path: "not_existing".to_string(),
+                chunks_per_frame: 1,
+                placeholder: Some(Color::RED),
+            })
+            .add_systems(
+                Update,
+                (
+                    load_placeholder_spawner.before(load_color_layer),
+                    load_color_layer,
+                    load_placeholder_cleaner,
+                ),
+            );
+
+        let tilemap = app.world.spawn_empty().id();
+        app.world.entity_mut(tilemap).insert((
+            TilemapName("test".to_string()),
+            TilemapStorage::new(4, tilemap),
+            TilemapType::Square,
+            TilePivot::default(),
+            TilemapAxisFlip::NONE,
+            TilemapSlotSize(Vec2::splat(16.)),
+            TilemapTransform::default(),
+            ScheduledLoadChunks,
+        ));
+        app.world
+            .resource_mut::<ChunkLoadCache>()
+            .0
+            .entry(tilemap)
+            .or_default()
+            .insert(TilemapLayer::COLOR, VecDeque::from([IVec2::ZERO]));
+        app.update();
+
+        // The chunk failed to load, so the placeholder stays.
+        let placeholders = app.world.resource::<ChunkLoadPlaceholders>();
+        assert!(placeholders.contains(tilemap, IVec2::ZERO));
+        let placeholder = placeholders.0[&tilemap][&IVec2::ZERO];
+        assert!(app.world.get_entity(placeholder).is_some());
+
+        app.world.despawn(tilemap);
+        app.update();
+        assert!(app.world.resource::<ChunkLoadPlaceholders>().0.is_empty());
+        assert!(app.world.get_entity(placeholder).is_none());
+    }
+}
@@ -1,19 +1,23 @@
 use bevy::{
     app::{App, Plugin, Update},
     ecs::{
+        component::Component,
         entity::Entity,
-        query::With,
-        system::{ParallelCommands, Query, Res},
+        query::{Or, With},
+        schedule::IntoSystemConfigs,
+        system::{Commands, ParallelCommands, Query, Res, ResMut},
     },
+    reflect::Reflect,
 };
 
 use self::{
-    load::{ChunkLoadCache, ChunkLoadConfig, ScheduledLoadChunks},
+    load::{ChunkLoadCache, ChunkLoadConfig, ChunkLoadPlaceholders, ScheduledLoadChunks},
     save::{ChunkSaveCache, ChunkSaveConfig, ScheduledSaveChunks},
 };
 
 pub mod load;
 pub mod save;
+pub mod stream;
 
 pub const TILE_CHUNKS_FOLDER: &str = "tile_chunks";
 pub const PATH_TILE_CHUNKS_FOLDER: &str = "path_tile_chunks";
@@ -26,31 +30,127 @@ impl Plugin for EntiTilesChunkSerializingPlugin {
         app.add_systems(
             Update,
             (
-                save::save_color_layer,
-                #[cfg(feature = "algorithm")]
-                save::save_path_layer,
-                #[cfg(feature = "physics")]
-                save::save_physics_layer,
+                (
+                    save::save_color_layer,
+                    #[cfg(feature = "algorithm")]
+                    save::save_path_layer,
+                    #[cfg(feature = "physics_core")]
+                    save::save_physics_layer,
+                    load::load_color_layer,
+                    #[cfg(feature = "algorithm")]
+                    load::load_path_layer,
+                    #[cfg(feature = "physics_core")]
+                    load::load_physics_layer,
+                )
+                    .before(chunk_progress_updater),
                 save::render_chunk_remover,
-                load::load_color_layer,
-                #[cfg(feature = "algorithm")]
-                load::load_path_layer,
-                #[cfg(feature = "physics")]
-                load::load_physics_layer,
+                load::load_placeholder_spawner.before(load::load_color_layer),
+                load::load_placeholder_cleaner,
+                stream::chunk_streamer
+                    .after(crate::tilemap::chunking::camera::camera_chunk_update)
+                    .before(load::load_placeholder_spawner),
                 chunk_tag_remover,
+                // Count the chunks after this frame's saves and loads are done,
+                // and before the finished tilemaps are untagged.
+                chunk_progress_updater.before(chunk_tag_remover),
             ),
         );
 
         app.register_type::<ChunkSaveConfig>()
-            .register_type::<ChunkLoadConfig>();
+            .register_type::<ChunkLoadConfig>()
+            .register_type::<ChunkSerializingProgress>();
 
         app.init_resource::<ChunkLoadCache>()
+            .init_resource::<ChunkLoadPlaceholders>()
             .init_resource::<ChunkLoadConfig>()
             .init_resource::<ChunkSaveCache>()
             .init_resource::<ChunkSaveConfig>();
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum ChunkSerializingPhase {
+    Saving,
+    Loading,
+}
+
+/// The progress of the scheduled chunk saves or loads on a tilemap.
+///
+/// This is inserted on the tilemap while chunks are being saved or loaded,
+/// and removed once all of them are done. Saving is reported first if the
+/// tilemap is saving and loading at the same time.
+#[derive(Component, Debug, Clone, Copy, Reflect)]
+pub struct ChunkSerializingProgress {
+    pub phase: ChunkSerializingPhase,
+    /// Number of chunks scheduled.
+    pub total: usize,
+    /// Number of chunks that are already processed.
+    pub finished: usize,
+}
+
+impl ChunkSerializingProgress {
+    /// Returns the progress in `0..=1`.
+    #[inline]
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            1.
+        } else {
+            self.finished as f32 / self.total as f32
+        }
+    }
+}
+
+fn chunk_progress_updater(
+    mut commands: Commands,
+    tilemaps_query: Query<
+        (Entity, Option<&ChunkSerializingProgress>),
+        Or<(With<ScheduledSaveChunks>, With<ScheduledLoadChunks>)>,
+    >,
+    mut save_cache: ResMut<ChunkSaveCache>,
+    mut load_cache: ResMut<ChunkLoadCache>,
+) {
+    tilemaps_query.iter().for_each(|(entity, progress)| {
+        let saving = save_cache.remaining(entity);
+        let loading = load_cache.remaining(entity);
+
+        if saving == 0 {
+            save_cache.1.remove(&entity);
+        }
+        if loading == 0 {
+            load_cache.1.remove(&entity);
+        }
+
+        let new_progress = if saving > 0 {
+            let total = save_cache.1.get(&entity).copied().unwrap_or(saving);
+            ChunkSerializingProgress {
+                phase: ChunkSerializingPhase::Saving,
+                total,
+                finished: total.saturating_sub(saving),
+            }
+        } else if loading > 0 {
+            let total = load_cache.1.get(&entity).copied().unwrap_or(loading);
+            ChunkSerializingProgress {
+                phase: ChunkSerializingPhase::Loading,
+                total,
+                finished: total.saturating_sub(loading),
+            }
+        } else {
+            if progress.is_some() {
+                commands.entity(entity).remove::<ChunkSerializingProgress>();
+            }
+            return;
+        };
+
+        if progress.map_or(true, |p| {
+            p.phase != new_progress.phase
+                || p.total != new_progress.total
+                || p.finished != new_progress.finished
+        }) {
+            commands.entity(entity).insert(new_progress);
+        }
+    });
+}
+
 fn chunk_tag_remover(
     commands: ParallelCommands,
     saves_query: Query<Entity, With<ScheduledSaveChunks>>,
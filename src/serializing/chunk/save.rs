@@ -16,10 +16,13 @@ use bevy::{
 use crate::{
     math::{aabb::IAabb2d, extension::ChunkIndex},
     render::chunk::{ChunkUnload, UnloadRenderChunk},
-    serializing::{map::TilemapLayer, save_object},
+    serializing::{
+        map::{SerializedTile, TilemapLayer},
+        save_object,
+    },
     tilemap::{
-        buffers::TileBuilderBuffer,
-        map::{TilemapName, TilemapStorage},
+        buffers::TileBuffer,
+        map::{TilemapAnimations, TilemapName, TilemapStorage},
         tile::Tile,
     },
 };
@@ -31,13 +34,13 @@ use crate::{
     algorithm::pathfinding::PathTilemaps, serializing::chunk::PATH_TILE_CHUNKS_FOLDER,
     tilemap::buffers::PathTileBuffer,
 };
-#[cfg(feature = "physics")]
+#[cfg(feature = "physics_core")]
 use crate::{
     serializing::chunk::PHYSICS_TILE_CHUNKS_FOLDER,
     tilemap::{buffers::PackedPhysicsTileBuffer, physics::PhysicsTilemap},
 };
 
-#[cfg(any(feature = "algorithm", feature = "physics"))]
+#[cfg(any(feature = "algorithm", feature = "physics_core"))]
 use bevy::log::error;
 
 #[derive(Component)]
@@ -50,7 +53,11 @@ pub struct ChunkSaveConfig {
 }
 
 #[derive(Resource, Default)]
-pub struct ChunkSaveCache(pub(crate) EntityHashMap<HashMap<TilemapLayer, VecDeque<(IVec2, bool)>>>);
+pub struct ChunkSaveCache(
+    pub(crate) EntityHashMap<HashMap<TilemapLayer, VecDeque<(IVec2, bool)>>>,
+    /// The number of chunks scheduled since the queue was empty.
+    pub(crate) EntityHashMap<usize>,
+);
 
 impl ChunkSaveCache {
     #[inline]
@@ -69,6 +76,7 @@ impl ChunkSaveCache {
                 .entry(layer)
                 .or_default()
                 .push_front((chunk_index, remove_after_save));
+            *self.1.entry(tilemap).or_default() += 1;
         }
         commands.entity(tilemap).insert(ScheduledSaveChunks);
     }
@@ -83,14 +91,25 @@ impl ChunkSaveCache {
     ) {
         for layer in layers.iter() {
             let queue = self.0.entry(tilemap).or_default().entry(layer).or_default();
+            let before = queue.len();
             queue.reserve(chunk_indices.size_hint().0);
             chunk_indices
                 .clone()
                 .for_each(|chunk_index| queue.push_front(chunk_index));
+            *self.1.entry(tilemap).or_default() += queue.len() - before;
         }
         commands.entity(tilemap).insert(ScheduledSaveChunks);
     }
 
+    /// Returns the number of chunks that are still waiting in the queue.
+    #[inline]
+    pub fn remaining(&self, tilemap: Entity) -> usize {
+        self.0
+            .get(&tilemap)
+            .map(|layers| layers.values().map(|chunks| chunks.len()).sum())
+            .unwrap_or_default()
+    }
+
     #[inline]
     pub fn pop_chunk(&mut self, tilemap: Entity, layer: TilemapLayer) -> Option<(IVec2, bool)> {
         self.0.get_mut(&tilemap).and_then(|layers| {
@@ -107,7 +126,7 @@ pub fn render_chunk_remover(mut tilemaps_query: Query<(&mut TilemapStorage, &Unl
         .par_iter_mut()
         .for_each(|(mut storage, unloaded)| {
             unloaded.0.iter().for_each(|chunk_index| {
-                storage.storage.chunks.remove(chunk_index);
+                storage.storage.remove_chunk(*chunk_index);
             });
         });
 }
@@ -115,7 +134,12 @@ pub fn render_chunk_remover(mut tilemaps_query: Query<(&mut TilemapStorage, &Unl
 pub fn save_color_layer(
     mut commands: Commands,
     mut tilemaps_query: Query<
-        (Entity, &TilemapName, &mut TilemapStorage),
+        (
+            Entity,
+            &TilemapName,
+            &mut TilemapStorage,
+            Option<&TilemapAnimations>,
+        ),
         With<ScheduledSaveChunks>,
     >,
     tiles_query: Query<&Tile>,
@@ -125,7 +149,7 @@ pub fn save_color_layer(
 ) {
     tilemaps_query
         .iter_mut()
-        .for_each(|(entity, name, mut storage)| {
+        .for_each(|(entity, name, mut storage, animations)| {
             let map_path = Path::new(&config.path).join(&name.0);
 
             (0..config.chunks_per_frame).into_iter().for_each(|_| {
@@ -140,35 +164,33 @@ pub fn save_color_layer(
                     return;
                 };
 
-                let Some(chunk) = storage.get_chunk(chunk_index) else {
+                if !storage.storage.contains_chunk(chunk_index) {
                     return;
-                };
+                }
 
-                let tiles = chunk
-                    .iter()
-                    .enumerate()
-                    .filter_map(|(index, t)| {
-                        t.map(|t| {
-                            (
-                                IVec2 {
-                                    x: (index as u32 % storage.storage.chunk_size) as i32,
-                                    y: (index as u32 / storage.storage.chunk_size) as i32,
-                                },
-                                tiles_query
-                                    .get(t)
-                                    .ok()
-                                    .cloned()
-                                    .map(|tile| tile.into())
-                                    .unwrap(),
-                            )
-                        })
+                let tiles = storage
+                    .storage
+                    .iter_chunk(chunk_index)
+                    .map(|(index, t)| {
+                        (
+                            IVec2 {
+                                x: (index as u32 % storage.storage.chunk_size) as i32,
+                                y: (index as u32 / storage.storage.chunk_size) as i32,
+                            },
+                            tiles_query
+                                .get(*t)
+                                .ok()
+                                .cloned()
+                                .map(|tile| SerializedTile::from_builder(&tile.into(), animations))
+                                .unwrap(),
+                        )
                     })
                     .collect();
 
                 save_object(
                     &map_path.join(TILE_CHUNKS_FOLDER),
                     format!("{}.ron", chunk_index.chunk_file_name()).as_str(),
-                    &TileBuilderBuffer {
+                    &TileBuffer {
                         tiles,
                         aabb: IAabb2d {
                             min: IVec2::ZERO,
@@ -227,18 +249,15 @@ pub fn save_path_layer(
             };
 
             let tiles = chunk
-                .iter()
-                .enumerate()
-                .filter_map(|(index, tile)| {
-                    tile.map(|t| {
-                        (
-                            IVec2 {
-                                x: (index as u32 % path_tilemap.storage.chunk_size) as i32,
-                                y: (index as u32 / path_tilemap.storage.chunk_size) as i32,
-                            },
-                            t,
-                        )
-                    })
+                .iter_some()
+                .map(|(index, tile)| {
+                    (
+                        IVec2 {
+                            x: (index as u32 % path_tilemap.storage.chunk_size) as i32,
+                            y: (index as u32 / path_tilemap.storage.chunk_size) as i32,
+                        },
+                        *tile,
+                    )
                 })
                 .collect();
 
@@ -261,7 +280,7 @@ pub fn save_path_layer(
     });
 }
 
-#[cfg(feature = "physics")]
+#[cfg(feature = "physics_core")]
 pub fn save_physics_layer(
     mut commands: Commands,
     mut tilemaps_query: Query<
@@ -293,18 +312,15 @@ pub fn save_physics_layer(
                 };
 
                 let tiles = chunk
-                    .iter()
-                    .enumerate()
-                    .filter_map(|(index, tile)| {
-                        tile.clone().map(|t| {
-                            (
-                                IVec2 {
-                                    x: (index as u32 % physics_tilemap.storage.chunk_size) as i32,
-                                    y: (index as u32 / physics_tilemap.storage.chunk_size) as i32,
-                                },
-                                t,
-                            )
-                        })
+                    .iter_some()
+                    .map(|(index, tile)| {
+                        (
+                            IVec2 {
+                                x: (index as u32 % physics_tilemap.storage.chunk_size) as i32,
+                                y: (index as u32 / physics_tilemap.storage.chunk_size) as i32,
+                            },
+                            tile.clone(),
+                        )
                     })
                     .collect();
 
@@ -0,0 +1,135 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::EntityHashMap,
+        event::EventReader,
+        system::{Commands, Query, ResMut},
+    },
+    math::IVec2,
+};
+
+use crate::{
+    serializing::map::TilemapLayer,
+    tilemap::{chunking::camera::CameraChunkUpdation, map::TilemapStorage},
+};
+
+use super::{load::ChunkLoadCache, save::ChunkSaveCache};
+
+/// Streams the chunks of the tilemap from and to the disk as the camera moves.
+///
+/// The chunks that the camera with `CameraChunkUpdater` enters are loaded, and the ones it
+/// leaves are saved and unloaded, along with their render meshes. So the view distance is
+/// the `update_scale` of the `CameraChunkUpdater`. Only the reserved chunks are streamed,
+/// see `TilemapStorage::reserve`.
+///
+/// Chunks are not loaded if they are already on the tilemap, and the ones that are
+/// not saved yet are skipped. Listen to `CameraChunkUpdation` to generate them instead.
+/// The paths are configured with `ChunkSaveConfig` and `ChunkLoadConfig`.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct ChunkStreaming {
+    pub layers: TilemapLayer,
+}
+
+impl ChunkStreaming {
+    pub fn new(layers: TilemapLayer) -> Self {
+        Self { layers }
+    }
+}
+
+impl Default for ChunkStreaming {
+    fn default() -> Self {
+        Self {
+            layers: TilemapLayer::all(),
+        }
+    }
+}
+
+pub fn chunk_streamer(
+    mut commands: Commands,
+    mut updations: EventReader<CameraChunkUpdation>,
+    tilemaps_query: Query<(&TilemapStorage, &ChunkStreaming)>,
+    mut load_cache: ResMut<ChunkLoadCache>,
+    mut save_cache: ResMut<ChunkSaveCache>,
+) {
+    let mut to_load = EntityHashMap::<Vec<IVec2>>::default();
+    let mut to_unload = EntityHashMap::<Vec<(IVec2, bool)>>::default();
+
+    updations.read().for_each(|updation| match *updation {
+        CameraChunkUpdation::Entered(tilemap, chunk_index) => {
+            if tilemaps_query
+                .get(tilemap)
+                .is_ok_and(|(storage, _)| !storage.storage.contains_chunk(chunk_index))
+            {
+                to_load.entry(tilemap).or_default().push(chunk_index);
+            }
+        }
+        CameraChunkUpdation::Left(tilemap, chunk_index) => {
+            if tilemaps_query
+                .get(tilemap)
+                .is_ok_and(|(storage, _)| storage.storage.contains_chunk(chunk_index))
+            {
+                to_unload
+                    .entry(tilemap)
+                    .or_default()
+                    .push((chunk_index, true));
+            }
+        }
+    });
+
+    to_load.into_iter().for_each(|(tilemap, chunks)| {
+        let (_, streaming) = tilemaps_query.get(tilemap).unwrap();
+        load_cache.schedule_many(&mut commands, tilemap, streaming.layers, chunks.into_iter());
+    });
+
+    to_unload.into_iter().for_each(|(tilemap, chunks)| {
+        let (_, streaming) = tilemaps_query.get(tilemap).unwrap();
+        save_cache.schedule_many(&mut commands, tilemap, streaming.layers, chunks.into_iter());
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::{
+        app::{App, Update},
+        ecs::entity::Entity,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_chunk_streamer() {
+        let mut app = App::new();
+        app.add_event::<CameraChunkUpdation>()
+            .init_resource::<ChunkLoadCache>()
+            .init_resource::<ChunkSaveCache>()
+            .add_systems(Update, chunk_streamer);
+
+        let tilemap = app.world.spawn_empty().id();
+        let mut storage = TilemapStorage::new(4, tilemap);
+        storage.storage.set_elem(IVec2::ZERO, Entity::PLACEHOLDER);
+        app.world
+            .entity_mut(tilemap)
+            .insert((storage, ChunkStreaming::new(TilemapLayer::COLOR)));
+
+        app.world.send_event_batch([
+            CameraChunkUpdation::Entered(tilemap, IVec2::ZERO),
+            CameraChunkUpdation::Entered(tilemap, IVec2::X),
+            CameraChunkUpdation::Left(tilemap, IVec2::ZERO),
+            CameraChunkUpdation::Left(tilemap, IVec2::Y),
+        ]);
+        app.update();
+
+        let mut load_cache = app.world.resource_mut::<ChunkLoadCache>();
+        assert_eq!(load_cache.remaining(tilemap), 1);
+        assert_eq!(
+            load_cache.pop_chunk(tilemap, TilemapLayer::COLOR),
+            Some(IVec2::X)
+        );
+        let mut save_cache = app.world.resource_mut::<ChunkSaveCache>();
+        assert_eq!(
+            save_cache.pop_chunk(tilemap, TilemapLayer::COLOR),
+            Some((IVec2::ZERO, true))
+        );
+        assert_eq!(save_cache.remaining(tilemap), 0);
+    }
+}
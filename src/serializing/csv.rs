@@ -0,0 +1,230 @@
+use bevy::{
+    ecs::system::Query,
+    math::{IVec2, UVec2},
+};
+
+use crate::{
+    math::TileArea,
+    tilemap::{
+        buffers::TileBuilderBuffer,
+        map::TilemapStorage,
+        tile::{Tile, TileBuilder, TileFlip, TileLayer, TileTexture},
+    },
+};
+
+const FLIP_H: u32 = 1 << 31;
+const FLIP_V: u32 = 1 << 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayerGridError {
+    /// The value is not an integer.
+    InvalidValue { row: usize, column: usize },
+    /// The row doesn't have the same amount of values as the first row.
+    RaggedRow { row: usize },
+}
+
+/// A single tile layer as a grid of texture indices, for exchanging with
+/// spreadsheets and scripts.
+///
+/// The indices are in rows from top to bottom, and `-1` means no texture,
+/// which is compatible with the CSV exported by Tiled. Flipped tiles have
+/// the flip flags of Tiled in the highest bits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerGrid {
+    pub(crate) size: UVec2,
+    pub(crate) indices: Vec<i64>,
+}
+
+impl LayerGrid {
+    /// Parse comma separated values. Empty lines are skipped.
+    pub fn from_csv(csv: &str) -> Result<Self, LayerGridError> {
+        Self::from_rows(csv.lines())
+    }
+
+    /// Parse a Rust array literal like `[[0, 1], [-1, 2]]`.
+    pub fn from_array_literal(literal: &str) -> Result<Self, LayerGridError> {
+        let literal = literal.trim();
+        let literal = literal
+            .strip_prefix('[')
+            .and_then(|l| l.strip_suffix(']'))
+            .unwrap_or(literal);
+        Self::from_rows(literal.split(']').map(|row| {
+            row.trim_start_matches(|c: char| c == ',' || c.is_whitespace())
+                .trim_start_matches('[')
+        }))
+    }
+
+    fn from_rows<'a>(rows: impl Iterator<Item = &'a str>) -> Result<Self, LayerGridError> {
+        let mut indices = Vec::new();
+        let mut width = None;
+        let mut height = 0;
+
+        for (row, line) in rows.filter(|l| !l.trim().is_empty()).enumerate() {
+            let len = indices.len();
+            for (column, value) in line
+                .split(',')
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .enumerate()
+            {
+                indices.push(
+                    value
+                        .parse::<i64>()
+                        .map_err(|_| LayerGridError::InvalidValue { row, column })?,
+                );
+            }
+
+            let row_width = indices.len() - len;
+            if *width.get_or_insert(row_width) != row_width {
+                return Err(LayerGridError::RaggedRow { row });
+            }
+            height += 1;
+        }
+
+        Ok(Self {
+            size: UVec2::new(width.unwrap_or_default() as u32, height),
+            indices,
+        })
+    }
+
+    /// Read a layer of the tiles in the area. Animated tiles are treated as empty.
+    pub fn from_tilemap(
+        storage: &TilemapStorage,
+        tiles_query: &Query<&Tile>,
+        area: TileArea,
+        layer: usize,
+    ) -> Self {
+        let indices = (area.origin.y..=area.dest.y)
+            .rev()
+            .flat_map(|y| (area.origin.x..=area.dest.x).map(move |x| IVec2 { x, y }))
+            .map(|index| {
+                storage
+                    .get(index)
+                    .and_then(|entity| tiles_query.get(entity).ok())
+                    .and_then(|tile| match &tile.texture {
+                        TileTexture::Static(layers) => layers.get(layer).copied(),
+                        TileTexture::Animated(_) => None,
+                    })
+                    .map_or(-1, |layer| {
+                        let mut value = layer.texture_index as i64;
+                        if value >= 0 {
+                            if layer.flip.contains(TileFlip::HORIZONTAL) {
+                                value |= FLIP_H as i64;
+                            }
+                            if layer.flip.contains(TileFlip::VERTICAL) {
+                                value |= FLIP_V as i64;
+                            }
+                        }
+                        value
+                    })
+            })
+            .collect();
+
+        Self {
+            size: area.extent,
+            indices,
+        }
+    }
+
+    #[inline]
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// Get the layer at the given position, where `(0, 0)` is the bottom left corner.
+    pub fn get(&self, index: UVec2) -> Option<TileLayer> {
+        if index.x >= self.size.x || index.y >= self.size.y {
+            return None;
+        }
+
+        let value = self.indices[((self.size.y - index.y - 1) * self.size.x + index.x) as usize];
+        if value < 0 {
+            return None;
+        }
+
+        let value = value as u32;
+        let mut flip = TileFlip::NONE;
+        if value & FLIP_H != 0 {
+            flip |= TileFlip::HORIZONTAL;
+        }
+        if value & FLIP_V != 0 {
+            flip |= TileFlip::VERTICAL;
+        }
+        Some(TileLayer {
+            // The diagonal and the hexagonal flags are not supported.
+            texture_index: (value & 0x0FFF_FFFF) as i32,
+            flip,
+        })
+    }
+
+    /// Create a buffer with the grid on the given layer, the bottom left corner is at `origin`.
+    pub fn to_buffer(&self, origin: IVec2, layer: usize) -> TileBuilderBuffer {
+        let mut buffer = TileBuilderBuffer::new();
+        (0..self.size.y)
+            .flat_map(|y| (0..self.size.x).map(move |x| UVec2 { x, y }))
+            .for_each(|index| {
+                if let Some(tile_layer) = self.get(index) {
+                    buffer.set(
+                        origin + index.as_ivec2(),
+                        TileBuilder::new().with_layer(layer, tile_layer),
+                    );
+                }
+            });
+        buffer
+    }
+
+    pub fn to_csv(&self) -> String {
+        self.rows()
+            .map(|row| row.join(","))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn to_array_literal(&self) -> String {
+        let rows = self
+            .rows()
+            .map(|row| format!("    [{}],\n", row.join(", ")))
+            .collect::<String>();
+        format!("[\n{}]", rows)
+    }
+
+    fn rows(&self) -> impl Iterator<Item = Vec<String>> + '_ {
+        self.indices
+            .chunks(self.size.x.max(1) as usize)
+            .map(|row| row.iter().map(|v| v.to_string()).collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_layer_grid() {
+        let csv = "0,1,-1\n2,-1,2147483651\n";
+        let grid = LayerGrid::from_csv(csv).unwrap();
+        assert_eq!(grid.size(), UVec2::new(3, 2));
+        assert_eq!(grid.get(UVec2::new(1, 1)).unwrap().texture_index, 1);
+        assert!(grid.get(UVec2::new(1, 0)).is_none());
+
+        let flipped = grid.get(UVec2::new(2, 0)).unwrap();
+        assert_eq!(flipped.texture_index, 3);
+        assert_eq!(flipped.flip.bits(), TileFlip::HORIZONTAL.bits());
+
+        assert_eq!(grid.to_csv(), csv.trim());
+        assert_eq!(
+            LayerGrid::from_array_literal(&grid.to_array_literal()).unwrap(),
+            grid
+        );
+        assert_eq!(grid.to_buffer(IVec2::ZERO, 0).tiles.len(), 4);
+
+        assert_eq!(
+            LayerGrid::from_csv("0,1\n2"),
+            Err(LayerGridError::RaggedRow { row: 1 })
+        );
+        assert_eq!(
+            LayerGrid::from_csv("0,a"),
+            Err(LayerGridError::InvalidValue { row: 0, column: 1 })
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use std::{marker::PhantomData, path::Path};
+
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        system::Resource,
+        world::{Mut, World},
+    },
+    log::error,
+    utils::HashMap,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{serializing::load_object, tilemap::map::TilemapName};
+
+use super::{
+    load::TilemapLoader,
+    save::{TilemapSaver, TilemapSaverMode},
+    SerializedTilemap, TilemapLayer, TILEMAP_META,
+};
+
+/// Saves extra data of a tilemap into its own section of the map save, and receives it back on load.
+///
+/// This allows games and mods to store their own per-tile data inside the same save.
+/// The sections are stored in the `tilemap.ron` of the tilemap.
+/// Register it using `TilemapSerializerHooks::register`.
+pub trait TilemapSerializerHook: Send + Sync + 'static {
+    /// The name of the section. It must be unique.
+    fn name(&self) -> &str;
+
+    /// The section will only be saved and loaded when the saver or loader
+    /// includes any of these layers. Use `TilemapLayer::from_bits_retain` for custom layers.
+    fn layers(&self) -> TilemapLayer {
+        TilemapLayer::all()
+    }
+
+    /// Serialize the data of the tilemap into a string. Return `None` if there's nothing to save.
+    ///
+    /// If this fails, the error is logged and the section of the last save is kept.
+    fn save(&self, world: &World, tilemap: Entity) -> Result<Option<String>, ron::Error>;
+
+    /// Receive the data saved by `save`.
+    ///
+    /// This is called before the tilemap itself is loaded, so the tilemap entity
+    /// doesn't have the tiles and other tilemap components yet.
+    fn load(&self, world: &mut World, tilemap: Entity, data: String);
+}
+
+/// A hook that saves a serializable value using ron.
+pub struct TypedSerializerHook<T, S, L>
+where
+    T: Serialize + DeserializeOwned,
+    S: Fn(&World, Entity) -> Option<T> + Send + Sync + 'static,
+    L: Fn(&mut World, Entity, T) + Send + Sync + 'static,
+{
+    pub name: String,
+    pub layers: TilemapLayer,
+    pub save: S,
+    pub load: L,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T, S, L> TypedSerializerHook<T, S, L>
+where
+    T: Serialize + DeserializeOwned,
+    S: Fn(&World, Entity) -> Option<T> + Send + Sync + 'static,
+    L: Fn(&mut World, Entity, T) + Send + Sync + 'static,
+{
+    pub fn new(name: impl Into<String>, layers: TilemapLayer, save: S, load: L) -> Self {
+        Self {
+            name: name.into(),
+            layers,
+            save,
+            load,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, S, L> TilemapSerializerHook for TypedSerializerHook<T, S, L>
+where
+    T: Serialize + DeserializeOwned + 'static,
+    S: Fn(&World, Entity) -> Option<T> + Send + Sync + 'static,
+    L: Fn(&mut World, Entity, T) + Send + Sync + 'static,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn layers(&self) -> TilemapLayer {
+        self.layers
+    }
+
+    fn save(&self, world: &World, tilemap: Entity) -> Result<Option<String>, ron::Error> {
+        (self.save)(world, tilemap)
+            .map(|data| ron::to_string(&data))
+            .transpose()
+    }
+
+    fn load(&self, world: &mut World, tilemap: Entity, data: String) {
+        match ron::from_str(&data) {
+            Ok(data) => (self.load)(world, tilemap, data),
+            Err(err) => error!(
+                "Failed to load section {} of tilemap {:?}: {}",
+                self.name, tilemap, err
+            ),
+        }
+    }
+}
+
+/// The registered serializer hooks. They are only used in `TilemapSaverMode::Tilemap`.
+#[derive(Resource, Default)]
+pub struct TilemapSerializerHooks(pub(crate) Vec<Box<dyn TilemapSerializerHook>>);
+
+impl TilemapSerializerHooks {
+    pub fn register(&mut self, hook: impl TilemapSerializerHook) {
+        assert!(
+            self.0.iter().all(|h| h.name() != hook.name()),
+            "Serializer hook {} is already registered!",
+            hook.name()
+        );
+        self.0.push(Box::new(hook));
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// The sections collected by `save_hooks`, waiting to be written by the saver.
+#[derive(Component, Default)]
+pub struct TilemapSavedSections(pub(crate) HashMap<String, String>);
+
+/// The sections of the last save of the tilemap.
+fn saved_sections(map_path: &Path) -> HashMap<String, String> {
+    load_object::<SerializedTilemap>(map_path, TILEMAP_META)
+        .map(|tilemap| tilemap.sections)
+        .unwrap_or_default()
+}
+
+pub fn save_hooks(world: &mut World) {
+    if world.resource::<TilemapSerializerHooks>().is_empty() {
+        return;
+    }
+
+    let savers = world
+        .query::<(Entity, &TilemapName, &TilemapSaver)>()
+        .iter(world)
+        .filter(|(_, _, saver)| saver.mode == TilemapSaverMode::Tilemap)
+        .map(|(entity, name, saver)| (entity, Path::new(&saver.path).join(&name.0), saver.layers))
+        .collect::<Vec<_>>();
+
+    world.resource_scope(|world, hooks: Mut<TilemapSerializerHooks>| {
+        for (entity, map_path, layers) in savers {
+            // Keep the sections of the layers that are not saved this time.
+            let mut sections = saved_sections(&map_path);
+            hooks
+                .0
+                .iter()
+                .filter(|hook| layers.intersects(hook.layers()))
+                .for_each(|hook| match hook.save(world, entity) {
+                    Ok(Some(data)) => {
+                        sections.insert(hook.name().to_string(), data);
+                    }
+                    Ok(None) => {
+                        sections.remove(hook.name());
+                    }
+                    Err(err) => error!(
+                        "Failed to save section {} of tilemap {:?}: {}",
+                        hook.name(),
+                        entity,
+                        err
+                    ),
+                });
+            world
+                .entity_mut(entity)
+                .insert(TilemapSavedSections(sections));
+        }
+    });
+}
+
+pub fn load_hooks(world: &mut World) {
+    if world.resource::<TilemapSerializerHooks>().is_empty() {
+        return;
+    }
+
+    let loaders = world
+        .query::<(Entity, &TilemapLoader)>()
+        .iter(world)
+        .map(|(entity, loader)| {
+            (
+                entity,
+                Path::new(&loader.path).join(&loader.map_name),
+                loader.layers,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    world.resource_scope(|world, hooks: Mut<TilemapSerializerHooks>| {
+        for (entity, map_path, layers) in loaders {
+            let mut sections = saved_sections(&map_path);
+            hooks
+                .0
+                .iter()
+                .filter(|hook| layers.intersects(hook.layers()))
+                .for_each(|hook| {
+                    if let Some(data) = sections.remove(hook.name()) {
+                        hook.load(world, entity, data);
+                    }
+                });
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Resource, Default)]
+    struct Heights(Vec<u8>);
+
+    #[test]
+    fn test_typed_hook() {
+        let hook = TypedSerializerHook::new(
+            "heights",
+            TilemapLayer::COLOR,
+            |world: &World, _| world.get_resource::<Heights>().map(|h| h.0.clone()),
+            |world: &mut World, _, data: Vec<u8>| world.insert_resource(Heights(data)),
+        );
+        let tilemap = Entity::from_raw(0);
+
+        let mut world = World::new();
+        assert_eq!(hook.save(&world, tilemap), Ok(None));
+        world.insert_resource(Heights(vec![1, 2, 3]));
+        let data = hook.save(&world, tilemap).unwrap().unwrap();
+
+        let mut world = World::new();
+        hook.load(&mut world, tilemap, data);
+        assert_eq!(world.resource::<Heights>().0, vec![1, 2, 3]);
+    }
+}
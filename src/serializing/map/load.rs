@@ -1,39 +1,52 @@
-use std::path::Path;
+use std::{fmt::Debug, path::Path};
 
 use bevy::{
-    asset::AssetServer,
+    asset::{AssetServer, Assets, Handle, LoadState},
     ecs::{
         bundle::Bundle,
         component::Component,
         entity::Entity,
-        system::{Commands, Query, Res},
+        event::{Event, EventWriter},
+        query::Changed,
+        system::{Commands, Query, Res, ResMut},
     },
     hierarchy::DespawnRecursiveExt,
+    log::warn,
+    reflect::Reflect,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+        texture::Image,
+    },
 };
+use ron::error::SpannedError;
+use serde::de::DeserializeOwned;
 
 use crate::{
     serializing::load_object,
     tilemap::{
-        chunking::storage::{ChunkedStorage, TileBuilderChunkedStorage},
-        map::{TilemapStorage, TilemapTexture},
+        budget::{TileSpawnBudget, TileSpawnQueue},
+        chunking::storage::ChunkedStorage,
+        map::{TilemapAnimations, TilemapStorage, TilemapTexture, WaitForTextureUsageChange},
         tile::Tile,
     },
 };
 
-use super::{SerializedTilemap, TilemapLayer, TILEMAP_META, TILES};
+use super::{
+    SaveManifest, SerializedChunkedDelta, SerializedTile, SerializedTilemap, TilemapLayer,
+    MANIFEST, TILEMAP_META, TILES, TILES_DELTA,
+};
 
 #[cfg(feature = "algorithm")]
 use crate::{
-    serializing::map::PATH_TILES,
+    serializing::map::{PATH_TILES, PATH_TILES_DELTA},
     tilemap::{algorithm::path::PathTilemap, chunking::storage::PathTileChunkedStorage},
     algorithm::pathfinding::PathTilemaps,
 };
-#[cfg(feature = "algorithm")]
-use bevy::ecs::system::ResMut;
 
-#[cfg(feature = "physics")]
+#[cfg(feature = "physics_core")]
 use crate::{
-    serializing::map::PHYSICS_TILES,
+    serializing::map::{PHYSICS_TILES, PHYSICS_TILES_DELTA},
     tilemap::{chunking::storage::PackedPhysicsTileChunkedStorage, physics::PhysicsTilemap},
 };
 
@@ -54,15 +67,59 @@ pub struct TilemapLoader {
     pub layers: TilemapLayer,
 }
 
+/// Inserted on the loaded tilemaps until their texture is loaded.
+#[derive(Component, Debug, Clone)]
+pub struct LoadingTilemapTexture {
+    pub path: String,
+}
+
+/// The texture of a loaded tilemap is missing, so a magenta placeholder is used instead.
+///
+/// Assign a new `TilemapTexture` to the tilemap to fix it, and this will be removed.
+#[derive(Component, Debug, Clone, Reflect)]
+pub struct TilemapTexturePlaceholder {
+    /// The path of the missing texture.
+    pub path: String,
+    pub(crate) placeholder: Handle<Image>,
+}
+
+/// Fired when the texture of a loaded tilemap failed to load.
+#[derive(Event, Debug, Clone, Reflect)]
+pub struct TilemapTextureMissing {
+    pub tilemap: Entity,
+    pub path: String,
+}
+
 pub fn load(
     mut commands: Commands,
     tilemaps_query: Query<(Entity, &TilemapLoader)>,
     asset_server: Res<AssetServer>,
+    spawn_budget: Res<TileSpawnBudget>,
+    mut spawn_queue: ResMut<TileSpawnQueue>,
     #[cfg(feature = "algorithm")] mut path_tilemaps: ResMut<PathTilemaps>,
 ) {
     for (entity, loader) in tilemaps_query.iter() {
         let map_path = Path::new(&loader.path).join(&loader.map_name);
 
+        match load_object::<SaveManifest>(&map_path, MANIFEST) {
+            Ok(manifest) => {
+                let mismatches = manifest.mismatches(&map_path);
+                if !mismatches.is_empty() {
+                    warn!(
+                        "The last save of {:?} was interrupted, these files may be outdated: {:?}",
+                        map_path, mismatches
+                    );
+                }
+            }
+            Err(_) if map_path.join(TILEMAP_META).exists() => {
+                warn!(
+                    "The last save of {:?} has no manifest, it may be interrupted",
+                    map_path
+                );
+            }
+            Err(_) => {}
+        }
+
         let Ok(ser_tilemap) = load_object::<SerializedTilemap>(&map_path, TILEMAP_META) else {
             complete(&mut commands, entity, (), false);
             continue;
@@ -80,16 +137,21 @@ pub fn load(
 
         // texture
         let ser_tiles = if loader.layers.contains(TilemapLayer::COLOR) {
-            Some(load_object::<TileBuilderChunkedStorage>(&map_path, TILES))
+            Some(load_with_delta::<SerializedTile>(
+                &map_path,
+                TILES,
+                TILES_DELTA,
+            ))
         } else {
             None
         };
 
         let mut storage = TilemapStorage {
             tilemap: entity,
-            storage: ChunkedStorage::new(ser_tilemap.chunk_size),
+            storage: ser_tilemap.new_storage(),
             ..Default::default()
         };
+        let mut animations = TilemapAnimations::default();
 
         // color
         if let Some(ser_tiles) = ser_tiles {
@@ -98,64 +160,98 @@ pub fn load(
                 continue;
             };
 
-            let mut bundles = Vec::new();
-            ser_tiles
-                .chunked_iter_some()
-                .for_each(|(chunk_index, in_chunk_index, tile)| {
-                    let tile_entity = commands.spawn_empty().id();
-                    storage
-                        .storage
-                        .set_elem_precise(chunk_index, in_chunk_index, tile_entity);
-                    bundles.push((
-                        tile_entity,
-                        Tile {
-                            tilemap_id: entity,
-                            chunk_index,
-                            in_chunk_index,
-                            index: storage
-                                .storage
-                                .inverse_transform_index(chunk_index, in_chunk_index),
-                            texture: tile.texture.clone(),
-                            tint: tile.tint,
-                        },
-                    ));
-                });
-            commands.insert_or_spawn_batch(bundles);
+            let legacy = ser_tilemap.animations.as_ref();
+            let mut into_builder =
+                |tile: &SerializedTile| tile.clone().into_builder(&mut animations, legacy);
+
+            if spawn_budget.is_limited() {
+                ser_tiles
+                    .chunked_iter_some()
+                    .for_each(|(chunk_index, in_chunk_index, tile)| {
+                        spawn_queue.set(
+                            entity,
+                            ser_tiles.inverse_transform_index(chunk_index, in_chunk_index),
+                            into_builder(tile),
+                        );
+                    });
+            } else {
+                let mut bundles = Vec::new();
+                ser_tiles
+                    .chunked_iter_some()
+                    .for_each(|(chunk_index, in_chunk_index, tile)| {
+                        let tile = into_builder(tile);
+                        let tile_entity = commands.spawn_empty().id();
+                        storage
+                            .storage
+                            .set_elem_precise(chunk_index, in_chunk_index, tile_entity);
+                        bundles.push((
+                            tile_entity,
+                            Tile {
+                                tilemap_id: entity,
+                                chunk_index,
+                                in_chunk_index,
+                                index: storage
+                                    .storage
+                                    .inverse_transform_index(chunk_index, in_chunk_index),
+                                texture: tile.texture,
+                                tint: tile.tint,
+                                offset: tile.offset,
+                            },
+                        ));
+                    });
+                commands.insert_or_spawn_batch(bundles);
+            }
         }
 
         if let Some(tex) = texture {
             let mut bundle = ser_tilemap.into_tilemap(entity, tex);
             bundle.storage = storage;
-            complete(&mut commands, entity, bundle, true);
+            bundle.animations = animations;
+            let loading = LoadingTilemapTexture {
+                path: ser_tilemap.texture.as_ref().unwrap().path.clone(),
+            };
+            complete(&mut commands, entity, (bundle, loading), true);
         } else {
             let mut bundle = ser_tilemap.into_pure_color_tilemap(entity);
             bundle.storage = storage;
             complete(&mut commands, entity, bundle, true);
         }
 
+        if let Some(id) = &ser_tilemap.id {
+            commands.entity(entity).insert(id.clone());
+        }
+
         // algorithm
         #[cfg(feature = "algorithm")]
         if loader.layers.contains(TilemapLayer::PATH) {
-            let Ok(path_storage) = load_object::<PathTileChunkedStorage>(&map_path, PATH_TILES)
+            let Ok::<PathTileChunkedStorage, _>(path_storage) =
+                load_with_delta(&map_path, PATH_TILES, PATH_TILES_DELTA)
             else {
                 complete(&mut commands, entity, (), false);
                 continue;
             };
 
-            path_tilemaps.insert(entity, PathTilemap { storage: path_storage });
+            path_tilemaps.insert(
+                entity,
+                PathTilemap {
+                    storage: path_storage,
+                    revision: 0,
+                    walls: Default::default(),
+                },
+            );
         }
 
         // physics
-        #[cfg(feature = "physics")]
+        #[cfg(feature = "physics_core")]
         if loader.layers.contains(TilemapLayer::PHYSICS) {
-            let Ok(physics_tiles) =
-                load_object::<PackedPhysicsTileChunkedStorage>(&map_path, PHYSICS_TILES)
+            let Ok::<PackedPhysicsTileChunkedStorage, _>(physics_tiles) =
+                load_with_delta(&map_path, PHYSICS_TILES, PHYSICS_TILES_DELTA)
             else {
                 complete(&mut commands, entity, (), false);
                 continue;
             };
 
-            let mut physics_storage = ChunkedStorage::new(ser_tilemap.chunk_size);
+            let mut physics_storage = ser_tilemap.new_storage();
 
             physics_tiles
                 .chunked_iter_some()
@@ -170,12 +266,103 @@ pub fn load(
             commands.entity(entity).insert(PhysicsTilemap {
                 storage: physics_storage,
                 spawn_queue: Vec::new(),
+                shaped_queue: Vec::new(),
                 data: physics_tiles,
             });
         }
     }
 }
 
+/// Replace the textures that failed to load with magenta placeholders.
+pub fn missing_texture_detector(
+    mut commands: Commands,
+    mut tilemaps_query: Query<(Entity, &mut TilemapTexture, &LoadingTilemapTexture)>,
+    asset_server: Res<AssetServer>,
+    mut image_assets: ResMut<Assets<Image>>,
+    mut missing: EventWriter<TilemapTextureMissing>,
+) {
+    tilemaps_query
+        .iter_mut()
+        .for_each(|(entity, mut texture, loading)| {
+            match asset_server.get_load_state(texture.handle()) {
+                Some(LoadState::Loaded) => {
+                    commands.entity(entity).remove::<LoadingTilemapTexture>();
+                }
+                Some(LoadState::Failed) => {
+                    warn!(
+                        "Failed to load the texture {} of tilemap {:?}, using a placeholder.",
+                        loading.path, entity
+                    );
+
+                    let size = texture.desc().size;
+                    let mut image = Image::new_fill(
+                        Extent3d {
+                            width: size.x.max(1),
+                            height: size.y.max(1),
+                            depth_or_array_layers: 1,
+                        },
+                        TextureDimension::D2,
+                        &[255, 0, 255, 255],
+                        TextureFormat::Rgba8UnormSrgb,
+                        RenderAssetUsages::default(),
+                    );
+                    image.texture_descriptor.usage |= TextureUsages::COPY_SRC;
+                    let placeholder = image_assets.add(image);
+                    texture.texture = placeholder.clone();
+
+                    commands
+                        .entity(entity)
+                        .remove::<LoadingTilemapTexture>()
+                        .insert(TilemapTexturePlaceholder {
+                            path: loading.path.clone(),
+                            placeholder,
+                        });
+                    missing.send(TilemapTextureMissing {
+                        tilemap: entity,
+                        path: loading.path.clone(),
+                    });
+                }
+                _ => {}
+            }
+        });
+}
+
+/// Leave the placeholder mode once a new texture is assigned.
+pub fn texture_placeholder_remover(
+    mut commands: Commands,
+    tilemaps_query: Query<
+        (Entity, &TilemapTexture, &TilemapTexturePlaceholder),
+        Changed<TilemapTexture>,
+    >,
+) {
+    tilemaps_query
+        .iter()
+        .for_each(|(entity, texture, placeholder)| {
+            if texture.handle() != &placeholder.placeholder {
+                commands
+                    .entity(entity)
+                    .remove::<TilemapTexturePlaceholder>()
+                    .insert(WaitForTextureUsageChange);
+            }
+        });
+}
+
+/// Load the base save and apply the delta over it if there is one.
+fn load_with_delta<T>(
+    map_path: &Path,
+    file_name: &str,
+    delta_file_name: &str,
+) -> Result<ChunkedStorage<T>, SpannedError>
+where
+    T: Debug + Clone + Reflect + DeserializeOwned,
+{
+    let mut storage = load_object::<ChunkedStorage<T>>(map_path, file_name)?;
+    if map_path.join(delta_file_name).exists() {
+        load_object::<SerializedChunkedDelta<T>>(map_path, delta_file_name)?.apply(&mut storage);
+    }
+    Ok(storage)
+}
+
 fn complete(commands: &mut Commands, entity: Entity, bundle: impl Bundle, is_success: bool) {
     if is_success {
         commands.entity(entity).remove::<TilemapLoader>();
@@ -1,20 +1,41 @@
+use std::{fmt::Debug, path::Path};
+
 use bevy::{
-    app::{App, Plugin, Update},
-    ecs::entity::Entity,
-    math::UVec2,
-    render::render_resource::FilterMode,
+    app::{App, Plugin, PostUpdate, Update},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::{Changed, Or, With},
+        schedule::IntoSystemConfigs,
+        system::Query,
+    },
+    log::warn,
+    math::{IVec2, UVec2},
+    reflect::Reflect,
+    render::{color::Color, render_resource::FilterMode},
+    utils::{HashMap, HashSet},
 };
 use serde::{Deserialize, Serialize};
 
-use crate::tilemap::{
-    bundles::{StandardPureColorTilemapBundle, StandardTilemapBundle},
-    chunking::storage::ChunkedStorage,
-    map::{
-        TilePivot, TileRenderSize, TilemapAnimations, TilemapLayerOpacities, TilemapName,
-        TilemapRotation, TilemapSlotSize, TilemapStorage, TilemapTexture, TilemapTextureDescriptor,
-        TilemapTransform, TilemapType,
+use crate::{
+    serializing::{serialize_sorted, TEMP_SUFFIX},
+    tilemap::{
+        buffers::Tiles,
+        bundles::{StandardPureColorTilemapBundle, StandardTilemapBundle},
+        chunking::storage::ChunkedStorage,
+        despawn::{self, DespawnMe},
+        id::TilemapId,
+        map::{
+            AnimationRemap, TilePivot, TileRenderSize, TilemapAnimations, TilemapLayerOpacities,
+            TilemapName, TilemapRotation, TilemapSlotSize, TilemapStorage, TilemapTexture,
+            TilemapTextureDescriptor, TilemapTransform, TilemapType,
+        },
+        tile::{
+            self, RawTileAnimation, Tile, TileAnimation, TileBuilder, TileLayer, TileOffset,
+            TileTexture,
+        },
+        tracker::TileChanges,
     },
-    tile::TileBuilder,
 };
 
 use self::save::TilemapSaver;
@@ -23,15 +44,341 @@ pub const TILEMAP_META: &str = "tilemap.ron";
 pub const TILES: &str = "tiles.ron";
 pub const PATH_TILES: &str = "path_tiles.ron";
 pub const PHYSICS_TILES: &str = "physics_tiles.ron";
+pub const TILES_DELTA: &str = "tiles.delta.ron";
+pub const PATH_TILES_DELTA: &str = "path_tiles.delta.ron";
+pub const PHYSICS_TILES_DELTA: &str = "physics_tiles.delta.ron";
+pub const MANIFEST: &str = "manifest.ron";
 
+pub mod hook;
 pub mod load;
+pub mod remap;
 pub mod save;
 
 pub struct EntiTilesTilemapSerializingPlugin;
 
 impl Plugin for EntiTilesTilemapSerializingPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (save::save, load::load));
+        app.add_systems(
+            Update,
+            (
+                save::save,
+                load::load,
+                hook::save_hooks.before(save::save),
+                hook::load_hooks.before(load::load),
+                load::missing_texture_detector,
+                load::texture_placeholder_remover,
+            ),
+        )
+        .add_systems(
+            PostUpdate,
+            (dirty_chunks_tracker, tile_mirror_syncer).before(despawn::despawn_tiles),
+        );
+
+        app.init_resource::<hook::TilemapSerializerHooks>();
+
+        app.register_type::<load::TilemapTexturePlaceholder>()
+            .register_type::<load::TilemapTextureMissing>();
+
+        app.add_event::<load::TilemapTextureMissing>();
+    }
+}
+
+/// Records the chunks that changed since the last save.
+///
+/// Insert this component on a tilemap to enable `TilemapSaverRange::Dirty`.
+/// Changes of the color layer are tracked automatically, other layers
+/// need to be marked manually using `mark`.
+#[derive(Component, Default, Debug, Clone)]
+pub struct TilemapDirtyChunks {
+    chunks: HashMap<TilemapLayer, HashSet<IVec2>>,
+}
+
+impl TilemapDirtyChunks {
+    pub fn mark(&mut self, layers: TilemapLayer, chunk_index: IVec2) {
+        layers.iter().for_each(|layer| {
+            self.chunks.entry(layer).or_default().insert(chunk_index);
+        });
+    }
+
+    #[inline]
+    pub fn is_dirty(&self, layer: TilemapLayer) -> bool {
+        self.chunks.get(&layer).is_some_and(|c| !c.is_empty())
+    }
+
+    #[inline]
+    pub fn iter(&self, layer: TilemapLayer) -> impl Iterator<Item = IVec2> + '_ {
+        self.chunks.get(&layer).into_iter().flatten().copied()
+    }
+
+    pub fn clear(&mut self, layers: TilemapLayer) {
+        layers.iter().for_each(|layer| {
+            self.chunks.remove(&layer);
+        });
+    }
+}
+
+pub fn dirty_chunks_tracker(
+    mut tilemaps_query: Query<&mut TilemapDirtyChunks>,
+    tiles_query: Query<&Tile, Or<(Changed<Tile>, With<DespawnMe>)>>,
+) {
+    tiles_query.iter().for_each(|tile| {
+        if let Ok(mut dirty) = tilemaps_query.get_mut(tile.tilemap_id) {
+            dirty.mark(TilemapLayer::COLOR, tile.chunk_index);
+        }
+    });
+}
+
+/// A compact copy of the tiles of the tilemap, kept in sync with the tile entities.
+///
+/// Insert this component on a tilemap to let `TilemapSaver` serialize the color layer
+/// from it, instead of querying every tile entity. This makes saving large tilemaps
+/// much faster, at the cost of keeping a second copy of the tiles.
+///
+/// The copy is built in `PostUpdate` after inserting this component, saves before that
+/// still query the tile entities.
+#[derive(Component, Default, Debug, Clone)]
+pub struct TilemapTileMirror {
+    pub(crate) tiles: Option<ChunkedStorage<TileBuilder>>,
+}
+
+impl TilemapTileMirror {
+    /// The copied tiles, or `None` if it's not built yet.
+    #[inline]
+    pub fn tiles(&self) -> Option<&ChunkedStorage<TileBuilder>> {
+        self.tiles.as_ref()
+    }
+
+    pub(crate) fn set(&mut self, tile: &Tile) {
+        if let Some(tiles) = &mut self.tiles {
+            tiles.set_elem_precise(tile.chunk_index, tile.in_chunk_index, tile.clone().into());
+        }
+    }
+
+    pub(crate) fn remove(&mut self, index: IVec2) {
+        if let Some(tiles) = &mut self.tiles {
+            tiles.remove_elem(index);
+        }
+    }
+}
+
+pub fn tile_mirror_syncer(
+    mut tilemaps_query: Query<(Entity, &mut TilemapTileMirror, &TilemapStorage)>,
+    tiles: TileChanges,
+) {
+    // Build the new mirrors.
+    let mut built = HashSet::new();
+    tilemaps_query
+        .iter_mut()
+        .filter(|(_, mirror, _)| mirror.tiles.is_none())
+        .for_each(|(entity, mut mirror, storage)| {
+            mirror.tiles = Some(ChunkedStorage::new_like(&storage.storage));
+            storage.storage.iter_some().for_each(|tile| {
+                if let Some(tile) = tiles.get(*tile) {
+                    mirror.set(tile);
+                }
+            });
+            built.insert(entity);
+        });
+
+    // Removals first, as a new tile may be set at the same index in the same frame.
+    tiles.iter_removed().for_each(|tile| {
+        if built.contains(&tile.tilemap_id) {
+            return;
+        }
+        if let Ok((_, mut mirror, _)) = tilemaps_query.get_mut(tile.tilemap_id) {
+            mirror.remove(tile.index);
+        }
+    });
+
+    tiles.iter_changed().for_each(|tile| {
+        if built.contains(&tile.tilemap_id) {
+            return;
+        }
+        if let Ok((_, mut mirror, _)) = tilemaps_query.get_mut(tile.tilemap_id) {
+            mirror.set(tile);
+        }
+    });
+}
+
+/// Chunks that should be applied over a base save.
+/// A chunk with value `None` was removed after the base save.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound(serialize = "T: Serialize", deserialize = "T: Deserialize<'de>"))]
+pub struct SerializedChunkedDelta<T: Debug + Clone + Reflect> {
+    #[serde(serialize_with = "serialize_sorted")]
+    pub chunks: HashMap<IVec2, Option<Vec<Option<T>>>>,
+}
+
+impl<T: Debug + Clone + Reflect> Default for SerializedChunkedDelta<T> {
+    fn default() -> Self {
+        Self {
+            chunks: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Debug + Clone + Reflect> SerializedChunkedDelta<T> {
+    /// Collect the given chunks from the storage.
+    pub fn from_storage(storage: &ChunkedStorage<T>, chunks: impl Iterator<Item = IVec2>) -> Self {
+        Self::from_storage_mapped(storage, chunks, |elem| Some(elem.clone()))
+    }
+
+    pub fn from_storage_mapped<U: Debug + Clone + Reflect>(
+        storage: &ChunkedStorage<U>,
+        chunks: impl Iterator<Item = IVec2>,
+        mut f: impl FnMut(&U) -> Option<T>,
+    ) -> Self {
+        let chunk_area = (storage.chunk_size * storage.chunk_size) as usize;
+        Self {
+            chunks: chunks
+                .map(|chunk_index| {
+                    if !storage.contains_chunk(chunk_index) {
+                        return (chunk_index, None);
+                    }
+
+                    let mut chunk = vec![None; chunk_area];
+                    storage
+                        .iter_chunk(chunk_index)
+                        .for_each(|(i, elem)| chunk[i] = f(elem));
+                    (chunk_index, Some(chunk))
+                })
+                .collect(),
+        }
+    }
+
+    /// Merge a newer delta into this one.
+    pub fn merge(&mut self, newer: Self) {
+        self.chunks.extend(newer.chunks);
+    }
+
+    pub fn apply(self, storage: &mut ChunkedStorage<T>) {
+        self.chunks
+            .into_iter()
+            .for_each(|(chunk_index, chunk)| match chunk {
+                Some(chunk) => storage.set_chunk(chunk_index, chunk),
+                None => {
+                    storage.remove_chunk(chunk_index);
+                }
+            });
+    }
+}
+
+/// A saved tile. Unlike `TileBuilder`, animations are saved with their frames,
+/// so they don't depend on where they are in the `TilemapAnimations` buffer.
+#[derive(Serialize, Deserialize, Debug, Clone, Reflect)]
+pub struct SerializedTile {
+    pub texture: SerializedTileTexture,
+    pub tint: Color,
+    #[serde(default)]
+    pub offset: TileOffset,
+}
+
+impl Tiles for SerializedTile {}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Reflect)]
+pub enum SerializedTileTexture {
+    Static(Vec<TileLayer>),
+    /// Only in old saves, pointing into the animation buffer saved with the tilemap.
+    Animated(TileAnimation),
+    Sequence(SerializedTileAnimation),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Reflect)]
+pub struct SerializedTileAnimation {
+    pub sequence: Vec<u32>,
+    pub fps: u32,
+    #[serde(default)]
+    pub group: u32,
+}
+
+impl SerializedTile {
+    /// `animations` should be the ones the tile is using.
+    pub fn from_builder(tile: &TileBuilder, animations: Option<&TilemapAnimations>) -> Self {
+        let texture = match &tile.texture {
+            TileTexture::Static(layers) => SerializedTileTexture::Static(layers.clone()),
+            TileTexture::Animated(anim) => {
+                // Follow the moves that are not applied to the tiles yet.
+                let remapped = match animations.and_then(|a| a.3.get(&anim.start)) {
+                    Some(AnimationRemap::Moved(moved)) => Ok(*moved),
+                    Some(AnimationRemap::Removed(frame)) => Err(*frame),
+                    None => Ok(*anim),
+                };
+                match remapped.map(|a| animations.and_then(|anims| anims.get(a))) {
+                    Ok(Some(raw)) => SerializedTileTexture::Sequence(SerializedTileAnimation {
+                        sequence: raw.sequence,
+                        fps: raw.fps,
+                        group: anim.group,
+                    }),
+                    Ok(None) => {
+                        warn!(
+                            "The animation {:?} of a tile is not registered in the TilemapAnimations, \
+                            saving the tile as an empty static tile.",
+                            anim
+                        );
+                        SerializedTileTexture::Static(Vec::new())
+                    }
+                    Err(frame) => SerializedTileTexture::Static(vec![TileLayer::no_flip(frame)]),
+                }
+            }
+        };
+
+        Self {
+            texture,
+            tint: tile.tint,
+            offset: tile.offset,
+        }
+    }
+
+    /// Register the animation of the tile into `animations` if there isn't one with
+    /// the same frames yet. `legacy` is the animation buffer saved with old saves.
+    pub fn into_builder(
+        self,
+        animations: &mut TilemapAnimations,
+        legacy: Option<&TilemapAnimations>,
+    ) -> TileBuilder {
+        let mut register = |raw: RawTileAnimation, group: u32| {
+            let anim = animations
+                .find(&raw)
+                .unwrap_or_else(|| animations.register(raw));
+            TileTexture::Animated(TileAnimation { group, ..anim })
+        };
+
+        let texture = match self.texture {
+            SerializedTileTexture::Static(layers) => TileTexture::Static(layers),
+            SerializedTileTexture::Animated(anim) => {
+                match legacy.and_then(|legacy| legacy.get(anim)) {
+                    Some(raw) => register(raw, anim.group),
+                    None => TileTexture::Animated(anim),
+                }
+            }
+            SerializedTileTexture::Sequence(anim) => register(
+                RawTileAnimation {
+                    sequence: anim.sequence,
+                    fps: anim.fps,
+                },
+                anim.group,
+            ),
+        };
+
+        TileBuilder {
+            texture,
+            tint: self.tint,
+            offset: self.offset,
+        }
+    }
+
+    /// Change the texture indices of the layers or the animation frames.
+    /// Legacy animations are remapped through the animation buffer saved with the tilemap.
+    pub fn remap_texture_indices(&mut self, mapping: &HashMap<u32, u32>) {
+        match &mut self.texture {
+            SerializedTileTexture::Static(layers) => layers.iter_mut().for_each(|layer| {
+                layer.texture_index = tile::remap_texture_index(layer.texture_index, mapping)
+            }),
+            SerializedTileTexture::Animated(_) => {}
+            SerializedTileTexture::Sequence(anim) => anim.sequence.iter_mut().for_each(|frame| {
+                *frame = tile::remap_texture_index(*frame as i32, mapping) as u32
+            }),
+        }
     }
 }
 
@@ -44,6 +391,8 @@ pub struct SerializedTilemapData {
 #[derive(Serialize, Deserialize)]
 pub struct SerializedTilemap {
     pub name: TilemapName,
+    #[serde(default)]
+    pub id: Option<TilemapId>,
     pub tile_render_size: TileRenderSize,
     pub slot_size: TilemapSlotSize,
     pub ty: TilemapType,
@@ -51,14 +400,23 @@ pub struct SerializedTilemap {
     pub layer_opacities: TilemapLayerOpacities,
     pub tilemap_transform: TilemapTransform,
     pub texture: Option<SerializedTilemapTexture>,
+    /// The animation buffer of old saves, which the tiles point into.
+    /// New saves store the frames in `SerializedTile`s instead.
+    #[serde(default)]
     pub animations: Option<TilemapAnimations>,
     pub layers: TilemapLayer,
     pub chunk_size: u32,
+    #[serde(default)]
+    pub sparse: bool,
+    /// The sections saved by the `TilemapSerializerHooks`, by their names.
+    #[serde(default, serialize_with = "serialize_sorted")]
+    pub sections: HashMap<String, String>,
 }
 
 impl SerializedTilemap {
     pub fn from_tilemap(
         name: TilemapName,
+        id: Option<TilemapId>,
         tile_render_size: TileRenderSize,
         slot_size: TilemapSlotSize,
         ty: TilemapType,
@@ -67,11 +425,11 @@ impl SerializedTilemap {
         storage: TilemapStorage,
         tilemap_transform: TilemapTransform,
         texture: Option<TilemapTexture>,
-        animations: Option<TilemapAnimations>,
         saver: &TilemapSaver,
     ) -> Self {
         SerializedTilemap {
             name: name.clone(),
+            id,
             ty,
             tile_render_size,
             slot_size,
@@ -86,11 +444,23 @@ impl SerializedTilemap {
             layer_opacities,
             tilemap_transform,
             layers: saver.layers,
-            animations,
+            animations: None,
             chunk_size: storage.storage.chunk_size,
+            sparse: storage.is_sparse(),
+            sections: HashMap::new(),
+        }
+    }
+
+    /// Create an empty chunked storage with the same layout as the saved tilemap.
+    pub fn new_storage<T: Debug + Clone + Reflect>(&self) -> ChunkedStorage<T> {
+        if self.sparse {
+            ChunkedStorage::new_sparse(self.chunk_size)
+        } else {
+            ChunkedStorage::new(self.chunk_size)
         }
     }
 
+    /// The animations are empty, register them using `SerializedTile::into_builder`.
     pub fn into_tilemap(&self, tilemap: Entity, texture: TilemapTexture) -> StandardTilemapBundle {
         StandardTilemapBundle {
             name: self.name.clone(),
@@ -101,12 +471,11 @@ impl SerializedTilemap {
             layer_opacities: self.layer_opacities,
             storage: TilemapStorage {
                 tilemap,
-                storage: ChunkedStorage::new(self.chunk_size),
+                storage: self.new_storage(),
                 ..Default::default()
             },
             transform: self.tilemap_transform,
             texture,
-            animations: self.animations.clone().unwrap(),
             ..Default::default()
         }
     }
@@ -121,7 +490,7 @@ impl SerializedTilemap {
             layer_opacities: self.layer_opacities,
             storage: TilemapStorage {
                 tilemap,
-                storage: ChunkedStorage::new(self.chunk_size),
+                storage: self.new_storage(),
                 ..Default::default()
             },
             transform: self.tilemap_transform,
@@ -142,6 +511,16 @@ pub struct SerializedTilemapTextureDescriptor {
     pub size: UVec2,
     pub tile_size: UVec2,
     pub filter_mode: SerializedFilterMode,
+    #[serde(default = "default_texture_array")]
+    pub texture_array: bool,
+    #[serde(default)]
+    pub margin: u32,
+    #[serde(default)]
+    pub spacing: u32,
+}
+
+fn default_texture_array() -> bool {
+    TilemapTextureDescriptor::default().texture_array
 }
 
 impl From<TilemapTextureDescriptor> for SerializedTilemapTextureDescriptor {
@@ -150,6 +529,9 @@ impl From<TilemapTextureDescriptor> for SerializedTilemapTextureDescriptor {
             size: value.size,
             tile_size: value.tile_size,
             filter_mode: value.filter_mode.into(),
+            texture_array: value.texture_array,
+            margin: value.margin,
+            spacing: value.spacing,
         }
     }
 }
@@ -160,6 +542,9 @@ impl Into<TilemapTextureDescriptor> for SerializedTilemapTextureDescriptor {
             size: self.size,
             tile_size: self.tile_size,
             filter_mode: self.filter_mode.into(),
+            texture_array: self.texture_array,
+            margin: self.margin,
+            spacing: self.spacing,
         }
     }
 }
@@ -196,3 +581,227 @@ bitflags::bitflags! {
         const PHYSICS = 1 << 2;
     }
 }
+
+/// Written last when saving a tilemap, so a save with a matching manifest is complete.
+///
+/// Each file is replaced atomically, but if the game crashes in the middle of a save,
+/// some files may be from the new save while the others are from the old one.
+/// The old manifest is removed before saving, so such a save has no manifest.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct SaveManifest {
+    /// The files in the folder of the tilemap.
+    #[serde(serialize_with = "serialize_sorted")]
+    pub files: HashMap<String, SavedFileInfo>,
+}
+
+/// The size and the hash of a file in a `SaveManifest`.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SavedFileInfo {
+    /// In bytes.
+    pub len: u64,
+    /// The 64-bit FNV-1a hash of the content.
+    pub hash: u64,
+}
+
+impl SavedFileInfo {
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            len: bytes.len() as u64,
+            hash: bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+                (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+            }),
+        }
+    }
+}
+
+impl SaveManifest {
+    /// Record the files in the folder of the tilemap, excluding the sub folders.
+    pub fn from_dir(map_path: &Path) -> std::io::Result<Self> {
+        let mut files = HashMap::new();
+        for entry in std::fs::read_dir(map_path)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            if metadata.is_file() && name != MANIFEST && !name.ends_with(TEMP_SUFFIX) {
+                let info = SavedFileInfo::from_bytes(&std::fs::read(entry.path())?);
+                files.insert(name, info);
+            }
+        }
+        Ok(Self { files })
+    }
+
+    /// The files that are missing or don't match the manifest.
+    /// Empty if the save is complete.
+    pub fn mismatches(&self, map_path: &Path) -> Vec<String> {
+        let mut mismatches = self
+            .files
+            .iter()
+            .filter(|(name, info)| {
+                let path = map_path.join(name.as_str());
+                // Only read the files whose size matches.
+                std::fs::metadata(&path).map_or(true, |metadata| metadata.len() != info.len)
+                    || std::fs::read(&path)
+                        .map_or(true, |bytes| SavedFileInfo::from_bytes(&bytes) != **info)
+            })
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        mismatches.sort();
+        mismatches
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_chunked_delta() {
+        let mut base = ChunkedStorage::<i32>::new(4);
+        base.set_elem(IVec2::new(0, 0), 1);
+        base.set_elem(IVec2::new(5, 0), 2);
+        base.set_elem(IVec2::new(9, 0), 3);
+
+        let mut current = base.clone();
+        current.set_elem(IVec2::new(1, 1), 4);
+        current.remove_chunk(IVec2::new(1, 0));
+
+        let delta = SerializedChunkedDelta::from_storage(
+            &current,
+            [IVec2::new(0, 0), IVec2::new(1, 0)].into_iter(),
+        );
+        let delta =
+            ron::from_str::<SerializedChunkedDelta<i32>>(&ron::to_string(&delta).unwrap()).unwrap();
+        delta.apply(&mut base);
+
+        assert_eq!(base.get_elem(IVec2::new(0, 0)), Some(&1));
+        assert_eq!(base.get_elem(IVec2::new(1, 1)), Some(&4));
+        assert_eq!(base.get_elem(IVec2::new(5, 0)), None);
+        assert_eq!(base.get_elem(IVec2::new(9, 0)), Some(&3));
+    }
+
+    #[test]
+    fn test_padded_texture_round_trip() {
+        let desc = TilemapTextureDescriptor::new_padded(
+            UVec2::new(54, 37),
+            UVec2::splat(16),
+            2,
+            1,
+            FilterMode::Nearest,
+        );
+        let saved = SerializedTilemapTextureDescriptor::from(desc.clone());
+        let saved = ron::from_str::<SerializedTilemapTextureDescriptor>(
+            &ron::to_string(&saved).unwrap(),
+        )
+        .unwrap();
+        let loaded: TilemapTextureDescriptor = saved.into();
+        assert_eq!(loaded, desc);
+
+        // Saves from before the padding was supported.
+        let legacy = ron::from_str::<SerializedTilemapTextureDescriptor>(
+            "(size: (32, 32), tile_size: (16, 16), filter_mode: Nearest)",
+        )
+        .unwrap();
+        let loaded: TilemapTextureDescriptor = legacy.into();
+        assert!(!loaded.is_padded());
+    }
+
+    #[test]
+    fn test_tile_mirror() {
+        use crate::tilemap::tile::TileLayer;
+
+        let storage = TilemapStorage::new(4, Entity::from_raw(0));
+        let mut mirror = TilemapTileMirror {
+            tiles: Some(ChunkedStorage::new_like(&storage.storage)),
+        };
+        let tile = |index: IVec2, texture: i32| {
+            TileBuilder::new()
+                .with_layer(0, TileLayer::no_flip(texture))
+                .build_component(index, &storage, storage.tilemap)
+        };
+
+        mirror.set(&tile(IVec2::ZERO, 1));
+        mirror.set(&tile(IVec2::new(5, -3), 2));
+        mirror.set(&tile(IVec2::ZERO, 3));
+        mirror.remove(IVec2::new(5, -3));
+
+        let tiles = mirror.tiles().unwrap();
+        let expected: TileBuilder = tile(IVec2::ZERO, 3).into();
+        assert_eq!(tiles.iter_some().count(), 1);
+        assert_eq!(
+            ron::to_string(tiles.get_elem(IVec2::ZERO).unwrap()).unwrap(),
+            ron::to_string(&expected).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_animation_round_trip() {
+        let raw = |sequence: Vec<u32>| RawTileAnimation { sequence, fps: 5 };
+        let mut saved_anims = TilemapAnimations::default();
+        let unused = saved_anims.register(raw(vec![9, 9]));
+        let anim = saved_anims.register(raw(vec![1, 2, 3])).with_group(1);
+        saved_anims.unregister(unused);
+
+        let tile = TileBuilder::new().with_animation(anim);
+        let saved = SerializedTile::from_builder(&tile, Some(&saved_anims));
+        let saved = ron::from_str::<SerializedTile>(&ron::to_string(&saved).unwrap()).unwrap();
+
+        // The loaded tilemap has different animations registered before.
+        let mut anims = TilemapAnimations::default();
+        anims.register(raw(vec![4]));
+        let loaded = saved.clone().into_builder(&mut anims, None);
+        let TileTexture::Animated(loaded_anim) = loaded.texture else {
+            panic!("The tile is not animated!");
+        };
+        assert_eq!(anims.get(loaded_anim).unwrap().sequence, vec![1, 2, 3]);
+        assert_eq!(loaded_anim.group(), 1);
+
+        // The same animation is only registered once.
+        saved.into_builder(&mut anims, None);
+        assert_eq!(anims.len(), 2);
+
+        // Old saves point into the saved animation buffer.
+        let legacy = SerializedTile {
+            texture: SerializedTileTexture::Animated(anim),
+            tint: Color::WHITE,
+            offset: TileOffset::ZERO,
+        };
+        let mut anims = TilemapAnimations::default();
+        let TileTexture::Animated(loaded_anim) = legacy
+            .into_builder(&mut anims, Some(&saved_anims))
+            .texture
+        else {
+            panic!("The tile is not animated!");
+        };
+        assert_eq!(loaded_anim.start(), 1);
+        assert_eq!(anims.get(loaded_anim).unwrap().sequence, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_save_manifest() {
+        let map_path = std::env::temp_dir().join("entitiles_test_manifest");
+        let _ = std::fs::remove_dir_all(&map_path);
+        crate::serializing::save_object(&map_path, TILES, &vec![1, 2, 3]);
+        crate::serializing::save_object(&map_path, PATH_TILES, &vec![4]);
+        // Left by a crash while writing.
+        std::fs::write(map_path.join(format!("{}{}", TILES, TEMP_SUFFIX)), "(").unwrap();
+
+        let manifest = SaveManifest::from_dir(&map_path).unwrap();
+        assert_eq!(manifest.files.len(), 2);
+        assert!(manifest.mismatches(&map_path).is_empty());
+
+        // The same size, but a different content.
+        crate::serializing::save_object(&map_path, TILES, &vec![1, 2, 4]);
+        assert_eq!(manifest.mismatches(&map_path), vec![TILES.to_string()]);
+
+        crate::serializing::save_object(&map_path, TILES, &vec![1, 2, 3, 4]);
+        std::fs::remove_file(map_path.join(PATH_TILES)).unwrap();
+        assert_eq!(
+            manifest.mismatches(&map_path),
+            vec![PATH_TILES.to_string(), TILES.to_string()]
+        );
+
+        std::fs::remove_dir_all(&map_path).unwrap();
+    }
+}
@@ -0,0 +1,169 @@
+use std::path::Path;
+
+use bevy::utils::HashMap;
+use ron::error::SpannedError;
+
+use crate::{
+    serializing::{
+        chunk::TILE_CHUNKS_FOLDER,
+        load_object,
+        map::{SerializedChunkedDelta, SerializedTile, SerializedTilemap},
+        pattern::TilemapPattern,
+        save_object,
+    },
+    tilemap::{buffers::TileBuffer, chunking::storage::ChunkedStorage},
+};
+
+use super::{TILEMAP_META, TILES, TILES_DELTA};
+
+/// Change the texture indices of a saved tilemap without loading it,
+/// like after rearranging the tileset. Indices that are not in `mapping` are kept.
+///
+/// `map_path` is the folder of the tilemap, which is `path/map_name` of the `TilemapSaver`.
+/// This includes the full save, the delta save and the chunks saved by the chunk saver.
+pub fn remap_saved_texture_indices(
+    map_path: &Path,
+    mapping: &HashMap<u32, u32>,
+) -> Result<(), SpannedError> {
+    if map_path.join(TILEMAP_META).exists() {
+        let mut tilemap = load_object::<SerializedTilemap>(map_path, TILEMAP_META)?;
+        if let Some(animations) = &mut tilemap.animations {
+            animations.remap_texture_indices(mapping);
+            save_object(map_path, TILEMAP_META, &tilemap);
+        }
+    }
+
+    if map_path.join(TILES).exists() {
+        let mut tiles = load_object::<ChunkedStorage<SerializedTile>>(map_path, TILES)?;
+        tiles
+            .iter_some_mut()
+            .for_each(|tile| tile.remap_texture_indices(mapping));
+        save_object(map_path, TILES, &tiles);
+    }
+
+    if map_path.join(TILES_DELTA).exists() {
+        let mut delta =
+            load_object::<SerializedChunkedDelta<SerializedTile>>(map_path, TILES_DELTA)?;
+        delta
+            .chunks
+            .values_mut()
+            .flatten()
+            .flatten()
+            .flatten()
+            .for_each(|tile| tile.remap_texture_indices(mapping));
+        save_object(map_path, TILES_DELTA, &delta);
+    }
+
+    let chunks_path = map_path.join(TILE_CHUNKS_FOLDER);
+    if chunks_path.exists() {
+        for entry in std::fs::read_dir(&chunks_path)? {
+            let file_name = entry?.file_name();
+            let Some(file_name) = file_name.to_str() else {
+                continue;
+            };
+            if !file_name.ends_with(".ron") {
+                continue;
+            }
+
+            let mut chunk = load_object::<TileBuffer<SerializedTile>>(&chunks_path, file_name)?;
+            chunk
+                .tiles
+                .values_mut()
+                .for_each(|tile| tile.remap_texture_indices(mapping));
+            save_object(&chunks_path, file_name, &chunk);
+        }
+    }
+
+    Ok(())
+}
+
+/// Change the texture indices of a saved pattern, including the animations.
+pub fn remap_saved_pattern_texture_indices(
+    path: &Path,
+    file_name: &str,
+    mapping: &HashMap<u32, u32>,
+) -> Result<(), SpannedError> {
+    let mut pattern = load_object::<TilemapPattern>(path, file_name)?;
+    pattern.remap_texture_indices(mapping);
+    save_object(path, file_name, &pattern);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::{math::IVec2, render::color::Color};
+
+    use crate::{
+        serializing::map::{SerializedTileAnimation, SerializedTileTexture},
+        tilemap::tile::{RawTileAnimation, TileFlip, TileLayer, TileOffset},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_remap_saved_texture_indices() {
+        let map_path = std::env::temp_dir().join("entitiles_test_remap");
+        let _ = std::fs::remove_dir_all(&map_path);
+        let mapping = HashMap::from([(1, 5), (2, 1)]);
+
+        let mut tiles = ChunkedStorage::<SerializedTile>::new(4);
+        tiles.set_elem(
+            IVec2::ZERO,
+            SerializedTile {
+                texture: SerializedTileTexture::Static(vec![
+                    TileLayer::flip_h(1),
+                    TileLayer::no_flip(-1),
+                    TileLayer::no_flip(3),
+                ]),
+                tint: Color::WHITE,
+                offset: TileOffset::ZERO,
+            },
+        );
+        tiles.set_elem(
+            IVec2::new(5, 0),
+            SerializedTile {
+                texture: SerializedTileTexture::Sequence(SerializedTileAnimation {
+                    sequence: vec![2, 1, 0],
+                    fps: 5,
+                    group: 0,
+                }),
+                tint: Color::WHITE,
+                offset: TileOffset::ZERO,
+            },
+        );
+        save_object(&map_path, TILES, &tiles);
+
+        let mut pattern = TilemapPattern::new(None);
+        pattern.animations.register(RawTileAnimation {
+            sequence: vec![1, 2],
+            fps: 5,
+        });
+        save_object(&map_path, "pattern.ron", &pattern);
+
+        remap_saved_texture_indices(&map_path, &mapping).unwrap();
+        remap_saved_pattern_texture_indices(&map_path, "pattern.ron", &mapping).unwrap();
+
+        let tiles = load_object::<ChunkedStorage<SerializedTile>>(&map_path, TILES).unwrap();
+        let SerializedTileTexture::Static(layers) = &tiles.get_elem(IVec2::ZERO).unwrap().texture
+        else {
+            panic!("The tile is not static!");
+        };
+        assert_eq!(
+            layers.iter().map(|l| l.texture_index).collect::<Vec<_>>(),
+            vec![5, -1, 3]
+        );
+        assert!(layers[0].flip.contains(TileFlip::HORIZONTAL));
+        let SerializedTileTexture::Sequence(anim) =
+            &tiles.get_elem(IVec2::new(5, 0)).unwrap().texture
+        else {
+            panic!("The tile is not animated!");
+        };
+        assert_eq!(anim.sequence, vec![1, 5, 0]);
+
+        let pattern = load_object::<TilemapPattern>(&map_path, "pattern.ron").unwrap();
+        let anim = *pattern.animations.iter().next().unwrap();
+        assert_eq!(pattern.animations.get(anim).unwrap().sequence, vec![5, 1]);
+
+        std::fs::remove_dir_all(&map_path).unwrap();
+    }
+}
@@ -1,19 +1,24 @@
-use std::path::Path;
+use std::{fmt::Debug, path::Path};
 
 use bevy::{
     ecs::{
         component::Component,
-        entity::Entity,
-        system::{Commands, Query},
+        entity::{Entity, EntityHashMap},
+        system::{Commands, Query, Res},
     },
+    math::IVec2,
     reflect::Reflect,
 };
+use serde::{de::DeserializeOwned, Serialize};
 
 use crate::{
-    serializing::{pattern::TilemapPattern, save_object},
+    math::aabb::IAabb2d,
+    serializing::{load_object, pattern::TilemapPattern, save_object},
     tilemap::{
+        budget::TileSpawnQueue,
         chunking::storage::ChunkedStorage,
         despawn::DespawnMe,
+        id::TilemapId,
         map::{
             TilePivot, TileRenderSize, TilemapAnimations, TilemapLayerOpacities, TilemapName,
             TilemapSlotSize, TilemapStorage, TilemapTexture, TilemapTransform, TilemapType,
@@ -22,16 +27,21 @@ use crate::{
     },
 };
 
-use super::{SerializedTilemap, TilemapLayer, TILEMAP_META, TILES};
+use super::{
+    hook::TilemapSavedSections, SaveManifest, SerializedChunkedDelta, SerializedTile,
+    SerializedTilemap, TilemapDirtyChunks, TilemapLayer, TilemapTileMirror, MANIFEST, TILEMAP_META,
+    TILES, TILES_DELTA,
+};
 
 #[cfg(feature = "algorithm")]
-use crate::{algorithm::pathfinding::PathTilemaps, serializing::map::PATH_TILES};
-#[cfg(feature = "algorithm")]
-use bevy::ecs::system::Res;
+use crate::{
+    algorithm::pathfinding::PathTilemaps,
+    serializing::map::{PATH_TILES, PATH_TILES_DELTA},
+};
 
-#[cfg(feature = "physics")]
+#[cfg(feature = "physics_core")]
 use crate::{
-    serializing::map::PHYSICS_TILES,
+    serializing::map::{PHYSICS_TILES, PHYSICS_TILES_DELTA},
     tilemap::{buffers::PackedPhysicsTileBuffer, physics::SerializablePhysicsSource},
 };
 
@@ -41,6 +51,25 @@ pub enum TilemapSaverMode {
     MapPattern,
 }
 
+/// Which part of the tilemap to save.
+///
+/// Partial saves are written into sidecar delta files next to the base save,
+/// and will be applied over it when loading. So make sure there's a full save
+/// before saving partially.
+#[derive(Debug, Clone, Copy, Default, Reflect)]
+pub enum TilemapSaverRange {
+    /// Save the whole tilemap, and discard the existing deltas.
+    #[default]
+    Full,
+    /// Save the chunks that intersect with this area.
+    Region(IAabb2d),
+    /// Save the chunks that changed since the last save.
+    ///
+    /// This requires `TilemapDirtyChunks` on the tilemap.
+    /// Layers that didn't change will be skipped.
+    Dirty,
+}
+
 #[derive(Component)]
 pub struct TilemapSaver {
     /// For example if path = C:\\maps, then the crate will create:
@@ -63,6 +92,67 @@ pub struct TilemapSaver {
     pub layers: TilemapLayer,
     pub texture_path: Option<String>,
     pub remove_after_save: bool,
+    /// Only available in `TilemapSaverMode::Tilemap` mode.
+    pub range: TilemapSaverRange,
+}
+
+impl TilemapSaver {
+    #[inline]
+    fn is_partial(&self) -> bool {
+        self.mode == TilemapSaverMode::Tilemap && !matches!(self.range, TilemapSaverRange::Full)
+    }
+
+    fn partial_chunks<T: Debug + Clone + Reflect>(
+        &self,
+        layer: TilemapLayer,
+        storage: &ChunkedStorage<T>,
+        dirty: Option<&TilemapDirtyChunks>,
+    ) -> Vec<IVec2> {
+        match self.range {
+            TilemapSaverRange::Full => storage.chunk_indices().copied().collect(),
+            TilemapSaverRange::Region(region) => {
+                let min = storage.transform_index(region.min).0;
+                let max = storage.transform_index(region.max).0;
+                (min.y..=max.y)
+                    .flat_map(|y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+                    .collect()
+            }
+            TilemapSaverRange::Dirty => dirty.map(|d| d.iter(layer).collect()).unwrap_or_default(),
+        }
+    }
+}
+
+/// Merge the delta into the existing delta file.
+fn save_delta<T>(map_path: &Path, file_name: &str, delta: SerializedChunkedDelta<T>)
+where
+    T: Debug + Clone + Reflect + Serialize + DeserializeOwned,
+{
+    if delta.chunks.is_empty() {
+        return;
+    }
+
+    let mut merged =
+        load_object::<SerializedChunkedDelta<T>>(map_path, file_name).unwrap_or_default();
+    merged.merge(delta);
+    save_object(map_path, file_name, &merged);
+}
+
+/// Tiles queued by `TileSpawnBudget` don't have the `Tile` component yet,
+/// so they are saved as they will be spawned.
+fn get_tile_builder(
+    entity: Entity,
+    tiles_query: &Query<&Tile>,
+    queued: &EntityHashMap<&TileBuilder>,
+) -> Option<TileBuilder> {
+    tiles_query
+        .get(entity)
+        .ok()
+        .map(|tile| tile.clone().into())
+        .or_else(|| queued.get(&entity).map(|builder| (*builder).clone()))
+}
+
+fn remove_delta(map_path: &Path, file_name: &str) {
+    let _ = std::fs::remove_file(map_path.join(file_name));
 }
 
 pub fn save(
@@ -70,6 +160,7 @@ pub fn save(
     mut tilemaps_query: Query<(
         Entity,
         &TilemapName,
+        Option<&TilemapId>,
         &TileRenderSize,
         &TilemapSlotSize,
         &TilemapType,
@@ -79,17 +170,21 @@ pub fn save(
         &TilemapTransform,
         Option<&TilemapTexture>,
         Option<&TilemapAnimations>,
-        &TilemapSaver,
+        (&TilemapSaver, Option<&TilemapSavedSections>),
+        Option<&mut TilemapDirtyChunks>,
+        Option<&TilemapTileMirror>,
     )>,
     tiles_query: Query<&Tile>,
+    queue: Res<TileSpawnQueue>,
     #[cfg(feature = "algorithm")] path_tilemaps: Res<PathTilemaps>,
-    #[cfg(feature = "physics")] physics_tilemaps_query: Query<
+    #[cfg(feature = "physics_core")] physics_tilemaps_query: Query<
         &crate::tilemap::physics::PhysicsTilemap,
     >,
 ) {
     for (
         entity,
         name,
+        id,
         tile_render_size,
         slot_size,
         ty,
@@ -99,15 +194,21 @@ pub fn save(
         transform,
         texture,
         animations,
-        saver,
+        (saver, sections),
+        mut dirty,
+        mirror,
     ) in tilemaps_query.iter_mut()
     {
         let map_dir = Path::new(&saver.path);
         let map_path = map_dir.join(&name.0);
 
         if saver.mode == TilemapSaverMode::Tilemap {
-            let serialized_tilemap = SerializedTilemap::from_tilemap(
+            // The old manifest doesn't match the files anymore once any of them is written.
+            let _ = std::fs::remove_file(map_path.join(MANIFEST));
+
+            let mut serialized_tilemap = SerializedTilemap::from_tilemap(
                 name.clone(),
+                id.cloned(),
                 *tile_render_size,
                 *slot_size,
                 *ty,
@@ -116,32 +217,82 @@ pub fn save(
                 storage.clone(),
                 transform.clone(),
                 texture.cloned(),
-                animations.cloned(),
                 saver,
             );
+            if let Some(sections) = sections {
+                serialized_tilemap.sections = sections.0.clone();
+                commands.entity(entity).remove::<TilemapSavedSections>();
+            }
             save_object(&map_path, TILEMAP_META, &serialized_tilemap);
         }
         let mut pattern = TilemapPattern::new(Some(name.0.clone()));
+        let mirrored = mirror.and_then(|m| m.tiles());
 
         // color
-        if saver.layers.contains(TilemapLayer::COLOR) {
-            let chunk_size = storage.storage.chunk_size;
-            let ser_tiles = storage.storage.chunked_iter_some().fold(
-                ChunkedStorage::<TileBuilder>::new(chunk_size),
-                |mut acc, (chunk_index, in_chunk_index, tile)| {
-                    acc.set_elem_precise(
-                        chunk_index,
-                        in_chunk_index,
-                        tiles_query.get(*tile).unwrap().clone().into(),
-                    );
-                    acc
-                },
-            );
+        if saver.layers.contains(TilemapLayer::COLOR) && saver.is_partial() {
+            let chunks =
+                saver.partial_chunks(TilemapLayer::COLOR, &storage.storage, dirty.as_deref());
+            let delta = match mirrored {
+                Some(tiles) => {
+                    SerializedChunkedDelta::from_storage_mapped(tiles, chunks.into_iter(), |tile| {
+                        Some(SerializedTile::from_builder(tile, animations))
+                    })
+                }
+                None => {
+                    let queued = queue.queued_tiles(&storage);
+                    SerializedChunkedDelta::from_storage_mapped(
+                        &storage.storage,
+                        chunks.into_iter(),
+                        |tile| {
+                            get_tile_builder(*tile, &tiles_query, &queued)
+                                .map(|t| SerializedTile::from_builder(&t, animations))
+                        },
+                    )
+                }
+            };
+            save_delta(&map_path, TILES_DELTA, delta);
+        } else if saver.layers.contains(TilemapLayer::COLOR) {
+            let ser_tiles = match mirrored {
+                Some(tiles) => tiles.chunked_iter_some().fold(
+                    ChunkedStorage::<SerializedTile>::new_like(tiles),
+                    |mut acc, (chunk_index, in_chunk_index, tile)| {
+                        acc.set_elem_precise(
+                            chunk_index,
+                            in_chunk_index,
+                            SerializedTile::from_builder(tile, animations),
+                        );
+                        acc
+                    },
+                ),
+                None => {
+                    let queued = queue.queued_tiles(&storage);
+                    storage.storage.chunked_iter_some().fold(
+                        ChunkedStorage::<SerializedTile>::new_like(&storage.storage),
+                        |mut acc, (chunk_index, in_chunk_index, tile)| {
+                            if let Some(tile) = get_tile_builder(*tile, &tiles_query, &queued) {
+                                acc.set_elem_precise(
+                                    chunk_index,
+                                    in_chunk_index,
+                                    SerializedTile::from_builder(&tile, animations),
+                                );
+                            }
+                            acc
+                        },
+                    )
+                }
+            };
 
             match saver.mode {
                 TilemapSaverMode::Tilemap => save_object(&map_path, TILES, &ser_tiles),
                 TilemapSaverMode::MapPattern => {
-                    pattern.tiles.tiles = ser_tiles.into_mapper();
+                    // Only the animations used by the tiles are copied into the pattern.
+                    pattern.tiles.tiles = ser_tiles
+                        .into_mapper()
+                        .into_iter()
+                        .map(|(index, tile)| {
+                            (index, tile.into_builder(&mut pattern.animations, None))
+                        })
+                        .collect();
                     pattern.tiles.recalculate_aabb();
                 }
             }
@@ -161,6 +312,21 @@ pub fn save(
                 };
 
                 match saver.mode {
+                    TilemapSaverMode::Tilemap if saver.is_partial() => {
+                        let chunks = saver.partial_chunks(
+                            TilemapLayer::PATH,
+                            &path_tilemap.storage,
+                            dirty.as_deref(),
+                        );
+                        save_delta(
+                            &map_path,
+                            PATH_TILES_DELTA,
+                            SerializedChunkedDelta::from_storage(
+                                &path_tilemap.storage,
+                                chunks.into_iter(),
+                            ),
+                        );
+                    }
                     TilemapSaverMode::Tilemap => {
                         save_object(&map_path, PATH_TILES, &path_tilemap.storage)
                     }
@@ -173,10 +339,25 @@ pub fn save(
             }
         }
 
-        #[cfg(feature = "physics")]
+        #[cfg(feature = "physics_core")]
         if saver.layers.contains(TilemapLayer::PHYSICS) {
             if let Ok(physics_tilemap) = physics_tilemaps_query.get(entity) {
                 match saver.mode {
+                    TilemapSaverMode::Tilemap if saver.is_partial() => {
+                        let chunks = saver.partial_chunks(
+                            TilemapLayer::PHYSICS,
+                            &physics_tilemap.data,
+                            dirty.as_deref(),
+                        );
+                        save_delta(
+                            &map_path,
+                            PHYSICS_TILES_DELTA,
+                            SerializedChunkedDelta::from_storage(
+                                &physics_tilemap.data,
+                                chunks.into_iter(),
+                            ),
+                        );
+                    }
                     TilemapSaverMode::Tilemap => {
                         save_object(&map_path, PHYSICS_TILES, &physics_tilemap.data)
                     }
@@ -201,7 +382,37 @@ pub fn save(
             }
         }
 
+        if saver.mode == TilemapSaverMode::Tilemap {
+            if !saver.is_partial() {
+                if saver.layers.contains(TilemapLayer::COLOR) {
+                    remove_delta(&map_path, TILES_DELTA);
+                }
+                #[cfg(feature = "algorithm")]
+                if saver.layers.contains(TilemapLayer::PATH) {
+                    remove_delta(&map_path, PATH_TILES_DELTA);
+                }
+                #[cfg(feature = "physics_core")]
+                if saver.layers.contains(TilemapLayer::PHYSICS) {
+                    remove_delta(&map_path, PHYSICS_TILES_DELTA);
+                }
+            }
+
+            if !matches!(saver.range, TilemapSaverRange::Region(_)) {
+                if let Some(dirty) = dirty.as_mut() {
+                    dirty.clear(saver.layers);
+                }
+            }
+        }
+
+        // Only a complete save has a manifest matching the files.
+        if saver.mode == TilemapSaverMode::Tilemap {
+            let manifest =
+                SaveManifest::from_dir(&map_path).unwrap_or_else(|err| panic!("{:?}", err));
+            save_object(&map_path, MANIFEST, &manifest);
+        }
+
         if saver.mode == TilemapSaverMode::MapPattern {
+            pattern.canonicalize();
             save_object(map_dir, format!("{}.ron", name.0).as_str(), &pattern);
         }
 
@@ -1,12 +1,14 @@
-use std::{fs::File, io::Write, path::Path};
+use std::{fs::File, hash::Hash, io::Write, path::Path};
 
-use bevy::app::Plugin;
+use bevy::{app::Plugin, math::IVec2, utils::HashMap};
 use ron::error::SpannedError;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 
 pub mod chunk;
+pub mod csv;
 pub mod map;
 pub mod pattern;
+pub mod slot;
 
 pub struct EntiTilesSerializingPlugin;
 
@@ -19,13 +21,28 @@ impl Plugin for EntiTilesSerializingPlugin {
     }
 }
 
+/// The suffix of the temporary files written before replacing the real ones.
+pub const TEMP_SUFFIX: &str = ".tmp";
+
+/// Write to a temporary file and rename it over the target, so the target is never
+/// left half-written if the game crashes while saving.
+pub fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(TEMP_SUFFIX);
+
+    let mut file = File::create(&temp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    std::fs::rename(temp, path)
+}
+
 pub fn save_object<T: Serialize>(path: &Path, file_name: &str, object: &T) {
     std::fs::create_dir_all(path).unwrap_or_else(|err| panic!("{:?}", err));
-    let path = path.join(file_name);
-    File::create(path.clone())
-        .unwrap_or(File::open(path).unwrap())
-        .write(ron::to_string(object).unwrap().as_bytes())
-        .unwrap_or_else(|err| panic!("{:?}", err));
+    write_atomic(
+        &path.join(file_name),
+        ron::to_string(object).unwrap().as_bytes(),
+    )
+    .unwrap_or_else(|err| panic!("{:?}", err));
 }
 
 pub fn load_object<T: for<'a> Deserialize<'a>>(
@@ -34,3 +51,85 @@ pub fn load_object<T: for<'a> Deserialize<'a>>(
 ) -> Result<T, SpannedError> {
     ron::from_str(std::fs::read_to_string(path.join(file_name))?.as_str())
 }
+
+/// Keys that can be sorted when serializing maps.
+pub trait SortedKey: Hash + Eq {
+    type Key: Ord;
+
+    fn sorted_key(&self) -> Self::Key;
+}
+
+impl SortedKey for IVec2 {
+    type Key = (i32, i32);
+
+    #[inline]
+    fn sorted_key(&self) -> Self::Key {
+        (self.y, self.x)
+    }
+}
+
+impl SortedKey for i32 {
+    type Key = i32;
+
+    #[inline]
+    fn sorted_key(&self) -> Self::Key {
+        *self
+    }
+}
+
+impl SortedKey for String {
+    type Key = String;
+
+    #[inline]
+    fn sorted_key(&self) -> Self::Key {
+        self.clone()
+    }
+}
+
+impl SortedKey for usize {
+    type Key = usize;
+
+    #[inline]
+    fn sorted_key(&self) -> Self::Key {
+        *self
+    }
+}
+
+fn sorted_entries<K: SortedKey, V>(map: &HashMap<K, V>) -> Vec<(&K, &V)> {
+    let mut entries = map.iter().collect::<Vec<_>>();
+    entries.sort_unstable_by_key(|(k, _)| k.sorted_key());
+    entries
+}
+
+/// Serialize a map with its entries sorted by key, so the output
+/// is the same across runs and can be diffed or checksummed.
+pub fn serialize_sorted<S, K, V>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    K: SortedKey + Serialize,
+    V: Serialize,
+{
+    serializer.collect_map(sorted_entries(map))
+}
+
+/// Same as `serialize_sorted` but also sorts the inner maps.
+pub fn serialize_sorted_nested<S, K1, K2, V>(
+    map: &HashMap<K1, HashMap<K2, V>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    K1: SortedKey + Serialize,
+    K2: SortedKey + Serialize,
+    V: Serialize,
+{
+    struct Sorted<'a, K, V>(&'a HashMap<K, V>);
+
+    impl<'a, K: SortedKey + Serialize, V: Serialize> Serialize for Sorted<'a, K, V> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serialize_sorted(self.0, serializer)
+        }
+    }
+
+    serializer.collect_map(sorted_entries(map).into_iter().map(|(k, v)| (k, Sorted(v))))
+}
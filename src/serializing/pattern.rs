@@ -1,16 +1,28 @@
 use crate::{
+    math::aabb::IAabb2d,
     prelude::TilemapAnimations,
-    tilemap::{buffers::TileBuffer, map::TilemapTexture},
+    tilemap::{
+        buffers::{TileBuffer, Tiles},
+        id::TilemapId,
+        map::TilemapTexture,
+        tile::{TileBuilder, TileFlip, TileTexture},
+    },
+};
+use bevy::{
+    math::{IVec2, UVec2},
+    reflect::Reflect,
+    utils::HashMap,
 };
-use bevy::{math::UVec2, reflect::Reflect};
 use serde::{Deserialize, Serialize};
 
+use super::SortedKey;
+
 use crate::tilemap::buffers::TileBuilderBuffer;
 
 #[cfg(feature = "algorithm")]
-use crate::tilemap::buffers::PathTileBuffer;
+use crate::tilemap::{algorithm::path::PathTile, buffers::PathTileBuffer};
 
-#[cfg(feature = "physics")]
+#[cfg(feature = "physics_core")]
 use crate::tilemap::physics::SerializablePhysicsSource;
 
 /// A pattern of tiles.
@@ -21,9 +33,19 @@ pub struct TilemapPattern {
     pub label: Option<String>,
     pub tiles: TileBuilderBuffer,
     pub animations: TilemapAnimations,
+    /// The tile that will be placed at the target index. See `origin_at_anchor`.
+    #[serde(default)]
+    pub anchor: IVec2,
+    /// Named points in the pattern, like doors or spawn points.
+    #[serde(default, serialize_with = "super::serialize_sorted")]
+    pub sockets: HashMap<String, IVec2>,
+    /// The tilemaps the sockets lead to, like portals to other maps.
+    /// Find the tilemaps using `TilemapIds`.
+    #[serde(default, serialize_with = "super::serialize_sorted")]
+    pub links: HashMap<String, TilemapId>,
     #[cfg(feature = "algorithm")]
     pub path_tiles: PathTileBuffer,
-    #[cfg(feature = "physics")]
+    #[cfg(feature = "physics_core")]
     pub physics_tiles: SerializablePhysicsSource,
 }
 
@@ -33,12 +55,253 @@ impl TilemapPattern {
             label,
             tiles: TileBuffer::new(),
             animations: TilemapAnimations::default(),
+            anchor: IVec2::ZERO,
+            sockets: HashMap::new(),
+            links: HashMap::new(),
             #[cfg(feature = "algorithm")]
             path_tiles: TileBuffer::new(),
-            #[cfg(feature = "physics")]
+            #[cfg(feature = "physics_core")]
             physics_tiles: SerializablePhysicsSource::Buffer(TileBuffer::new()),
         }
     }
+
+    /// Bring the pattern into a canonical form, so equivalent patterns
+    /// always produce the same serialized output.
+    ///
+    /// Currently this removes the trailing empty layers of the tiles.
+    pub fn canonicalize(&mut self) {
+        self.tiles.tiles.values_mut().for_each(|tile| {
+            if let TileTexture::Static(layers) = &mut tile.texture {
+                while layers.last().is_some_and(|layer| layer.texture_index < 0) {
+                    layers.pop();
+                }
+            }
+        });
+    }
+
+    /// Change the texture indices of the tiles and the animation frames.
+    /// Indices that are not in `mapping` are kept.
+    pub fn remap_texture_indices(&mut self, mapping: &HashMap<u32, u32>) {
+        self.tiles
+            .tiles
+            .values_mut()
+            .for_each(|tile| tile.texture.remap_texture_indices(mapping));
+        self.animations.remap_texture_indices(mapping);
+    }
+
+    /// The changes that turn `a` into `b`, for testing generators, undo entries
+    /// or sending deltas over the network.
+    ///
+    /// Only the tiles and the path tiles are compared. Use `diff(b, a)` to get the
+    /// changes that turn `b` back into `a`.
+    pub fn diff(a: &TilemapPattern, b: &TilemapPattern) -> TilemapPatternDiff {
+        TilemapPatternDiff {
+            tiles: TileBufferDiff::new(&a.tiles, &b.tiles),
+            #[cfg(feature = "algorithm")]
+            path_tiles: TileBufferDiff::new(&a.path_tiles, &b.path_tiles),
+        }
+    }
+
+    /// Apply the changes of `TilemapPattern::diff`.
+    pub fn apply_diff(&mut self, diff: &TilemapPatternDiff) {
+        diff.tiles.apply(&mut self.tiles);
+        #[cfg(feature = "algorithm")]
+        diff.path_tiles.apply(&mut self.path_tiles);
+    }
+
+    pub fn with_anchor(mut self, anchor: IVec2) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    pub fn with_socket(mut self, name: impl Into<String>, index: IVec2) -> Self {
+        self.sockets.insert(name.into(), index);
+        self
+    }
+
+    #[inline]
+    pub fn get_socket(&self, name: &str) -> Option<IVec2> {
+        self.sockets.get(name).copied()
+    }
+
+    /// Link the socket to another tilemap.
+    pub fn with_link(mut self, socket: impl Into<String>, tilemap: TilemapId) -> Self {
+        self.links.insert(socket.into(), tilemap);
+        self
+    }
+
+    #[inline]
+    pub fn get_link(&self, socket: &str) -> Option<&TilemapId> {
+        self.links.get(socket)
+    }
+
+    /// The origin to use in `TilemapStorage::fill_with_buffer` so the anchor lands on `target`.
+    #[inline]
+    pub fn origin_at_anchor(&self, target: IVec2) -> IVec2 {
+        target - self.anchor
+    }
+
+    /// The origin to use in `TilemapStorage::fill_with_buffer` so the socket lands on `target`.
+    /// Useful for snapping a pattern to a socket of another placed pattern.
+    #[inline]
+    pub fn origin_at_socket(&self, name: &str, target: IVec2) -> Option<IVec2> {
+        self.get_socket(name).map(|socket| target - socket)
+    }
+
+    /// Rotate the pattern 90 degrees clockwise, keeping the bottom left corner in place.
+    ///
+    /// This only makes sense for square tilemaps. The textures of the tiles can't be rotated,
+    /// so this works best with symmetric tiles. Physics tiles are left unchanged
+    /// as their colliders are already in tilemap space.
+    pub fn rotate90(&mut self) {
+        let aabb = self.tiles.aabb;
+        let width = aabb.width();
+        self.transform_indices(|index| {
+            let rel = index - aabb.min;
+            aabb.min + IVec2::new(rel.y, width - rel.x)
+        });
+    }
+
+    /// Mirror the pattern horizontally, also flipping the textures of the static tiles.
+    /// Physics tiles are left unchanged like in `rotate90`.
+    pub fn flip_x(&mut self) {
+        let aabb = self.tiles.aabb;
+        self.transform_indices(|index| IVec2::new(aabb.min.x + aabb.max.x - index.x, index.y));
+        self.toggle_flip(TileFlip::HORIZONTAL);
+    }
+
+    /// Mirror the pattern vertically. See `flip_x`.
+    pub fn flip_y(&mut self) {
+        let aabb = self.tiles.aabb;
+        self.transform_indices(|index| IVec2::new(index.x, aabb.min.y + aabb.max.y - index.y));
+        self.toggle_flip(TileFlip::VERTICAL);
+    }
+
+    fn transform_indices(&mut self, f: impl Fn(IVec2) -> IVec2) {
+        transform_buffer(&mut self.tiles, &f);
+        #[cfg(feature = "algorithm")]
+        transform_buffer(&mut self.path_tiles, &f);
+        self.anchor = f(self.anchor);
+        self.sockets
+            .values_mut()
+            .for_each(|socket| *socket = f(*socket));
+    }
+
+    fn toggle_flip(&mut self, flip: TileFlip) {
+        self.tiles.tiles.values_mut().for_each(|tile| {
+            if let TileTexture::Static(layers) = &mut tile.texture {
+                layers.iter_mut().for_each(|layer| layer.flip.toggle(flip));
+            }
+        });
+    }
+}
+
+fn transform_buffer<T: Tiles>(buffer: &mut TileBuffer<T>, f: &impl Fn(IVec2) -> IVec2) {
+    if buffer.is_empty() {
+        return;
+    }
+
+    buffer.tiles = buffer
+        .tiles
+        .drain()
+        .map(|(index, tile)| (f(index), tile))
+        .collect();
+    // Keep the empty space of the buffer.
+    let (min, max) = (f(buffer.aabb.min), f(buffer.aabb.max));
+    buffer.aabb = IAabb2d {
+        min: min.min(max),
+        max: min.max(max),
+    };
+}
+
+/// The changes between two patterns. See `TilemapPattern::diff`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TilemapPatternDiff {
+    pub tiles: TileBufferDiff<TileBuilder>,
+    #[cfg(feature = "algorithm")]
+    pub path_tiles: TileBufferDiff<PathTile>,
+}
+
+impl TilemapPatternDiff {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        #[cfg(feature = "algorithm")]
+        if !self.path_tiles.is_empty() {
+            return false;
+        }
+        self.tiles.is_empty()
+    }
+}
+
+/// The changes between two buffers of tiles.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound(serialize = "T: Serialize", deserialize = "T: Deserialize<'de>"))]
+pub struct TileBufferDiff<T: Tiles> {
+    /// The tiles that are only in the new buffer.
+    #[serde(serialize_with = "super::serialize_sorted")]
+    pub added: HashMap<IVec2, T>,
+    /// The tiles that are only in the old buffer, sorted.
+    pub removed: Vec<IVec2>,
+    /// The new tiles that are in both buffers but are different.
+    #[serde(serialize_with = "super::serialize_sorted")]
+    pub changed: HashMap<IVec2, T>,
+}
+
+impl<T: Tiles + PartialEq> TileBufferDiff<T> {
+    /// The changes that turn `a` into `b`.
+    pub fn new(a: &TileBuffer<T>, b: &TileBuffer<T>) -> Self {
+        let mut removed = a
+            .tiles
+            .keys()
+            .filter(|index| !b.tiles.contains_key(*index))
+            .copied()
+            .collect::<Vec<_>>();
+        removed.sort_unstable_by_key(|index| index.sorted_key());
+
+        let mut added = HashMap::new();
+        let mut changed = HashMap::new();
+        b.tiles
+            .iter()
+            .for_each(|(index, tile)| match a.tiles.get(index) {
+                Some(old) if old == tile => {}
+                Some(_) => {
+                    changed.insert(*index, tile.clone());
+                }
+                None => {
+                    added.insert(*index, tile.clone());
+                }
+            });
+
+        Self {
+            added,
+            removed,
+            changed,
+        }
+    }
+
+    pub fn apply(&self, buffer: &mut TileBuffer<T>) {
+        if !self.removed.is_empty() {
+            self.removed.iter().for_each(|index| {
+                buffer.tiles.remove(index);
+            });
+            buffer.recalculate_aabb();
+        }
+        self.added
+            .iter()
+            .chain(self.changed.iter())
+            .for_each(|(index, tile)| buffer.set(*index, tile.clone()));
+    }
+
+    /// The number of tiles that changed.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.added.len() + self.removed.len() + self.changed.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 /// A layer of patterns. This can be used when performing wfc.
@@ -137,3 +400,88 @@ impl PackedPatternLayers {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::tilemap::tile::{TileBuilder, TileLayer};
+
+    use super::*;
+
+    #[test]
+    fn test_pattern_transforms() {
+        let mut pattern = TilemapPattern::new(None)
+            .with_anchor(IVec2::new(1, 0))
+            .with_socket("door", IVec2::new(2, 1));
+        for x in 0..3 {
+            for y in 0..2 {
+                pattern.tiles.set(
+                    IVec2::new(x, y),
+                    TileBuilder::new().with_layer(0, TileLayer::no_flip(y * 3 + x)),
+                );
+            }
+        }
+        let texture_at = |pattern: &TilemapPattern, index: IVec2| {
+            let TileTexture::Static(layers) = &pattern.tiles.get(index).unwrap().texture else {
+                unreachable!()
+            };
+            layers[0]
+        };
+
+        assert_eq!(pattern.origin_at_anchor(IVec2::new(5, 5)), IVec2::new(4, 5));
+        assert_eq!(
+            pattern.origin_at_socket("door", IVec2::new(5, 5)),
+            Some(IVec2::new(3, 4))
+        );
+        assert_eq!(pattern.origin_at_socket("window", IVec2::ZERO), None);
+
+        pattern.rotate90();
+        assert_eq!(pattern.tiles.aabb.max, IVec2::new(1, 2));
+        assert_eq!(texture_at(&pattern, IVec2::new(0, 2)).texture_index, 0);
+        assert_eq!(texture_at(&pattern, IVec2::new(1, 0)).texture_index, 5);
+        assert_eq!(pattern.anchor, IVec2::new(0, 1));
+        assert_eq!(pattern.get_socket("door"), Some(IVec2::new(1, 0)));
+
+        pattern.flip_x();
+        assert_eq!(pattern.tiles.aabb.max, IVec2::new(1, 2));
+        let layer = texture_at(&pattern, IVec2::new(0, 0));
+        assert_eq!(layer.texture_index, 5);
+        assert!(layer.flip.contains(TileFlip::HORIZONTAL));
+        assert_eq!(pattern.anchor, IVec2::new(1, 1));
+
+        pattern.flip_y();
+        let layer = texture_at(&pattern, IVec2::new(0, 2));
+        assert_eq!(layer.texture_index, 5);
+        assert!(layer.flip.contains(TileFlip::BOTH));
+        assert_eq!(pattern.get_socket("door"), Some(IVec2::new(0, 2)));
+    }
+
+    #[test]
+    fn test_pattern_diff() {
+        let tile = |texture_index: i32| {
+            TileBuilder::new().with_layer(0, TileLayer::no_flip(texture_index))
+        };
+        let mut a = TilemapPattern::new(None);
+        a.tiles.set(IVec2::ZERO, tile(0));
+        a.tiles.set(IVec2::X, tile(1));
+        a.tiles.set(IVec2::Y, tile(2));
+        let mut b = a.clone();
+        b.tiles.remove(IVec2::Y);
+        b.tiles.set(IVec2::X, tile(3));
+        b.tiles.set(IVec2::new(2, 0), tile(4));
+
+        let diff = TilemapPattern::diff(&a, &b);
+        assert_eq!(diff.tiles.removed, vec![IVec2::Y]);
+        assert_eq!(diff.tiles.changed[&IVec2::X], tile(3));
+        assert_eq!(diff.tiles.added[&IVec2::new(2, 0)], tile(4));
+        assert_eq!(diff.tiles.len(), 3);
+        assert!(TilemapPattern::diff(&b, &b).is_empty());
+
+        let undo = TilemapPattern::diff(&b, &a);
+        let mut c = a.clone();
+        c.apply_diff(&diff);
+        assert!(TilemapPattern::diff(&c, &b).is_empty());
+        assert_eq!(c.tiles.aabb.max, IVec2::new(2, 0));
+        c.apply_diff(&undo);
+        assert!(TilemapPattern::diff(&c, &a).is_empty());
+    }
+}
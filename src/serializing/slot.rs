@@ -0,0 +1,257 @@
+use std::{
+    io::{Error, ErrorKind},
+    path::{Component, Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{ecs::system::Resource, utils::HashMap};
+use ron::error::SpannedError;
+use serde::{Deserialize, Serialize};
+
+use super::{load_object, save_object, serialize_sorted};
+
+#[cfg(feature = "baking")]
+use crate::render::bake::TilemapExporter;
+
+pub const SLOTS_MANIFEST: &str = "slots.ron";
+pub const THUMBNAIL: &str = "thumbnail.png";
+
+/// The information of a save slot, stored in the manifest of `SaveSlots`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SaveSlotInfo {
+    pub name: String,
+    /// Seconds since the unix epoch.
+    pub created: u64,
+    /// Seconds since the unix epoch.
+    pub modified: u64,
+    /// The total play time in seconds.
+    pub play_time: f64,
+    /// The file name of the thumbnail in the slot folder.
+    #[serde(default)]
+    pub thumbnail: Option<String>,
+    /// Anything the game wants to show in the save menu, like the level or the character name.
+    #[serde(default, serialize_with = "serialize_sorted")]
+    pub metadata: HashMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+struct SlotsManifest {
+    #[serde(serialize_with = "serialize_sorted")]
+    slots: HashMap<String, SaveSlotInfo>,
+}
+
+/// Manages the save slots under a folder. Each slot is a sub folder
+/// and a manifest lists all the slots with their information.
+///
+/// Use `path` as the `path` of the `TilemapSaver` and `TilemapLoader` to save
+/// the tilemaps into a slot, and call `record` after saving.
+/// ```
+/// saves
+/// ├── slots.ron
+/// └── (slot name)
+///     ├── thumbnail.png
+///     └── (your tilemap's name)
+/// ```
+#[derive(Resource, Debug, Clone)]
+pub struct SaveSlots {
+    root: PathBuf,
+    slots: HashMap<String, SaveSlotInfo>,
+}
+
+impl SaveSlots {
+    /// Open the save slots under `root`. Starts with no slots if there's no manifest yet.
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self, SpannedError> {
+        let root = root.into();
+        let slots = if root.join(SLOTS_MANIFEST).exists() {
+            load_object::<SlotsManifest>(&root, SLOTS_MANIFEST)?.slots
+        } else {
+            HashMap::new()
+        };
+        Ok(Self { root, slots })
+    }
+
+    #[inline]
+    pub fn root(&self) -> &PathBuf {
+        &self.root
+    }
+
+    /// The folder of the slot. The slot name is used as the folder name,
+    /// so it must be a single path component, like `slot_1`.
+    ///
+    /// Returns an `InvalidInput` error for empty names, `.`, `..`, absolute paths
+    /// and names with separators.
+    pub fn path(&self, slot: &str) -> std::io::Result<PathBuf> {
+        let mut components = Path::new(slot).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) if !slot.contains(['/', '\\']) => {
+                Ok(self.root.join(slot))
+            }
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Invalid save slot name: {:?}", slot),
+            )),
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, slot: &str) -> Option<&SaveSlotInfo> {
+        self.slots.get(slot)
+    }
+
+    /// All the slots, the most recently modified first.
+    pub fn list(&self) -> Vec<&SaveSlotInfo> {
+        let mut slots = self.slots.values().collect::<Vec<_>>();
+        slots.sort_by(|a, b| b.modified.cmp(&a.modified).then(a.name.cmp(&b.name)));
+        slots
+    }
+
+    /// Create or update the slot after saving into it, and write the manifest.
+    ///
+    /// The metadata is merged into the existing one. Fails if the slot name is invalid,
+    /// see `path`.
+    pub fn record(
+        &mut self,
+        slot: &str,
+        play_time: f64,
+        metadata: impl IntoIterator<Item = (String, String)>,
+    ) -> std::io::Result<&SaveSlotInfo> {
+        let path = self.path(slot)?;
+        let now = now();
+        let info = self
+            .slots
+            .entry(slot.to_string())
+            .or_insert_with(|| SaveSlotInfo {
+                name: slot.to_string(),
+                created: now,
+                modified: now,
+                play_time: 0.,
+                thumbnail: None,
+                metadata: HashMap::new(),
+            });
+        info.modified = now;
+        info.play_time = play_time;
+        info.metadata.extend(metadata);
+        if info.thumbnail.is_none() && path.join(THUMBNAIL).exists() {
+            info.thumbnail = Some(THUMBNAIL.to_string());
+        }
+
+        self.save_manifest();
+        Ok(&self.slots[slot])
+    }
+
+    /// Delete the slot folder and remove it from the manifest.
+    pub fn delete(&mut self, slot: &str) -> std::io::Result<()> {
+        let path = self.path(slot)?;
+        if path.exists() {
+            std::fs::remove_dir_all(path)?;
+        }
+        if self.slots.remove(slot).is_some() {
+            self.save_manifest();
+        }
+        Ok(())
+    }
+
+    /// The path of the thumbnail if the slot has one.
+    pub fn thumbnail_path(&self, slot: &str) -> Option<PathBuf> {
+        let thumbnail = self.get(slot)?.thumbnail.as_ref()?;
+        self.path(slot).ok().map(|path| path.join(thumbnail))
+    }
+
+    /// Insert this on a tilemap to bake it into the thumbnail of the slot.
+    /// The thumbnail will be recorded in the next `record`.
+    #[cfg(feature = "baking")]
+    pub fn thumbnail_exporter(&self, slot: &str) -> std::io::Result<TilemapExporter> {
+        let path = self.path(slot)?;
+        std::fs::create_dir_all(&path)?;
+        Ok(TilemapExporter {
+            path: path.join(THUMBNAIL).to_string_lossy().to_string(),
+        })
+    }
+
+    fn save_manifest(&self) {
+        save_object(
+            &self.root,
+            SLOTS_MANIFEST,
+            &SlotsManifest {
+                slots: self.slots.clone(),
+            },
+        );
+    }
+}
+
+#[inline]
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_save_slots() {
+        let root = std::env::temp_dir().join("entitiles_test_slots");
+        let _ = std::fs::remove_dir_all(&root);
+
+        let mut slots = SaveSlots::new(&root).unwrap();
+        assert!(slots.list().is_empty());
+
+        save_object(&slots.path("a").unwrap(), "tilemap.ron", &0);
+        std::fs::write(slots.path("a").unwrap().join(THUMBNAIL), []).unwrap();
+        slots
+            .record("a", 10., [("level".to_string(), "1".to_string())])
+            .unwrap();
+        slots.record("b", 5., []).unwrap();
+        let info = slots
+            .record("a", 20., [("hero".to_string(), "Ada".to_string())])
+            .unwrap()
+            .clone();
+        assert_eq!(info.play_time, 20.);
+        assert_eq!(info.metadata.len(), 2);
+        assert_eq!(
+            slots.thumbnail_path("a"),
+            Some(root.join("a").join(THUMBNAIL))
+        );
+        assert_eq!(slots.thumbnail_path("b"), None);
+
+        let reopened = SaveSlots::new(&root).unwrap();
+        assert_eq!(reopened.get("a"), Some(&info));
+        assert_eq!(reopened.list().len(), 2);
+
+        slots.delete("a").unwrap();
+        assert!(!slots.path("a").unwrap().exists());
+        let reopened = SaveSlots::new(&root).unwrap();
+        assert_eq!(
+            reopened.list().iter().map(|s| &s.name).collect::<Vec<_>>(),
+            vec!["b"]
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_slot_names() {
+        let root = std::env::temp_dir().join("entitiles_test_invalid_slots");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("kept")).unwrap();
+
+        let mut slots = SaveSlots::new(root.join("saves")).unwrap();
+        assert!(slots.path("slot_1").is_ok());
+        for name in ["", ".", "..", "../kept", "a/b", "a\\b", "/tmp", "a/"] {
+            assert_eq!(
+                slots.path(name).unwrap_err().kind(),
+                ErrorKind::InvalidInput,
+                "{:?}",
+                name
+            );
+            assert!(slots.delete(name).is_err());
+            assert!(slots.record(name, 0., []).is_err());
+        }
+        assert!(root.join("kept").exists());
+        assert!(slots.list().is_empty());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
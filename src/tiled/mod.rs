@@ -23,6 +23,7 @@ use crate::{
             TilePivot, TileRenderSize, TilemapAxisFlip, TilemapName, TilemapSlotSize,
             TilemapStorage, TilemapTransform, TilemapType,
         },
+        marker::{TileMarkers, TileMarkersSource},
     },
     DEFAULT_CHUNK_SIZE,
 };
@@ -186,7 +187,66 @@ fn load_tiled_tilemap(
         )
     });
 
-    commands.entity(map_entity).insert(loaded_map);
+    let mut markers = TileMarkers::new();
+    collect_markers(
+        &tiled_data.xml.layers,
+        &tiled_data.xml.groups,
+        tiled_data,
+        &mut markers,
+    );
+
+    loaded_map.layers.values().for_each(|layer| {
+        commands
+            .entity(*layer)
+            .insert(TileMarkersSource(map_entity));
+    });
+    commands.entity(map_entity).insert((loaded_map, markers));
+}
+
+/// Mark the tiles under the named objects. Only orthogonal and isometric maps are supported.
+fn collect_markers(
+    layers: &[TiledLayer],
+    groups: &[TiledGroup],
+    tiled_data: &PackedTiledTilemap,
+    markers: &mut TileMarkers,
+) {
+    let tile_size = match tiled_data.xml.orientation {
+        MapOrientation::Orthogonal => Vec2::new(
+            tiled_data.xml.tile_width as f32,
+            tiled_data.xml.tile_height as f32,
+        ),
+        // The objects are placed along the axes of the map,
+        // where both sides of the tiles are as long as the tile height.
+        MapOrientation::Isometric => Vec2::splat(tiled_data.xml.tile_height as f32),
+        MapOrientation::Staggered | MapOrientation::Hexagonal => return,
+    };
+
+    layers.iter().for_each(|layer| {
+        let TiledLayer::Objects(layer) = layer else {
+            return;
+        };
+
+        layer
+            .objects
+            .iter()
+            .filter(|obj| !obj.name.is_empty())
+            .for_each(|obj| {
+                // Tile objects are aligned to the bottom left corner.
+                let center = Vec2::new(
+                    obj.x + obj.width / 2.,
+                    if obj.gid.is_some() {
+                        obj.y - obj.height / 2.
+                    } else {
+                        obj.y + obj.height / 2.
+                    },
+                );
+                markers.add(obj.name.clone(), (center / tile_size).floor().as_ivec2());
+            });
+    });
+
+    groups
+        .iter()
+        .for_each(|group| collect_markers(&group.layers, &group.groups, tiled_data, markers));
 }
 
 fn load_group(
@@ -33,7 +33,8 @@ use super::{
     components::{TiledLoader, TiledUnloader},
     sprite::{SpriteUniform, TiledSpriteMaterial},
     xml::{
-        layer::TiledLayer,
+        layer::{decode_gid, TiledLayer},
+        property::PropertyInstance,
         tileset::{TiledTile, TiledTileset},
         MapOrientation, TiledGroup, TiledTilemap,
     },
@@ -62,6 +63,15 @@ pub struct PackedTiledTileset {
     pub texture: TilemapTexture,
 }
 
+impl PackedTiledTileset {
+    /// Get a custom property of a tile in this tileset.
+    pub fn get_tile_property(&self, tile_id: u32, name: &str) -> Option<&PropertyInstance> {
+        self.special_tiles
+            .get(&tile_id)
+            .and_then(|tile| tile.properties.get(name))
+    }
+}
+
 /// A resource that manages tiled tilemaps.
 ///
 /// You can load/unload tiled tilemaps using this resource.
@@ -267,19 +277,17 @@ impl TiledAssets {
                 )
                 .unwrap();
 
-                match tileset_records.entry(tileset_xml.name.clone()) {
-                    Entry::Occupied(e) => {
-                        self.tilemap_tilesets
-                            .entry(map.name.clone())
-                            .or_default()
-                            .push((*e.get(), tileset_def.first_gid));
-                    }
-                    Entry::Vacant(_) => {
-                        self.tilemap_tilesets
-                            .entry(map.name.clone())
-                            .or_default()
-                            .push((self.tilesets.len(), tileset_def.first_gid));
-                    }
+                let index = match tileset_records.entry(tileset_xml.name.clone()) {
+                    Entry::Occupied(e) => *e.get(),
+                    Entry::Vacant(e) => *e.insert(self.tilesets.len()),
+                };
+                self.tilemap_tilesets
+                    .entry(map.name.clone())
+                    .or_default()
+                    .push((index, tileset_def.first_gid));
+                if index < self.tilesets.len() {
+                    // Shared by multiple maps, and already loaded.
+                    return;
                 }
 
                 let source_path = tileset_path
@@ -298,6 +306,9 @@ impl TiledAssets {
                             y: tileset_xml.tile_height,
                         },
                         filter_mode: FilterMode::Nearest,
+                        margin: tileset_xml.margin,
+                        spacing: tileset_xml.spacing,
+                        ..Default::default()
                     },
                     rotation: TilemapRotation::None,
                 };
@@ -672,7 +683,7 @@ impl TiledAssets {
         let mat_ext = objects
             .iter()
             .map(|(object, tint)| {
-                let gid = object.gid.unwrap() & 0x3FFF_FFFF;
+                let gid = decode_gid(object.gid.unwrap()).0;
                 let (tileset, first_gid) = &self.get_tileset(gid, &map.name);
                 (
                     object.id,
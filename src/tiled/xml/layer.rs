@@ -2,6 +2,7 @@ use std::fmt::Formatter;
 
 use bevy::{
     ecs::system::EntityCommands,
+    log::warn,
     math::{IVec2, Vec2, Vec4},
     reflect::Reflect,
     render::color::Color,
@@ -17,7 +18,7 @@ use crate::{
     tilemap::{
         bundles::StandardTilemapBundle,
         coordinates,
-        tile::{RawTileAnimation, TileBuilder, TileFlip, TileLayer},
+        tile::{TileBuilder, TileFlip, TileLayer, TileOffset},
     },
 };
 
@@ -295,7 +296,7 @@ impl Tiles {
                     return None;
                 }
 
-                let texture = *texture;
+                let (texture, flip, rotation) = decode_gid(*texture);
                 let tileset = tileset.unwrap_or_else(|| {
                     let (ts, gid) = tiled_assets.get_tileset(texture, &tiled_data.name);
                     tileset = Some(ts);
@@ -315,18 +316,10 @@ impl Tiles {
                     first_gid
                 );
 
-                let mut tile_id = texture - first_gid;
-                if texture > i32::MAX as u32 {
-                    let flip = texture >> 30;
-                    layer.flip = {
-                        if flip == 3 {
-                            TileFlip::from_bits(flip)
-                        } else {
-                            TileFlip::from_bits(flip ^ 3)
-                        }
-                        .unwrap()
-                    };
-                    tile_id = (texture & 0x3FFF_FFFF) - first_gid;
+                let tile_id = texture - first_gid;
+                layer.flip = flip;
+                if rotation != 0. {
+                    builder = builder.with_offset(TileOffset::new(Vec2::ZERO, rotation));
                 }
 
                 if let Some(anim) = tileset
@@ -334,12 +327,8 @@ impl Tiles {
                     .get(&tile_id)
                     .and_then(|t| t.animation.as_ref())
                 {
-                    builder = builder.with_animation(layer_tilemap.animations.register(
-                        RawTileAnimation {
-                            sequence: anim.frames.iter().map(|f| f.tile_id).collect(),
-                            fps: 1000 / anim.frames[0].duration,
-                        },
-                    ));
+                    builder =
+                        builder.with_animation(layer_tilemap.animations.register(anim.to_raw()));
                 } else {
                     layer.texture_index = tile_id as i32;
                     builder = builder.with_layer(0, layer);
@@ -366,6 +355,41 @@ impl Tiles {
     }
 }
 
+const FLIPPED_HORIZONTALLY: u32 = 1 << 31;
+const FLIPPED_VERTICALLY: u32 = 1 << 30;
+const FLIPPED_DIAGONALLY: u32 = 1 << 29;
+/// Only used on hexagonal maps, where it means a 120 degree rotation.
+/// Not supported, the bit is stripped with a warning.
+const ROTATED_HEXAGONAL_120: u32 = 1 << 28;
+
+/// Split a gid into the actual gid, the flip and the clockwise rotation in radians.
+///
+/// The diagonal flip swaps the x and y axes, which is the same as rotating
+/// 90 degrees clockwise after flipping vertically.
+pub fn decode_gid(gid: u32) -> (u32, TileFlip, f32) {
+    let h = gid & FLIPPED_HORIZONTALLY != 0;
+    let v = gid & FLIPPED_VERTICALLY != 0;
+    if gid & ROTATED_HEXAGONAL_120 != 0 {
+        warn!(
+            "The 120 degree rotation of hexagonal tiles is not supported, gid {} is imported without it.",
+            gid
+        );
+    }
+    let id = gid
+        & !(FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY | ROTATED_HEXAGONAL_120);
+
+    let (h, v, rotation) = if gid & FLIPPED_DIAGONALLY != 0 {
+        (v, !h, -std::f32::consts::FRAC_PI_2)
+    } else {
+        (h, v, 0.)
+    };
+
+    let mut flip = TileFlip::NONE;
+    flip.set(TileFlip::HORIZONTAL, h);
+    flip.set(TileFlip::VERTICAL, v);
+    (id, flip, rotation)
+}
+
 #[derive(Debug, Clone, Reflect, Serialize, Deserialize)]
 pub struct Chunk {
     /// The x coordinate of the chunk in tiles.
@@ -790,6 +814,24 @@ pub struct Image {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_decode_gid() {
+        assert_eq!(decode_gid(5), (5, TileFlip::NONE, 0.));
+        assert_eq!(
+            decode_gid(5 | FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY),
+            (5, TileFlip::BOTH, 0.)
+        );
+        // Rotated 90 degrees clockwise in Tiled.
+        assert_eq!(
+            decode_gid(5 | FLIPPED_HORIZONTALLY | FLIPPED_DIAGONALLY),
+            (5, TileFlip::NONE, -std::f32::consts::FRAC_PI_2)
+        );
+        assert_eq!(
+            decode_gid(5 | FLIPPED_DIAGONALLY | ROTATED_HEXAGONAL_120),
+            (5, TileFlip::VERTICAL, -std::f32::consts::FRAC_PI_2)
+        );
+    }
+
     #[test]
     fn test_deserialize_polygon() {
         let polygon = r#"
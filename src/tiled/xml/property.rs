@@ -12,6 +12,20 @@ pub struct Components {
     pub instances: Vec<ClassInstance>,
 }
 
+/// Plain custom properties, like the members of the class of a tile.
+#[derive(Debug, Default, Clone, Reflect, Serialize, Deserialize)]
+pub struct Properties {
+    #[serde(rename = "property")]
+    #[serde(default)]
+    pub instances: Vec<PropertyInstance>,
+}
+
+impl Properties {
+    pub fn get(&self, name: &str) -> Option<&PropertyInstance> {
+        self.instances.iter().find(|prop| prop.name == name)
+    }
+}
+
 #[derive(Debug, Clone, Reflect, Serialize)]
 pub struct ClassInstance {
     pub name: String,
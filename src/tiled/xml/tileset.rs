@@ -1,6 +1,10 @@
 use bevy::reflect::Reflect;
 use serde::{Deserialize, Serialize};
 
+use crate::tilemap::tile::RawTileAnimation;
+
+use super::property::Properties;
+
 #[derive(Debug, Clone, Reflect, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub struct TiledTileset {
@@ -190,6 +194,10 @@ pub struct TiledTile {
 
     #[serde(default)]
     pub animation: Option<TiledAnimation>,
+
+    /// The custom properties of this tile.
+    #[serde(default)]
+    pub properties: Properties,
 }
 
 #[derive(Debug, Clone, Reflect, Serialize, Deserialize)]
@@ -198,6 +206,58 @@ pub struct TiledAnimation {
     pub frames: Vec<TiledAnimationFrame>,
 }
 
+/// The frame rate of the converted animations won't be higher than this.
+const MAX_ANIMATION_FPS: u32 = 60;
+/// The sequences of the converted animations won't be longer than this,
+/// unless they have more frames.
+const MAX_ANIMATION_SEQUENCE: u32 = 256;
+
+impl TiledAnimation {
+    /// Convert to an animation with a fixed frame rate.
+    ///
+    /// Frames lasting longer than the others are repeated. The durations are kept
+    /// if they are multiples of a duration that divides one second, like 50ms.
+    /// Otherwise they are rounded to the frames of at most 60 fps, and long animations
+    /// have their frame rate lowered to keep the sequence under 256 frames.
+    pub fn to_raw(&self) -> RawTileAnimation {
+        let step = self
+            .frames
+            .iter()
+            .fold(1000, |acc, frame| gcd(acc, frame.duration))
+            .max(1);
+        let total = self
+            .frames
+            .iter()
+            .map(|frame| frame.duration as u64)
+            .sum::<u64>()
+            .max(1);
+        let fps = (1000 / step)
+            .min(MAX_ANIMATION_FPS)
+            .min((MAX_ANIMATION_SEQUENCE as u64 * 1000 / total) as u32)
+            .max(1);
+
+        RawTileAnimation {
+            sequence: self
+                .frames
+                .iter()
+                .flat_map(|frame| {
+                    let repeats = (frame.duration as f64 * fps as f64 / 1000.).round().max(1.);
+                    std::iter::repeat_n(frame.tile_id, repeats as usize)
+                })
+                .collect(),
+            fps,
+        }
+    }
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
 #[derive(Debug, Clone, Reflect, Serialize, Deserialize)]
 pub struct TiledAnimationFrame {
     /// The local ID of a tile within the parent <tileset>.
@@ -209,3 +269,46 @@ pub struct TiledAnimationFrame {
     #[serde(rename = "@duration")]
     pub duration: u32,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_animation_to_raw() {
+        let frame = |tile_id, duration| TiledAnimationFrame { tile_id, duration };
+        let anim = TiledAnimation {
+            frames: vec![frame(3, 100), frame(4, 300), frame(5, 200)],
+        };
+        let raw = anim.to_raw();
+        assert_eq!(raw.fps, 10);
+        assert_eq!(raw.sequence, vec![3, 4, 4, 4, 5, 5]);
+
+        // 1000 is not a multiple of 300.
+        let anim = TiledAnimation {
+            frames: vec![frame(3, 300), frame(4, 600)],
+        };
+        let raw = anim.to_raw();
+        assert_eq!(raw.fps, 10);
+        assert_eq!(raw.sequence, vec![3, 3, 3, 4, 4, 4, 4, 4, 4]);
+    }
+
+    #[test]
+    fn test_uneven_animation_to_raw() {
+        let frame = |tile_id, duration| TiledAnimationFrame { tile_id, duration };
+        // No common duration but 1ms.
+        let anim = TiledAnimation {
+            frames: vec![frame(3, 101), frame(4, 307)],
+        };
+        let raw = anim.to_raw();
+        assert_eq!(raw.fps, 60);
+        assert_eq!(raw.sequence.len(), 6 + 18);
+
+        let anim = TiledAnimation {
+            frames: vec![frame(3, 100_003), frame(4, 7)],
+        };
+        let raw = anim.to_raw();
+        assert_eq!(raw.fps, 2);
+        assert_eq!(raw.sequence.len(), 200 + 1);
+    }
+}
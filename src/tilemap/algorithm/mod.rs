@@ -1,6 +1,11 @@
-use bevy::app::Plugin;
+use bevy::{
+    app::{Plugin, PostUpdate},
+    ecs::schedule::IntoSystemConfigs,
+};
 
-use self::path::{PathTile, PathTilemap};
+use self::path::{PathTile, PathTilemap, PathTilemapGenerator};
+
+use super::despawn;
 
 pub mod path;
 
@@ -8,7 +13,15 @@ pub struct EntiTilesAlgorithmTilemapPlugin;
 
 impl Plugin for EntiTilesAlgorithmTilemapPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_systems(
+            PostUpdate,
+            (path::path_tilemap_generator, path::path_walls_syncer)
+                .chain()
+                .before(despawn::despawn_tiles),
+        );
+
         app.register_type::<PathTilemap>()
-            .register_type::<PathTile>();
+            .register_type::<PathTile>()
+            .register_type::<PathTilemapGenerator>();
     }
 }
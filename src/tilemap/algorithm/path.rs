@@ -1,18 +1,80 @@
-use bevy::{ecs::component::Component, math::IVec2, reflect::Reflect};
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::Changed,
+        removal_detection::RemovedComponents,
+        system::{Query, ResMut},
+    },
+    math::IVec2,
+    reflect::Reflect,
+    utils::{HashMap, HashSet},
+};
 
 use crate::{
+    algorithm::pathfinding::PathTilemaps,
     math::TileArea,
     tilemap::{
         buffers::{PathTileBuffer, Tiles},
         chunking::storage::{ChunkedStorage, PathTileChunkedStorage},
+        map::TilemapStorage,
+        tile::TileTexture,
+        tracker::TileChanges,
+        wall::{TileEdge, TilemapWalls},
     },
 };
 
+bitflags::bitflags! {
+    /// The ways an agent can move, like walking or swimming.
+    ///
+    /// A path tile can be passed by agents with any of its capabilities,
+    /// so one path tilemap serves different kinds of units.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    #[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
+    pub struct PathCapabilities: u32 {
+        const WALK = 1;
+        const SWIM = 1 << 1;
+        const FLY = 1 << 2;
+        const BURROW = 1 << 3;
+    }
+}
+
+impl Default for PathCapabilities {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
 /// A tile for path-finding.
-#[derive(Component, Debug, Clone, Copy, Reflect)]
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
 #[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
 pub struct PathTile {
     pub cost: u32,
+    /// The agents that can pass this tile. Everyone by default.
+    #[cfg_attr(feature = "serializing", serde(default))]
+    #[reflect(ignore)]
+    pub capabilities: PathCapabilities,
+}
+
+impl PathTile {
+    /// Create a tile that can be passed by everyone.
+    pub fn new(cost: u32) -> Self {
+        Self {
+            cost,
+            capabilities: PathCapabilities::all(),
+        }
+    }
+
+    pub fn with_capabilities(mut self, capabilities: PathCapabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Returns `true` if an agent with `capabilities` can pass this tile.
+    #[inline]
+    pub fn is_passable(&self, capabilities: PathCapabilities) -> bool {
+        self.capabilities.intersects(capabilities)
+    }
 }
 
 impl Tiles for PathTile {}
@@ -22,6 +84,12 @@ impl Tiles for PathTile {}
 #[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
 pub struct PathTilemap {
     pub(crate) storage: PathTileChunkedStorage,
+    #[cfg_attr(feature = "serializing", serde(skip))]
+    pub(crate) revision: u32,
+    /// The edges that can't be crossed. Synced from `TilemapWalls`.
+    #[cfg_attr(feature = "serializing", serde(skip))]
+    #[reflect(ignore)]
+    pub(crate) walls: HashSet<TileEdge>,
 }
 
 impl PathTilemap {
@@ -31,6 +99,8 @@ impl PathTilemap {
     pub fn new() -> Self {
         Self {
             storage: ChunkedStorage::default(),
+            revision: 0,
+            walls: HashSet::default(),
         }
     }
 
@@ -38,6 +108,8 @@ impl PathTilemap {
     pub fn new_with_chunk_size(chunk_size: u32) -> Self {
         Self {
             storage: ChunkedStorage::new(chunk_size),
+            revision: 0,
+            walls: HashSet::default(),
         }
     }
 
@@ -45,18 +117,75 @@ impl PathTilemap {
         self.storage.get_elem(index)
     }
 
+    /// Get the tile if an agent with `capabilities` can pass it.
+    #[inline]
+    pub fn get_passable(&self, index: IVec2, capabilities: PathCapabilities) -> Option<&PathTile> {
+        self.get(index)
+            .filter(|tile| tile.is_passable(capabilities))
+    }
+
     pub fn get_mut(&mut self, index: IVec2) -> Option<&mut PathTile> {
+        self.revision = self.revision.wrapping_add(1);
         self.storage.get_elem_mut(index)
     }
 
     pub fn set(&mut self, index: IVec2, tile: PathTile) {
+        self.revision = self.revision.wrapping_add(1);
         self.storage.set_elem(index, tile)
     }
 
     pub fn remove(&mut self, index: IVec2) -> Option<PathTile> {
+        self.revision = self.revision.wrapping_add(1);
         self.storage.remove_elem(index)
     }
 
+    /// Replace the edges that can't be crossed.
+    pub fn set_walls(&mut self, walls: impl IntoIterator<Item = TileEdge>) {
+        self.revision = self.revision.wrapping_add(1);
+        self.walls = walls.into_iter().collect();
+    }
+
+    #[inline]
+    pub fn has_walls(&self) -> bool {
+        !self.walls.is_empty()
+    }
+
+    /// Returns `true` if a wall blocks the move between two neighbours.
+    ///
+    /// Diagonal moves are blocked if both ways around the corner are blocked.
+    pub fn is_blocked(&self, from: IVec2, to: IVec2) -> bool {
+        if self.walls.is_empty() {
+            return false;
+        }
+
+        let crosses = |a: IVec2, b: IVec2| {
+            TileEdge::between(a, b).is_some_and(|edge| self.walls.contains(&edge))
+        };
+        let d = to - from;
+        if d.x != 0 && d.y != 0 {
+            let (x, y) = (from + IVec2::new(d.x, 0), from + IVec2::new(0, d.y));
+            (crosses(from, x) || crosses(x, to)) && (crosses(from, y) || crosses(y, to))
+        } else {
+            crosses(from, to)
+        }
+    }
+
+    /// Increases every time the path tilemap is modified.
+    /// Used to invalidate the cached paths.
+    #[inline]
+    pub fn revision(&self) -> u32 {
+        self.revision
+    }
+
+    /// Returns `true` if all the tiles have the same cost.
+    pub fn is_uniform(&self) -> bool {
+        let mut tiles = self.storage.iter_some();
+        let Some(first) = tiles.next() else {
+            return true;
+        };
+        tiles.all(|tile| tile.cost == first.cost)
+    }
+
     /// Set path-finding data using a custom function.
     pub fn fill_path_rect_custom(
         &mut self,
@@ -89,3 +218,206 @@ impl PathTilemap {
         });
     }
 }
+
+/// Generates the `PathTilemap` of the tilemap from the texture indices of its tiles,
+/// and keeps it in sync when tiles are changed or removed.
+///
+/// The layers of a tile are checked from the top to the bottom, and the first texture
+/// found in the mapping decides the cost. Animated tiles and tiles without any mapped
+/// texture use `default_cost`. A cost of `None` means the tile is impassable.
+/// The same layer decides the `capabilities`, which default to everyone.
+///
+/// The path tilemap is rebuilt from scratch whenever this component changes.
+#[derive(Component, Debug, Clone, Reflect)]
+pub struct PathTilemapGenerator {
+    pub costs: HashMap<i32, Option<u32>>,
+    pub default_cost: Option<u32>,
+    #[reflect(ignore)]
+    pub capabilities: HashMap<i32, PathCapabilities>,
+}
+
+impl PathTilemapGenerator {
+    pub fn new(default_cost: Option<u32>) -> Self {
+        Self {
+            costs: HashMap::default(),
+            default_cost,
+            capabilities: HashMap::default(),
+        }
+    }
+
+    pub fn with_cost(mut self, texture_index: i32, cost: u32) -> Self {
+        self.costs.insert(texture_index, Some(cost));
+        self
+    }
+
+    pub fn with_impassable(mut self, texture_index: i32) -> Self {
+        self.costs.insert(texture_index, None);
+        self
+    }
+
+    /// Only the agents with any of the `capabilities` can pass the tiles with this texture,
+    /// like `PathCapabilities::SWIM` for water.
+    pub fn with_capabilities(mut self, texture_index: i32, capabilities: PathCapabilities) -> Self {
+        self.capabilities.insert(texture_index, capabilities);
+        self
+    }
+
+    /// The path tile of a tile with this texture.
+    pub fn get(&self, texture: &TileTexture) -> Option<PathTile> {
+        let layer = match texture {
+            TileTexture::Static(layers) => layers.iter().rev().find(|layer| {
+                self.costs.contains_key(&layer.texture_index)
+                    || self.capabilities.contains_key(&layer.texture_index)
+            }),
+            TileTexture::Animated(_) => None,
+        };
+        let (cost, capabilities) = match layer {
+            Some(layer) => (
+                self.costs
+                    .get(&layer.texture_index)
+                    .copied()
+                    .unwrap_or(self.default_cost),
+                self.capabilities
+                    .get(&layer.texture_index)
+                    .copied()
+                    .unwrap_or_default(),
+            ),
+            None => (self.default_cost, PathCapabilities::all()),
+        };
+        cost.map(|cost| PathTile::new(cost).with_capabilities(capabilities))
+    }
+}
+
+pub fn path_tilemap_generator(
+    mut path_tilemaps: ResMut<PathTilemaps>,
+    generators_query: Query<(Entity, &PathTilemapGenerator, &TilemapStorage)>,
+    changed_generators_query: Query<Entity, Changed<PathTilemapGenerator>>,
+    tiles: TileChanges,
+) {
+    // Rebuild the path tilemaps whose generators changed.
+    let rebuilt = changed_generators_query.iter().collect::<HashSet<_>>();
+    if !rebuilt.is_empty() {
+        let mut new_path_tilemaps = generators_query
+            .iter()
+            .filter(|(entity, ..)| rebuilt.contains(entity))
+            .map(|(entity, _, storage)| {
+                (
+                    entity,
+                    PathTilemap::new_with_chunk_size(storage.storage.chunk_size),
+                )
+            })
+            .collect::<HashMap<_, _>>();
+
+        tiles.iter_all().for_each(|tile| {
+            let (Some(path_tilemap), Ok((_, generator, _))) = (
+                new_path_tilemaps.get_mut(&tile.tilemap_id),
+                generators_query.get(tile.tilemap_id),
+            ) else {
+                return;
+            };
+            if let Some(path_tile) = generator.get(&tile.texture) {
+                path_tilemap.set(tile.index, path_tile);
+            }
+        });
+
+        new_path_tilemaps
+            .into_iter()
+            .for_each(|(entity, path_tilemap)| path_tilemaps.insert(entity, path_tilemap));
+    }
+
+    // Removals first, as a new tile may be set at the same index in the same frame.
+    tiles.iter_removed().for_each(|tile| {
+        if generators_query.contains(tile.tilemap_id) {
+            path_tilemaps.edit(tile.tilemap_id, |path_tilemap| {
+                path_tilemap.remove(tile.index);
+            });
+        }
+    });
+
+    tiles.iter_changed().for_each(|tile| {
+        if rebuilt.contains(&tile.tilemap_id) {
+            return;
+        }
+        let Ok((_, generator, _)) = generators_query.get(tile.tilemap_id) else {
+            return;
+        };
+        path_tilemaps.edit(tile.tilemap_id, |path_tilemap| {
+            match generator.get(&tile.texture) {
+                Some(path_tile) => path_tilemap.set(tile.index, path_tile),
+                None => {
+                    path_tilemap.remove(tile.index);
+                }
+            }
+        });
+    });
+}
+
+/// Copies the walls of `TilemapWalls` into the `PathTilemap` of the tilemap.
+pub fn path_walls_syncer(
+    mut path_tilemaps: ResMut<PathTilemaps>,
+    walls_query: Query<(Entity, &TilemapWalls)>,
+    changed_walls_query: Query<Entity, Changed<TilemapWalls>>,
+    mut removed: RemovedComponents<TilemapWalls>,
+) {
+    removed.read().for_each(|entity| {
+        path_tilemaps.edit(entity, |path_tilemap| {
+            path_tilemap.set_walls([]);
+        });
+    });
+
+    walls_query.iter().for_each(|(entity, walls)| {
+        // Also check the count, as the path tilemap may be rebuilt without the walls.
+        let changed = changed_walls_query.contains(entity);
+        path_tilemaps.edit(entity, |path_tilemap| {
+            if changed || path_tilemap.walls.len() != walls.len() {
+                path_tilemap.set_walls(walls.iter().map(|(edge, _)| edge));
+            }
+        });
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tilemap::tile::{TileAnimation, TileLayer};
+
+    use super::*;
+
+    #[test]
+    fn test_path_tilemap_generator() {
+        let generator = PathTilemapGenerator::new(Some(1))
+            .with_cost(2, 5)
+            .with_impassable(3);
+        let texture = |layers: &[i32]| {
+            TileTexture::Static(layers.iter().map(|t| TileLayer::no_flip(*t)).collect())
+        };
+
+        assert_eq!(generator.get(&texture(&[0])).map(|t| t.cost), Some(1));
+        assert_eq!(generator.get(&texture(&[2])).map(|t| t.cost), Some(5));
+        assert!(generator.get(&texture(&[3])).is_none());
+        // A bridge over the water.
+        assert_eq!(generator.get(&texture(&[3, 2])).map(|t| t.cost), Some(5));
+        assert!(generator.get(&texture(&[3, 0])).is_none());
+
+        let generator = generator
+            .with_cost(4, 3)
+            .with_capabilities(4, PathCapabilities::SWIM);
+        let water = generator.get(&texture(&[4])).unwrap();
+        assert!(water.is_passable(PathCapabilities::SWIM | PathCapabilities::WALK));
+        assert!(!water.is_passable(PathCapabilities::WALK));
+        assert!(generator
+            .get(&texture(&[4, 2]))
+            .unwrap()
+            .is_passable(PathCapabilities::WALK));
+        assert_eq!(
+            generator
+                .get(&TileTexture::Animated(TileAnimation {
+                    start: 0,
+                    length: 1,
+                    fps: 1,
+                    group: 0,
+                }))
+                .map(|t| t.cost),
+            Some(1)
+        );
+    }
+}
@@ -0,0 +1,608 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::{Event, EventReader},
+        query::Added,
+        reflect::ReflectCommandExt,
+        system::{Commands, Query, Res, Resource},
+    },
+    hierarchy::Parent,
+    math::IVec2,
+    reflect::Reflect,
+    transform::components::GlobalTransform,
+    utils::HashMap,
+};
+
+use super::{
+    coordinates,
+    map::{
+        TilePivot, TilemapAxisFlip, TilemapSlotSize, TilemapStorage, TilemapTransform, TilemapType,
+    },
+    marker::{self, TileMarkers, TileMarkersSource},
+    sensor::TilePosition,
+    tile::{Tile, TileTexture},
+};
+
+pub struct EntiTilesTileBehaviorPlugin;
+
+impl Plugin for EntiTilesTileBehaviorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                spawn_behavior_runner,
+                step_behavior_runner,
+                interact_behavior_runner,
+            ),
+        );
+
+        app.init_resource::<TileBehaviors>();
+
+        app.register_type::<TileBehaviorActivator>()
+            .register_type::<TileInteraction>();
+
+        app.add_event::<TileInteraction>();
+    }
+}
+
+/// What selects the tiles a behavior runs on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TileBehaviorKey {
+    /// Tiles with this texture index on any layer. Animated tiles are ignored.
+    Texture(i32),
+    /// Tiles with this marker in the `TileMarkers` of the tilemap.
+    /// See `marker::find_markers` for where they are looked up.
+    Marker(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TileBehaviorTrigger {
+    /// The tile is spawned, or the `TileMarkers` is inserted.
+    ///
+    /// If the markers are shared by several tilemaps, the behaviors of a marker run once,
+    /// on the first tilemap with a tile at the marked index.
+    Spawned,
+    /// An entity with `TileBehaviorActivator` moved onto the tile.
+    SteppedOn,
+    /// A `TileInteraction` event was sent for the tile.
+    Interacted,
+}
+
+/// Where and why a behavior runs.
+#[derive(Debug, Clone, Copy)]
+pub struct TileBehaviorContext {
+    pub trigger: TileBehaviorTrigger,
+    pub tilemap: Entity,
+    pub index: IVec2,
+    /// The tile entity. Markers can be on tiles that don't exist.
+    pub tile: Option<Entity>,
+    /// The entity that stepped on or interacted with the tile.
+    pub activator: Option<Entity>,
+}
+
+pub type TileBehaviorCallback = Box<dyn Fn(&mut Commands, &TileBehaviorContext) + Send + Sync>;
+
+pub enum TileBehavior {
+    Callback(TileBehaviorCallback),
+    /// Insert clones of the components on the activator, or on the tile if there's none.
+    /// The types must be registered with `#[reflect(Component)]`.
+    Components(Vec<Box<dyn Reflect>>),
+}
+
+impl TileBehavior {
+    fn run(&self, commands: &mut Commands, ctx: &TileBehaviorContext) {
+        match self {
+            TileBehavior::Callback(callback) => callback(commands, ctx),
+            TileBehavior::Components(components) => {
+                let Some(target) = ctx.activator.or(ctx.tile) else {
+                    return;
+                };
+                let mut target = commands.entity(target);
+                components.iter().for_each(|component| {
+                    target.insert_reflect(component.clone_value());
+                });
+            }
+        }
+    }
+}
+
+/// Maps texture indices and markers to the behaviors that run when the tiles
+/// are spawned, stepped on or interacted with.
+///
+/// A lightweight way to add gameplay to tiles, like spikes that hurt when stepped on
+/// or chests that open when interacted with. Behaviors run in `Update`.
+#[derive(Resource, Default)]
+pub struct TileBehaviors {
+    pub(crate) behaviors: HashMap<(TileBehaviorKey, TileBehaviorTrigger), Vec<TileBehavior>>,
+}
+
+impl TileBehaviors {
+    /// Run `callback` when the tiles of `key` are triggered.
+    pub fn on(
+        &mut self,
+        key: TileBehaviorKey,
+        trigger: TileBehaviorTrigger,
+        callback: impl Fn(&mut Commands, &TileBehaviorContext) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.add(key, trigger, TileBehavior::Callback(Box::new(callback)))
+    }
+
+    /// Insert the components when the tiles of `key` are triggered.
+    /// See `TileBehavior::Components`.
+    pub fn insert_on(
+        &mut self,
+        key: TileBehaviorKey,
+        trigger: TileBehaviorTrigger,
+        components: Vec<Box<dyn Reflect>>,
+    ) -> &mut Self {
+        self.add(key, trigger, TileBehavior::Components(components))
+    }
+
+    pub fn add(
+        &mut self,
+        key: TileBehaviorKey,
+        trigger: TileBehaviorTrigger,
+        behavior: TileBehavior,
+    ) -> &mut Self {
+        self.behaviors
+            .entry((key, trigger))
+            .or_default()
+            .push(behavior);
+        self
+    }
+
+    /// Remove all the behaviors of `key` for `trigger`.
+    pub fn remove(&mut self, key: TileBehaviorKey, trigger: TileBehaviorTrigger) {
+        self.behaviors.remove(&(key, trigger));
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.behaviors.is_empty()
+    }
+
+    /// Run the behaviors of all the keys.
+    pub fn run(
+        &self,
+        commands: &mut Commands,
+        keys: impl IntoIterator<Item = TileBehaviorKey>,
+        ctx: &TileBehaviorContext,
+    ) {
+        keys.into_iter().for_each(|key| {
+            if let Some(behaviors) = self.behaviors.get(&(key, ctx.trigger)) {
+                behaviors
+                    .iter()
+                    .for_each(|behavior| behavior.run(commands, ctx));
+            }
+        });
+    }
+}
+
+/// The keys of a tile, texture indices first.
+pub fn tile_behavior_keys(
+    tile: Option<&Tile>,
+    markers: Option<&TileMarkers>,
+    index: IVec2,
+) -> Vec<TileBehaviorKey> {
+    let mut keys = Vec::new();
+    if let Some(TileTexture::Static(layers)) = tile.map(|t| &t.texture) {
+        layers
+            .iter()
+            .filter(|layer| layer.texture_index >= 0)
+            .for_each(|layer| {
+                let key = TileBehaviorKey::Texture(layer.texture_index);
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            });
+    }
+    if let Some(markers) = markers {
+        keys.extend(
+            markers
+                .at(index)
+                .map(|name| TileBehaviorKey::Marker(name.to_string())),
+        );
+    }
+    keys
+}
+
+/// Add this to entities that can step on tiles, like the player.
+///
+/// The position is read from `TilePosition`, or `GlobalTransform` if it doesn't have one,
+/// like `TileTriggerActivator`.
+#[derive(Component, Debug, Default, Clone, Reflect)]
+pub struct TileBehaviorActivator {
+    pub(crate) current: Vec<(Entity, IVec2)>,
+}
+
+impl TileBehaviorActivator {
+    /// The tilemaps and the tiles this entity is on.
+    #[inline]
+    pub fn current(&self) -> &[(Entity, IVec2)] {
+        &self.current
+    }
+}
+
+/// Send this event to run the `Interacted` behaviors of a tile,
+/// like when the player presses a key in front of a chest.
+#[derive(Event, Debug, Clone, Copy, Reflect)]
+pub struct TileInteraction {
+    pub activator: Entity,
+    pub tilemap: Entity,
+    pub index: IVec2,
+}
+
+pub fn spawn_behavior_runner(
+    mut commands: Commands,
+    tiles_query: Query<(Entity, &Tile), Added<Tile>>,
+    added_markers_query: Query<(Entity, &TileMarkers), Added<TileMarkers>>,
+    tilemaps_query: Query<(Entity, &TilemapStorage)>,
+    markers_query: Query<&TileMarkers>,
+    sources_query: Query<(Option<&TileMarkersSource>, Option<&Parent>)>,
+    behaviors: Res<TileBehaviors>,
+) {
+    if behaviors.is_empty() {
+        return;
+    }
+
+    tiles_query.iter().for_each(|(entity, tile)| {
+        behaviors.run(
+            &mut commands,
+            tile_behavior_keys(Some(tile), None, tile.index),
+            &TileBehaviorContext {
+                trigger: TileBehaviorTrigger::Spawned,
+                tilemap: tile.tilemap_id,
+                index: tile.index,
+                tile: Some(entity),
+                activator: None,
+            },
+        );
+    });
+
+    added_markers_query.iter().for_each(|(holder, markers)| {
+        let tilemaps = tilemaps_query
+            .iter()
+            .filter(|(tilemap, _)| {
+                marker::find_markers(*tilemap, &markers_query, &sources_query)
+                    .is_some_and(|(e, _)| e == holder)
+            })
+            .collect::<Vec<_>>();
+        let Some(&(first, _)) = tilemaps.first() else {
+            return;
+        };
+
+        markers.markers.iter().for_each(|(name, indices)| {
+            indices.iter().for_each(|index| {
+                let (tilemap, tile) = tilemaps
+                    .iter()
+                    .find_map(|(tilemap, storage)| {
+                        storage.get(*index).map(|tile| (*tilemap, Some(tile)))
+                    })
+                    .unwrap_or((first, None));
+                behaviors.run(
+                    &mut commands,
+                    [TileBehaviorKey::Marker(name.clone())],
+                    &TileBehaviorContext {
+                        trigger: TileBehaviorTrigger::Spawned,
+                        tilemap,
+                        index: *index,
+                        tile,
+                        activator: None,
+                    },
+                );
+            });
+        });
+    });
+}
+
+pub fn step_behavior_runner(
+    mut commands: Commands,
+    mut activators_query: Query<(
+        Entity,
+        Option<&TilePosition>,
+        Option<&GlobalTransform>,
+        &mut TileBehaviorActivator,
+    )>,
+    tilemaps_query: Query<(
+        Entity,
+        &TilemapStorage,
+        &TilemapType,
+        &TilemapTransform,
+        &TilePivot,
+        &TilemapSlotSize,
+        &TilemapAxisFlip,
+    )>,
+    tiles_query: Query<&Tile>,
+    markers_query: Query<&TileMarkers>,
+    sources_query: Query<(Option<&TileMarkersSource>, Option<&Parent>)>,
+    behaviors: Res<TileBehaviors>,
+) {
+    activators_query
+        .iter_mut()
+        .for_each(|(entity, position, transform, mut activator)| {
+            let current = match (position, transform) {
+                (Some(position), _) => vec![(position.tilemap, position.index)],
+                (None, Some(transform)) => {
+                    let world = transform.translation().truncate();
+                    tilemaps_query
+                        .iter()
+                        .map(
+                            |(tilemap, _, ty, tilemap_transform, pivot, slot_size, axis_flip)| {
+                                let index = coordinates::world_to_index_flipped(
+                                    world,
+                                    *ty,
+                                    tilemap_transform,
+                                    pivot.0,
+                                    slot_size.0,
+                                    *axis_flip,
+                                );
+                                (tilemap, index)
+                            },
+                        )
+                        .collect()
+                }
+                (None, None) => Vec::new(),
+            };
+
+            if current == activator.current {
+                return;
+            }
+
+            // The markers shared by several tilemaps only run once.
+            let mut stepped_markers = Vec::new();
+            current
+                .iter()
+                .filter(|c| !activator.current.contains(c))
+                .for_each(|&(tilemap, index)| {
+                    let Ok((_, storage, ..)) = tilemaps_query.get(tilemap) else {
+                        return;
+                    };
+                    let markers = marker::find_markers(tilemap, &markers_query, &sources_query)
+                        .filter(|(holder, _)| {
+                            if stepped_markers.contains(&(*holder, index)) {
+                                return false;
+                            }
+                            stepped_markers.push((*holder, index));
+                            true
+                        })
+                        .map(|(_, markers)| markers);
+                    let tile = storage.get(index);
+                    behaviors.run(
+                        &mut commands,
+                        tile_behavior_keys(
+                            tile.and_then(|t| tiles_query.get(t).ok()),
+                            markers,
+                            index,
+                        ),
+                        &TileBehaviorContext {
+                            trigger: TileBehaviorTrigger::SteppedOn,
+                            tilemap,
+                            index,
+                            tile,
+                            activator: Some(entity),
+                        },
+                    );
+                });
+            activator.current = current;
+        });
+}
+
+pub fn interact_behavior_runner(
+    mut commands: Commands,
+    mut interactions: EventReader<TileInteraction>,
+    tilemaps_query: Query<&TilemapStorage>,
+    tiles_query: Query<&Tile>,
+    markers_query: Query<&TileMarkers>,
+    sources_query: Query<(Option<&TileMarkersSource>, Option<&Parent>)>,
+    behaviors: Res<TileBehaviors>,
+) {
+    interactions.read().for_each(|interaction| {
+        let Ok(storage) = tilemaps_query.get(interaction.tilemap) else {
+            return;
+        };
+        let markers = marker::find_markers(interaction.tilemap, &markers_query, &sources_query);
+        let tile = storage.get(interaction.index);
+        behaviors.run(
+            &mut commands,
+            tile_behavior_keys(
+                tile.and_then(|t| tiles_query.get(t).ok()),
+                markers.map(|(_, markers)| markers),
+                interaction.index,
+            ),
+            &TileBehaviorContext {
+                trigger: TileBehaviorTrigger::Interacted,
+                tilemap: interaction.tilemap,
+                index: interaction.index,
+                tile,
+                activator: Some(interaction.activator),
+            },
+        );
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::{ecs::reflect::ReflectComponent, math::Vec2, render::color::Color};
+
+    use crate::tilemap::tile::{TileLayer, TileOffset};
+
+    use super::*;
+
+    #[derive(Component, Reflect, Default)]
+    #[reflect(Component)]
+    struct Burning;
+
+    #[derive(Component)]
+    struct Opened;
+
+    #[test]
+    fn test_tile_behaviors() {
+        let mut app = App::new();
+        app.add_plugins(EntiTilesTileBehaviorPlugin)
+            .register_type::<Burning>();
+
+        let mut behaviors = app.world.resource_mut::<TileBehaviors>();
+        behaviors
+            .insert_on(
+                TileBehaviorKey::Texture(3),
+                TileBehaviorTrigger::SteppedOn,
+                vec![Box::new(Burning)],
+            )
+            .on(
+                TileBehaviorKey::Marker("chest".to_string()),
+                TileBehaviorTrigger::Interacted,
+                |commands, ctx| {
+                    commands.entity(ctx.tile.unwrap()).insert(Opened);
+                },
+            )
+            .on(
+                TileBehaviorKey::Marker("chest".to_string()),
+                TileBehaviorTrigger::Spawned,
+                |commands, ctx| {
+                    commands.entity(ctx.tile.unwrap()).insert(Burning);
+                },
+            );
+
+        let tilemap = app.world.spawn_empty().id();
+        let mut storage = TilemapStorage::new(16, tilemap);
+        let mut spawn_tile = |index: IVec2, texture_index: i32| {
+            let tile = app
+                .world
+                .spawn(Tile {
+                    tilemap_id: tilemap,
+                    chunk_index: IVec2::ZERO,
+                    in_chunk_index: 0,
+                    index,
+                    texture: TileTexture::Static(vec![TileLayer::no_flip(texture_index)]),
+                    tint: Color::WHITE,
+                    offset: TileOffset::default(),
+                })
+                .id();
+            storage.storage.set_elem(index, tile);
+            tile
+        };
+        let spikes = spawn_tile(IVec2::new(1, 0), 3);
+        let chest = spawn_tile(IVec2::new(2, 0), 5);
+        app.world.entity_mut(tilemap).insert((
+            storage,
+            TileMarkers::new().with_marker("chest", IVec2::new(2, 0)),
+            TilemapType::Square,
+            TilemapTransform::default(),
+            TilePivot::default(),
+            TilemapSlotSize::default(),
+            TilemapAxisFlip::NONE,
+        ));
+        let player = app
+            .world
+            .spawn((
+                TileBehaviorActivator::default(),
+                TilePosition {
+                    tilemap,
+                    index: IVec2::ZERO,
+                },
+            ))
+            .id();
+        app.update();
+        assert!(app.world.get::<Burning>(chest).is_some());
+        assert!(app.world.get::<Burning>(player).is_none());
+
+        app.world.get_mut::<TilePosition>(player).unwrap().index = IVec2::new(1, 0);
+        app.update();
+        assert!(app.world.get::<Burning>(player).is_some());
+        assert!(app.world.get::<Burning>(spikes).is_none());
+
+        app.world.send_event(TileInteraction {
+            activator: player,
+            tilemap,
+            index: IVec2::new(2, 0),
+        });
+        app.update();
+        assert!(app.world.get::<Opened>(chest).is_some());
+    }
+
+    #[derive(Component)]
+    struct Looted;
+
+    #[test]
+    fn test_shared_markers() {
+        let mut app = App::new();
+        app.add_plugins(EntiTilesTileBehaviorPlugin);
+
+        app.world
+            .resource_mut::<TileBehaviors>()
+            .on(
+                TileBehaviorKey::Marker("chest".to_string()),
+                TileBehaviorTrigger::Spawned,
+                |commands, ctx| {
+                    commands.entity(ctx.tile.unwrap()).insert(Opened);
+                },
+            )
+            .on(
+                TileBehaviorKey::Marker("chest".to_string()),
+                TileBehaviorTrigger::SteppedOn,
+                |commands, ctx| {
+                    commands.entity(ctx.activator.unwrap()).insert(Looted);
+                },
+            );
+
+        // Like the layers of a LDtk level, with the markers on the level entity.
+        let level = app
+            .world
+            .spawn(TileMarkers::new().with_marker("chest", IVec2::new(1, 1)))
+            .id();
+        let mut spawn_layer = |with_tile: bool| {
+            let tilemap = app.world.spawn_empty().id();
+            let mut storage = TilemapStorage::new(16, tilemap);
+            let tile = with_tile.then(|| {
+                let tile = app
+                    .world
+                    .spawn(Tile {
+                        tilemap_id: tilemap,
+                        chunk_index: IVec2::ZERO,
+                        in_chunk_index: 0,
+                        index: IVec2::new(1, 1),
+                        texture: TileTexture::Static(vec![TileLayer::no_flip(0)]),
+                        tint: Color::WHITE,
+                        offset: TileOffset::default(),
+                    })
+                    .id();
+                storage.storage.set_elem(IVec2::new(1, 1), tile);
+                tile
+            });
+            app.world.entity_mut(tilemap).insert((
+                storage,
+                TileMarkersSource(level),
+                TilemapType::Square,
+                TilemapTransform::default(),
+                TilePivot::default(),
+                TilemapSlotSize(Vec2::splat(16.)),
+                TilemapAxisFlip::Y,
+            ));
+            tile
+        };
+        spawn_layer(false);
+        let chest = spawn_layer(true).unwrap();
+
+        // The y axis is flipped, so (1, 1) is in the second row under the origin.
+        let player = app
+            .world
+            .spawn((
+                TileBehaviorActivator::default(),
+                GlobalTransform::from_xyz(24., -24., 0.),
+            ))
+            .id();
+        app.update();
+        assert!(app.world.get::<Opened>(chest).is_some());
+        assert!(app.world.get::<Looted>(player).is_some());
+        assert_eq!(
+            app.world
+                .get::<TileBehaviorActivator>(player)
+                .unwrap()
+                .current
+                .len(),
+            2
+        );
+    }
+}
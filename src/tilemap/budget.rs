@@ -0,0 +1,247 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        entity::{Entities, Entity, EntityHashMap},
+        system::{Commands, Query, Res, ResMut, Resource},
+        world::World,
+    },
+    math::IVec2,
+    reflect::Reflect,
+    utils::Instant,
+};
+
+use crate::math::TileArea;
+
+use super::{
+    buffers::TileBuilderBuffer,
+    map::TilemapStorage,
+    tile::{Tile, TileBuilder},
+};
+
+pub struct EntiTilesTileBudgetPlugin;
+
+impl Plugin for EntiTilesTileBudgetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, budgeted_tile_spawner);
+
+        app.init_resource::<TileSpawnBudget>()
+            .init_resource::<TileSpawnQueue>();
+
+        app.register_type::<TileSpawnBudget>();
+    }
+}
+
+/// Limits how many tiles in `TileSpawnQueue` are spawned every frame.
+///
+/// The bulk operations of `TilemapStorage`, like `fill_rect` and `fill_with_buffer`,
+/// the built-in tilemap loader and the generators will also push their tiles
+/// into the queue instead of spawning them at once if the budget is limited.
+#[derive(Resource, Default, Debug, Clone, Copy, Reflect)]
+pub struct TileSpawnBudget {
+    /// The maximum amount of tiles to spawn per frame.
+    pub max_per_frame: Option<usize>,
+    /// The maximum time in microseconds to spend on spawning tiles per frame.
+    pub max_micros: Option<u64>,
+}
+
+impl TileSpawnBudget {
+    #[inline]
+    pub fn is_limited(&self) -> bool {
+        self.max_per_frame.is_some() || self.max_micros.is_some()
+    }
+
+    #[inline]
+    fn is_exhausted(&self, spawned: usize, start: Instant) -> bool {
+        self.max_per_frame.is_some_and(|max| spawned >= max)
+            || self
+                .max_micros
+                .is_some_and(|max| start.elapsed().as_micros() >= max as u128)
+    }
+}
+
+/// Tiles waiting to be spawned according to `TileSpawnBudget`.
+///
+/// Tiles are spawned in the order they are queued. If the tilemap
+/// doesn't have a `TilemapStorage` yet, they will wait until it has one.
+#[derive(Resource, Default)]
+pub struct TileSpawnQueue(pub(crate) EntityHashMap<VecDeque<(IVec2, TileBuilder)>>);
+
+impl TileSpawnQueue {
+    #[inline]
+    pub fn set(&mut self, tilemap: Entity, index: IVec2, tile_builder: TileBuilder) {
+        self.0
+            .entry(tilemap)
+            .or_default()
+            .push_back((index, tile_builder));
+    }
+
+    pub fn fill_rect(&mut self, tilemap: Entity, area: TileArea, tile_builder: TileBuilder) {
+        let queue = self.0.entry(tilemap).or_default();
+        queue.reserve(area.size());
+        for y in area.origin.y..=area.dest.y {
+            for x in area.origin.x..=area.dest.x {
+                queue.push_back((IVec2 { x, y }, tile_builder.clone()));
+            }
+        }
+    }
+
+    /// Set `relative_index` to true if your function takes index relative to the area origin.
+    pub fn fill_rect_custom(
+        &mut self,
+        tilemap: Entity,
+        area: TileArea,
+        mut tile_builder: impl FnMut(IVec2) -> Option<TileBuilder>,
+        relative_index: bool,
+    ) {
+        let queue = self.0.entry(tilemap).or_default();
+        for y in area.origin.y..=area.dest.y {
+            for x in area.origin.x..=area.dest.x {
+                let index = IVec2 { x, y };
+                if let Some(builder) = tile_builder(if relative_index {
+                    index - area.origin
+                } else {
+                    index
+                }) {
+                    queue.push_back((index, builder));
+                }
+            }
+        }
+    }
+
+    pub fn fill_with_buffer(&mut self, tilemap: Entity, origin: IVec2, buffer: TileBuilderBuffer) {
+        self.0.entry(tilemap).or_default().extend(
+            buffer
+                .tiles
+                .into_iter()
+                .map(|(index, builder)| (index + origin, builder)),
+        );
+    }
+
+    /// Returns the tiles that are still waiting to be spawned on the tilemap,
+    /// keyed by the entities reserved for them in the storage.
+    ///
+    /// These entities don't have the `Tile` component yet, so use this to look up
+    /// what they will become. If an index is queued more than once, the last one wins.
+    pub fn queued_tiles(&self, storage: &TilemapStorage) -> EntityHashMap<&TileBuilder> {
+        self.0
+            .get(&storage.tilemap)
+            .map(|queue| {
+                queue
+                    .iter()
+                    .filter_map(|(index, builder)| storage.get(*index).map(|e| (e, builder)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the amount of tiles that are still waiting to be spawned on the tilemap.
+    #[inline]
+    pub fn remaining(&self, tilemap: Entity) -> usize {
+        self.0.get(&tilemap).map(|q| q.len()).unwrap_or_default()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.values().all(|q| q.is_empty())
+    }
+}
+
+/// Insert the tiles at once, or queue them if the `TileSpawnBudget` is limited.
+///
+/// The entities are already reserved in the storage, so the queued tiles reuse them.
+pub(crate) fn insert_tiles(commands: &mut Commands, tilemap: Entity, batch: Vec<(Entity, Tile)>) {
+    commands.add(move |world: &mut World| {
+        let limited = world
+            .get_resource::<TileSpawnBudget>()
+            .is_some_and(|budget| budget.is_limited());
+        if limited {
+            if let Some(mut queue) = world.get_resource_mut::<TileSpawnQueue>() {
+                queue
+                    .0
+                    .entry(tilemap)
+                    .or_default()
+                    .extend(batch.into_iter().map(|(_, tile)| (tile.index, tile.into())));
+                return;
+            }
+        }
+
+        // The entities are reserved by the storage.
+        let _ = world.insert_or_spawn_batch(batch);
+    });
+}
+
+pub fn budgeted_tile_spawner(
+    mut commands: Commands,
+    mut tilemaps_query: Query<&mut TilemapStorage>,
+    mut queue: ResMut<TileSpawnQueue>,
+    budget: Res<TileSpawnBudget>,
+    entities: &Entities,
+) {
+    if queue.0.is_empty() {
+        return;
+    }
+
+    let start = Instant::now();
+    let mut spawned = 0;
+
+    for (tilemap, tiles) in queue.0.iter_mut() {
+        let Ok(mut storage) = tilemaps_query.get_mut(*tilemap) else {
+            continue;
+        };
+
+        let mut batch = Vec::new();
+        while !budget.is_exhausted(spawned, start) {
+            let Some((index, builder)) = tiles.pop_front() else {
+                break;
+            };
+
+            let tile = builder.build_component(index, &storage, *tilemap);
+            let entity = storage.get(index).unwrap_or_else(|| {
+                let e = storage.spawn_tile_entity(&mut commands);
+                storage.set_entity(index, Some(e));
+                e
+            });
+            batch.push((entity, tile));
+            spawned += 1;
+        }
+        commands.insert_or_spawn_batch(batch);
+
+        if budget.is_exhausted(spawned, start) {
+            break;
+        }
+    }
+
+    queue
+        .0
+        .retain(|tilemap, tiles| !tiles.is_empty() && entities.contains(*tilemap));
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tilemap::tile::TileLayer;
+
+    use super::*;
+
+    #[test]
+    fn test_queued_tiles() {
+        let tilemap = Entity::from_raw(0);
+        let mut storage = TilemapStorage::new(4, tilemap);
+        storage.set_entity(IVec2::ZERO, Some(Entity::from_raw(1)));
+        storage.set_entity(IVec2::X, Some(Entity::from_raw(2)));
+
+        let mut queue = TileSpawnQueue::default();
+        let first = TileBuilder::new().with_layer(0, TileLayer::no_flip(1));
+        let second = TileBuilder::new().with_layer(0, TileLayer::no_flip(2));
+        queue.set(tilemap, IVec2::ZERO, first);
+        queue.set(tilemap, IVec2::ZERO, second.clone());
+        // Not reserved in the storage.
+        queue.set(tilemap, IVec2::Y, second.clone());
+
+        let queued = queue.queued_tiles(&storage);
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued.get(&Entity::from_raw(1)), Some(&&second));
+        assert!(queue.queued_tiles(&TilemapStorage::new(4, Entity::from_raw(9))).is_empty());
+    }
+}
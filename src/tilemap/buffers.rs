@@ -1,6 +1,11 @@
-use std::fmt::Debug;
+use std::{borrow::Cow, fmt::Debug};
 
-use bevy::{math::IVec2, reflect::Reflect, utils::HashMap};
+use bevy::{
+    math::{IVec2, UVec2},
+    reflect::Reflect,
+    render::{color::Color, render_resource::TextureFormat, texture::Image},
+    utils::HashMap,
+};
 
 use crate::math::aabb::IAabb2d;
 
@@ -13,19 +18,33 @@ pub type ColorTileBuffer = TileBuffer<Tile>;
 pub type TileBuilderBuffer = TileBuffer<TileBuilder>;
 #[cfg(feature = "algorithm")]
 pub type PathTileBuffer = TileBuffer<super::algorithm::path::PathTile>;
-#[cfg(feature = "physics")]
+#[cfg(feature = "physics_core")]
 pub type PhysicsTileBuffer = TileBuffer<super::physics::PhysicsTile>;
-#[cfg(feature = "physics")]
+#[cfg(feature = "physics_core")]
 pub type PackedPhysicsTileBuffer = TileBuffer<super::physics::PackedPhysicsTile>;
 
 /// A buffer of tiles.
 #[derive(Debug, Clone, Reflect)]
 #[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serializing",
+    serde(bound(serialize = "T: serde::Serialize"))
+)]
 pub struct TileBuffer<T: Tiles> {
+    #[cfg_attr(
+        feature = "serializing",
+        serde(serialize_with = "crate::serializing::serialize_sorted")
+    )]
     pub(crate) tiles: HashMap<IVec2, T>,
     pub(crate) aabb: IAabb2d,
 }
 
+impl<T: Tiles> Default for TileBuffer<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T: Tiles> TileBuffer<T> {
     pub fn new() -> Self {
         Self {
@@ -77,3 +96,236 @@ impl<T: Tiles> TileBuffer<T> {
         self.aabb
     }
 }
+
+impl TileBuilderBuffer {
+    /// Create a buffer from an image, where each pixel is mapped to a tile using the palette.
+    /// Useful for blocking out levels in any paint program.
+    ///
+    /// The bottom left pixel is at `(0, 0)`. Pixels with colors not in the palette are skipped,
+    /// and the colors are compared by their 8 bit rgba values.
+    ///
+    /// Images that are not `Rgba8` are converted with `Image::convert` first. Returns `None`
+    /// if that's not possible, like for compressed or floating point formats.
+    ///
+    /// The buffer starts at `(0, 0)`, use `TilemapStorage::fill_with_buffer` to apply it
+    /// anywhere on the tilemap.
+    pub fn from_image(
+        image: &Image,
+        palette: impl IntoIterator<Item = (Color, TileBuilder)>,
+    ) -> Option<Self> {
+        let image = match image.texture_descriptor.format {
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => Cow::Borrowed(image),
+            _ => Cow::Owned(image.convert(TextureFormat::Rgba8UnormSrgb)?),
+        };
+
+        // Colors can't be hashed, so compare the bytes instead.
+        let palette = palette
+            .into_iter()
+            .map(|(color, tile)| (color.as_rgba_u8(), tile))
+            .collect::<HashMap<_, _>>();
+        let size = image.size();
+        let mut buffer = Self::new();
+
+        image
+            .data
+            .chunks_exact(4)
+            .enumerate()
+            .for_each(|(i, pixel)| {
+                let Some(tile) = palette.get(pixel) else {
+                    return;
+                };
+                let (x, y) = (i as u32 % size.x, i as u32 / size.x);
+                buffer.set(IVec2::new(x as i32, (size.y - y - 1) as i32), tile.clone());
+            });
+
+        Some(buffer)
+    }
+}
+
+/// A dense grid of colors for updating pure color tilemaps in bulk,
+/// like heatmaps or board games.
+///
+/// The colors are packed into 4 bytes of srgba each, plus one bit
+/// telling if the color is set.
+///
+/// Use `TilemapStorage::set_colors` to apply it.
+#[derive(Debug, Clone, Reflect)]
+pub struct PureColorTileBuffer {
+    pub(crate) origin: IVec2,
+    pub(crate) size: UVec2,
+    pub(crate) colors: Vec<[u8; 4]>,
+    pub(crate) mask: Vec<u64>,
+}
+
+impl PureColorTileBuffer {
+    /// Create an empty buffer covering `size` tiles starting from `origin`.
+    pub fn new(origin: IVec2, size: UVec2) -> Self {
+        let len = (size.x * size.y) as usize;
+        Self {
+            origin,
+            size,
+            colors: vec![[0; 4]; len],
+            mask: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    /// Create a buffer from colors in row-major order.
+    pub fn from_colors(origin: IVec2, size: UVec2, colors: Vec<Color>) -> Self {
+        assert_eq!(
+            colors.len(),
+            (size.x * size.y) as usize,
+            "The amount of colors doesn't match the size of the buffer!"
+        );
+        let mut buffer = Self::new(origin, size);
+        colors.into_iter().enumerate().for_each(|(i, color)| {
+            buffer.colors[i] = color.as_rgba_u8();
+            buffer.mask[i / 64] |= 1 << (i % 64);
+        });
+        buffer
+    }
+
+    #[inline]
+    fn linear_index(&self, index: IVec2) -> Option<usize> {
+        let rel = index - self.origin;
+        if rel.x < 0 || rel.y < 0 || rel.x >= self.size.x as i32 || rel.y >= self.size.y as i32 {
+            None
+        } else {
+            Some((rel.y * self.size.x as i32 + rel.x) as usize)
+        }
+    }
+
+    #[inline]
+    fn is_set(&self, i: usize) -> bool {
+        self.mask[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    #[inline]
+    fn color_at(&self, i: usize) -> Color {
+        let [r, g, b, a] = self.colors[i];
+        Color::rgba_u8(r, g, b, a)
+    }
+
+    /// Set the color at the given index. Indices out of the buffer are ignored.
+    #[inline]
+    pub fn set(&mut self, index: IVec2, color: Color) {
+        if let Some(i) = self.linear_index(index) {
+            self.colors[i] = color.as_rgba_u8();
+            self.mask[i / 64] |= 1 << (i % 64);
+        }
+    }
+
+    /// Unset the color, the tile will be left unchanged when applying.
+    #[inline]
+    pub fn remove(&mut self, index: IVec2) {
+        if let Some(i) = self.linear_index(index) {
+            self.mask[i / 64] &= !(1 << (i % 64));
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, index: IVec2) -> Option<Color> {
+        self.linear_index(index)
+            .filter(|i| self.is_set(*i))
+            .map(|i| self.color_at(i))
+    }
+
+    /// Iterate over all the colors that are set.
+    pub fn iter(&self) -> impl Iterator<Item = (IVec2, Color)> + '_ {
+        (0..self.colors.len()).filter(|i| self.is_set(*i)).map(|i| {
+            (
+                self.origin
+                    + IVec2::new(
+                        (i as u32 % self.size.x) as i32,
+                        (i as u32 / self.size.x) as i32,
+                    ),
+                self.color_at(i),
+            )
+        })
+    }
+
+    #[inline]
+    pub fn aabb(&self) -> IAabb2d {
+        IAabb2d {
+            min: self.origin,
+            max: self.origin + self.size.as_ivec2() - 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pure_color_buffer() {
+        let mut buffer = PureColorTileBuffer::new(IVec2::new(-2, 3), UVec2::new(3, 2));
+        buffer.set(IVec2::new(-1, 4), Color::RED);
+        buffer.set(IVec2::new(5, 5), Color::BLUE);
+
+        assert_eq!(buffer.get(IVec2::new(-1, 4)), Some(Color::RED));
+        assert_eq!(buffer.get(IVec2::new(-2, 3)), None);
+        assert_eq!(
+            buffer.iter().collect::<Vec<_>>(),
+            vec![(IVec2::new(-1, 4), Color::RED)]
+        );
+        assert_eq!(buffer.aabb().max, IVec2::new(0, 4));
+
+        buffer.remove(IVec2::new(-1, 4));
+        assert_eq!(buffer.iter().count(), 0);
+
+        let buffer = PureColorTileBuffer::from_colors(
+            IVec2::ZERO,
+            UVec2::new(13, 5),
+            vec![Color::GREEN; 65],
+        );
+        assert_eq!(buffer.iter().count(), 65);
+        assert_eq!(buffer.get(IVec2::new(12, 4)), Some(Color::GREEN));
+    }
+
+    #[test]
+    fn test_buffer_from_image() {
+        use bevy::render::{
+            render_asset::RenderAssetUsages,
+            render_resource::{Extent3d, TextureDimension},
+        };
+
+        // The top row is red and blue, the bottom row is red and transparent.
+        let image = Image::new(
+            Extent3d {
+                width: 2,
+                height: 2,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            [[255, 0, 0, 255], [0, 0, 255, 255], [255, 0, 0, 255], [0; 4]].concat(),
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::all(),
+        );
+        let wall = TileBuilder::new().with_tint(Color::GRAY);
+        let buffer = TileBuilderBuffer::from_image(
+            &image,
+            [(Color::RED, wall), (Color::BLUE, TileBuilder::new())],
+        )
+        .unwrap();
+
+        assert_eq!(buffer.tiles.len(), 3);
+        assert_eq!(buffer.get(IVec2::new(0, 0)).unwrap().tint, Color::GRAY);
+        assert_eq!(buffer.get(IVec2::new(1, 1)).unwrap().tint, Color::WHITE);
+        assert!(buffer.get(IVec2::new(1, 0)).is_none());
+
+        // Other formats are converted.
+        let bgra = Image::new(
+            image.texture_descriptor.size,
+            TextureDimension::D2,
+            [[0, 0, 255, 255], [255, 0, 0, 255], [0, 0, 255, 255], [0; 4]].concat(),
+            TextureFormat::Bgra8UnormSrgb,
+            RenderAssetUsages::all(),
+        );
+        let converted = TileBuilderBuffer::from_image(
+            &bgra,
+            [(Color::RED, TileBuilder::new()), (Color::BLUE, TileBuilder::new())],
+        )
+        .unwrap();
+        assert_eq!(converted.tiles.len(), 3);
+    }
+}
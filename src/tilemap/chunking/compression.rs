@@ -0,0 +1,105 @@
+use bevy::{
+    ecs::{change_detection::DetectChangesMut, component::Component, system::Query},
+    reflect::Reflect,
+};
+
+use crate::tilemap::{sensor::TileSensors, zone::TileZones};
+
+use super::storage::ChunkCompressionConfig;
+
+/// Compresses the cold chunks of the data layers on the tilemap, like `TileZones`
+/// and `TileSensors`, trading CPU for memory on giant maps.
+///
+/// With the `serializing` feature, the copied tiles of `TilemapTileMirror` are
+/// compressed as well.
+///
+/// `ChunkCompressionConfig::cold_after` is in frames.
+#[derive(Component, Debug, Default, Clone, Copy, Reflect)]
+pub struct TilemapChunkCompression(pub ChunkCompressionConfig);
+
+pub fn chunk_compressor(
+    mut tilemaps_query: Query<(
+        &TilemapChunkCompression,
+        Option<&mut TileZones>,
+        Option<&mut TileSensors>,
+    )>,
+    #[cfg(feature = "serializing")] mut mirrors_query: Query<(
+        &TilemapChunkCompression,
+        &mut crate::serializing::map::TilemapTileMirror,
+    )>,
+) {
+    tilemaps_query
+        .par_iter_mut()
+        .for_each(|(compression, zones, sensors)| {
+            // Compression is invisible to users, so don't trigger change detection.
+            if let Some(mut zones) = zones {
+                zones
+                    .bypass_change_detection()
+                    .storage
+                    .compress_cold_chunks(&compression.0);
+            }
+            if let Some(mut sensors) = sensors {
+                sensors
+                    .bypass_change_detection()
+                    .storage
+                    .compress_cold_chunks(&compression.0);
+            }
+        });
+
+    #[cfg(feature = "serializing")]
+    mirrors_query
+        .par_iter_mut()
+        .for_each(|(compression, mut mirror)| {
+            if let Some(tiles) = &mut mirror.bypass_change_detection().tiles {
+                tiles.compress_cold_chunks(&compression.0);
+            }
+        });
+}
+
+#[cfg(all(test, feature = "serializing"))]
+mod test {
+    use bevy::{
+        app::{App, Update},
+        math::IVec2,
+    };
+
+    use crate::{
+        serializing::map::TilemapTileMirror,
+        tilemap::{
+            chunking::storage::ChunkedStorage,
+            tile::{TileBuilder, TileLayer},
+        },
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_mirror_compression() {
+        let mut app = App::new();
+        app.add_systems(Update, chunk_compressor);
+
+        let mut tiles = ChunkedStorage::new(4);
+        let tile = TileBuilder::new().with_layer(0, TileLayer::no_flip(3));
+        tiles.set_elem(IVec2::new(1, 2), tile.clone());
+        let tilemap = app
+            .world
+            .spawn((
+                TilemapChunkCompression(ChunkCompressionConfig {
+                    cold_after: 1,
+                    min_chunks: 0,
+                }),
+                TilemapTileMirror { tiles: Some(tiles) },
+            ))
+            .id();
+        app.update();
+
+        let tiles = app
+            .world
+            .get::<TilemapTileMirror>(tilemap)
+            .unwrap()
+            .tiles()
+            .unwrap();
+        assert!(tiles.is_chunk_compressed(IVec2::ZERO));
+        assert_eq!(tiles.get_elem(IVec2::new(1, 2)), Some(&tile));
+    }
+}
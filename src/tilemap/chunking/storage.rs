@@ -16,17 +16,62 @@ pub type ColorTileChunkedStorage = ChunkedStorage<Tile>;
 pub type TileBuilderChunkedStorage = ChunkedStorage<TileBuilder>;
 #[cfg(feature = "algorithm")]
 pub type PathTileChunkedStorage = ChunkedStorage<crate::tilemap::algorithm::path::PathTile>;
-#[cfg(feature = "physics")]
+#[cfg(feature = "physics_core")]
 pub type PhysicsTileChunkedStorage = ChunkedStorage<crate::tilemap::physics::PhysicsTile>;
-#[cfg(feature = "physics")]
+#[cfg(feature = "physics_core")]
 pub type PackedPhysicsTileChunkedStorage =
     ChunkedStorage<crate::tilemap::physics::PackedPhysicsTile>;
 
+/// A storage that splits the elements into chunks.
+///
+/// By default, every chunk is a dense array, which is fast but wastes memory
+/// if most of the elements are empty. Use `new_sparse` to create a storage
+/// that only stores the elements that exist.
+///
+/// Dense chunks that are not modified for a while can be compressed using
+/// `compress_cold_chunks`. They can still be read without decompressing, and
+/// are decompressed once they are accessed mutably.
 #[derive(Debug, Clone, Reflect)]
 #[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serializing",
+    serde(bound(serialize = "T: serde::Serialize"))
+)]
 pub struct ChunkedStorage<T: Debug + Clone + Reflect> {
     pub chunk_size: u32,
+    #[cfg_attr(
+        feature = "serializing",
+        serde(serialize_with = "crate::serializing::serialize_sorted")
+    )]
     pub chunks: HashMap<IVec2, Vec<Option<T>>>,
+    /// The chunks of a sparse storage.
+    #[cfg_attr(
+        feature = "serializing",
+        serde(
+            default = "HashMap::new",
+            serialize_with = "crate::serializing::serialize_sorted_nested"
+        )
+    )]
+    pub sparse_chunks: HashMap<IVec2, HashMap<InChunkIndex, T>>,
+    #[cfg_attr(feature = "serializing", serde(default))]
+    pub sparse: bool,
+    /// The compressed dense chunks.
+    #[cfg_attr(
+        feature = "serializing",
+        serde(
+            default = "HashMap::new",
+            serialize_with = "crate::serializing::serialize_sorted"
+        )
+    )]
+    pub compressed_chunks: HashMap<IVec2, CompressedChunk<T>>,
+    /// The number of times `compress_cold_chunks` was called.
+    #[cfg_attr(feature = "serializing", serde(skip))]
+    #[reflect(ignore)]
+    compression_tick: u32,
+    /// The `compression_tick` when each chunk was accessed mutably the last time.
+    #[cfg_attr(feature = "serializing", serde(skip))]
+    #[reflect(ignore)]
+    chunk_ticks: HashMap<IVec2, u32>,
 }
 
 impl<T: Debug + Clone + Reflect> Default for ChunkedStorage<T> {
@@ -34,6 +79,11 @@ impl<T: Debug + Clone + Reflect> Default for ChunkedStorage<T> {
         Self {
             chunk_size: DEFAULT_CHUNK_SIZE,
             chunks: HashMap::new(),
+            sparse_chunks: HashMap::new(),
+            sparse: false,
+            compressed_chunks: HashMap::new(),
+            compression_tick: 0,
+            chunk_ticks: HashMap::new(),
         }
     }
 }
@@ -46,6 +96,29 @@ impl<T: Debug + Clone + Reflect> ChunkedStorage<T> {
         }
     }
 
+    /// Create a sparse storage. Use this for mostly-empty storages.
+    pub fn new_sparse(chunk_size: u32) -> Self {
+        Self {
+            chunk_size,
+            sparse: true,
+            ..Default::default()
+        }
+    }
+
+    /// Create an empty storage with the same chunk size and layout.
+    pub fn new_like<U: Debug + Clone + Reflect>(other: &ChunkedStorage<U>) -> Self {
+        Self {
+            chunk_size: other.chunk_size,
+            sparse: other.sparse,
+            ..Default::default()
+        }
+    }
+
+    #[inline]
+    pub fn is_sparse(&self) -> bool {
+        self.sparse
+    }
+
     pub fn from_mapper(mapper: HashMap<IVec2, T>, chunk_size: Option<u32>) -> Self {
         let mut storage = Self::new(chunk_size.unwrap_or(32));
         mapper.into_iter().for_each(|(index, elem)| {
@@ -56,14 +129,29 @@ impl<T: Debug + Clone + Reflect> ChunkedStorage<T> {
 
     pub fn get_elem(&self, index: IVec2) -> Option<&T> {
         let idx = self.transform_index(index);
-        self.chunks
-            .get(&idx.0)
-            .and_then(|c| c.get(idx.1).as_ref().cloned())
-            .and_then(|t| t.as_ref())
+        if self.sparse {
+            return self.sparse_chunks.get(&idx.0).and_then(|c| c.get(&idx.1));
+        }
+
+        match self.chunks.get(&idx.0) {
+            Some(chunk) => chunk.get(idx.1).and_then(|t| t.as_ref()),
+            None => self
+                .compressed_chunks
+                .get(&idx.0)
+                .and_then(|c| c.get(idx.1)),
+        }
     }
 
     pub fn get_elem_mut(&mut self, index: IVec2) -> Option<&mut T> {
         let idx = self.transform_index(index);
+        if self.sparse {
+            return self
+                .sparse_chunks
+                .get_mut(&idx.0)
+                .and_then(|c| c.get_mut(&idx.1));
+        }
+
+        self.warm_chunk(idx.0);
         if let Some(chunk) = self.chunks.get_mut(&idx.0) {
             chunk.get_mut(idx.1).map(|t| t.as_mut()).flatten()
         } else {
@@ -73,13 +161,19 @@ impl<T: Debug + Clone + Reflect> ChunkedStorage<T> {
 
     pub fn set_elem(&mut self, index: IVec2, elem: T) {
         let idx = self.transform_index(index);
-        self.chunks
-            .entry(idx.0)
-            .or_insert_with(|| vec![None; (self.chunk_size * self.chunk_size) as usize])[idx.1] =
-            Some(elem);
+        self.set_elem_precise(idx.0, idx.1, elem);
     }
 
     pub fn set_elem_precise(&mut self, chunk_index: IVec2, in_chunk_index: usize, elem: T) {
+        if self.sparse {
+            self.sparse_chunks
+                .entry(chunk_index)
+                .or_default()
+                .insert(in_chunk_index, elem);
+            return;
+        }
+
+        self.warm_chunk(chunk_index);
         self.chunks
             .entry(chunk_index)
             .or_insert_with(|| vec![None; (self.chunk_size * self.chunk_size) as usize])
@@ -88,37 +182,142 @@ impl<T: Debug + Clone + Reflect> ChunkedStorage<T> {
 
     pub fn remove_elem(&mut self, index: IVec2) -> Option<T> {
         let idx = self.transform_index(index);
+        if self.sparse {
+            return self
+                .sparse_chunks
+                .get_mut(&idx.0)
+                .and_then(|c| c.remove(&idx.1));
+        }
+
+        self.warm_chunk(idx.0);
         self.chunks.get_mut(&idx.0).and_then(|c| c[idx.1].take())
     }
 
     pub fn remove_chunk(&mut self, index: IVec2) -> Option<Vec<Option<T>>> {
-        self.chunks.remove(&index)
+        if self.sparse {
+            return self.sparse_chunks.remove(&index).map(|chunk| {
+                let mut dense = vec![None; (self.chunk_size * self.chunk_size) as usize];
+                chunk
+                    .into_iter()
+                    .for_each(|(i, elem)| dense[i] = Some(elem));
+                dense
+            });
+        }
+
+        self.chunk_ticks.remove(&index);
+        self.chunks.remove(&index).or_else(|| {
+            self.compressed_chunks
+                .remove(&index)
+                .map(|c| c.decompress())
+        })
     }
 
     pub fn clear(&mut self) {
         self.chunks.clear();
+        self.sparse_chunks.clear();
+        self.compressed_chunks.clear();
+        self.chunk_ticks.clear();
     }
 
+    /// Get a chunk, whichever layout it's stored in.
     #[inline]
-    pub fn get_chunk(&self, index: IVec2) -> Option<&Vec<Option<T>>> {
-        self.chunks.get(&index)
+    pub fn get_chunk(&self, index: IVec2) -> Option<ChunkRef<'_, T>> {
+        if self.sparse {
+            return self.sparse_chunks.get(&index).map(ChunkRef::Sparse);
+        }
+
+        self.chunks.get(&index).map(ChunkRef::Dense).or_else(|| {
+            self.compressed_chunks
+                .get(&index)
+                .map(ChunkRef::Compressed)
+        })
     }
 
+    /// Get a mutable chunk, whichever layout it's stored in.
+    ///
+    /// Compressed chunks are decompressed.
     #[inline]
-    pub fn get_chunk_mut(&mut self, index: IVec2) -> Option<&mut Vec<Option<T>>> {
-        self.chunks.get_mut(&index)
+    pub fn get_chunk_mut(&mut self, index: IVec2) -> Option<ChunkMut<'_, T>> {
+        if self.sparse {
+            return self.sparse_chunks.get_mut(&index).map(ChunkMut::Sparse);
+        }
+
+        self.warm_chunk(index);
+        self.chunks.get_mut(&index).map(ChunkMut::Dense)
     }
 
+    /// Get a chunk or insert an empty one.
     #[inline]
-    pub fn get_chunk_or_insert(&mut self, index: IVec2) -> &mut Vec<Option<T>> {
-        self.chunks
-            .entry(index)
-            .or_insert(vec![None; (self.chunk_size * self.chunk_size) as usize])
+    pub fn get_chunk_or_insert(&mut self, index: IVec2) -> ChunkMut<'_, T> {
+        if self.sparse {
+            return ChunkMut::Sparse(self.sparse_chunks.entry(index).or_default());
+        }
+
+        self.warm_chunk(index);
+        ChunkMut::Dense(
+            self.chunks
+                .entry(index)
+                .or_insert(vec![None; (self.chunk_size * self.chunk_size) as usize]),
+        )
     }
 
     #[inline]
     pub fn set_chunk(&mut self, index: IVec2, chunk: Vec<Option<T>>) {
-        self.chunks.insert(index, chunk);
+        if self.sparse {
+            self.sparse_chunks.insert(
+                index,
+                chunk
+                    .into_iter()
+                    .enumerate()
+                    .filter_map(|(i, elem)| elem.map(|e| (i, e)))
+                    .collect(),
+            );
+        } else {
+            self.compressed_chunks.remove(&index);
+            self.warm_chunk(index);
+            self.chunks.insert(index, chunk);
+        }
+    }
+
+    #[inline]
+    pub fn contains_chunk(&self, index: IVec2) -> bool {
+        self.chunks.contains_key(&index)
+            || self.sparse_chunks.contains_key(&index)
+            || self.compressed_chunks.contains_key(&index)
+    }
+
+    /// Iterate over the indices of all the existing chunks.
+    #[inline]
+    pub fn chunk_indices(&self) -> impl Iterator<Item = &ChunkIndex> {
+        self.chunks
+            .keys()
+            .chain(self.sparse_chunks.keys())
+            .chain(self.compressed_chunks.keys())
+    }
+
+    /// Iterate over the elements in a chunk.
+    #[inline]
+    pub fn iter_chunk(&self, index: IVec2) -> impl Iterator<Item = (InChunkIndex, &T)> {
+        self.chunks
+            .get(&index)
+            .into_iter()
+            .flat_map(|c| {
+                c.iter()
+                    .enumerate()
+                    .filter_map(|(i, elem)| elem.as_ref().map(|e| (i, e)))
+            })
+            .chain(
+                self.sparse_chunks
+                    .get(&index)
+                    .into_iter()
+                    .flat_map(|c| c.iter().map(|(i, e)| (*i, e))),
+            )
+            .chain(
+                self.compressed_chunks
+                    .get(&index)
+                    .into_iter()
+                    .flat_map(|c| c.iter_some()),
+            )
     }
 
     pub fn transform_index(&self, index: IVec2) -> (ChunkIndex, InChunkIndex) {
@@ -138,6 +337,7 @@ impl<T: Debug + Clone + Reflect> ChunkedStorage<T> {
     }
 
     pub fn into_mapper(mut self) -> HashMap<IVec2, T> {
+        self.decompress_all();
         let mut mapper = HashMap::new();
         self.chunks.drain().for_each(|(chunk_index, chunk)| {
             chunk.into_iter().enumerate().for_each(|(index, elem)| {
@@ -153,14 +353,33 @@ impl<T: Debug + Clone + Reflect> ChunkedStorage<T> {
                 }
             });
         });
+        let chunk_size = self.chunk_size as i32;
+        self.sparse_chunks.drain().for_each(|(chunk_index, chunk)| {
+            chunk.into_iter().for_each(|(index, elem)| {
+                mapper.insert(
+                    chunk_index * chunk_size
+                        + IVec2 {
+                            x: index as i32 % chunk_size,
+                            y: index as i32 / chunk_size,
+                        },
+                    elem,
+                );
+            });
+        });
         mapper
     }
 
+    /// Iterate over all the slots of the dense chunks.
+    ///
+    /// **Notice**: Sparse and compressed chunks are not included, use `iter_some` instead.
     #[inline]
     pub fn iter(&self) -> impl Iterator<Item = &Option<T>> {
         self.chunks.values().map(|c| c.iter()).flatten()
     }
 
+    /// Iterate over all the slots of the dense chunks.
+    ///
+    /// **Notice**: Sparse and compressed chunks are not included, use `iter_some_mut` instead.
     #[inline]
     pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Option<T>> {
         self.chunks.values_mut().map(|c| c.iter_mut()).flatten()
@@ -168,12 +387,25 @@ impl<T: Debug + Clone + Reflect> ChunkedStorage<T> {
 
     #[inline]
     pub fn iter_some(&self) -> impl Iterator<Item = &T> {
-        self.iter().map(|o| o.as_ref()).flatten()
+        self.iter()
+            .map(|o| o.as_ref())
+            .flatten()
+            .chain(self.sparse_chunks.values().flat_map(|c| c.values()))
+            .chain(
+                self.compressed_chunks
+                    .values()
+                    .flat_map(|c| c.iter_some().map(|(_, e)| e)),
+            )
     }
 
+    /// Iterate over all the elements mutably. This decompresses all the chunks.
     #[inline]
     pub fn iter_some_mut(&mut self) -> impl Iterator<Item = &mut T> {
-        self.iter_mut().map(|o| o.as_mut()).flatten()
+        self.decompress_all();
+        self.chunks
+            .values_mut()
+            .flat_map(|c| c.iter_mut().filter_map(|o| o.as_mut()))
+            .chain(self.sparse_chunks.values_mut().flat_map(|c| c.values_mut()))
     }
 
     #[inline]
@@ -190,5 +422,367 @@ impl<T: Debug + Clone + Reflect> ChunkedStorage<T> {
                     })
             })
             .flatten()
+            .chain(self.sparse_chunks.iter().flat_map(|(chunk_index, chunk)| {
+                chunk
+                    .iter()
+                    .map(move |(in_chunk_index, elem)| (*chunk_index, *in_chunk_index, elem))
+            }))
+            .chain(
+                self.compressed_chunks
+                    .iter()
+                    .flat_map(|(chunk_index, chunk)| {
+                        chunk
+                            .iter_some()
+                            .map(move |(in_chunk_index, elem)| (*chunk_index, in_chunk_index, elem))
+                    }),
+            )
+    }
+
+    #[inline]
+    pub fn is_chunk_compressed(&self, index: IVec2) -> bool {
+        self.compressed_chunks.contains_key(&index)
+    }
+
+    /// Decompress the chunk if it's compressed, and mark it as recently accessed.
+    fn warm_chunk(&mut self, index: IVec2) {
+        if let Some(chunk) = self.compressed_chunks.remove(&index) {
+            self.chunks.insert(index, chunk.decompress());
+        }
+        // Only track the access if compression is in use, to keep accessing cheap.
+        if self.compression_tick > 0 {
+            self.chunk_ticks.insert(index, self.compression_tick);
+        }
+    }
+
+    /// Decompress a chunk. Returns false if the chunk is not compressed.
+    pub fn decompress_chunk(&mut self, index: IVec2) -> bool {
+        match self.compressed_chunks.remove(&index) {
+            Some(chunk) => {
+                self.chunks.insert(index, chunk.decompress());
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn decompress_all(&mut self) {
+        self.compressed_chunks.drain().for_each(|(index, chunk)| {
+            self.chunks.insert(index, chunk.decompress());
+        });
+    }
+}
+
+impl<T: Debug + Clone + Reflect + PartialEq> ChunkedStorage<T> {
+    /// Compress a dense chunk. Returns false if the chunk doesn't exist or
+    /// the storage is sparse.
+    pub fn compress_chunk(&mut self, index: IVec2) -> bool {
+        match self.chunks.remove(&index) {
+            Some(chunk) => {
+                self.compressed_chunks
+                    .insert(index, CompressedChunk::compress(&chunk));
+                self.chunk_ticks.remove(&index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Compress the dense chunks that are not accessed mutably during the last
+    /// `config.cold_after` calls of this method. Returns the number of compressed chunks.
+    ///
+    /// Call this periodically, like once per frame.
+    pub fn compress_cold_chunks(&mut self, config: &ChunkCompressionConfig) -> usize {
+        self.compression_tick += 1;
+        if self.chunks.len() + self.compressed_chunks.len() < config.min_chunks {
+            return 0;
+        }
+
+        let cold = self
+            .chunks
+            .keys()
+            .filter(|index| {
+                self.chunk_ticks.get(*index).copied().unwrap_or_default() + config.cold_after
+                    <= self.compression_tick
+            })
+            .copied()
+            .collect::<Vec<_>>();
+        cold.iter().for_each(|index| {
+            self.compress_chunk(*index);
+        });
+        cold.len()
+    }
+}
+
+/// Thresholds for compressing cold chunks.
+#[derive(Debug, Clone, Copy, Reflect)]
+#[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChunkCompressionConfig {
+    /// Chunks that are not accessed mutably for this many compressions are cold.
+    pub cold_after: u32,
+    /// Nothing will be compressed if the storage has fewer chunks than this.
+    pub min_chunks: usize,
+}
+
+impl Default for ChunkCompressionConfig {
+    fn default() -> Self {
+        Self {
+            cold_after: 600,
+            min_chunks: 64,
+        }
+    }
+}
+
+/// A chunk of a `ChunkedStorage`, in the layout it's stored in.
+#[derive(Debug)]
+pub enum ChunkRef<'a, T: Debug + Clone + Reflect> {
+    Dense(&'a Vec<Option<T>>),
+    Sparse(&'a HashMap<InChunkIndex, T>),
+    Compressed(&'a CompressedChunk<T>),
+}
+
+impl<'a, T: Debug + Clone + Reflect> ChunkRef<'a, T> {
+    pub fn get(&self, in_chunk_index: InChunkIndex) -> Option<&'a T> {
+        match self {
+            ChunkRef::Dense(c) => c.get(in_chunk_index).and_then(|e| e.as_ref()),
+            ChunkRef::Sparse(c) => c.get(&in_chunk_index),
+            ChunkRef::Compressed(c) => c.get(in_chunk_index),
+        }
+    }
+
+    /// Iterate over the existing elements.
+    pub fn iter_some(&self) -> impl Iterator<Item = (InChunkIndex, &'a T)> {
+        let (dense, sparse, compressed) = match *self {
+            ChunkRef::Dense(c) => (Some(c), None, None),
+            ChunkRef::Sparse(c) => (None, Some(c), None),
+            ChunkRef::Compressed(c) => (None, None, Some(c)),
+        };
+
+        dense
+            .into_iter()
+            .flat_map(|c| {
+                c.iter()
+                    .enumerate()
+                    .filter_map(|(i, e)| e.as_ref().map(|e| (i, e)))
+            })
+            .chain(
+                sparse
+                    .into_iter()
+                    .flat_map(|c| c.iter().map(|(i, e)| (*i, e))),
+            )
+            .chain(compressed.into_iter().flat_map(|c| c.iter_some()))
+    }
+
+    /// Copy the chunk into a dense array.
+    pub fn to_dense(&self, chunk_size: u32) -> Vec<Option<T>> {
+        match self {
+            ChunkRef::Dense(c) => c.to_vec(),
+            ChunkRef::Compressed(c) => c.decompress(),
+            ChunkRef::Sparse(_) => {
+                let mut dense = vec![None; (chunk_size * chunk_size) as usize];
+                self.iter_some()
+                    .for_each(|(i, e)| dense[i] = Some(e.clone()));
+                dense
+            }
+        }
+    }
+}
+
+/// A mutable chunk of a `ChunkedStorage`, in the layout it's stored in.
+#[derive(Debug)]
+pub enum ChunkMut<'a, T: Debug + Clone + Reflect> {
+    Dense(&'a mut Vec<Option<T>>),
+    Sparse(&'a mut HashMap<InChunkIndex, T>),
+}
+
+impl<'a, T: Debug + Clone + Reflect> ChunkMut<'a, T> {
+    pub fn get(&self, in_chunk_index: InChunkIndex) -> Option<&T> {
+        match self {
+            ChunkMut::Dense(c) => c.get(in_chunk_index).and_then(|e| e.as_ref()),
+            ChunkMut::Sparse(c) => c.get(&in_chunk_index),
+        }
+    }
+
+    pub fn get_mut(&mut self, in_chunk_index: InChunkIndex) -> Option<&mut T> {
+        match self {
+            ChunkMut::Dense(c) => c.get_mut(in_chunk_index).and_then(|e| e.as_mut()),
+            ChunkMut::Sparse(c) => c.get_mut(&in_chunk_index),
+        }
+    }
+
+    /// Set an element and return the old one.
+    pub fn set(&mut self, in_chunk_index: InChunkIndex, elem: T) -> Option<T> {
+        match self {
+            ChunkMut::Dense(c) => c[in_chunk_index].replace(elem),
+            ChunkMut::Sparse(c) => c.insert(in_chunk_index, elem),
+        }
+    }
+
+    pub fn remove(&mut self, in_chunk_index: InChunkIndex) -> Option<T> {
+        match self {
+            ChunkMut::Dense(c) => c.get_mut(in_chunk_index).and_then(|e| e.take()),
+            ChunkMut::Sparse(c) => c.remove(&in_chunk_index),
+        }
+    }
+
+    /// Iterate over the existing elements mutably.
+    pub fn iter_some_mut(&mut self) -> impl Iterator<Item = (InChunkIndex, &mut T)> {
+        let (dense, sparse) = match self {
+            ChunkMut::Dense(c) => (Some(&mut **c), None),
+            ChunkMut::Sparse(c) => (None, Some(&mut **c)),
+        };
+
+        dense
+            .into_iter()
+            .flat_map(|c| {
+                c.iter_mut()
+                    .enumerate()
+                    .filter_map(|(i, e)| e.as_mut().map(|e| (i, e)))
+            })
+            .chain(
+                sparse
+                    .into_iter()
+                    .flat_map(|c| c.iter_mut().map(|(i, e)| (*i, e))),
+            )
+    }
+}
+
+/// A run-length encoded dense chunk.
+#[derive(Debug, Clone, Reflect)]
+#[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompressedChunk<T: Debug + Clone + Reflect> {
+    /// The exclusive end index and the element of each run.
+    runs: Vec<(u32, Option<T>)>,
+}
+
+impl<T: Debug + Clone + Reflect> CompressedChunk<T> {
+    pub fn compress(chunk: &[Option<T>]) -> Self
+    where
+        T: PartialEq,
+    {
+        let mut runs: Vec<(u32, Option<T>)> = Vec::new();
+        chunk
+            .iter()
+            .enumerate()
+            .for_each(|(i, elem)| match runs.last_mut() {
+                Some((end, last)) if last == elem => *end = i as u32 + 1,
+                _ => runs.push((i as u32 + 1, elem.clone())),
+            });
+        Self { runs }
+    }
+
+    pub fn decompress(&self) -> Vec<Option<T>> {
+        let mut start = 0;
+        self.runs
+            .iter()
+            .flat_map(|(end, elem)| {
+                let len = *end - start;
+                start = *end;
+                std::iter::repeat(elem.clone()).take(len as usize)
+            })
+            .collect()
+    }
+
+    pub fn get(&self, in_chunk_index: InChunkIndex) -> Option<&T> {
+        let run = self
+            .runs
+            .partition_point(|(end, _)| *end as usize <= in_chunk_index);
+        self.runs.get(run).and_then(|(_, elem)| elem.as_ref())
+    }
+
+    pub fn iter_some(&self) -> impl Iterator<Item = (InChunkIndex, &T)> {
+        let mut start = 0;
+        self.runs.iter().flat_map(move |(end, elem)| {
+            let range = start as usize..*end as usize;
+            start = *end;
+            elem.iter()
+                .flat_map(move |e| range.clone().map(move |i| (i, e)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sparse_storage() {
+        let mut dense = ChunkedStorage::<u32>::new(4);
+        let mut sparse = ChunkedStorage::<u32>::new_sparse(4);
+        let indices = [IVec2::new(0, 0), IVec2::new(-3, 7), IVec2::new(15, -9)];
+
+        for (i, index) in indices.iter().enumerate() {
+            dense.set_elem(*index, i as u32);
+            sparse.set_elem(*index, i as u32);
+        }
+
+        for index in indices {
+            assert_eq!(dense.get_elem(index), sparse.get_elem(index));
+        }
+        assert_eq!(sparse.get_elem(IVec2::new(1, 1)), None);
+        assert_eq!(sparse.iter_some().count(), indices.len());
+        assert_eq!(sparse.clone().into_mapper(), dense.clone().into_mapper());
+
+        let chunk = sparse.transform_index(indices[1]).0;
+        assert_eq!(
+            sparse.get_chunk(chunk).unwrap().to_dense(4),
+            dense.get_chunk(chunk).unwrap().to_dense(4)
+        );
+        sparse.get_chunk_or_insert(IVec2::new(9, 9)).set(5, 7);
+        assert_eq!(sparse.get_elem(IVec2::new(37, 37)), Some(&7));
+        *sparse.get_chunk_mut(IVec2::new(9, 9)).unwrap().get_mut(5).unwrap() = 8;
+        assert_eq!(sparse.get_chunk(IVec2::new(9, 9)).unwrap().get(5), Some(&8));
+        sparse.remove_chunk(IVec2::new(9, 9));
+        assert_eq!(sparse.remove_chunk(chunk), dense.remove_chunk(chunk));
+        assert_eq!(sparse.remove_elem(indices[2]), Some(2));
+        assert_eq!(sparse.iter_some().count(), 1);
+    }
+
+    #[test]
+    fn test_chunk_compression() {
+        let mut storage = ChunkedStorage::<u32>::new(4);
+        (0..8).for_each(|x| storage.set_elem(IVec2::new(x, 0), x as u32 / 3));
+        storage.set_elem(IVec2::new(-1, 3), 7);
+        let mapper = storage.clone().into_mapper();
+
+        let config = ChunkCompressionConfig {
+            cold_after: 2,
+            min_chunks: 0,
+        };
+        assert_eq!(storage.compress_cold_chunks(&config), 0);
+        storage.set_elem(IVec2::new(-1, 2), 8);
+        assert_eq!(storage.compress_cold_chunks(&config), 2);
+        assert!(!storage.is_chunk_compressed(IVec2::new(-1, 0)));
+        assert_eq!(storage.compress_cold_chunks(&config), 1);
+
+        assert_eq!(storage.get_elem(IVec2::new(5, 0)), Some(&1));
+        assert_eq!(storage.get_elem(IVec2::new(5, 1)), None);
+        assert_eq!(storage.iter_some().count(), 10);
+        assert_eq!(storage.iter_chunk(IVec2::ZERO).count(), 4);
+
+        storage.remove_elem(IVec2::new(-1, 2));
+        assert!(!storage.is_chunk_compressed(IVec2::new(-1, 0)));
+        assert_eq!(storage.into_mapper(), mapper);
+    }
+
+    #[cfg(feature = "serializing")]
+    #[test]
+    fn test_deterministic_serialization() {
+        let indices = (-20..20)
+            .flat_map(|x| (-20..20).map(move |y| IVec2::new(x * 7, y * 3)))
+            .collect::<Vec<_>>();
+
+        for mut storage in [ChunkedStorage::new(4), ChunkedStorage::new_sparse(4)] {
+            let mut reversed = ChunkedStorage::new_like(&storage);
+            indices.iter().for_each(|i| storage.set_elem(*i, i.x));
+            indices
+                .iter()
+                .rev()
+                .for_each(|i| reversed.set_elem(*i, i.x));
+
+            assert_eq!(
+                ron::to_string(&storage).unwrap(),
+                ron::to_string(&reversed).unwrap()
+            );
+        }
     }
 }
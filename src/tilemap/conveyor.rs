@@ -0,0 +1,305 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        component::Component,
+        schedule::IntoSystemConfigs,
+        system::{Query, Res},
+    },
+    math::Vec2,
+    reflect::Reflect,
+    time::Time,
+    transform::components::{GlobalTransform, Transform},
+};
+
+use super::{
+    flow::TilemapFlowField,
+    map::{TilePivot, TilemapAxisFlip, TilemapSlotSize, TilemapTransform, TilemapType},
+    sensor::TilePosition,
+};
+
+pub struct EntiTilesTileConveyorPlugin;
+
+impl Plugin for EntiTilesTileConveyorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                conveyance_sampler,
+                (transform_conveyor, tile_position_conveyor),
+            )
+                .chain(),
+        );
+
+        app.register_type::<TileConveyed>()
+            .register_type::<ConveyanceMode>();
+    }
+}
+
+/// How the conveyance moves the entity.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum ConveyanceMode {
+    /// Move the `Transform` directly.
+    #[default]
+    Transform,
+    /// Push the physics body. The velocity is added to the body by the physics backend,
+    /// see `TileConveyed::apply_velocity`.
+    Velocity,
+    /// Move the `TilePosition` tile by tile, for headless simulations.
+    TilePosition,
+}
+
+/// Entities with this component are carried by the `TilemapFlowField` of the tilemaps
+/// they stand on, like on conveyors, escalators or rivers.
+///
+/// The entity stands on the tile of its `TilePosition`, or on the tiles under its
+/// `GlobalTransform` if it doesn't have one. The vectors of all the tilemaps
+/// under the entity are added together.
+#[derive(Component, Debug, Clone, Copy, Reflect)]
+pub struct TileConveyed {
+    /// How many tiles per second a flow vector of length 1 moves the entity.
+    pub speed: f32,
+    pub mode: ConveyanceMode,
+    /// In world units per second, or tiles per second in `TilePosition` mode.
+    pub(crate) velocity: Vec2,
+    /// The velocity that is added to the physics body for the current physics step.
+    pub(crate) applied: Vec2,
+    /// The part of a tile moved in `TilePosition` mode.
+    pub(crate) progress: Vec2,
+}
+
+impl Default for TileConveyed {
+    fn default() -> Self {
+        Self::new(1., ConveyanceMode::default())
+    }
+}
+
+impl TileConveyed {
+    pub fn new(speed: f32, mode: ConveyanceMode) -> Self {
+        Self {
+            speed,
+            mode,
+            velocity: Vec2::ZERO,
+            applied: Vec2::ZERO,
+            progress: Vec2::ZERO,
+        }
+    }
+
+    /// The current velocity of the conveyance.
+    ///
+    /// In world units per second, or tiles per second in `TilePosition` mode.
+    #[inline]
+    pub fn velocity(&self) -> Vec2 {
+        self.velocity
+    }
+
+    /// The velocity to add to the physics body before every physics step.
+    ///
+    /// Physics backends take it back with `take_applied_velocity` after the step,
+    /// so the conveyance doesn't pile up and stops pushing once the entity leaves
+    /// the conveyor, while the velocity from the other sources can still be set
+    /// or overwritten freely between the frames.
+    #[inline]
+    pub fn apply_velocity(&mut self) -> Vec2 {
+        self.applied = self.velocity;
+        self.applied
+    }
+
+    /// The velocity added by the last `apply_velocity`, which should be removed
+    /// from the body after the physics step.
+    #[inline]
+    pub fn take_applied_velocity(&mut self) -> Vec2 {
+        std::mem::take(&mut self.applied)
+    }
+}
+
+pub fn conveyance_sampler(
+    mut conveyed_query: Query<(
+        &mut TileConveyed,
+        Option<&TilePosition>,
+        Option<&GlobalTransform>,
+    )>,
+    tilemaps_query: Query<(
+        &TilemapFlowField,
+        &TilemapType,
+        &TilemapTransform,
+        &TilePivot,
+        &TilemapSlotSize,
+        &TilemapAxisFlip,
+    )>,
+) {
+    conveyed_query
+        .par_iter_mut()
+        .for_each(|(mut conveyed, position, transform)| {
+            let velocity = match (position, transform) {
+                (Some(position), _) => tilemaps_query
+                    .get(position.tilemap)
+                    .map(|(flow, _, tilemap_transform, _, slot_size, axis_flip)| {
+                        let flow = flow.sample(position.index) * conveyed.speed;
+                        if conveyed.mode == ConveyanceMode::TilePosition {
+                            flow
+                        } else {
+                            tilemap_transform
+                                .apply_rotation(flow * axis_flip.as_vec2() * slot_size.0)
+                        }
+                    })
+                    .unwrap_or_default(),
+                (None, Some(transform)) => {
+                    let world = transform.translation().truncate();
+                    tilemaps_query
+                        .iter()
+                        .map(
+                            |(flow, ty, tilemap_transform, pivot, slot_size, axis_flip)| {
+                                let flow = flow.sample_world(
+                                    world,
+                                    *ty,
+                                    tilemap_transform,
+                                    pivot.0,
+                                    slot_size.0,
+                                    *axis_flip,
+                                );
+                                tilemap_transform
+                                    .apply_rotation(flow * axis_flip.as_vec2() * slot_size.0)
+                            },
+                        )
+                        .sum::<Vec2>()
+                        * conveyed.speed
+                }
+                (None, None) => Vec2::ZERO,
+            };
+
+            if conveyed.velocity != velocity {
+                conveyed.velocity = velocity;
+            }
+        });
+}
+
+pub fn transform_conveyor(
+    mut conveyed_query: Query<(&TileConveyed, &mut Transform)>,
+    time: Res<Time>,
+) {
+    conveyed_query
+        .iter_mut()
+        .filter(|(conveyed, _)| {
+            conveyed.mode == ConveyanceMode::Transform && conveyed.velocity != Vec2::ZERO
+        })
+        .for_each(|(conveyed, mut transform)| {
+            transform.translation += (conveyed.velocity * time.delta_seconds()).extend(0.);
+        });
+}
+
+pub fn tile_position_conveyor(
+    mut conveyed_query: Query<(&mut TileConveyed, &mut TilePosition)>,
+    time: Res<Time>,
+) {
+    conveyed_query
+        .iter_mut()
+        .filter(|(conveyed, _)| conveyed.mode == ConveyanceMode::TilePosition)
+        .for_each(|(mut conveyed, mut position)| {
+            if conveyed.velocity == Vec2::ZERO {
+                conveyed.progress = Vec2::ZERO;
+                return;
+            }
+
+            let progress = conveyed.progress + conveyed.velocity * time.delta_seconds();
+            let moved = progress.trunc();
+            conveyed.progress = progress - moved;
+            if moved != Vec2::ZERO {
+                position.index += moved.as_ivec2();
+            }
+        });
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use bevy::math::{IVec2, Vec3};
+
+    use crate::math::TileArea;
+
+    use super::*;
+
+    #[test]
+    fn test_conveyance() {
+        let mut app = App::new();
+        app.add_plugins(EntiTilesTileConveyorPlugin);
+        app.init_resource::<Time>();
+
+        let mut flow = TilemapFlowField::new_with_chunk_size(4);
+        flow.fill_rect(TileArea::new(IVec2::ZERO, (4, 1).into()), Vec2::X);
+        let tilemap = app
+            .world
+            .spawn((
+                flow,
+                TilemapType::Square,
+                TilemapTransform::default(),
+                TilePivot::default(),
+                TilemapSlotSize(Vec2::splat(16.)),
+                TilemapAxisFlip::NONE,
+            ))
+            .id();
+
+        let headless = app
+            .world
+            .spawn((
+                TileConveyed::new(2., ConveyanceMode::TilePosition),
+                TilePosition {
+                    tilemap,
+                    index: IVec2::ZERO,
+                },
+            ))
+            .id();
+        let body = app
+            .world
+            .spawn((
+                TileConveyed::new(1., ConveyanceMode::Velocity),
+                GlobalTransform::from_xyz(8., 8., 0.),
+            ))
+            .id();
+        let sprite = app
+            .world
+            .spawn((
+                TileConveyed::new(1., ConveyanceMode::Transform),
+                TilePosition {
+                    tilemap,
+                    index: IVec2::ZERO,
+                },
+                Transform::default(),
+            ))
+            .id();
+
+        app.world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_millis(750));
+        app.update();
+
+        let (conveyed, position) = app
+            .world
+            .query::<(&TileConveyed, &TilePosition)>()
+            .get(&app.world, headless)
+            .unwrap();
+        assert_eq!(position.index, IVec2::new(1, 0));
+        assert_eq!(conveyed.progress, Vec2::new(0.5, 0.));
+
+        // Standing on a `TilePosition` still moves in world units outside `TilePosition` mode.
+        let transform = app.world.get::<Transform>(sprite).unwrap();
+        assert_eq!(transform.translation, Vec3::new(12., 0., 0.));
+
+        let mut conveyed = app.world.get_mut::<TileConveyed>(body).unwrap();
+        assert_eq!(conveyed.velocity(), Vec2::new(16., 0.));
+
+        // Like the physics backends, around two physics steps.
+        let mut velocity = Vec2::new(5., 0.);
+        velocity += conveyed.apply_velocity();
+        assert_eq!(velocity, Vec2::new(21., 0.));
+        velocity -= conveyed.take_applied_velocity();
+        assert_eq!(velocity, Vec2::new(5., 0.));
+        assert_eq!(conveyed.take_applied_velocity(), Vec2::ZERO);
+
+        // The velocity is overwritten by the game between the frames,
+        // but the conveyance still pushes the body.
+        velocity = Vec2::new(0., 3.);
+        velocity += conveyed.apply_velocity();
+        assert_eq!(velocity, Vec2::new(16., 3.));
+    }
+}
@@ -30,6 +30,111 @@ pub fn index_to_world(
     })
 }
 
+/// Get the index of the slot that contains the world position.
+pub fn world_to_index(
+    world: Vec2,
+    ty: TilemapType,
+    transform: &TilemapTransform,
+    pivot: Vec2,
+    slot_size: Vec2,
+) -> IVec2 {
+    let local = transform.inverse_transform_point(world);
+    match ty {
+        TilemapType::Square => (local / slot_size + pivot).floor().as_ivec2(),
+        TilemapType::Isometric => {
+            // Move the origin to the center of the tile at (0, 0).
+            let p = local / slot_size + pivot - 0.5;
+            Vec2::new(p.x + p.y, p.y - p.x).round().as_ivec2()
+        }
+        TilemapType::Hexagonal(legs) => {
+            let center = |index: IVec2| {
+                index_to_world(index, ty, &TilemapTransform::IDENTITY, pivot, slot_size)
+                    + slot_size / 2.
+            };
+
+            let p = local - slot_size / 2.;
+            let y = p.y / ((slot_size.y + legs as f32) / 2.) + pivot.y;
+            let x = p.x / slot_size.x + 0.5 * y + pivot.x;
+            let guess = Vec2::new(x, y).round().as_ivec2();
+
+            // The nearest center is the tile we are looking for.
+            let mut nearest = (guess, center(guess).distance_squared(local));
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let index = guess + IVec2::new(dx, dy);
+                    let dist = center(index).distance_squared(local);
+                    if dist < nearest.1 {
+                        nearest = (index, dist);
+                    }
+                }
+            }
+            nearest.0
+        }
+    }
+}
+
+/// The offset of the tiles on a flipped tilemap, matching the shaders.
+///
+/// The tile at `index` on a flipped tilemap covers the same area as the tile at
+/// `index * flip` on an unflipped one, moved by `-offset` in local space.
+pub fn axis_flip_offset(ty: TilemapType, slot_size: Vec2, axis_flip: TilemapAxisFlip) -> Vec2 {
+    let flipped = (1. - axis_flip.as_vec2()) / 2.;
+    match ty {
+        TilemapType::Square | TilemapType::Hexagonal(_) => flipped * slot_size,
+        TilemapType::Isometric => Vec2::new(0., (flipped.x + flipped.y) / 2. * slot_size.y),
+    }
+}
+
+/// Get the world position of the pivot of a slot, for tilemaps with `TilemapAxisFlip`.
+///
+/// With `TilemapAxisFlip::Y` on a square tilemap, `(0, 0)` is the top-left tile and y goes down,
+/// just like in Tiled and LDtk.
+pub fn index_to_world_flipped(
+    index: IVec2,
+    ty: TilemapType,
+    transform: &TilemapTransform,
+    pivot: Vec2,
+    slot_size: Vec2,
+    axis_flip: TilemapAxisFlip,
+) -> Vec2 {
+    index_to_world(
+        index * axis_flip.as_vec2().as_ivec2(),
+        ty,
+        transform,
+        pivot,
+        slot_size,
+    ) - transform.apply_rotation(axis_flip_offset(ty, slot_size, axis_flip))
+}
+
+/// Get the index of the slot that contains the world position, for tilemaps with `TilemapAxisFlip`.
+pub fn world_to_index_flipped(
+    world: Vec2,
+    ty: TilemapType,
+    transform: &TilemapTransform,
+    pivot: Vec2,
+    slot_size: Vec2,
+    axis_flip: TilemapAxisFlip,
+) -> IVec2 {
+    world_to_index(
+        world + transform.apply_rotation(axis_flip_offset(ty, slot_size, axis_flip)),
+        ty,
+        transform,
+        pivot,
+        slot_size,
+    ) * axis_flip.as_vec2().as_ivec2()
+}
+
+/// Map a slot corner between a flipped tilemap and an unflipped one. Works both ways.
+///
+/// Corner `(x, y)` is the corner of the slot `(x, y)` on the side of the smaller indices.
+/// Like `axis_flip_offset`, the corner on the flipped tilemap is at the same place as
+/// the mapped corner on an unflipped one, moved by `-offset`.
+#[inline]
+pub fn flip_corner(corner: IVec2, axis_flip: TilemapAxisFlip) -> IVec2 {
+    let flip = axis_flip.as_vec2().as_ivec2();
+    corner * flip + (IVec2::ONE - flip) / 2
+}
+
 /// Get the relative position of the pivot of a slot to the tilemap.
 pub fn index_to_rel(
     index: IVec2,
@@ -147,6 +252,30 @@ pub fn get_tile_collider_world(
         .collect()
 }
 
+/// Get the tile collider in world space, for tilemaps with `TilemapAxisFlip`.
+pub fn get_tile_collider_world_flipped(
+    origin: IVec2,
+    ty: TilemapType,
+    size: UVec2,
+    transform: &TilemapTransform,
+    pivot: Vec2,
+    slot_size: Vec2,
+    axis_flip: TilemapAxisFlip,
+) -> Vec<Vec2> {
+    // The flipped area is the mirrored area on an unflipped tilemap, moved by the offset.
+    let flip = axis_flip.as_vec2().as_ivec2();
+    let min = IVec2::select(
+        flip.cmplt(IVec2::ZERO),
+        -(origin + size.as_ivec2() - 1),
+        origin,
+    );
+    let offset = transform.apply_rotation(axis_flip_offset(ty, slot_size, axis_flip));
+    get_tile_collider_world(min, ty, size, transform, pivot, slot_size)
+        .into_iter()
+        .map(|v| v - offset)
+        .collect()
+}
+
 /// Calculate the size of the tilemap in world space.
 pub fn calculate_map_size(size: UVec2, slot_size: Vec2, ty: TilemapType) -> Vec2 {
     let sizef = size.as_vec2();
@@ -226,6 +355,118 @@ pub fn destaggerize_index(index: IVec2, staggered_mode: StaggerMode) -> IVec2 {
 mod test {
     use super::*;
 
+    #[test]
+    fn test_world_to_index() {
+        let slot_size = Vec2::new(32., 16.);
+        let transform = TilemapTransform {
+            translation: Vec2::new(10., -20.),
+            rotation: crate::tilemap::map::TilemapRotation::Cw90,
+            ..Default::default()
+        };
+
+        for ty in [
+            TilemapType::Square,
+            TilemapType::Isometric,
+            TilemapType::Hexagonal(8),
+        ] {
+            for index in [IVec2::ZERO, IVec2::new(3, -2), IVec2::new(-5, 7)] {
+                let world = index_to_world(index, ty, &transform, Vec2::ZERO, slot_size)
+                    + transform.apply_rotation(slot_size / 2.);
+                assert_eq!(
+                    world_to_index(world, ty, &transform, Vec2::ZERO, slot_size),
+                    index,
+                    "{:?}",
+                    ty
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_world_to_index_flipped() {
+        let slot_size = Vec2::new(32., 16.);
+        let transform = TilemapTransform {
+            translation: Vec2::new(10., -20.),
+            rotation: crate::tilemap::map::TilemapRotation::Cw90,
+            ..Default::default()
+        };
+
+        for ty in [
+            TilemapType::Square,
+            TilemapType::Isometric,
+            TilemapType::Hexagonal(8),
+        ] {
+            for flip in [
+                TilemapAxisFlip::NONE,
+                TilemapAxisFlip::X,
+                TilemapAxisFlip::Y,
+                TilemapAxisFlip::all(),
+            ] {
+                for index in [IVec2::ZERO, IVec2::new(3, -2), IVec2::new(-5, 7)] {
+                    let world =
+                        index_to_world_flipped(index, ty, &transform, Vec2::ZERO, slot_size, flip)
+                            + transform.apply_rotation(slot_size / 2.);
+                    assert_eq!(
+                        world_to_index_flipped(world, ty, &transform, Vec2::ZERO, slot_size, flip),
+                        index,
+                        "{:?} {:?}",
+                        ty,
+                        flip
+                    );
+                }
+            }
+        }
+
+        // Top-left origin, y-down.
+        let identity = TilemapTransform::IDENTITY;
+        let slot_size = Vec2::splat(16.);
+        assert_eq!(
+            index_to_world_flipped(
+                IVec2::new(1, 2),
+                TilemapType::Square,
+                &identity,
+                Vec2::ZERO,
+                slot_size,
+                TilemapAxisFlip::Y
+            ),
+            Vec2::new(16., -48.)
+        );
+        assert_eq!(
+            world_to_index_flipped(
+                Vec2::new(20., -1.),
+                TilemapType::Square,
+                &identity,
+                Vec2::ZERO,
+                slot_size,
+                TilemapAxisFlip::Y
+            ),
+            IVec2::new(1, 0)
+        );
+        assert_eq!(
+            flip_corner(IVec2::new(1, 3), TilemapAxisFlip::Y),
+            IVec2::new(1, -2)
+        );
+        assert_eq!(
+            get_tile_collider_world_flipped(
+                IVec2::ZERO,
+                TilemapType::Square,
+                UVec2::new(2, 1),
+                &identity,
+                Vec2::ZERO,
+                slot_size,
+                TilemapAxisFlip::Y
+            ),
+            get_tile_collider_world(
+                IVec2::new(0, -1),
+                TilemapType::Square,
+                UVec2::new(2, 1),
+                &identity,
+                Vec2::ZERO,
+                slot_size
+            )
+        );
+    }
+
     #[test]
     fn test_calc_staggered_size() {
         let size = UVec2::new(3, 3);
@@ -2,9 +2,10 @@ use bevy::{
     ecs::{
         component::Component,
         entity::Entity,
-        query::{Or, With},
+        query::{Has, Or, With},
         system::{Commands, ParallelCommands, Query},
     },
+    hierarchy::{BuildChildren, Parent},
     math::IVec2,
 };
 
@@ -28,10 +29,17 @@ pub struct DespawnedTile {
 
 pub fn despawn_applier(
     commands: ParallelCommands,
-    query: Query<Entity, Or<(With<DespawnedTilemap>, With<DespawnedTile>, With<DespawnMe>)>>,
+    query: Query<
+        (Entity, Has<Parent>),
+        Or<(With<DespawnedTilemap>, With<DespawnedTile>, With<DespawnMe>)>,
+    >,
 ) {
-    query.par_iter().for_each(|e| {
+    query.par_iter().for_each(|(e, has_parent)| {
         commands.command_scope(|mut c| {
+            // Keep the `Children` of the parent valid, like for tiles in `TilemapTileHierarchy`.
+            if has_parent {
+                c.entity(e).remove_parent();
+            }
             c.entity(e).despawn();
         });
     });
@@ -64,7 +72,7 @@ pub fn despawn_tiles(mut commands: Commands, query: Query<&Tile, With<DespawnMe>
     commands.spawn_batch(despawned_tiles);
 }
 
-#[cfg(feature = "physics")]
+#[cfg(feature = "physics_core")]
 pub fn despawn_physics_tilemaps(
     commands: ParallelCommands,
     query: Query<(Entity, &super::physics::PhysicsTilemap), With<DespawnMe>>,
@@ -0,0 +1,174 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::{Event, EventWriter},
+        system::{Commands, Query, Res},
+    },
+    reflect::Reflect,
+    time::Time,
+};
+
+use super::map::TilemapStorage;
+
+pub struct EntiTilesTilemapFadePlugin;
+
+impl Plugin for EntiTilesTilemapFadePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, tilemap_fader);
+
+        app.register_type::<TilemapOpacity>()
+            .register_type::<TilemapFade>()
+            .register_type::<TilemapFadeCompleted>();
+
+        app.add_event::<TilemapFadeCompleted>();
+    }
+}
+
+/// The opacity of the whole tilemap, multiplied with the tint of every tile.
+///
+/// Without this component, the tilemap is fully opaque.
+#[derive(Component, Debug, Clone, Copy, Reflect)]
+pub struct TilemapOpacity(pub f32);
+
+impl Default for TilemapOpacity {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
+/// Fades the `TilemapOpacity` over time. Removed when done.
+///
+/// ```ignore
+/// commands
+///     .entity(tilemap)
+///     .insert(TilemapFade::fade_out(0.5).then_despawn());
+/// ```
+#[derive(Component, Debug, Clone, Copy, Reflect)]
+pub struct TilemapFade {
+    /// `None` means starting from the current opacity.
+    pub from: Option<f32>,
+    pub to: f32,
+    /// In seconds.
+    pub duration: f32,
+    /// Despawn the tilemap using `TilemapStorage::despawn` when the fade is completed.
+    pub despawn_on_complete: bool,
+    pub(crate) elapsed: f32,
+}
+
+impl TilemapFade {
+    /// Fade from fully transparent to fully opaque.
+    pub fn fade_in(duration: f32) -> Self {
+        Self {
+            from: Some(0.),
+            ..Self::fade_to(1., duration)
+        }
+    }
+
+    /// Fade from the current opacity to fully transparent.
+    pub fn fade_out(duration: f32) -> Self {
+        Self::fade_to(0., duration)
+    }
+
+    /// Fade from the current opacity to `opacity`.
+    pub fn fade_to(opacity: f32, duration: f32) -> Self {
+        Self {
+            from: None,
+            to: opacity,
+            duration,
+            despawn_on_complete: false,
+            elapsed: 0.,
+        }
+    }
+
+    pub fn then_despawn(mut self) -> Self {
+        self.despawn_on_complete = true;
+        self
+    }
+
+    #[inline]
+    pub fn is_completed(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    /// Advance the fade and returns the new opacity.
+    pub fn tick(&mut self, delta: f32, current: f32) -> f32 {
+        let from = *self.from.get_or_insert(current);
+        self.elapsed += delta;
+
+        if self.is_completed() {
+            self.to
+        } else {
+            from + (self.to - from) * (self.elapsed / self.duration)
+        }
+    }
+}
+
+/// Fired when a `TilemapFade` is completed.
+#[derive(Event, Debug, Clone, Copy, Reflect)]
+pub struct TilemapFadeCompleted {
+    pub tilemap: Entity,
+    /// True if the tilemap is being despawned.
+    pub despawned: bool,
+}
+
+pub fn tilemap_fader(
+    mut commands: Commands,
+    mut tilemaps_query: Query<(
+        Entity,
+        &mut TilemapFade,
+        Option<&mut TilemapOpacity>,
+        &mut TilemapStorage,
+    )>,
+    time: Res<Time>,
+    mut completed: EventWriter<TilemapFadeCompleted>,
+) {
+    tilemaps_query
+        .iter_mut()
+        .for_each(|(entity, mut fade, opacity, mut storage)| {
+            let current = opacity.as_ref().map(|o| o.0).unwrap_or(1.);
+            let new = fade.tick(time.delta_seconds(), current);
+            match opacity {
+                Some(mut opacity) => opacity.0 = new,
+                None => {
+                    commands.entity(entity).insert(TilemapOpacity(new));
+                }
+            }
+
+            if !fade.is_completed() {
+                return;
+            }
+
+            commands.entity(entity).remove::<TilemapFade>();
+            if fade.despawn_on_complete {
+                storage.despawn(&mut commands);
+            }
+            completed.send(TilemapFadeCompleted {
+                tilemap: entity,
+                despawned: fade.despawn_on_complete,
+            });
+        });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fade() {
+        let mut fade = TilemapFade::fade_out(2.);
+        assert_eq!(fade.tick(0.5, 0.8), 0.6);
+        assert_eq!(fade.tick(0.5, 0.6), 0.4);
+        assert!(!fade.is_completed());
+        assert_eq!(fade.tick(1.5, 0.4), 0.);
+        assert!(fade.is_completed());
+
+        let mut fade = TilemapFade::fade_in(1.);
+        assert_eq!(fade.tick(0.25, 1.), 0.25);
+
+        let mut fade = TilemapFade::fade_to(0.5, 0.);
+        assert_eq!(fade.tick(0., 1.), 0.5);
+        assert!(fade.is_completed());
+    }
+}
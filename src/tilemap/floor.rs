@@ -0,0 +1,247 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        change_detection::{DetectChanges, Ref},
+        component::Component,
+        entity::{Entity, EntityHashMap},
+        event::{Event, EventWriter},
+        query::With,
+        system::{Local, Query},
+    },
+    math::IVec2,
+    reflect::Reflect,
+    render::view::Visibility,
+};
+
+use super::map::TilemapStorage;
+
+#[cfg(feature = "algorithm")]
+use crate::algorithm::connection::TilemapConnections;
+
+pub struct EntiTilesTilemapFloorPlugin;
+
+impl Plugin for EntiTilesTilemapFloorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, floor_visibility_updater);
+
+        app.register_type::<TilemapFloors>()
+            .register_type::<TilemapFloorChanged>();
+
+        app.add_event::<TilemapFloorChanged>();
+    }
+}
+
+/// A stack of tilemaps as the floors of a building or the levels of a dungeon.
+/// Insert this on any entity, the tilemaps are not modified except their `Visibility`.
+///
+/// All the floors should have the same tile indices, so the tile at `(3, 4)` on
+/// a floor is right above the tile at `(3, 4)` on the floor below.
+#[derive(Component, Debug, Clone, Reflect)]
+pub struct TilemapFloors {
+    /// From the bottom to the top.
+    floors: Vec<Entity>,
+    current: usize,
+    /// Hide the floors above the current one, so the player can see inside.
+    pub hide_above: bool,
+}
+
+impl TilemapFloors {
+    /// `floors` are from the bottom to the top. The bottom floor is the current one.
+    pub fn new(floors: Vec<Entity>) -> Self {
+        assert!(!floors.is_empty(), "There should be at least one floor!");
+        Self {
+            floors,
+            current: 0,
+            hide_above: true,
+        }
+    }
+
+    pub fn with_current(mut self, level: usize) -> Self {
+        self.set_current(level);
+        self
+    }
+
+    pub fn with_hide_above(mut self, hide_above: bool) -> Self {
+        self.hide_above = hide_above;
+        self
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.floors.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.floors.is_empty()
+    }
+
+    #[inline]
+    pub fn floors(&self) -> &[Entity] {
+        &self.floors
+    }
+
+    #[inline]
+    pub fn get(&self, level: usize) -> Option<Entity> {
+        self.floors.get(level).copied()
+    }
+
+    #[inline]
+    pub fn level_of(&self, tilemap: Entity) -> Option<usize> {
+        self.floors.iter().position(|floor| *floor == tilemap)
+    }
+
+    #[inline]
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    #[inline]
+    pub fn current_tilemap(&self) -> Entity {
+        self.floors[self.current]
+    }
+
+    /// Switch to the level, clamped to the floors available.
+    #[inline]
+    pub fn set_current(&mut self, level: usize) {
+        self.current = level.min(self.floors.len() - 1);
+    }
+
+    #[inline]
+    pub fn go_up(&mut self) {
+        self.set_current(self.current + 1);
+    }
+
+    #[inline]
+    pub fn go_down(&mut self) {
+        self.set_current(self.current.saturating_sub(1));
+    }
+
+    /// Add a floor on the top.
+    pub fn push(&mut self, tilemap: Entity) {
+        self.floors.push(tilemap);
+    }
+
+    /// The tiles at the index on every floor, from the bottom to the top.
+    pub fn get_tiles(
+        &self,
+        index: IVec2,
+        storages: &Query<&TilemapStorage>,
+    ) -> Vec<(usize, Entity)> {
+        self.floors
+            .iter()
+            .enumerate()
+            .filter_map(|(level, floor)| {
+                storages
+                    .get(*floor)
+                    .ok()
+                    .and_then(|storage| storage.get(index))
+                    .map(|tile| (level, tile))
+            })
+            .collect()
+    }
+
+    /// The top most tile at the index on the current floor or the floors below,
+    /// which is what the player sees from above.
+    pub fn get_visible_tile(
+        &self,
+        index: IVec2,
+        storages: &Query<&TilemapStorage>,
+    ) -> Option<(usize, Entity)> {
+        self.get_tiles(index, storages)
+            .into_iter()
+            .take_while(|(level, _)| *level <= self.current)
+            .last()
+    }
+
+    /// Connect the stairs or the ladder at `lower` on the floor `level` to `upper`
+    /// on the floor above, so `WorldPathFinder` can go between the floors.
+    #[cfg(feature = "algorithm")]
+    pub fn add_stairs(
+        &self,
+        connections: &mut TilemapConnections,
+        level: usize,
+        lower: IVec2,
+        upper: IVec2,
+        cost: u32,
+    ) {
+        let (Some(lower_floor), Some(upper_floor)) = (self.get(level), self.get(level + 1)) else {
+            panic!("There's no floor above level {}!", level);
+        };
+        connections.connect((lower_floor, lower), (upper_floor, upper), cost);
+    }
+}
+
+/// Fired when the current floor of `TilemapFloors` changes.
+#[derive(Event, Debug, Clone, Copy, Reflect)]
+pub struct TilemapFloorChanged {
+    /// The entity with `TilemapFloors`.
+    pub floors: Entity,
+    pub previous: usize,
+    pub current: usize,
+}
+
+pub fn floor_visibility_updater(
+    floors_query: Query<(Entity, Ref<TilemapFloors>)>,
+    mut tilemaps_query: Query<&mut Visibility, With<TilemapStorage>>,
+    mut previous_levels: Local<EntityHashMap<usize>>,
+    mut changed: EventWriter<TilemapFloorChanged>,
+) {
+    floors_query.iter().for_each(|(entity, floors)| {
+        if !floors.is_changed() {
+            return;
+        }
+
+        let current = floors.current();
+        if let Some(previous) = previous_levels.insert(entity, current) {
+            if previous != current {
+                changed.send(TilemapFloorChanged {
+                    floors: entity,
+                    previous,
+                    current,
+                });
+            }
+        }
+
+        floors
+            .floors()
+            .iter()
+            .enumerate()
+            .for_each(|(level, floor)| {
+                let Ok(mut visibility) = tilemaps_query.get_mut(*floor) else {
+                    return;
+                };
+                let new = if floors.hide_above && level > current {
+                    Visibility::Hidden
+                } else {
+                    Visibility::Inherited
+                };
+                if *visibility != new {
+                    *visibility = new;
+                }
+            });
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_floors() {
+        let floors = (0..3).map(Entity::from_raw).collect::<Vec<_>>();
+        let mut stack = TilemapFloors::new(floors.clone());
+        assert_eq!(stack.current_tilemap(), floors[0]);
+
+        stack.go_down();
+        assert_eq!(stack.current(), 0);
+        stack.go_up();
+        stack.go_up();
+        stack.go_up();
+        assert_eq!(stack.current(), 2);
+        assert_eq!(stack.level_of(floors[1]), Some(1));
+
+        let stack = TilemapFloors::new(floors).with_current(10);
+        assert_eq!(stack.current(), 2);
+    }
+}
@@ -0,0 +1,253 @@
+use bevy::{
+    ecs::component::Component,
+    math::{IVec2, Vec2},
+    reflect::Reflect,
+    render::color::Color,
+};
+
+use crate::math::TileArea;
+
+use super::{
+    chunking::storage::ChunkedStorage,
+    coordinates,
+    map::{TilemapAxisFlip, TilemapTransform, TilemapType},
+};
+
+/// A 2d vector for each tile, like the wind, the water current or the direction of a conveyor.
+///
+/// Goes on the tilemap entity, and is sampled by tile index with `sample` or by
+/// world position with `sample_world`. The vectors are in tiles, so a vector of
+/// `(1, 0)` moves one tile to the right. Indices without a vector, including the
+/// ones without a tile, are treated as still.
+///
+/// Add `TileConveyed` to the entities that should be carried by the field.
+/// Insert `TilemapFlowArrows` as well to draw the vectors with the `debug` feature.
+#[derive(Component, Debug, Clone, Reflect)]
+#[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
+pub struct TilemapFlowField {
+    pub(crate) storage: ChunkedStorage<Vec2>,
+}
+
+impl Default for TilemapFlowField {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TilemapFlowField {
+    /// Create a new flow field with default chunk size.
+    ///
+    /// Use `new_with_chunk_size` to specify a custom chunk size.
+    pub fn new() -> Self {
+        Self {
+            storage: ChunkedStorage::default(),
+        }
+    }
+
+    /// Create a new flow field with custom chunk size.
+    pub fn new_with_chunk_size(chunk_size: u32) -> Self {
+        Self {
+            storage: ChunkedStorage::new(chunk_size),
+        }
+    }
+
+    /// Get the vector of a tile.
+    #[inline]
+    pub fn get(&self, index: IVec2) -> Option<Vec2> {
+        self.storage.get_elem(index).cloned()
+    }
+
+    #[inline]
+    pub fn set(&mut self, index: IVec2, flow: Vec2) {
+        self.storage.set_elem(index, flow);
+    }
+
+    #[inline]
+    pub fn remove(&mut self, index: IVec2) -> Option<Vec2> {
+        self.storage.remove_elem(index)
+    }
+
+    /// Fill a rectangle area with the same vector.
+    pub fn fill_rect(&mut self, area: TileArea, flow: Vec2) {
+        area.aabb()
+            .into_iter()
+            .for_each(|index| self.set(index, flow));
+    }
+
+    /// Fill a rectangle area with vectors returned by `flow`, like from a noise function.
+    pub fn fill_rect_custom(&mut self, area: TileArea, flow: impl Fn(IVec2) -> Option<Vec2>) {
+        area.aabb().into_iter().for_each(|index| {
+            if let Some(f) = flow(index) {
+                self.set(index, f);
+            }
+        });
+    }
+
+    /// Iterate over the tiles with a vector.
+    pub fn iter(&self) -> impl Iterator<Item = (IVec2, Vec2)> + '_ {
+        self.storage
+            .chunked_iter_some()
+            .map(|(chunk_index, in_chunk_index, flow)| {
+                (
+                    self.storage
+                        .inverse_transform_index(chunk_index, in_chunk_index),
+                    *flow,
+                )
+            })
+    }
+
+    /// Get the vector of a tile, or zero if it doesn't have one.
+    #[inline]
+    pub fn sample(&self, index: IVec2) -> Vec2 {
+        self.get(index).unwrap_or_default()
+    }
+
+    /// Bilinearly interpolate the vectors of the four nearest tiles,
+    /// so things drifting in the field don't turn abruptly at the tile borders.
+    ///
+    /// `position` is in tiles, where the center of tile `(x, y)` is `(x + 0.5, y + 0.5)`.
+    pub fn sample_smooth(&self, position: Vec2) -> Vec2 {
+        let p = position - 0.5;
+        let min = p.floor();
+        let t = p - min;
+        let min = min.as_ivec2();
+
+        let bottom = self.sample(min).lerp(self.sample(min + IVec2::X), t.x);
+        let top = self
+            .sample(min + IVec2::Y)
+            .lerp(self.sample(min + IVec2::ONE), t.x);
+        bottom.lerp(top, t.y)
+    }
+
+    /// Get the vector in tiles at a world position.
+    ///
+    /// The vector is interpolated on square tilemaps, and taken from the
+    /// tile under the position on the others.
+    pub fn sample_world(
+        &self,
+        world: Vec2,
+        ty: TilemapType,
+        transform: &TilemapTransform,
+        pivot: Vec2,
+        slot_size: Vec2,
+        axis_flip: TilemapAxisFlip,
+    ) -> Vec2 {
+        match ty {
+            TilemapType::Square => {
+                let local = transform.inverse_transform_point(world)
+                    + coordinates::axis_flip_offset(ty, slot_size, axis_flip);
+                // The position on the unflipped tilemap, mirrored so the centers
+                // of the flipped tiles are still at `index + 0.5`.
+                let flip = axis_flip.as_vec2();
+                self.sample_smooth((local / slot_size + pivot) * flip + (1. - flip) / 2.)
+            }
+            _ => self.sample(coordinates::world_to_index_flipped(
+                world, ty, transform, pivot, slot_size, axis_flip,
+            )),
+        }
+    }
+
+    /// Get the velocity in world units at a world position, to push entities along the field.
+    ///
+    /// `speed` is how many tiles per second a vector of length 1 moves.
+    /// Only works on square tilemaps, as the other shapes don't have a uniform tile size.
+    pub fn velocity_world(
+        &self,
+        world: Vec2,
+        transform: &TilemapTransform,
+        pivot: Vec2,
+        slot_size: Vec2,
+        axis_flip: TilemapAxisFlip,
+        speed: f32,
+    ) -> Vec2 {
+        let flow = self.sample_world(
+            world,
+            TilemapType::Square,
+            transform,
+            pivot,
+            slot_size,
+            axis_flip,
+        );
+        transform.apply_rotation(flow * axis_flip.as_vec2() * slot_size * speed)
+    }
+}
+
+/// Draws the vectors of the `TilemapFlowField` as arrows. Requires the `debug` feature.
+#[derive(Component, Debug, Clone, Copy, Reflect)]
+pub struct TilemapFlowArrows {
+    pub color: Color,
+    /// The length of the arrow of a vector of length 1, in slots.
+    pub scale: f32,
+}
+
+impl Default for TilemapFlowArrows {
+    fn default() -> Self {
+        Self {
+            color: Color::CYAN,
+            scale: 0.5,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_flow_sampling() {
+        let mut flow = TilemapFlowField::new_with_chunk_size(4);
+        flow.fill_rect(TileArea::new(IVec2::ZERO, (2, 2).into()), Vec2::X);
+        flow.set(IVec2::new(1, 1), Vec2::Y);
+        assert_eq!(flow.iter().count(), 4);
+        assert_eq!(flow.sample(IVec2::new(5, 5)), Vec2::ZERO);
+
+        assert_eq!(flow.sample_smooth(Vec2::new(0.5, 0.5)), Vec2::X);
+        assert_eq!(flow.sample_smooth(Vec2::new(1., 1.)), Vec2::new(0.75, 0.25));
+        assert_eq!(flow.sample_smooth(Vec2::new(2.5, 0.5)), Vec2::ZERO);
+
+        let transform = TilemapTransform::default();
+        let world = flow.sample_world(
+            Vec2::new(24., 24.),
+            TilemapType::Square,
+            &transform,
+            Vec2::ZERO,
+            Vec2::splat(16.),
+            TilemapAxisFlip::NONE,
+        );
+        assert_eq!(world, Vec2::Y);
+        assert_eq!(
+            flow.velocity_world(
+                Vec2::new(8., 8.),
+                &transform,
+                Vec2::ZERO,
+                Vec2::splat(16.),
+                TilemapAxisFlip::NONE,
+                2.
+            ),
+            Vec2::new(32., 0.)
+        );
+
+        // With the y axis flipped, tile (1, 1) is below the x axis and its
+        // vector points down.
+        let flipped = flow.sample_world(
+            Vec2::new(24., -24.),
+            TilemapType::Square,
+            &transform,
+            Vec2::ZERO,
+            Vec2::splat(16.),
+            TilemapAxisFlip::Y,
+        );
+        assert_eq!(flipped, Vec2::Y);
+        assert_eq!(
+            flow.velocity_world(
+                Vec2::new(24., -24.),
+                &transform,
+                Vec2::ZERO,
+                Vec2::splat(16.),
+                TilemapAxisFlip::Y,
+                1.
+            ),
+            Vec2::new(0., -16.)
+        );
+    }
+}
@@ -0,0 +1,265 @@
+use bevy::{
+    ecs::{component::Component, system::Commands},
+    math::{IVec2, Vec2},
+    reflect::Reflect,
+    utils::HashMap,
+};
+
+use crate::math::TileArea;
+
+use super::{
+    buffers::TileBuilderBuffer,
+    chunking::storage::ChunkedStorage,
+    coordinates,
+    map::{TilemapAxisFlip, TilemapStorage, TilemapTransform, TilemapType},
+    tile::TileBuilder,
+};
+
+pub type TileHeight = i32;
+
+bitflags::bitflags! {
+    /// The sides of a tile that drop to a lower neighbour.
+    ///
+    /// The sides are in index space, so they are the four sides of square and isometric tiles.
+    /// Hexagonal tiles have six sides, so they are not supported.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct CliffEdges: u32 {
+        const NONE = 0;
+        const UP = 1;
+        const RIGHT = 1 << 1;
+        const DOWN = 1 << 2;
+        const LEFT = 1 << 3;
+    }
+}
+
+impl CliffEdges {
+    const SIDES: [(CliffEdges, IVec2); 4] = [
+        (CliffEdges::UP, IVec2::Y),
+        (CliffEdges::RIGHT, IVec2::X),
+        (CliffEdges::DOWN, IVec2::NEG_Y),
+        (CliffEdges::LEFT, IVec2::NEG_X),
+    ];
+}
+
+/// A height value for each tile, for top-down games with multiple levels of terrain.
+///
+/// Goes on the tilemap entity. The heights describe the terrain rather than the
+/// tiles, which are usually generated from them: use `cliff_tiles` to get the
+/// cliff tiles along the height changes, so heights can be set before any tile exists.
+#[derive(Component, Debug, Clone, Reflect)]
+#[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
+pub struct TilemapHeights {
+    pub(crate) storage: ChunkedStorage<TileHeight>,
+}
+
+impl Default for TilemapHeights {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TilemapHeights {
+    /// Create a new height layer with default chunk size.
+    ///
+    /// Use `new_with_chunk_size` to specify a custom chunk size.
+    pub fn new() -> Self {
+        Self {
+            storage: ChunkedStorage::default(),
+        }
+    }
+
+    /// Create a new height layer with custom chunk size.
+    pub fn new_with_chunk_size(chunk_size: u32) -> Self {
+        Self {
+            storage: ChunkedStorage::new(chunk_size),
+        }
+    }
+
+    /// Get the height of a tile.
+    #[inline]
+    pub fn get(&self, index: IVec2) -> Option<TileHeight> {
+        self.storage.get_elem(index).cloned()
+    }
+
+    /// Get the height at a world position.
+    #[inline]
+    pub fn get_world(
+        &self,
+        world: Vec2,
+        ty: TilemapType,
+        transform: &TilemapTransform,
+        pivot: Vec2,
+        slot_size: Vec2,
+        axis_flip: TilemapAxisFlip,
+    ) -> Option<TileHeight> {
+        self.get(coordinates::world_to_index_flipped(
+            world, ty, transform, pivot, slot_size, axis_flip,
+        ))
+    }
+
+    #[inline]
+    pub fn set(&mut self, index: IVec2, height: TileHeight) {
+        self.storage.set_elem(index, height);
+    }
+
+    #[inline]
+    pub fn remove(&mut self, index: IVec2) -> Option<TileHeight> {
+        self.storage.remove_elem(index)
+    }
+
+    /// Fill a rectangle area with the same height.
+    pub fn fill_rect(&mut self, area: TileArea, height: TileHeight) {
+        area.aabb()
+            .into_iter()
+            .for_each(|index| self.set(index, height));
+    }
+
+    /// Fill a rectangle area with heights returned by `height`, like from a noise function.
+    pub fn fill_rect_custom(
+        &mut self,
+        area: TileArea,
+        height: impl Fn(IVec2) -> Option<TileHeight>,
+    ) {
+        area.aabb().into_iter().for_each(|index| {
+            if let Some(h) = height(index) {
+                self.set(index, h);
+            }
+        });
+    }
+
+    /// The sides of the tile where the neighbour is lower.
+    /// Neighbours without a height are ignored.
+    pub fn cliff_edges(&self, index: IVec2) -> CliffEdges {
+        let Some(height) = self.get(index) else {
+            return CliffEdges::NONE;
+        };
+
+        CliffEdges::SIDES
+            .into_iter()
+            .filter(|(_, offset)| self.get(index + *offset).is_some_and(|h| h < height))
+            .fold(CliffEdges::NONE, |acc, (edge, _)| acc | edge)
+    }
+
+    /// Iterate over the tiles with at least one cliff edge.
+    pub fn iter_cliffs(&self) -> impl Iterator<Item = (IVec2, CliffEdges)> + '_ {
+        self.storage
+            .chunked_iter_some()
+            .map(|(chunk_index, in_chunk_index, _)| {
+                let index = self
+                    .storage
+                    .inverse_transform_index(chunk_index, in_chunk_index);
+                (index, self.cliff_edges(index))
+            })
+            .filter(|(_, edges)| !edges.is_empty())
+    }
+
+    /// Pick the cliff tile for every tile with cliff edges. Tiles whose edges
+    /// are not in the tile set are skipped if there's no fallback.
+    ///
+    /// The result can be applied with `TilemapStorage::fill_with_buffer` at `IVec2::ZERO`,
+    /// or use `TilemapPostProcessor::with_cliffs` to place them before the post processing rules.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tilemap is hexagonal, see `CliffEdges`.
+    pub fn cliff_tiles(&self, ty: TilemapType, tile_set: &CliffTileSet) -> TileBuilderBuffer {
+        assert!(
+            !matches!(ty, TilemapType::Hexagonal(_)),
+            "Cliff tiles are not supported on hexagonal tilemaps!"
+        );
+
+        let mut buffer = TileBuilderBuffer::new();
+        self.iter_cliffs().for_each(|(index, edges)| {
+            if let Some(tile) = tile_set.get(edges) {
+                buffer.set(index, tile.clone());
+            }
+        });
+        buffer
+    }
+
+    /// Set the cliff tiles directly on the tilemap.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tilemap is hexagonal, see `CliffEdges`.
+    pub fn apply_cliffs(
+        &self,
+        commands: &mut Commands,
+        storage: &mut TilemapStorage,
+        ty: TilemapType,
+        tile_set: &CliffTileSet,
+    ) {
+        storage.fill_with_buffer(commands, IVec2::ZERO, self.cliff_tiles(ty, tile_set));
+    }
+}
+
+/// The tiles to use for each combination of cliff edges.
+#[derive(Debug, Default, Clone, Reflect)]
+pub struct CliffTileSet {
+    pub(crate) tiles: HashMap<u32, TileBuilder>,
+    /// Used when the combination of edges is not in the set.
+    pub fallback: Option<TileBuilder>,
+}
+
+impl CliffTileSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_tile(mut self, edges: CliffEdges, tile: TileBuilder) -> Self {
+        self.tiles.insert(edges.bits(), tile);
+        self
+    }
+
+    pub fn with_fallback(mut self, tile: TileBuilder) -> Self {
+        self.fallback = Some(tile);
+        self
+    }
+
+    #[inline]
+    pub fn get(&self, edges: CliffEdges) -> Option<&TileBuilder> {
+        self.tiles.get(&edges.bits()).or(self.fallback.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::math::UVec2;
+
+    use crate::tilemap::tile::TileLayer;
+
+    use super::*;
+
+    #[test]
+    fn test_cliffs() {
+        let mut heights = TilemapHeights::new_with_chunk_size(4);
+        heights.fill_rect(TileArea::new(IVec2::ZERO, UVec2::splat(5)), 0);
+        heights.fill_rect(TileArea::new(IVec2::ONE, UVec2::new(3, 2)), 1);
+        heights.set(IVec2::new(2, 2), 2);
+
+        assert_eq!(
+            heights.cliff_edges(IVec2::new(1, 1)),
+            CliffEdges::DOWN | CliffEdges::LEFT
+        );
+        assert_eq!(heights.cliff_edges(IVec2::new(2, 1)), CliffEdges::DOWN);
+        assert_eq!(heights.cliff_edges(IVec2::new(2, 2)), CliffEdges::all());
+        assert_eq!(heights.cliff_edges(IVec2::ZERO), CliffEdges::NONE);
+        assert_eq!(heights.iter_cliffs().count(), 6);
+
+        let tile = |texture| TileBuilder::new().with_layer(0, TileLayer::no_flip(texture));
+        let tile_set = CliffTileSet::new()
+            .with_tile(CliffEdges::DOWN, tile(1))
+            .with_tile(CliffEdges::DOWN | CliffEdges::LEFT, tile(2));
+        let buffer = heights.cliff_tiles(TilemapType::Square, &tile_set);
+        assert_eq!(buffer.tiles.len(), 2);
+
+        let buffer = heights.cliff_tiles(TilemapType::Isometric, &tile_set.with_fallback(tile(0)));
+        assert_eq!(buffer.tiles.len(), 6);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_hexagonal_cliffs() {
+        TilemapHeights::new().cliff_tiles(TilemapType::Hexagonal(8), &CliffTileSet::new());
+    }
+}
@@ -0,0 +1,679 @@
+use bevy::{
+    ecs::{
+        change_detection::{DetectChanges, DetectChangesMut},
+        component::Component,
+        entity::Entity,
+        removal_detection::RemovedComponents,
+        system::{Commands, Query},
+        world::Ref,
+    },
+    math::{IVec2, Vec2},
+    reflect::Reflect,
+    utils::{HashMap, HashSet},
+};
+
+use super::{
+    buffers::TileBuilderBuffer,
+    coordinates,
+    map::{
+        TilePivot, TileRenderSize, TilemapAxisFlip, TilemapSlotSize, TilemapStorage,
+        TilemapTransform, TilemapType,
+    },
+    tile::TileBuilder,
+};
+
+/*
+ * The directions and the corners of a hexagonal tile.
+ * The edges are named by the neighbour on the other side.
+ *
+ *      Top
+ *    UL / \ UR
+ *  L   |   |   R
+ *    DL \ / DR
+ *     Bottom
+ */
+
+/// The six neighbours of a hexagonal tile, counter-clockwise from the right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+#[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
+pub enum HexDirection {
+    Right,
+    UpRight,
+    UpLeft,
+    Left,
+    DownLeft,
+    DownRight,
+}
+
+impl HexDirection {
+    pub const ALL: [HexDirection; 6] = [
+        HexDirection::Right,
+        HexDirection::UpRight,
+        HexDirection::UpLeft,
+        HexDirection::Left,
+        HexDirection::DownLeft,
+        HexDirection::DownRight,
+    ];
+
+    /// The offset to the neighbour in this direction.
+    pub fn offset(self) -> IVec2 {
+        match self {
+            HexDirection::Right => IVec2::X,
+            HexDirection::UpRight => IVec2::ONE,
+            HexDirection::UpLeft => IVec2::Y,
+            HexDirection::Left => IVec2::NEG_X,
+            HexDirection::DownLeft => IVec2::NEG_ONE,
+            HexDirection::DownRight => IVec2::NEG_Y,
+        }
+    }
+
+    pub fn opposite(self) -> HexDirection {
+        match self {
+            HexDirection::Right => HexDirection::Left,
+            HexDirection::UpRight => HexDirection::DownLeft,
+            HexDirection::UpLeft => HexDirection::DownRight,
+            HexDirection::Left => HexDirection::Right,
+            HexDirection::DownLeft => HexDirection::UpRight,
+            HexDirection::DownRight => HexDirection::UpLeft,
+        }
+    }
+}
+
+/// An edge between two hexagonal tiles, for things along the tile borders like
+/// roads, rivers and walls.
+///
+/// Every edge is stored on the tile on its left or bottom side, so the same edge
+/// seen from both tiles is equal.
+///
+/// The neighbours are the ones of an unflipped tilemap. On a tilemap with `TilemapAxisFlip`,
+/// the tile `index` of an edge or a corner is the tile `index * axis_flip.as_vec2().as_ivec2()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+#[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
+pub struct HexEdge {
+    pub(crate) index: IVec2,
+    /// Always `Right`, `UpRight` or `UpLeft`.
+    pub(crate) direction: HexDirection,
+}
+
+impl HexEdge {
+    /// The edge of the tile in the direction.
+    pub fn new(index: IVec2, direction: HexDirection) -> Self {
+        match direction {
+            HexDirection::Right | HexDirection::UpRight | HexDirection::UpLeft => {
+                Self { index, direction }
+            }
+            _ => Self {
+                index: index + direction.offset(),
+                direction: direction.opposite(),
+            },
+        }
+    }
+
+    /// The six edges of a tile, counter-clockwise from the right.
+    pub fn of_tile(index: IVec2) -> [HexEdge; 6] {
+        HexDirection::ALL.map(|direction| HexEdge::new(index, direction))
+    }
+
+    /// The tile this edge is stored on.
+    #[inline]
+    pub fn index(&self) -> IVec2 {
+        self.index
+    }
+
+    /// The direction from `index` to the other tile.
+    #[inline]
+    pub fn direction(&self) -> HexDirection {
+        self.direction
+    }
+
+    /// The two tiles on both sides of the edge.
+    #[inline]
+    pub fn tiles(&self) -> [IVec2; 2] {
+        [self.index, self.index + self.direction.offset()]
+    }
+
+    /// The two ends of the edge, the lower one first.
+    pub fn corners(&self) -> [HexCorner; 2] {
+        let index = self.index;
+        match self.direction {
+            HexDirection::Right => [
+                HexCorner::new(index + IVec2::NEG_Y, HexCornerSide::Top),
+                HexCorner::new(index + IVec2::ONE, HexCornerSide::Bottom),
+            ],
+            HexDirection::UpRight => [
+                HexCorner::new(index + IVec2::ONE, HexCornerSide::Bottom),
+                HexCorner::new(index, HexCornerSide::Top),
+            ],
+            _ => [
+                HexCorner::new(index + IVec2::Y, HexCornerSide::Bottom),
+                HexCorner::new(index, HexCornerSide::Top),
+            ],
+        }
+    }
+
+    /// The four edges sharing a corner with this one.
+    pub fn neighbours(&self) -> [HexEdge; 4] {
+        let [a, b] = self.corners().map(|corner| {
+            let mut others = corner.edges().into_iter().filter(|e| e != self);
+            [others.next().unwrap(), others.next().unwrap()]
+        });
+        [a[0], a[1], b[0], b[1]]
+    }
+
+    /// The world position of the middle of the edge.
+    pub fn to_world(
+        &self,
+        legs: u32,
+        transform: &TilemapTransform,
+        pivot: Vec2,
+        slot_size: Vec2,
+        axis_flip: TilemapAxisFlip,
+    ) -> Vec2 {
+        let [a, b] = self
+            .corners()
+            .map(|corner| corner.to_world(legs, transform, pivot, slot_size, axis_flip));
+        (a + b) / 2.
+    }
+
+    /// The nearest edge to the world position.
+    pub fn from_world(
+        world: Vec2,
+        legs: u32,
+        transform: &TilemapTransform,
+        pivot: Vec2,
+        slot_size: Vec2,
+        axis_flip: TilemapAxisFlip,
+    ) -> HexEdge {
+        let index = unflipped_index(world, legs, transform, pivot, slot_size, axis_flip);
+        HexEdge::of_tile(index)
+            .into_iter()
+            .min_by(|a, b| {
+                let a = a.to_world(legs, transform, pivot, slot_size, axis_flip);
+                let b = b.to_world(legs, transform, pivot, slot_size, axis_flip);
+                a.distance_squared(world)
+                    .total_cmp(&b.distance_squared(world))
+            })
+            .unwrap()
+    }
+
+    /// The index of the slot of this edge in the overlay tilemap. See `HexEdgeOverlay`.
+    #[inline]
+    pub fn overlay_index(&self) -> IVec2 {
+        self.index * 2 + self.direction.offset()
+    }
+
+    /// The edge in the slot of the overlay tilemap.
+    /// Returns `None` for the slots on the centers of the tiles.
+    pub fn from_overlay_index(index: IVec2) -> Option<HexEdge> {
+        let direction = match (index.x & 1, index.y & 1) {
+            (1, 0) => HexDirection::Right,
+            (1, 1) => HexDirection::UpRight,
+            (0, 1) => HexDirection::UpLeft,
+            _ => return None,
+        };
+        Some(HexEdge {
+            index: (index - direction.offset()) / 2,
+            direction,
+        })
+    }
+}
+
+/// Which corner of the tile a `HexCorner` is stored on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+#[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
+pub enum HexCornerSide {
+    Top,
+    Bottom,
+}
+
+/// A corner where three hexagonal tiles meet, for things like the settlements in Catan.
+///
+/// Every corner is either the top corner or the bottom corner of exactly one tile,
+/// so a corner has only one representation. The other four corners of a tile are
+/// the top and bottom corners of its neighbours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+#[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
+pub struct HexCorner {
+    pub index: IVec2,
+    pub side: HexCornerSide,
+}
+
+impl HexCorner {
+    #[inline]
+    pub fn new(index: IVec2, side: HexCornerSide) -> Self {
+        Self { index, side }
+    }
+
+    /// The six corners of a tile, counter-clockwise from the top.
+    pub fn of_tile(index: IVec2) -> [HexCorner; 6] {
+        [
+            HexCorner::new(index, HexCornerSide::Top),
+            HexCorner::new(index + IVec2::Y, HexCornerSide::Bottom),
+            HexCorner::new(index + IVec2::NEG_ONE, HexCornerSide::Top),
+            HexCorner::new(index, HexCornerSide::Bottom),
+            HexCorner::new(index + IVec2::NEG_Y, HexCornerSide::Top),
+            HexCorner::new(index + IVec2::ONE, HexCornerSide::Bottom),
+        ]
+    }
+
+    /// The three tiles meeting at the corner.
+    pub fn tiles(&self) -> [IVec2; 3] {
+        let index = self.index;
+        match self.side {
+            HexCornerSide::Top => [index, index + IVec2::Y, index + IVec2::ONE],
+            HexCornerSide::Bottom => [index, index + IVec2::NEG_Y, index + IVec2::NEG_ONE],
+        }
+    }
+
+    /// The three edges meeting at the corner.
+    pub fn edges(&self) -> [HexEdge; 3] {
+        let index = self.index;
+        match self.side {
+            HexCornerSide::Top => [
+                HexEdge::new(index, HexDirection::UpLeft),
+                HexEdge::new(index, HexDirection::UpRight),
+                HexEdge::new(index + IVec2::Y, HexDirection::Right),
+            ],
+            HexCornerSide::Bottom => [
+                HexEdge::new(index, HexDirection::DownRight),
+                HexEdge::new(index, HexDirection::DownLeft),
+                HexEdge::new(index + IVec2::NEG_ONE, HexDirection::Right),
+            ],
+        }
+    }
+
+    /// The three corners connected to this one by an edge.
+    pub fn neighbours(&self) -> [HexCorner; 3] {
+        self.edges().map(|edge| {
+            let [a, b] = edge.corners();
+            if a == *self {
+                b
+            } else {
+                a
+            }
+        })
+    }
+
+    /// The world position of the corner.
+    pub fn to_world(
+        &self,
+        legs: u32,
+        transform: &TilemapTransform,
+        pivot: Vec2,
+        slot_size: Vec2,
+        axis_flip: TilemapAxisFlip,
+    ) -> Vec2 {
+        let ty = TilemapType::Hexagonal(legs);
+        let slot = coordinates::index_to_world(
+            self.index,
+            ty,
+            &TilemapTransform::IDENTITY,
+            pivot,
+            slot_size,
+        );
+        let corner = match self.side {
+            HexCornerSide::Top => Vec2::new(slot_size.x / 2., slot_size.y),
+            HexCornerSide::Bottom => Vec2::new(slot_size.x / 2., 0.),
+        };
+        transform.transform_point(
+            slot + corner - coordinates::axis_flip_offset(ty, slot_size, axis_flip),
+        )
+    }
+
+    /// The nearest corner to the world position.
+    pub fn from_world(
+        world: Vec2,
+        legs: u32,
+        transform: &TilemapTransform,
+        pivot: Vec2,
+        slot_size: Vec2,
+        axis_flip: TilemapAxisFlip,
+    ) -> HexCorner {
+        let index = unflipped_index(world, legs, transform, pivot, slot_size, axis_flip);
+        HexCorner::of_tile(index)
+            .into_iter()
+            .min_by(|a, b| {
+                let a = a.to_world(legs, transform, pivot, slot_size, axis_flip);
+                let b = b.to_world(legs, transform, pivot, slot_size, axis_flip);
+                a.distance_squared(world)
+                    .total_cmp(&b.distance_squared(world))
+            })
+            .unwrap()
+    }
+}
+
+/// The index under the world position, as if the tilemap was not flipped.
+fn unflipped_index(
+    world: Vec2,
+    legs: u32,
+    transform: &TilemapTransform,
+    pivot: Vec2,
+    slot_size: Vec2,
+    axis_flip: TilemapAxisFlip,
+) -> IVec2 {
+    let ty = TilemapType::Hexagonal(legs);
+    coordinates::world_to_index_flipped(world, ty, transform, pivot, slot_size, axis_flip)
+        * axis_flip.as_vec2().as_ivec2()
+}
+
+/// The tiles drawn on the edges of a hexagonal tilemap, like roads, rivers and walls.
+///
+/// They are drawn by a separate overlay tilemap with its own tile set. The overlay
+/// has half the slot size and legs of the hexagonal tilemap, so every edge is the center
+/// of a slot, see `overlay_layout`. The legs of the hexagonal tilemap must be even.
+///
+/// The tiles of the edges should be drawn for the `Right` edge, a vertical line through
+/// the center of the tile. They are rotated to match the other edges.
+///
+/// Insert this on the hexagonal tilemap together with `HexEdgeOverlayTilemap` to keep
+/// the overlay in sync with the edges, or set the tiles yourself with `apply`.
+#[derive(Component, Debug, Default, Clone, Reflect)]
+pub struct HexEdgeOverlay {
+    pub(crate) edges: HashMap<HexEdge, TileBuilder>,
+}
+
+impl HexEdgeOverlay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The type and the slot size of the overlay tilemap. Use the same `TilemapTransform`
+    /// and `TilePivot` as the hexagonal tilemap.
+    pub fn overlay_layout(legs: u32, slot_size: Vec2) -> (TilemapType, Vec2) {
+        (TilemapType::Hexagonal(legs / 2), slot_size / 2.)
+    }
+
+    #[inline]
+    pub fn set(&mut self, edge: HexEdge, tile: TileBuilder) {
+        self.edges.insert(edge, tile);
+    }
+
+    #[inline]
+    pub fn get(&self, edge: HexEdge) -> Option<&TileBuilder> {
+        self.edges.get(&edge)
+    }
+
+    #[inline]
+    pub fn remove(&mut self, edge: HexEdge) -> Option<TileBuilder> {
+        self.edges.remove(&edge)
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&HexEdge, &TileBuilder)> {
+        self.edges.iter()
+    }
+
+    /// The tiles of the overlay tilemap, moved and rotated onto the edges.
+    ///
+    /// `legs`, `pivot` and `slot_size` are the ones of the hexagonal tilemap,
+    /// and `render_size` is the `TileRenderSize` of the overlay.
+    /// The result can be applied with `TilemapStorage::fill_with_buffer` at `IVec2::ZERO`.
+    pub fn overlay_tiles(
+        &self,
+        legs: u32,
+        pivot: Vec2,
+        slot_size: Vec2,
+        render_size: Vec2,
+    ) -> TileBuilderBuffer {
+        // The center of the overlay slot is the middle of the edge, but the tiles are
+        // drawn from the corner of the slot.
+        let translation = (0.5 - pivot) * (slot_size - render_size);
+        let rotation = |direction| {
+            let edge = HexEdge::new(IVec2::ZERO, direction);
+            let [a, b] = edge.corners().map(|c| {
+                c.to_world(
+                    legs,
+                    &TilemapTransform::IDENTITY,
+                    Vec2::ZERO,
+                    slot_size,
+                    TilemapAxisFlip::NONE,
+                )
+            });
+            let dir = b - a;
+            (-dir.x).atan2(dir.y)
+        };
+        let rotations = [
+            rotation(HexDirection::Right),
+            rotation(HexDirection::UpRight),
+            rotation(HexDirection::UpLeft),
+        ];
+
+        let mut buffer = TileBuilderBuffer::new();
+        self.edges.iter().for_each(|(edge, tile)| {
+            let mut tile = tile.clone();
+            tile.offset.translation += translation;
+            tile.offset.rotation += match edge.direction {
+                HexDirection::Right => rotations[0],
+                HexDirection::UpRight => rotations[1],
+                _ => rotations[2],
+            };
+            buffer.set(edge.overlay_index(), tile);
+        });
+        buffer
+    }
+
+    /// Set the tiles directly on the overlay tilemap.
+    ///
+    /// This doesn't remove the tiles of the edges that are removed, use
+    /// `HexEdgeOverlayTilemap` to keep the overlay in sync.
+    pub fn apply(
+        &self,
+        commands: &mut Commands,
+        overlay: &mut TilemapStorage,
+        legs: u32,
+        pivot: Vec2,
+        slot_size: Vec2,
+        render_size: Vec2,
+    ) {
+        overlay.fill_with_buffer(
+            commands,
+            IVec2::ZERO,
+            self.overlay_tiles(legs, pivot, slot_size, render_size),
+        );
+    }
+}
+
+/// The overlay tilemap that draws the `HexEdgeOverlay` of this hexagonal tilemap.
+///
+/// The edge tiles are spawned and despawned on the overlay whenever the edges change.
+#[derive(Component, Debug, Clone, Reflect)]
+pub struct HexEdgeOverlayTilemap {
+    pub overlay: Entity,
+    /// The tiles currently set on the overlay.
+    #[reflect(ignore)]
+    pub(crate) applied: TileBuilderBuffer,
+}
+
+impl HexEdgeOverlayTilemap {
+    pub fn new(overlay: Entity) -> Self {
+        Self {
+            overlay,
+            applied: TileBuilderBuffer::new(),
+        }
+    }
+}
+
+pub fn hex_edge_overlay_syncer(
+    mut commands: Commands,
+    mut tilemaps_query: Query<(
+        Entity,
+        Option<Ref<HexEdgeOverlay>>,
+        &mut HexEdgeOverlayTilemap,
+        &TilemapType,
+        &TilePivot,
+        &TilemapSlotSize,
+    )>,
+    mut overlays_query: Query<(&mut TilemapStorage, &TileRenderSize)>,
+    mut removed: RemovedComponents<HexEdgeOverlay>,
+) {
+    let removed = removed.read().collect::<HashSet<_>>();
+
+    tilemaps_query
+        .iter_mut()
+        .for_each(|(entity, edges, mut overlay, ty, pivot, slot_size)| {
+            let changed = edges.as_ref().is_some_and(|e| e.is_changed());
+            if !changed && !overlay.is_changed() && !removed.contains(&entity) {
+                return;
+            }
+            let TilemapType::Hexagonal(legs) = *ty else {
+                return;
+            };
+            let Ok((mut storage, render_size)) = overlays_query.get_mut(overlay.overlay) else {
+                return;
+            };
+
+            let buffer = edges.map_or_else(TileBuilderBuffer::new, |edges| {
+                edges.overlay_tiles(legs, pivot.0, slot_size.0, render_size.0)
+            });
+            storage.sync_with_buffer(
+                &mut commands,
+                &mut overlay.bypass_change_detection().applied,
+                buffer,
+            );
+        });
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tilemap::tile::TileLayer;
+
+    use super::*;
+
+    #[test]
+    fn test_hex_edges() {
+        let index = IVec2::new(2, -1);
+        HexDirection::ALL.into_iter().for_each(|dir| {
+            let edge = HexEdge::new(index, dir);
+            assert_eq!(edge, HexEdge::new(index + dir.offset(), dir.opposite()));
+            assert!(edge.tiles().contains(&index));
+            assert_eq!(
+                HexEdge::from_overlay_index(edge.overlay_index()),
+                Some(edge)
+            );
+            assert_eq!(edge.neighbours().len(), 4);
+        });
+        assert_eq!(HexEdge::from_overlay_index(IVec2::new(4, -2)), None);
+
+        // Each corner of a tile is shared by two of its edges.
+        let edges = HexEdge::of_tile(index);
+        HexCorner::of_tile(index).into_iter().for_each(|corner| {
+            assert!(corner.tiles().contains(&index));
+            assert_eq!(
+                corner.edges().iter().filter(|e| edges.contains(e)).count(),
+                2
+            );
+            corner.neighbours().into_iter().for_each(|n| {
+                assert!(n.neighbours().contains(&corner));
+            });
+        });
+    }
+
+    #[test]
+    fn test_hex_world() {
+        let (legs, slot_size) = (20, Vec2::new(40., 40.));
+        let transform = TilemapTransform::default();
+        let edge = HexEdge::new(IVec2::ZERO, HexDirection::Right);
+        let [a, b] = edge.corners().map(|c| {
+            c.to_world(
+                legs,
+                &transform,
+                Vec2::ZERO,
+                slot_size,
+                TilemapAxisFlip::NONE,
+            )
+        });
+        assert_eq!(a, Vec2::new(40., 10.));
+        assert_eq!(b, Vec2::new(40., 30.));
+        assert_eq!(
+            HexEdge::from_world(
+                Vec2::new(38., 21.),
+                legs,
+                &transform,
+                Vec2::ZERO,
+                slot_size,
+                TilemapAxisFlip::NONE
+            ),
+            edge
+        );
+        assert_eq!(
+            HexCorner::from_world(
+                Vec2::new(21., 38.),
+                legs,
+                &transform,
+                Vec2::ZERO,
+                slot_size,
+                TilemapAxisFlip::NONE
+            ),
+            HexCorner::new(IVec2::ZERO, HexCornerSide::Top)
+        );
+
+        // With the y axis flipped, tile (0, 0) is moved down by a slot.
+        let [a, b] = edge
+            .corners()
+            .map(|c| c.to_world(legs, &transform, Vec2::ZERO, slot_size, TilemapAxisFlip::Y));
+        assert_eq!(a, Vec2::new(40., -30.));
+        assert_eq!(b, Vec2::new(40., -10.));
+        assert_eq!(
+            HexEdge::from_world(
+                Vec2::new(38., -19.),
+                legs,
+                &transform,
+                Vec2::ZERO,
+                slot_size,
+                TilemapAxisFlip::Y
+            ),
+            edge
+        );
+        assert_eq!(
+            HexCorner::from_world(
+                Vec2::new(21., -2.),
+                legs,
+                &transform,
+                Vec2::ZERO,
+                slot_size,
+                TilemapAxisFlip::Y
+            ),
+            HexCorner::new(IVec2::ZERO, HexCornerSide::Top)
+        );
+
+        // The middle of the edge is the center of its overlay slot.
+        let (ty, overlay_slot) = HexEdgeOverlay::overlay_layout(legs, slot_size);
+        HexEdge::of_tile(IVec2::new(-3, 2))
+            .into_iter()
+            .for_each(|edge| {
+                let center = coordinates::index_to_world(
+                    edge.overlay_index(),
+                    ty,
+                    &transform,
+                    Vec2::ZERO,
+                    overlay_slot,
+                ) + slot_size / 2.;
+                assert_eq!(
+                    center,
+                    edge.to_world(
+                        legs,
+                        &transform,
+                        Vec2::ZERO,
+                        slot_size,
+                        TilemapAxisFlip::NONE
+                    )
+                );
+            });
+
+        let mut overlay = HexEdgeOverlay::new();
+        overlay.set(
+            edge,
+            TileBuilder::new().with_layer(0, TileLayer::no_flip(0)),
+        );
+        overlay.set(
+            HexEdge::new(IVec2::ZERO, HexDirection::DownLeft),
+            TileBuilder::new().with_layer(0, TileLayer::no_flip(0)),
+        );
+        let buffer = overlay.overlay_tiles(legs, Vec2::ZERO, slot_size, Vec2::splat(8.));
+        let right = &buffer.tiles[&IVec2::new(1, 0)];
+        assert_eq!(right.offset.translation, Vec2::splat(16.));
+        assert_eq!(right.offset.rotation, 0.);
+        let up_right = &buffer.tiles[&IVec2::new(-1, -1)];
+        assert_eq!(up_right.offset.rotation, 20f32.atan2(10.));
+    }
+}
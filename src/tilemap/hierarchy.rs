@@ -0,0 +1,144 @@
+use bevy::{
+    app::{App, Plugin, PostUpdate},
+    ecs::{
+        component::Component,
+        entity::{Entity, EntityHashMap},
+        query::{Added, Changed, With},
+        removal_detection::RemovedComponents,
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query},
+    },
+    hierarchy::{BuildChildren, Parent},
+    reflect::Reflect,
+};
+
+use super::{despawn, map::TilemapStorage, tile::Tile};
+
+pub struct EntiTilesTileHierarchyPlugin;
+
+impl Plugin for EntiTilesTileHierarchyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            (tile_hierarchy_remover, tile_hierarchy_builder)
+                .chain()
+                .before(despawn::despawn_tiles),
+        );
+
+        app.register_type::<TilemapTileHierarchy>();
+    }
+}
+
+/// Parents the tiles to the tilemap entity, so `despawn_recursive`, inspectors and
+/// other hierarchy tools show the structure of the map.
+///
+/// The tiles still don't have a `Transform`, so there's no transform propagation
+/// per tile. But if the tilemap has a `Transform`, the propagation will walk through its
+/// children every frame, so avoid adding one to huge tilemaps with this component.
+///
+/// The tiles are parented in `PostUpdate`, and unparented when this component is removed.
+#[derive(Component, Default, Debug, Clone, Copy, Reflect)]
+pub struct TilemapTileHierarchy;
+
+pub fn tile_hierarchy_builder(
+    mut commands: Commands,
+    tiles_query: Query<(Entity, &Tile, Option<&Parent>), Changed<Tile>>,
+    tilemaps_query: Query<(), With<TilemapTileHierarchy>>,
+    added_query: Query<(Entity, &TilemapStorage), Added<TilemapTileHierarchy>>,
+) {
+    if tilemaps_query.is_empty() {
+        return;
+    }
+
+    added_query.iter().for_each(|(tilemap, storage)| {
+        let tiles = storage.storage.iter_some().cloned().collect::<Vec<_>>();
+        commands.entity(tilemap).push_children(&tiles);
+    });
+
+    let mut children = EntityHashMap::<Vec<Entity>>::default();
+    tiles_query.iter().for_each(|(entity, tile, parent)| {
+        if parent.map(|p| p.get()) != Some(tile.tilemap_id)
+            && tilemaps_query.contains(tile.tilemap_id)
+            && !added_query.contains(tile.tilemap_id)
+        {
+            children.entry(tile.tilemap_id).or_default().push(entity);
+        }
+    });
+
+    children.into_iter().for_each(|(tilemap, tiles)| {
+        commands.entity(tilemap).push_children(&tiles);
+    });
+}
+
+pub fn tile_hierarchy_remover(
+    mut commands: Commands,
+    mut removed: RemovedComponents<TilemapTileHierarchy>,
+    tilemaps_query: Query<&TilemapStorage>,
+    tiles_query: Query<&Parent, With<Tile>>,
+) {
+    removed.read().for_each(|tilemap| {
+        let Ok(storage) = tilemaps_query.get(tilemap) else {
+            return;
+        };
+
+        storage.storage.iter_some().for_each(|tile| {
+            if tiles_query.get(*tile).is_ok_and(|p| p.get() == tilemap) {
+                commands.entity(*tile).remove_parent();
+            }
+        });
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::{hierarchy::Children, math::IVec2};
+
+    use crate::tilemap::tile::TileTexture;
+
+    use super::*;
+
+    #[test]
+    fn test_tile_hierarchy() {
+        let mut app = App::new();
+        app.add_plugins(EntiTilesTileHierarchyPlugin);
+
+        let tilemap = app.world.spawn_empty().id();
+        let mut storage = TilemapStorage::new(16, tilemap);
+        let tile = |index: IVec2| Tile {
+            tilemap_id: tilemap,
+            chunk_index: IVec2::ZERO,
+            in_chunk_index: 0,
+            index,
+            texture: TileTexture::Static(Vec::new()),
+            tint: Default::default(),
+            offset: Default::default(),
+        };
+
+        let a = app.world.spawn(tile(IVec2::ZERO)).id();
+        storage.storage.set_elem(IVec2::ZERO, a);
+        app.world
+            .entity_mut(tilemap)
+            .insert((storage, TilemapTileHierarchy));
+        app.update();
+        assert_eq!(app.world.get::<Parent>(a).unwrap().get(), tilemap);
+
+        let b = app.world.spawn(tile(IVec2::ONE)).id();
+        app.world
+            .get_mut::<TilemapStorage>(tilemap)
+            .unwrap()
+            .storage
+            .set_elem(IVec2::ONE, b);
+        app.update();
+        assert_eq!(
+            app.world.get::<Children>(tilemap).unwrap().as_ref(),
+            &[a, b]
+        );
+
+        app.world
+            .entity_mut(tilemap)
+            .remove::<TilemapTileHierarchy>();
+        app.update();
+        assert!(app.world.get::<Parent>(a).is_none());
+        assert!(app.world.get::<Children>(tilemap).is_none());
+    }
+}
@@ -0,0 +1,144 @@
+use bevy::{
+    app::{App, Plugin, PostUpdate},
+    ecs::{
+        component::Component,
+        entity::{Entity, EntityHashMap},
+        query::Changed,
+        removal_detection::RemovedComponents,
+        system::{Query, ResMut, Resource},
+    },
+    log::warn,
+    reflect::Reflect,
+    utils::{HashMap, Uuid},
+};
+
+pub struct EntiTilesTilemapIdPlugin;
+
+impl Plugin for EntiTilesTilemapIdPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, tilemap_id_indexer);
+
+        app.init_resource::<TilemapIds>();
+
+        app.register_type::<TilemapId>();
+    }
+}
+
+/// A stable id of the tilemap that survives saving and loading, unlike the `Entity`.
+///
+/// Use it to point to other tilemaps in your data, like portals to other maps,
+/// and find the tilemap using `TilemapIds`. It's saved by `TilemapSaver` and
+/// tilemaps loaded from LDtk use the iid of the layer.
+#[derive(Component, Debug, Clone, PartialEq, Eq, Hash, Reflect)]
+#[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
+pub struct TilemapId(pub String);
+
+impl Default for TilemapId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TilemapId {
+    /// Generate a random id.
+    pub fn new() -> Self {
+        Self(Uuid::new_v4().to_string())
+    }
+}
+
+impl From<&str> for TilemapId {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl From<String> for TilemapId {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+/// Finds the tilemap entities by their `TilemapId`.
+///
+/// Updated in `PostUpdate`, so tilemaps spawned or loaded this frame are
+/// available in the next frame.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct TilemapIds {
+    pub(crate) entities: HashMap<TilemapId, Entity>,
+    pub(crate) ids: EntityHashMap<TilemapId>,
+}
+
+impl TilemapIds {
+    #[inline]
+    pub fn get(&self, id: &TilemapId) -> Option<Entity> {
+        self.entities.get(id).cloned()
+    }
+
+    #[inline]
+    pub fn get_id(&self, tilemap: Entity) -> Option<&TilemapId> {
+        self.ids.get(&tilemap)
+    }
+
+    pub(crate) fn insert(&mut self, tilemap: Entity, id: TilemapId) {
+        self.remove(tilemap);
+        if let Some(other) = self.entities.insert(id.clone(), tilemap) {
+            if other != tilemap {
+                warn!(
+                    "Tilemap {:?} has the same id {:?} as {:?}, the latter won't be found anymore.",
+                    tilemap, id, other
+                );
+                self.ids.remove(&other);
+            }
+        }
+        self.ids.insert(tilemap, id);
+    }
+
+    pub(crate) fn remove(&mut self, tilemap: Entity) {
+        if let Some(id) = self.ids.remove(&tilemap) {
+            if self.entities.get(&id) == Some(&tilemap) {
+                self.entities.remove(&id);
+            }
+        }
+    }
+}
+
+pub fn tilemap_id_indexer(
+    tilemaps_query: Query<(Entity, &TilemapId), Changed<TilemapId>>,
+    mut removed: RemovedComponents<TilemapId>,
+    mut ids: ResMut<TilemapIds>,
+) {
+    removed.read().for_each(|entity| ids.remove(entity));
+
+    tilemaps_query
+        .iter()
+        .for_each(|(entity, id)| ids.insert(entity, id.clone()));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tilemap_ids() {
+        let a = Entity::from_raw(0);
+        let b = Entity::from_raw(1);
+        let mut ids = TilemapIds::default();
+
+        ids.insert(a, "forest".into());
+        ids.insert(b, "cave".into());
+        assert_eq!(ids.get(&"forest".into()), Some(a));
+        assert_eq!(ids.get_id(b), Some(&"cave".into()));
+
+        ids.insert(a, "village".into());
+        assert_eq!(ids.get(&"forest".into()), None);
+        assert_eq!(ids.get(&"village".into()), Some(a));
+
+        ids.insert(b, "village".into());
+        assert_eq!(ids.get(&"village".into()), Some(b));
+        assert_eq!(ids.get_id(a), None);
+
+        ids.remove(b);
+        assert_eq!(ids.get(&"village".into()), None);
+        assert_ne!(TilemapId::new(), TilemapId::new());
+    }
+}
@@ -0,0 +1,193 @@
+use bevy::{
+    app::{App, Plugin, PostUpdate},
+    ecs::{
+        component::Component, entity::Entity, query::Added, schedule::IntoSystemConfigs,
+        system::Query,
+    },
+    math::IVec2,
+    reflect::Reflect,
+    utils::{HashMap, HashSet},
+};
+
+use super::{
+    despawn,
+    tile::{Tile, TileTexture},
+    tracker::TileChanges,
+};
+
+pub struct EntiTilesTextureLookupPlugin;
+
+impl Plugin for EntiTilesTextureLookupPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            texture_lookup_updater.before(despawn::despawn_tiles),
+        );
+
+        app.register_type::<TilemapTextureLookup>();
+    }
+}
+
+/// Keeps track of which tiles use each texture index, so queries like
+/// "all the water tiles" don't need to iterate over the whole tilemap.
+///
+/// Insert this on the tilemap to enable it. Animated tiles are not tracked.
+/// The lookup is updated in `PostUpdate`, so changes are visible in the next frame.
+#[derive(Component, Default, Debug, Clone, Reflect)]
+pub struct TilemapTextureLookup {
+    /// `(layer, texture_index)` -> tiles
+    #[reflect(ignore)]
+    pub(crate) tiles: HashMap<(usize, i32), HashSet<IVec2>>,
+    /// The texture indices of each tracked tile.
+    #[reflect(ignore)]
+    pub(crate) textures: HashMap<IVec2, Vec<i32>>,
+}
+
+impl TilemapTextureLookup {
+    /// Get the tiles using `texture_index` on the layer.
+    #[inline]
+    pub fn get(&self, layer: usize, texture_index: i32) -> Option<&HashSet<IVec2>> {
+        self.tiles.get(&(layer, texture_index))
+    }
+
+    /// Iterate over the tiles using `texture_index` on any layer.
+    /// A tile will appear several times if it uses the texture on several layers.
+    pub fn iter_any_layer(&self, texture_index: i32) -> impl Iterator<Item = IVec2> + '_ {
+        self.tiles
+            .iter()
+            .filter(move |((_, texture), _)| *texture == texture_index)
+            .flat_map(|(_, tiles)| tiles.iter().cloned())
+    }
+
+    /// The amount of tiles using `texture_index` on the layer.
+    #[inline]
+    pub fn count(&self, layer: usize, texture_index: i32) -> usize {
+        self.get(layer, texture_index)
+            .map(|tiles| tiles.len())
+            .unwrap_or_default()
+    }
+
+    #[inline]
+    pub fn contains(&self, layer: usize, texture_index: i32) -> bool {
+        self.count(layer, texture_index) > 0
+    }
+
+    /// The texture indices of a tile, from the bottom layer to the top.
+    #[inline]
+    pub fn get_tile(&self, index: IVec2) -> Option<&[i32]> {
+        self.textures.get(&index).map(|t| t.as_slice())
+    }
+
+    pub(crate) fn insert(&mut self, tile: &Tile) {
+        self.remove(tile.index);
+
+        let TileTexture::Static(layers) = &tile.texture else {
+            return;
+        };
+
+        let textures = layers.iter().map(|l| l.texture_index).collect::<Vec<_>>();
+        textures
+            .iter()
+            .enumerate()
+            .filter(|(_, texture)| **texture >= 0)
+            .for_each(|(layer, texture)| {
+                self.tiles
+                    .entry((layer, *texture))
+                    .or_default()
+                    .insert(tile.index);
+            });
+        self.textures.insert(tile.index, textures);
+    }
+
+    pub(crate) fn remove(&mut self, index: IVec2) {
+        let Some(textures) = self.textures.remove(&index) else {
+            return;
+        };
+
+        textures
+            .into_iter()
+            .enumerate()
+            .for_each(|(layer, texture)| {
+                if let Some(tiles) = self.tiles.get_mut(&(layer, texture)) {
+                    tiles.remove(&index);
+                    if tiles.is_empty() {
+                        self.tiles.remove(&(layer, texture));
+                    }
+                }
+            });
+    }
+}
+
+pub fn texture_lookup_updater(
+    mut tilemaps_query: Query<(Entity, &mut TilemapTextureLookup)>,
+    new_lookups_query: Query<(), Added<TilemapTextureLookup>>,
+    tiles: TileChanges,
+) {
+    // Build the lookup from all the existing tiles when it's inserted.
+    let new_lookups = tilemaps_query
+        .iter()
+        .filter(|(entity, _)| new_lookups_query.contains(*entity))
+        .map(|(entity, _)| entity)
+        .collect::<HashSet<_>>();
+    if !new_lookups.is_empty() {
+        tiles
+            .iter_all()
+            .filter(|tile| new_lookups.contains(&tile.tilemap_id))
+            .for_each(|tile| {
+                if let Ok((_, mut lookup)) = tilemaps_query.get_mut(tile.tilemap_id) {
+                    lookup.insert(tile);
+                }
+            });
+    }
+
+    // Removals first, as a new tile may be set at the same index in the same frame.
+    tiles.iter_removed().for_each(|tile| {
+        if let Ok((_, mut lookup)) = tilemaps_query.get_mut(tile.tilemap_id) {
+            lookup.remove(tile.index);
+        }
+    });
+
+    tiles.iter_changed().for_each(|tile| {
+        if new_lookups.contains(&tile.tilemap_id) {
+            return;
+        }
+        if let Ok((_, mut lookup)) = tilemaps_query.get_mut(tile.tilemap_id) {
+            lookup.insert(tile);
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tilemap::{map::TilemapStorage, tile::TileBuilder, tile::TileLayer};
+
+    use super::*;
+
+    #[test]
+    fn test_texture_lookup() {
+        let storage = TilemapStorage::new(16, Entity::from_raw(0));
+        let mut lookup = TilemapTextureLookup::default();
+        let water = TileBuilder::new().with_layer(0, TileLayer::no_flip(3));
+        let bridge = water.clone().with_layer(1, TileLayer::no_flip(5));
+
+        lookup.insert(&water.build_component(IVec2::ZERO, &storage, storage.tilemap));
+        lookup.insert(&water.build_component(IVec2::X, &storage, storage.tilemap));
+        lookup.insert(&bridge.build_component(IVec2::Y, &storage, storage.tilemap));
+        assert_eq!(lookup.count(0, 3), 3);
+        assert_eq!(lookup.count(1, 5), 1);
+        assert_eq!(lookup.iter_any_layer(5).collect::<Vec<_>>(), vec![IVec2::Y]);
+
+        // Replacing a tile updates the old entries.
+        lookup.insert(
+            &TileBuilder::new()
+                .with_layer(0, TileLayer::no_flip(4))
+                .build_component(IVec2::X, &storage, storage.tilemap),
+        );
+        assert_eq!(lookup.count(0, 3), 2);
+        assert_eq!(lookup.get_tile(IVec2::X), Some([4].as_slice()));
+
+        lookup.remove(IVec2::Y);
+        assert!(!lookup.contains(1, 5));
+        assert_eq!(lookup.count(0, 3), 1);
+    }
+}
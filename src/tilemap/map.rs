@@ -1,13 +1,19 @@
-use std::{f32::consts::SQRT_2, fmt::Debug};
+use std::{borrow::Borrow, f32::consts::SQRT_2, fmt::Debug};
 
 use bevy::{
     asset::Handle,
-    ecs::{component::Component, query::Changed, system::Query},
+    ecs::{
+        change_detection::DetectChangesMut,
+        component::Component,
+        query::Changed,
+        system::{Query, Res},
+    },
     math::{Mat2, Quat, Vec4},
     prelude::{Commands, Entity, IVec2, Image, UVec2, Vec2},
     reflect::Reflect,
-    render::render_resource::FilterMode,
+    render::{color::Color, render_resource::FilterMode},
     sprite::TextureAtlasLayout,
+    time::Time,
     transform::components::Transform,
     utils::{HashMap, HashSet},
 };
@@ -17,12 +23,15 @@ use crate::math::{
     TileArea,
 };
 use crate::tilemap::tile::RawTileAnimation;
+use crate::MAX_ANIMATION_GROUPS;
 
 use super::{
-    buffers::TileBuilderBuffer,
-    chunking::storage::{ChunkedStorage, EntityChunkedStorage},
-    despawn::DespawnMe,
-    tile::{TileAnimation, TileBuilder, TileUpdater},
+    budget,
+    buffers::{PureColorTileBuffer, TileBuilderBuffer},
+    chunking::storage::{ChunkMut, ChunkRef, ChunkedStorage, EntityChunkedStorage},
+    coordinates,
+    despawn::{DespawnMe, DespawnedTile},
+    tile::{self, Tile, TileAnimation, TileBuilder, TileLayer, TileTexture, TileUpdater},
 };
 
 /// Defines the shape of tiles in a tilemap.
@@ -140,6 +149,18 @@ impl TilemapTransform {
     pub fn apply_translation(&self, point: Vec2) -> Vec2 {
         point + self.translation
     }
+
+    /// Transform a point from world space into tilemap space.
+    #[inline]
+    pub fn inverse_transform_point(&self, point: Vec2) -> Vec2 {
+        let point = point - self.translation;
+        match self.rotation {
+            TilemapRotation::None => point,
+            TilemapRotation::Cw90 => Vec2::new(point.y, -point.x),
+            TilemapRotation::Cw180 => Vec2::new(-point.x, -point.y),
+            TilemapRotation::Cw270 => Vec2::new(-point.y, point.x),
+        }
+    }
 }
 
 impl Into<Transform> for TilemapTransform {
@@ -216,33 +237,32 @@ impl TilemapTexture {
     }
 
     pub fn as_atlas_layout(&self) -> TextureAtlasLayout {
+        let tile_count = self.desc.tile_count();
         TextureAtlasLayout::from_grid(
             self.desc.tile_size.as_vec2(),
-            self.desc.size.x as usize,
-            self.desc.size.y as usize,
-            Some(Vec2::ZERO),
-            Some(Vec2::ZERO),
+            tile_count.x as usize,
+            tile_count.y as usize,
+            Some(Vec2::splat(self.desc.spacing as f32)),
+            Some(Vec2::splat(self.desc.margin as f32)),
         )
     }
 
     /// Get the atlas rect of a tile in uv coordinates.
     pub fn get_atlas_rect(&self, index: u32) -> Aabb2d {
-        let tile_count = self.desc.size / self.desc.tile_size;
-        let tile_index = Vec2::new((index % tile_count.x) as f32, (index / tile_count.x) as f32);
-        let tile_size = self.desc.tile_size.as_vec2() / self.desc.size.as_vec2();
+        let min = self.desc.tile_origin(index).as_vec2();
+        let size = self.desc.size.as_vec2();
         Aabb2d {
-            min: tile_index * tile_size,
-            max: (tile_index + Vec2::ONE) * tile_size,
+            min: min / size,
+            max: (min + self.desc.tile_size.as_vec2()) / size,
         }
     }
 
     /// Get the atlas rect of a tile in pixel coordinates.
     pub fn get_atlas_urect(&self, index: u32) -> UAabb2d {
-        let tile_count = self.desc.size / self.desc.tile_size;
-        let tile_index = UVec2::new(index % tile_count.x, index / tile_count.x);
+        let min = self.desc.tile_origin(index);
         UAabb2d {
-            min: tile_index * self.desc.tile_size,
-            max: (tile_index + 1) * self.desc.tile_size - 1,
+            min,
+            max: min + self.desc.tile_size - 1,
         }
     }
 }
@@ -251,12 +271,30 @@ impl TilemapTexture {
 pub struct WaitForTextureUsageChange;
 
 /// A descriptor for a tilemap texture.
-#[derive(Clone, Default, Debug, PartialEq, Reflect)]
+#[derive(Clone, Debug, PartialEq, Reflect)]
 pub struct TilemapTextureDescriptor {
     pub(crate) size: UVec2,
     pub(crate) tile_size: UVec2,
     #[reflect(ignore)]
     pub(crate) filter_mode: FilterMode,
+    pub(crate) texture_array: bool,
+    /// The pixels around the tiles at the border of the image.
+    pub(crate) margin: u32,
+    /// The pixels between the tiles.
+    pub(crate) spacing: u32,
+}
+
+impl Default for TilemapTextureDescriptor {
+    fn default() -> Self {
+        Self {
+            size: UVec2::ZERO,
+            tile_size: UVec2::ZERO,
+            filter_mode: FilterMode::default(),
+            texture_array: !cfg!(feature = "atlas"),
+            margin: 0,
+            spacing: 0,
+        }
+    }
 }
 
 impl TilemapTextureDescriptor {
@@ -271,8 +309,75 @@ impl TilemapTextureDescriptor {
             size,
             tile_size,
             filter_mode,
+            ..Default::default()
+        }
+    }
+
+    /// Split the texture into a `texture_2d_array` with one layer per tile, instead of
+    /// sampling it as an atlas. This eliminates the bleeding between tiles entirely.
+    ///
+    /// This is the default unless the `atlas` feature is enabled.
+    /// The image must have the `COPY_SRC` usage, which is set by the tilemap bundles.
+    ///
+    /// Compressed textures like KTX2 or Basis keep their format and mipmaps, but the tile size
+    /// must be a multiple of the block size. Otherwise they are sampled as atlases instead.
+    pub fn with_texture_array(mut self, texture_array: bool) -> Self {
+        self.texture_array = texture_array;
+        self
+    }
+
+    #[inline]
+    pub fn is_texture_array(&self) -> bool {
+        self.texture_array
+    }
+
+    /// Create a descriptor for a texture whose tiles are padded, like the tilesets
+    /// exported by Tiled. The pixels left over at the right and the bottom are ignored.
+    ///
+    /// Texture arrays made from padded textures only keep the first mip level.
+    pub fn new_padded(
+        size: UVec2,
+        tile_size: UVec2,
+        margin: u32,
+        spacing: u32,
+        filter_mode: FilterMode,
+    ) -> Self {
+        Self {
+            size,
+            tile_size,
+            filter_mode,
+            margin,
+            spacing,
+            ..Default::default()
         }
     }
+
+    #[inline]
+    pub fn margin(&self) -> u32 {
+        self.margin
+    }
+
+    #[inline]
+    pub fn spacing(&self) -> u32 {
+        self.spacing
+    }
+
+    #[inline]
+    pub fn is_padded(&self) -> bool {
+        self.margin != 0 || self.spacing != 0
+    }
+
+    /// The count of the tiles in each axis.
+    pub fn tile_count(&self) -> UVec2 {
+        (self.size + self.spacing).saturating_sub(UVec2::splat(self.margin * 2))
+            / (self.tile_size + self.spacing).max(UVec2::ONE)
+    }
+
+    /// The pixel position of the top left corner of a tile.
+    pub fn tile_origin(&self, index: u32) -> UVec2 {
+        let columns = self.tile_count().x.max(1);
+        UVec2::new(index % columns, index / columns) * (self.tile_size + self.spacing) + self.margin
+    }
 }
 
 #[derive(Component, Default, Debug, Clone, Reflect)]
@@ -338,6 +443,129 @@ impl Default for TilemapLayerOpacities {
     }
 }
 
+/// Draws grid lines on the tiles and/or tints every other tile like a checkerboard.
+///
+/// This is done in the shader so it's cheap to toggle at runtime. Remove this
+/// component to hide the grid. Note that the overlay follows the tile quads,
+/// so empty cells will not be covered.
+#[derive(Component, Debug, Clone, Copy, Reflect)]
+pub struct TilemapGrid {
+    pub line_color: Color,
+    /// The thickness of the lines in world units. Set it to `0.` to disable lines.
+    pub line_thickness: f32,
+    /// The color to multiply with every other tile.
+    pub checker_tint: Option<Color>,
+}
+
+impl Default for TilemapGrid {
+    fn default() -> Self {
+        Self {
+            line_color: Color::rgba(0., 0., 0., 0.5),
+            line_thickness: 1.,
+            checker_tint: None,
+        }
+    }
+}
+
+/// Marks a tilemap that never changes after it's loaded, like a background map.
+///
+/// The renderer skips the per-frame checks of its chunks and merges them into
+/// buffers that are never written again, so adjacent visible chunks are drawn
+/// with a single draw call. Changing the tiles still works but the whole tilemap
+/// will be uploaded into new buffers, so keep the changes rare.
+///
+/// Only static tilemaps get their chunks merged. The chunks of other tilemaps
+/// are still drawn one by one, as merging them would upload the whole tilemap
+/// again whenever a single tile changes.
+///
+/// Layers imported from LDtk or Tiled are separate tilemaps, so they can be marked one by one.
+#[derive(Component, Default, Debug, Clone, Copy, Reflect)]
+pub struct TilemapStatic;
+
+/// Uses a compact vertex format for the tilemap, which takes about half of the memory
+/// and upload bandwidth of the default one. Useful for huge tilemaps.
+///
+/// The tile offsets are stored as half precision floats, the tints are stored with
+/// 8 bits per channel and clamped to `0..=1`, and the texture indices must be less than `32768`.
+#[derive(Component, Default, Debug, Clone, Copy, Reflect)]
+pub struct TilemapCompactVertices;
+
+/// Colors the whole tilemap with a single tint instead of the tints of the tiles.
+///
+/// The color is left out of the vertices, which makes them smaller and the meshes
+/// cheaper to rebuild. Useful for tilemaps that never tint individual tiles.
+/// The tints of the tiles are ignored while this is present, but `TilemapChunkTints` still apply.
+#[derive(Component, Debug, Clone, Copy, Reflect)]
+pub struct TilemapUniformTint(pub Color);
+
+impl Default for TilemapUniformTint {
+    fn default() -> Self {
+        Self(Color::WHITE)
+    }
+}
+
+/// Colors that will be multiplied with every tile in the corresponding chunk.
+///
+/// This is much cheaper than updating the tint of every tile, so use this
+/// for large-area effects like dimming unexplored chunks. The tints are applied
+/// in the shader, so changing them doesn't rebuild any mesh.
+#[derive(Component, Default, Debug, Clone, Reflect)]
+#[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
+pub struct TilemapChunkTints(
+    #[cfg_attr(
+        feature = "serializing",
+        serde(serialize_with = "crate::serializing::serialize_sorted")
+    )]
+    pub(crate) HashMap<IVec2, Color>,
+);
+
+impl TilemapChunkTints {
+    /// Get the tint of a chunk.
+    #[inline]
+    pub fn get(&self, chunk_index: IVec2) -> Option<Color> {
+        self.0.get(&chunk_index).cloned()
+    }
+
+    /// Tint a chunk. Use the alpha channel to fade the chunk.
+    #[inline]
+    pub fn set(&mut self, chunk_index: IVec2, tint: Color) {
+        self.0.insert(chunk_index, tint);
+    }
+
+    /// Remove the tint of a chunk.
+    #[inline]
+    pub fn remove(&mut self, chunk_index: IVec2) -> Option<Color> {
+        self.0.remove(&chunk_index)
+    }
+
+    /// Remove the tints of all chunks.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+/// Decides the drawing order of the tiles in the same chunk.
+///
+/// Tiles with greater keys are drawn on top of the ones with smaller keys.
+/// This matters when the tiles are larger than their slots and overlap
+/// their neighbours, like trees.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct TilemapTileSortKey(pub fn(IVec2) -> i64);
+
+impl Default for TilemapTileSortKey {
+    fn default() -> Self {
+        Self::Y_THEN_X
+    }
+}
+
+impl TilemapTileSortKey {
+    /// Tiles with smaller y are drawn on top. If y is the same, the one with smaller x.
+    pub const Y_THEN_X: Self = Self(|index| -(((index.y as i64) << 32) + index.x as i64));
+    /// Tiles with smaller x are drawn on top. If x is the same, the one with smaller y.
+    pub const X_THEN_Y: Self = Self(|index| -(((index.x as i64) << 32) + index.y as i64));
+}
+
 /// The tilemap's aabb.
 #[derive(Component, Default, Debug, Clone, Copy, Reflect)]
 pub struct TilemapAabbs {
@@ -358,6 +586,11 @@ impl TilemapAabbs {
 }
 
 /// The tilemap's storage. It stores all the tiles in entity form.
+///
+/// Tilemaps have no fixed size. The tiles are stored in chunks that are allocated
+/// when the first tile is set in them, and the renderer only builds meshes for those chunks,
+/// so procedurally generated worlds can grow in any direction. Listen to `CameraChunkUpdation`
+/// to generate the chunks around the camera and `remove_chunk` the ones that are left.
 #[derive(Component, Debug, Clone, Reflect)]
 #[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
 pub struct TilemapStorage {
@@ -365,6 +598,10 @@ pub struct TilemapStorage {
     pub(crate) storage: EntityChunkedStorage,
     pub(crate) reserved: HashMap<IVec2, Aabb2d>,
     pub(crate) calc_queue: HashSet<IVec2>,
+    /// Spare entities taken from `TilemapPool`, used before spawning new ones.
+    #[reflect(ignore)]
+    #[cfg_attr(feature = "serializing", serde(skip))]
+    pub(crate) pool: Vec<Entity>,
 }
 
 impl TilemapStorage {
@@ -375,6 +612,18 @@ impl TilemapStorage {
             ..Default::default()
         }
     }
+
+    /// Create a storage that only stores the existing tiles.
+    ///
+    /// This saves a lot of memory for mostly-empty tilemaps like decoration layers,
+    /// at the cost of slightly slower tile access.
+    pub fn new_sparse(chunk_size: u32, binded_tilemap: Entity) -> Self {
+        Self {
+            tilemap: binded_tilemap,
+            storage: ChunkedStorage::new_sparse(chunk_size),
+            ..Default::default()
+        }
+    }
 }
 
 impl Default for TilemapStorage {
@@ -384,6 +633,7 @@ impl Default for TilemapStorage {
             storage: Default::default(),
             reserved: Default::default(),
             calc_queue: Default::default(),
+            pool: Default::default(),
         }
     }
 }
@@ -395,18 +645,32 @@ impl TilemapStorage {
         self.storage.get_elem(index).cloned()
     }
 
-    /// Get a chunk.
+    /// Get a chunk, works for both dense and sparse storages.
     #[inline]
-    pub fn get_chunk(&self, index: IVec2) -> Option<&Vec<Option<Entity>>> {
-        self.storage.chunks.get(&index)
+    pub fn get_chunk(&self, index: IVec2) -> Option<ChunkRef<'_, Entity>> {
+        self.storage.get_chunk(index)
     }
 
-    /// Get a mutable chunk.
+    /// Get a mutable chunk, works for both dense and sparse storages.
     ///
     /// **Notice**: This is not recommended as we may do something extra when you remove/set tiles.
     #[inline]
-    pub fn get_chunk_mut(&mut self, index: IVec2) -> Option<&mut Vec<Option<Entity>>> {
-        self.storage.chunks.get_mut(&index)
+    pub fn get_chunk_mut(&mut self, index: IVec2) -> Option<ChunkMut<'_, Entity>> {
+        self.storage.get_chunk_mut(index)
+    }
+
+    /// Iterate over the tiles in a chunk, works for both dense and sparse storages.
+    #[inline]
+    pub fn iter_chunk(&self, index: IVec2) -> impl Iterator<Item = (IVec2, Entity)> + '_ {
+        self.storage
+            .iter_chunk(index)
+            .map(move |(i, e)| (self.storage.inverse_transform_index(index, i), *e))
+    }
+
+    /// Returns true if the tiles are stored sparsely.
+    #[inline]
+    pub fn is_sparse(&self) -> bool {
+        self.storage.is_sparse()
     }
 
     /// Set a tile.
@@ -418,10 +682,18 @@ impl TilemapStorage {
         }
         let new_tile = tile_builder.build_component(index, &self, self.tilemap);
 
-        let mut tile_entity = commands.spawn_empty();
-        self.storage.set_elem(index, tile_entity.id());
+        let tile_entity = self.spawn_tile_entity(commands);
+        self.storage.set_elem(index, tile_entity);
         self.reserve(new_tile.chunk_index);
-        tile_entity.insert(new_tile);
+        commands.entity(tile_entity).insert(new_tile);
+    }
+
+    /// Get an empty entity for a new tile, reusing the pooled ones first.
+    #[inline]
+    pub(crate) fn spawn_tile_entity(&mut self, commands: &mut Commands) -> Entity {
+        self.pool
+            .pop()
+            .unwrap_or_else(|| commands.spawn_empty().id())
     }
 
     #[inline]
@@ -439,7 +711,7 @@ impl TilemapStorage {
     #[inline]
     #[allow(dead_code)]
     pub(crate) fn set_chunk_entity(&mut self, index: IVec2, chunk: Vec<Option<Entity>>) {
-        self.storage.chunks.insert(index, chunk);
+        self.storage.set_chunk(index, chunk);
         self.reserve(index);
     }
 
@@ -472,13 +744,94 @@ impl TilemapStorage {
 
     /// Remove all the tiles in the tilemap.
     pub fn remove_all(&mut self, commands: &mut Commands) {
+        self.storage.iter_some().for_each(|entity| {
+            commands.entity(*entity).insert(DespawnMe);
+        });
+        self.storage.clear();
+    }
+
+    /// Remove all the tiles outside `area`.
+    pub fn crop(&mut self, commands: &mut Commands, area: TileArea) {
+        let outside = self
+            .iter_indices()
+            .filter(|(index, _)| !area.aabb().contains(*index))
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        outside.into_iter().for_each(|index| {
+            self.remove(commands, index);
+        });
+    }
+
+    /// Move the tiles so the bottom left corner of their bounds is at `(0, 0)`,
+    /// and move `transform` so they stay at the same world position.
+    ///
+    /// The storage is rebuilt, so empty chunks are dropped. The tile entities are kept,
+    /// but other things indexed by tiles like the `PathTilemap` are not moved.
+    /// Returns the offset the tiles are moved by, or `None` if there's no tile.
+    pub fn trim(
+        &mut self,
+        commands: &mut Commands,
+        tiles_query: &mut Query<&mut Tile>,
+        ty: TilemapType,
+        transform: &mut TilemapTransform,
+        slot_size: Vec2,
+        axis_flip: TilemapAxisFlip,
+    ) -> Option<IVec2> {
+        let tiles = self.iter_indices().collect::<Vec<_>>();
+        let min = tiles.iter().map(|(index, _)| *index).reduce(IVec2::min)?;
+
+        let mut storage = ChunkedStorage::new_like(&self.storage);
+        tiles.iter().for_each(|(index, entity)| {
+            storage.set_elem(*index - min, *entity);
+        });
+
+        tiles.iter().for_each(|(index, entity)| {
+            let (chunk_index, in_chunk_index) = self.storage.transform_index(*index);
+            // The slot will be overwritten if another tile moves into it.
+            if storage.get_elem(*index).is_none() {
+                commands.spawn(DespawnedTile {
+                    tilemap: self.tilemap,
+                    chunk_index,
+                    in_chunk_index,
+                });
+            }
+
+            if let Ok(mut tile) = tiles_query.get_mut(*entity) {
+                let new_index = *index - min;
+                (tile.chunk_index, tile.in_chunk_index) = storage.transform_index(new_index);
+                tile.index = new_index;
+            }
+        });
+
+        let world = |index: IVec2| {
+            coordinates::index_to_world_flipped(
+                index,
+                ty,
+                transform,
+                Vec2::ZERO,
+                slot_size,
+                axis_flip,
+            )
+        };
+        transform.translation += world(min) - world(IVec2::ZERO);
+
+        self.storage = storage;
+        self.reserved.clear();
+        self.calc_queue = self.storage.chunk_indices().copied().collect();
+        Some(-min)
+    }
+
+    /// Iterate over the indices and entities of all the tiles.
+    fn iter_indices(&self) -> impl Iterator<Item = (IVec2, Entity)> + '_ {
         self.storage
-            .chunks
-            .drain()
-            .flat_map(|(_, chunk)| chunk.into_iter().filter_map(|e| e))
-            .for_each(|entity| {
-                commands.entity(entity).insert(DespawnMe);
-            });
+            .chunked_iter_some()
+            .map(|(chunk_index, in_chunk_index, entity)| {
+                (
+                    self.storage
+                        .inverse_transform_index(chunk_index, in_chunk_index),
+                    *entity,
+                )
+            })
     }
 
     /// Declare that a chunk is existent.
@@ -535,28 +888,19 @@ impl TilemapStorage {
     }
 
     /// Fill a rectangle area with the same tile.
+    ///
+    /// The tiles are queued in `TileSpawnQueue` if `TileSpawnBudget` is limited,
+    /// like all the other bulk operations that spawn tiles. Their entities are reserved
+    /// right away, but they only get the `Tile` component when it's their turn.
     pub fn fill_rect(
         &mut self,
         commands: &mut Commands,
         area: TileArea,
         tile_builder: TileBuilder,
     ) {
-        let mut tile_batch = Vec::with_capacity(area.size());
-
-        for y in area.origin.y..=area.dest.y {
-            for x in area.origin.x..=area.dest.x {
-                let index = IVec2 { x, y };
-                let tile = tile_builder.build_component(index, &self, self.tilemap);
-                let entity = self.get(index).unwrap_or_else(|| {
-                    let e = commands.spawn_empty().id();
-                    self.set_entity(index, Some(e));
-                    e
-                });
-                tile_batch.push((entity, tile));
-            }
-        }
-
-        commands.insert_or_spawn_batch(tile_batch);
+        let indices = (area.origin.y..=area.dest.y)
+            .flat_map(|y| (area.origin.x..=area.dest.x).map(move |x| IVec2 { x, y }));
+        self.set_batch(commands, indices.map(|index| (index, &tile_builder)));
     }
 
     /// Fill a rectangle area with tiles returned by `tile_builder`.
@@ -569,32 +913,77 @@ impl TilemapStorage {
         mut tile_builder: impl FnMut(IVec2) -> Option<TileBuilder>,
         relative_index: bool,
     ) {
-        let mut tile_batch = Vec::with_capacity(area.size());
+        let tiles = (area.origin.y..=area.dest.y)
+            .flat_map(|y| (area.origin.x..=area.dest.x).map(move |x| IVec2 { x, y }))
+            .filter_map(|index| {
+                tile_builder(if relative_index {
+                    index - area.origin
+                } else {
+                    index
+                })
+                .map(|builder| (index, builder))
+            });
+        self.set_batch(commands, tiles);
+    }
 
-        for y in area.origin.y..=area.dest.y {
-            for x in area.origin.x..=area.dest.x {
-                let index = IVec2 { x, y };
-                let Some(builder) = tile_builder({
-                    if relative_index {
-                        index - area.origin
-                    } else {
-                        index
-                    }
-                }) else {
-                    continue;
-                };
-
-                let tile = builder.build_component(index, &self, self.tilemap);
+    /// Fill the border of a rectangle area with the same tile.
+    ///
+    /// `thickness` is how many tiles the border goes inwards.
+    /// The whole area is filled if it's thicker than half of the area.
+    pub fn fill_ring(
+        &mut self,
+        commands: &mut Commands,
+        area: TileArea,
+        thickness: u32,
+        tile_builder: TileBuilder,
+    ) {
+        let t = thickness as i32;
+        let indices = (area.origin.y..=area.dest.y)
+            .flat_map(|y| (area.origin.x..=area.dest.x).map(move |x| IVec2 { x, y }))
+            .filter(|index| {
+                index.x < area.origin.x + t
+                    || index.x > area.dest.x - t
+                    || index.y < area.origin.y + t
+                    || index.y > area.dest.y - t
+            });
+        self.set_batch(commands, indices.map(|index| (index, &tile_builder)));
+    }
+
+    /// Set a lot of tiles at once.
+    ///
+    /// This is much faster than calling `set()` for each tile, as the tiles
+    /// are inserted in one batch, and the chunks are only reserved once.
+    pub fn set_many(&mut self, commands: &mut Commands, tiles: &[(IVec2, TileBuilder)]) {
+        self.set_batch(
+            commands,
+            tiles.iter().map(|(index, builder)| (*index, builder)),
+        );
+    }
+
+    /// Insert the tiles in one batch, reusing the existing entities,
+    /// and reserve the touched chunks after all the tiles are set.
+    fn set_batch<B: Borrow<TileBuilder>>(
+        &mut self,
+        commands: &mut Commands,
+        tiles: impl Iterator<Item = (IVec2, B)>,
+    ) {
+        let mut chunks = HashSet::new();
+        let batch = tiles
+            .map(|(index, builder)| {
+                let tile = builder.borrow().build_component(index, self, self.tilemap);
                 let entity = self.get(index).unwrap_or_else(|| {
-                    let e = commands.spawn_empty().id();
-                    self.set_entity(index, Some(e));
+                    let e = self.spawn_tile_entity(commands);
+                    self.storage
+                        .set_elem_precise(tile.chunk_index, tile.in_chunk_index, e);
                     e
                 });
-                tile_batch.push((entity, tile));
-            }
-        }
+                chunks.insert(tile.chunk_index);
+                (entity, tile)
+            })
+            .collect::<Vec<_>>();
 
-        commands.insert_or_spawn_batch(tile_batch);
+        self.reserve_many(chunks.into_iter());
+        budget::insert_tiles(commands, self.tilemap, batch);
     }
 
     /// Fill a rectangle area with tiles from a buffer. This can be faster than setting them one by one.
@@ -613,17 +1002,81 @@ impl TilemapStorage {
                 if let Some(e) = self.get(tile.index) {
                     (e, tile)
                 } else {
-                    let e = commands.spawn_empty().id();
+                    let e = self.spawn_tile_entity(commands);
                     self.set_entity(tile.index, Some(e));
                     (e, tile)
                 }
             })
             .collect::<Vec<_>>();
 
-        commands.insert_or_spawn_batch(batch);
+        budget::insert_tiles(commands, self.tilemap, batch);
+    }
+
+    /// Replace the tiles set from the `previous` buffer with the ones in `buffer`,
+    /// like for the overlay tilemaps that mirror another component.
+    ///
+    /// The tiles that are not in `buffer` anymore are removed, and only the tiles
+    /// that differ from `previous` are set. `previous` becomes `buffer` afterwards.
+    pub fn sync_with_buffer(
+        &mut self,
+        commands: &mut Commands,
+        previous: &mut TileBuilderBuffer,
+        buffer: TileBuilderBuffer,
+    ) {
+        previous
+            .tiles
+            .keys()
+            .filter(|index| !buffer.tiles.contains_key(*index))
+            .for_each(|index| self.remove(commands, *index));
+
+        let mut changed = TileBuilderBuffer::new();
+        buffer
+            .tiles
+            .iter()
+            .filter(|(index, tile)| previous.get(**index) != Some(*tile))
+            .for_each(|(index, tile)| changed.set(*index, tile.clone()));
+        if !changed.is_empty() {
+            self.fill_with_buffer(commands, IVec2::ZERO, changed);
+        }
+
+        *previous = buffer;
+    }
+
+    /// Set the tints of the tiles in bulk, like for pure color tilemaps.
+    ///
+    /// Only the tint of the existing tiles is updated, their layers and
+    /// animations are kept. The missing tiles are spawned with no layers.
+    pub fn set_colors(&mut self, commands: &mut Commands, buffer: &PureColorTileBuffer) {
+        let mut updaters = Vec::new();
+        let mut spawned = Vec::new();
+        buffer.iter().for_each(|(index, color)| {
+            if let Some(e) = self.get(index) {
+                updaters.push((
+                    e,
+                    TileUpdater {
+                        tint: Some(color),
+                        ..Default::default()
+                    },
+                ));
+            } else {
+                let tile =
+                    TileBuilder::new()
+                        .with_tint(color)
+                        .build_component(index, self, self.tilemap);
+                let e = self.spawn_tile_entity(commands);
+                self.set_entity(index, Some(e));
+                spawned.push((e, tile));
+            }
+        });
+
+        commands.insert_or_spawn_batch(updaters);
+        budget::insert_tiles(commands, self.tilemap, spawned);
     }
 
     /// Simlar to `TilemapStorage::fill_rect()`.
+    ///
+    /// The updaters are inserted right away. If some tiles are still queued
+    /// in `TileSpawnQueue`, they are updated as soon as they are spawned.
     pub fn update_rect(&mut self, commands: &mut Commands, area: TileArea, updater: TileUpdater) {
         let mut batch = Vec::with_capacity(area.size());
 
@@ -638,6 +1091,25 @@ impl TilemapStorage {
         commands.insert_or_spawn_batch(batch);
     }
 
+    /// Request re-extraction of all the tiles in the area, so the render chunks
+    /// containing them will be rebuilt in the next frame.
+    ///
+    /// Use this after mutating `Tile`s in ways that bypass change detection,
+    /// for example through reflection in an editor.
+    pub fn mark_dirty(&self, commands: &mut Commands, area: TileArea) {
+        let mut batch = Vec::with_capacity(area.size());
+
+        for y in area.origin.y..=area.dest.y {
+            for x in area.origin.x..=area.dest.x {
+                if let Some(entity) = self.get(IVec2 { x, y }) {
+                    batch.push((entity, TileUpdater::default()));
+                }
+            }
+        }
+
+        commands.insert_or_spawn_batch(batch);
+    }
+
     /// Simlar to `TilemapStorage::fill_rect_custom()`.
     pub fn update_rect_custom(
         &mut self,
@@ -665,30 +1137,441 @@ impl TilemapStorage {
 
         commands.insert_or_spawn_batch(batch);
     }
+
+    /// Change the texture indices of the static tiles, like after rearranging the tileset.
+    /// Indices that are not in `mapping` are kept. Use `TilemapAnimations::remap_texture_indices`
+    /// for the animated tiles.
+    pub fn remap_texture_indices(
+        &self,
+        tiles_query: &mut Query<&mut Tile>,
+        mapping: &HashMap<u32, u32>,
+    ) {
+        self.storage.iter_some().for_each(|entity| {
+            if let Ok(mut tile) = tiles_query.get_mut(*entity) {
+                tile.texture.remap_texture_indices(mapping);
+            }
+        });
+    }
 }
 
 /// The tilemap's animation buffer.
 ///
 /// Its format is `[fps, seq_elem_1, ..., seq_elem_n, fps, seq_elem_1, ..., seq_elem_n, ...]`.
+///
+/// Unregistering an animation leaves a free slot in the buffer, which will be reused
+/// by later registrations. Use `compact` to remove all the free slots.
 #[derive(Component, Default, Debug, Clone, Reflect)]
 #[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
-pub struct TilemapAnimations(pub(crate) Vec<i32>);
+pub struct TilemapAnimations(
+    pub(crate) Vec<i32>,
+    /// The registered animations, sorted by their start.
+    #[cfg_attr(feature = "serializing", serde(default))]
+    pub(crate) Vec<TileAnimation>,
+    /// The free slots in the buffer as `(start, size)`, including the fps.
+    #[cfg_attr(feature = "serializing", serde(default))]
+    pub(crate) Vec<(u32, u32)>,
+    /// Animations that are moved or removed, keyed by their old start.
+    /// Tiles using them will be updated by `animation_remapper`.
+    #[cfg_attr(feature = "serializing", serde(skip))]
+    #[reflect(ignore)]
+    pub(crate) HashMap<u32, AnimationRemap>,
+);
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum AnimationRemap {
+    Moved(TileAnimation),
+    /// The tiles will become static tiles showing this frame.
+    Removed(i32),
+}
 
 impl TilemapAnimations {
     /// Register a tile animation so you can use it in `TileBuilder::with_animation`.
     pub fn register(&mut self, anim: RawTileAnimation) -> TileAnimation {
-        self.0.push(anim.fps as i32);
-        let start = self.0.len() as u32;
         let length = anim.sequence.len() as u32;
-        self.0.extend(anim.sequence.into_iter().map(|i| i as i32));
-        TileAnimation {
-            start,
+        let size = length + 1;
+
+        // Slots that still have tiles waiting to be remapped can't be reused yet.
+        let slot = self.2.iter().position(|(start, slot_size)| {
+            *slot_size >= size
+                && !self
+                    .3
+                    .keys()
+                    .any(|key| (*start..*start + *slot_size).contains(key))
+        });
+        let fps_index = match slot {
+            Some(i) => {
+                let (start, slot_size) = self.2[i];
+                if slot_size == size {
+                    self.2.remove(i);
+                } else {
+                    self.2[i] = (start + size, slot_size - size);
+                }
+                start as usize
+            }
+            None => {
+                let start = self.0.len();
+                self.0.resize(start + size as usize, 0);
+                start
+            }
+        };
+
+        self.0[fps_index] = anim.fps as i32;
+        self.0[fps_index + 1..fps_index + size as usize]
+            .iter_mut()
+            .zip(anim.sequence)
+            .for_each(|(dst, src)| *dst = src as i32);
+
+        let anim = TileAnimation {
+            start: fps_index as u32 + 1,
             length,
             fps: anim.fps,
+            group: 0,
+        };
+        let i = self.1.partition_point(|a| a.start < anim.start);
+        self.1.insert(i, anim);
+        anim
+    }
+
+    /// All the registered animations, sorted by their position in the buffer.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &TileAnimation> {
+        self.1.iter()
+    }
+
+    /// The amount of registered animations.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.1.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.1.is_empty()
+    }
+
+    #[inline]
+    pub fn contains(&self, anim: TileAnimation) -> bool {
+        self.index_of(anim).is_some()
+    }
+
+    /// Get the sequence and fps of a registered animation.
+    pub fn get(&self, anim: TileAnimation) -> Option<RawTileAnimation> {
+        self.index_of(anim).map(|i| {
+            let anim = self.1[i];
+            let start = anim.start as usize;
+            RawTileAnimation {
+                sequence: self.0[start..start + anim.length as usize]
+                    .iter()
+                    .map(|i| *i as u32)
+                    .collect(),
+                fps: self.0[start - 1] as u32,
+            }
+        })
+    }
+
+    /// Find a registered animation with the same sequence and fps.
+    pub fn find(&self, anim: &RawTileAnimation) -> Option<TileAnimation> {
+        self.1.iter().copied().find(|a| {
+            let start = a.start as usize;
+            a.fps == anim.fps
+                && a.length as usize == anim.sequence.len()
+                && self.0[start..start + a.length as usize]
+                    .iter()
+                    .zip(&anim.sequence)
+                    .all(|(a, b)| *a == *b as i32)
+        })
+    }
+
+    /// Change the frames of all the registered animations. Frames that are not in `mapping` are kept.
+    pub fn remap_texture_indices(&mut self, mapping: &HashMap<u32, u32>) {
+        for anim in self.1.iter() {
+            let start = anim.start as usize;
+            self.0[start..start + anim.length as usize]
+                .iter_mut()
+                .for_each(|frame| *frame = tile::remap_texture_index(*frame, mapping));
+        }
+    }
+
+    /// The raw animation buffer that will be sent to the GPU.
+    #[inline]
+    pub fn buffer(&self) -> &[i32] {
+        &self.0
+    }
+
+    /// The free slots in the buffer as `(start, size)`. A slot of size `n`
+    /// can hold an animation with at most `n - 1` frames.
+    #[inline]
+    pub fn free_slots(&self) -> &[(u32, u32)] {
+        &self.2
+    }
+
+    /// Unregister an animation and return its sequence.
+    ///
+    /// Tiles using this animation will become static tiles showing the first frame.
+    pub fn unregister(&mut self, anim: TileAnimation) -> Option<RawTileAnimation> {
+        let raw = self.get(anim)?;
+        let i = self.index_of(anim)?;
+        self.1.remove(i);
+
+        // Remap first, so the slot is kept until the tiles are updated.
+        self.remap(
+            anim.start,
+            AnimationRemap::Removed(raw.sequence.first().map(|f| *f as i32).unwrap_or(-1)),
+        );
+        self.free(anim.start - 1, anim.length + 1);
+        Some(raw)
+    }
+
+    /// Replace a registered animation with a new one and return the new `TileAnimation`.
+    ///
+    /// Tiles using the old animation will be updated to use the new one.
+    pub fn replace(&mut self, anim: TileAnimation, new: RawTileAnimation) -> Option<TileAnimation> {
+        let i = self.index_of(anim)?;
+
+        let new_anim = if new.sequence.len() == anim.length as usize {
+            let start = anim.start as usize;
+            self.0[start - 1] = new.fps as i32;
+            self.0[start..start + new.sequence.len()]
+                .iter_mut()
+                .zip(new.sequence)
+                .for_each(|(dst, src)| *dst = src as i32);
+            self.1[i].fps = new.fps;
+            self.1[i]
+        } else {
+            self.1.remove(i);
+            self.free(anim.start - 1, anim.length + 1);
+            self.register(new)
+        };
+
+        self.remap(anim.start, AnimationRemap::Moved(new_anim));
+        Some(new_anim)
+    }
+
+    /// Move all the animations to the front of the buffer to remove the free slots.
+    ///
+    /// Tiles will be updated to use the moved animations at the end of the frame,
+    /// so don't use the animations returned before compacting on new tiles in the same frame.
+    pub fn compact(&mut self) {
+        if self.2.is_empty() {
+            return;
+        }
+
+        let mut buffer = Vec::with_capacity(self.0.len());
+        let mut moved = Vec::new();
+        for anim in self.1.iter_mut() {
+            let old = *anim;
+            let start = old.start as usize;
+            buffer.extend_from_slice(&self.0[start - 1..start + old.length as usize]);
+            anim.start = (buffer.len() - old.length as usize) as u32;
+            if anim.start != old.start {
+                moved.push((old.start, *anim));
+            }
+        }
+
+        self.0 = buffer;
+        self.2.clear();
+        moved
+            .into_iter()
+            .for_each(|(old, anim)| self.remap(old, AnimationRemap::Moved(anim)));
+    }
+
+    #[inline]
+    fn index_of(&self, anim: TileAnimation) -> Option<usize> {
+        self.1
+            .binary_search_by_key(&anim.start, |a| a.start)
+            .ok()
+            .filter(|i| self.1[*i].length == anim.length)
+    }
+
+    fn free(&mut self, start: u32, size: u32) {
+        let i = self.2.partition_point(|(s, _)| *s < start);
+        self.2.insert(i, (start, size));
+
+        // Merge with the neighbours.
+        if i + 1 < self.2.len() && self.2[i].0 + self.2[i].1 == self.2[i + 1].0 {
+            self.2[i].1 += self.2[i + 1].1;
+            self.2.remove(i + 1);
+        }
+        if i > 0 && self.2[i - 1].0 + self.2[i - 1].1 == self.2[i].0 {
+            self.2[i - 1].1 += self.2[i].1;
+            self.2.remove(i);
+        }
+
+        self.trim();
+    }
+
+    /// Remove the free slot at the end of the buffer, unless it still has tiles
+    /// waiting to be remapped. Otherwise the slot would be reused by the next
+    /// registration, and those tiles would be remapped wrongly.
+    fn trim(&mut self) {
+        if let Some((start, size)) = self.2.last().copied() {
+            if (start + size) as usize == self.0.len()
+                && !self.3.keys().any(|key| (start..start + size).contains(key))
+            {
+                self.0.truncate(start as usize);
+                self.2.pop();
+            }
+        }
+    }
+
+    /// Take the pending remaps to apply them, and release the slots they reserved.
+    pub(crate) fn take_remaps(&mut self) -> HashMap<u32, AnimationRemap> {
+        let remaps = std::mem::take(&mut self.3);
+        self.trim();
+        remaps
+    }
+
+    fn remap(&mut self, old: u32, to: AnimationRemap) {
+        // Animations that are already remapped to the old one should follow it.
+        self.3.values_mut().for_each(|remap| {
+            if matches!(remap, AnimationRemap::Moved(a) if a.start == old) {
+                *remap = to;
+            }
+        });
+        self.3.entry(old).or_insert(to);
+    }
+}
+
+/// The clocks of the animated tiles in the tilemap.
+///
+/// Tiles are assigned to groups using `TileAnimation::with_group`. Tiles using the same
+/// animation in the same group always show the same frame, and each group can be
+/// restarted, paused or sped up independently.
+///
+/// Without this component, all the groups follow the global time.
+/// The groups are created when first accessed, and the ones not created yet also follow the global time.
+/// The groups are uploaded along with the animation sequences, so the count is only limited by
+/// `MAX_ANIMATION_GROUPS` and the storage buffer size of the device.
+#[derive(Component, Debug, Clone, Default, Reflect)]
+pub struct TilemapAnimationGroups(pub(crate) Vec<AnimationGroup>);
+
+impl TilemapAnimationGroups {
+    /// Create the first `count` groups up front.
+    pub fn new(count: u32) -> Self {
+        Self(vec![AnimationGroup::default(); count as usize])
+    }
+
+    #[inline]
+    pub fn get(&self, group: u32) -> Option<&AnimationGroup> {
+        self.0.get(group as usize)
+    }
+
+    /// Get the group, creating it and the groups before it if needed.
+    /// Returns `None` if the group is not less than `MAX_ANIMATION_GROUPS`.
+    pub fn get_mut(&mut self, group: u32) -> Option<&mut AnimationGroup> {
+        if group as usize >= MAX_ANIMATION_GROUPS {
+            return None;
+        }
+        if self.0.len() <= group as usize {
+            self.0.resize(group as usize + 1, AnimationGroup::default());
+        }
+        self.0.get_mut(group as usize)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Restart all the groups so they are in sync with each other again.
+    pub fn restart_all(&mut self) {
+        self.0.iter_mut().for_each(|group| group.restart());
+    }
+}
+
+#[derive(Debug, Clone, Copy, Reflect)]
+pub struct AnimationGroup {
+    pub speed: f32,
+    pub paused: bool,
+    /// Offsets the frame of each tile by `dot(index, phase)` frames.
+    /// Use this to make waves across the tilemap instead of playing in unison.
+    pub phase: Vec2,
+    pub(crate) elapsed: f32,
+}
+
+impl Default for AnimationGroup {
+    fn default() -> Self {
+        Self {
+            speed: 1.,
+            paused: false,
+            phase: Vec2::ZERO,
+            elapsed: 0.,
         }
     }
 }
 
+impl AnimationGroup {
+    /// The time this group has played, in seconds.
+    #[inline]
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    /// Play the animations from the first frame.
+    #[inline]
+    pub fn restart(&mut self) {
+        self.elapsed = 0.;
+    }
+}
+
+/// Update the tiles using animations that are moved or removed.
+pub fn animation_remapper(
+    mut tilemaps_query: Query<(Entity, &mut TilemapAnimations), Changed<TilemapAnimations>>,
+    mut tiles_query: Query<&mut Tile>,
+) {
+    let remaps = tilemaps_query
+        .iter_mut()
+        .filter(|(_, anims)| !anims.3.is_empty())
+        .map(|(entity, mut anims)| {
+            (entity, anims.bypass_change_detection().take_remaps())
+        })
+        .collect::<HashMap<_, _>>();
+
+    if remaps.is_empty() {
+        return;
+    }
+
+    tiles_query.par_iter_mut().for_each(|mut tile| {
+        let Some(remaps) = remaps.get(&tile.tilemap_id) else {
+            return;
+        };
+        let TileTexture::Animated(anim) = tile.texture else {
+            return;
+        };
+
+        match remaps.get(&anim.start).copied() {
+            Some(AnimationRemap::Moved(new)) => {
+                tile.texture = TileTexture::Animated(TileAnimation {
+                    group: anim.group,
+                    ..new
+                });
+            }
+            Some(AnimationRemap::Removed(frame)) => {
+                tile.texture = TileTexture::Static(vec![TileLayer::no_flip(frame)]);
+            }
+            None => {}
+        }
+    });
+}
+
+pub fn animation_group_ticker(
+    time: Res<Time>,
+    mut tilemaps_query: Query<&mut TilemapAnimationGroups>,
+) {
+    tilemaps_query.iter_mut().for_each(|mut groups| {
+        groups
+            .0
+            .iter_mut()
+            .filter(|group| !group.paused)
+            .for_each(|group| group.elapsed += time.delta_seconds() * group.speed);
+    });
+}
+
 pub fn transform_syncer(
     mut tilemap_query: Query<(&TilemapTransform, &mut Transform), Changed<TilemapTransform>>,
 ) {
@@ -755,7 +1638,7 @@ pub fn tilemap_aabb_calculator(
     tilemaps_query.par_iter_mut().for_each(
         |(mut aabbs, storage, ty, tile_pivot, axis_direction, slot_size, transform)| {
             let mut chunk_aabb: Option<IAabb2d> = None;
-            storage.storage.chunks.keys().for_each(|chunk_index| {
+            storage.storage.chunk_indices().for_each(|chunk_index| {
                 if let Some(aabb) = &mut chunk_aabb {
                     aabb.expand_to_contain(*chunk_index);
                 } else {
@@ -794,3 +1677,236 @@ pub fn tilemap_aabb_calculator(
         },
     );
 }
+
+#[cfg(test)]
+mod test {
+    use bevy::ecs::{system::SystemState, world::World};
+
+    use super::*;
+
+    fn raw(sequence: Vec<u32>) -> RawTileAnimation {
+        RawTileAnimation { sequence, fps: 10 }
+    }
+
+    #[test]
+    fn test_padded_texture() {
+        let desc = TilemapTextureDescriptor::new_padded(
+            UVec2::new(54, 37),
+            UVec2::splat(16),
+            2,
+            1,
+            FilterMode::Nearest,
+        );
+        assert_eq!(desc.tile_count(), UVec2::new(3, 2));
+        assert_eq!(desc.tile_origin(0), UVec2::splat(2));
+        assert_eq!(desc.tile_origin(4), UVec2::new(19, 19));
+
+        let texture = TilemapTexture::new(Default::default(), desc, TilemapRotation::None);
+        let rect = texture.get_atlas_urect(5);
+        assert_eq!(rect.min, UVec2::new(36, 19));
+        assert_eq!(rect.max, UVec2::new(51, 34));
+    }
+
+    #[test]
+    fn test_animation_management() {
+        let mut anims = TilemapAnimations::default();
+        let a = anims.register(raw(vec![0, 1, 2]));
+        let b = anims.register(raw(vec![3, 4]));
+        let c = anims.register(raw(vec![5]));
+        assert_eq!(anims.buffer(), &[10, 0, 1, 2, 10, 3, 4, 10, 5]);
+        assert_eq!(anims.len(), 3);
+
+        assert_eq!(anims.unregister(a).unwrap().sequence, vec![0, 1, 2]);
+        assert!(!anims.contains(a));
+        assert_eq!(anims.free_slots(), &[(0, 4)]);
+
+        // The slot of `a` is reserved until the tiles are remapped.
+        let d = anims.register(raw(vec![6]));
+        assert_eq!(d.start(), 10);
+        anims.3.clear();
+        let e = anims.register(raw(vec![7]));
+        assert_eq!(e.start(), 1);
+        assert_eq!(anims.free_slots(), &[(2, 2)]);
+
+        // Removing the last animation shrinks the buffer,
+        // but only after the tiles are remapped.
+        anims.unregister(d);
+        assert_eq!(anims.buffer().len(), 11);
+        let f = anims.register(raw(vec![6, 6]));
+        assert_eq!(f.start(), 12);
+        anims.unregister(f);
+        assert!(anims.take_remaps().contains_key(&10));
+        assert_eq!(anims.buffer().len(), 9);
+
+        let b = anims.replace(b, raw(vec![8, 9])).unwrap();
+        assert_eq!(anims.get(b).unwrap().sequence, vec![8, 9]);
+
+        anims.compact();
+        assert!(anims.free_slots().is_empty());
+        assert_eq!(anims.buffer(), &[10, 7, 10, 8, 9, 10, 5]);
+        assert_eq!(
+            anims.iter().map(|a| a.start()).collect::<Vec<_>>(),
+            vec![1, 3, 6]
+        );
+        assert!(matches!(
+            anims.3.get(&c.start()),
+            Some(AnimationRemap::Moved(a)) if a.start() == 6
+        ));
+    }
+
+    #[test]
+    fn test_animation_groups() {
+        let mut groups = TilemapAnimationGroups::default();
+        assert!(groups.get(3).is_none());
+
+        groups.get_mut(3).unwrap().speed = 2.;
+        assert_eq!(groups.len(), 4);
+        assert_eq!(groups.get(3).unwrap().speed, 2.);
+        assert_eq!(groups.get(0).unwrap().speed, 1.);
+
+        assert!(groups.get_mut(1).is_some());
+        assert_eq!(groups.len(), 4);
+
+        assert!(groups.get_mut(MAX_ANIMATION_GROUPS as u32).is_none());
+        assert_eq!(groups.len(), 4);
+    }
+
+    #[test]
+    fn test_crop_and_trim() {
+        let mut world = World::new();
+        let tilemap = world.spawn_empty().id();
+        let mut storage = TilemapStorage::new(4, tilemap);
+        let mut state = SystemState::<(Commands, Query<&mut Tile>)>::new(&mut world);
+
+        let (mut commands, _) = state.get_mut(&mut world);
+        storage.fill_rect(
+            &mut commands,
+            TileArea::new(IVec2::new(-6, 3), UVec2::new(8, 2)),
+            TileBuilder::new(),
+        );
+        storage.crop(
+            &mut commands,
+            TileArea::from_min_max(IVec2::new(-3, 3), IVec2::new(1, 10)),
+        );
+        state.apply(&mut world);
+        assert_eq!(storage.iter_indices().count(), 10);
+        assert!(storage.get(IVec2::new(-4, 3)).is_none());
+        let corner = storage.get(IVec2::new(-3, 3)).unwrap();
+
+        let mut transform = TilemapTransform::default();
+        let (mut commands, mut tiles_query) = state.get_mut(&mut world);
+        let offset = storage.trim(
+            &mut commands,
+            &mut tiles_query,
+            TilemapType::Square,
+            &mut transform,
+            Vec2::splat(16.),
+            TilemapAxisFlip::NONE,
+        );
+        state.apply(&mut world);
+        assert_eq!(offset, Some(IVec2::new(3, -3)));
+        assert_eq!(transform.translation, Vec2::new(-48., 48.));
+        assert_eq!(storage.get(IVec2::ZERO), Some(corner));
+        assert!(storage.get(IVec2::new(-3, 3)).is_none());
+        let tile = world.get::<Tile>(corner).unwrap();
+        assert_eq!(tile.index, IVec2::ZERO);
+        assert_eq!((tile.chunk_index, tile.in_chunk_index), (IVec2::ZERO, 0));
+    }
+
+    #[test]
+    fn test_budgeted_insertion() {
+        let mut world = World::new();
+        world.insert_resource(budget::TileSpawnBudget {
+            max_per_frame: Some(1),
+            max_micros: None,
+        });
+        world.init_resource::<budget::TileSpawnQueue>();
+        let tilemap = world.spawn_empty().id();
+        let mut storage = TilemapStorage::new(4, tilemap);
+        let mut state = SystemState::<Commands>::new(&mut world);
+
+        let mut commands = state.get_mut(&mut world);
+        storage.fill_rect(
+            &mut commands,
+            TileArea::new(IVec2::ZERO, UVec2::new(2, 2)),
+            TileBuilder::new(),
+        );
+        state.apply(&mut world);
+        assert_eq!(storage.iter_indices().count(), 4);
+        assert_eq!(world.query::<&Tile>().iter(&world).count(), 0);
+        assert_eq!(
+            world.resource::<budget::TileSpawnQueue>().remaining(tilemap),
+            4
+        );
+    }
+
+    #[test]
+    fn test_batch_insertion() {
+        let mut world = World::new();
+        let tilemap = world.spawn_empty().id();
+        let mut storage = TilemapStorage::new(4, tilemap);
+        let mut state = SystemState::<Commands>::new(&mut world);
+
+        let mut commands = state.get_mut(&mut world);
+        storage.fill_ring(
+            &mut commands,
+            TileArea::new(IVec2::ZERO, UVec2::new(6, 5)),
+            1,
+            TileBuilder::new(),
+        );
+        state.apply(&mut world);
+        assert_eq!(storage.iter_indices().count(), 18);
+        assert!(storage.get(IVec2::new(5, 4)).is_some());
+        assert!(storage.get(IVec2::new(2, 2)).is_none());
+        assert_eq!(storage.calc_queue.len(), 4);
+
+        let corner = storage.get(IVec2::ZERO).unwrap();
+        let mut commands = state.get_mut(&mut world);
+        storage.set_many(
+            &mut commands,
+            &[
+                (IVec2::ZERO, TileBuilder::new().with_tint(Color::RED)),
+                (IVec2::new(2, 2), TileBuilder::new()),
+                (IVec2::new(-1, 0), TileBuilder::new()),
+            ],
+        );
+        state.apply(&mut world);
+        assert_eq!(storage.iter_indices().count(), 20);
+        assert_eq!(storage.get(IVec2::ZERO), Some(corner));
+        assert_eq!(world.get::<Tile>(corner).unwrap().tint, Color::RED);
+        assert_eq!(storage.calc_queue.len(), 5);
+    }
+
+    #[test]
+    fn test_set_colors() {
+        let mut world = World::new();
+        let tilemap = world.spawn_empty().id();
+        let mut storage = TilemapStorage::new(4, tilemap);
+        let mut state = SystemState::<Commands>::new(&mut world);
+
+        let mut commands = state.get_mut(&mut world);
+        storage.set(
+            &mut commands,
+            IVec2::ZERO,
+            TileBuilder::new().with_layer(0, TileLayer::no_flip(3)),
+        );
+        state.apply(&mut world);
+        let existing = storage.get(IVec2::ZERO).unwrap();
+
+        let mut buffer = PureColorTileBuffer::new(IVec2::ZERO, UVec2::new(2, 1));
+        buffer.set(IVec2::ZERO, Color::RED);
+        buffer.set(IVec2::X, Color::BLUE);
+        let mut commands = state.get_mut(&mut world);
+        storage.set_colors(&mut commands, &buffer);
+        state.apply(&mut world);
+
+        // Only the tint of the existing tile is updated.
+        assert_eq!(
+            world.get::<TileUpdater>(existing).unwrap().tint,
+            Some(Color::RED)
+        );
+        assert!(world.get::<TileUpdater>(existing).unwrap().layer.is_none());
+        let spawned = storage.get(IVec2::X).unwrap();
+        assert_eq!(world.get::<Tile>(spawned).unwrap().tint, Color::BLUE);
+    }
+}
@@ -0,0 +1,196 @@
+use bevy::{
+    ecs::{component::Component, entity::Entity, system::Query},
+    hierarchy::Parent,
+    math::{IVec2, Vec2},
+    reflect::Reflect,
+    utils::HashMap,
+};
+
+use super::{
+    coordinates,
+    map::{TilemapAxisFlip, TilemapTransform, TilemapType},
+};
+
+/// Named markers on tiles, like spawn points, chests and exits.
+///
+/// Insert this component on the tilemap entity. A tile can have several markers and
+/// a marker can be on several tiles, in the order they were added.
+///
+/// The LDtk importer inserts this on the level entity with the entities in the entity
+/// layers by their identifiers, and the Tiled importer on the map entity with the named
+/// objects of orthogonal and isometric maps. The layers point to them with `TileMarkersSource`.
+#[derive(Component, Debug, Default, Clone, Reflect)]
+#[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileMarkers {
+    #[cfg_attr(
+        feature = "serializing",
+        serde(serialize_with = "crate::serializing::serialize_sorted")
+    )]
+    pub(crate) markers: HashMap<String, Vec<IVec2>>,
+}
+
+impl TileMarkers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_marker(mut self, name: impl Into<String>, index: IVec2) -> Self {
+        self.add(name, index);
+        self
+    }
+
+    /// Mark the tile. Does nothing if it's already marked with this name.
+    pub fn add(&mut self, name: impl Into<String>, index: IVec2) {
+        let indices = self.markers.entry(name.into()).or_default();
+        if !indices.contains(&index) {
+            indices.push(index);
+        }
+    }
+
+    /// Remove the marker from the tile. Returns true if it was there.
+    pub fn remove(&mut self, name: &str, index: IVec2) -> bool {
+        let Some(indices) = self.markers.get_mut(name) else {
+            return false;
+        };
+        let Some(i) = indices.iter().position(|i| *i == index) else {
+            return false;
+        };
+
+        indices.remove(i);
+        if indices.is_empty() {
+            self.markers.remove(name);
+        }
+        true
+    }
+
+    /// Remove the marker from all the tiles.
+    #[inline]
+    pub fn clear(&mut self, name: &str) -> Option<Vec<IVec2>> {
+        self.markers.remove(name)
+    }
+
+    /// All the tiles with the marker.
+    #[inline]
+    pub fn get(&self, name: &str) -> &[IVec2] {
+        self.markers
+            .get(name)
+            .map_or(&[], |indices| indices.as_slice())
+    }
+
+    /// The first tile with the marker, for markers that should be unique like the player spawn.
+    #[inline]
+    pub fn first(&self, name: &str) -> Option<IVec2> {
+        self.get(name).first().cloned()
+    }
+
+    /// The world position of the first tile with the marker.
+    #[inline]
+    pub fn first_world(
+        &self,
+        name: &str,
+        ty: TilemapType,
+        transform: &TilemapTransform,
+        pivot: Vec2,
+        slot_size: Vec2,
+        axis_flip: TilemapAxisFlip,
+    ) -> Option<Vec2> {
+        self.first(name).map(|index| {
+            coordinates::index_to_world_flipped(index, ty, transform, pivot, slot_size, axis_flip)
+        })
+    }
+
+    #[inline]
+    pub fn contains(&self, name: &str) -> bool {
+        self.markers.contains_key(name)
+    }
+
+    /// The markers on a tile.
+    pub fn at(&self, index: IVec2) -> impl Iterator<Item = &str> {
+        self.markers
+            .iter()
+            .filter(move |(_, indices)| indices.contains(&index))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Iterate over the names of the markers.
+    #[inline]
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.markers.keys().map(|name| name.as_str())
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.markers.is_empty()
+    }
+}
+
+/// Points a tilemap to the entity holding its `TileMarkers`, when they are shared by
+/// several tilemaps like the layers of a LDtk level or a Tiled map.
+#[derive(Component, Debug, Clone, Copy, Reflect)]
+pub struct TileMarkersSource(pub Entity);
+
+/// The markers of a tilemap. These are on the tilemap itself, the entity in its
+/// `TileMarkersSource`, or its parent.
+pub fn find_markers<'a>(
+    tilemap: Entity,
+    markers_query: &'a Query<&TileMarkers>,
+    sources_query: &Query<(Option<&TileMarkersSource>, Option<&Parent>)>,
+) -> Option<(Entity, &'a TileMarkers)> {
+    let (source, parent) = sources_query.get(tilemap).unwrap_or_default();
+    [Some(tilemap), source.map(|s| s.0), parent.map(|p| p.get())]
+        .into_iter()
+        .flatten()
+        .find_map(|entity| markers_query.get(entity).ok().map(|m| (entity, m)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_markers() {
+        let mut markers = TileMarkers::new()
+            .with_marker("spawn", IVec2::new(1, 2))
+            .with_marker("chest", IVec2::new(3, 0))
+            .with_marker("chest", IVec2::new(0, 3))
+            .with_marker("chest", IVec2::new(3, 0))
+            .with_marker("exit", IVec2::new(3, 0));
+
+        assert_eq!(markers.first("spawn"), Some(IVec2::new(1, 2)));
+        assert_eq!(markers.get("chest"), &[IVec2::new(3, 0), IVec2::new(0, 3)]);
+        assert!(markers.get("boss").is_empty());
+
+        let mut at = markers.at(IVec2::new(3, 0)).collect::<Vec<_>>();
+        at.sort();
+        assert_eq!(at, vec!["chest", "exit"]);
+
+        assert!(markers.remove("chest", IVec2::new(3, 0)));
+        assert!(!markers.remove("chest", IVec2::new(3, 0)));
+        assert_eq!(markers.first("chest"), Some(IVec2::new(0, 3)));
+        assert!(markers.remove("exit", IVec2::new(3, 0)));
+        assert!(!markers.contains("exit"));
+    }
+
+    #[test]
+    fn test_first_world_flipped() {
+        let markers = TileMarkers::new().with_marker("spawn", IVec2::new(1, 2));
+        let first_world = |axis_flip| {
+            markers.first_world(
+                "spawn",
+                TilemapType::Square,
+                &TilemapTransform::default(),
+                Vec2::ZERO,
+                Vec2::splat(16.),
+                axis_flip,
+            )
+        };
+
+        // The bottom left corners of the slots.
+        assert_eq!(
+            first_world(TilemapAxisFlip::NONE),
+            Some(Vec2::new(16., 32.))
+        );
+        // y goes down from the top-left tile, like in LDtk and Tiled.
+        assert_eq!(first_world(TilemapAxisFlip::Y), Some(Vec2::new(16., -48.)));
+    }
+}
@@ -0,0 +1,363 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    asset::Assets,
+    ecs::{
+        change_detection::{DetectChanges, DetectChangesMut, Ref},
+        component::Component,
+        system::{Query, Res},
+    },
+    math::{UVec2, Vec2, Vec4},
+    reflect::Reflect,
+    render::{render_resource::TextureFormat, texture::Image},
+};
+
+use crate::MAX_LAYER_COUNT;
+
+use super::{
+    map::{TilemapLayerOpacities, TilemapStorage, TilemapTexture, TilemapTextureDescriptor},
+    packed::TilemapPackedTiles,
+    tile::{Tile, TileTexture},
+};
+
+pub struct EntiTilesTextureMetadataPlugin;
+
+impl Plugin for EntiTilesTextureMetadataPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, texture_metadata_analyzer);
+
+        app.register_type::<TilemapTextureMetadata>()
+            .register_type::<TileTextureMetadata>()
+            .register_type::<TileAlpha>();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum TileAlpha {
+    /// Every pixel has zero alpha. Tiles using this texture don't need to be rendered.
+    Transparent,
+    /// Every pixel has full alpha. Tiles using this texture hide everything behind them.
+    Opaque,
+    Translucent,
+}
+
+#[derive(Debug, Clone, Reflect)]
+pub struct TileTextureMetadata {
+    pub alpha: TileAlpha,
+    /// The convex hull of the solid pixels, in tile space where `(0, 0)` is the
+    /// bottom left corner and `(1, 1)` is the top right corner.
+    /// Empty if there are no solid pixels.
+    pub shape: Vec<Vec2>,
+}
+
+/// Per texture index metadata analyzed from the pixels of the tilemap texture.
+///
+/// Insert this on the tilemap. The texture is analyzed once it's loaded, and again
+/// when the `TilemapTexture` changes. Only `Rgba8` textures are supported.
+///
+/// Once analyzed, the renderer skips the transparent layers of the tiles,
+/// and the layers under an opaque one. See `cull_layers`.
+#[derive(Component, Debug, Clone, Reflect)]
+pub struct TilemapTextureMetadata {
+    /// Pixels with alpha at least this are considered solid when calculating the shapes.
+    pub alpha_threshold: u8,
+    pub(crate) tiles: Vec<TileTextureMetadata>,
+}
+
+impl Default for TilemapTextureMetadata {
+    fn default() -> Self {
+        Self::new(128)
+    }
+}
+
+impl TilemapTextureMetadata {
+    pub fn new(alpha_threshold: u8) -> Self {
+        Self {
+            alpha_threshold,
+            tiles: Vec::new(),
+        }
+    }
+
+    /// Returns false if the texture is not analyzed yet.
+    #[inline]
+    pub fn is_ready(&self) -> bool {
+        !self.tiles.is_empty()
+    }
+
+    #[inline]
+    pub fn get(&self, texture_index: u32) -> Option<&TileTextureMetadata> {
+        self.tiles.get(texture_index as usize)
+    }
+
+    #[inline]
+    pub fn is_transparent(&self, texture_index: u32) -> bool {
+        self.get(texture_index)
+            .is_some_and(|tile| tile.alpha == TileAlpha::Transparent)
+    }
+
+    #[inline]
+    pub fn is_opaque(&self, texture_index: u32) -> bool {
+        self.get(texture_index)
+            .is_some_and(|tile| tile.alpha == TileAlpha::Opaque)
+    }
+
+    /// The approximate collision shape of the texture. See `TileTextureMetadata::shape`.
+    #[inline]
+    pub fn shape(&self, texture_index: u32) -> Option<&[Vec2]> {
+        self.get(texture_index).map(|tile| tile.shape.as_slice())
+    }
+
+    /// Hide the layers that won't be visible, so they are not sampled when rendering.
+    /// These are the transparent layers, and the layers under an opaque layer
+    /// with full layer opacity.
+    ///
+    /// The hidden layers keep their positions, so the layer opacities still match.
+    pub fn cull_layers(&self, texture: &mut TileTexture, layer_opacities: Vec4) {
+        let TileTexture::Static(layers) = texture else {
+            return;
+        };
+
+        let top_opaque = layers
+            .iter()
+            .take(MAX_LAYER_COUNT)
+            .enumerate()
+            .filter(|(i, layer)| {
+                layer.texture_index >= 0
+                    && layer_opacities[*i] >= 1.
+                    && self.is_opaque(layer.texture_index as u32)
+            })
+            .map(|(i, _)| i)
+            .last()
+            .unwrap_or_default();
+
+        layers.iter_mut().enumerate().for_each(|(i, layer)| {
+            if layer.texture_index >= 0
+                && (i < top_opaque || self.is_transparent(layer.texture_index as u32))
+            {
+                layer.texture_index = -1;
+            }
+        });
+    }
+}
+
+/// Analyze every tile in the texture. Returns `None` if the format of the image is not `Rgba8`,
+/// or the image is smaller than the size in the descriptor.
+pub fn analyze_texture(
+    image: &Image,
+    desc: &TilemapTextureDescriptor,
+    alpha_threshold: u8,
+) -> Option<Vec<TileTextureMetadata>> {
+    if !matches!(
+        image.texture_descriptor.format,
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb
+    ) {
+        return None;
+    }
+
+    // Rows are read with the width in the descriptor.
+    let image_size = image.size();
+    if image_size.x != desc.size.x
+        || image_size.y < desc.size.y
+        || image.data.len() < (desc.size.x * desc.size.y * 4) as usize
+    {
+        return None;
+    }
+
+    let tile_count = desc.tile_count();
+    let alpha = |px: UVec2| image.data[((px.y * desc.size.x + px.x) * 4 + 3) as usize];
+
+    Some(
+        (0..tile_count.x * tile_count.y)
+            .map(|index| {
+                let origin = desc.tile_origin(index);
+                analyze_tile(desc.tile_size, alpha_threshold, |px| alpha(origin + px))
+            })
+            .collect(),
+    )
+}
+
+/// `alpha` takes the pixel coordinates in the tile, with y pointing down.
+fn analyze_tile(
+    tile_size: UVec2,
+    alpha_threshold: u8,
+    alpha: impl Fn(UVec2) -> u8,
+) -> TileTextureMetadata {
+    let (mut transparent, mut opaque) = (true, true);
+    // Only the corners of the leftmost and rightmost solid pixels
+    // in each row can be on the hull.
+    let mut points = Vec::new();
+
+    for y in 0..tile_size.y {
+        let mut solid = None;
+        for x in 0..tile_size.x {
+            let a = alpha(UVec2::new(x, y));
+            transparent &= a == 0;
+            opaque &= a == u8::MAX;
+
+            if a >= alpha_threshold.max(1) {
+                let (min, _) = solid.unwrap_or((x, x));
+                solid = Some((min, x));
+            }
+        }
+
+        if let Some((min, max)) = solid {
+            let (top, bottom) = (
+                (tile_size.y - y) as f32 / tile_size.y as f32,
+                (tile_size.y - y - 1) as f32 / tile_size.y as f32,
+            );
+            let (left, right) = (
+                min as f32 / tile_size.x as f32,
+                (max + 1) as f32 / tile_size.x as f32,
+            );
+            points.extend([
+                Vec2::new(left, top),
+                Vec2::new(left, bottom),
+                Vec2::new(right, top),
+                Vec2::new(right, bottom),
+            ]);
+        }
+    }
+
+    TileTextureMetadata {
+        alpha: if transparent {
+            TileAlpha::Transparent
+        } else if opaque {
+            TileAlpha::Opaque
+        } else {
+            TileAlpha::Translucent
+        },
+        shape: convex_hull(points),
+    }
+}
+
+/// Andrew's monotone chain. Returns the hull in counter-clockwise order.
+fn convex_hull(mut points: Vec<Vec2>) -> Vec<Vec2> {
+    points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+
+    let mut hull = half_hull(points.iter().copied());
+    hull.extend(half_hull(points.iter().rev().copied()));
+    hull
+}
+
+fn half_hull(points: impl Iterator<Item = Vec2>) -> Vec<Vec2> {
+    let mut chain: Vec<Vec2> = Vec::new();
+    for p in points {
+        while let [.., a, b] = chain[..] {
+            if (b - a).perp_dot(p - a) > 0. {
+                break;
+            }
+            chain.pop();
+        }
+        chain.push(p);
+    }
+    // The last point is the first point of the other half.
+    chain.pop();
+    chain
+}
+
+pub fn texture_metadata_analyzer(
+    mut tilemaps_query: Query<(
+        Ref<TilemapTexture>,
+        &mut TilemapTextureMetadata,
+        Ref<TilemapLayerOpacities>,
+        &TilemapStorage,
+        Option<&mut TilemapPackedTiles>,
+    )>,
+    mut tiles_query: Query<&mut Tile>,
+    images: Res<Assets<Image>>,
+) {
+    tilemaps_query.iter_mut().for_each(
+        |(texture, mut metadata, layer_opacities, storage, packed)| {
+            if texture.is_changed() && metadata.is_ready() {
+                metadata.tiles.clear();
+            }
+
+            if !metadata.is_ready() {
+                let Some(image) = images.get(texture.handle()) else {
+                    return;
+                };
+                let Some(tiles) =
+                    analyze_texture(image, texture.desc(), metadata.alpha_threshold)
+                else {
+                    return;
+                };
+                metadata.tiles = tiles;
+            } else if !layer_opacities.is_changed() {
+                return;
+            }
+
+            // Extract the tiles again so the layers are culled with the new metadata.
+            storage.storage.iter_some().for_each(|entity| {
+                if let Ok(mut tile) = tiles_query.get_mut(*entity) {
+                    tile.set_changed();
+                }
+            });
+            if let Some(mut packed) = packed {
+                packed.mark_all_changed();
+            }
+        },
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tilemap::tile::TileLayer;
+
+    use super::*;
+
+    #[test]
+    fn test_analyze_tile() {
+        let size = UVec2::splat(4);
+        let transparent = analyze_tile(size, 128, |_| 0);
+        assert_eq!(transparent.alpha, TileAlpha::Transparent);
+        assert!(transparent.shape.is_empty());
+
+        let opaque = analyze_tile(size, 128, |_| 255);
+        assert_eq!(opaque.alpha, TileAlpha::Opaque);
+        assert_eq!(
+            opaque.shape,
+            vec![Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y]
+        );
+
+        // The bottom half is solid, and the top half is faint.
+        let half = analyze_tile(size, 128, |px| if px.y >= 2 { 255 } else { 10 });
+        assert_eq!(half.alpha, TileAlpha::Translucent);
+        assert_eq!(
+            half.shape,
+            vec![
+                Vec2::ZERO,
+                Vec2::X,
+                Vec2::new(1., 0.5),
+                Vec2::new(0., 0.5)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cull_layers() {
+        let size = UVec2::splat(2);
+        let mut metadata = TilemapTextureMetadata::default();
+        metadata.tiles = vec![
+            analyze_tile(size, 128, |_| 0),
+            analyze_tile(size, 128, |_| 255),
+            analyze_tile(size, 128, |px| if px.y == 0 { 255 } else { 0 }),
+        ];
+
+        let layers = |indices: &[i32]| {
+            TileTexture::Static(indices.iter().map(|i| TileLayer::no_flip(*i)).collect())
+        };
+
+        // The layer under the opaque one and the transparent one are hidden.
+        let mut texture = layers(&[2, 1, 0, 2]);
+        metadata.cull_layers(&mut texture, Vec4::ONE);
+        assert_eq!(texture, layers(&[-1, 1, -1, 2]));
+
+        // The opaque layer is faded, so it doesn't hide anything.
+        let mut texture = layers(&[2, 1]);
+        metadata.cull_layers(&mut texture, Vec4::new(1., 0.5, 1., 1.));
+        assert_eq!(texture, layers(&[2, 1]));
+    }
+}
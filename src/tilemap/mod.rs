@@ -1,26 +1,64 @@
 use bevy::app::{Plugin, PostUpdate, PreUpdate, Update};
 
 use self::{
-    chunking::camera::{CameraChunkUpdater, CameraChunkUpdation},
+    chunking::{
+        camera::{CameraChunkUpdater, CameraChunkUpdation},
+        compression::TilemapChunkCompression,
+        storage::ChunkCompressionConfig,
+    },
+    flow::{TilemapFlowArrows, TilemapFlowField},
+    height::TilemapHeights,
+    hex::{HexEdgeOverlay, HexEdgeOverlayTilemap},
     map::{
-        TilePivot, TileRenderSize, TilemapAabbs, TilemapAnimations, TilemapLayerOpacities,
-        TilemapName, TilemapSlotSize, TilemapStorage, TilemapTexture, TilemapTextureDescriptor,
-        TilemapTransform, TilemapType,
+        AnimationGroup, TilePivot, TileRenderSize, TilemapAabbs, TilemapAnimationGroups,
+        TilemapAnimations, TilemapChunkTints, TilemapCompactVertices, TilemapGrid,
+        TilemapLayerOpacities, TilemapName, TilemapSlotSize, TilemapStatic, TilemapStorage,
+        TilemapTexture, TilemapTextureDescriptor, TilemapTransform, TilemapType,
+        TilemapUniformTint,
     },
+    marker::{TileMarkers, TileMarkersSource},
     tile::{LayerUpdater, Tile, TileLayer, TileTexture, TileUpdater},
+    validation::ValidateTilemap,
+    wall::{TilemapWalls, WallOverlayTilemap},
 };
 
 #[cfg(feature = "algorithm")]
 pub mod algorithm;
+pub mod behavior;
+pub mod budget;
 pub mod buffers;
 pub mod bundles;
 pub mod chunking;
+pub mod conveyor;
 pub mod coordinates;
 pub mod despawn;
+pub mod fade;
+pub mod floor;
+pub mod flow;
+pub mod height;
+pub mod hex;
+pub mod hierarchy;
+pub mod id;
+pub mod lookup;
 pub mod map;
-#[cfg(feature = "physics")]
+pub mod marker;
+pub mod metadata;
+pub mod object;
+pub mod origin;
+pub mod ownership;
+pub mod packed;
+#[cfg(feature = "physics_core")]
 pub mod physics;
+pub mod pool;
+pub mod portal;
+pub mod sensor;
 pub mod tile;
+pub mod tracker;
+pub mod transaction;
+pub mod validation;
+pub mod wall;
+pub mod weather;
+pub mod zone;
 
 pub struct EntiTilesTilemapPlugin;
 
@@ -34,17 +72,22 @@ impl Plugin for EntiTilesTilemapPlugin {
                 map::transform_syncer,
                 map::queued_chunk_aabb_calculator,
                 map::tilemap_aabb_calculator,
+                map::animation_group_ticker,
                 tile::tile_updater,
                 chunking::camera::camera_chunk_update,
+                chunking::compression::chunk_compressor,
+                wall::wall_overlay_syncer,
+                hex::hex_edge_overlay_syncer,
             ),
         );
 
         app.add_systems(
             PostUpdate,
             (
+                map::animation_remapper,
                 despawn::despawn_tilemap,
                 despawn::despawn_tiles,
-                #[cfg(feature = "physics")]
+                #[cfg(feature = "physics_core")]
                 despawn::despawn_physics_tilemaps,
             ),
         );
@@ -66,16 +109,60 @@ impl Plugin for EntiTilesTilemapPlugin {
             .register_type::<TilemapTransform>()
             .register_type::<TilemapTexture>()
             .register_type::<TilemapTextureDescriptor>()
-            .register_type::<TilemapAnimations>();
+            .register_type::<TilemapAnimations>()
+            .register_type::<TilemapAnimationGroups>()
+            .register_type::<AnimationGroup>()
+            .register_type::<TilemapChunkTints>()
+            .register_type::<TilemapGrid>()
+            .register_type::<TilemapStatic>()
+            .register_type::<TilemapCompactVertices>()
+            .register_type::<TilemapUniformTint>()
+            .register_type::<TilemapHeights>()
+            .register_type::<TileMarkers>()
+            .register_type::<TileMarkersSource>()
+            .register_type::<HexEdgeOverlay>()
+            .register_type::<HexEdgeOverlayTilemap>()
+            .register_type::<TilemapWalls>()
+            .register_type::<WallOverlayTilemap>()
+            .register_type::<TilemapFlowField>()
+            .register_type::<TilemapFlowArrows>()
+            .register_type::<ValidateTilemap>();
 
         app.register_type::<CameraChunkUpdation>()
-            .register_type::<CameraChunkUpdater>();
+            .register_type::<CameraChunkUpdater>()
+            .register_type::<TilemapChunkCompression>()
+            .register_type::<ChunkCompressionConfig>();
 
         app.add_event::<CameraChunkUpdation>();
 
+        app.add_plugins((
+            zone::EntiTilesTileZonePlugin,
+            weather::EntiTilesWeatherPlugin,
+            pool::EntiTilesTilemapPoolPlugin,
+            budget::EntiTilesTileBudgetPlugin,
+            transaction::EntiTilesTransactionPlugin,
+            origin::EntiTilesFloatingOriginPlugin,
+            object::EntiTilesTileObjectPlugin,
+            lookup::EntiTilesTextureLookupPlugin,
+            fade::EntiTilesTilemapFadePlugin,
+            floor::EntiTilesTilemapFloorPlugin,
+            metadata::EntiTilesTextureMetadataPlugin,
+            sensor::EntiTilesTileSensorPlugin,
+            ownership::EntiTilesTileOwnershipPlugin,
+        ));
+
+        app.add_plugins((
+            id::EntiTilesTilemapIdPlugin,
+            portal::EntiTilesTilePortalPlugin,
+            behavior::EntiTilesTileBehaviorPlugin,
+            hierarchy::EntiTilesTileHierarchyPlugin,
+            packed::EntiTilesPackedTilesPlugin,
+            conveyor::EntiTilesTileConveyorPlugin,
+        ));
+
         #[cfg(feature = "algorithm")]
         app.add_plugins(algorithm::EntiTilesAlgorithmTilemapPlugin);
-        #[cfg(feature = "physics")]
+        #[cfg(feature = "physics_core")]
         app.add_plugins(physics::EntiTilesPhysicsTilemapPlugin);
     }
 }
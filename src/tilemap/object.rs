@@ -0,0 +1,440 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        component::Component,
+        entity::{Entity, EntityHashMap},
+        query::{Added, Changed, Or},
+        system::{Commands, Query},
+    },
+    math::{IVec2, UVec2},
+    reflect::Reflect,
+    transform::components::Transform,
+    utils::HashMap,
+};
+
+use crate::math::TileArea;
+
+use super::{
+    coordinates,
+    map::{
+        TilePivot, TilemapAxisFlip, TilemapSlotSize, TilemapStorage, TilemapTransform, TilemapType,
+    },
+    tile::TileBuilder,
+};
+
+#[cfg(feature = "algorithm")]
+use crate::{algorithm::pathfinding::PathTilemaps, tilemap::algorithm::path::PathTile};
+#[cfg(feature = "algorithm")]
+use bevy::ecs::system::ResMut;
+
+#[cfg(feature = "physics_core")]
+use super::physics::{PhysicsTile, PhysicsTilemap};
+
+pub struct EntiTilesTileObjectPlugin;
+
+impl Plugin for EntiTilesTileObjectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (object_occupier, object_anchor_syncer));
+
+        app.register_type::<TileObject>()
+            .register_type::<TilemapObjects>();
+    }
+}
+
+/// An object covering several tiles, like a house or a big tree.
+///
+/// Objects are placed using `TilemapObjects::place`. Add a sprite to the
+/// object entity if you want it to be rendered as a sprite. Its `Transform` will be
+/// kept at the pivot of the root tile, so use `Anchor::BottomLeft` for the sprite.
+#[derive(Component, Debug, Clone, Reflect)]
+pub struct TileObject {
+    pub(crate) tilemap: Entity,
+    pub(crate) root: IVec2,
+    pub(crate) size: UVec2,
+    pub(crate) block_path: bool,
+    pub(crate) has_tile: bool,
+    #[cfg(feature = "physics_core")]
+    pub(crate) physics: Option<PhysicsTile>,
+}
+
+impl TileObject {
+    #[inline]
+    pub fn tilemap(&self) -> Entity {
+        self.tilemap
+    }
+
+    /// The bottom left tile of the object.
+    #[inline]
+    pub fn root(&self) -> IVec2 {
+        self.root
+    }
+
+    #[inline]
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// The tiles covered by this object.
+    #[inline]
+    pub fn area(&self) -> TileArea {
+        TileArea::new(self.root, self.size)
+    }
+}
+
+/// Describes an object to place on the tilemap.
+#[derive(Debug, Clone, Reflect)]
+pub struct TileObjectBuilder {
+    pub(crate) size: UVec2,
+    pub(crate) block_path: bool,
+    pub(crate) require_tiles: bool,
+    pub(crate) tile: Option<TileBuilder>,
+    #[cfg(feature = "physics_core")]
+    pub(crate) physics: Option<PhysicsTile>,
+}
+
+impl TileObjectBuilder {
+    /// Create a new object that covers `size` tiles. It blocks path finding by default.
+    pub fn new(size: UVec2) -> Self {
+        assert!(
+            size.cmpgt(UVec2::ZERO).all(),
+            "The size of a tile object must be greater than zero!"
+        );
+
+        Self {
+            size,
+            block_path: true,
+            require_tiles: false,
+            tile: None,
+            #[cfg(feature = "physics_core")]
+            physics: None,
+        }
+    }
+
+    /// Set whether the covered tiles are removed from the `PathTilemap` while the object exists.
+    pub fn with_path_blocked(mut self, block_path: bool) -> Self {
+        self.block_path = block_path;
+        self
+    }
+
+    /// Only allow placing the object when all the covered tiles exist.
+    pub fn with_tiles_required(mut self, require_tiles: bool) -> Self {
+        self.require_tiles = require_tiles;
+        self
+    }
+
+    /// Render the object as a single tile at the root.
+    ///
+    /// Use a tilemap whose `TileRenderSize` is as large as the object to make it cover the whole footprint.
+    pub fn with_tile(mut self, tile: TileBuilder) -> Self {
+        self.tile = Some(tile);
+        self
+    }
+
+    /// Cover the footprint with one physics collider.
+    #[cfg(feature = "physics_core")]
+    pub fn with_physics(mut self, physics: PhysicsTile) -> Self {
+        self.physics = Some(physics);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum PlacementError {
+    /// The tile is already covered by another object.
+    Occupied { index: IVec2, object: Entity },
+    /// The tile doesn't exist while the object requires it.
+    MissingTile(IVec2),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct TileObjectRecord {
+    pub(crate) object: TileObject,
+    /// The path tiles that are removed because of this object.
+    #[cfg(feature = "algorithm")]
+    pub(crate) blocked: Vec<(IVec2, PathTile)>,
+}
+
+/// Records which tiles are covered by objects. Insert this on the tilemap to place objects.
+#[derive(Component, Default, Debug, Clone, Reflect)]
+pub struct TilemapObjects {
+    pub(crate) occupancy: HashMap<IVec2, Entity>,
+    #[reflect(ignore)]
+    pub(crate) objects: EntityHashMap<TileObjectRecord>,
+    /// Objects that are removed but their occupancy on other layers is not cleared yet.
+    #[reflect(ignore)]
+    pub(crate) removed: Vec<TileObjectRecord>,
+}
+
+impl TilemapObjects {
+    /// Get the object covering the tile.
+    #[inline]
+    pub fn get(&self, index: IVec2) -> Option<Entity> {
+        self.occupancy.get(&index).cloned()
+    }
+
+    #[inline]
+    pub fn is_occupied(&self, index: IVec2) -> bool {
+        self.occupancy.contains_key(&index)
+    }
+
+    #[inline]
+    pub fn get_object(&self, object: Entity) -> Option<&TileObject> {
+        self.objects.get(&object).map(|record| &record.object)
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &TileObject)> {
+        self.objects
+            .iter()
+            .map(|(entity, record)| (*entity, &record.object))
+    }
+
+    /// Check if the object can be placed at `root`.
+    pub fn validate(
+        &self,
+        storage: &TilemapStorage,
+        root: IVec2,
+        object: &TileObjectBuilder,
+    ) -> Result<(), PlacementError> {
+        TileArea::new(root, object.size)
+            .aabb()
+            .into_iter()
+            .try_for_each(|index| {
+                if let Some(other) = self.get(index) {
+                    return Err(PlacementError::Occupied {
+                        index,
+                        object: other,
+                    });
+                }
+                if object.require_tiles && storage.get(index).is_none() {
+                    return Err(PlacementError::MissingTile(index));
+                }
+                Ok(())
+            })
+    }
+
+    /// Place an object with its bottom left tile at `root` and return the object entity.
+    ///
+    /// Nothing will happen if the placement is invalid.
+    pub fn place(
+        &mut self,
+        commands: &mut Commands,
+        storage: &mut TilemapStorage,
+        root: IVec2,
+        object: TileObjectBuilder,
+    ) -> Result<Entity, PlacementError> {
+        self.validate(storage, root, &object)?;
+
+        let has_tile = object.tile.is_some();
+        if let Some(tile) = object.tile {
+            storage.set(commands, root, tile);
+        }
+
+        let object = TileObject {
+            tilemap: storage.tilemap,
+            root,
+            size: object.size,
+            block_path: object.block_path,
+            has_tile,
+            #[cfg(feature = "physics_core")]
+            physics: object.physics,
+        };
+        let entity = commands.spawn(object.clone()).id();
+
+        object.area().aabb().into_iter().for_each(|index| {
+            self.occupancy.insert(index, entity);
+        });
+        self.objects.insert(
+            entity,
+            TileObjectRecord {
+                object,
+                #[cfg(feature = "algorithm")]
+                blocked: Vec::new(),
+            },
+        );
+        Ok(entity)
+    }
+
+    /// Remove an object and despawn it.
+    pub fn remove(
+        &mut self,
+        commands: &mut Commands,
+        storage: &mut TilemapStorage,
+        object: Entity,
+    ) -> Option<TileObject> {
+        let record = self.objects.remove(&object)?;
+        record.object.area().aabb().into_iter().for_each(|index| {
+            self.occupancy.remove(&index);
+        });
+        if record.object.has_tile {
+            storage.remove(commands, record.object.root);
+        }
+        commands.entity(object).despawn();
+
+        let removed = record.object.clone();
+        self.removed.push(record);
+        Some(removed)
+    }
+}
+
+/// Blocks and unblocks the tiles covered by objects on path tilemaps and physics tilemaps.
+pub fn object_occupier(
+    #[cfg(feature = "physics_core")] mut commands: Commands,
+    objects_query: Query<(Entity, &TileObject), Added<TileObject>>,
+    mut tilemaps_query: Query<&mut TilemapObjects>,
+    #[cfg(feature = "algorithm")] mut path_tilemaps: Option<ResMut<PathTilemaps>>,
+    #[cfg(feature = "physics_core")] mut physics_tilemaps_query: Query<&mut PhysicsTilemap>,
+) {
+    objects_query.iter().for_each(|(entity, object)| {
+        // Skip the objects that are already removed.
+        if !tilemaps_query
+            .get(object.tilemap)
+            .is_ok_and(|objects| objects.objects.contains_key(&entity))
+        {
+            return;
+        }
+
+        #[cfg(feature = "algorithm")]
+        if object.block_path {
+            if let (Some(path_tilemaps), Ok(mut objects)) = (
+                path_tilemaps.as_mut(),
+                tilemaps_query.get_mut(object.tilemap),
+            ) {
+                let record = objects.objects.get_mut(&entity).unwrap();
+                path_tilemaps.edit(object.tilemap, |path_tilemap| {
+                    record.blocked = object
+                        .area()
+                        .aabb()
+                        .into_iter()
+                        .filter_map(|index| path_tilemap.remove(index).map(|tile| (index, tile)))
+                        .collect();
+                });
+            }
+        }
+
+        #[cfg(feature = "physics_core")]
+        if let Some(physics) = &object.physics {
+            if let Ok(mut physics_tilemap) = physics_tilemaps_query.get_mut(object.tilemap) {
+                physics_tilemap.fill_rect(object.area(), physics.clone(), true);
+            }
+        }
+    });
+
+    tilemaps_query.iter_mut().for_each(|mut objects| {
+        if objects.removed.is_empty() {
+            return;
+        }
+
+        #[allow(unused_variables)]
+        for record in objects.removed.drain(..) {
+            #[cfg(feature = "algorithm")]
+            if let Some(path_tilemaps) = path_tilemaps.as_mut() {
+                path_tilemaps.edit(record.object.tilemap, |path_tilemap| {
+                    record
+                        .blocked
+                        .iter()
+                        .for_each(|(index, tile)| path_tilemap.set(*index, *tile));
+                });
+            }
+
+            #[cfg(feature = "physics_core")]
+            if record.object.physics.is_some() {
+                if let Ok(mut physics_tilemap) =
+                    physics_tilemaps_query.get_mut(record.object.tilemap)
+                {
+                    // The footprint is covered by one concatenated collider.
+                    let colliders = record
+                        .object
+                        .area()
+                        .aabb()
+                        .into_iter()
+                        .filter_map(|index| physics_tilemap.storage.remove_elem(index))
+                        .collect::<bevy::utils::HashSet<_>>();
+                    colliders.into_iter().for_each(|collider| {
+                        commands.entity(collider).despawn();
+                    });
+                }
+            }
+        }
+    });
+}
+
+/// Keeps the `Transform` of the objects at the pivot of their root tiles.
+pub fn object_anchor_syncer(
+    mut objects_query: Query<(Entity, &TileObject, &mut Transform)>,
+    new_objects_query: Query<(), Or<(Added<TileObject>, Added<Transform>)>>,
+    changed_tilemaps_query: Query<
+        (),
+        Or<(
+            Changed<TilemapTransform>,
+            Changed<TilePivot>,
+            Changed<TilemapSlotSize>,
+            Changed<TilemapAxisFlip>,
+        )>,
+    >,
+    tilemaps_query: Query<(
+        &TilemapType,
+        &TilemapAxisFlip,
+        &TilemapTransform,
+        &TilePivot,
+        &TilemapSlotSize,
+    )>,
+) {
+    objects_query
+        .iter_mut()
+        .for_each(|(entity, object, mut transform)| {
+            if !new_objects_query.contains(entity)
+                && !changed_tilemaps_query.contains(object.tilemap)
+            {
+                return;
+            }
+            let Ok((ty, axis_flip, tilemap_transform, pivot, slot_size)) =
+                tilemaps_query.get(object.tilemap)
+            else {
+                return;
+            };
+
+            let world = coordinates::index_to_world_flipped(
+                object.root,
+                *ty,
+                tilemap_transform,
+                pivot.0,
+                slot_size.0,
+                *axis_flip,
+            );
+            transform.translation = world.extend(transform.translation.z);
+        });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_object_validation() {
+        let tilemap = Entity::from_raw(0);
+        let storage = TilemapStorage::new(16, tilemap);
+        let mut objects = TilemapObjects::default();
+        let house = Entity::from_raw(1);
+        TileArea::new(IVec2::ZERO, UVec2::new(3, 2))
+            .aabb()
+            .into_iter()
+            .for_each(|index| {
+                objects.occupancy.insert(index, house);
+            });
+
+        let tree = TileObjectBuilder::new(UVec2::splat(2));
+        assert_eq!(
+            objects.validate(&storage, IVec2::new(2, 1), &tree),
+            Err(PlacementError::Occupied {
+                index: IVec2::new(2, 1),
+                object: house
+            })
+        );
+        assert_eq!(objects.validate(&storage, IVec2::new(3, 0), &tree), Ok(()));
+        assert_eq!(
+            objects.validate(&storage, IVec2::new(3, 0), &tree.with_tiles_required(true)),
+            Err(PlacementError::MissingTile(IVec2::new(3, 0)))
+        );
+    }
+}
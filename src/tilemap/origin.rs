@@ -0,0 +1,184 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        change_detection::DetectChanges,
+        component::Component,
+        schedule::IntoSystemConfigs,
+        system::{Query, Res, Resource},
+        world::Ref,
+    },
+    math::{DVec2, I64Vec2, IVec2, Mat2, Vec2},
+    reflect::Reflect,
+};
+
+use super::{
+    coordinates,
+    map::{self, TilemapAxisFlip, TilemapSlotSize, TilemapTransform, TilemapType},
+};
+
+pub struct EntiTilesFloatingOriginPlugin;
+
+impl Plugin for EntiTilesFloatingOriginPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, origin_rebaser.before(map::transform_syncer));
+
+        app.init_resource::<FloatingOrigin>();
+
+        app.register_type::<FloatingOrigin>()
+            .register_type::<TilemapAnchor>();
+    }
+}
+
+/// The origin of the rendering space in world units, with double precision.
+///
+/// Tilemaps with a `TilemapAnchor` are placed relative to this origin. Move it along
+/// with the camera in huge worlds so the `f32` coordinates always stay near zero.
+/// Remember to move your camera and other entities by the same amount when changing it.
+#[derive(Resource, Default, Debug, Clone, Copy, Reflect)]
+pub struct FloatingOrigin(pub DVec2);
+
+/// Places the tilemap in a huge world using 64 bit tile coordinates.
+///
+/// The `TilemapTransform::translation` of the tilemap will be managed according to
+/// the `FloatingOrigin`, so don't change it manually. The tiles are also rebased
+/// when rendering, so tiles far from the tilemap origin won't jitter either.
+#[derive(Component, Default, Debug, Clone, Copy, Reflect)]
+pub struct TilemapAnchor {
+    /// Where the tile `(0, 0)` of this tilemap is, in the coordinates of the tilemap itself.
+    pub tile: I64Vec2,
+    /// Extra offset in world units.
+    pub offset: Vec2,
+}
+
+/// The offset from the mesh origin of tile `(0, 0)` to the one of `index`,
+/// in the local space of the tilemap. This matches `get_mesh_origin` in the shaders.
+pub fn index_offset(
+    index: I64Vec2,
+    ty: TilemapType,
+    axis_flip: TilemapAxisFlip,
+    slot_size: Vec2,
+) -> DVec2 {
+    let index = index.as_dvec2() * axis_flip.as_vec2().as_dvec2();
+    let slot_size = slot_size.as_dvec2();
+    match ty {
+        TilemapType::Square => index * slot_size,
+        TilemapType::Isometric => DVec2::new(index.x - index.y, index.x + index.y) / 2. * slot_size,
+        TilemapType::Hexagonal(legs) => DVec2::new(
+            slot_size.x * (index.x - 0.5 * index.y),
+            (slot_size.y + legs as f64) / 2. * index.y,
+        ),
+    }
+}
+
+#[inline]
+fn rotate(rotation: Mat2, v: DVec2) -> DVec2 {
+    rotation.x_axis.as_dvec2() * v.x + rotation.y_axis.as_dvec2() * v.y
+}
+
+/// The translation of an anchored tilemap relative to the floating origin.
+pub fn anchored_translation(
+    anchor: &TilemapAnchor,
+    origin: &FloatingOrigin,
+    ty: TilemapType,
+    transform: &TilemapTransform,
+    axis_flip: TilemapAxisFlip,
+    slot_size: Vec2,
+) -> DVec2 {
+    rotate(
+        transform.get_rotation_matrix(),
+        index_offset(anchor.tile, ty, axis_flip, slot_size),
+    ) + anchor.offset.as_dvec2()
+        - origin.0
+}
+
+/// Returns the tile index that should be treated as `(0, 0)` when rendering,
+/// and the translation of the tilemap that matches it.
+pub(crate) fn rebase(
+    anchor: &TilemapAnchor,
+    origin: &FloatingOrigin,
+    ty: TilemapType,
+    transform: &TilemapTransform,
+    axis_flip: TilemapAxisFlip,
+    pivot: Vec2,
+    slot_size: Vec2,
+) -> (IVec2, Vec2) {
+    // The tile at the floating origin.
+    let index = coordinates::world_to_index(Vec2::ZERO, ty, transform, pivot, slot_size)
+        * axis_flip.as_vec2().as_ivec2();
+    let translation = anchored_translation(anchor, origin, ty, transform, axis_flip, slot_size)
+        + rotate(
+            transform.get_rotation_matrix(),
+            index_offset(index.as_i64vec2(), ty, axis_flip, slot_size),
+        );
+    (index, translation.as_vec2())
+}
+
+pub fn origin_rebaser(
+    origin: Res<FloatingOrigin>,
+    mut tilemaps_query: Query<(
+        Ref<TilemapAnchor>,
+        &TilemapType,
+        &TilemapAxisFlip,
+        &TilemapSlotSize,
+        &mut TilemapTransform,
+    )>,
+) {
+    tilemaps_query
+        .iter_mut()
+        .for_each(|(anchor, ty, axis_flip, slot_size, mut transform)| {
+            if !origin.is_changed() && !anchor.is_changed() {
+                return;
+            }
+
+            transform.translation =
+                anchored_translation(&anchor, &origin, *ty, &transform, *axis_flip, slot_size.0)
+                    .as_vec2();
+        });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rebase() {
+        let origin = FloatingOrigin(DVec2::new(1e9 + 0.25, -3e8));
+        let anchor = TilemapAnchor {
+            tile: I64Vec2::new(62_500_000, -18_750_000),
+            offset: Vec2::ZERO,
+        };
+        let slot_size = Vec2::splat(16.);
+        let ty = TilemapType::Square;
+        let mut transform = TilemapTransform::default();
+        transform.translation = anchored_translation(
+            &anchor,
+            &origin,
+            ty,
+            &transform,
+            Default::default(),
+            slot_size,
+        )
+        .as_vec2();
+        assert_eq!(transform.translation, Vec2::new(-0.25, 0.));
+
+        // Far away from the tilemap origin.
+        let far = TilemapAnchor {
+            tile: anchor.tile - I64Vec2::new(1_000_003, 0),
+            ..anchor
+        };
+        transform.translation =
+            anchored_translation(&far, &origin, ty, &transform, Default::default(), slot_size)
+                .as_vec2();
+        let (index, translation) = rebase(
+            &far,
+            &origin,
+            ty,
+            &transform,
+            Default::default(),
+            Vec2::ZERO,
+            slot_size,
+        );
+        assert_eq!(index, IVec2::new(1_000_003, 0));
+        assert_eq!(translation, Vec2::new(-0.25, 0.));
+    }
+}
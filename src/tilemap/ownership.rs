@@ -0,0 +1,288 @@
+use bevy::{
+    app::{App, Plugin, PostUpdate},
+    ecs::{
+        change_detection::{DetectChanges, DetectChangesMut},
+        component::Component,
+        entity::Entity,
+        removal_detection::RemovedComponents,
+        schedule::IntoSystemConfigs,
+        system::Query,
+    },
+    math::IVec2,
+    reflect::Reflect,
+    render::color::Color,
+    utils::{HashMap, HashSet},
+};
+
+use crate::math::{extension::TileIndex, TileArea};
+
+use super::{
+    chunking::storage::ChunkedStorage,
+    despawn,
+    map::{TilemapStorage, TilemapType},
+    packed::TilemapPackedTiles,
+    tile::Tile,
+};
+
+pub type OwnerId = u32;
+
+pub struct EntiTilesTileOwnershipPlugin;
+
+impl Plugin for EntiTilesTileOwnershipPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            ownership_tint_updater.before(despawn::despawn_tiles),
+        );
+
+        app.register_type::<TilemapOwnership>();
+    }
+}
+
+/// A layer that records which player or team owns each tile, for strategy games.
+///
+/// Goes on the tilemap entity and is indexed by tile index. The owners are kept
+/// apart from the tiles, so land can be claimed before its tiles are spawned and
+/// stays claimed when they are despawned. Owners with a tint set in `set_tint`
+/// tint the tiles they own.
+#[derive(Component, Debug, Clone, Reflect)]
+#[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
+pub struct TilemapOwnership {
+    pub(crate) storage: ChunkedStorage<OwnerId>,
+    #[cfg_attr(feature = "serializing", serde(default))]
+    pub(crate) tints: HashMap<OwnerId, Color>,
+    /// Tiles whose tints changed since the last update.
+    #[cfg_attr(feature = "serializing", serde(skip))]
+    #[reflect(ignore)]
+    pub(crate) dirty: HashSet<IVec2>,
+    #[cfg_attr(feature = "serializing", serde(skip))]
+    #[reflect(ignore)]
+    pub(crate) tints_changed: bool,
+}
+
+impl Default for TilemapOwnership {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TilemapOwnership {
+    /// Create a new ownership layer with default chunk size.
+    ///
+    /// Use `new_with_chunk_size` to specify a custom chunk size.
+    pub fn new() -> Self {
+        Self::new_with_chunk_size(crate::DEFAULT_CHUNK_SIZE)
+    }
+
+    /// Create a new ownership layer with custom chunk size.
+    pub fn new_with_chunk_size(chunk_size: u32) -> Self {
+        Self {
+            storage: ChunkedStorage::new(chunk_size),
+            tints: HashMap::new(),
+            dirty: HashSet::new(),
+            tints_changed: false,
+        }
+    }
+
+    pub fn with_tint(mut self, owner: OwnerId, tint: Color) -> Self {
+        self.set_tint(owner, Some(tint));
+        self
+    }
+
+    /// Set the color that will be multiplied with the tiles owned by `owner`.
+    pub fn set_tint(&mut self, owner: OwnerId, tint: Option<Color>) {
+        match tint {
+            Some(tint) => self.tints.insert(owner, tint),
+            None => self.tints.remove(&owner),
+        };
+        self.tints_changed = true;
+    }
+
+    #[inline]
+    pub fn get_tint(&self, owner: OwnerId) -> Option<Color> {
+        self.tints.get(&owner).cloned()
+    }
+
+    /// Get the owner of a tile.
+    #[inline]
+    pub fn get(&self, index: IVec2) -> Option<OwnerId> {
+        self.storage.get_elem(index).cloned()
+    }
+
+    /// Set or clear the owner of a tile.
+    pub fn set(&mut self, index: IVec2, owner: Option<OwnerId>) {
+        if self.get(index) == owner {
+            return;
+        }
+
+        match owner {
+            Some(owner) => self.storage.set_elem(index, owner),
+            None => {
+                self.storage.remove_elem(index);
+            }
+        }
+        self.dirty.insert(index);
+    }
+
+    /// Give the tiles to `owner`.
+    pub fn claim(&mut self, indices: impl IntoIterator<Item = IVec2>, owner: OwnerId) {
+        indices
+            .into_iter()
+            .for_each(|index| self.set(index, Some(owner)));
+    }
+
+    /// Give a rectangle area to `owner`.
+    #[inline]
+    pub fn claim_area(&mut self, area: TileArea, owner: OwnerId) {
+        self.claim(area.aabb().into_iter(), owner);
+    }
+
+    /// Remove the owner of the tiles.
+    pub fn release(&mut self, indices: impl IntoIterator<Item = IVec2>) {
+        indices.into_iter().for_each(|index| self.set(index, None));
+    }
+
+    /// Iterate over the tiles owned by `owner`.
+    pub fn territory(&self, owner: OwnerId) -> impl Iterator<Item = IVec2> + '_ {
+        self.storage
+            .chunked_iter_some()
+            .filter(move |(_, _, o)| **o == owner)
+            .map(|(chunk_index, in_chunk_index, _)| {
+                self.storage
+                    .inverse_transform_index(chunk_index, in_chunk_index)
+            })
+    }
+
+    /// The amount of tiles each owner has.
+    pub fn territory_sizes(&self) -> HashMap<OwnerId, usize> {
+        self.storage
+            .iter_some()
+            .fold(HashMap::new(), |mut acc, owner| {
+                *acc.entry(*owner).or_default() += 1;
+                acc
+            })
+    }
+
+    /// Returns true if the tile is owned and any of its neighbours is owned by someone else.
+    pub fn is_frontier(&self, index: IVec2, ty: TilemapType) -> bool {
+        let Some(owner) = self.get(index) else {
+            return false;
+        };
+
+        index
+            .neighbours(ty, false)
+            .into_iter()
+            .flatten()
+            .any(|neighbour| self.get(neighbour).is_some_and(|o| o != owner))
+    }
+
+    /// The tiles of `owner` that are adjacent to tiles of other owners.
+    pub fn frontier(&self, owner: OwnerId, ty: TilemapType) -> Vec<IVec2> {
+        self.territory(owner)
+            .filter(|index| self.is_frontier(*index, ty))
+            .collect()
+    }
+
+    pub(crate) fn apply_tint(&self, index: IVec2, tint: Color) -> Color {
+        match self.get(index).and_then(|owner| self.get_tint(owner)) {
+            Some(owner_tint) => Color::rgba_linear_from_array(
+                tint.rgba_linear_to_vec4() * owner_tint.rgba_linear_to_vec4(),
+            ),
+            None => tint,
+        }
+    }
+}
+
+/// Marks the tiles whose owner tints changed so they will be extracted again.
+pub fn ownership_tint_updater(
+    mut tilemaps_query: Query<(Entity, &TilemapStorage, &mut TilemapOwnership)>,
+    storages_query: Query<&TilemapStorage>,
+    mut packed_query: Query<&mut TilemapPackedTiles>,
+    mut tiles_query: Query<&mut Tile>,
+    mut removed: RemovedComponents<TilemapOwnership>,
+) {
+    let mut changed = Vec::<Entity>::new();
+
+    tilemaps_query
+        .iter_mut()
+        .for_each(|(entity, storage, mut ownership)| {
+            if !ownership.is_changed() {
+                return;
+            }
+
+            let added = ownership.is_added();
+            let ownership = ownership.bypass_change_detection();
+            let packed = packed_query.get_mut(entity).ok();
+            if added || ownership.tints_changed {
+                changed.extend(storage.storage.iter_some().cloned());
+                if let Some(mut packed) = packed {
+                    packed.mark_all_changed();
+                }
+            } else if !ownership.tints.is_empty() {
+                changed.extend(
+                    ownership
+                        .dirty
+                        .iter()
+                        .filter_map(|index| storage.get(*index)),
+                );
+                if let Some(mut packed) = packed {
+                    ownership
+                        .dirty
+                        .iter()
+                        .for_each(|index| packed.mark_changed(*index));
+                }
+            }
+            ownership.tints_changed = false;
+            ownership.dirty.clear();
+        });
+
+    removed.read().for_each(|entity| {
+        if let Ok(storage) = storages_query.get(entity) {
+            changed.extend(storage.storage.iter_some().cloned());
+        }
+        if let Ok(mut packed) = packed_query.get_mut(entity) {
+            packed.mark_all_changed();
+        }
+    });
+
+    changed.into_iter().for_each(|entity| {
+        if let Ok(mut tile) = tiles_query.get_mut(entity) {
+            tile.set_changed();
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::math::UVec2;
+
+    use super::*;
+
+    #[test]
+    fn test_ownership() {
+        let mut ownership = TilemapOwnership::new_with_chunk_size(4).with_tint(1, Color::RED);
+        ownership.claim_area(TileArea::new(IVec2::ZERO, UVec2::new(3, 2)), 1);
+        ownership.claim([IVec2::new(3, 0), IVec2::new(3, 1)], 2);
+        ownership.release([IVec2::new(0, 0)]);
+
+        assert_eq!(ownership.get(IVec2::new(0, 0)), None);
+        assert_eq!(ownership.get(IVec2::new(1, 1)), Some(1));
+        assert_eq!(ownership.territory(2).count(), 2);
+        assert_eq!(ownership.territory_sizes()[&1], 5);
+
+        let mut frontier = ownership.frontier(1, TilemapType::Square);
+        frontier.sort_by_key(|index| (index.y, index.x));
+        assert_eq!(frontier, vec![IVec2::new(2, 0), IVec2::new(2, 1)]);
+        assert!(ownership.is_frontier(IVec2::new(3, 0), TilemapType::Square));
+        assert!(!ownership.is_frontier(IVec2::new(1, 1), TilemapType::Square));
+
+        assert_eq!(
+            ownership.apply_tint(IVec2::new(1, 0), Color::WHITE),
+            Color::rgba_linear_from_array(Color::RED.rgba_linear_to_vec4())
+        );
+        assert_eq!(
+            ownership.apply_tint(IVec2::new(3, 0), Color::WHITE),
+            Color::WHITE
+        );
+    }
+}
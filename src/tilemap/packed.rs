@@ -0,0 +1,241 @@
+use bevy::{
+    app::{App, Last, Plugin},
+    ecs::{change_detection::DetectChangesMut, component::Component, system::Query},
+    math::IVec2,
+    reflect::Reflect,
+    utils::HashSet,
+};
+
+use crate::math::TileArea;
+
+use super::{chunking::storage::ChunkedStorage, map::TilemapStorage, tile::TileBuilder};
+
+pub struct EntiTilesPackedTilesPlugin;
+
+impl Plugin for EntiTilesPackedTilesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Last, packed_tiles_flusher);
+
+        app.register_type::<TilemapPackedTiles>();
+    }
+}
+
+/// Tiles that are stored in a packed buffer on the tilemap instead of as entities.
+///
+/// Insert this component on a tilemap to store millions of tiles without spawning
+/// an entity for each of them. The tiles are still rendered in the same chunks, but
+/// they can't have components, so things like `TileUpdater`, sensors or behaviors don't
+/// work on them. Only the changed tiles are sent to the renderer.
+///
+/// The chunk size **must** be the same as the `TilemapStorage` of the tilemap.
+/// Don't put an entity tile and a packed tile at the same index.
+#[derive(Component, Debug, Clone, Reflect)]
+pub struct TilemapPackedTiles {
+    pub(crate) storage: ChunkedStorage<TileBuilder>,
+    /// The indices that are changed since the last extraction.
+    pub(crate) dirty: HashSet<IVec2>,
+    /// The changes that are extracted in this frame.
+    pub(crate) extracting: HashSet<IVec2>,
+}
+
+impl TilemapPackedTiles {
+    pub fn new(chunk_size: u32) -> Self {
+        Self {
+            storage: ChunkedStorage::new(chunk_size),
+            dirty: Default::default(),
+            extracting: Default::default(),
+        }
+    }
+
+    /// Get a tile.
+    #[inline]
+    pub fn get(&self, index: IVec2) -> Option<&TileBuilder> {
+        self.storage.get_elem(index)
+    }
+
+    /// Get a tile mutably. The tile will be re-rendered.
+    #[inline]
+    pub fn get_mut(&mut self, index: IVec2) -> Option<&mut TileBuilder> {
+        let tile = self.storage.get_elem_mut(index)?;
+        self.dirty.insert(index);
+        Some(tile)
+    }
+
+    /// Set a tile.
+    ///
+    /// Overwrites the tile if it already exists.
+    #[inline]
+    pub fn set(&mut self, index: IVec2, tile_builder: TileBuilder) {
+        self.storage.set_elem(index, tile_builder);
+        self.dirty.insert(index);
+    }
+
+    /// Remove a tile.
+    #[inline]
+    pub fn remove(&mut self, index: IVec2) -> Option<TileBuilder> {
+        let tile = self.storage.remove_elem(index)?;
+        self.dirty.insert(index);
+        Some(tile)
+    }
+
+    /// Fill a rectangle area with the same tile.
+    pub fn fill_rect(&mut self, area: TileArea, tile_builder: TileBuilder) {
+        self.fill_rect_custom(area, |_| Some(tile_builder.clone()));
+    }
+
+    /// Fill a rectangle area with tiles returned by `tile_builder`.
+    pub fn fill_rect_custom(
+        &mut self,
+        area: TileArea,
+        mut tile_builder: impl FnMut(IVec2) -> Option<TileBuilder>,
+    ) {
+        for y in area.origin.y..=area.dest.y {
+            for x in area.origin.x..=area.dest.x {
+                let index = IVec2 { x, y };
+                if let Some(builder) = tile_builder(index) {
+                    self.set(index, builder);
+                }
+            }
+        }
+    }
+
+    /// Remove all the tiles.
+    pub fn clear(&mut self) {
+        let indices = self.iter().map(|(index, _)| index).collect::<Vec<_>>();
+        self.dirty.extend(indices);
+        self.storage.clear();
+    }
+
+    /// Iterate over the indices and the tiles.
+    pub fn iter(&self) -> impl Iterator<Item = (IVec2, &TileBuilder)> {
+        self.storage
+            .chunked_iter_some()
+            .map(|(chunk_index, in_chunk_index, tile)| {
+                (
+                    self.storage
+                        .inverse_transform_index(chunk_index, in_chunk_index),
+                    tile,
+                )
+            })
+    }
+
+    /// Send all the tiles to the renderer again.
+    pub fn mark_all_changed(&mut self) {
+        let indices = self.iter().map(|(index, _)| index).collect::<Vec<_>>();
+        self.dirty.extend(indices);
+    }
+
+    /// Send the tile to the renderer again.
+    #[inline]
+    pub fn mark_changed(&mut self, index: IVec2) {
+        if self.storage.get_elem(index).is_some() {
+            self.dirty.insert(index);
+        }
+    }
+
+    /// Send the tiles in the chunk to the renderer again.
+    pub fn mark_chunk_changed(&mut self, chunk_index: IVec2) {
+        let indices = self
+            .storage
+            .iter_chunk(chunk_index)
+            .map(|(in_chunk_index, _)| {
+                self.storage
+                    .inverse_transform_index(chunk_index, in_chunk_index)
+            })
+            .collect::<Vec<_>>();
+        self.dirty.extend(indices);
+    }
+
+    /// The chunk size of the packed buffer.
+    #[inline]
+    pub fn chunk_size(&self) -> u32 {
+        self.storage.chunk_size
+    }
+}
+
+/// Hands the changes of this frame over to the extraction and reserves their chunks.
+///
+/// Runs in `Last`, so the tiles set anywhere in the frame, including `Startup`,
+/// are extracted exactly once.
+pub fn packed_tiles_flusher(
+    mut tilemaps_query: Query<(&mut TilemapPackedTiles, &mut TilemapStorage)>,
+) {
+    tilemaps_query
+        .par_iter_mut()
+        .for_each(|(mut packed, mut storage)| {
+            if packed.dirty.is_empty() && packed.extracting.is_empty() {
+                return;
+            }
+
+            let packed = packed.bypass_change_detection();
+            packed.extracting = std::mem::take(&mut packed.dirty);
+            let chunks = packed
+                .extracting
+                .iter()
+                .map(|index| packed.storage.transform_index(*index).0)
+                .filter(|chunk_index| !storage.reserved.contains_key(chunk_index))
+                .collect::<HashSet<_>>();
+            if !chunks.is_empty() {
+                storage.reserve_many(chunks.into_iter());
+            }
+        });
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::{
+        app::App,
+        ecs::{entity::Entity, world::Mut},
+    };
+
+    use crate::tilemap::tile::TileLayer;
+
+    use super::*;
+
+    #[test]
+    fn test_packed_tiles() {
+        let mut app = App::new();
+        app.add_plugins(EntiTilesPackedTilesPlugin);
+
+        let mut packed = TilemapPackedTiles::new(4);
+        packed.fill_rect(
+            TileArea::new(IVec2::ZERO, (6, 2).into()),
+            TileBuilder::new().with_layer(0, TileLayer::no_flip(1)),
+        );
+        assert_eq!(packed.iter().count(), 12);
+        assert_eq!(packed.dirty.len(), 12);
+        assert_eq!(
+            packed.get(IVec2::new(5, 1)),
+            Some(&TileBuilder::new().with_layer(0, TileLayer::no_flip(1)))
+        );
+
+        let tilemap = app.world.spawn_empty().id();
+        app.world
+            .entity_mut(tilemap)
+            .insert((packed, TilemapStorage::new(4, tilemap)));
+        app.update();
+
+        let mut entity = app.world.entity_mut(tilemap);
+        let storage = entity.get::<TilemapStorage>().unwrap();
+        assert!(storage.calc_queue.contains(&IVec2::ZERO));
+        assert!(storage.calc_queue.contains(&IVec2::X));
+        assert_eq!(storage.storage.get_elem(IVec2::ZERO), None::<&Entity>);
+
+        let mut packed: Mut<TilemapPackedTiles> = entity.get_mut().unwrap();
+        // The tiles set before the first frame are all extracted.
+        assert!(packed.dirty.is_empty());
+        assert_eq!(packed.extracting.len(), 12);
+        assert!(packed.remove(IVec2::ZERO).is_some());
+        assert!(packed.remove(IVec2::ZERO).is_none());
+        assert!(packed.get_mut(IVec2::ONE).is_some());
+        assert_eq!(packed.dirty.len(), 2);
+        app.update();
+
+        let mut packed = app.world.get_mut::<TilemapPackedTiles>(tilemap).unwrap();
+        assert_eq!(packed.extracting.len(), 2);
+        packed.clear();
+        assert_eq!(packed.iter().count(), 0);
+        // The removed tile was extracted in the last frame.
+        assert_eq!(packed.dirty.len(), 11);
+    }
+}
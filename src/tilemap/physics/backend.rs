@@ -0,0 +1,66 @@
+use std::marker::PhantomData;
+
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        entity::Entity,
+        query::Added,
+        schedule::IntoSystemConfigs,
+        system::{Commands, EntityCommands, Query},
+    },
+};
+
+use super::{PackedPhysicsTile, TilemapPhysicsPlatform};
+
+/// Spawns the actual colliders of physics tiles for a physics engine.
+///
+/// The crate figures out the shapes of the colliders, and spawns an entity with
+/// `PackedPhysicsTile` for each of them. Backends then insert their own components
+/// onto these entities. Add `EntiTilesPhysicsBackendPlugin` with your backend
+/// to use it. The `bevy_xpbd` backend is added automatically with the `physics` feature,
+/// use `physics_core` to leave it out.
+pub trait TilemapPhysicsBackend: Send + Sync + 'static {
+    /// Insert the collider and other physics components of the tile.
+    fn insert_collider(commands: &mut EntityCommands, tile: &PackedPhysicsTile);
+
+    /// Insert the kinematic body onto a tilemap with `TilemapPhysicsPlatform`.
+    fn insert_platform_body(commands: &mut EntityCommands);
+}
+
+pub struct EntiTilesPhysicsBackendPlugin<B: TilemapPhysicsBackend>(PhantomData<B>);
+
+impl<B: TilemapPhysicsBackend> Default for EntiTilesPhysicsBackendPlugin<B> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<B: TilemapPhysicsBackend> Plugin for EntiTilesPhysicsBackendPlugin<B> {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                physics_backend_collider_inserter::<B>.after(super::systems::spawn_colliders),
+                physics_backend_platform_inserter::<B>,
+            ),
+        );
+    }
+}
+
+pub fn physics_backend_collider_inserter<B: TilemapPhysicsBackend>(
+    mut commands: Commands,
+    tiles_query: Query<(Entity, &PackedPhysicsTile), Added<PackedPhysicsTile>>,
+) {
+    tiles_query.iter().for_each(|(entity, tile)| {
+        B::insert_collider(&mut commands.entity(entity), tile);
+    });
+}
+
+pub fn physics_backend_platform_inserter<B: TilemapPhysicsBackend>(
+    mut commands: Commands,
+    tilemaps_query: Query<Entity, Added<TilemapPhysicsPlatform>>,
+) {
+    tilemaps_query.iter().for_each(|entity| {
+        B::insert_platform_body(&mut commands.entity(entity));
+    });
+}
@@ -5,10 +5,6 @@ use bevy::{
     reflect::Reflect,
     utils::HashMap,
 };
-use bevy_xpbd_2d::{
-    components::{Friction, RigidBody},
-    plugins::collision::Collider,
-};
 
 use crate::math::{aabb::IAabb2d, TileArea};
 
@@ -17,7 +13,10 @@ use super::{
     chunking::storage::{ChunkedStorage, EntityChunkedStorage, PackedPhysicsTileChunkedStorage},
 };
 
+pub mod backend;
 pub mod systems;
+#[cfg(feature = "physics")]
+pub mod xpbd;
 
 pub struct EntiTilesPhysicsTilemapPlugin;
 
@@ -28,11 +27,34 @@ impl Plugin for EntiTilesPhysicsTilemapPlugin {
             (
                 systems::spawn_colliders,
                 systems::data_physics_tilemap_analyzer,
+                systems::platform_transform_syncer,
             ),
         );
 
+        #[cfg(feature = "physics")]
+        {
+            use bevy::{app::PostUpdate, ecs::schedule::IntoSystemConfigs};
+            use bevy_xpbd_2d::plugins::setup::PhysicsSet;
+
+            app.add_plugins(backend::EntiTilesPhysicsBackendPlugin::<
+                xpbd::XpbdPhysicsBackend,
+            >::default());
+            // The conveyance is only added to the bodies during the physics step.
+            app.add_systems(
+                PostUpdate,
+                (
+                    xpbd::conveyance_applier.before(PhysicsSet::Prepare),
+                    xpbd::conveyance_remover.after(PhysicsSet::StepSimulation),
+                    xpbd::platform_velocity_driver.before(PhysicsSet::Prepare),
+                ),
+            );
+        }
+
         app.register_type::<PhysicsTileSpawn>()
+            .register_type::<PackedPhysicsTile>()
             .register_type::<PhysicsTilemap>()
+            .register_type::<TilemapPhysicsPlatform>()
+            .register_type::<PlatformVelocityDriven>()
             .register_type::<DataPhysicsTilemap>()
             .register_type::<PhysicsTile>();
 
@@ -85,7 +107,9 @@ impl PhysicsCollider {
     }
 }
 
-#[derive(Debug, Clone, Reflect)]
+/// The entity of a spawned physics tile holds this component. The colliders
+/// are inserted by the `TilemapPhysicsBackend`.
+#[derive(Component, Debug, Clone, Reflect)]
 #[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
 pub struct PackedPhysicsTile {
     pub parent: IVec2,
@@ -103,17 +127,7 @@ impl Tiles for PackedPhysicsTile {}
 
 impl PackedPhysicsTile {
     pub fn spawn(&self, commands: &mut Commands) -> Entity {
-        let mut entity = commands.spawn(match self.collider.clone() {
-            PhysicsCollider::Convex(verts) => Collider::convex_hull(verts).unwrap(),
-            PhysicsCollider::Polyline(verts) => Collider::polyline(verts, None),
-        });
-        if self.physics_tile.rigid_body {
-            entity.insert(RigidBody::Static);
-        }
-        if let Some(friction) = &self.physics_tile.friction {
-            entity.insert(Friction::new(*friction));
-        }
-        entity.id()
+        commands.spawn(self.clone()).id()
     }
 }
 
@@ -146,6 +160,10 @@ pub struct DataPhysicsTilemap {
     pub(crate) data: Vec<i32>,
     pub(crate) size: UVec2,
     pub(crate) air: i32,
+    #[cfg_attr(
+        feature = "serializing",
+        serde(serialize_with = "crate::serializing::serialize_sorted")
+    )]
     pub(crate) tiles: HashMap<i32, PhysicsTile>,
 }
 
@@ -230,11 +248,27 @@ impl DataPhysicsTilemap {
     }
 }
 
+/// Makes the whole tilemap a kinematic body, like elevator platforms or ships built of tiles.
+///
+/// Insert this before any physics tiles are spawned, and move the tilemap using `TilemapTransform`.
+/// The `Transform` of the tilemap is kept in sync with it, and the colliders of the tiles
+/// are spawned as children of the tilemap. Backends that insert `PlatformVelocityDriven`
+/// move the body with its velocities instead, so the bodies on it are carried along.
+#[derive(Component, Debug, Default, Clone, Copy, Reflect)]
+pub struct TilemapPhysicsPlatform;
+
+/// Inserted by the physics backends that move the `TilemapPhysicsPlatform` towards its
+/// `TilemapTransform` by setting its velocities. The `Transform` of these platforms is
+/// left to the physics engine after it's first inserted.
+#[derive(Component, Debug, Default, Clone, Copy, Reflect)]
+pub struct PlatformVelocityDriven;
+
 /// A tilemap with physics tiles.
 #[derive(Component, Debug, Clone, Reflect)]
 pub struct PhysicsTilemap {
     pub(crate) storage: EntityChunkedStorage,
     pub(crate) spawn_queue: Vec<(IAabb2d, PhysicsTile, Option<i32>)>,
+    pub(crate) shaped_queue: Vec<(IVec2, PhysicsTile, Vec<Vec2>)>,
     pub(crate) data: PackedPhysicsTileChunkedStorage,
 }
 
@@ -246,6 +280,7 @@ impl PhysicsTilemap {
         PhysicsTilemap {
             storage: ChunkedStorage::default(),
             spawn_queue: Vec::new(),
+            shaped_queue: Vec::new(),
             data: ChunkedStorage::default(),
         }
     }
@@ -255,6 +290,7 @@ impl PhysicsTilemap {
         PhysicsTilemap {
             storage: ChunkedStorage::new(chunk_size),
             spawn_queue: Vec::new(),
+            shaped_queue: Vec::new(),
             data: ChunkedStorage::new(chunk_size),
         }
     }
@@ -271,6 +307,19 @@ impl PhysicsTilemap {
         self.spawn_queue.push((IAabb2d::splat(index), tile, None));
     }
 
+    /// Set a tile with a custom convex shape, in tile space where `(0, 0)` is the
+    /// bottom left corner and `(1, 1)` is the top right corner of the tile.
+    ///
+    /// Use `TilemapTextureMetadata::shape` to get the shape from the texture of the tile.
+    /// Only square tilemaps are supported.
+    ///
+    /// Shapes with less than 2 points are skipped. If all the points are on one line,
+    /// the tile gets a polyline collider instead.
+    #[inline]
+    pub fn set_shaped(&mut self, index: IVec2, tile: PhysicsTile, shape: Vec<Vec2>) {
+        self.shaped_queue.push((index, tile, shape));
+    }
+
     /// Remove a tile.
     #[inline]
     pub fn remove(&mut self, commands: &mut Commands, index: IVec2) {
@@ -2,9 +2,12 @@ use bevy::{
     ecs::{
         entity::Entity,
         event::EventWriter,
-        system::{ParallelCommands, Query},
+        query::{Added, Changed, Has, Or, With},
+        system::{Commands, ParallelCommands, Query},
     },
-    math::UVec2,
+    hierarchy::BuildChildren,
+    math::{UVec2, Vec2},
+    transform::{components::Transform, TransformBundle},
 };
 
 use crate::{
@@ -12,12 +15,13 @@ use crate::{
     tilemap::{
         chunking::storage::ChunkedStorage,
         coordinates,
-        map::{TilePivot, TilemapSlotSize, TilemapTransform, TilemapType},
+        map::{TilePivot, TilemapAxisFlip, TilemapSlotSize, TilemapTransform, TilemapType},
     },
 };
 
 use super::{
     DataPhysicsTilemap, PackedPhysicsTile, PhysicsCollider, PhysicsTileSpawn, PhysicsTilemap,
+    PlatformVelocityDriven, TilemapPhysicsPlatform,
 };
 
 pub fn spawn_colliders(
@@ -26,27 +30,45 @@ pub fn spawn_colliders(
         Entity,
         &mut PhysicsTilemap,
         &TilemapType,
+        &TilemapAxisFlip,
         &TilemapTransform,
         &TilePivot,
         &TilemapSlotSize,
+        Has<TilemapPhysicsPlatform>,
     )>,
     mut spawn_event: EventWriter<PhysicsTileSpawn>,
 ) {
-    for (tilemap_entity, mut physics_tilemap, ty, transform, tile_pivot, slot_size) in
-        &mut tilemaps_query
+    for (
+        tilemap_entity,
+        mut physics_tilemap,
+        ty,
+        axis_flip,
+        transform,
+        tile_pivot,
+        slot_size,
+        is_platform,
+    ) in &mut tilemaps_query
     {
+        // The colliders of platforms are children of the tilemap, so they are in local space.
+        let transform = if is_platform {
+            &TilemapTransform::IDENTITY
+        } else {
+            transform
+        };
+
         let physics_tiles = physics_tilemap.spawn_queue.drain(..).collect::<Vec<_>>();
         physics_tiles
             .into_iter()
             .for_each(|(aabb, physics_tile, maybe_int_repr)| {
                 commands.command_scope(|mut c| {
-                    let vertices = coordinates::get_tile_collider_world(
+                    let vertices = coordinates::get_tile_collider_world_flipped(
                         aabb.min,
                         *ty,
                         aabb.size().as_uvec2(),
                         transform,
                         tile_pivot.0,
                         slot_size.0,
+                        *axis_flip,
                     );
 
                     let packed_tile = PackedPhysicsTile {
@@ -61,8 +83,9 @@ pub fn spawn_colliders(
                         },
                         physics_tile,
                     };
-                    let tile_entity = packed_tile.spawn(&mut c);
-                    
+                    let tile_entity =
+                        spawn_physics_tile(&mut c, &packed_tile, tilemap_entity, is_platform);
+
                     spawn_event.send(PhysicsTileSpawn {
                         tilemap: tilemap_entity,
                         tile: tile_entity,
@@ -73,9 +96,114 @@ pub fn spawn_colliders(
                     physics_tilemap.data.set_elem(aabb.min, packed_tile);
                 });
             });
+
+        let shaped_tiles = physics_tilemap.shaped_queue.drain(..).collect::<Vec<_>>();
+        shaped_tiles
+            .into_iter()
+            .filter(|(_, _, shape)| shape.len() >= 2)
+            .for_each(|(index, physics_tile, shape)| {
+                commands.command_scope(|mut c| {
+                    let vertices = coordinates::get_tile_collider_world_flipped(
+                        index,
+                        *ty,
+                        UVec2::ONE,
+                        transform,
+                        tile_pivot.0,
+                        slot_size.0,
+                        *axis_flip,
+                    );
+                    // Map the shape from tile space to the bounds of the tile.
+                    let (min, max) = vertices
+                        .iter()
+                        .fold((Vec2::MAX, Vec2::MIN), |(min, max), v| {
+                            (min.min(*v), max.max(*v))
+                        });
+
+                    let packed_tile = PackedPhysicsTile {
+                        parent: index,
+                        collider: PhysicsCollider::Convex(
+                            shape.into_iter().map(|v| min + v * (max - min)).collect(),
+                        ),
+                        physics_tile,
+                    };
+                    let tile_entity =
+                        spawn_physics_tile(&mut c, &packed_tile, tilemap_entity, is_platform);
+
+                    spawn_event.send(PhysicsTileSpawn {
+                        tilemap: tilemap_entity,
+                        tile: tile_entity,
+                        int_repr: None,
+                    });
+
+                    physics_tilemap.storage.set_elem(index, tile_entity);
+                    physics_tilemap.data.set_elem(index, packed_tile);
+                });
+            });
     }
 }
 
+fn spawn_physics_tile(
+    commands: &mut Commands,
+    tile: &PackedPhysicsTile,
+    tilemap: Entity,
+    is_platform: bool,
+) -> Entity {
+    if !is_platform {
+        return tile.spawn(commands);
+    }
+
+    // The tilemap is the rigid body, so the tiles are just colliders attached to it.
+    let mut tile = tile.clone();
+    tile.physics_tile.rigid_body = false;
+    let entity = tile.spawn(commands);
+    commands
+        .entity(entity)
+        .insert(TransformBundle::default())
+        .set_parent(tilemap);
+    entity
+}
+
+pub fn platform_transform_syncer(
+    commands: ParallelCommands,
+    mut tilemaps_query: Query<
+        (
+            Entity,
+            &TilemapTransform,
+            Option<&mut Transform>,
+            Has<PlatformVelocityDriven>,
+        ),
+        (
+            With<TilemapPhysicsPlatform>,
+            Or<(Changed<TilemapTransform>, Added<TilemapPhysicsPlatform>)>,
+        ),
+    >,
+) {
+    tilemaps_query
+        .par_iter_mut()
+        .for_each(|(entity, tilemap_transform, transform, is_driven)| {
+            // The backend moves the body, writing the transform would teleport it.
+            if is_driven && transform.is_some() {
+                return;
+            }
+
+            let new = Transform {
+                translation: tilemap_transform
+                    .translation
+                    .extend(tilemap_transform.z_index),
+                rotation: tilemap_transform.get_rotation_quat(),
+                ..Default::default()
+            };
+
+            match transform {
+                Some(mut transform) => *transform = new,
+                None => commands.command_scope(|mut c| {
+                    c.entity(entity)
+                        .insert(TransformBundle::from_transform(new));
+                }),
+            }
+        });
+}
+
 pub fn data_physics_tilemap_analyzer(
     commands: ParallelCommands,
     mut tilemaps_query: Query<(Entity, &mut DataPhysicsTilemap, Option<&mut PhysicsTilemap>)>,
@@ -152,6 +280,7 @@ pub fn data_physics_tilemap_analyzer(
                     c.entity(entity).insert(PhysicsTilemap {
                         storage: Default::default(),
                         spawn_queue: aabbs,
+                        shaped_queue: Vec::new(),
                         data: ChunkedStorage::default(),
                     });
                 }
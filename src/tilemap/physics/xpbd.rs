@@ -0,0 +1,114 @@
+use std::f32::consts::{PI, TAU};
+
+use bevy::{
+    ecs::{
+        query::With,
+        system::{EntityCommands, Query, Res},
+    },
+    math::Vec2,
+    time::Time,
+};
+use bevy_xpbd_2d::{
+    components::{AngularVelocity, Friction, LinearVelocity, Position, RigidBody, Rotation},
+    plugins::collision::Collider,
+};
+
+use crate::tilemap::{
+    conveyor::{ConveyanceMode, TileConveyed},
+    map::TilemapTransform,
+};
+
+use super::{
+    backend::TilemapPhysicsBackend, PackedPhysicsTile, PhysicsCollider, PlatformVelocityDriven,
+    TilemapPhysicsPlatform,
+};
+
+/// The physics backend using `bevy_xpbd`.
+pub struct XpbdPhysicsBackend;
+
+impl TilemapPhysicsBackend for XpbdPhysicsBackend {
+    fn insert_collider(commands: &mut EntityCommands, tile: &PackedPhysicsTile) {
+        commands.insert(match tile.collider.clone() {
+            // The hull can't be built if all the points are on one line.
+            PhysicsCollider::Convex(verts) => Collider::convex_hull(verts.clone())
+                .unwrap_or_else(|| Collider::polyline(verts, None)),
+            PhysicsCollider::Polyline(verts) => Collider::polyline(verts, None),
+        });
+        if tile.physics_tile.rigid_body {
+            commands.insert(RigidBody::Static);
+        }
+        if let Some(friction) = &tile.physics_tile.friction {
+            commands.insert(Friction::new(*friction));
+        }
+    }
+
+    fn insert_platform_body(commands: &mut EntityCommands) {
+        commands.insert((RigidBody::Kinematic, PlatformVelocityDriven));
+    }
+}
+
+/// Adds the conveyance to the velocities of the bodies before the physics step.
+pub fn conveyance_applier(mut bodies_query: Query<(&mut TileConveyed, &mut LinearVelocity)>) {
+    bodies_query
+        .iter_mut()
+        .filter(|(conveyed, _)| conveyed.mode == ConveyanceMode::Velocity)
+        .for_each(|(mut conveyed, mut velocity)| {
+            let conveyance = conveyed.apply_velocity();
+            if conveyance != Vec2::ZERO {
+                velocity.0 += conveyance;
+            }
+        });
+}
+
+/// Removes the conveyance added by `conveyance_applier` after the physics step,
+/// so it's not added again on the next frame.
+pub fn conveyance_remover(mut bodies_query: Query<(&mut TileConveyed, &mut LinearVelocity)>) {
+    bodies_query
+        .iter_mut()
+        .for_each(|(mut conveyed, mut velocity)| {
+            let conveyance = conveyed.take_applied_velocity();
+            if conveyance != Vec2::ZERO {
+                velocity.0 -= conveyance;
+            }
+        });
+}
+
+/// Sets the velocities of the platforms so they reach their `TilemapTransform` in this frame.
+///
+/// Moving the kinematic body by its velocities instead of its `Transform` lets the
+/// physics engine carry the bodies standing on it.
+pub fn platform_velocity_driver(
+    mut platforms_query: Query<
+        (
+            &TilemapTransform,
+            &Position,
+            &Rotation,
+            &mut LinearVelocity,
+            &mut AngularVelocity,
+        ),
+        (With<TilemapPhysicsPlatform>, With<PlatformVelocityDriven>),
+    >,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+    if dt <= 0. {
+        return;
+    }
+
+    platforms_query.iter_mut().for_each(
+        |(tilemap_transform, position, rotation, mut linear, mut angular)| {
+            let target = tilemap_transform.get_rotation_matrix().x_axis;
+            // The shortest way to the target angle.
+            let turn = (target.y.atan2(target.x) - rotation.as_radians() + PI).rem_euclid(TAU) - PI;
+
+            let new_linear = (tilemap_transform.translation - position.0) / dt;
+            let new_angular = turn / dt;
+            if linear.0 != new_linear {
+                linear.0 = new_linear;
+            }
+            if angular.0 != new_angular {
+                angular.0 = new_angular;
+            }
+        },
+    );
+}
@@ -0,0 +1,159 @@
+use bevy::{
+    app::{App, Plugin, PostUpdate},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::{Has, With},
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query, ResMut, Resource},
+    },
+    hierarchy::{BuildChildren, Parent},
+    reflect::Reflect,
+};
+
+use super::{
+    despawn::{self, DespawnMe},
+    map::TilemapStorage,
+    tile::Tile,
+};
+
+pub struct EntiTilesTilemapPoolPlugin;
+
+impl Plugin for EntiTilesTilemapPoolPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, tilemap_pooler.after(despawn::despawn_tiles));
+
+        app.init_resource::<TilemapPool>();
+
+        app.register_type::<TilemapPool>()
+            .register_type::<PooledTilemap>();
+    }
+}
+
+/// Tiles of the tilemap with this component will be returned to `TilemapPool`
+/// instead of being despawned.
+#[derive(Component, Default, Debug, Clone, Copy, Reflect)]
+pub struct PooledTilemap;
+
+/// Keeps the entities of the despawned tiles and the render chunks of
+/// the despawned tilemaps, so new tilemaps can reuse them. This reduces hitches
+/// when switching levels frequently.
+#[derive(Resource, Debug, Clone, Reflect)]
+pub struct TilemapPool {
+    /// The maximum amount of tile entities kept in the pool.
+    /// Tiles beyond this will be despawned as usual.
+    pub max_tiles: usize,
+    /// The maximum amount of render chunks (and their GPU buffers)
+    /// kept in the pool for each material.
+    pub max_chunks: usize,
+    #[reflect(ignore)]
+    pub(crate) tiles: Vec<Entity>,
+}
+
+impl Default for TilemapPool {
+    fn default() -> Self {
+        Self {
+            max_tiles: 1 << 16,
+            max_chunks: 64,
+            tiles: Vec::new(),
+        }
+    }
+}
+
+impl TilemapPool {
+    /// The amount of tile entities in the pool.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// Move at most `count` entities from the pool to the storage.
+    /// They will be used when spawning tiles on this tilemap.
+    ///
+    /// Usually `count` is the amount of tiles you are going to spawn.
+    pub fn acquire(&mut self, storage: &mut TilemapStorage, count: usize) {
+        let count = count.min(self.tiles.len());
+        storage
+            .pool
+            .extend(self.tiles.drain(self.tiles.len() - count..));
+    }
+
+    /// Despawn all the pooled entities.
+    pub fn clear(&mut self, commands: &mut Commands) {
+        self.tiles.drain(..).for_each(|entity| {
+            commands.entity(entity).despawn();
+        });
+    }
+}
+
+pub fn tilemap_pooler(
+    mut commands: Commands,
+    mut pool: ResMut<TilemapPool>,
+    tiles_query: Query<(Entity, &Tile, Has<Parent>), With<DespawnMe>>,
+    pooled_query: Query<(), With<PooledTilemap>>,
+    mut despawned_tilemaps_query: Query<(&mut TilemapStorage, Has<PooledTilemap>), With<DespawnMe>>,
+) {
+    // The entities acquired but not used yet.
+    despawned_tilemaps_query
+        .iter_mut()
+        .for_each(|(mut storage, is_pooled)| {
+            std::mem::take(&mut storage.pool)
+                .into_iter()
+                .for_each(|entity| {
+                    if is_pooled && pool.tiles.len() < pool.max_tiles {
+                        pool.tiles.push(entity);
+                    } else {
+                        commands.entity(entity).despawn();
+                    }
+                });
+        });
+
+    tiles_query.iter().for_each(|(entity, tile, has_parent)| {
+        if pool.tiles.len() >= pool.max_tiles || !pooled_query.contains(tile.tilemap_id) {
+            return;
+        }
+
+        if has_parent {
+            commands.entity(entity).remove_parent();
+        }
+        // Remove everything including `DespawnMe` so it won't be despawned.
+        commands.entity(entity).retain::<()>();
+        pool.tiles.push(entity);
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::app::{App, PostUpdate};
+
+    use super::*;
+
+    #[test]
+    fn test_spare_entities() {
+        let mut app = App::new();
+        app.init_resource::<TilemapPool>()
+            .add_systems(PostUpdate, tilemap_pooler);
+
+        let spare = [app.world.spawn_empty().id(), app.world.spawn_empty().id()];
+        let pooled = app.world.spawn((PooledTilemap, DespawnMe)).id();
+        let mut storage = TilemapStorage::new(4, pooled);
+        storage.pool.push(spare[0]);
+        app.world.entity_mut(pooled).insert(storage);
+
+        // The spare entities of tilemaps without `PooledTilemap` are despawned.
+        let other = app.world.spawn(DespawnMe).id();
+        let mut storage = TilemapStorage::new(4, other);
+        storage.pool.push(spare[1]);
+        app.world.entity_mut(other).insert(storage);
+
+        app.update();
+        assert_eq!(app.world.resource::<TilemapPool>().tiles, vec![spare[0]]);
+        assert!(app.world.get_entity(spare[0]).is_some());
+        assert!(app.world.get_entity(spare[1]).is_none());
+    }
+}
@@ -0,0 +1,456 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::{Event, EventWriter},
+        system::{Query, Res},
+    },
+    hierarchy::Parent,
+    math::IVec2,
+    reflect::Reflect,
+    transform::components::{GlobalTransform, Transform},
+};
+
+use crate::math::aabb::IAabb2d;
+
+use super::{
+    coordinates,
+    id::{TilemapId, TilemapIds},
+    map::{TilePivot, TilemapAxisFlip, TilemapSlotSize, TilemapTransform, TilemapType},
+    sensor::TilePosition,
+};
+
+pub type PortalId = usize;
+
+pub struct EntiTilesTilePortalPlugin;
+
+impl Plugin for EntiTilesTilePortalPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, portal_teleporter);
+
+        app.register_type::<TilemapPortals>()
+            .register_type::<TilePortal>()
+            .register_type::<PortalTraveler>()
+            .register_type::<PortalTraveled>()
+            .register_type::<PortalTargetMissing>();
+
+        app.add_event::<PortalTraveled>()
+            .add_event::<PortalTargetMissing>();
+    }
+}
+
+/// A region of tiles that leads to a location in another tilemap.
+#[derive(Debug, Clone, Reflect)]
+#[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
+pub struct TilePortal {
+    /// The tiles of the portal, inclusive.
+    pub region: IAabb2d,
+    pub target: TilemapId,
+    /// Where the `min` of the region lands in the target tilemap.
+    pub target_index: IVec2,
+}
+
+impl TilePortal {
+    pub fn new(region: IAabb2d, target: TilemapId, target_index: IVec2) -> Self {
+        Self {
+            region,
+            target,
+            target_index,
+        }
+    }
+
+    /// Where the tile lands in the target tilemap, keeping its offset in the region.
+    #[inline]
+    pub fn destination(&self, index: IVec2) -> Option<IVec2> {
+        self.region
+            .contains(index)
+            .then(|| self.target_index + index - self.region.min)
+    }
+}
+
+/// The portals of a tilemap, for worlds made of several maps like houses, dungeons and
+/// the overworld.
+///
+/// Insert this component on the tilemap entity. Entities with `PortalTraveler` that
+/// enter a portal are teleported to the tilemap with the target `TilemapId`.
+#[derive(Component, Debug, Default, Clone, Reflect)]
+#[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
+pub struct TilemapPortals {
+    pub(crate) portals: Vec<TilePortal>,
+}
+
+impl TilemapPortals {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_portal(mut self, portal: TilePortal) -> Self {
+        self.add(portal);
+        self
+    }
+
+    pub fn add(&mut self, portal: TilePortal) -> PortalId {
+        self.portals.push(portal);
+        self.portals.len() - 1
+    }
+
+    #[inline]
+    pub fn get(&self, portal: PortalId) -> Option<&TilePortal> {
+        self.portals.get(portal)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, portal: PortalId) -> Option<&mut TilePortal> {
+        self.portals.get_mut(portal)
+    }
+
+    /// Find the portal containing the tile. The first one wins if they overlap.
+    pub fn find(&self, index: IVec2) -> Option<(PortalId, &TilePortal)> {
+        self.portals
+            .iter()
+            .enumerate()
+            .find(|(_, portal)| portal.region.contains(index))
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &TilePortal> {
+        self.portals.iter()
+    }
+}
+
+/// Add this to entities that can use portals.
+///
+/// The position is read from `TilePosition`, or `GlobalTransform` if it doesn't have one,
+/// like `TileTriggerActivator`. When teleported, the offset to the tile is preserved, and
+/// the `Transform` is moved by the same amount as the `GlobalTransform`.
+///
+/// The entity must leave the portal before using it again, so arriving on
+/// the portal back won't teleport it immediately.
+#[derive(Component, Debug, Default, Clone, Reflect)]
+pub struct PortalTraveler {
+    pub(crate) inside: Option<(Entity, PortalId)>,
+}
+
+impl PortalTraveler {
+    /// The tilemap and the portal this entity is standing in.
+    #[inline]
+    pub fn inside(&self) -> Option<(Entity, PortalId)> {
+        self.inside
+    }
+}
+
+/// Fired after an entity is teleported through a portal.
+#[derive(Event, Debug, Clone, Copy, Reflect)]
+pub struct PortalTraveled {
+    pub entity: Entity,
+    pub portal: PortalId,
+    pub from: Entity,
+    pub from_index: IVec2,
+    pub to: Entity,
+    pub to_index: IVec2,
+}
+
+/// Fired when an entity enters a portal but there's no tilemap with the target id,
+/// like when the map is not loaded yet. The entity has to leave and enter the portal again.
+#[derive(Event, Debug, Clone, Reflect)]
+pub struct PortalTargetMissing {
+    pub entity: Entity,
+    pub tilemap: Entity,
+    pub portal: PortalId,
+    pub target: TilemapId,
+}
+
+pub fn portal_teleporter(
+    mut travelers_query: Query<(
+        Entity,
+        &mut PortalTraveler,
+        Option<&mut TilePosition>,
+        Option<&mut Transform>,
+        Option<&GlobalTransform>,
+        Option<&Parent>,
+    )>,
+    tilemaps_query: Query<(
+        Entity,
+        Option<&TilemapPortals>,
+        &TilemapType,
+        &TilemapTransform,
+        &TilePivot,
+        &TilemapSlotSize,
+        &TilemapAxisFlip,
+    )>,
+    parents_query: Query<&GlobalTransform>,
+    ids: Res<TilemapIds>,
+    mut traveled: EventWriter<PortalTraveled>,
+    mut missing: EventWriter<PortalTargetMissing>,
+) {
+    let index_to_world = |tilemap: Entity, index: IVec2| {
+        tilemaps_query.get(tilemap).ok().map(
+            |(_, _, ty, transform, pivot, slot_size, axis_flip)| {
+                coordinates::index_to_world_flipped(
+                    index,
+                    *ty,
+                    transform,
+                    pivot.0,
+                    slot_size.0,
+                    *axis_flip,
+                )
+            },
+        )
+    };
+    let find_portal = |tilemap: Entity, index: IVec2| {
+        tilemaps_query
+            .get(tilemap)
+            .ok()
+            .and_then(|(_, portals, ..)| portals?.find(index))
+    };
+
+    travelers_query.iter_mut().for_each(
+        |(entity, mut traveler, mut position, transform, global, parent)| {
+            let current = match (position.as_deref(), global) {
+                (Some(position), _) => Some((position.tilemap, position.index)),
+                (None, Some(global)) => {
+                    let world = global.translation().truncate();
+                    tilemaps_query.iter().find_map(
+                        |(tilemap, portals, ty, tilemap_transform, pivot, slot_size, axis_flip)| {
+                            let index = coordinates::world_to_index_flipped(
+                                world,
+                                *ty,
+                                tilemap_transform,
+                                pivot.0,
+                                slot_size.0,
+                                *axis_flip,
+                            );
+                            portals?.find(index).map(|_| (tilemap, index))
+                        },
+                    )
+                }
+                (None, None) => None,
+            };
+
+            let Some((tilemap, index)) = current else {
+                traveler.inside = None;
+                return;
+            };
+            let Some((portal_id, portal)) = find_portal(tilemap, index) else {
+                traveler.inside = None;
+                return;
+            };
+            if traveler.inside == Some((tilemap, portal_id)) {
+                return;
+            }
+            traveler.inside = Some((tilemap, portal_id));
+
+            let Some(target) = ids.get(&portal.target) else {
+                missing.send(PortalTargetMissing {
+                    entity,
+                    tilemap,
+                    portal: portal_id,
+                    target: portal.target.clone(),
+                });
+                return;
+            };
+            let to_index = portal.destination(index).unwrap();
+
+            match (position.as_deref_mut(), transform) {
+                (Some(position), _) => {
+                    position.tilemap = target;
+                    position.index = to_index;
+                }
+                (None, Some(mut transform)) => {
+                    // The offset to the tile is preserved.
+                    let (Some(from), Some(to)) = (
+                        index_to_world(tilemap, index),
+                        index_to_world(target, to_index),
+                    ) else {
+                        return;
+                    };
+                    let delta = (to - from).extend(0.);
+                    // The tilemaps are in world space, but the traveler may be a child.
+                    match (global, parent.and_then(|p| parents_query.get(p.get()).ok())) {
+                        (Some(global), Some(parent)) => {
+                            transform.translation = parent
+                                .affine()
+                                .inverse()
+                                .transform_point3(global.translation() + delta);
+                        }
+                        (None, Some(parent)) => {
+                            transform.translation +=
+                                parent.affine().inverse().transform_vector3(delta);
+                        }
+                        _ => transform.translation += delta,
+                    }
+                }
+                (None, None) => return,
+            }
+
+            traveler.inside = find_portal(target, to_index).map(|(portal, _)| (target, portal));
+            traveled.send(PortalTraveled {
+                entity,
+                portal: portal_id,
+                from: tilemap,
+                from_index: index,
+                to: target,
+                to_index,
+            });
+        },
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::{
+        ecs::event::Events,
+        hierarchy::BuildWorldChildren,
+        math::{Vec2, Vec3},
+    };
+
+    use crate::tilemap::id::EntiTilesTilemapIdPlugin;
+
+    use super::*;
+
+    #[test]
+    fn test_portals() {
+        let portals = TilemapPortals::new()
+            .with_portal(TilePortal::new(
+                IAabb2d::new(0, 0, 1, 2),
+                "cave".into(),
+                IVec2::new(10, 20),
+            ))
+            .with_portal(TilePortal::new(
+                IAabb2d::new(1, 0, 3, 0),
+                "forest".into(),
+                IVec2::ZERO,
+            ));
+
+        let (id, portal) = portals.find(IVec2::new(1, 2)).unwrap();
+        assert_eq!(id, 0);
+        assert_eq!(
+            portal.destination(IVec2::new(1, 2)),
+            Some(IVec2::new(11, 22))
+        );
+        assert_eq!(portal.destination(IVec2::new(2, 2)), None);
+
+        assert_eq!(portals.find(IVec2::new(1, 0)).unwrap().0, 0);
+        assert_eq!(portals.find(IVec2::new(3, 0)).unwrap().0, 1);
+        assert!(portals.find(IVec2::new(3, 1)).is_none());
+    }
+
+    #[test]
+    fn test_portal_teleporter() {
+        let mut app = App::new();
+        app.add_plugins((EntiTilesTilemapIdPlugin, EntiTilesTilePortalPlugin));
+
+        let tilemap = |id: &str, portals: TilemapPortals| {
+            (
+                TilemapId::from(id),
+                portals,
+                TilemapType::Square,
+                TilemapTransform::default(),
+                TilePivot::default(),
+                TilemapSlotSize::default(),
+                TilemapAxisFlip::NONE,
+            )
+        };
+        let town = app
+            .world
+            .spawn(tilemap(
+                "town",
+                TilemapPortals::new().with_portal(TilePortal::new(
+                    IAabb2d::new(0, 0, 1, 0),
+                    "house".into(),
+                    IVec2::new(5, 5),
+                )),
+            ))
+            .id();
+        let house = app
+            .world
+            .spawn(tilemap(
+                "house",
+                TilemapPortals::new().with_portal(TilePortal::new(
+                    IAabb2d::new(5, 5, 6, 5),
+                    "town".into(),
+                    IVec2::ZERO,
+                )),
+            ))
+            .id();
+        let traveler = app
+            .world
+            .spawn((
+                PortalTraveler::default(),
+                TilePosition {
+                    tilemap: town,
+                    index: IVec2::new(0, 1),
+                },
+            ))
+            .id();
+        app.update();
+
+        app.world.get_mut::<TilePosition>(traveler).unwrap().index = IVec2::new(1, 0);
+        app.update();
+        let position = *app.world.get::<TilePosition>(traveler).unwrap();
+        assert_eq!(position.tilemap, house);
+        assert_eq!(position.index, IVec2::new(6, 5));
+
+        // Arriving on the portal back doesn't teleport.
+        app.update();
+        assert_eq!(
+            app.world.get::<TilePosition>(traveler).unwrap().tilemap,
+            house
+        );
+        assert_eq!(app.world.resource::<Events<PortalTraveled>>().len(), 1);
+    }
+
+    #[test]
+    fn test_portal_teleporter_world() {
+        let mut app = App::new();
+        app.add_plugins((EntiTilesTilemapIdPlugin, EntiTilesTilePortalPlugin));
+
+        app.world.spawn((
+            TilemapId::from("town"),
+            TilemapPortals::new().with_portal(TilePortal::new(
+                IAabb2d::new(0, 0, 1, 0),
+                "house".into(),
+                IVec2::new(5, 5),
+            )),
+            TilemapType::Square,
+            TilemapTransform::default(),
+            TilePivot::default(),
+            TilemapSlotSize(Vec2::splat(16.)),
+            TilemapAxisFlip::NONE,
+        ));
+        app.world.spawn((
+            TilemapId::from("house"),
+            TilemapType::Square,
+            TilemapTransform::from_translation(Vec2::new(1000., 0.)),
+            TilePivot::default(),
+            TilemapSlotSize(Vec2::splat(16.)),
+            TilemapAxisFlip::Y,
+        ));
+        app.update();
+
+        let parent = app
+            .world
+            .spawn(GlobalTransform::from(
+                Transform::from_xyz(100., 0., 0.).with_scale(Vec3::splat(2.)),
+            ))
+            .id();
+        let traveler = app
+            .world
+            .spawn((
+                PortalTraveler::default(),
+                Transform::from_xyz(-38., 4., 0.),
+                GlobalTransform::from_xyz(24., 8., 0.),
+            ))
+            .set_parent(parent)
+            .id();
+        app.update();
+
+        // From (16, 0) on tile (1, 0) of the town to tile (6, 5) of the flipped house,
+        // which starts at (1096, -96), and back to the space of the parent.
+        assert_eq!(
+            app.world.get::<Transform>(traveler).unwrap().translation,
+            Vec3::new(502., -44., 0.)
+        );
+    }
+}
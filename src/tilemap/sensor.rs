@@ -0,0 +1,238 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::{Event, EventWriter},
+        system::Query,
+    },
+    math::{IVec2, Vec2},
+    reflect::Reflect,
+    transform::components::GlobalTransform,
+};
+
+use crate::math::TileArea;
+
+use super::{
+    chunking::storage::ChunkedStorage,
+    coordinates,
+    map::{TilePivot, TilemapAxisFlip, TilemapSlotSize, TilemapTransform, TilemapType},
+};
+
+pub type SensorId = u32;
+
+pub struct EntiTilesTileSensorPlugin;
+
+impl Plugin for EntiTilesTileSensorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, trigger_detector);
+
+        app.register_type::<TileSensors>()
+            .register_type::<TileTriggerActivator>()
+            .register_type::<TilePosition>()
+            .register_type::<TileTriggerEntered>()
+            .register_type::<TileTriggerExited>();
+
+        app.add_event::<TileTriggerEntered>()
+            .add_event::<TileTriggerExited>();
+    }
+}
+
+/// A layer that marks tiles as sensors, like damage floors, pressure plates or level exits.
+///
+/// Insert this component on the tilemap entity. All the tiles with the same id
+/// are treated as a single sensor, even if they are not adjacent, so moving between
+/// them won't fire the events again. Use different ids for separate areas.
+#[derive(Component, Debug, Clone, Reflect)]
+#[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileSensors {
+    pub(crate) storage: ChunkedStorage<SensorId>,
+}
+
+impl Default for TileSensors {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TileSensors {
+    /// Create a new sensor layer with default chunk size.
+    ///
+    /// Use `new_with_chunk_size` to specify a custom chunk size.
+    pub fn new() -> Self {
+        Self {
+            storage: ChunkedStorage::default(),
+        }
+    }
+
+    /// Create a new sensor layer with custom chunk size.
+    pub fn new_with_chunk_size(chunk_size: u32) -> Self {
+        Self {
+            storage: ChunkedStorage::new(chunk_size),
+        }
+    }
+
+    /// Get the sensor of a tile.
+    #[inline]
+    pub fn get(&self, index: IVec2) -> Option<SensorId> {
+        self.storage.get_elem(index).cloned()
+    }
+
+    /// Get the sensor at a world position.
+    #[inline]
+    pub fn get_world(
+        &self,
+        world: Vec2,
+        ty: TilemapType,
+        transform: &TilemapTransform,
+        pivot: Vec2,
+        slot_size: Vec2,
+        axis_flip: TilemapAxisFlip,
+    ) -> Option<SensorId> {
+        self.get(coordinates::world_to_index_flipped(
+            world, ty, transform, pivot, slot_size, axis_flip,
+        ))
+    }
+
+    #[inline]
+    pub fn set(&mut self, index: IVec2, sensor: SensorId) {
+        self.storage.set_elem(index, sensor);
+    }
+
+    #[inline]
+    pub fn remove(&mut self, index: IVec2) -> Option<SensorId> {
+        self.storage.remove_elem(index)
+    }
+
+    /// Fill a rectangle area with the same sensor.
+    pub fn fill_rect(&mut self, area: TileArea, sensor: SensorId) {
+        area.aabb()
+            .into_iter()
+            .for_each(|index| self.set(index, sensor));
+    }
+}
+
+/// The position of an entity on a tilemap, for entities that don't have transforms,
+/// like the ones in a headless simulation.
+#[derive(Component, Debug, Clone, Copy, Reflect)]
+pub struct TilePosition {
+    pub tilemap: Entity,
+    pub index: IVec2,
+}
+
+/// Add this to entities that can activate sensors, like physics bodies.
+///
+/// The entity is considered inside a sensor when its `TilePosition` is, or when
+/// its `GlobalTransform` is if it doesn't have one. The size of the collider
+/// is not taken into account.
+#[derive(Component, Debug, Default, Clone, Reflect)]
+pub struct TileTriggerActivator {
+    pub(crate) current: Vec<(Entity, SensorId)>,
+}
+
+impl TileTriggerActivator {
+    /// The tilemaps and the sensors this entity is currently in.
+    #[inline]
+    pub fn current(&self) -> &[(Entity, SensorId)] {
+        &self.current
+    }
+}
+
+/// Fired when an entity with `TileTriggerActivator` enters a sensor.
+#[derive(Event, Debug, Clone, Copy, Reflect)]
+pub struct TileTriggerEntered {
+    pub entity: Entity,
+    pub tilemap: Entity,
+    pub sensor: SensorId,
+}
+
+/// Fired when an entity with `TileTriggerActivator` leaves a sensor.
+#[derive(Event, Debug, Clone, Copy, Reflect)]
+pub struct TileTriggerExited {
+    pub entity: Entity,
+    pub tilemap: Entity,
+    pub sensor: SensorId,
+}
+
+pub fn trigger_detector(
+    mut activators_query: Query<(
+        Entity,
+        Option<&TilePosition>,
+        Option<&GlobalTransform>,
+        &mut TileTriggerActivator,
+    )>,
+    tilemaps_query: Query<(
+        Entity,
+        &TileSensors,
+        &TilemapType,
+        &TilemapTransform,
+        &TilePivot,
+        &TilemapSlotSize,
+        &TilemapAxisFlip,
+    )>,
+    mut entered: EventWriter<TileTriggerEntered>,
+    mut exited: EventWriter<TileTriggerExited>,
+) {
+    activators_query
+        .iter_mut()
+        .for_each(|(entity, position, transform, mut activator)| {
+            let sensors = match (position, transform) {
+                (Some(position), _) => tilemaps_query
+                    .get(position.tilemap)
+                    .ok()
+                    .and_then(|(tilemap, sensors, ..)| {
+                        sensors.get(position.index).map(|s| (tilemap, s))
+                    })
+                    .into_iter()
+                    .collect(),
+                (None, Some(transform)) => {
+                    let world = transform.translation().truncate();
+                    tilemaps_query
+                        .iter()
+                        .filter_map(
+                            |(tilemap, sensors, ty, tilemap_transform, pivot, slot_size, flip)| {
+                                sensors
+                                    .get_world(
+                                        world,
+                                        *ty,
+                                        tilemap_transform,
+                                        pivot.0,
+                                        slot_size.0,
+                                        *flip,
+                                    )
+                                    .map(|s| (tilemap, s))
+                            },
+                        )
+                        .collect()
+                }
+                (None, None) => Vec::new(),
+            };
+
+            if sensors == activator.current {
+                return;
+            }
+
+            activator
+                .current
+                .iter()
+                .filter(|s| !sensors.contains(s))
+                .for_each(|&(tilemap, sensor)| {
+                    exited.send(TileTriggerExited {
+                        entity,
+                        tilemap,
+                        sensor,
+                    });
+                });
+            sensors
+                .iter()
+                .filter(|s| !activator.current.contains(s))
+                .for_each(|&(tilemap, sensor)| {
+                    entered.send(TileTriggerEntered {
+                        entity,
+                        tilemap,
+                        sensor,
+                    });
+                });
+            activator.current = sensors;
+        });
+}
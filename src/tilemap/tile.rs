@@ -1,17 +1,23 @@
 use bevy::{
-    ecs::system::{ParallelCommands, Query},
-    math::IVec2,
+    ecs::{
+        change_detection::DetectChangesMut,
+        system::{ParallelCommands, Query},
+    },
+    math::{IVec2, Vec2},
     prelude::{Component, Entity},
     reflect::Reflect,
     render::{color::Color, render_resource::ShaderType},
+    utils::HashMap,
 };
 
+use crate::MAX_ANIMATION_GROUPS;
+
 use super::{buffers::Tiles, map::TilemapStorage};
 
 /// A tile layer. This is the logical representation of a tile layer.
 /// Not all the layers you added to a tile will be taken into consideration
 /// when rendering. Only the top 4 layers will be rendered.
-#[derive(Debug, Clone, Copy, Reflect)]
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
 #[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
 pub struct TileLayer {
     pub texture_index: i32,
@@ -83,11 +89,81 @@ pub struct LayerUpdater {
 pub struct TileUpdater {
     pub layer: Option<LayerUpdater>,
     pub tint: Option<Color>,
+    pub offset: Option<TileOffset>,
+}
+
+/// Moves and rotates a tile inside its slot, so scatter layers like stones
+/// and grass tufts don't look grid-locked. This only affects rendering.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Reflect)]
+#[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileOffset {
+    /// The offset in pixels.
+    pub translation: Vec2,
+    /// The rotation around the center of the tile in radians, counter-clockwise.
+    pub rotation: f32,
+}
+
+impl TileOffset {
+    pub const ZERO: Self = Self {
+        translation: Vec2::ZERO,
+        rotation: 0.,
+    };
+
+    #[inline]
+    pub fn new(translation: Vec2, rotation: f32) -> Self {
+        Self {
+            translation,
+            rotation,
+        }
+    }
+}
+
+/// The range of random `TileOffset`s, for painting organic layouts.
+///
+/// The offset is picked from the hash of the tile index, so the same tile always
+/// gets the same offset, no matter how many times it's painted.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Reflect)]
+#[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileJitter {
+    /// The max offset in pixels on each axis.
+    pub translation: Vec2,
+    /// The max rotation in radians on both directions.
+    pub rotation: f32,
+}
+
+impl TileJitter {
+    #[inline]
+    pub fn new(translation: Vec2, rotation: f32) -> Self {
+        Self {
+            translation,
+            rotation,
+        }
+    }
+
+    /// Get the offset of the tile at `index`.
+    pub fn sample(&self, index: IVec2) -> TileOffset {
+        let mut seed = (index.x as u32 as u64) << 32 | index.y as u32 as u64;
+        let mut next = || {
+            // splitmix64
+            seed = seed.wrapping_add(0x9E3779B97F4A7C15);
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+            z ^= z >> 31;
+            // Map to [-1, 1].
+            (z >> 40) as f32 / (1u64 << 23) as f32 - 1.
+        };
+
+        TileOffset {
+            translation: Vec2::new(next(), next()) * self.translation,
+            rotation: next() * self.rotation,
+        }
+    }
 }
 
 bitflags::bitflags! {
     /// The flip of a tile.
-    #[derive(Debug, Clone, Copy)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     #[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
     pub struct TileFlip: u32 {
         const NONE = 0b00;
@@ -104,11 +180,13 @@ impl Default for TileFlip {
 }
 
 /// A tile builder. This is used to create a tile.
-#[derive(Debug, Clone, Reflect)]
+#[derive(Debug, Clone, PartialEq, Reflect)]
 #[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
 pub struct TileBuilder {
     pub(crate) texture: TileTexture,
     pub(crate) tint: Color,
+    #[cfg_attr(feature = "serializing", serde(default))]
+    pub(crate) offset: TileOffset,
 }
 
 impl Tiles for TileBuilder {}
@@ -119,6 +197,7 @@ impl TileBuilder {
         Self {
             texture: TileTexture::Static(Vec::new()),
             tint: Color::WHITE,
+            offset: TileOffset::ZERO,
         }
     }
 
@@ -128,6 +207,12 @@ impl TileBuilder {
         self
     }
 
+    /// Move and rotate the tile inside its slot. Default is no offset.
+    pub fn with_offset(mut self, offset: TileOffset) -> Self {
+        self.offset = offset;
+        self
+    }
+
     /// Set the specific layer of the tile.
     ///
     /// You don't need to worry about the index of the layer. If the index is greater than the current
@@ -166,18 +251,57 @@ impl TileBuilder {
             index,
             texture: self.texture.clone(),
             tint: self.tint,
+            offset: self.offset,
         }
     }
 }
 
 /// A tile animation. This is actually information about the position of the animation
 /// in the tilemap animation buffer. So it's cheap to clone.
-#[derive(ShaderType, Debug, Clone, Copy, Reflect)]
+#[derive(ShaderType, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
 #[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
 pub struct TileAnimation {
     pub(crate) start: u32,
     pub(crate) length: u32,
     pub(crate) fps: u32,
+    /// The animation group that drives this animation. See `TilemapAnimationGroups`.
+    #[cfg_attr(feature = "serializing", serde(default))]
+    pub(crate) group: u32,
+}
+
+impl TileAnimation {
+    /// The position of the first frame in the tilemap animation buffer.
+    #[inline]
+    pub fn start(&self) -> u32 {
+        self.start
+    }
+
+    /// The amount of frames.
+    #[inline]
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+
+    #[inline]
+    pub fn fps(&self) -> u32 {
+        self.fps
+    }
+
+    #[inline]
+    pub fn group(&self) -> u32 {
+        self.group
+    }
+
+    /// Play this animation using the clock of another group. See `TilemapAnimationGroups`.
+    pub fn with_group(mut self, group: u32) -> Self {
+        assert!(
+            (group as usize) < MAX_ANIMATION_GROUPS,
+            "The animation group {} is out of range!",
+            group
+        );
+        self.group = group;
+        self
+    }
 }
 
 /// A raw tile animation. This is contains the full information of a tile animation.
@@ -188,13 +312,36 @@ pub struct RawTileAnimation {
 }
 
 /// A tile texture. This is either a static texture or an animation.
-#[derive(Debug, Clone, Reflect)]
+#[derive(Debug, Clone, PartialEq, Reflect)]
 #[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
 pub enum TileTexture {
     Static(Vec<TileLayer>),
     Animated(TileAnimation),
 }
 
+impl TileTexture {
+    /// Change the texture indices of the layers. Animations are not affected,
+    /// as the frames are stored in `TilemapAnimations`.
+    pub fn remap_texture_indices(&mut self, mapping: &HashMap<u32, u32>) {
+        if let TileTexture::Static(layers) = self {
+            layers.iter_mut().for_each(|layer| {
+                layer.texture_index = remap_texture_index(layer.texture_index, mapping)
+            });
+        }
+    }
+}
+
+/// Empty layers and indices that are not in `mapping` are kept.
+#[inline]
+pub(crate) fn remap_texture_index(index: i32, mapping: &HashMap<u32, u32>) -> i32 {
+    if index < 0 {
+        return index;
+    }
+    mapping
+        .get(&(index as u32))
+        .map_or(index, |mapped| *mapped as i32)
+}
+
 /// The component of a tile.
 #[derive(Component, Clone, Debug, Reflect)]
 pub struct Tile {
@@ -204,6 +351,7 @@ pub struct Tile {
     pub index: IVec2,
     pub texture: TileTexture,
     pub tint: Color,
+    pub offset: TileOffset,
 }
 
 impl Tiles for Tile {}
@@ -213,6 +361,7 @@ impl Into<TileBuilder> for Tile {
         TileBuilder {
             texture: self.texture,
             tint: self.tint,
+            offset: self.offset,
         }
     }
 }
@@ -245,8 +394,35 @@ pub fn tile_updater(
             if let Some(color) = updater.tint {
                 tile.tint = color;
             }
+            if let Some(offset) = updater.offset {
+                tile.offset = offset;
+            }
+            // Empty updaters are used to mark the tile dirty.
+            tile.set_changed();
             commands.command_scope(|mut c| {
                 c.entity(entity).remove::<TileUpdater>();
             });
         });
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_jitter() {
+        let jitter = TileJitter::new(Vec2::new(4., 2.), 0.1);
+        let offsets = (0..100)
+            .map(|i| jitter.sample(IVec2::new(i % 10 - 5, i / 10)))
+            .collect::<Vec<_>>();
+        assert!(offsets
+            .iter()
+            .all(|o| o.translation.abs().cmple(jitter.translation).all()
+                && o.rotation.abs() <= jitter.rotation));
+        assert_eq!(offsets[42], jitter.sample(IVec2::new(-3, 4)));
+        assert_ne!(offsets[0], offsets[1]);
+        assert!(offsets.iter().any(|o| o.translation.x < -2.));
+        assert!(offsets.iter().any(|o| o.translation.x > 2.));
+        assert_eq!(TileJitter::default().sample(IVec2::ONE), TileOffset::ZERO);
+    }
+}
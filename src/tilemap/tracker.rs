@@ -0,0 +1,48 @@
+use bevy::ecs::{
+    entity::Entity,
+    query::{Changed, With, Without},
+    system::{Query, SystemParam},
+};
+
+use super::{despawn::DespawnMe, tile::Tile};
+
+/// The existing, changed and despawning tiles, for systems that keep some data
+/// in sync with the tiles, like `TilemapTextureLookup`.
+///
+/// The despawning tiles are only visible before `despawn::despawn_tiles`,
+/// so run these systems before it.
+#[derive(SystemParam)]
+pub struct TileChanges<'w, 's> {
+    all: Query<'w, 's, &'static Tile, Without<DespawnMe>>,
+    changed: Query<'w, 's, &'static Tile, (Changed<Tile>, Without<DespawnMe>)>,
+    removed: Query<'w, 's, &'static Tile, With<DespawnMe>>,
+}
+
+impl<'w, 's> TileChanges<'w, 's> {
+    /// Get a tile that is not being despawned.
+    #[inline]
+    pub fn get(&self, entity: Entity) -> Option<&Tile> {
+        self.all.get(entity).ok()
+    }
+
+    /// Iterate over all the tiles that are not being despawned.
+    #[inline]
+    pub fn iter_all(&self) -> impl Iterator<Item = &Tile> + '_ {
+        self.all.iter()
+    }
+
+    /// Iterate over the tiles added or changed since the last run.
+    #[inline]
+    pub fn iter_changed(&self) -> impl Iterator<Item = &Tile> + '_ {
+        self.changed.iter()
+    }
+
+    /// Iterate over the tiles being despawned.
+    ///
+    /// Handle them before the changed ones, as a new tile may be set
+    /// at the same index in the same frame.
+    #[inline]
+    pub fn iter_removed(&self) -> impl Iterator<Item = &Tile> + '_ {
+        self.removed.iter()
+    }
+}
@@ -0,0 +1,526 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::{Event, EventWriter},
+        system::{Commands, Query, ResMut, Resource},
+    },
+    math::IVec2,
+    reflect::Reflect,
+    utils::{HashMap, HashSet},
+};
+
+use crate::math::TileArea;
+
+use super::{
+    map::{TilemapAnimations, TilemapStorage, TilemapTexture},
+    tile::{Tile, TileBuilder, TileTexture},
+};
+
+pub struct EntiTilesTransactionPlugin;
+
+impl Plugin for EntiTilesTransactionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, transaction_applier);
+
+        app.init_resource::<TilemapTransactions>();
+
+        app.register_type::<TilemapEditJournal>()
+            .register_type::<TilemapTransactionApplied>()
+            .register_type::<TilemapTransactionFailed>();
+
+        app.add_event::<TilemapTransactionApplied>()
+            .add_event::<TilemapTransactionFailed>();
+    }
+}
+
+/// A single edit in a `TilemapTransaction`.
+#[derive(Debug, Clone, Reflect)]
+pub enum TileEdit {
+    Set(IVec2, TileBuilder),
+    Remove(IVec2),
+}
+
+impl TileEdit {
+    #[inline]
+    pub fn index(&self) -> IVec2 {
+        match self {
+            TileEdit::Set(index, _) | TileEdit::Remove(index) => *index,
+        }
+    }
+}
+
+/// A batch of edits that will be applied to a tilemap all at once.
+///
+/// The edits are validated before being applied. If any of them is invalid,
+/// none of them will be applied.
+#[derive(Debug, Default, Clone, Reflect)]
+pub struct TilemapTransaction {
+    pub(crate) edits: Vec<TileEdit>,
+}
+
+impl TilemapTransaction {
+    #[inline]
+    pub fn set(&mut self, index: IVec2, tile_builder: TileBuilder) -> &mut Self {
+        self.edits.push(TileEdit::Set(index, tile_builder));
+        self
+    }
+
+    #[inline]
+    pub fn remove(&mut self, index: IVec2) -> &mut Self {
+        self.edits.push(TileEdit::Remove(index));
+        self
+    }
+
+    pub fn fill_rect(&mut self, area: TileArea, tile_builder: TileBuilder) -> &mut Self {
+        self.edits.extend(
+            area.aabb()
+                .into_iter()
+                .map(|index| TileEdit::Set(index, tile_builder.clone())),
+        );
+        self
+    }
+
+    pub fn remove_rect(&mut self, area: TileArea) -> &mut Self {
+        self.edits
+            .extend(area.aabb().into_iter().map(TileEdit::Remove));
+        self
+    }
+
+    #[inline]
+    pub fn edits(&self) -> &[TileEdit] {
+        &self.edits
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.edits.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    /// Check if all the edits can be applied to a tilemap with the given texture and animations.
+    pub fn validate(
+        &self,
+        texture: Option<&TilemapTexture>,
+        animations: Option<&TilemapAnimations>,
+    ) -> Result<(), TransactionError> {
+        let texture_count = texture.map(|tex| {
+            let tiles = tex.desc.tile_count();
+            (tiles.x * tiles.y) as i32
+        });
+        let animation_len = animations.map(|anim| anim.0.len()).unwrap_or_default();
+
+        self.edits.iter().try_for_each(|edit| {
+            let TileEdit::Set(index, builder) = edit else {
+                return Ok(());
+            };
+
+            match &builder.texture {
+                TileTexture::Static(layers) => {
+                    if let Some(count) = texture_count {
+                        if layers.iter().any(|layer| layer.texture_index >= count) {
+                            return Err(TransactionError::InvalidTexture(*index));
+                        }
+                    }
+                }
+                TileTexture::Animated(anim) => {
+                    if anim.start == 0 || (anim.start + anim.length) as usize > animation_len {
+                        return Err(TransactionError::InvalidAnimation(*index));
+                    }
+                }
+            }
+
+            Ok(())
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum TransactionError {
+    /// The tilemap doesn't exist or doesn't have a `TilemapStorage`.
+    MissingTilemap,
+    /// A tile layer uses a texture index that is out of the tilemap texture.
+    InvalidTexture(IVec2),
+    /// A tile uses an animation that is not registered in the `TilemapAnimations`.
+    InvalidAnimation(IVec2),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum PendingTransaction {
+    Edit(TilemapTransaction),
+    Undo,
+    Redo,
+}
+
+/// Transactions waiting to be applied. They are applied in the order they are submitted.
+#[derive(Resource, Default)]
+pub struct TilemapTransactions(pub(crate) Vec<(Entity, PendingTransaction)>);
+
+impl TilemapTransactions {
+    /// Build a transaction for the tilemap and submit it.
+    ///
+    /// ```ignore
+    /// transactions.transaction(tilemap, |tx| {
+    ///     tx.set(IVec2::ZERO, TileBuilder::new());
+    ///     tx.remove(IVec2::ONE);
+    /// });
+    /// ```
+    pub fn transaction(&mut self, tilemap: Entity, f: impl FnOnce(&mut TilemapTransaction)) {
+        let mut transaction = TilemapTransaction::default();
+        f(&mut transaction);
+        self.submit(tilemap, transaction);
+    }
+
+    #[inline]
+    pub fn submit(&mut self, tilemap: Entity, transaction: TilemapTransaction) {
+        if !transaction.is_empty() {
+            self.0
+                .push((tilemap, PendingTransaction::Edit(transaction)));
+        }
+    }
+
+    /// Revert the last transaction recorded in the `TilemapEditJournal` of the tilemap.
+    #[inline]
+    pub fn undo(&mut self, tilemap: Entity) {
+        self.0.push((tilemap, PendingTransaction::Undo));
+    }
+
+    /// Reapply the last transaction reverted by `undo`.
+    #[inline]
+    pub fn redo(&mut self, tilemap: Entity) {
+        self.0.push((tilemap, PendingTransaction::Redo));
+    }
+}
+
+/// Records the transactions applied to the tilemap so they can be undone.
+///
+/// Only the edits made through `TilemapTransactions` are recorded.
+#[derive(Component, Debug, Clone, Reflect)]
+pub struct TilemapEditJournal {
+    /// The maximum amount of transactions that can be undone.
+    pub max_len: usize,
+    pub(crate) undo: Vec<TilemapTransaction>,
+    pub(crate) redo: Vec<TilemapTransaction>,
+}
+
+impl Default for TilemapEditJournal {
+    fn default() -> Self {
+        Self {
+            max_len: 64,
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+}
+
+impl TilemapEditJournal {
+    #[inline]
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    #[inline]
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    fn record(&mut self, inverse: TilemapTransaction) {
+        self.undo.push(inverse);
+        if self.undo.len() > self.max_len {
+            self.undo.remove(0);
+        }
+    }
+}
+
+/// Fired once for every transaction that is applied.
+#[derive(Event, Debug, Clone, Reflect)]
+pub struct TilemapTransactionApplied {
+    pub tilemap: Entity,
+    /// The indices of the tiles that are changed.
+    pub indices: Vec<IVec2>,
+}
+
+/// Fired when a transaction is rejected. Nothing is changed on the tilemap.
+#[derive(Event, Debug, Clone, Copy, Reflect)]
+pub struct TilemapTransactionFailed {
+    pub tilemap: Entity,
+    pub error: TransactionError,
+}
+
+pub fn transaction_applier(
+    mut commands: Commands,
+    mut tilemaps_query: Query<(
+        &mut TilemapStorage,
+        Option<&TilemapTexture>,
+        Option<&TilemapAnimations>,
+        Option<&mut TilemapEditJournal>,
+    )>,
+    tiles_query: Query<&Tile>,
+    mut transactions: ResMut<TilemapTransactions>,
+    mut applied: EventWriter<TilemapTransactionApplied>,
+    mut failed: EventWriter<TilemapTransactionFailed>,
+) {
+    // Tiles edited this frame, as the `Tile` components are not updated until the commands are applied.
+    let mut edited = HashMap::<(Entity, IVec2), Option<TileBuilder>>::new();
+
+    for (tilemap, pending) in transactions.0.drain(..) {
+        let Ok((mut storage, texture, animations, mut journal)) = tilemaps_query.get_mut(tilemap)
+        else {
+            failed.send(TilemapTransactionFailed {
+                tilemap,
+                error: TransactionError::MissingTilemap,
+            });
+            continue;
+        };
+
+        let transaction = match &pending {
+            PendingTransaction::Edit(transaction) => transaction.clone(),
+            PendingTransaction::Undo => match journal.as_mut().and_then(|j| j.undo.pop()) {
+                Some(transaction) => transaction,
+                None => continue,
+            },
+            PendingTransaction::Redo => match journal.as_mut().and_then(|j| j.redo.pop()) {
+                Some(transaction) => transaction,
+                None => continue,
+            },
+        };
+
+        if let Err(error) = transaction.validate(texture, animations) {
+            // Put it back so the journal stays consistent.
+            if let Some(journal) = journal.as_mut() {
+                match pending {
+                    PendingTransaction::Edit(_) => {}
+                    PendingTransaction::Undo => journal.undo.push(transaction),
+                    PendingTransaction::Redo => journal.redo.push(transaction),
+                }
+            }
+            failed.send(TilemapTransactionFailed { tilemap, error });
+            continue;
+        }
+
+        let mut originals = Vec::new();
+        let mut touched = HashSet::new();
+        for edit in transaction.edits {
+            let index = edit.index();
+            let current = edited.get(&(tilemap, index)).cloned().unwrap_or_else(|| {
+                storage
+                    .get(index)
+                    .and_then(|e| tiles_query.get(e).ok())
+                    .map(|tile| tile.clone().into())
+            });
+            if touched.insert(index) {
+                originals.push((index, current));
+            }
+
+            match edit {
+                TileEdit::Set(index, builder) => {
+                    let tile = builder.build_component(index, &storage, tilemap);
+                    let entity = storage.get(index).unwrap_or_else(|| {
+                        let e = storage.spawn_tile_entity(&mut commands);
+                        storage.set_entity(index, Some(e));
+                        e
+                    });
+                    commands.entity(entity).insert(tile);
+                    edited.insert((tilemap, index), Some(builder));
+                }
+                TileEdit::Remove(index) => {
+                    storage.remove(&mut commands, index);
+                    edited.insert((tilemap, index), None);
+                }
+            }
+        }
+
+        let indices = originals.iter().map(|(index, _)| *index).collect();
+        if let Some(journal) = journal.as_mut() {
+            let inverse = TilemapTransaction {
+                edits: originals
+                    .into_iter()
+                    .map(|(index, original)| match original {
+                        Some(builder) => TileEdit::Set(index, builder),
+                        None => TileEdit::Remove(index),
+                    })
+                    .collect(),
+            };
+
+            match pending {
+                PendingTransaction::Edit(_) => {
+                    journal.record(inverse);
+                    journal.redo.clear();
+                }
+                PendingTransaction::Undo => journal.redo.push(inverse),
+                PendingTransaction::Redo => journal.record(inverse),
+            }
+        }
+
+        applied.send(TilemapTransactionApplied { tilemap, indices });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::{ecs::event::Events, math::UVec2, render::render_resource::FilterMode};
+
+    use crate::tilemap::{
+        map::{TilemapRotation, TilemapTextureDescriptor},
+        tile::{RawTileAnimation, TileLayer},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_validate_transaction() {
+        let texture = TilemapTexture::new(
+            Default::default(),
+            TilemapTextureDescriptor::new(UVec2::splat(32), UVec2::splat(16), FilterMode::Nearest),
+            TilemapRotation::None,
+        );
+        let mut animations = TilemapAnimations::default();
+        let anim = animations.register(RawTileAnimation {
+            sequence: vec![0, 1, 2],
+            fps: 10,
+        });
+
+        let mut tx = TilemapTransaction::default();
+        tx.set(
+            IVec2::ZERO,
+            TileBuilder::new().with_layer(0, TileLayer::no_flip(3)),
+        )
+        .set(IVec2::ONE, TileBuilder::new().with_animation(anim))
+        .remove(IVec2::NEG_ONE);
+        assert_eq!(tx.validate(Some(&texture), Some(&animations)), Ok(()));
+        assert_eq!(
+            tx.validate(Some(&texture), None),
+            Err(TransactionError::InvalidAnimation(IVec2::ONE))
+        );
+
+        tx.set(
+            IVec2::X,
+            TileBuilder::new().with_layer(1, TileLayer::no_flip(4)),
+        );
+        assert_eq!(
+            tx.validate(Some(&texture), Some(&animations)),
+            Err(TransactionError::InvalidTexture(IVec2::X))
+        );
+        // Pure color tilemaps don't care about texture indices.
+        assert_eq!(tx.validate(None, Some(&animations)), Ok(()));
+    }
+
+    fn texture_at(app: &App, tilemap: Entity, index: IVec2) -> Option<i32> {
+        let entity = app
+            .world
+            .get::<TilemapStorage>(tilemap)
+            .unwrap()
+            .get(index)?;
+        match &app.world.get::<Tile>(entity)?.texture {
+            TileTexture::Static(layers) => Some(layers[0].texture_index),
+            TileTexture::Animated(_) => None,
+        }
+    }
+
+    fn tile(texture_index: i32) -> TileBuilder {
+        TileBuilder::new().with_layer(0, TileLayer::no_flip(texture_index))
+    }
+
+    #[test]
+    fn test_transaction_applier() {
+        let mut app = App::new();
+        app.add_plugins(EntiTilesTransactionPlugin);
+
+        let tilemap = app.world.spawn_empty().id();
+        app.world.entity_mut(tilemap).insert((
+            TilemapStorage::new(4, tilemap),
+            TilemapTexture::new(
+                Default::default(),
+                TilemapTextureDescriptor::new(
+                    UVec2::splat(32),
+                    UVec2::splat(16),
+                    FilterMode::Nearest,
+                ),
+                TilemapRotation::None,
+            ),
+            TilemapEditJournal::default(),
+        ));
+
+        let mut transactions = app.world.resource_mut::<TilemapTransactions>();
+        transactions.transaction(tilemap, |tx| {
+            tx.set(IVec2::ZERO, tile(1)).set(IVec2::X, tile(2));
+        });
+        transactions.transaction(tilemap, |tx| {
+            tx.set(IVec2::ZERO, tile(3)).remove(IVec2::X);
+        });
+        app.update();
+        assert_eq!(texture_at(&app, tilemap, IVec2::ZERO), Some(3));
+        assert_eq!(texture_at(&app, tilemap, IVec2::X), None);
+        let applied = app
+            .world
+            .resource_mut::<Events<TilemapTransactionApplied>>()
+            .drain()
+            .count();
+        assert_eq!(applied, 2);
+
+        // An invalid edit rejects the whole transaction.
+        app.world
+            .resource_mut::<TilemapTransactions>()
+            .transaction(tilemap, |tx| {
+                tx.set(IVec2::Y, tile(1)).set(IVec2::ZERO, tile(4));
+            });
+        app.update();
+        assert_eq!(texture_at(&app, tilemap, IVec2::Y), None);
+        assert_eq!(texture_at(&app, tilemap, IVec2::ZERO), Some(3));
+        let failed = app
+            .world
+            .resource_mut::<Events<TilemapTransactionFailed>>()
+            .drain()
+            .map(|e| e.error)
+            .collect::<Vec<_>>();
+        assert_eq!(failed, [TransactionError::InvalidTexture(IVec2::ZERO)]);
+        assert_eq!(
+            app.world
+                .get::<TilemapEditJournal>(tilemap)
+                .unwrap()
+                .undo
+                .len(),
+            2
+        );
+
+        // Undo the second transaction, then the first one.
+        app.world
+            .resource_mut::<TilemapTransactions>()
+            .undo(tilemap);
+        app.update();
+        assert_eq!(texture_at(&app, tilemap, IVec2::ZERO), Some(1));
+        assert_eq!(texture_at(&app, tilemap, IVec2::X), Some(2));
+
+        app.world
+            .resource_mut::<TilemapTransactions>()
+            .undo(tilemap);
+        app.update();
+        assert_eq!(texture_at(&app, tilemap, IVec2::ZERO), None);
+        assert_eq!(texture_at(&app, tilemap, IVec2::X), None);
+        let journal = app.world.get::<TilemapEditJournal>(tilemap).unwrap();
+        assert!(!journal.can_undo());
+        assert!(journal.can_redo());
+
+        // Redo both of them in order.
+        let mut transactions = app.world.resource_mut::<TilemapTransactions>();
+        transactions.redo(tilemap);
+        transactions.redo(tilemap);
+        app.update();
+        assert_eq!(texture_at(&app, tilemap, IVec2::ZERO), Some(3));
+        assert_eq!(texture_at(&app, tilemap, IVec2::X), None);
+        let journal = app.world.get::<TilemapEditJournal>(tilemap).unwrap();
+        assert!(journal.can_undo());
+        assert!(!journal.can_redo());
+    }
+}
@@ -0,0 +1,349 @@
+use bevy::{
+    ecs::{component::Component, entity::Entity},
+    math::IVec2,
+    reflect::Reflect,
+};
+
+use super::{
+    map::{TilemapAnimations, TilemapStorage, TilemapTexture},
+    tile::{Tile, TileAnimation, TileTexture},
+};
+
+#[cfg(feature = "algorithm")]
+use super::algorithm::path::PathTilemap;
+
+#[cfg(feature = "debug")]
+use super::budget::TileSpawnQueue;
+#[cfg(feature = "debug")]
+use bevy::{
+    ecs::{
+        query::{Added, Changed, Or},
+        system::{Commands, Query, Res},
+    },
+    log::warn,
+};
+
+#[cfg(all(feature = "debug", feature = "algorithm"))]
+use crate::algorithm::pathfinding::PathTilemaps;
+
+/// Insert this to validate the tilemap again, like after editing the tiles.
+/// It will be removed once the tilemap is validated.
+///
+/// This requires the `debug` feature. Otherwise call `TilemapStorage::validate` directly.
+#[derive(Component, Debug, Default, Clone, Copy, Reflect)]
+pub struct ValidateTilemap;
+
+/// A problem found by `TilemapStorage::validate`.
+#[derive(Debug, Clone, Reflect)]
+pub enum TilemapIssue {
+    /// The storage points to an entity without `Tile`.
+    MissingTile { index: IVec2, entity: Entity },
+    /// The tile belongs to another tilemap.
+    WrongTilemap {
+        index: IVec2,
+        entity: Entity,
+        tilemap: Entity,
+    },
+    /// The position recorded in the tile doesn't match where it is in the storage.
+    MisplacedTile {
+        index: IVec2,
+        entity: Entity,
+        tile_index: IVec2,
+    },
+    /// The texture index is greater than the amount of tiles in the texture.
+    TextureIndexOutOfRange {
+        index: IVec2,
+        texture_index: i32,
+        tile_count: u32,
+    },
+    /// The animation is not registered in the `TilemapAnimations`.
+    UnregisteredAnimation {
+        index: IVec2,
+        animation: TileAnimation,
+    },
+    /// A frame of the registered animation is greater than the amount of tiles in the texture.
+    AnimationFrameOutOfRange {
+        animation: TileAnimation,
+        frame: u32,
+        tile_count: u32,
+    },
+    /// The path tilemap uses a different chunk size.
+    #[cfg(feature = "algorithm")]
+    PathChunkSizeMismatch {
+        chunk_size: u32,
+        path_chunk_size: u32,
+    },
+    /// There are path tiles in a chunk without any tile.
+    #[cfg(feature = "algorithm")]
+    PathChunkOutOfTilemap { chunk_index: IVec2 },
+}
+
+impl TilemapStorage {
+    /// Check the tiles for corrupted data, like the ones from a broken save.
+    ///
+    /// `tiles` should return the `Tile` of a tile entity.
+    pub fn validate<'a>(
+        &self,
+        tiles: impl Fn(Entity) -> Option<&'a Tile>,
+        texture: Option<&TilemapTexture>,
+        animations: Option<&TilemapAnimations>,
+    ) -> Vec<TilemapIssue> {
+        let mut issues = Vec::new();
+        let tile_count = texture.map(|tex| {
+            let count = tex.desc().tile_count();
+            count.x * count.y
+        });
+
+        self.storage
+            .chunked_iter_some()
+            .for_each(|(chunk_index, in_chunk_index, entity)| {
+                let index = self
+                    .storage
+                    .inverse_transform_index(chunk_index, in_chunk_index);
+                let Some(tile) = tiles(*entity) else {
+                    issues.push(TilemapIssue::MissingTile {
+                        index,
+                        entity: *entity,
+                    });
+                    return;
+                };
+
+                if tile.tilemap_id != self.tilemap {
+                    issues.push(TilemapIssue::WrongTilemap {
+                        index,
+                        entity: *entity,
+                        tilemap: tile.tilemap_id,
+                    });
+                }
+                if tile.index != index
+                    || tile.chunk_index != chunk_index
+                    || tile.in_chunk_index != in_chunk_index
+                {
+                    issues.push(TilemapIssue::MisplacedTile {
+                        index,
+                        entity: *entity,
+                        tile_index: tile.index,
+                    });
+                }
+
+                match &tile.texture {
+                    TileTexture::Static(layers) => {
+                        let Some(tile_count) = tile_count else {
+                            return;
+                        };
+                        layers
+                            .iter()
+                            .filter(|layer| layer.texture_index >= tile_count as i32)
+                            .for_each(|layer| {
+                                issues.push(TilemapIssue::TextureIndexOutOfRange {
+                                    index,
+                                    texture_index: layer.texture_index,
+                                    tile_count,
+                                })
+                            });
+                    }
+                    TileTexture::Animated(anim) => {
+                        if !animations.is_some_and(|a| a.contains(*anim)) {
+                            issues.push(TilemapIssue::UnregisteredAnimation {
+                                index,
+                                animation: *anim,
+                            });
+                        }
+                    }
+                }
+            });
+
+        if let (Some(animations), Some(tile_count)) = (animations, tile_count) {
+            animations.iter().for_each(|anim| {
+                let sequence = animations.get(*anim).unwrap().sequence;
+                if let Some(frame) = sequence.into_iter().find(|f| *f >= tile_count) {
+                    issues.push(TilemapIssue::AnimationFrameOutOfRange {
+                        animation: *anim,
+                        frame,
+                        tile_count,
+                    });
+                }
+            });
+        }
+
+        issues
+    }
+
+    /// Check if the path tilemap matches the tiles.
+    #[cfg(feature = "algorithm")]
+    pub fn validate_path_tilemap(&self, path_tilemap: &PathTilemap) -> Vec<TilemapIssue> {
+        if path_tilemap.storage.chunk_size != self.storage.chunk_size {
+            return vec![TilemapIssue::PathChunkSizeMismatch {
+                chunk_size: self.storage.chunk_size,
+                path_chunk_size: path_tilemap.storage.chunk_size,
+            }];
+        }
+
+        path_tilemap
+            .storage
+            .chunk_indices()
+            .filter(|chunk_index| !self.storage.contains_chunk(**chunk_index))
+            .map(|chunk_index| TilemapIssue::PathChunkOutOfTilemap {
+                chunk_index: *chunk_index,
+            })
+            .collect()
+    }
+}
+
+/// Validate the new tilemaps, including the loaded and imported ones,
+/// and report the issues as warnings.
+///
+/// The tilemaps are validated again when their texture or animations change,
+/// or when `ValidateTilemap` is inserted.
+#[cfg(feature = "debug")]
+pub fn tilemap_validator(
+    mut commands: Commands,
+    tilemaps_query: Query<
+        (
+            &TilemapStorage,
+            Option<&TilemapTexture>,
+            Option<&TilemapAnimations>,
+            Option<&ValidateTilemap>,
+        ),
+        Or<(
+            Added<TilemapStorage>,
+            Changed<TilemapTexture>,
+            Changed<TilemapAnimations>,
+            Added<ValidateTilemap>,
+        )>,
+    >,
+    tiles_query: Query<&Tile>,
+    queue: Res<TileSpawnQueue>,
+    #[cfg(feature = "algorithm")] path_tilemaps: Res<PathTilemaps>,
+) {
+    tilemaps_query
+        .iter()
+        .for_each(|(storage, texture, animations, request)| {
+            if request.is_some() {
+                commands.entity(storage.tilemap).remove::<ValidateTilemap>();
+            }
+
+            let mut issues = storage.validate(|e| tiles_query.get(e).ok(), texture, animations);
+
+            // The tiles queued by `TileSpawnBudget` get their `Tile` later.
+            let queued = queue.queued_tiles(storage);
+            issues.retain(|issue| match issue {
+                TilemapIssue::MissingTile { entity, .. } => !queued.contains_key(entity),
+                _ => true,
+            });
+
+            #[cfg(all(feature = "algorithm", feature = "multi-threaded"))]
+            if let Some(path_tilemap) = path_tilemaps.lock(storage.tilemap) {
+                issues.extend(storage.validate_path_tilemap(&path_tilemap));
+            }
+            #[cfg(all(feature = "algorithm", not(feature = "multi-threaded")))]
+            if let Some(path_tilemap) = path_tilemaps.get(storage.tilemap) {
+                issues.extend(storage.validate_path_tilemap(path_tilemap));
+            }
+
+            if !issues.is_empty() {
+                warn!(
+                    "Found {} issues in tilemap {:?}, the first one is {:?}",
+                    issues.len(),
+                    storage.tilemap,
+                    issues[0]
+                );
+            }
+        });
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::{math::UVec2, render::render_resource::FilterMode, utils::HashMap};
+
+    use crate::tilemap::{
+        map::{TilemapRotation, TilemapTextureDescriptor},
+        tile::{RawTileAnimation, TileBuilder, TileLayer},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let tilemap = Entity::from_raw(0);
+        let mut storage = TilemapStorage::new(4, tilemap);
+        let texture = TilemapTexture::new(
+            Default::default(),
+            TilemapTextureDescriptor::new(UVec2::splat(32), UVec2::splat(16), FilterMode::Nearest),
+            TilemapRotation::None,
+        );
+        let mut animations = TilemapAnimations::default();
+        let anim = animations.register(RawTileAnimation {
+            sequence: vec![0, 4],
+            fps: 1,
+        });
+        let mut unregistered = animations.register(RawTileAnimation {
+            sequence: vec![1],
+            fps: 1,
+        });
+        animations.unregister(unregistered);
+        unregistered.start += 10;
+
+        let mut tiles = HashMap::new();
+        let mut add = |index: IVec2, builder: TileBuilder| {
+            let entity = Entity::from_raw(tiles.len() as u32 + 1);
+            storage.set_entity(index, Some(entity));
+            tiles.insert(entity, builder.build_component(index, &storage, tilemap));
+        };
+        add(
+            IVec2::ZERO,
+            TileBuilder::new().with_layer(0, TileLayer::no_flip(3)),
+        );
+        add(
+            IVec2::X,
+            TileBuilder::new().with_layer(0, TileLayer::no_flip(4)),
+        );
+        add(IVec2::Y, TileBuilder::new().with_animation(anim));
+        add(IVec2::ONE, TileBuilder::new().with_animation(unregistered));
+        storage.set_entity(IVec2::new(5, 5), Some(Entity::from_raw(100)));
+
+        let issues = storage.validate(|e| tiles.get(&e), Some(&texture), Some(&animations));
+        assert_eq!(issues.len(), 4);
+        assert!(matches!(
+            issues
+                .iter()
+                .find(|i| matches!(i, TilemapIssue::TextureIndexOutOfRange { .. })),
+            Some(TilemapIssue::TextureIndexOutOfRange {
+                texture_index: 4,
+                tile_count: 4,
+                ..
+            })
+        ));
+        assert!(issues
+            .iter()
+            .any(|i| matches!(i, TilemapIssue::UnregisteredAnimation { index, .. } if *index == IVec2::ONE)));
+        assert!(issues.iter().any(
+            |i| matches!(i, TilemapIssue::MissingTile { index, .. } if *index == IVec2::new(5, 5))
+        ));
+        assert!(issues
+            .iter()
+            .any(|i| matches!(i, TilemapIssue::AnimationFrameOutOfRange { frame: 4, .. })));
+    }
+
+    #[cfg(feature = "debug")]
+    #[test]
+    fn test_validate_on_demand() {
+        use bevy::app::{App, Update};
+
+        let mut app = App::new();
+        app.init_resource::<TileSpawnQueue>()
+            .add_systems(Update, tilemap_validator);
+        #[cfg(feature = "algorithm")]
+        app.init_resource::<PathTilemaps>();
+
+        let tilemap = app.world.spawn_empty().id();
+        app.world
+            .entity_mut(tilemap)
+            .insert(TilemapStorage::new(4, tilemap));
+        app.update();
+
+        app.world.entity_mut(tilemap).insert(ValidateTilemap);
+        app.update();
+        assert!(app.world.get::<ValidateTilemap>(tilemap).is_none());
+    }
+}
@@ -0,0 +1,446 @@
+use bevy::{
+    ecs::{
+        change_detection::{DetectChanges, DetectChangesMut},
+        component::Component,
+        entity::Entity,
+        removal_detection::RemovedComponents,
+        system::{Commands, Query},
+        world::Ref,
+    },
+    math::{IVec2, Vec2},
+    reflect::Reflect,
+    utils::{HashMap, HashSet},
+};
+
+use super::{
+    buffers::TileBuilderBuffer,
+    map::{
+        TilePivot, TileRenderSize, TilemapSlotSize, TilemapStorage, TilemapTransform, TilemapType,
+    },
+    tile::{TileBuilder, TileLayer, TileOffset},
+};
+
+/// The four sides of a square tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+#[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
+pub enum TileSide {
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+impl TileSide {
+    pub const ALL: [TileSide; 4] = [
+        TileSide::Up,
+        TileSide::Right,
+        TileSide::Down,
+        TileSide::Left,
+    ];
+
+    /// The offset to the neighbour on this side.
+    pub fn offset(self) -> IVec2 {
+        match self {
+            TileSide::Up => IVec2::Y,
+            TileSide::Right => IVec2::X,
+            TileSide::Down => IVec2::NEG_Y,
+            TileSide::Left => IVec2::NEG_X,
+        }
+    }
+
+    pub fn opposite(self) -> TileSide {
+        match self {
+            TileSide::Up => TileSide::Down,
+            TileSide::Right => TileSide::Left,
+            TileSide::Down => TileSide::Up,
+            TileSide::Left => TileSide::Right,
+        }
+    }
+}
+
+/// An edge between two square tiles.
+///
+/// Every edge is stored on the tile on its left or bottom side, so the same edge
+/// seen from both tiles is equal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+#[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileEdge {
+    pub(crate) index: IVec2,
+    /// Always `Up` or `Right`.
+    pub(crate) side: TileSide,
+}
+
+impl TileEdge {
+    /// The edge on the side of the tile.
+    pub fn new(index: IVec2, side: TileSide) -> Self {
+        match side {
+            TileSide::Up | TileSide::Right => Self { index, side },
+            _ => Self {
+                index: index + side.offset(),
+                side: side.opposite(),
+            },
+        }
+    }
+
+    /// The edge between two adjacent tiles. Returns `None` if they are not adjacent.
+    pub fn between(a: IVec2, b: IVec2) -> Option<Self> {
+        TileSide::ALL
+            .into_iter()
+            .find(|side| a + side.offset() == b)
+            .map(|side| Self::new(a, side))
+    }
+
+    /// The four edges of a tile.
+    pub fn of_tile(index: IVec2) -> [TileEdge; 4] {
+        TileSide::ALL.map(|side| TileEdge::new(index, side))
+    }
+
+    /// The tile this edge is stored on.
+    #[inline]
+    pub fn index(&self) -> IVec2 {
+        self.index
+    }
+
+    /// The side of `index` this edge is on.
+    #[inline]
+    pub fn side(&self) -> TileSide {
+        self.side
+    }
+
+    /// The two tiles on both sides of the edge.
+    #[inline]
+    pub fn tiles(&self) -> [IVec2; 2] {
+        [self.index, self.index + self.side.offset()]
+    }
+
+    /// The world position of the middle of the edge on a square tilemap.
+    pub fn to_world(&self, transform: &TilemapTransform, pivot: Vec2, slot_size: Vec2) -> Vec2 {
+        let offset = match self.side {
+            TileSide::Up => Vec2::new(0.5, 1.),
+            _ => Vec2::new(1., 0.5),
+        };
+        transform.transform_point((self.index.as_vec2() + offset - pivot) * slot_size)
+    }
+
+    /// The index of the slot of this edge in the overlay tilemap. See `TilemapWalls`.
+    #[inline]
+    pub fn overlay_index(&self) -> IVec2 {
+        self.index * 2 + self.side.offset()
+    }
+}
+
+#[cfg(feature = "serializing")]
+impl crate::serializing::SortedKey for TileEdge {
+    type Key = (i32, i32, bool);
+
+    #[inline]
+    fn sorted_key(&self) -> Self::Key {
+        (self.index.y, self.index.x, self.side == TileSide::Up)
+    }
+}
+
+/// Thin walls between the tiles of a square tilemap, for interior walls in dungeon
+/// crawlers and sims.
+///
+/// Insert this component on the tilemap entity. Each wall stores the texture index
+/// in the wall tile set. The walls also block the path-finding across the edges
+/// if the tilemap has a `PathTilemap`.
+///
+/// The walls are drawn by a separate overlay tilemap with the wall tile set.
+/// The overlay has half the slot size of the tilemap, so every edge is the center
+/// of a slot, and the same `TilemapTransform` and `TilePivot`. The wall textures should
+/// be drawn for the `Right` edge, a vertical strip through the center of the tile,
+/// and they are rotated for the horizontal edges.
+///
+/// Insert `WallOverlayTilemap` as well to keep the overlay in sync with the walls,
+/// or set the tiles yourself with `apply`.
+///
+/// Use `TilemapWalls::serializer_hook` to save the walls with the tilemap.
+#[derive(Component, Debug, Default, Clone, Reflect)]
+#[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
+pub struct TilemapWalls {
+    #[cfg_attr(
+        feature = "serializing",
+        serde(serialize_with = "crate::serializing::serialize_sorted")
+    )]
+    pub(crate) walls: HashMap<TileEdge, i32>,
+}
+
+impl TilemapWalls {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_wall(mut self, edge: TileEdge, texture_index: i32) -> Self {
+        self.set(edge, texture_index);
+        self
+    }
+
+    /// The type and the slot size of the overlay tilemap.
+    pub fn overlay_layout(slot_size: Vec2) -> (TilemapType, Vec2) {
+        (TilemapType::Square, slot_size / 2.)
+    }
+
+    #[inline]
+    pub fn set(&mut self, edge: TileEdge, texture_index: i32) {
+        self.walls.insert(edge, texture_index);
+    }
+
+    #[inline]
+    pub fn get(&self, edge: TileEdge) -> Option<i32> {
+        self.walls.get(&edge).copied()
+    }
+
+    #[inline]
+    pub fn remove(&mut self, edge: TileEdge) -> Option<i32> {
+        self.walls.remove(&edge)
+    }
+
+    #[inline]
+    pub fn contains(&self, edge: TileEdge) -> bool {
+        self.walls.contains_key(&edge)
+    }
+
+    /// Returns `true` if there's a wall between the two adjacent tiles.
+    #[inline]
+    pub fn is_blocked(&self, a: IVec2, b: IVec2) -> bool {
+        TileEdge::between(a, b).is_some_and(|edge| self.contains(edge))
+    }
+
+    /// Place the same wall around a rectangle of tiles, inclusive.
+    pub fn fill_outline(&mut self, min: IVec2, max: IVec2, texture_index: i32) {
+        (min.x..=max.x).for_each(|x| {
+            self.set(
+                TileEdge::new(IVec2::new(x, min.y), TileSide::Down),
+                texture_index,
+            );
+            self.set(
+                TileEdge::new(IVec2::new(x, max.y), TileSide::Up),
+                texture_index,
+            );
+        });
+        (min.y..=max.y).for_each(|y| {
+            self.set(
+                TileEdge::new(IVec2::new(min.x, y), TileSide::Left),
+                texture_index,
+            );
+            self.set(
+                TileEdge::new(IVec2::new(max.x, y), TileSide::Right),
+                texture_index,
+            );
+        });
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (TileEdge, i32)> + '_ {
+        self.walls.iter().map(|(edge, texture)| (*edge, *texture))
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.walls.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.walls.is_empty()
+    }
+
+    /// The tiles of the overlay tilemap, moved and rotated onto the edges.
+    ///
+    /// `pivot` and `slot_size` are the ones of the tilemap, and `render_size` is
+    /// the `TileRenderSize` of the overlay.
+    /// The result can be applied with `TilemapStorage::fill_with_buffer` at `IVec2::ZERO`.
+    pub fn overlay_tiles(
+        &self,
+        pivot: Vec2,
+        slot_size: Vec2,
+        render_size: Vec2,
+    ) -> TileBuilderBuffer {
+        // The center of the overlay slot is the middle of the edge, but the tiles are
+        // drawn from the corner of the slot.
+        let translation = (0.5 - pivot) * (slot_size - render_size);
+
+        let mut buffer = TileBuilderBuffer::new();
+        self.walls.iter().for_each(|(edge, texture)| {
+            let rotation = match edge.side {
+                TileSide::Up => std::f32::consts::FRAC_PI_2,
+                _ => 0.,
+            };
+            buffer.set(
+                edge.overlay_index(),
+                TileBuilder::new()
+                    .with_layer(0, TileLayer::no_flip(*texture))
+                    .with_offset(TileOffset::new(translation, rotation)),
+            );
+        });
+        buffer
+    }
+
+    /// Set the wall tiles directly on the overlay tilemap.
+    ///
+    /// This doesn't remove the tiles of the walls that are removed, use
+    /// `WallOverlayTilemap` to keep the overlay in sync.
+    pub fn apply(
+        &self,
+        commands: &mut Commands,
+        overlay: &mut TilemapStorage,
+        pivot: Vec2,
+        slot_size: Vec2,
+        render_size: Vec2,
+    ) {
+        overlay.fill_with_buffer(
+            commands,
+            IVec2::ZERO,
+            self.overlay_tiles(pivot, slot_size, render_size),
+        );
+    }
+
+    /// A hook that saves the walls with the tilemap.
+    /// Register it using `TilemapSerializerHooks::register`.
+    #[cfg(feature = "serializing")]
+    pub fn serializer_hook() -> impl crate::serializing::map::hook::TilemapSerializerHook {
+        use bevy::ecs::{entity::Entity, world::World};
+
+        crate::serializing::map::hook::TypedSerializerHook::new(
+            "walls",
+            crate::serializing::map::TilemapLayer::all(),
+            |world: &World, tilemap: Entity| world.get::<TilemapWalls>(tilemap).cloned(),
+            |world: &mut World, tilemap: Entity, walls: TilemapWalls| {
+                world.entity_mut(tilemap).insert(walls);
+            },
+        )
+    }
+}
+
+/// The overlay tilemap that draws the `TilemapWalls` of this tilemap, see `TilemapWalls`.
+///
+/// The wall tiles are spawned and despawned on the overlay whenever the walls change.
+#[derive(Component, Debug, Clone, Reflect)]
+pub struct WallOverlayTilemap {
+    pub overlay: Entity,
+    /// The tiles currently set on the overlay.
+    #[reflect(ignore)]
+    pub(crate) applied: TileBuilderBuffer,
+}
+
+impl WallOverlayTilemap {
+    pub fn new(overlay: Entity) -> Self {
+        Self {
+            overlay,
+            applied: TileBuilderBuffer::new(),
+        }
+    }
+}
+
+pub fn wall_overlay_syncer(
+    mut commands: Commands,
+    mut tilemaps_query: Query<(
+        Entity,
+        Option<Ref<TilemapWalls>>,
+        &mut WallOverlayTilemap,
+        &TilePivot,
+        &TilemapSlotSize,
+    )>,
+    mut overlays_query: Query<(&mut TilemapStorage, &TileRenderSize)>,
+    mut removed: RemovedComponents<TilemapWalls>,
+) {
+    let removed = removed.read().collect::<HashSet<_>>();
+
+    tilemaps_query
+        .iter_mut()
+        .for_each(|(entity, walls, mut overlay, pivot, slot_size)| {
+            let changed = walls.as_ref().is_some_and(|w| w.is_changed());
+            if !changed && !overlay.is_changed() && !removed.contains(&entity) {
+                return;
+            }
+            let Ok((mut storage, render_size)) = overlays_query.get_mut(overlay.overlay) else {
+                return;
+            };
+
+            let buffer = walls.map_or_else(TileBuilderBuffer::new, |walls| {
+                walls.overlay_tiles(pivot.0, slot_size.0, render_size.0)
+            });
+            storage.sync_with_buffer(
+                &mut commands,
+                &mut overlay.bypass_change_detection().applied,
+                buffer,
+            );
+        });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_walls() {
+        let mut walls = TilemapWalls::new();
+        walls.fill_outline(IVec2::ZERO, IVec2::new(2, 1), 3);
+        assert_eq!(walls.len(), 10);
+        assert!(walls.is_blocked(IVec2::new(2, 0), IVec2::new(3, 0)));
+        assert!(walls.is_blocked(IVec2::new(1, 2), IVec2::new(1, 1)));
+        assert!(!walls.is_blocked(IVec2::ZERO, IVec2::X));
+        assert!(!walls.is_blocked(IVec2::ZERO, IVec2::ONE));
+
+        let edge = TileEdge::new(IVec2::new(1, 1), TileSide::Left);
+        assert_eq!(edge, TileEdge::new(IVec2::ZERO + IVec2::Y, TileSide::Right));
+        assert_eq!(edge.tiles(), [IVec2::Y, IVec2::ONE]);
+        assert_eq!(
+            edge.to_world(&TilemapTransform::default(), Vec2::ZERO, Vec2::splat(16.)),
+            Vec2::new(16., 24.)
+        );
+
+        walls.remove(TileEdge::new(IVec2::ZERO, TileSide::Down));
+        let buffer = walls.overlay_tiles(Vec2::ZERO, Vec2::splat(16.), Vec2::new(4., 16.));
+        assert_eq!(buffer.tiles.len(), 9);
+        let up = &buffer.tiles[&IVec2::new(2, 3)];
+        assert_eq!(up.offset.translation, Vec2::new(6., 0.));
+        assert_eq!(up.offset.rotation, std::f32::consts::FRAC_PI_2);
+    }
+
+    #[test]
+    fn test_wall_overlay_syncer() {
+        use bevy::app::{App, Update};
+
+        use crate::tilemap::{despawn::DespawnMe, tile::Tile};
+
+        let mut app = App::new();
+        app.add_systems(Update, wall_overlay_syncer);
+
+        let overlay = app.world.spawn_empty().id();
+        let storage = TilemapStorage::new(4, overlay);
+        app.world
+            .entity_mut(overlay)
+            .insert((storage, TileRenderSize(Vec2::new(4., 16.))));
+        let edge = TileEdge::new(IVec2::ZERO, TileSide::Right);
+        let tilemap = app
+            .world
+            .spawn((
+                TilemapWalls::new().with_wall(edge, 1),
+                WallOverlayTilemap::new(overlay),
+                TilePivot::default(),
+                TilemapSlotSize(Vec2::splat(16.)),
+            ))
+            .id();
+        app.update();
+
+        let tile = app
+            .world
+            .get::<TilemapStorage>(overlay)
+            .unwrap()
+            .get(edge.overlay_index())
+            .unwrap();
+        assert!(app.world.get::<Tile>(tile).is_some());
+
+        // Removing the wall despawns its tile.
+        app.world
+            .get_mut::<TilemapWalls>(tilemap)
+            .unwrap()
+            .remove(edge);
+        app.update();
+        let storage = app.world.get::<TilemapStorage>(overlay).unwrap();
+        assert!(storage.get(edge.overlay_index()).is_none());
+        assert!(app.world.get::<DespawnMe>(tile).is_some());
+    }
+}
@@ -0,0 +1,222 @@
+use bevy::{
+    app::{App, Plugin, PostUpdate},
+    ecs::{
+        change_detection::{DetectChanges, DetectChangesMut},
+        component::Component,
+        entity::Entity,
+        removal_detection::RemovedComponents,
+        schedule::IntoSystemConfigs,
+        system::{ParamSet, Query},
+    },
+    math::{IVec2, Vec2},
+    reflect::Reflect,
+    render::color::Color,
+};
+
+use super::{
+    chunking::storage::ChunkedStorage,
+    coordinates, despawn,
+    map::{TilemapAxisFlip, TilemapStorage, TilemapTransform, TilemapType},
+    packed::TilemapPackedTiles,
+    tile::Tile,
+    tracker::TileChanges,
+};
+
+pub struct EntiTilesWeatherPlugin;
+
+impl Plugin for EntiTilesWeatherPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            sky_exposure_updater.before(despawn::despawn_tiles),
+        );
+
+        app.register_type::<TileSkyExposure>();
+    }
+}
+
+/// Marks which tiles of a tilemap are exposed to the sky,
+/// i.e. not covered by any tile on the roof tilemap.
+///
+/// Insert this component on the ground tilemap. The roof tilemap should
+/// share the same grid with it. The flags are kept up to date automatically
+/// when tiles on either tilemap are added or removed. The `TilemapPackedTiles`
+/// of this tilemap only get their flags when this component changes.
+#[derive(Component, Debug, Clone, Reflect)]
+pub struct TileSkyExposure {
+    /// The tilemap that covers this tilemap.
+    pub roof: Entity,
+    /// If set, the exposed tiles will be tinted with this color when rendering.
+    /// Useful for making the ground look wet when it's raining.
+    pub wet_tint: Option<Color>,
+    pub(crate) exposed: ChunkedStorage<bool>,
+}
+
+impl TileSkyExposure {
+    pub fn new(roof: Entity) -> Self {
+        Self {
+            roof,
+            wet_tint: None,
+            exposed: ChunkedStorage::default(),
+        }
+    }
+
+    pub fn with_wet_tint(mut self, wet_tint: Color) -> Self {
+        self.wet_tint = Some(wet_tint);
+        self
+    }
+
+    /// Returns true if the tile exists and there's nothing on the roof tilemap above it.
+    #[inline]
+    pub fn is_exposed(&self, index: IVec2) -> bool {
+        self.exposed.get_elem(index).cloned().unwrap_or_default()
+    }
+
+    /// Returns true if the tile at the world position is exposed to the sky.
+    #[inline]
+    pub fn is_exposed_world(
+        &self,
+        world: Vec2,
+        ty: TilemapType,
+        transform: &TilemapTransform,
+        pivot: Vec2,
+        slot_size: Vec2,
+        axis_flip: TilemapAxisFlip,
+    ) -> bool {
+        self.is_exposed(coordinates::world_to_index_flipped(
+            world, ty, transform, pivot, slot_size, axis_flip,
+        ))
+    }
+
+    /// Iterate over all the exposed tiles.
+    pub fn iter_exposed(&self) -> impl Iterator<Item = IVec2> + '_ {
+        self.exposed
+            .chunked_iter_some()
+            .filter(|(_, _, exposed)| **exposed)
+            .map(|(chunk_index, in_chunk_index, _)| {
+                self.exposed
+                    .inverse_transform_index(chunk_index, in_chunk_index)
+            })
+    }
+
+    /// Recalculate all the flags.
+    pub fn recalculate(&mut self, storage: &TilemapStorage, roof: &TilemapStorage) {
+        self.exposed.clear();
+        storage.storage.chunk_indices().for_each(|chunk_index| {
+            storage.iter_chunk(*chunk_index).for_each(|(index, _)| {
+                self.exposed.set_elem(index, roof.get(index).is_none());
+            });
+        });
+    }
+
+    pub(crate) fn apply_wet_tint(&self, index: IVec2, tint: Color) -> Color {
+        match self.wet_tint {
+            Some(wet_tint) if self.is_exposed(index) => Color::rgba_linear_from_array(
+                tint.rgba_linear_to_vec4() * wet_tint.rgba_linear_to_vec4(),
+            ),
+            _ => tint,
+        }
+    }
+}
+
+pub fn sky_exposure_updater(
+    mut tilemaps_query: Query<(Entity, &TilemapStorage, &mut TileSkyExposure)>,
+    storages_query: Query<&TilemapStorage>,
+    mut packed_query: Query<&mut TilemapPackedTiles>,
+    mut tiles_query: ParamSet<(TileChanges, Query<&mut Tile>)>,
+    mut removed: RemovedComponents<TileSkyExposure>,
+) {
+    let tiles = tiles_query.p0();
+    let updated = tiles
+        .iter_changed()
+        .map(|tile| (tile.tilemap_id, tile.index))
+        .collect::<Vec<_>>();
+    let despawned = tiles
+        .iter_removed()
+        .map(|tile| (tile.tilemap_id, tile.index))
+        .collect::<Vec<_>>();
+    // Tiles that need to be extracted again as their tints changed.
+    let mut changed = Vec::new();
+
+    removed.read().for_each(|entity| {
+        if let Ok(storage) = storages_query.get(entity) {
+            changed.extend(storage.storage.iter_some().cloned());
+        }
+        if let Ok(mut packed) = packed_query.get_mut(entity) {
+            packed.mark_all_changed();
+        }
+    });
+
+    tilemaps_query
+        .iter_mut()
+        .for_each(|(entity, storage, mut exposure)| {
+            let Ok(roof) = storages_query.get(exposure.roof) else {
+                return;
+            };
+
+            let mut packed = packed_query.get_mut(entity).ok();
+
+            if exposure.is_changed() {
+                let exposure = exposure.bypass_change_detection();
+                exposure.recalculate(storage, roof);
+                changed.extend(storage.storage.iter_some().cloned());
+                if let Some(packed) = packed.as_mut() {
+                    packed.iter().for_each(|(index, _)| {
+                        exposure.exposed.set_elem(index, roof.get(index).is_none());
+                    });
+                    packed.mark_all_changed();
+                }
+                return;
+            }
+
+            let roof_entity = exposure.roof;
+            let exposure = exposure.bypass_change_detection();
+            let mut set_exposed = |index: IVec2, exposed: bool| {
+                if exposure
+                    .exposed
+                    .get_elem(index)
+                    .is_some_and(|e| *e != exposed)
+                {
+                    exposure.exposed.set_elem(index, exposed);
+                    if exposure.wet_tint.is_some() {
+                        changed.extend(storage.get(index));
+                        if let Some(packed) = packed.as_mut() {
+                            packed.mark_changed(index);
+                        }
+                    }
+                }
+            };
+
+            despawned.iter().for_each(|(tilemap, index)| {
+                if *tilemap == roof_entity {
+                    set_exposed(*index, true);
+                }
+            });
+            updated.iter().for_each(|(tilemap, index)| {
+                if *tilemap == roof_entity {
+                    set_exposed(*index, false);
+                }
+            });
+
+            despawned.iter().for_each(|(tilemap, index)| {
+                if *tilemap == entity {
+                    exposure.exposed.remove_elem(*index);
+                }
+            });
+            // Added or changed tiles will be extracted anyway.
+            updated.iter().for_each(|(tilemap, index)| {
+                if *tilemap == entity {
+                    exposure
+                        .exposed
+                        .set_elem(*index, roof.get(*index).is_none());
+                }
+            });
+        });
+
+    let mut tiles = tiles_query.p1();
+    changed.into_iter().for_each(|entity| {
+        if let Ok(mut tile) = tiles.get_mut(entity) {
+            tile.set_changed();
+        }
+    });
+}
@@ -0,0 +1,235 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::{Event, EventWriter},
+        system::Query,
+    },
+    math::{IVec2, Vec2},
+    reflect::Reflect,
+    transform::components::GlobalTransform,
+};
+
+use crate::math::TileArea;
+
+use super::{
+    chunking::storage::ChunkedStorage,
+    coordinates,
+    map::{TilePivot, TilemapAxisFlip, TilemapSlotSize, TilemapTransform, TilemapType},
+};
+
+pub type ZoneId = u32;
+
+pub struct EntiTilesTileZonePlugin;
+
+impl Plugin for EntiTilesTileZonePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, zone_tracker);
+
+        app.register_type::<TileZones>()
+            .register_type::<TileZoneTracker>()
+            .register_type::<TileZoneChanged>();
+
+        app.add_event::<TileZoneChanged>();
+    }
+}
+
+/// A layer that classifies tiles into zones like caves, indoors or water.
+///
+/// Goes on the tilemap entity. Zones are looked up by tile index, so empty tiles
+/// can belong to a zone too, like the open air of a cave. Add `TileZoneTracker`
+/// to the entities that should receive `TileZoneChanged`.
+#[derive(Component, Debug, Clone, Reflect)]
+#[cfg_attr(feature = "serializing", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileZones {
+    pub(crate) storage: ChunkedStorage<ZoneId>,
+}
+
+impl Default for TileZones {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TileZones {
+    /// Create a new zone layer with default chunk size.
+    ///
+    /// Use `new_with_chunk_size` to specify a custom chunk size.
+    pub fn new() -> Self {
+        Self {
+            storage: ChunkedStorage::default(),
+        }
+    }
+
+    /// Create a new zone layer with custom chunk size.
+    pub fn new_with_chunk_size(chunk_size: u32) -> Self {
+        Self {
+            storage: ChunkedStorage::new(chunk_size),
+        }
+    }
+
+    /// Get the zone of a tile.
+    #[inline]
+    pub fn get(&self, index: IVec2) -> Option<ZoneId> {
+        self.storage.get_elem(index).cloned()
+    }
+
+    /// Get the zone at a world position.
+    #[inline]
+    pub fn get_world(
+        &self,
+        world: Vec2,
+        ty: TilemapType,
+        transform: &TilemapTransform,
+        pivot: Vec2,
+        slot_size: Vec2,
+        axis_flip: TilemapAxisFlip,
+    ) -> Option<ZoneId> {
+        self.get(coordinates::world_to_index_flipped(
+            world, ty, transform, pivot, slot_size, axis_flip,
+        ))
+    }
+
+    #[inline]
+    pub fn set(&mut self, index: IVec2, zone: ZoneId) {
+        self.storage.set_elem(index, zone);
+    }
+
+    #[inline]
+    pub fn remove(&mut self, index: IVec2) -> Option<ZoneId> {
+        self.storage.remove_elem(index)
+    }
+
+    /// Fill a rectangle area with the same zone.
+    pub fn fill_rect(&mut self, area: TileArea, zone: ZoneId) {
+        area.aabb()
+            .into_iter()
+            .for_each(|index| self.set(index, zone));
+    }
+
+    /// Fill a rectangle area with zones returned by `zone`.
+    pub fn fill_rect_custom(&mut self, area: TileArea, zone: impl Fn(IVec2) -> Option<ZoneId>) {
+        area.aabb().into_iter().for_each(|index| {
+            if let Some(z) = zone(index) {
+                self.set(index, z);
+            }
+        });
+    }
+}
+
+/// Add this to entities that need to know which zone they are in.
+///
+/// A `TileZoneChanged` event will be sent when the entity crosses zones.
+#[derive(Component, Debug, Default, Clone, Copy, Reflect)]
+pub struct TileZoneTracker {
+    pub(crate) current: Option<(Entity, ZoneId)>,
+}
+
+impl TileZoneTracker {
+    /// The tilemap and the zone this entity is currently in.
+    #[inline]
+    pub fn current(&self) -> Option<(Entity, ZoneId)> {
+        self.current
+    }
+}
+
+/// Fired when an entity with `TileZoneTracker` enters a different zone.
+#[derive(Event, Debug, Clone, Copy, Reflect)]
+pub struct TileZoneChanged {
+    pub entity: Entity,
+    /// The tilemap and the zone the entity was in.
+    pub from: Option<(Entity, ZoneId)>,
+    /// The tilemap and the zone the entity is in now.
+    pub to: Option<(Entity, ZoneId)>,
+}
+
+pub fn zone_tracker(
+    mut trackers_query: Query<(Entity, &GlobalTransform, &mut TileZoneTracker)>,
+    tilemaps_query: Query<(
+        Entity,
+        &TileZones,
+        &TilemapType,
+        &TilemapTransform,
+        &TilePivot,
+        &TilemapSlotSize,
+        &TilemapAxisFlip,
+    )>,
+    mut zone_changed: EventWriter<TileZoneChanged>,
+) {
+    trackers_query
+        .iter_mut()
+        .for_each(|(entity, transform, mut tracker)| {
+            let world = transform.translation().truncate();
+            // If several tilemaps overlap, the one on the top wins.
+            let zone = tilemaps_query
+                .iter()
+                .filter_map(
+                    |(tilemap, zones, ty, tilemap_transform, pivot, slot_size, axis_flip)| {
+                        zones
+                            .get_world(
+                                world,
+                                *ty,
+                                tilemap_transform,
+                                pivot.0,
+                                slot_size.0,
+                                *axis_flip,
+                            )
+                            .map(|zone| (tilemap, zone, tilemap_transform.z_index))
+                    },
+                )
+                .max_by(|a, b| a.2.total_cmp(&b.2))
+                .map(|(tilemap, zone, _)| (tilemap, zone));
+
+            if zone != tracker.current {
+                zone_changed.send(TileZoneChanged {
+                    entity,
+                    from: tracker.current,
+                    to: zone,
+                });
+                tracker.current = zone;
+            }
+        });
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::ecs::event::Events;
+
+    use super::*;
+
+    #[test]
+    fn test_zone_tracker_flipped() {
+        let mut app = App::new();
+        app.add_plugins(EntiTilesTileZonePlugin);
+
+        let mut zones = TileZones::new();
+        zones.set(IVec2::new(1, 1), 7);
+        let tilemap = app
+            .world
+            .spawn((
+                zones,
+                TilemapType::Square,
+                TilemapTransform::default(),
+                TilePivot::default(),
+                TilemapSlotSize(Vec2::splat(16.)),
+                TilemapAxisFlip::Y,
+            ))
+            .id();
+        // Tile (1, 1) is below the x axis when y is flipped.
+        let entity = app
+            .world
+            .spawn((
+                GlobalTransform::from_xyz(24., -24., 0.),
+                TileZoneTracker::default(),
+            ))
+            .id();
+        app.update();
+
+        assert_eq!(
+            app.world.get::<TileZoneTracker>(entity).unwrap().current,
+            Some((tilemap, 7))
+        );
+        assert_eq!(app.world.resource::<Events<TileZoneChanged>>().len(), 1);
+    }
+}